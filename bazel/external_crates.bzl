@@ -469,6 +469,12 @@ def external_crates_repository(name, cargo_lockfile, lockfile, sanitizers_enable
             "group": crate.spec(
                 version = "^0.13",
             ),
+            "h3": crate.spec(
+                version = "^0.0.6",
+            ),
+            "h3-quinn": crate.spec(
+                version = "^0.0.7",
+            ),
             "hashlink": crate.spec(
                 version = "^0.8.0",
             ),
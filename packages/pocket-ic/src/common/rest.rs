@@ -3,13 +3,14 @@
 //! from and to JSON, and are used by both crates.
 
 use crate::UserError;
-use candid::Principal;
+use candid::{CandidType, Principal};
 use hex;
 use reqwest::Response;
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::fmt::Write;
 use std::path::PathBuf;
 
 pub type InstanceId = usize;
@@ -240,7 +241,9 @@ pub struct RawCycles {
     pub cycles: u128,
 }
 
-#[derive(Clone, Serialize, Eq, PartialEq, Ord, PartialOrd, Deserialize, Debug, JsonSchema)]
+#[derive(
+    Clone, Serialize, Eq, Hash, PartialEq, Ord, PartialOrd, Deserialize, Debug, JsonSchema,
+)]
 pub struct RawCanisterId {
     // raw bytes of the principal
     #[serde(deserialize_with = "base64::deserialize")]
@@ -256,6 +259,23 @@ impl From<Principal> for RawCanisterId {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawCreateSnapshot {
+    pub name: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawAddSubnet {
+    pub subnet_kind: SubnetKind,
+    pub subnet_spec: SubnetSpec,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawSetSubnetSize {
+    pub subnet_id: RawSubnetId,
+    pub num_nodes: u64,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, JsonSchema, PartialEq, Eq, Hash)]
 pub struct RawSubnetId {
     #[serde(deserialize_with = "base64::deserialize")]
@@ -453,7 +473,48 @@ impl From<SubnetConfigSet> for ExtendedSubnetConfigSet {
 pub struct InstanceConfig {
     pub subnet_config_set: ExtendedSubnetConfigSet,
     pub state_dir: Option<PathBuf>,
+    /// Name of a snapshot from which the instance is created.
+    /// If specified, then `subnet_config_set` is ignored.
+    pub snapshot: Option<String>,
     pub nonmainnet_features: bool,
+    /// NNS canisters to install on the NNS subnet of a new instance.
+    /// Ignored if the instance is created from a snapshot or an existing state.
+    #[serde(default)]
+    pub nns_canisters: Option<NnsCanistersConfig>,
+}
+
+/// The ICP/XDR conversion rate set in the cycles minting canister by default:
+/// 1 ICP = 10 XDR.
+pub const DEFAULT_XDR_PERMYRIAD_PER_ICP: u64 = 100_000;
+
+/// Configuration of the NNS canisters (registry, root, governance, lifeline,
+/// ICP ledger, cycles minting canister, and SNS-W) installed on the NNS subnet
+/// of a new PocketIC instance, so that canisters can be created via the
+/// cycles minting canister and SNSs can be launched out of the box.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct NnsCanistersConfig {
+    /// Initial ICP distribution: pairs of a hex-encoded ICP ledger account
+    /// identifier and its balance in e8s.
+    pub initial_balances: Vec<(String, u64)>,
+    /// Whether NNS governance is initialized with the test neurons
+    /// (including the neuron controlled by `TEST_NEURON_1_OWNER_PRINCIPAL`).
+    pub test_neurons: bool,
+    /// The ICP/XDR conversion rate set in the cycles minting canister.
+    pub xdr_permyriad_per_icp: u64,
+    /// The subnets on which the cycles minting canister creates canisters by
+    /// default. If `None`, all application subnets of the instance are used.
+    pub authorized_subnets: Option<Vec<RawSubnetId>>,
+}
+
+impl Default for NnsCanistersConfig {
+    fn default() -> Self {
+        Self {
+            initial_balances: vec![],
+            test_neurons: true,
+            xdr_permyriad_per_icp: DEFAULT_XDR_PERMYRIAD_PER_ICP,
+            authorized_subnets: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize, Default, JsonSchema)]
@@ -876,3 +937,221 @@ impl From<MockCanisterHttpResponse> for RawMockCanisterHttpResponse {
         }
     }
 }
+
+/// A persistent rule answering all pending canister HTTP outcalls that it matches
+/// whenever the PocketIC instance executes a round.
+/// If several rules match an outcall, then the rule that was added first is used.
+#[derive(Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, JsonSchema)]
+pub struct CanisterHttpMockRule {
+    /// Matches outcalls with any HTTP method if `None`.
+    pub http_method: Option<CanisterHttpMethod>,
+    /// A regular expression that the entire URL of an outcall must match.
+    pub url_pattern: String,
+    /// Headers that an outcall must contain (header names are matched case-insensitively).
+    pub headers: Vec<CanisterHttpHeader>,
+    /// A regular expression that the entire (UTF-8) body of an outcall must match.
+    /// Matches outcalls with any body if `None`.
+    pub body_pattern: Option<String>,
+    /// The response returned by all replicas except for those in `divergent_responses`.
+    pub response: CanisterHttpResponse,
+    /// Responses returned by the first replicas of the subnet instead of `response`
+    /// to test how canisters handle replicas observing divergent responses.
+    pub divergent_responses: Vec<CanisterHttpResponse>,
+    /// The minimum time (of the PocketIC instance) in milliseconds between
+    /// making an outcall and answering it.
+    pub delay_ms: Option<u64>,
+}
+
+impl CanisterHttpMockRule {
+    /// A rule answering all outcalls to URLs matching the given regular expression
+    /// with the given response.
+    pub fn new(url_pattern: impl Into<String>, response: CanisterHttpResponse) -> Self {
+        Self {
+            http_method: None,
+            url_pattern: url_pattern.into(),
+            headers: vec![],
+            body_pattern: None,
+            response,
+            divergent_responses: vec![],
+            delay_ms: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawCanisterHttpForwarding {
+    /// The base URL (e.g., `http://localhost:8080`) of a test server.
+    pub forward_to: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawCallGraphTracing {
+    pub enabled: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, JsonSchema)]
+pub enum CallOutcome {
+    Reply,
+    Reject(String),
+}
+
+/// A single message execution of a call in a call graph.
+#[derive(Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, JsonSchema)]
+pub struct RawCallGraphExecution {
+    pub round: u64,
+    /// The callee whose response was executed
+    /// or `None` if the call itself was executed.
+    pub respondent: Option<RawCanisterId>,
+    pub instructions_used: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, JsonSchema)]
+pub struct RawCallGraphNode {
+    #[serde(deserialize_with = "base64::deserialize")]
+    #[serde(serialize_with = "base64::serialize")]
+    pub caller: Vec<u8>,
+    pub callee: RawCanisterId,
+    pub method_name: Option<String>,
+    pub cycles: u128,
+    pub outcome: Option<CallOutcome>,
+    pub executions: Vec<RawCallGraphExecution>,
+    pub calls: Vec<RawCallGraphNode>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq)]
+pub struct CallGraphExecution {
+    pub round: u64,
+    /// The callee whose response was executed
+    /// or `None` if the call itself was executed.
+    pub respondent: Option<Principal>,
+    pub instructions_used: u64,
+}
+
+/// An ingress message or inter-canister call together with
+/// all (transitive) downstream calls it made.
+#[derive(Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq)]
+pub struct CallGraphNode {
+    /// The sender of the ingress message or the calling canister.
+    pub caller: Principal,
+    pub callee: Principal,
+    /// `None` if the callee has not executed the call
+    /// (e.g., for calls to the management canister).
+    pub method_name: Option<String>,
+    /// The cycles attached to the call.
+    pub cycles: u128,
+    /// `None` if the call has not completed yet.
+    pub outcome: Option<CallOutcome>,
+    /// The executions of the call and of the response callbacks of its downstream calls.
+    pub executions: Vec<CallGraphExecution>,
+    /// The downstream calls in the order in which they were made.
+    pub calls: Vec<CallGraphNode>,
+}
+
+impl CallGraphNode {
+    /// The total number of instructions used by all executions in this (sub)graph.
+    pub fn total_instructions_used(&self) -> u64 {
+        self.executions
+            .iter()
+            .map(|execution| execution.instructions_used)
+            .sum::<u64>()
+            + self
+                .calls
+                .iter()
+                .map(|call| call.total_instructions_used())
+                .sum::<u64>()
+    }
+}
+
+/// Instruction totals of a single Wasm function of a profiled canister.
+#[derive(Clone, CandidType, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct FunctionProfile {
+    /// The index of the function in the canister's Wasm module.
+    pub function_index: u32,
+    /// The name of the function from the `name` section of the Wasm module.
+    pub function_name: Option<String>,
+    pub calls: u64,
+    /// Instructions executed by the function and all its callees.
+    pub inclusive_instructions: u64,
+    /// Instructions executed by the function itself.
+    pub exclusive_instructions: u64,
+}
+
+/// Instructions executed by the innermost function of a call stack.
+#[derive(Clone, CandidType, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct CallStackProfile {
+    /// The function indices of the call stack, outermost first.
+    pub function_indices: Vec<u32>,
+    pub instructions: u64,
+}
+
+/// The execution profile of a canister with profiling enabled,
+/// as returned by the `fetch_canister_profile` management canister method.
+#[derive(Clone, CandidType, Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
+pub struct CanisterProfile {
+    pub functions: Vec<FunctionProfile>,
+    pub call_stacks: Vec<CallStackProfile>,
+}
+
+impl CanisterProfile {
+    /// Returns the call stacks in the "folded stacks" format understood by
+    /// flamegraph tools (e.g., `inferno-flamegraph`).
+    pub fn to_folded_stacks(&self) -> String {
+        let names: BTreeMap<u32, &str> = self
+            .functions
+            .iter()
+            .filter_map(|function| {
+                function
+                    .function_name
+                    .as_deref()
+                    .map(|name| (function.function_index, name))
+            })
+            .collect();
+        let mut folded_stacks = String::new();
+        for call_stack in &self.call_stacks {
+            let frames: Vec<String> = call_stack
+                .function_indices
+                .iter()
+                .map(|index| match names.get(index) {
+                    Some(name) => name.replace([';', ' '], "_"),
+                    None => format!("func[{}]", index),
+                })
+                .collect();
+            writeln!(
+                folded_stacks,
+                "{} {}",
+                frames.join(";"),
+                call_stack.instructions
+            )
+            .unwrap();
+        }
+        folded_stacks
+    }
+}
+
+impl From<RawCallGraphNode> for CallGraphNode {
+    fn from(raw_call_graph_node: RawCallGraphNode) -> Self {
+        Self {
+            caller: Principal::from_slice(&raw_call_graph_node.caller),
+            callee: Principal::from_slice(&raw_call_graph_node.callee.canister_id),
+            method_name: raw_call_graph_node.method_name,
+            cycles: raw_call_graph_node.cycles,
+            outcome: raw_call_graph_node.outcome,
+            executions: raw_call_graph_node
+                .executions
+                .into_iter()
+                .map(|execution| CallGraphExecution {
+                    round: execution.round,
+                    respondent: execution
+                        .respondent
+                        .map(|respondent| Principal::from_slice(&respondent.canister_id)),
+                    instructions_used: execution.instructions_used,
+                })
+                .collect(),
+            calls: raw_call_graph_node
+                .calls
+                .into_iter()
+                .map(|call| call.into())
+                .collect(),
+        }
+    }
+}
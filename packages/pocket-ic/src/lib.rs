@@ -34,9 +34,10 @@
 //! For more information, see the [README](https://crates.io/crates/pocket-ic).
 //!
 use crate::common::rest::{
-    BlobCompression, BlobId, CanisterHttpRequest, DtsFlag, ExtendedSubnetConfigSet, HttpsConfig,
-    InstanceId, MockCanisterHttpResponse, RawEffectivePrincipal, RawMessageId, SubnetId,
-    SubnetSpec, Topology,
+    BlobCompression, BlobId, CallGraphNode, CanisterHttpMockRule, CanisterHttpRequest,
+    CanisterProfile, DtsFlag, ExtendedSubnetConfigSet, HttpsConfig, InstanceId,
+    MockCanisterHttpResponse, NnsCanistersConfig, RawEffectivePrincipal, RawMessageId, SubnetId,
+    SubnetKind, SubnetSpec, Topology,
 };
 use crate::nonblocking::PocketIc as PocketIcAsync;
 use candid::{
@@ -74,7 +75,9 @@ pub struct PocketIcBuilder {
     server_url: Option<Url>,
     max_request_time_ms: Option<u64>,
     state_dir: Option<PathBuf>,
+    snapshot: Option<String>,
     nonmainnet_features: bool,
+    nns_canisters: Option<NnsCanistersConfig>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -85,7 +88,9 @@ impl PocketIcBuilder {
             server_url: None,
             max_request_time_ms: Some(DEFAULT_MAX_REQUEST_TIME_MS),
             state_dir: None,
+            snapshot: None,
             nonmainnet_features: false,
+            nns_canisters: None,
         }
     }
 
@@ -96,7 +101,9 @@ impl PocketIcBuilder {
             server_url,
             self.max_request_time_ms,
             self.state_dir,
+            self.snapshot,
             self.nonmainnet_features,
+            self.nns_canisters,
         )
     }
 
@@ -107,7 +114,9 @@ impl PocketIcBuilder {
             server_url,
             self.max_request_time_ms,
             self.state_dir,
+            self.snapshot,
             self.nonmainnet_features,
+            self.nns_canisters,
         )
         .await
     }
@@ -133,6 +142,15 @@ impl PocketIcBuilder {
         }
     }
 
+    /// Creates the PocketIC instance from the named snapshot
+    /// (see `PocketIc::create_snapshot`) instead of the subnet configuration.
+    pub fn with_snapshot(self, snapshot: String) -> Self {
+        Self {
+            snapshot: Some(snapshot),
+            ..self
+        }
+    }
+
     pub fn with_nonmainnet_features(self, nonmainnet_features: bool) -> Self {
         Self {
             nonmainnet_features,
@@ -140,6 +158,23 @@ impl PocketIcBuilder {
         }
     }
 
+    /// Install the NNS canisters (registry, root, governance, lifeline, ICP ledger,
+    /// cycles minting canister, and SNS-W) with the given configuration on the NNS subnet.
+    /// Adds an empty NNS and SNS subnet unless they are already configured.
+    /// The canisters are only installed if the instance is not created from a snapshot
+    /// or an existing state.
+    pub fn with_nns_canisters(self, nns_canisters: NnsCanistersConfig) -> Self {
+        Self {
+            config: ExtendedSubnetConfigSet {
+                nns: self.config.nns.or(Some(SubnetSpec::default())),
+                sns: self.config.sns.or(Some(SubnetSpec::default())),
+                ..self.config
+            },
+            nns_canisters: Some(nns_canisters),
+            ..self
+        }
+    }
+
     /// Add an empty NNS subnet
     pub fn with_nns_subnet(self) -> Self {
         Self {
@@ -297,7 +332,9 @@ impl PocketIc {
             server_url,
             Some(DEFAULT_MAX_REQUEST_TIME_MS),
             None,
+            None,
             false,
+            None,
         )
     }
 
@@ -309,7 +346,15 @@ impl PocketIc {
         max_request_time_ms: Option<u64>,
     ) -> Self {
         let server_url = crate::start_or_reuse_server();
-        Self::from_components(config, server_url, max_request_time_ms, None, false)
+        Self::from_components(
+            config,
+            server_url,
+            max_request_time_ms,
+            None,
+            None,
+            false,
+            None,
+        )
     }
 
     /// Creates a new PocketIC instance with the specified subnet config and server url.
@@ -323,7 +368,9 @@ impl PocketIc {
             server_url,
             Some(DEFAULT_MAX_REQUEST_TIME_MS),
             None,
+            None,
             false,
+            None,
         )
     }
 
@@ -332,7 +379,9 @@ impl PocketIc {
         server_url: Url,
         max_request_time_ms: Option<u64>,
         state_dir: Option<PathBuf>,
+        snapshot: Option<String>,
         nonmainnet_features: bool,
+        nns_canisters: Option<NnsCanistersConfig>,
     ) -> Self {
         let (tx, rx) = channel();
         let thread = thread::spawn(move || {
@@ -350,7 +399,9 @@ impl PocketIc {
                 server_url,
                 max_request_time_ms,
                 state_dir,
+                snapshot,
                 nonmainnet_features,
+                nns_canisters,
             )
             .await
         });
@@ -368,6 +419,77 @@ impl PocketIc {
         runtime.block_on(async { self.pocket_ic.topology().await })
     }
 
+    /// Adds a new subnet of the given kind to this PocketIC instance
+    /// and returns the ID of the new subnet.
+    /// NNS and II subnets cannot be added at runtime and
+    /// there can be at most one subnet of every other named subnet kind.
+    /// The subnet spec must specify a new (empty) state.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id))]
+    pub fn add_subnet(&self, subnet_kind: SubnetKind, subnet_spec: SubnetSpec) -> SubnetId {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.add_subnet(subnet_kind, subnet_spec).await })
+    }
+
+    /// Changes the number of nodes of the subnet with the given ID.
+    /// The subnet is restarted from a checkpoint of its latest state
+    /// and thus no (certified) state is lost.
+    #[instrument(skip(self), fields(instance_id=self.pocket_ic.instance_id, subnet_id = %subnet_id.to_string(), num_nodes = %num_nodes))]
+    pub fn set_subnet_size(&self, subnet_id: SubnetId, num_nodes: u64) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.set_subnet_size(subnet_id, num_nodes).await })
+    }
+
+    /// Restarts the subnet with the given ID from a checkpoint of its latest state.
+    /// This can be used to test that canisters behave correctly after their subnet
+    /// has been restarted (e.g., that their heap memory is rebuilt from the checkpoint).
+    #[instrument(skip(self), fields(instance_id=self.pocket_ic.instance_id, subnet_id = %subnet_id.to_string()))]
+    pub fn restart_subnet(&self, subnet_id: SubnetId) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.restart_subnet(subnet_id).await })
+    }
+
+    /// Creates a named snapshot of the current state of this PocketIC instance.
+    /// New PocketIC instances can be created from the snapshot
+    /// using `PocketIcBuilder::with_snapshot`.
+    #[instrument(skip(self), fields(instance_id=self.pocket_ic.instance_id, name = %name))]
+    pub fn create_snapshot(&self, name: &str) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.create_snapshot(name).await })
+    }
+
+    /// Returns the names of all snapshots stored on the PocketIC server.
+    pub fn list_snapshots(&self) -> Vec<String> {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.list_snapshots().await })
+    }
+
+    /// Deletes the named snapshot from the PocketIC server.
+    /// Instances created from the snapshot are not affected.
+    #[instrument(skip(self), fields(instance_id=self.pocket_ic.instance_id, name = %name))]
+    pub fn delete_snapshot(&self, name: &str) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.delete_snapshot(name).await })
+    }
+
+    /// Enables or disables tracing of inter-canister call graphs on all subnets.
+    /// Tracing is disabled by default. Call graphs of ingress messages
+    /// executed while tracing is enabled can be retrieved using `get_call_graph`
+    /// and are also shown on the instance dashboard.
+    #[instrument(skip(self), fields(instance_id=self.pocket_ic.instance_id, enabled = %enabled))]
+    pub fn set_call_graph_tracing(&self, enabled: bool) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.set_call_graph_tracing(enabled).await })
+    }
+
+    /// Returns the call graph of the ingress message with the given ID,
+    /// i.e., all (transitive) downstream calls made while executing the ingress message,
+    /// or `None` if the ingress message has not been executed while tracing was enabled.
+    #[instrument(skip(self), fields(instance_id=self.pocket_ic.instance_id))]
+    pub fn get_call_graph(&self, message_id: RawMessageId) -> Option<CallGraphNode> {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.get_call_graph(message_id).await })
+    }
+
     /// Upload and store a binary blob to the PocketIC server.
     #[instrument(ret(Display), skip(self, blob), fields(instance_id=self.pocket_ic.instance_id, blob_len = %blob.len(), compression = ?compression))]
     pub fn upload_blob(&self, blob: Vec<u8>, compression: BlobCompression) -> BlobId {
@@ -785,6 +907,40 @@ impl PocketIc {
         })
     }
 
+    /// Enable or disable function-level execution profiling of a canister.
+    /// Enabling profiling requires the canister's subnet to support it, which is
+    /// the case if PocketIC is started with non-mainnet features.
+    #[instrument(skip(self), fields(instance_id=self.pocket_ic.instance_id, canister_id = %canister_id.to_string(), sender = %sender.unwrap_or(Principal::anonymous()).to_string()))]
+    pub fn set_canister_profiling(
+        &self,
+        canister_id: CanisterId,
+        sender: Option<Principal>,
+        enabled: bool,
+    ) -> Result<(), CallError> {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .set_canister_profiling(canister_id, sender, enabled)
+                .await
+        })
+    }
+
+    /// Fetch the execution profile of a canister with profiling enabled.
+    /// Only controllers of the canister can fetch its profile.
+    #[instrument(skip(self), fields(instance_id=self.pocket_ic.instance_id, canister_id = %canister_id.to_string(), sender = %sender.unwrap_or(Principal::anonymous()).to_string()))]
+    pub fn fetch_canister_profile(
+        &self,
+        canister_id: CanisterId,
+        sender: Option<Principal>,
+    ) -> Result<CanisterProfile, CallError> {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .fetch_canister_profile(canister_id, sender)
+                .await
+        })
+    }
+
     /// Set canister's controllers.
     #[instrument(skip(self), fields(instance_id=self.pocket_ic.instance_id, canister_id = %canister_id.to_string(), sender = %sender.unwrap_or(Principal::anonymous()).to_string()))]
     pub fn set_controllers(
@@ -900,6 +1056,37 @@ impl PocketIc {
                 .await
         })
     }
+
+    /// Add a persistent rule answering all pending canister HTTP outcalls it matches
+    /// whenever the PocketIC instance executes a round (e.g., in `PocketIc::tick`
+    /// or while executing an update call).
+    /// Rules are matched in the order in which they were added.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id))]
+    pub fn add_canister_http_mock_rule(&self, rule: CanisterHttpMockRule) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.add_canister_http_mock_rule(rule).await })
+    }
+
+    /// Remove all canister HTTP outcall mock rules.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id))]
+    pub fn clear_canister_http_mock_rules(&self) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.clear_canister_http_mock_rules().await })
+    }
+
+    /// Forward all pending canister HTTP outcalls matching no mock rule
+    /// to the test server with the given base URL (e.g., `http://localhost:8080`)
+    /// keeping the path and query of the original URL.
+    /// Forwarding is disabled if `None` is provided.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id))]
+    pub fn set_canister_http_forwarding(&self, forward_to: Option<String>) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .set_canister_http_forwarding(forward_to)
+                .await
+        })
+    }
 }
 
 impl Default for PocketIc {
@@ -1,11 +1,13 @@
 use crate::common::rest::{
-    ApiResponse, AutoProgressConfig, BlobCompression, BlobId, CanisterHttpRequest,
-    CreateHttpGatewayResponse, CreateInstanceResponse, ExtendedSubnetConfigSet, HttpGatewayBackend,
-    HttpGatewayConfig, HttpGatewayInfo, HttpsConfig, InstanceConfig, InstanceId,
-    MockCanisterHttpResponse, RawAddCycles, RawCanisterCall, RawCanisterHttpRequest, RawCanisterId,
-    RawCanisterResult, RawCycles, RawEffectivePrincipal, RawMessageId, RawMockCanisterHttpResponse,
-    RawSetStableMemory, RawStableMemory, RawSubmitIngressResult, RawSubnetId, RawTime,
-    RawVerifyCanisterSigArg, RawWasmResult, SubnetId, Topology,
+    ApiResponse, AutoProgressConfig, BlobCompression, BlobId, CallGraphNode, CanisterHttpMockRule,
+    CanisterHttpRequest, CanisterProfile, CreateHttpGatewayResponse, CreateInstanceResponse,
+    ExtendedSubnetConfigSet, HttpGatewayBackend, HttpGatewayConfig, HttpGatewayInfo, HttpsConfig,
+    InstanceConfig, InstanceId, MockCanisterHttpResponse, NnsCanistersConfig, RawAddCycles,
+    RawAddSubnet, RawCallGraphNode, RawCallGraphTracing, RawCanisterCall,
+    RawCanisterHttpForwarding, RawCanisterHttpRequest, RawCanisterId, RawCanisterResult,
+    RawCreateSnapshot, RawCycles, RawEffectivePrincipal, RawMessageId, RawMockCanisterHttpResponse,
+    RawSetStableMemory, RawSetSubnetSize, RawStableMemory, RawSubmitIngressResult, RawSubnetId,
+    RawTime, RawVerifyCanisterSigArg, RawWasmResult, SubnetId, SubnetKind, SubnetSpec, Topology,
 };
 use crate::{CallError, PocketIcBuilder, UserError, WasmResult, DEFAULT_MAX_REQUEST_TIME_MS};
 use candid::{
@@ -85,7 +87,9 @@ impl PocketIc {
             server_url,
             Some(DEFAULT_MAX_REQUEST_TIME_MS),
             None,
+            None,
             false,
+            None,
         )
         .await
     }
@@ -98,7 +102,16 @@ impl PocketIc {
         max_request_time_ms: Option<u64>,
     ) -> Self {
         let server_url = crate::start_or_reuse_server();
-        Self::from_components(config, server_url, max_request_time_ms, None, false).await
+        Self::from_components(
+            config,
+            server_url,
+            max_request_time_ms,
+            None,
+            None,
+            false,
+            None,
+        )
+        .await
     }
 
     /// Creates a new PocketIC instance with the specified subnet config and server url.
@@ -112,7 +125,9 @@ impl PocketIc {
             server_url,
             Some(DEFAULT_MAX_REQUEST_TIME_MS),
             None,
+            None,
             false,
+            None,
         )
         .await
     }
@@ -122,18 +137,23 @@ impl PocketIc {
         server_url: Url,
         max_request_time_ms: Option<u64>,
         state_dir: Option<PathBuf>,
+        snapshot: Option<String>,
         nonmainnet_features: bool,
+        nns_canisters: Option<NnsCanistersConfig>,
     ) -> Self {
         let subnet_config_set = subnet_config_set.into();
-        if state_dir.is_none()
-            || File::open(state_dir.clone().unwrap().join("topology.json")).is_err()
+        if snapshot.is_none()
+            && (state_dir.is_none()
+                || File::open(state_dir.clone().unwrap().join("topology.json")).is_err())
         {
             subnet_config_set.validate().unwrap();
         }
         let instance_config = InstanceConfig {
             subnet_config_set,
             state_dir,
+            snapshot,
             nonmainnet_features,
+            nns_canisters,
         };
 
         let parent_pid = std::os::unix::process::parent_id();
@@ -171,6 +191,128 @@ impl PocketIc {
         self.get(endpoint).await
     }
 
+    /// Adds a new subnet of the given kind to this PocketIC instance
+    /// and returns the ID of the new subnet.
+    /// NNS and II subnets cannot be added at runtime and
+    /// there can be at most one subnet of every other named subnet kind.
+    /// The subnet spec must specify a new (empty) state.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id))]
+    pub async fn add_subnet(&self, subnet_kind: SubnetKind, subnet_spec: SubnetSpec) -> SubnetId {
+        let endpoint = "update/add_subnet";
+        let RawSubnetId { subnet_id } = self
+            .post(
+                endpoint,
+                RawAddSubnet {
+                    subnet_kind,
+                    subnet_spec,
+                },
+            )
+            .await;
+        SubnetId::from_slice(&subnet_id)
+    }
+
+    /// Changes the number of nodes of the subnet with the given ID.
+    /// The subnet is restarted from a checkpoint of its latest state
+    /// and thus no (certified) state is lost.
+    #[instrument(skip(self), fields(instance_id=self.instance_id, subnet_id = %subnet_id.to_string(), num_nodes = %num_nodes))]
+    pub async fn set_subnet_size(&self, subnet_id: SubnetId, num_nodes: u64) {
+        let endpoint = "update/set_subnet_size";
+        self.post::<(), _>(
+            endpoint,
+            RawSetSubnetSize {
+                subnet_id: RawSubnetId {
+                    subnet_id: subnet_id.as_slice().to_vec(),
+                },
+                num_nodes,
+            },
+        )
+        .await;
+    }
+
+    /// Restarts the subnet with the given ID from a checkpoint of its latest state.
+    /// This can be used to test that canisters behave correctly after their subnet
+    /// has been restarted (e.g., that their heap memory is rebuilt from the checkpoint).
+    #[instrument(skip(self), fields(instance_id=self.instance_id, subnet_id = %subnet_id.to_string()))]
+    pub async fn restart_subnet(&self, subnet_id: SubnetId) {
+        let endpoint = "update/restart_subnet";
+        self.post::<(), _>(
+            endpoint,
+            RawSubnetId {
+                subnet_id: subnet_id.as_slice().to_vec(),
+            },
+        )
+        .await;
+    }
+
+    /// Creates a named snapshot of the current state of this PocketIC instance.
+    /// New PocketIC instances can be created from the snapshot
+    /// using `PocketIcBuilder::with_snapshot`.
+    #[instrument(skip(self), fields(instance_id=self.instance_id, name = %name))]
+    pub async fn create_snapshot(&self, name: &str) {
+        let endpoint = "update/create_snapshot";
+        self.post::<(), _>(
+            endpoint,
+            RawCreateSnapshot {
+                name: name.to_string(),
+            },
+        )
+        .await;
+    }
+
+    /// Returns the names of all snapshots stored on the PocketIC server.
+    pub async fn list_snapshots(&self) -> Vec<String> {
+        self.reqwest_client
+            .get(self.server_url.join("snapshots/").unwrap())
+            .send()
+            .await
+            .expect("Failed to get result")
+            .json::<Vec<String>>()
+            .await
+            .expect("Could not parse response for list snapshots request")
+    }
+
+    /// Deletes the named snapshot from the PocketIC server.
+    /// Instances created from the snapshot are not affected.
+    #[instrument(skip(self), fields(instance_id=self.instance_id, name = %name))]
+    pub async fn delete_snapshot(&self, name: &str) {
+        let response = self
+            .reqwest_client
+            .delete(
+                self.server_url
+                    .join("snapshots/")
+                    .unwrap()
+                    .join(name)
+                    .unwrap(),
+            )
+            .send()
+            .await
+            .expect("Failed to send delete request");
+        if let ApiResponse::<()>::Error { message } = ApiResponse::from_response(response).await {
+            panic!("{}", message);
+        }
+    }
+
+    /// Enables or disables tracing of inter-canister call graphs on all subnets.
+    /// Tracing is disabled by default. Call graphs of ingress messages
+    /// executed while tracing is enabled can be retrieved using `get_call_graph`
+    /// and are also shown on the instance dashboard.
+    #[instrument(skip(self), fields(instance_id=self.instance_id, enabled = %enabled))]
+    pub async fn set_call_graph_tracing(&self, enabled: bool) {
+        let endpoint = "update/set_call_graph_tracing";
+        self.post::<(), _>(endpoint, RawCallGraphTracing { enabled })
+            .await;
+    }
+
+    /// Returns the call graph of the ingress message with the given ID,
+    /// i.e., all (transitive) downstream calls made while executing the ingress message,
+    /// or `None` if the ingress message has not been executed while tracing was enabled.
+    #[instrument(skip(self), fields(instance_id=self.instance_id))]
+    pub async fn get_call_graph(&self, message_id: RawMessageId) -> Option<CallGraphNode> {
+        let endpoint = "read/get_call_graph";
+        let result: Option<RawCallGraphNode> = self.post(endpoint, message_id).await;
+        result.map(|call_graph| call_graph.into())
+    }
+
     /// Upload and store a binary blob to the PocketIC server.
     #[instrument(ret(Display), skip(self, blob), fields(instance_id=self.instance_id, blob_len = %blob.len(), compression = ?compression))]
     pub async fn upload_blob(&self, blob: Vec<u8>, compression: BlobCompression) -> BlobId {
@@ -913,6 +1055,68 @@ impl PocketIc {
         .await
     }
 
+    /// Enable or disable function-level execution profiling of a canister.
+    /// Enabling profiling requires the canister's subnet to support it, which is
+    /// the case if PocketIC is started with non-mainnet features.
+    #[instrument(skip(self), fields(instance_id=self.instance_id, canister_id = %canister_id.to_string(), sender = %sender.unwrap_or(Principal::anonymous()).to_string()))]
+    pub async fn set_canister_profiling(
+        &self,
+        canister_id: CanisterId,
+        sender: Option<Principal>,
+        enabled: bool,
+    ) -> Result<(), CallError> {
+        // The settings type of `ic-cdk` does not support profiling yet.
+        #[derive(CandidType)]
+        struct ProfilingSettings {
+            profiling: Option<bool>,
+        }
+        #[derive(CandidType)]
+        struct UpdateProfilingSettingsArgument {
+            canister_id: CanisterId,
+            settings: ProfilingSettings,
+        }
+        call_candid_as::<_, ()>(
+            self,
+            Principal::management_canister(),
+            RawEffectivePrincipal::CanisterId(canister_id.as_slice().to_vec()),
+            sender.unwrap_or(Principal::anonymous()),
+            "update_settings",
+            (UpdateProfilingSettingsArgument {
+                canister_id,
+                settings: ProfilingSettings {
+                    profiling: Some(enabled),
+                },
+            },),
+        )
+        .await
+    }
+
+    /// Fetch the execution profile of a canister with profiling enabled.
+    /// Only controllers of the canister can fetch its profile.
+    #[instrument(skip(self), fields(instance_id=self.instance_id, canister_id = %canister_id.to_string(), sender = %sender.unwrap_or(Principal::anonymous()).to_string()))]
+    pub async fn fetch_canister_profile(
+        &self,
+        canister_id: CanisterId,
+        sender: Option<Principal>,
+    ) -> Result<CanisterProfile, CallError> {
+        with_candid::<_, (CanisterProfile,), _>(
+            (CanisterIdRecord { canister_id },),
+            |payload| async {
+                self.canister_call(
+                    "read/query",
+                    RawEffectivePrincipal::CanisterId(canister_id.as_slice().to_vec()),
+                    Principal::management_canister(),
+                    sender.unwrap_or(Principal::anonymous()),
+                    "fetch_canister_profile",
+                    payload,
+                )
+                .await
+            },
+        )
+        .await
+        .map(|responses| responses.0)
+    }
+
     /// Set canister's controllers.
     #[instrument(skip(self), fields(instance_id=self.instance_id, canister_id = %canister_id.to_string(), sender = %sender.unwrap_or(Principal::anonymous()).to_string()))]
     pub async fn set_controllers(
@@ -1201,6 +1405,9 @@ impl PocketIc {
     /// In auto progress mode, the PocketIC server produces a response for every
     /// pending canister HTTP outcall by actually making an HTTP request
     /// to the specified URL.
+    /// Pending canister HTTP outcalls that are answered automatically
+    /// (see `PocketIc::add_canister_http_mock_rule` and `PocketIc::set_canister_http_forwarding`)
+    /// are not returned.
     #[instrument(ret, skip(self), fields(instance_id=self.instance_id))]
     pub async fn get_canister_http(&self) -> Vec<CanisterHttpRequest> {
         let endpoint = "read/get_canister_http";
@@ -1219,6 +1426,34 @@ impl PocketIc {
             mock_canister_http_response.into();
         self.post(endpoint, raw_mock_canister_http_response).await
     }
+
+    /// Add a persistent rule answering all pending canister HTTP outcalls it matches
+    /// whenever the PocketIC instance executes a round (e.g., in `PocketIc::tick`
+    /// or while executing an update call).
+    /// Rules are matched in the order in which they were added.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id))]
+    pub async fn add_canister_http_mock_rule(&self, rule: CanisterHttpMockRule) {
+        let endpoint = "update/add_canister_http_mock_rule";
+        self.post::<(), _>(endpoint, rule).await
+    }
+
+    /// Remove all canister HTTP outcall mock rules.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id))]
+    pub async fn clear_canister_http_mock_rules(&self) {
+        let endpoint = "update/clear_canister_http_mock_rules";
+        self.post::<(), _>(endpoint, "").await
+    }
+
+    /// Forward all pending canister HTTP outcalls matching no mock rule
+    /// to the test server with the given base URL (e.g., `http://localhost:8080`)
+    /// keeping the path and query of the original URL.
+    /// Forwarding is disabled if `None` is provided.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id))]
+    pub async fn set_canister_http_forwarding(&self, forward_to: Option<String>) {
+        let endpoint = "update/set_canister_http_forwarding";
+        self.post::<(), _>(endpoint, RawCanisterHttpForwarding { forward_to })
+            .await
+    }
 }
 
 /// Call a canister candid method, authenticated. The sender can be impersonated (i.e., the
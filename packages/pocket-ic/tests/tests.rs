@@ -10,8 +10,8 @@ use icp_ledger::{
 };
 use pocket_ic::{
     common::rest::{
-        BlobCompression, CanisterHttpReply, CanisterHttpResponse, MockCanisterHttpResponse,
-        SubnetConfigSet, SubnetKind,
+        BlobCompression, CallOutcome, CanisterHttpMockRule, CanisterHttpReply,
+        CanisterHttpResponse, MockCanisterHttpResponse, SubnetConfigSet, SubnetKind, SubnetSpec,
     },
     update_candid, PocketIc, PocketIcBuilder, WasmResult,
 };
@@ -653,6 +653,74 @@ fn test_get_subnet_of_canister() {
     assert!(subnet_id.is_none());
 }
 
+#[test]
+fn test_change_topology_at_runtime() {
+    let pic = PocketIcBuilder::new().with_application_subnet().build();
+    assert_eq!(pic.topology().get_app_subnets().len(), 1);
+
+    // Add a new application subnet and create a canister on it.
+    let app_subnet = pic.add_subnet(SubnetKind::Application, SubnetSpec::default());
+    assert_eq!(pic.topology().get_app_subnets().len(), 2);
+    let canister_id = pic.create_canister_on_subnet(None, None, app_subnet);
+    assert_eq!(pic.get_subnet(canister_id).unwrap(), app_subnet);
+    pic.add_cycles(canister_id, INIT_CYCLES);
+    pic.install_canister(canister_id, counter_wasm(), vec![], None);
+    let reply = call_counter_can(&pic, canister_id, "write");
+    assert_eq!(reply, WasmResult::Reply(vec![1, 0, 0, 0]));
+
+    // Change the number of nodes of the new subnet.
+    pic.set_subnet_size(app_subnet, 4);
+    let node_ids = pic.topology().0.get(&app_subnet).unwrap().node_ids.clone();
+    assert_eq!(node_ids.len(), 4);
+    let reply = call_counter_can(&pic, canister_id, "write");
+    assert_eq!(reply, WasmResult::Reply(vec![2, 0, 0, 0]));
+
+    // Restart the new subnet: the canister state is preserved.
+    pic.restart_subnet(app_subnet);
+    let reply = call_counter_can(&pic, canister_id, "read");
+    assert_eq!(reply, WasmResult::Reply(vec![2, 0, 0, 0]));
+}
+
+#[test]
+#[should_panic(expected = "cannot be added to a running PocketIC instance")]
+fn test_add_nns_subnet_at_runtime_panics() {
+    let pic = PocketIcBuilder::new().with_application_subnet().build();
+    pic.add_subnet(SubnetKind::NNS, SubnetSpec::default());
+}
+
+#[test]
+fn test_create_instance_from_snapshot() {
+    let pic = PocketIcBuilder::new().with_application_subnet().build();
+    let canister_id = pic.create_canister();
+    pic.add_cycles(canister_id, INIT_CYCLES);
+    pic.install_canister(canister_id, counter_wasm(), vec![], None);
+    let reply = call_counter_can(&pic, canister_id, "write");
+    assert_eq!(reply, WasmResult::Reply(vec![1, 0, 0, 0]));
+
+    // The PocketIC server is shared by tests running in parallel
+    // and thus we use a unique snapshot name.
+    let snapshot = format!("counter-{}", pic.instance_id());
+    pic.create_snapshot(&snapshot);
+    assert!(pic.list_snapshots().contains(&snapshot));
+
+    // The forked instance starts from the snapshot
+    // and evolves independently of the original instance.
+    let fork = PocketIcBuilder::new()
+        .with_snapshot(snapshot.clone())
+        .build();
+    assert_eq!(fork.topology(), pic.topology());
+    let reply = call_counter_can(&fork, canister_id, "write");
+    assert_eq!(reply, WasmResult::Reply(vec![2, 0, 0, 0]));
+    let reply = call_counter_can(&pic, canister_id, "read");
+    assert_eq!(reply, WasmResult::Reply(vec![1, 0, 0, 0]));
+
+    // Deleting the snapshot does not affect the forked instance.
+    pic.delete_snapshot(&snapshot);
+    assert!(!pic.list_snapshots().contains(&snapshot));
+    let reply = call_counter_can(&fork, canister_id, "read");
+    assert_eq!(reply, WasmResult::Reply(vec![2, 0, 0, 0]));
+}
+
 #[test]
 fn test_set_and_get_stable_memory_not_compressed() {
     let pic = PocketIc::new();
@@ -1035,6 +1103,101 @@ fn test_xnet_call_and_create_canister_with_specified_id() {
     }
 }
 
+#[test]
+fn test_call_graph_tracing() {
+    let pic = PocketIcBuilder::new()
+        .with_application_subnet()
+        .with_application_subnet()
+        .build();
+    let subnet_id_1 = pic.topology().get_app_subnets()[0];
+    let subnet_id_2 = pic.topology().get_app_subnets()[1];
+    let canister_1 = pic.create_canister_on_subnet(None, None, subnet_id_1);
+    let canister_2 = pic.create_canister_on_subnet(None, None, subnet_id_2);
+    for canister in [canister_1, canister_2] {
+        pic.add_cycles(canister, INIT_CYCLES);
+        pic.install_canister(canister, UNIVERSAL_CANISTER_WASM.to_vec(), vec![], None);
+    }
+    let payload = wasm()
+        .inter_update(
+            canister_2,
+            CallArgs::default().other_side(wasm().reply_data(b"pong")),
+        )
+        .build();
+
+    // Call graphs are only traced if tracing is enabled.
+    let message_id = pic
+        .submit_call(
+            canister_1,
+            Principal::anonymous(),
+            "update",
+            payload.clone(),
+        )
+        .unwrap();
+    pic.await_call(message_id.clone()).unwrap();
+    assert!(pic.get_call_graph(message_id).is_none());
+
+    pic.set_call_graph_tracing(true);
+    let message_id = pic
+        .submit_call(canister_1, Principal::anonymous(), "update", payload)
+        .unwrap();
+    let result = pic.await_call(message_id.clone()).unwrap();
+    assert_eq!(result, WasmResult::Reply(b"pong".to_vec()));
+    let call_graph = pic.get_call_graph(message_id).unwrap();
+
+    // The ingress message is executed once and
+    // the response from the downstream call once.
+    assert_eq!(call_graph.caller, Principal::anonymous());
+    assert_eq!(call_graph.callee, canister_1);
+    assert_eq!(call_graph.method_name, Some("update".to_string()));
+    assert_eq!(call_graph.outcome, Some(CallOutcome::Reply));
+    assert_eq!(call_graph.executions.len(), 2);
+    assert_eq!(call_graph.executions[0].respondent, None);
+    assert_eq!(call_graph.executions[1].respondent, Some(canister_2));
+
+    // The downstream call is executed on the other subnet.
+    assert_eq!(call_graph.calls.len(), 1);
+    let call = &call_graph.calls[0];
+    assert_eq!(call.caller, canister_1);
+    assert_eq!(call.callee, canister_2);
+    assert_eq!(call.method_name, Some("update".to_string()));
+    assert_eq!(call.outcome, Some(CallOutcome::Reply));
+    assert_eq!(call.executions.len(), 1);
+    assert!(call.calls.is_empty());
+    assert!(call_graph.total_instructions_used() > call.total_instructions_used());
+}
+
+#[test]
+fn test_call_graph_tracing_management_canister() {
+    let pic = PocketIc::new();
+    let canister_id = pic.create_canister();
+    pic.add_cycles(canister_id, INIT_CYCLES);
+    pic.install_canister(canister_id, UNIVERSAL_CANISTER_WASM.to_vec(), vec![], None);
+    pic.set_call_graph_tracing(true);
+
+    let payload = wasm()
+        .call_simple(
+            Principal::management_canister(),
+            "raw_rand",
+            CallArgs::default().other_side(encode_one(()).unwrap()),
+        )
+        .build();
+    let message_id = pic
+        .submit_call(canister_id, Principal::anonymous(), "update", payload)
+        .unwrap();
+    pic.await_call(message_id.clone()).unwrap();
+    let call_graph = pic.get_call_graph(message_id).unwrap();
+
+    // The call to the management canister is traced as well.
+    assert_eq!(call_graph.calls.len(), 1);
+    let call = &call_graph.calls[0];
+    assert_eq!(call.caller, canister_id);
+    assert_eq!(call.callee, Principal::management_canister());
+    assert_eq!(call.method_name, Some("raw_rand".to_string()));
+    assert_eq!(call.outcome, Some(CallOutcome::Reply));
+    assert_eq!(call.executions.len(), 1);
+    assert_eq!(call.total_instructions_used(), 0);
+}
+
 #[test]
 fn test_query_call_on_new_pocket_ic() {
     let pic = PocketIc::new();
@@ -1284,3 +1447,88 @@ fn test_canister_http() {
     let canister_http_requests = pic.get_canister_http();
     assert_eq!(canister_http_requests.len(), 0);
 }
+
+#[test]
+fn test_canister_http_mock_rules() {
+    let pic = PocketIc::new();
+
+    // Create a canister and charge it with 2T cycles.
+    let can_id = pic.create_canister();
+    pic.add_cycles(can_id, INIT_CYCLES);
+
+    // Install the test canister wasm file on the canister.
+    let test_wasm = test_canister_wasm();
+    pic.install_canister(can_id, test_wasm, vec![], None);
+
+    // The canister http outcall is answered automatically by the mock rule
+    // while the update call is executed.
+    let body = b"hello".to_vec();
+    let rule = CanisterHttpMockRule::new(
+        r"https://example\.com/?",
+        CanisterHttpResponse::CanisterHttpReply(CanisterHttpReply {
+            status: 200,
+            headers: vec![],
+            body: body.clone(),
+        }),
+    );
+    pic.add_canister_http_mock_rule(rule.clone());
+    let reply = pic
+        .update_call(
+            can_id,
+            Principal::anonymous(),
+            "canister_http",
+            encode_one(()).unwrap(),
+        )
+        .unwrap();
+    match reply {
+        WasmResult::Reply(data) => {
+            let http_response: HttpResponse = decode_one(&data).unwrap();
+            assert_eq!(http_response.body, body);
+        }
+        WasmResult::Reject(msg) => panic!("Unexpected reject {}", msg),
+    };
+
+    // A delayed mock rule only answers the canister http outcall
+    // once the time of the PocketIC instance has progressed.
+    pic.clear_canister_http_mock_rules();
+    pic.add_canister_http_mock_rule(CanisterHttpMockRule {
+        delay_ms: Some(10_000),
+        ..rule
+    });
+    let call_id = pic
+        .submit_call(
+            can_id,
+            Principal::anonymous(),
+            "canister_http",
+            encode_one(()).unwrap(),
+        )
+        .unwrap();
+    pic.tick();
+    pic.tick();
+    pic.tick();
+    // Canister http outcalls matching a mock rule are not returned.
+    assert!(pic.get_canister_http().is_empty());
+    pic.advance_time(std::time::Duration::from_secs(10));
+    pic.tick();
+    let reply = pic.await_call(call_id).unwrap();
+    match reply {
+        WasmResult::Reply(data) => {
+            let http_response: HttpResponse = decode_one(&data).unwrap();
+            assert_eq!(http_response.body, body);
+        }
+        WasmResult::Reject(msg) => panic!("Unexpected reject {}", msg),
+    };
+
+    // Without mock rules, canister http outcalls must be mocked manually.
+    pic.clear_canister_http_mock_rules();
+    pic.submit_call(
+        can_id,
+        Principal::anonymous(),
+        "canister_http",
+        encode_one(()).unwrap(),
+    )
+    .unwrap();
+    pic.tick();
+    pic.tick();
+    assert_eq!(pic.get_canister_http().len(), 1);
+}
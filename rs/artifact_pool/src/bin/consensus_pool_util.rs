@@ -8,34 +8,43 @@ use ic_interfaces::consensus_pool::*;
 use ic_logger::{LoggerImpl, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_types::{
-    consensus::{certification::CertificationMessage, CatchUpPackage, ConsensusMessageHashable},
+    consensus::{
+        certification::CertificationMessage, Block, CatchUpPackage, ConsensusMessageHashable,
+        HasBlockHash, HasHeight,
+    },
+    crypto::{threshold_sig::ni_dkg::NiDkgTag, CryptoHashOf},
     time::current_time,
-    NodeId, PrincipalId,
+    Height, NodeId, PrincipalId,
 };
 use prost::Message;
 use serde::{Deserialize, Serialize};
 use serde_bytes_repr::{ByteFmtDeserializer, ByteFmtSerializer};
 use serde_json::{Deserializer, Serializer};
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryFrom;
 use std::io::BufRead;
 use std::io::Write;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 fn main() {
     let mut app = Command::new("ic-consensus-pool-util")
         .version("0.1")
         .about("IC Consensus Pool Unitity")
         .subcommand(
-            Command::new("export").about("Export data to stdout").arg(
-                Arg::new("artifact")
-                    .short('a')
-                    .long("artifact")
-                    .value_name("NAME")
-                    .help("Artifact name")
-                    .multiple_occurrences(true)
-                    .multiple_values(true)
-                    .takes_value(true),
-            ),
+            Command::new("export")
+                .about("Export data to stdout")
+                .arg(
+                    Arg::new("artifact")
+                        .short('a')
+                        .long("artifact")
+                        .value_name("NAME")
+                        .help("Artifact name")
+                        .multiple_occurrences(true)
+                        .multiple_values(true)
+                        .takes_value(true),
+                )
+                .args(height_range_args()),
         )
         .subcommand(Command::new("import").about("Import data from stdin"))
         .subcommand(
@@ -51,6 +60,45 @@ fn main() {
                         .takes_value(true),
                 ),
         )
+        .subcommand(
+            Command::new("signers")
+                .about("Show which nodes signed notarizations and finalizations per height")
+                .args(height_range_args()),
+        )
+        .subcommand(
+            Command::new("missing-shares")
+                .about(
+                    "Show nodes that have no random beacon, notarization or finalization share \
+                     at heights where other nodes' shares are still in the pool",
+                )
+                .arg(
+                    Arg::new("node")
+                        .short('n')
+                        .long("node")
+                        .value_name("NODE_ID")
+                        .help(
+                            "Expected signer (default: the committee of the highest \
+                             CatchUpPackage at or below each height)",
+                        )
+                        .multiple_occurrences(true)
+                        .multiple_values(true)
+                        .takes_value(true),
+                )
+                .args(height_range_args()),
+        )
+        .subcommand(
+            Command::new("equivocations")
+                .about(
+                    "Show nodes that signed different blocks at the same height, \
+                     as well as stored equivocation proofs",
+                )
+                .args(height_range_args()),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Print a JSON summary of the number and heights of artifacts")
+                .args(height_range_args()),
+        )
         .arg(arg!(<PATH>       "PATH to the consensus pool directory"));
     let mut help = Vec::new();
     app.write_help(&mut help)
@@ -65,6 +113,14 @@ fn main() {
         import(path)
     } else if let Some(matches) = matches.subcommand_matches("export-cup-proto") {
         export_cup_proto(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("signers") {
+        signers(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("missing-shares") {
+        missing_shares(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("equivocations") {
+        equivocations(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("stats") {
+        stats(path, matches)
     } else {
         eprintln!(
             "{}",
@@ -73,6 +129,53 @@ fn main() {
     }
 }
 
+fn height_range_args() -> [Arg<'static>; 2] {
+    [
+        Arg::new("from")
+            .long("from")
+            .value_name("HEIGHT")
+            .help("Lowest height to include (default: 0)")
+            .takes_value(true),
+        Arg::new("to")
+            .long("to")
+            .value_name("HEIGHT")
+            .help("Highest height to include (default: unbounded)")
+            .takes_value(true),
+    ]
+}
+
+/// Returns the height range given by the `--from` and `--to` arguments, if any
+/// of them is present.
+fn parse_height_range(matches: &clap::ArgMatches) -> Option<HeightRange> {
+    let parse = |name: &str| {
+        matches.value_of(name).map(|value| {
+            value
+                .parse::<u64>()
+                .map(Height::from)
+                .unwrap_or_else(|err| panic!("Invalid height '{}': {}", value, err))
+        })
+    };
+    match (parse("from"), parse("to")) {
+        (None, None) => None,
+        (min, max) => Some(HeightRange::new(
+            min.unwrap_or_else(|| Height::from(0)),
+            max.unwrap_or_else(|| Height::from(u64::MAX)),
+        )),
+    }
+}
+
+/// Returns the artifacts of `pool` within `range`, or all of them if no range
+/// is given.
+fn select<T>(
+    pool: &dyn HeightIndexedPool<T>,
+    range: &Option<HeightRange>,
+) -> Box<dyn Iterator<Item = T>> {
+    match range {
+        Some(range) => pool.get_by_height_range(range.clone()),
+        None => pool.get_all(),
+    }
+}
+
 const ALL_ARTIFACT_NAMES: [&str; 13] = [
     "RandomBeacon",
     "Finalization",
@@ -145,6 +248,7 @@ fn export(path: &str, matches: &clap::ArgMatches) {
         Some(names) => parse_artifact_names(&names.collect::<Vec<&str>>()),
         None => ALL_ARTIFACT_NAMES.to_vec(),
     };
+    let range = parse_height_range(matches);
 
     let consensus_pool = open_consensus_pool(path, true);
     let certification_pool = open_certification_pool(path, true);
@@ -152,79 +256,70 @@ fn export(path: &str, matches: &clap::ArgMatches) {
     for artifact in artifacts {
         match artifact {
             "RandomBeacon" => {
-                for x in consensus_pool.validated().random_beacon().get_all() {
+                for x in select(consensus_pool.validated().random_beacon(), &range) {
                     println!("{}", to_string(&x.into_message()));
                 }
             }
             "Finalization" => {
-                for x in consensus_pool.validated().finalization().get_all() {
+                for x in select(consensus_pool.validated().finalization(), &range) {
                     println!("{}", to_string(&x.into_message()));
                 }
             }
             "Notarization" => {
-                for x in consensus_pool.validated().notarization().get_all() {
+                for x in select(consensus_pool.validated().notarization(), &range) {
                     println!("{}", to_string(&x.into_message()));
                 }
             }
             "BlockProposal" => {
-                for x in consensus_pool.validated().block_proposal().get_all() {
+                for x in select(consensus_pool.validated().block_proposal(), &range) {
                     println!("{}", to_string(&x.into_message()));
                 }
             }
             "RandomBeaconShare" => {
-                for x in consensus_pool.validated().random_beacon_share().get_all() {
+                for x in select(consensus_pool.validated().random_beacon_share(), &range) {
                     println!("{}", to_string(&x.into_message()));
                 }
             }
             "NotarizationShare" => {
-                for x in consensus_pool.validated().notarization_share().get_all() {
+                for x in select(consensus_pool.validated().notarization_share(), &range) {
                     println!("{}", to_string(&x.into_message()));
                 }
             }
             "FinalizationShare" => {
-                for x in consensus_pool.validated().finalization_share().get_all() {
+                for x in select(consensus_pool.validated().finalization_share(), &range) {
                     println!("{}", to_string(&x.into_message()));
                 }
             }
             "RandomTape" => {
-                for x in consensus_pool.validated().random_tape().get_all() {
+                for x in select(consensus_pool.validated().random_tape(), &range) {
                     println!("{}", to_string(&x.into_message()));
                 }
             }
             "RandomTapeShare" => {
-                for x in consensus_pool.validated().random_tape_share().get_all() {
+                for x in select(consensus_pool.validated().random_tape_share(), &range) {
                     println!("{}", to_string(&x.into_message()));
                 }
             }
             "CatchUpPackage" => {
-                for x in consensus_pool.validated().catch_up_package().get_all() {
+                for x in select(consensus_pool.validated().catch_up_package(), &range) {
                     println!("{}", to_string(&x.into_message()));
                 }
             }
             "CatchUpPackageShare" => {
-                for x in consensus_pool
-                    .validated()
-                    .catch_up_package_share()
-                    .get_all()
-                {
+                for x in select(consensus_pool.validated().catch_up_package_share(), &range) {
                     println!("{}", to_string(&x.into_message()));
                 }
             }
             "Certification" => {
-                for x in certification_pool
-                    .persistent_pool
-                    .certifications()
-                    .get_all()
-                {
+                for x in select(certification_pool.persistent_pool.certifications(), &range) {
                     println!("{}", to_string(&CertificationMessage::Certification(x)));
                 }
             }
             "CertificationShare" => {
-                for x in certification_pool
-                    .persistent_pool
-                    .certification_shares()
-                    .get_all()
-                {
+                for x in select(
+                    certification_pool.persistent_pool.certification_shares(),
+                    &range,
+                ) {
                     println!(
                         "{}",
                         to_string(&CertificationMessage::CertificationShare(x))
@@ -274,3 +369,480 @@ fn export_cup_proto(path: &str, matches: &clap::ArgMatches) {
     file.write_all(&buf)
         .unwrap_or_else(|err| panic!("Cannot write to file {}: {:?}", filename, err));
 }
+
+fn hash_to_string(hash: &CryptoHashOf<Block>) -> String {
+    hash.get_ref()
+        .0
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Nodes that signed notarizations and finalizations at one height, by block
+/// hash.
+#[derive(Default, Serialize)]
+struct HeightSigners {
+    height: u64,
+    notarization: BTreeMap<String, BTreeSet<String>>,
+    notarization_shares: BTreeMap<String, BTreeSet<String>>,
+    finalization: BTreeMap<String, BTreeSet<String>>,
+    finalization_shares: BTreeMap<String, BTreeSet<String>>,
+}
+
+fn height_signers(
+    heights: &mut BTreeMap<Height, HeightSigners>,
+    height: Height,
+) -> &mut HeightSigners {
+    heights.entry(height).or_insert_with(|| HeightSigners {
+        height: height.get(),
+        ..Default::default()
+    })
+}
+
+fn signers(path: &str, matches: &clap::ArgMatches) {
+    let range = parse_height_range(matches);
+    let consensus_pool = open_consensus_pool(path, true);
+    let pool = consensus_pool.validated();
+
+    let mut heights = BTreeMap::<Height, HeightSigners>::new();
+    for x in select(pool.notarization(), &range) {
+        height_signers(&mut heights, x.height())
+            .notarization
+            .entry(hash_to_string(x.block_hash()))
+            .or_default()
+            .extend(x.signature.signers.iter().map(|node| node.to_string()));
+    }
+    for x in select(pool.notarization_share(), &range) {
+        height_signers(&mut heights, x.height())
+            .notarization_shares
+            .entry(hash_to_string(x.block_hash()))
+            .or_default()
+            .insert(x.signature.signer.to_string());
+    }
+    for x in select(pool.finalization(), &range) {
+        height_signers(&mut heights, x.height())
+            .finalization
+            .entry(hash_to_string(x.block_hash()))
+            .or_default()
+            .extend(x.signature.signers.iter().map(|node| node.to_string()));
+    }
+    for x in select(pool.finalization_share(), &range) {
+        height_signers(&mut heights, x.height())
+            .finalization_shares
+            .entry(hash_to_string(x.block_hash()))
+            .or_default()
+            .insert(x.signature.signer.to_string());
+    }
+
+    for signers in heights.values() {
+        println!("{}", to_string(signers));
+    }
+}
+
+/// Returns the expected signers that are not among the `present` ones. Heights
+/// without any share of a type are skipped for that type, as there is no way to
+/// tell missing shares apart from purged ones.
+fn missing_signers(expected: &BTreeSet<NodeId>, present: Option<&BTreeSet<NodeId>>) -> Vec<String> {
+    present
+        .map(|present| {
+            expected
+                .difference(present)
+                .map(|node| node.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Expected signers without a share at one height, by share type.
+#[derive(Debug, PartialEq, Serialize)]
+struct MissingShares {
+    height: u64,
+    random_beacon_share: Vec<String>,
+    notarization_share: Vec<String>,
+    finalization_share: Vec<String>,
+}
+
+fn missing_shares(path: &str, matches: &clap::ArgMatches) {
+    let range = parse_height_range(matches);
+    let consensus_pool = open_consensus_pool(path, true);
+    let nodes = matches.values_of("node").map(|nodes| {
+        nodes
+            .map(|node| {
+                PrincipalId::from_str(node)
+                    .map(NodeId::from)
+                    .unwrap_or_else(|err| panic!("Invalid node id '{}': {}", node, err))
+            })
+            .collect()
+    });
+
+    for report in find_missing_shares(consensus_pool.validated(), &range, nodes) {
+        println!("{}", to_string(&report));
+    }
+}
+
+/// Returns the committee of the current DKG interval of every CatchUpPackage in
+/// the pool, by the height of the CatchUpPackage.
+fn committees_by_cup_height(
+    pool: &dyn PoolSection<ValidatedConsensusArtifact>,
+) -> BTreeMap<Height, BTreeSet<NodeId>> {
+    pool.catch_up_package()
+        .get_all()
+        .map(|cup| {
+            let committee = cup
+                .content
+                .block
+                .as_ref()
+                .payload
+                .as_ref()
+                .as_summary()
+                .dkg
+                .current_transcript(&NiDkgTag::LowThreshold)
+                .committee
+                .get()
+                .clone();
+            (cup.height(), committee)
+        })
+        .collect()
+}
+
+/// Returns the expected signers without a share, for every height with a
+/// missing share. If no `nodes` are given, the expected signers of a height are
+/// the committee of the highest CatchUpPackage at or below it, and heights
+/// without such a CatchUpPackage are skipped.
+fn find_missing_shares(
+    pool: &dyn PoolSection<ValidatedConsensusArtifact>,
+    range: &Option<HeightRange>,
+    nodes: Option<BTreeSet<NodeId>>,
+) -> Vec<MissingShares> {
+    // Nodes that contributed a share, by height. Aggregated signatures are taken
+    // into account as well, as the shares they consist of may already be purged.
+    let mut random_beacon = BTreeMap::<Height, BTreeSet<NodeId>>::new();
+    let mut notarization = BTreeMap::<Height, BTreeSet<NodeId>>::new();
+    let mut finalization = BTreeMap::<Height, BTreeSet<NodeId>>::new();
+    for x in select(pool.random_beacon_share(), range) {
+        random_beacon
+            .entry(x.height())
+            .or_default()
+            .insert(x.signature.signer);
+    }
+    for x in select(pool.notarization_share(), range) {
+        notarization
+            .entry(x.height())
+            .or_default()
+            .insert(x.signature.signer);
+    }
+    for x in select(pool.notarization(), range) {
+        notarization
+            .entry(x.height())
+            .or_default()
+            .extend(x.signature.signers);
+    }
+    for x in select(pool.finalization_share(), range) {
+        finalization
+            .entry(x.height())
+            .or_default()
+            .insert(x.signature.signer);
+    }
+    for x in select(pool.finalization(), range) {
+        finalization
+            .entry(x.height())
+            .or_default()
+            .extend(x.signature.signers);
+    }
+
+    let committees = committees_by_cup_height(pool);
+    let heights: BTreeSet<Height> = random_beacon
+        .keys()
+        .chain(notarization.keys())
+        .chain(finalization.keys())
+        .cloned()
+        .collect();
+    let mut reports = Vec::new();
+    for height in heights {
+        let expected = match &nodes {
+            Some(nodes) => nodes,
+            None => match committees.range(..=height).next_back() {
+                Some((_, committee)) => committee,
+                None => {
+                    eprintln!(
+                        "Skipping height {}: no CatchUpPackage at or below it, \
+                         use --node to specify the expected signers",
+                        height
+                    );
+                    continue;
+                }
+            },
+        };
+        let report = MissingShares {
+            height: height.get(),
+            random_beacon_share: missing_signers(expected, random_beacon.get(&height)),
+            notarization_share: missing_signers(expected, notarization.get(&height)),
+            finalization_share: missing_signers(expected, finalization.get(&height)),
+        };
+        if !report.random_beacon_share.is_empty()
+            || !report.notarization_share.is_empty()
+            || !report.finalization_share.is_empty()
+        {
+            reports.push(report);
+        }
+    }
+    reports
+}
+
+/// A node that signed more than one block at the same height.
+#[derive(Serialize)]
+struct Equivocation {
+    height: u64,
+    artifact: &'static str,
+    signer: String,
+    blocks: Vec<String>,
+}
+
+fn equivocations(path: &str, matches: &clap::ArgMatches) {
+    let range = parse_height_range(matches);
+    let consensus_pool = open_consensus_pool(path, true);
+    let pool = consensus_pool.validated();
+
+    // Notarization shares are not checked, since nodes may legitimately
+    // notarize several blocks at the same height.
+    let mut block_proposals = BTreeMap::<(Height, NodeId), BTreeSet<String>>::new();
+    for x in select(pool.block_proposal(), &range) {
+        block_proposals
+            .entry((x.height(), x.signature.signer))
+            .or_default()
+            .insert(hash_to_string(x.block_hash()));
+    }
+    let mut finalization_shares = BTreeMap::<(Height, NodeId), BTreeSet<String>>::new();
+    for x in select(pool.finalization_share(), &range) {
+        finalization_shares
+            .entry((x.height(), x.signature.signer))
+            .or_default()
+            .insert(hash_to_string(x.block_hash()));
+    }
+
+    for (artifact, signed_blocks) in [
+        ("BlockProposal", block_proposals),
+        ("FinalizationShare", finalization_shares),
+    ] {
+        for ((height, signer), blocks) in signed_blocks {
+            if blocks.len() > 1 {
+                let equivocation = Equivocation {
+                    height: height.get(),
+                    artifact,
+                    signer: signer.to_string(),
+                    blocks: blocks.into_iter().collect(),
+                };
+                println!("{}", to_string(&equivocation));
+            }
+        }
+    }
+    for x in select(pool.equivocation_proof(), &range) {
+        let equivocation = Equivocation {
+            height: x.height.get(),
+            artifact: "EquivocationProof",
+            signer: x.signer.to_string(),
+            blocks: vec![hash_to_string(&x.hash1), hash_to_string(&x.hash2)],
+        };
+        println!("{}", to_string(&equivocation));
+    }
+}
+
+#[derive(Default, Serialize)]
+struct ArtifactStats {
+    count: usize,
+    min_height: Option<u64>,
+    max_height: Option<u64>,
+}
+
+fn artifact_stats<T: HasHeight>(artifacts: Box<dyn Iterator<Item = T>>) -> ArtifactStats {
+    let mut stats = ArtifactStats::default();
+    for height in artifacts.map(|x| x.height().get()) {
+        stats.count += 1;
+        stats.min_height = Some(stats.min_height.map_or(height, |min| min.min(height)));
+        stats.max_height = Some(stats.max_height.map_or(height, |max| max.max(height)));
+    }
+    stats
+}
+
+fn stats(path: &str, matches: &clap::ArgMatches) {
+    let range = parse_height_range(matches);
+    let consensus_pool = open_consensus_pool(path, true);
+    let certification_pool = open_certification_pool(path, true);
+    let pool = consensus_pool.validated();
+    let certifications = &certification_pool.persistent_pool;
+
+    let stats = BTreeMap::from([
+        (
+            "RandomBeacon",
+            artifact_stats(select(pool.random_beacon(), &range)),
+        ),
+        (
+            "Finalization",
+            artifact_stats(select(pool.finalization(), &range)),
+        ),
+        (
+            "Notarization",
+            artifact_stats(select(pool.notarization(), &range)),
+        ),
+        (
+            "BlockProposal",
+            artifact_stats(select(pool.block_proposal(), &range)),
+        ),
+        (
+            "RandomBeaconShare",
+            artifact_stats(select(pool.random_beacon_share(), &range)),
+        ),
+        (
+            "NotarizationShare",
+            artifact_stats(select(pool.notarization_share(), &range)),
+        ),
+        (
+            "FinalizationShare",
+            artifact_stats(select(pool.finalization_share(), &range)),
+        ),
+        (
+            "RandomTape",
+            artifact_stats(select(pool.random_tape(), &range)),
+        ),
+        (
+            "RandomTapeShare",
+            artifact_stats(select(pool.random_tape_share(), &range)),
+        ),
+        (
+            "CatchUpPackage",
+            artifact_stats(select(pool.catch_up_package(), &range)),
+        ),
+        (
+            "CatchUpPackageShare",
+            artifact_stats(select(pool.catch_up_package_share(), &range)),
+        ),
+        (
+            "EquivocationProof",
+            artifact_stats(select(pool.equivocation_proof(), &range)),
+        ),
+        (
+            "Certification",
+            artifact_stats(select(certifications.certifications(), &range)),
+        ),
+        (
+            "CertificationShare",
+            artifact_stats(select(certifications.certification_shares(), &range)),
+        ),
+    ]);
+    println!("{}", to_string(&stats));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_test_utilities::artifact_pool_config::with_test_pool_config;
+    use ic_test_utilities_consensus::{fake::*, make_genesis};
+    use ic_test_utilities_types::ids::node_test_id;
+    use ic_types::consensus::{
+        dkg::Summary, ConsensusMessage, FinalizationShare, NotarizationShare, RandomBeaconShare,
+    };
+
+    /// Inserts the given messages into the validated section of the pool.
+    fn insert(pool: &mut UncachedConsensusPoolImpl, messages: Vec<ConsensusMessage>) {
+        let mut ops = PoolSectionOps::new();
+        for msg in messages {
+            ops.insert(ValidatedConsensusArtifact {
+                msg,
+                timestamp: current_time(),
+            });
+        }
+        pool.validated.mutate(ops);
+    }
+
+    /// Returns the shares at height 1 on top of the given CatchUpPackage: node 0
+    /// contributes a random beacon and a finalization share, node 1 only a
+    /// notarization share.
+    fn shares_at_height_1(cup: &CatchUpPackage) -> Vec<ConsensusMessage> {
+        let block = Block::from_parent(cup.content.block.as_ref());
+        vec![
+            RandomBeaconShare::fake(cup.content.random_beacon.as_ref(), node_test_id(0))
+                .into_message(),
+            NotarizationShare::fake(&block, node_test_id(1)).into_message(),
+            FinalizationShare::fake(&block, node_test_id(0)).into_message(),
+        ]
+    }
+
+    fn missing(
+        height: u64,
+        random_beacon_share: &[u64],
+        notarization_share: &[u64],
+        finalization_share: &[u64],
+    ) -> MissingShares {
+        let names = |nodes: &[u64]| {
+            nodes
+                .iter()
+                .map(|node| node_test_id(*node).to_string())
+                .collect()
+        };
+        MissingShares {
+            height,
+            random_beacon_share: names(random_beacon_share),
+            notarization_share: names(notarization_share),
+            finalization_share: names(finalization_share),
+        }
+    }
+
+    #[test]
+    fn test_missing_shares_of_cup_committee() {
+        with_test_pool_config(|config| {
+            let mut pool = UncachedConsensusPoolImpl::new(config, no_op_logger());
+            // The committee of the fake summary consists of node 0 only, so the
+            // shares of node 1 are not expected and node 0 misses a notarization
+            // share.
+            let cup = make_genesis(Summary::fake());
+            let mut messages = shares_at_height_1(&cup);
+            messages.push(cup.into_message());
+            insert(&mut pool, messages);
+
+            assert_eq!(
+                find_missing_shares(pool.validated(), &None, None),
+                vec![missing(1, &[], &[0], &[])]
+            );
+        })
+    }
+
+    #[test]
+    fn test_missing_shares_of_given_nodes() {
+        with_test_pool_config(|config| {
+            let mut pool = UncachedConsensusPoolImpl::new(config, no_op_logger());
+            let cup = make_genesis(Summary::fake());
+            let mut messages = shares_at_height_1(&cup);
+            messages.push(cup.into_message());
+            insert(&mut pool, messages);
+
+            let nodes = BTreeSet::from([node_test_id(0), node_test_id(1)]);
+            assert_eq!(
+                find_missing_shares(pool.validated(), &None, Some(nodes)),
+                vec![missing(1, &[1], &[0], &[1])]
+            );
+        })
+    }
+
+    #[test]
+    fn test_missing_shares_skips_heights_without_cup() {
+        with_test_pool_config(|config| {
+            let mut pool = UncachedConsensusPoolImpl::new(config, no_op_logger());
+            let cup = make_genesis(Summary::fake());
+            insert(&mut pool, shares_at_height_1(&cup));
+
+            assert_eq!(
+                find_missing_shares(pool.validated(), &None, None),
+                Vec::new()
+            );
+            assert_eq!(
+                find_missing_shares(
+                    pool.validated(),
+                    &None,
+                    Some(BTreeSet::from([node_test_id(0)]))
+                ),
+                vec![missing(1, &[], &[0], &[])]
+            );
+        })
+    }
+}
@@ -55,6 +55,7 @@ pub fn generate_custom_registry_snapshot(
                 tls_certificate: valid_tls_certificate_and_validation_time()
                     .0
                     .certificate_der,
+                signing_public_key: None,
                 avg_latency_secs: f64::MAX,
             };
             let node = Arc::new(node);
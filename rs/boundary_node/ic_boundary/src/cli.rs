@@ -1,5 +1,6 @@
 use std::{net::SocketAddr, path::PathBuf};
 
+use candid::Principal;
 use clap::{Args, Parser};
 use url::Url;
 
@@ -90,6 +91,18 @@ pub struct ListenConfig {
     #[clap(long, default_value = "443")]
     pub https_port: u16,
 
+    /// Port to listen on for HTTP/3 over QUIC (UDP).
+    /// Usually the same as HTTPS port. If not specified - HTTP/3 is disabled.
+    #[cfg(feature = "tls")]
+    #[clap(long)]
+    pub http3_port: Option<u16>,
+
+    /// For how long, in seconds, the clients should remember that HTTP/3 is available.
+    /// This is advertised in the Alt-Svc header of HTTPS responses.
+    #[cfg(feature = "tls")]
+    #[clap(long, default_value = "86400")]
+    pub http3_alt_svc_max_age: u64,
+
     /// Skip replica TLS certificate verification. DANGER: to be used only for testing
     #[clap(long)]
     pub skip_replica_tls_verification: bool,
@@ -260,6 +273,16 @@ pub struct RateLimitingConfig {
         default_value = "/run/ic-node/etc/ic-boundary/canister-ratelimit.yml"
     )]
     pub rate_limit_generic: PathBuf,
+    /// ID of a canister to poll the generic rate-limiter rules from instead of the file.
+    /// The canister provides versioned rule sets in the same YAML format, certified
+    /// by its certified data. Rules also support a `scope` field (`ip` or `sender`)
+    /// to apply the limit separately per client IP or per sender principal, and an `id`
+    /// field that labels the rule's metrics (a hash of the rule is used otherwise).
+    #[clap(long)]
+    pub rate_limit_generic_canister_id: Option<Principal>,
+    /// URL of the API endpoint to reach the rate-limit configuration canister
+    #[clap(long, default_value = "https://ic0.app")]
+    pub rate_limit_generic_canister_url: Url,
 }
 
 #[derive(Args)]
@@ -293,6 +316,12 @@ pub struct RetryConfig {
     /// Whether to use latency-based routing for /call
     #[clap(long, default_value = "false")]
     pub disable_latency_routing: bool,
+
+    /// Whether to verify the signatures of query responses against the node keys
+    /// from the registry. Responses with invalid signatures are rejected and
+    /// the query is retried on another node.
+    #[clap(long, default_value = "false")]
+    pub verify_query_signatures: bool,
 }
 
 #[derive(Args)]
@@ -10,6 +10,7 @@ use {
 
 use crate::{
     core::Run,
+    http3,
     metrics::{MetricParams, WithMetrics},
     tls::{self, generate_rustls_config, load_pem, Provision, ProvisionResult, TLSCert},
 };
@@ -78,23 +79,29 @@ impl Configure for Configurator {
 
 pub struct TlsConfigurator {
     acceptor: Arc<ArcSwapOption<RustlsAcceptor>>,
+    http3_endpoint: Option<quinn::Endpoint>,
     provisioner: Box<dyn Provision>,
 }
 
 impl TlsConfigurator {
     pub fn new(
         acceptor: Arc<ArcSwapOption<RustlsAcceptor>>,
+        http3_endpoint: Option<quinn::Endpoint>,
         provisioner: Box<dyn Provision>,
     ) -> Self {
         Self {
             acceptor,
+            http3_endpoint,
             provisioner,
         }
     }
 
     async fn apply(&self, tls_cert: TLSCert) -> Result<(), ConfigureError> {
-        let (certs, key) = load_pem(tls_cert.0.into_bytes(), tls_cert.1.into_bytes())
-            .map_err(|e| anyhow!("unable to load PEM: {e:?}"))?;
+        let (certs, key) = load_pem(
+            tls_cert.0.clone().into_bytes(),
+            tls_cert.1.clone().into_bytes(),
+        )
+        .map_err(|e| anyhow!("unable to load PEM: {e:?}"))?;
 
         let cfg = generate_rustls_config(certs, key)?;
         let cfg = RustlsConfig::from_config(Arc::new(cfg));
@@ -105,6 +112,11 @@ impl TlsConfigurator {
         // Replace current acceptor
         self.acceptor.store(acceptor);
 
+        // HTTP/3 uses the same certificate
+        if let Some(v) = &self.http3_endpoint {
+            http3::set_server_config(v, tls_cert.0.as_bytes(), tls_cert.1.as_bytes())?;
+        }
+
         Ok(())
     }
 }
@@ -49,7 +49,8 @@ use crate::{
         WithMetricsCheck, WithMetricsPersist, WithMetricsSnapshot, HTTP_DURATION_BUCKETS,
     },
     persist::{Persist, Persister, Routes},
-    rate_limiting::{generic, RateLimit},
+    query_verify::{self, QueryVerifier},
+    rate_limiting::{canister, generic, RateLimit},
     retry::{retry_request, RetryParams},
     routes::{self, ErrorCause, Health, Lookup, Proxy, ProxyRouter, RootKey},
     snapshot::{
@@ -66,6 +67,7 @@ use {crate::socket::UnixServerExt, std::os::unix::fs::PermissionsExt};
 #[cfg(feature = "tls")]
 use {
     crate::{
+        http3,
         socket::listen_tcp_backlog,
         tls::{acme_challenge, prepare_tls, redirect_to_https},
     },
@@ -164,10 +166,20 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
     );
     let http_client = Arc::new(http_client);
 
+    // HTTP/3 endpoint, it gets its TLS configuration along with the HTTPS server
     #[cfg(feature = "tls")]
-    let (configuration_runner, tls_acceptor, token_owner) = prepare_tls(&cli, &metrics_registry)
-        .await
-        .context("unable to prepare TLS")?;
+    let http3_endpoint = cli
+        .listen
+        .http3_port
+        .map(|x| http3::bind_endpoint(SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), x)))
+        .transpose()
+        .context("unable to prepare HTTP/3 endpoint")?;
+
+    #[cfg(feature = "tls")]
+    let (configuration_runner, tls_acceptor, token_owner) =
+        prepare_tls(&cli, &metrics_registry, http3_endpoint.clone())
+            .await
+            .context("unable to prepare TLS")?;
 
     // Caching
     let cache = cli.cache.cache_size_bytes.map(|x| {
@@ -190,9 +202,21 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
     };
 
     // Canister Ratelimiter
-    let generic_limiter = Arc::new(generic::Limiter::new(
-        cli.rate_limiting.rate_limit_generic.clone(),
-    ));
+    let generic_fetcher: Arc<dyn generic::FetchRules> =
+        match cli.rate_limiting.rate_limit_generic_canister_id {
+            Some(canister_id) => Arc::new(
+                canister::CanisterFetcher::new(
+                    cli.rate_limiting.rate_limit_generic_canister_url.clone(),
+                    canister_id,
+                    registry_snapshot.clone(),
+                )
+                .context("unable to create rate-limit rules fetcher")?,
+            ),
+            None => Arc::new(generic::FileFetcher(
+                cli.rate_limiting.rate_limit_generic.clone(),
+            )),
+        };
+    let generic_limiter = Arc::new(generic::Limiter::new(generic_fetcher, &metrics_registry));
 
     // Server / API
     let routers_https = setup_router(
@@ -269,11 +293,30 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
         cfg
     })
     .acceptor(tls_acceptor.clone())
-    .serve(
+    .serve({
+        // Advertise HTTP/3 if it's enabled
+        let alt_svc = cli
+            .listen
+            .http3_port
+            .map(|x| http3::alt_svc_value(x, cli.listen.http3_alt_svc_max_age));
+
         routers_https
             .clone()
-            .into_make_service_with_connect_info::<SocketAddr>(),
-    );
+            .layer(option_layer(alt_svc.map(|x| {
+                middleware::from_fn_with_state(x, http3::alt_svc_middleware)
+            })))
+            .into_make_service_with_connect_info::<SocketAddr>()
+    });
+
+    // HTTP/3
+    #[cfg(feature = "tls")]
+    let srvs_http3 = http3_endpoint.map(|x| {
+        http3::Server::new(
+            x,
+            routers_https.clone(),
+            http3::Metrics::new(&metrics_registry),
+        )
+    });
 
     // Metrics
     let metrics_cache = Arc::new(RwLock::new(MetricsCache::new(METRICS_CACHE_CAPACITY)));
@@ -372,6 +415,14 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
         #[cfg(feature = "tls")]
         s.spawn(srvs_https.map_err(|err| anyhow!("failed to start https server: {:?}", err)));
 
+        #[cfg(feature = "tls")]
+        if let Some(v) = srvs_http3 {
+            s.spawn(
+                v.serve()
+                    .map_err(|err| anyhow!("failed to start http3 server: {:?}", err)),
+            );
+        }
+
         // Runners
         runners.into_iter().for_each(|mut r| {
             s.spawn(async move {
@@ -430,6 +481,7 @@ fn setup_registry(
                 registry_client.clone(),
                 Duration::from_secs(cli.registry.min_version_age),
             );
+            snapshotter.set_fetch_node_signing_keys(cli.retry.verify_query_signatures);
 
             if let Some(v) = &cli.firewall.nftables_system_replicas_path {
                 let fw_reloader = SystemdReloader::new(SYSTEMCTL_BIN.into(), "nftables", "reload");
@@ -543,6 +595,13 @@ pub fn setup_router(
         .route(routes::PATH_QUERY, {
             post(routes::handle_canister).with_state(proxy.clone())
         })
+        // Verification is done per node before caching, so that only verified responses are cached
+        .layer(option_layer(cli.retry.verify_query_signatures.then(|| {
+            middleware::from_fn_with_state(
+                Arc::new(QueryVerifier::new(metrics_registry)),
+                query_verify::middleware,
+            )
+        })))
         .layer(option_layer(cache.map(|x| {
             middleware::from_fn_with_state(x.clone(), cache_middleware)
         })));
@@ -0,0 +1,254 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{Context, Error};
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    middleware::Next,
+    response::IntoResponse,
+    Router,
+};
+use bytes::{Buf, Bytes, BytesMut};
+use h3::{error::ErrorLevel, server::RequestStream};
+use http::{header::ALT_SVC, HeaderValue, Request, Version};
+use hyper::body::HttpBody;
+use prometheus::{
+    register_int_counter_vec_with_registry, register_int_gauge_with_registry, IntCounterVec,
+    IntGauge, Registry,
+};
+use quinn::{crypto::rustls::QuicServerConfig, rustls};
+use tower::ServiceExt;
+use tracing::{debug, warn};
+
+use crate::{core::MAX_REQUEST_BODY_SIZE, routes::ErrorCause};
+
+const ALPN_H3: &[u8] = b"h3";
+
+// Binds a QUIC endpoint to the given address.
+// It doesn't accept connections until the TLS configuration is applied using `set_server_config()`.
+pub fn bind_endpoint(addr: SocketAddr) -> Result<quinn::Endpoint, Error> {
+    let socket = std::net::UdpSocket::bind(addr).context("unable to bind UDP socket")?;
+
+    quinn::Endpoint::new(
+        quinn::EndpointConfig::default(),
+        None,
+        socket,
+        Arc::new(quinn::TokioRuntime),
+    )
+    .context("unable to create QUIC endpoint")
+}
+
+// Applies the given PEM-encoded certificate chain & private key to the endpoint,
+// replacing the current TLS configuration. Already established connections are not affected.
+// QUIC needs a newer Rustls than the TCP listener, so the configuration can't be shared.
+pub fn set_server_config(
+    endpoint: &quinn::Endpoint,
+    certs: &[u8],
+    key: &[u8],
+) -> Result<(), Error> {
+    let certs = rustls_pemfile::certs(&mut &certs[..])
+        .collect::<Result<Vec<_>, _>>()
+        .context("unable to parse certificates")?;
+    let key = rustls_pemfile::private_key(&mut &key[..])
+        .context("unable to parse private key")?
+        .context("no private key found")?;
+
+    let mut cfg = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_protocol_versions(&[&rustls::version::TLS13])?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)?;
+    cfg.alpn_protocols = vec![ALPN_H3.to_vec()];
+
+    let cfg = QuicServerConfig::try_from(cfg)?;
+    endpoint.set_server_config(Some(quinn::ServerConfig::with_crypto(Arc::new(cfg))));
+
+    Ok(())
+}
+
+// Value of the Alt-Svc header that advertises HTTP/3 to the clients
+pub fn alt_svc_value(port: u16, max_age: u64) -> HeaderValue {
+    HeaderValue::from_str(&format!("h3=\":{port}\"; ma={max_age}")).unwrap()
+}
+
+// Middleware: advertises HTTP/3 availability in responses sent over TCP
+pub async fn alt_svc_middleware(
+    State(alt_svc): State<HeaderValue>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> impl IntoResponse {
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(ALT_SVC, alt_svc);
+    response
+}
+
+#[derive(Clone)]
+pub struct Metrics {
+    connections: IntCounterVec,
+    connections_open: IntGauge,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Self {
+        Self {
+            connections: register_int_counter_vec_with_registry!(
+                "http3_connections_total",
+                "Number of incoming HTTP/3 connections, by handshake status",
+                &["status"],
+                registry
+            )
+            .unwrap(),
+
+            connections_open: register_int_gauge_with_registry!(
+                "http3_connections_open",
+                "Number of currently open HTTP/3 connections",
+                registry
+            )
+            .unwrap(),
+        }
+    }
+}
+
+// Serves the router over HTTP/3
+pub struct Server {
+    endpoint: quinn::Endpoint,
+    router: Router,
+    metrics: Metrics,
+}
+
+impl Server {
+    pub fn new(endpoint: quinn::Endpoint, router: Router, metrics: Metrics) -> Self {
+        Self {
+            endpoint,
+            router,
+            metrics,
+        }
+    }
+
+    pub async fn serve(self) -> Result<(), Error> {
+        while let Some(incoming) = self.endpoint.accept().await {
+            let (router, metrics) = (self.router.clone(), self.metrics.clone());
+
+            tokio::spawn(async move {
+                let conn = match incoming.await {
+                    Ok(v) => v,
+                    Err(e) => {
+                        metrics.connections.with_label_values(&["fail"]).inc();
+                        debug!("HTTP3: handshake failed: {e}");
+                        return;
+                    }
+                };
+
+                metrics.connections.with_label_values(&["ok"]).inc();
+                metrics.connections_open.inc();
+                if let Err(e) = handle_connection(conn, router).await {
+                    debug!("HTTP3: connection failed: {e:#}");
+                }
+                metrics.connections_open.dec();
+            });
+        }
+
+        Ok(())
+    }
+}
+
+async fn handle_connection(conn: quinn::Connection, router: Router) -> Result<(), Error> {
+    let remote_addr = conn.remote_address();
+
+    let mut conn: h3::server::Connection<_, Bytes> =
+        h3::server::Connection::new(h3_quinn::Connection::new(conn))
+            .await
+            .context("unable to establish HTTP3 connection")?;
+
+    loop {
+        match conn.accept().await {
+            Ok(Some((request, stream))) => {
+                let router = router.clone();
+
+                tokio::spawn(async move {
+                    if let Err(e) = handle_request(router, request, stream, remote_addr).await {
+                        debug!("HTTP3: request failed: {e:#}");
+                    }
+                });
+            }
+
+            // Connection was gracefully closed by the client
+            Ok(None) => return Ok(()),
+
+            Err(e) => match e.get_error_level() {
+                ErrorLevel::ConnectionError => return Err(e.into()),
+                ErrorLevel::StreamError => {
+                    warn!("HTTP3: stream error: {e}");
+                    continue;
+                }
+            },
+        }
+    }
+}
+
+async fn handle_request<S>(
+    router: Router,
+    request: http_1::Request<()>,
+    mut stream: RequestStream<S, Bytes>,
+    remote_addr: SocketAddr,
+) -> Result<(), Error>
+where
+    S: h3::quic::BidiStream<Bytes>,
+{
+    // Buffer the request body
+    let mut body = BytesMut::new();
+    while let Some(mut chunk) = stream.recv_data().await? {
+        if body.len() + chunk.remaining() > MAX_REQUEST_BODY_SIZE {
+            return send_response(
+                &mut stream,
+                ErrorCause::PayloadTooLarge(MAX_REQUEST_BODY_SIZE).into_response(),
+            )
+            .await;
+        }
+
+        body.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+    }
+
+    // h3 uses HTTP types v1 while the router is still on v0.2
+    let mut builder = Request::builder()
+        .method(request.method().as_str())
+        .uri(request.uri().to_string())
+        .version(Version::HTTP_3)
+        .extension(ConnectInfo(remote_addr));
+    for (name, value) in request.headers() {
+        builder = builder.header(name.as_str(), value.as_bytes());
+    }
+    let request = builder.body(Body::from(body.freeze()))?;
+
+    // Router is infallible
+    let response = router.oneshot(request).await.unwrap();
+
+    send_response(&mut stream, response).await
+}
+
+async fn send_response<S>(
+    stream: &mut RequestStream<S, Bytes>,
+    response: axum::response::Response,
+) -> Result<(), Error>
+where
+    S: h3::quic::BidiStream<Bytes>,
+{
+    let (parts, mut body) = response.into_parts();
+
+    let mut builder = http_1::Response::builder().status(parts.status.as_u16());
+    for (name, value) in &parts.headers {
+        builder = builder.header(name.as_str(), value.as_bytes());
+    }
+    stream.send_response(builder.body(())?).await?;
+
+    while let Some(chunk) = body.data().await {
+        stream.send_data(chunk?).await?;
+    }
+
+    stream.finish().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test;
@@ -0,0 +1,98 @@
+use super::*;
+
+use std::net::Ipv4Addr;
+
+use axum::{middleware, routing::get};
+use quinn::crypto::rustls::QuicClientConfig;
+
+#[tokio::test]
+async fn test_alt_svc() -> Result<(), Error> {
+    let alt_svc = alt_svc_value(443, 3600);
+    assert_eq!(alt_svc.to_str()?, "h3=\":443\"; ma=3600");
+
+    let router = Router::new()
+        .route("/", get(|| async { "foo" }))
+        .layer(middleware::from_fn_with_state(alt_svc, alt_svc_middleware));
+
+    let request = Request::builder().uri("/").body(Body::empty())?;
+    let response = router.oneshot(request).await.unwrap();
+
+    assert_eq!(
+        response.headers().get(ALT_SVC).unwrap(),
+        "h3=\":443\"; ma=3600"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_serve_http3() -> Result<(), Error> {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()])?;
+
+    // Server
+    let endpoint = bind_endpoint(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0))?;
+    set_server_config(
+        &endpoint,
+        cert.cert.pem().as_bytes(),
+        cert.key_pair.serialize_pem().as_bytes(),
+    )?;
+    let server_addr = endpoint.local_addr()?;
+
+    let router = Router::new().route(
+        "/foo",
+        get(|request: Request<Body>| async move {
+            let ConnectInfo(addr) = request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .unwrap();
+            assert_eq!(request.version(), Version::HTTP_3);
+            assert_eq!(request.headers().get("x-foo").unwrap(), "bar");
+            format!("foo from {}", addr.ip())
+        }),
+    );
+    let server = Server::new(endpoint, router, Metrics::new(&Registry::new()));
+    let server = tokio::spawn(server.serve());
+
+    // Client
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(cert.cert.der().clone())?;
+    let mut tls = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_protocol_versions(&[&rustls::version::TLS13])?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    tls.alpn_protocols = vec![ALPN_H3.to_vec()];
+
+    let mut client = quinn::Endpoint::client(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0))?;
+    client.set_default_client_config(quinn::ClientConfig::new(Arc::new(
+        QuicClientConfig::try_from(tls)?,
+    )));
+    let conn = client.connect(server_addr, "localhost")?.await?;
+
+    let (mut driver, mut send_request) = h3::client::new(h3_quinn::Connection::new(conn)).await?;
+    let driver =
+        tokio::spawn(async move { std::future::poll_fn(|cx| driver.poll_close(cx)).await });
+
+    let request = http_1::Request::get("https://localhost/foo")
+        .header("x-foo", "bar")
+        .body(())?;
+    let mut stream = send_request.send_request(request).await?;
+    stream.finish().await?;
+
+    let response = stream.recv_response().await?;
+    assert_eq!(response.status(), http_1::StatusCode::OK);
+
+    let mut body = BytesMut::new();
+    while let Some(mut chunk) = stream.recv_data().await? {
+        body.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+    }
+    assert_eq!(body, "foo from 127.0.0.1");
+
+    drop(send_request);
+    client.close(0u32.into(), b"done");
+    driver.abort();
+    server.abort();
+
+    Ok(())
+}
@@ -15,9 +15,12 @@ mod dns;
 mod firewall;
 mod geoip;
 mod http;
+#[cfg(feature = "tls")]
+mod http3;
 mod log;
 mod metrics;
 mod persist;
+mod query_verify;
 mod rate_limiting;
 mod retry;
 mod routes;
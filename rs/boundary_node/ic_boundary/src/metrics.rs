@@ -507,6 +507,7 @@ impl HttpMetricParams {
             "cache_status",
             "cache_bypass",
             "retry",
+            "http_version",
         ];
 
         Self {
@@ -751,6 +752,7 @@ pub async fn metrics_middleware(
             cache_status_lbl.as_str(),        // x4
             cache_bypass_reason_lbl.as_str(), // x6 but since it relates only to BYPASS cache status -> total for 2 fields is x9
             retry_lbl,                        // x3
+            http_version,                     // x3
         ];
 
         counter.with_label_values(labels).inc();
@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::State,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use http::{Request, StatusCode};
+use ic_types::{
+    crypto::Signable,
+    messages::{
+        HttpQueryContent, HttpQueryResponse, HttpRequestEnvelope, NodeSignature, Query,
+        QueryResponseHash,
+    },
+};
+use prometheus::{register_int_counter_vec_with_registry, IntCounterVec, Registry};
+use serde::Deserialize;
+use strum::IntoStaticStr;
+use tracing::warn;
+
+use crate::{
+    core::MAX_REQUEST_BODY_SIZE,
+    http::read_streaming_body,
+    routes::{ApiError, ErrorCause},
+    snapshot::Node,
+};
+
+// Upper bound on the size of a query response that we buffer for verification
+const MAX_RESPONSE_BODY_SIZE: usize = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum VerifyError {
+    #[error("unable to decode query request: {0}")]
+    MalformedRequest(String),
+    #[error("unable to decode query response: {0}")]
+    MalformedResponse(String),
+    #[error("query response carries no signatures")]
+    NoSignatures,
+    #[error("query response is signed by {0} and not by the node it was sent to")]
+    UnexpectedSigner(String),
+    #[error("node signing key is not available")]
+    MissingPublicKey,
+    #[error("invalid node signing key in the registry: {0}")]
+    InvalidPublicKey(String),
+    #[error("invalid query response signature: {0}")]
+    InvalidSignature(String),
+}
+
+// Query response as sent by the replica, see HttpSignedQueryResponse.
+// The signatures are serialized as a list, so we can't use that type directly.
+#[derive(Deserialize)]
+struct SignedQueryResponse {
+    #[serde(flatten)]
+    response: HttpQueryResponse,
+    signatures: Vec<NodeSignature>,
+}
+
+/// Verifies that the query response was signed by the given node for the given query request.
+/// Both request & response are expected as CBOR-encoded bodies.
+pub fn verify_query_response(
+    node: &Node,
+    request: &[u8],
+    response: &[u8],
+) -> Result<(), VerifyError> {
+    let envelope: HttpRequestEnvelope<HttpQueryContent> = serde_cbor::from_slice(request)
+        .map_err(|e| VerifyError::MalformedRequest(e.to_string()))?;
+    let HttpQueryContent::Query { query } = envelope.content;
+    let query = Query::try_from(query).map_err(|e| VerifyError::MalformedRequest(e.to_string()))?;
+
+    let response: SignedQueryResponse = serde_cbor::from_slice(response)
+        .map_err(|e| VerifyError::MalformedResponse(e.to_string()))?;
+    if response.signatures.is_empty() {
+        return Err(VerifyError::NoSignatures);
+    }
+
+    let public_key = node
+        .signing_public_key
+        .as_deref()
+        .ok_or(VerifyError::MissingPublicKey)?;
+    let public_key = ic_crypto_ed25519::PublicKey::deserialize_raw(public_key)
+        .map_err(|e| VerifyError::InvalidPublicKey(format!("{e:?}")))?;
+
+    for signature in response.signatures.iter() {
+        if signature.identity.get().0 != node.id {
+            return Err(VerifyError::UnexpectedSigner(
+                signature.identity.to_string(),
+            ));
+        }
+
+        let hash = QueryResponseHash::new(&response.response, &query, signature.timestamp);
+        public_key
+            .verify_signature(&hash.as_signed_bytes(), &signature.signature.0)
+            .map_err(|e| VerifyError::InvalidSignature(format!("{e:?}")))?;
+    }
+
+    Ok(())
+}
+
+pub struct QueryVerifier {
+    rejected: IntCounterVec,
+}
+
+impl QueryVerifier {
+    pub fn new(registry: &Registry) -> Self {
+        Self {
+            rejected: register_int_counter_vec_with_registry!(
+                "query_verify_rejected_responses",
+                "Number of query responses rejected due to failed signature verification",
+                &["subnet_id", "node_id", "reason"],
+                registry
+            )
+            .unwrap(),
+        }
+    }
+}
+
+// Middleware: verifies the signature of the query response from the node that the request was sent to.
+// If the verification fails - a retriable error is returned so that the retry middleware can pick another node.
+pub async fn middleware(
+    State(verifier): State<Arc<QueryVerifier>>,
+    Extension(node): Extension<Arc<Node>>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<impl IntoResponse, ApiError> {
+    // Buffer the request body since we need it to compute the request id
+    let (parts, body) = request.into_parts();
+    let request_body = read_streaming_body(body, MAX_REQUEST_BODY_SIZE).await?;
+    let request = Request::from_parts(parts, Body::from(request_body.clone()));
+
+    let response = next.run(request).await;
+
+    // Only the replies from the replica are signed
+    if response.status() != StatusCode::OK || response.extensions().get::<ErrorCause>().is_some() {
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let response_body = read_streaming_body(body, MAX_RESPONSE_BODY_SIZE).await?;
+
+    if let Err(e) = verify_query_response(&node, &request_body, &response_body) {
+        let reason: &'static str = (&e).into();
+        verifier
+            .rejected
+            .with_label_values(&[&node.subnet_id.to_string(), &node.id.to_string(), reason])
+            .inc();
+
+        warn!("query response from node {} rejected: {e}", node.id);
+        return Err(ErrorCause::ReplicaInvalidSignature(e.to_string()).into());
+    }
+
+    Ok(Response::from_parts(
+        parts,
+        axum::body::boxed(Body::from(response_body)),
+    ))
+}
+
+#[cfg(test)]
+pub mod test;
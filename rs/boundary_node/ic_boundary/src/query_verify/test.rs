@@ -0,0 +1,164 @@
+use super::*;
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use candid::Principal;
+use ic_registry_subnet_type::SubnetType;
+use ic_types::{
+    messages::{Blob, HttpQueryResponseReply, HttpSignedQueryResponse, HttpUserQuery},
+    time::Time,
+    NodeId,
+};
+
+use crate::{
+    snapshot::{node_test_id, subnet_test_id},
+    test_utils::node_signing_key,
+};
+
+fn test_node(node_id: NodeId) -> Node {
+    Node {
+        id: node_id.get().0,
+        subnet_id: subnet_test_id(1).get().0,
+        subnet_type: SubnetType::Application,
+        addr: IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
+        port: 8080,
+        tls_certificate: vec![],
+        signing_public_key: Some(
+            node_signing_key(node_id)
+                .public_key()
+                .serialize_raw()
+                .to_vec(),
+        ),
+        avg_latency_secs: f64::MAX,
+    }
+}
+
+fn test_query() -> HttpUserQuery {
+    HttpUserQuery {
+        canister_id: Blob(
+            Principal::from_text("rwlgt-iiaaa-aaaaa-aaaaa-cai")
+                .unwrap()
+                .as_slice()
+                .to_vec(),
+        ),
+        method_name: "greet".into(),
+        arg: Blob(b"foo".to_vec()),
+        sender: Blob(Principal::anonymous().as_slice().to_vec()),
+        ingress_expiry: 1234,
+        nonce: Some(Blob(b"nonce".to_vec())),
+    }
+}
+
+fn encode_request(query: HttpUserQuery) -> Vec<u8> {
+    serde_cbor::to_vec(&HttpRequestEnvelope {
+        content: HttpQueryContent::Query { query },
+        sender_pubkey: None,
+        sender_sig: None,
+        sender_delegation: None,
+    })
+    .unwrap()
+}
+
+// Creates a response to the query signed by the given node
+fn encode_response(
+    query: HttpUserQuery,
+    response: HttpQueryResponse,
+    signer: NodeId,
+    identity: NodeId,
+) -> Vec<u8> {
+    let timestamp = Time::from_nanos_since_unix_epoch(1_000_000);
+    let hash = QueryResponseHash::new(&response, &Query::try_from(query).unwrap(), timestamp);
+    let signature = node_signing_key(signer).sign_message(&hash.as_signed_bytes());
+
+    serde_cbor::to_vec(&HttpSignedQueryResponse {
+        response,
+        node_signature: NodeSignature {
+            timestamp,
+            signature: Blob(signature.to_vec()),
+            identity,
+        },
+    })
+    .unwrap()
+}
+
+fn replied(arg: &[u8]) -> HttpQueryResponse {
+    HttpQueryResponse::Replied {
+        reply: HttpQueryResponseReply {
+            arg: Blob(arg.to_vec()),
+        },
+    }
+}
+
+#[test]
+fn test_verify_query_response() {
+    let node_id = node_test_id(1);
+    let node = test_node(node_id);
+    let request = encode_request(test_query());
+
+    // Valid reply
+    let response = encode_response(test_query(), replied(b"hello"), node_id, node_id);
+    assert_eq!(verify_query_response(&node, &request, &response), Ok(()));
+
+    // Valid reject
+    let reject = HttpQueryResponse::Rejected {
+        error_code: "IC0406".into(),
+        reject_code: 4,
+        reject_message: "rejected".into(),
+    };
+    let response = encode_response(test_query(), reject, node_id, node_id);
+    assert_eq!(verify_query_response(&node, &request, &response), Ok(()));
+
+    // Signature by another node's key
+    let response = encode_response(test_query(), replied(b"hello"), node_test_id(2), node_id);
+    assert!(matches!(
+        verify_query_response(&node, &request, &response),
+        Err(VerifyError::InvalidSignature(_))
+    ));
+
+    // Signature claims to come from another node
+    let response = encode_response(test_query(), replied(b"hello"), node_id, node_test_id(2));
+    assert!(matches!(
+        verify_query_response(&node, &request, &response),
+        Err(VerifyError::UnexpectedSigner(_))
+    ));
+
+    // Response signed for another request
+    let mut other_query = test_query();
+    other_query.arg = Blob(b"bar".to_vec());
+    let response = encode_response(other_query, replied(b"hello"), node_id, node_id);
+    assert!(matches!(
+        verify_query_response(&node, &request, &response),
+        Err(VerifyError::InvalidSignature(_))
+    ));
+
+    // Garbage
+    assert!(matches!(
+        verify_query_response(&node, &request, b"foobar"),
+        Err(VerifyError::MalformedResponse(_))
+    ));
+    assert!(matches!(
+        verify_query_response(&node, b"foobar", &response),
+        Err(VerifyError::MalformedRequest(_))
+    ));
+
+    // Invalid key in the registry
+    let response = encode_response(test_query(), replied(b"hello"), node_id, node_id);
+    let node = Node {
+        signing_public_key: Some(vec![]),
+        ..node
+    };
+    assert!(matches!(
+        verify_query_response(&node, &request, &response),
+        Err(VerifyError::InvalidPublicKey(_))
+    ));
+
+    // No key in the registry
+    let node = Node {
+        signing_public_key: None,
+        ..node
+    };
+    assert_eq!(
+        verify_query_response(&node, &request, &response),
+        Err(VerifyError::MissingPublicKey)
+    );
+}
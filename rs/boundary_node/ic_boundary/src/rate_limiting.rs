@@ -130,6 +130,7 @@ impl RateLimit {
     }
 }
 
+pub mod canister;
 pub mod generic;
 
 #[cfg(test)]
@@ -0,0 +1,313 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Error};
+use arc_swap::ArcSwapOption;
+use async_trait::async_trait;
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_agent::{lookup_value, Agent, Certificate};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use super::generic::{parse_rules, FetchRules, RuleSet};
+use crate::snapshot::RegistrySnapshot;
+
+const METHOD_GET_CONFIG: &str = "get_config";
+
+/// Response of the `get_config` query of the rate-limit configuration canister.
+///
+/// The canister certifies each rule set by setting its certified data to
+/// `sha256(version as 8 big-endian bytes || rules)` and returns the
+/// certificate obtained with `ic0.data_certificate` along with the rules.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct GetConfigResponse {
+    pub version: u64,
+    /// YAML list of rules in the same format as the rules file
+    pub rules: String,
+    pub certificate: Vec<u8>,
+}
+
+/// Hash of a rule set that the configuration canister sets as its certified data
+pub fn certified_data(version: u64, rules: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(version.to_be_bytes());
+    hasher.update(rules.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+/// Fetches versioned, certified rules from a rate-limit configuration canister
+pub struct CanisterFetcher {
+    agent: Agent,
+    canister_id: Principal,
+    registry_snapshot: Arc<ArcSwapOption<RegistrySnapshot>>,
+}
+
+impl CanisterFetcher {
+    pub fn new(
+        url: Url,
+        canister_id: Principal,
+        registry_snapshot: Arc<ArcSwapOption<RegistrySnapshot>>,
+    ) -> Result<Self, Error> {
+        let agent = Agent::builder()
+            .with_url(url)
+            .build()
+            .context("unable to create agent")?;
+
+        Ok(Self::with_agent(agent, canister_id, registry_snapshot))
+    }
+
+    fn with_agent(
+        agent: Agent,
+        canister_id: Principal,
+        registry_snapshot: Arc<ArcSwapOption<RegistrySnapshot>>,
+    ) -> Self {
+        Self {
+            agent,
+            canister_id,
+            registry_snapshot,
+        }
+    }
+
+    /// Sets the NNS public key from the registry snapshot as the agent's root key.
+    /// The key is never fetched from the endpoint since the rules couldn't be trusted then,
+    /// so without a registry (i.e. when using stub replicas) fetching the rules fails.
+    fn update_root_key(&self) -> Result<(), Error> {
+        let snapshot = self
+            .registry_snapshot
+            .load_full()
+            .ok_or_else(|| anyhow!("registry snapshot is not yet available"))?;
+
+        if snapshot.nns_public_key.is_empty() {
+            return Err(anyhow!("registry snapshot has no NNS public key"));
+        }
+
+        self.agent.set_root_key(snapshot.nns_public_key.clone());
+        Ok(())
+    }
+
+    /// Checks that the certificate is valid and certifies the given rule set
+    fn verify(&self, response: &GetConfigResponse) -> Result<(), Error> {
+        let cert: Certificate = serde_cbor::from_slice(&response.certificate)
+            .context("unable to decode certificate")?;
+
+        self.agent
+            .verify(&cert, self.canister_id)
+            .context("agent failed to verify certificate")?;
+
+        let witness = lookup_value(
+            &cert,
+            vec![
+                "canister".as_bytes(),
+                self.canister_id.as_slice(),
+                "certified_data".as_bytes(),
+            ],
+        )
+        .context("failed to lookup certified data")?;
+
+        if witness != certified_data(response.version, &response.rules) {
+            return Err(anyhow!("certified data does not match the rule set"));
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl FetchRules for CanisterFetcher {
+    async fn fetch_rules(&self) -> Result<RuleSet, Error> {
+        self.update_root_key()?;
+
+        let response = self
+            .agent
+            .query(&self.canister_id, METHOD_GET_CONFIG)
+            .with_arg(Encode!()?)
+            .call()
+            .await
+            .context("unable to query config canister")?;
+
+        let response =
+            Decode!(&response, GetConfigResponse).context("unable to decode response")?;
+
+        self.verify(&response)
+            .context("unable to verify rule set")?;
+
+        Ok(RuleSet {
+            version: Some(response.version),
+            rules: parse_rules(response.rules.as_bytes())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use ic_agent::agent::ReplyResponse;
+    use ic_certification_test_utils::{CertificateBuilder, CertificateData};
+    use ic_crypto_tree_hash::Digest as TreeDigest;
+    use ic_crypto_utils_threshold_sig_der::public_key_to_der;
+    use ic_types::{crypto::threshold_sig::ThresholdSigPublicKey, CanisterId};
+    use indoc::indoc;
+    use serde::Serialize;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::snapshot::generate_stub_snapshot;
+
+    const RULES: &str = indoc! {"
+    - canister_id: aaaaa-aa
+      limit: 10/1s
+    "};
+
+    /// Query response as returned by the replica's query endpoint
+    #[derive(Serialize)]
+    struct QueryReply {
+        status: &'static str,
+        reply: ReplyResponse,
+    }
+
+    fn now_nanos() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64
+    }
+
+    /// Builds a `get_config` response with a certificate for the given certified data
+    fn get_config_response(
+        canister_id: Principal,
+        version: u64,
+        certified_data: Vec<u8>,
+    ) -> (GetConfigResponse, ThresholdSigPublicKey) {
+        let (_, root_key, certificate) = CertificateBuilder::new(CertificateData::CanisterData {
+            canister_id: CanisterId::unchecked_from_principal(canister_id.into()),
+            certified_data: TreeDigest(certified_data.try_into().unwrap()),
+        })
+        .with_time(now_nanos())
+        .build();
+
+        let response = GetConfigResponse {
+            version,
+            rules: RULES.into(),
+            certificate,
+        };
+
+        (response, root_key)
+    }
+
+    /// Starts a stub config canister that replies to every query with the given response
+    async fn start_canister(canister_id: Principal, response: &GetConfigResponse) -> MockServer {
+        let body = serde_cbor::to_vec(&QueryReply {
+            status: "replied",
+            reply: ReplyResponse {
+                arg: Encode!(response).unwrap(),
+            },
+        })
+        .unwrap();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(format!("/api/v2/canister/{canister_id}/query")))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "application/cbor")
+                    .set_body_bytes(body),
+            )
+            .mount(&server)
+            .await;
+
+        server
+    }
+
+    fn fetcher(
+        server: &MockServer,
+        canister_id: Principal,
+        nns_public_key: Option<ThresholdSigPublicKey>,
+    ) -> CanisterFetcher {
+        // The stub doesn't sign its responses, the rules are checked using the certificate
+        let agent = Agent::builder()
+            .with_url(server.uri())
+            .with_verify_query_signatures(false)
+            .build()
+            .unwrap();
+
+        let mut snapshot = generate_stub_snapshot(vec![]);
+        if let Some(key) = nns_public_key {
+            snapshot.nns_public_key = public_key_to_der(&key.into_bytes()).unwrap();
+        }
+
+        CanisterFetcher::with_agent(
+            agent,
+            canister_id,
+            Arc::new(ArcSwapOption::new(Some(Arc::new(snapshot)))),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_fetch_certified_rules() {
+        let canister_id = Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap();
+        let (response, root_key) = get_config_response(canister_id, 7, certified_data(7, RULES));
+        let server = start_canister(canister_id, &response).await;
+
+        let ruleset = fetcher(&server, canister_id, Some(root_key))
+            .fetch_rules()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            ruleset,
+            RuleSet {
+                version: Some(7),
+                rules: parse_rules(RULES.as_bytes()).unwrap(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rules_with_mismatching_certified_data() {
+        let canister_id = Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap();
+        // The certified data is for another version than the one returned
+        let (response, root_key) = get_config_response(canister_id, 7, certified_data(6, RULES));
+        let server = start_canister(canister_id, &response).await;
+
+        let err = fetcher(&server, canister_id, Some(root_key))
+            .fetch_rules()
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("certified data does not match"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rules_with_untrusted_certificate() {
+        let canister_id = Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap();
+        let (response, _) = get_config_response(canister_id, 7, certified_data(7, RULES));
+        // Root key of an unrelated certificate
+        let (_, other_root_key) = get_config_response(canister_id, 7, certified_data(7, RULES));
+        let server = start_canister(canister_id, &response).await;
+
+        let err = fetcher(&server, canister_id, Some(other_root_key))
+            .fetch_rules()
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("agent failed to verify certificate"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rules_without_nns_public_key() {
+        let canister_id = Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap();
+        let (response, _) = get_config_response(canister_id, 7, certified_data(7, RULES));
+        let server = start_canister(canister_id, &response).await;
+
+        let err = fetcher(&server, canister_id, None)
+            .fetch_rules()
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("no NNS public key"));
+
+        // The canister must not be reached without a root key to verify the response with
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+}
@@ -1,11 +1,17 @@
-use std::{fmt, path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::{Context, Error};
 use arc_swap::ArcSwap;
 use async_trait::async_trait;
 use axum::{
     body::Body,
-    extract::{Extension, State},
+    extract::{ConnectInfo, Extension, State},
     http::Request,
     middleware::Next,
     response::IntoResponse,
@@ -13,12 +19,18 @@ use axum::{
 use candid::Principal;
 use humantime::parse_duration;
 use ic_types::CanisterId;
+use moka::sync::{Cache, CacheBuilder};
+use prometheus::{
+    register_int_counter_vec_with_registry, register_int_gauge_with_registry, IntCounterVec,
+    IntGauge, Registry,
+};
 use ratelimit::Ratelimiter;
 use regex::Regex;
 use serde::{
     de::{self, Deserializer},
     Deserialize,
 };
+use sha2::{Digest, Sha256};
 use tokio::fs;
 use tracing::warn;
 
@@ -26,8 +38,12 @@ use crate::{
     core::Run,
     persist::RouteSubnet,
     routes::{ErrorCause, RateLimitCause, RequestContext, RequestType},
+    socket::TcpConnectInfo,
 };
 
+/// Maximum number of per-IP or per-sender buckets kept for a single scoped rule
+const MAX_SCOPED_BUCKETS: u64 = 100_000;
+
 /// Implement serde parser for Action
 struct ActionVisitor;
 impl<'de> de::Visitor<'de> for ActionVisitor {
@@ -78,13 +94,27 @@ impl<'de> Deserialize<'de> for Action {
     }
 }
 
+/// Defines what the limit of a rule is applied to.
+/// Without a scope all matching requests share a single limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Scope {
+    /// Separate limit for each client IP address
+    Ip,
+    /// Separate limit for each sender principal
+    Sender,
+}
+
 #[derive(Debug, Clone, Deserialize)]
-struct Rule {
+pub struct Rule {
+    /// Identifier used to label the metrics of the rule
+    id: Option<String>,
     subnet_id: Option<Principal>,
     canister_id: Option<Principal>,
     request_type: Option<RequestType>,
     #[serde(default, with = "serde_regex")]
     methods: Option<Regex>,
+    scope: Option<Scope>,
     limit: Action,
 }
 
@@ -94,97 +124,274 @@ impl PartialEq for Rule {
         self.methods.as_ref().map(|x| x.as_str()) == other.methods.as_ref().map(|x| x.as_str())
             && self.canister_id == other.canister_id
             && self.subnet_id == other.subnet_id
+            && self.request_type == other.request_type
+            && self.scope == other.scope
             && self.limit == other.limit
+            && self.id == other.id
     }
 }
 impl Eq for Rule {}
 
+impl Rule {
+    /// Returns the configured id of the rule or, if there's none, a short hash of its fields.
+    /// Unlike the position of the rule it stays the same when other rules are added or removed.
+    fn metric_id(&self) -> String {
+        if let Some(id) = &self.id {
+            return id.clone();
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(format!(
+            "{:?}|{:?}|{:?}|{:?}|{:?}|{:?}",
+            self.subnet_id,
+            self.canister_id,
+            self.request_type,
+            self.methods.as_ref().map(|x| x.as_str()),
+            self.scope,
+            self.limit,
+        ));
+        hex::encode(&hasher.finalize()[..4])
+    }
+}
+
+/// Parses the YAML representation of a rule list
+pub fn parse_rules(data: &[u8]) -> Result<Vec<Rule>, Error> {
+    serde_yaml::from_slice(data).context("unable to parse rules")
+}
+
+/// Set of rules together with its version, if the source provides one.
+/// Versioned rule sets are only applied if they're newer than the current one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleSet {
+    pub version: Option<u64>,
+    pub rules: Vec<Rule>,
+}
+
+/// Source of the generic rate-limiting rules
+#[async_trait]
+pub trait FetchRules: Send + Sync {
+    async fn fetch_rules(&self) -> Result<RuleSet, Error>;
+}
+
+/// Loads unversioned rules from a local YAML file
+pub struct FileFetcher(pub PathBuf);
+
+#[async_trait]
+impl FetchRules for FileFetcher {
+    async fn fetch_rules(&self) -> Result<RuleSet, Error> {
+        // no file -> no rules
+        if fs::metadata(&self.0).await.is_err() {
+            return Ok(RuleSet::default());
+        }
+
+        let data = fs::read(&self.0)
+            .await
+            .context("unable to read rules file")?;
+
+        Ok(RuleSet {
+            version: None,
+            rules: parse_rules(&data)?,
+        })
+    }
+}
+
+/// Key of a scoped bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ScopeKey {
+    Ip(IpAddr),
+    Sender(Principal),
+    /// The request does not carry the value the rule is scoped by
+    Unknown,
+}
+
+enum BucketLimiter {
+    Block,
+    Shared(Ratelimiter),
+    Scoped(Cache<ScopeKey, Arc<Ratelimiter>>),
+}
+
 struct Bucket {
     rule: Rule,
-    limiter: Option<Ratelimiter>,
+    id: String,
+    limiter: BucketLimiter,
 }
 
-impl PartialEq for Bucket {
-    fn eq(&self, other: &Self) -> bool {
-        self.rule == other.rule
+impl Bucket {
+    fn new(rule: Rule) -> Self {
+        let limiter = match (rule.limit.clone(), rule.scope) {
+            (Action::Block, _) => BucketLimiter::Block,
+            (Action::Limit(limit, duration), None) => {
+                BucketLimiter::Shared(new_ratelimiter(limit, duration))
+            }
+            (Action::Limit(_, duration), Some(_)) => BucketLimiter::Scoped(
+                CacheBuilder::new(MAX_SCOPED_BUCKETS)
+                    // A bucket that was idle for the whole interval is full again,
+                    // so it can be dropped without changing the limiting behavior
+                    .time_to_idle(duration)
+                    .build(),
+            ),
+        };
+
+        Self {
+            id: rule.metric_id(),
+            rule,
+            limiter,
+        }
+    }
+
+    fn acquire_token(&self, ip: Option<IpAddr>, sender: Option<Principal>) -> bool {
+        match &self.limiter {
+            BucketLimiter::Block => false,
+            BucketLimiter::Shared(r) => r.try_wait().is_ok(),
+            BucketLimiter::Scoped(buckets) => {
+                let key = match self.rule.scope {
+                    Some(Scope::Ip) => ip.map(ScopeKey::Ip),
+                    Some(Scope::Sender) => sender.map(ScopeKey::Sender),
+                    None => None,
+                }
+                .unwrap_or(ScopeKey::Unknown);
+
+                let Action::Limit(limit, duration) = self.rule.limit else {
+                    return false;
+                };
+
+                buckets
+                    .get_with(key, || Arc::new(new_ratelimiter(limit, duration)))
+                    .try_wait()
+                    .is_ok()
+            }
+        }
+    }
+}
+
+fn new_ratelimiter(limit: u32, duration: Duration) -> Ratelimiter {
+    Ratelimiter::builder(1, duration.checked_div(limit).unwrap_or(Duration::ZERO))
+        .max_tokens(limit as u64)
+        .initial_available(limit as u64)
+        .build()
+        .unwrap()
+}
+
+/// Currently applied rule set
+#[derive(Default)]
+struct RuleState {
+    version: Option<u64>,
+    buckets: Vec<Arc<Bucket>>,
+}
+
+struct Metrics {
+    decisions: IntCounterVec,
+    version: IntGauge,
+}
+
+impl Metrics {
+    fn new(registry: &Registry) -> Self {
+        Self {
+            decisions: register_int_counter_vec_with_registry!(
+                "generic_limiter_decisions",
+                "Number of requests that matched a generic rate-limiting rule, by rule id and decision",
+                &["rule", "decision"],
+                registry
+            )
+            .unwrap(),
+
+            version: register_int_gauge_with_registry!(
+                "generic_limiter_ruleset_version",
+                "Version of the currently applied generic rate-limiting rule set",
+                registry
+            )
+            .unwrap(),
+        }
     }
 }
-impl Eq for Bucket {}
 
 pub struct Limiter {
-    path: PathBuf,
-    buckets: ArcSwap<Vec<Bucket>>,
+    fetcher: Arc<dyn FetchRules>,
+    state: ArcSwap<RuleState>,
+    metrics: Metrics,
 }
 
 impl Limiter {
-    pub fn new(path: PathBuf) -> Self {
+    pub fn new(fetcher: Arc<dyn FetchRules>, registry: &Registry) -> Self {
         Self {
-            path,
-            buckets: ArcSwap::new(Arc::new(vec![])),
+            fetcher,
+            state: ArcSwap::new(Arc::new(RuleState::default())),
+            metrics: Metrics::new(registry),
         }
     }
 
-    fn process_rules(rules: Vec<Rule>) -> Vec<Bucket> {
+    /// Builds buckets for the given rules, keeping the buckets (and thus the
+    /// limiting state) of the rules that did not change.
+    fn process_rules(rules: Vec<Rule>, old: &[Arc<Bucket>]) -> Vec<Arc<Bucket>> {
         rules
             .into_iter()
             .map(|rule| {
-                let limiter = if let Action::Limit(limit, duration) = rule.limit {
-                    Some(
-                        Ratelimiter::builder(
-                            1,
-                            duration.checked_div(limit).unwrap_or(Duration::ZERO),
-                        )
-                        .max_tokens(limit as u64)
-                        .initial_available(limit as u64)
-                        .build()
-                        .unwrap(),
-                    )
-                } else {
-                    None
-                };
-
-                Bucket { rule, limiter }
+                old.iter()
+                    .find(|b| b.rule == rule)
+                    .cloned()
+                    .unwrap_or_else(|| Arc::new(Bucket::new(rule)))
             })
             .collect()
     }
 
-    async fn load_rules(&self) -> Result<Vec<Rule>, Error> {
-        // no file -> no rules
-        if fs::metadata(&self.path).await.is_err() {
-            return Ok(vec![]);
+    /// Applies the rule set, all buckets are replaced at once.
+    /// Returns true if the rules were updated.
+    fn apply_rules(&self, ruleset: RuleSet) -> bool {
+        let old = self.state.load_full();
+
+        if let (Some(new), Some(current)) = (ruleset.version, old.version) {
+            if new < current {
+                warn!(
+                    "GenericLimiter: ignoring ruleset version {new} older than current version {current}"
+                );
+                return false;
+            }
         }
 
-        let data = fs::read(&self.path)
-            .await
-            .context("unable to read rules file")?;
-        let rules: Vec<Rule> = serde_yaml::from_slice(&data).context("unable to parse rules")?;
-        Ok(rules)
-    }
+        let rules_changed = old.buckets.len() != ruleset.rules.len()
+            || old
+                .buckets
+                .iter()
+                .zip(ruleset.rules.iter())
+                .any(|(b, r)| &b.rule != r);
 
-    fn apply_rules(&self, rules: Vec<Rule>) -> bool {
-        let new = Arc::new(Self::process_rules(rules));
-        let old = self.buckets.load_full();
+        if !rules_changed && old.version == ruleset.version {
+            return false;
+        }
 
-        if old != new {
-            warn!("GenericLimiter: ruleset updated: {} rules", new.len());
+        let new = RuleState {
+            version: ruleset.version,
+            buckets: Self::process_rules(ruleset.rules, &old.buckets),
+        };
 
-            for b in new.as_ref() {
-                warn!(
-                    "GenericLimiter: subnet: {:?}, canister: {:?}, methods: {:?}, action: {:?}",
-                    b.rule.subnet_id, b.rule.canister_id, b.rule.methods, b.rule.limit,
-                );
-            }
+        warn!(
+            "GenericLimiter: ruleset updated: version {:?}, {} rules",
+            new.version,
+            new.buckets.len()
+        );
 
-            self.buckets.store(new);
-            return true;
+        for b in &new.buckets {
+            warn!(
+                "GenericLimiter: subnet: {:?}, canister: {:?}, methods: {:?}, scope: {:?}, action: {:?}",
+                b.rule.subnet_id, b.rule.canister_id, b.rule.methods, b.rule.scope, b.rule.limit,
+            );
         }
 
-        false
+        self.metrics
+            .version
+            .set(new.version.unwrap_or_default() as i64);
+        self.state.store(Arc::new(new));
+
+        true
     }
 
     async fn refresh(&self) -> Result<(), Error> {
-        let rules = self.load_rules().await.context("unable to load rules")?;
-        self.apply_rules(rules);
+        let ruleset = self
+            .fetcher
+            .fetch_rules()
+            .await
+            .context("unable to load rules")?;
+        self.apply_rules(ruleset);
         Ok(())
     }
 
@@ -194,8 +401,10 @@ impl Limiter {
         canister_id: Option<Principal>,
         method: Option<&str>,
         request_type: RequestType,
+        ip: Option<IpAddr>,
+        sender: Option<Principal>,
     ) -> bool {
-        for b in self.buckets.load_full().as_ref() {
+        for b in self.state.load_full().buckets.iter() {
             if let Some(v) = b.rule.subnet_id {
                 if subnet_id != v {
                     continue;
@@ -226,12 +435,13 @@ impl Limiter {
                 }
             }
 
-            if let Some(r) = &b.limiter {
-                return r.try_wait().is_ok();
-            } else {
-                // Always block
-                return false;
-            }
+            let allowed = b.acquire_token(ip, sender);
+            self.metrics
+                .decisions
+                .with_label_values(&[&b.id, if allowed { "pass" } else { "drop" }])
+                .inc();
+
+            return allowed;
         }
 
         // No rules / no match -> pass
@@ -254,11 +464,23 @@ pub async fn middleware(
     request: Request<Body>,
     next: Next<Body>,
 ) -> Result<impl IntoResponse, ErrorCause> {
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<TcpConnectInfo>>()
+        .map(|x| (x.0).0)
+        .or(request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|x| x.0))
+        .map(|x| x.ip());
+
     if !state.acquire_token(
         subnet.id,
         canister_id.map(|x| (x.0).get().into()),
         ctx.method_name.as_deref(),
         ctx.request_type,
+        ip,
+        ctx.sender,
     ) {
         return Err(ErrorCause::RateLimited(RateLimitCause::Generic));
     }
@@ -305,20 +527,25 @@ mod test {
             rules,
             vec![
                 Rule {
+                    id: None,
                     subnet_id: None,
                     canister_id: Some(Principal::from_text("aaaaa-aa").unwrap()),
                     request_type: None,
                     methods: Some(Regex::new("^.*$").unwrap()),
+                    scope: None,
                     limit: Action::Limit(100, Duration::from_secs(1)),
                 },
                 Rule {
+                    id: None,
                     subnet_id: None,
                     canister_id: Some(Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap()),
                     request_type: None,
                     methods: Some(Regex::new("^(foo|bar)$").unwrap()),
+                    scope: None,
                     limit: Action::Limit(60, Duration::from_secs(60)),
                 },
                 Rule {
+                    id: None,
                     subnet_id: Some(
                         Principal::from_text(
                             "3hhby-wmtmw-umt4t-7ieyg-bbiig-xiylg-sblrt-voxgt-bqckd-a75bf-rqe"
@@ -328,33 +555,40 @@ mod test {
                     canister_id: Some(Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap()),
                     request_type: None,
                     methods: None,
+                    scope: None,
                     limit: Action::Limit(90, Duration::from_secs(60)),
                 },
                 Rule {
+                    id: None,
                     subnet_id: None,
                     canister_id: Some(Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap()),
                     request_type: None,
                     methods: Some(Regex::new("^(foo|bar)$").unwrap()),
+                    scope: None,
                     limit: Action::Block,
                 },
                 Rule {
+                    id: None,
                     subnet_id: None,
                     canister_id: Some(Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap()),
                     request_type: Some(RequestType::Query),
                     methods: Some(Regex::new("^(foo|bar)$").unwrap()),
+                    scope: None,
                     limit: Action::Block,
                 },
                 Rule {
+                    id: None,
                     subnet_id: None,
                     canister_id: Some(Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap()),
                     request_type: Some(RequestType::Call),
                     methods: None,
+                    scope: None,
                     limit: Action::Block,
                 },
             ],
         );
 
-        Limiter::process_rules(rules);
+        Limiter::process_rules(rules, &[]);
 
         // Bad canister
         let rules = indoc! {"
@@ -458,8 +692,11 @@ mod test {
         "};
         let rules: Vec<Rule> = serde_yaml::from_str(rules).unwrap();
 
-        let limiter = Limiter::new("/tmp/foo".into());
-        limiter.apply_rules(rules);
+        let limiter = Limiter::new(Arc::new(FileFetcher("/tmp/foo".into())), &Registry::new());
+        limiter.apply_rules(RuleSet {
+            version: None,
+            rules,
+        });
 
         let id1 = Principal::from_text("aaaaa-aa").unwrap();
         let id2 = Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap();
@@ -474,54 +711,323 @@ mod test {
         // Check id1 blocking with any method
         // 10 pass
         for _ in 0..10 {
-            assert!(limiter.acquire_token(subnet_id, Some(id1), Some("foo"), RequestType::Query));
+            assert!(limiter.acquire_token(
+                subnet_id,
+                Some(id1),
+                Some("foo"),
+                RequestType::Query,
+                None,
+                None
+            ));
         }
         // then all blocked
         for _ in 0..100 {
-            assert!(!limiter.acquire_token(subnet_id, Some(id1), Some("bar"), RequestType::Query));
+            assert!(!limiter.acquire_token(
+                subnet_id,
+                Some(id1),
+                Some("bar"),
+                RequestType::Query,
+                None,
+                None
+            ));
         }
 
         // Check id2 blocking with two methods
         // 20 pass
         // Another subnet_id which shouldn't have any difference
         for _ in 0..20 {
-            assert!(limiter.acquire_token(subnet_id2, Some(id2), Some("foo"), RequestType::Query));
+            assert!(limiter.acquire_token(
+                subnet_id2,
+                Some(id2),
+                Some("foo"),
+                RequestType::Query,
+                None,
+                None
+            ));
         }
         // Then all blocked
         for _ in 0..100 {
-            assert!(!limiter.acquire_token(subnet_id2, Some(id2), Some("bar"), RequestType::Query));
+            assert!(!limiter.acquire_token(
+                subnet_id2,
+                Some(id2),
+                Some("bar"),
+                RequestType::Query,
+                None,
+                None
+            ));
         }
         // Other methods should not block ever
         for _ in 0..100 {
-            assert!(limiter.acquire_token(subnet_id2, Some(id2), Some("lol"), RequestType::Query));
+            assert!(limiter.acquire_token(
+                subnet_id2,
+                Some(id2),
+                Some("lol"),
+                RequestType::Query,
+                None,
+                None
+            ));
         }
         for _ in 0..100 {
-            assert!(limiter.acquire_token(subnet_id2, Some(id2), Some("rofl"), RequestType::Query));
+            assert!(limiter.acquire_token(
+                subnet_id2,
+                Some(id2),
+                Some("rofl"),
+                RequestType::Query,
+                None,
+                None
+            ));
         }
 
         // This method should be blocked always
         for _ in 0..100 {
-            assert!(!limiter.acquire_token(subnet_id, Some(id2), Some("baz"), RequestType::Query));
+            assert!(!limiter.acquire_token(
+                subnet_id,
+                Some(id2),
+                Some("baz"),
+                RequestType::Query,
+                None,
+                None
+            ));
         }
 
         // Check id3 blocking with any method and request type call
         // 10 pass
         for _ in 0..10 {
-            assert!(limiter.acquire_token(subnet_id, Some(id3), Some("foo"), RequestType::Call));
+            assert!(limiter.acquire_token(
+                subnet_id,
+                Some(id3),
+                Some("foo"),
+                RequestType::Call,
+                None,
+                None
+            ));
         }
         // then all blocked
         for _ in 0..100 {
-            assert!(!limiter.acquire_token(subnet_id, Some(id3), Some("bar"), RequestType::Call));
+            assert!(!limiter.acquire_token(
+                subnet_id,
+                Some(id3),
+                Some("bar"),
+                RequestType::Call,
+                None,
+                None
+            ));
         }
 
         // Then check id3 blocking with any method and request type query
         // 20 pass
         for _ in 0..20 {
-            assert!(limiter.acquire_token(subnet_id, Some(id3), Some("baz"), RequestType::Query));
+            assert!(limiter.acquire_token(
+                subnet_id,
+                Some(id3),
+                Some("baz"),
+                RequestType::Query,
+                None,
+                None
+            ));
         }
         // then all blocked
         for _ in 0..100 {
-            assert!(!limiter.acquire_token(subnet_id, Some(id3), Some("zob"), RequestType::Query));
+            assert!(!limiter.acquire_token(
+                subnet_id,
+                Some(id3),
+                Some("zob"),
+                RequestType::Query,
+                None,
+                None
+            ));
+        }
+    }
+
+    #[test]
+    fn test_ratelimit_scoped() {
+        let rules = indoc! {"
+        - id: per-ip
+          canister_id: aaaaa-aa
+          scope: ip
+          limit: 10/1h
+
+        - canister_id: 5s2ji-faaaa-aaaaa-qaaaq-cai
+          scope: sender
+          limit: 5/1h
+        "};
+        let rules = parse_rules(rules.as_bytes()).unwrap();
+        assert_eq!(rules[0].scope, Some(Scope::Ip));
+        assert_eq!(rules[1].scope, Some(Scope::Sender));
+        let sender_rule_id = rules[1].metric_id();
+
+        let limiter = Limiter::new(Arc::new(FileFetcher("/tmp/foo".into())), &Registry::new());
+        limiter.apply_rules(RuleSet {
+            version: None,
+            rules,
+        });
+
+        let id1 = Principal::from_text("aaaaa-aa").unwrap();
+        let id2 = Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap();
+        let subnet_id =
+            Principal::from_text("3hhby-wmtmw-umt4t-7ieyg-bbiig-xiylg-sblrt-voxgt-bqckd-a75bf-rqe")
+                .unwrap();
+        let ip1 = Some(IpAddr::from([10, 0, 0, 1]));
+        let ip2 = Some(IpAddr::from([10, 0, 0, 2]));
+        let sender1 = Some(Principal::from_text("2vxsx-fae").unwrap());
+        let sender2 = Some(Principal::from_slice(&[1, 2, 3]));
+
+        // Each IP has its own limit
+        for ip in [ip1, ip2] {
+            for _ in 0..10 {
+                assert!(limiter.acquire_token(
+                    subnet_id,
+                    Some(id1),
+                    Some("foo"),
+                    RequestType::Query,
+                    ip,
+                    None
+                ));
+            }
+            assert!(!limiter.acquire_token(
+                subnet_id,
+                Some(id1),
+                Some("foo"),
+                RequestType::Query,
+                ip,
+                None
+            ));
+        }
+
+        // Each sender has its own limit
+        for sender in [sender1, sender2] {
+            for _ in 0..5 {
+                assert!(limiter.acquire_token(
+                    subnet_id,
+                    Some(id2),
+                    Some("foo"),
+                    RequestType::Call,
+                    ip1,
+                    sender
+                ));
+            }
+            assert!(!limiter.acquire_token(
+                subnet_id,
+                Some(id2),
+                Some("foo"),
+                RequestType::Call,
+                ip1,
+                sender
+            ));
         }
+
+        assert_eq!(
+            limiter
+                .metrics
+                .decisions
+                .with_label_values(&["per-ip", "pass"])
+                .get(),
+            20
+        );
+        assert_eq!(
+            limiter
+                .metrics
+                .decisions
+                .with_label_values(&[&sender_rule_id, "drop"])
+                .get(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_rule_metric_ids() {
+        let rules = parse_rules(
+            indoc! {"
+            - id: block-foo
+              canister_id: aaaaa-aa
+              methods: ^foo$
+              limit: block
+
+            - canister_id: aaaaa-aa
+              limit: 1/1h
+
+            - canister_id: aaaaa-aa
+              limit: 2/1h
+            "}
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(rules[0].metric_id(), "block-foo");
+        assert_eq!(rules[1].metric_id().len(), 8);
+        assert_ne!(rules[1].metric_id(), rules[2].metric_id());
+
+        // Ids don't depend on the position of the rule
+        let reordered = parse_rules(
+            indoc! {"
+            - canister_id: aaaaa-aa
+              limit: 2/1h
+
+            - canister_id: aaaaa-aa
+              limit: 1/1h
+            "}
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(reordered[0].metric_id(), rules[2].metric_id());
+        assert_eq!(reordered[1].metric_id(), rules[1].metric_id());
+    }
+
+    #[test]
+    fn test_ruleset_versions() {
+        let rules_v1 = parse_rules(
+            indoc! {"
+            - canister_id: aaaaa-aa
+              limit: 1/1h
+            "}
+            .as_bytes(),
+        )
+        .unwrap();
+        let rules_v2 = parse_rules(
+            indoc! {"
+            - canister_id: 5s2ji-faaaa-aaaaa-qaaaq-cai
+              limit: block
+
+            - canister_id: aaaaa-aa
+              limit: 1/1h
+            "}
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let limiter = Limiter::new(Arc::new(FileFetcher("/tmp/foo".into())), &Registry::new());
+        let id1 = Principal::from_text("aaaaa-aa").unwrap();
+        let id2 = Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap();
+        let subnet_id = Principal::from_text("aaaaa-aa").unwrap();
+
+        assert!(limiter.apply_rules(RuleSet {
+            version: Some(1),
+            rules: rules_v1.clone(),
+        }));
+        assert!(limiter.acquire_token(subnet_id, Some(id1), None, RequestType::Query, None, None));
+        assert!(limiter.acquire_token(subnet_id, Some(id2), None, RequestType::Query, None, None));
+
+        // Same version and rules -> no update
+        assert!(!limiter.apply_rules(RuleSet {
+            version: Some(1),
+            rules: rules_v1.clone(),
+        }));
+
+        assert!(limiter.apply_rules(RuleSet {
+            version: Some(2),
+            rules: rules_v2,
+        }));
+        assert_eq!(limiter.metrics.version.get(), 2);
+        assert!(!limiter.acquire_token(subnet_id, Some(id2), None, RequestType::Query, None, None));
+        // The state of the unchanged rule is kept, so its single token is still used up
+        assert!(!limiter.acquire_token(subnet_id, Some(id1), None, RequestType::Query, None, None));
+
+        // Older versions are ignored
+        assert!(!limiter.apply_rules(RuleSet {
+            version: Some(1),
+            rules: rules_v1,
+        }));
+        assert_eq!(limiter.metrics.version.get(), 2);
     }
 }
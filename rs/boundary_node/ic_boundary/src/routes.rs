@@ -156,6 +156,7 @@ pub enum ErrorCause {
     ReplicaTLSErrorOther(String),
     ReplicaTLSErrorCert(String),
     ReplicaErrorOther(String),
+    ReplicaInvalidSignature(String),
     RateLimited(RateLimitCause),
     Other(String),
 }
@@ -180,6 +181,7 @@ impl ErrorCause {
             Self::ReplicaTLSErrorOther(_) => StatusCode::SERVICE_UNAVAILABLE,
             Self::ReplicaTLSErrorCert(_) => StatusCode::SERVICE_UNAVAILABLE,
             Self::ReplicaErrorOther(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::ReplicaInvalidSignature(_) => StatusCode::BAD_GATEWAY,
             Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
         }
     }
@@ -198,6 +200,7 @@ impl ErrorCause {
             Self::ReplicaTLSErrorOther(x) => Some(x.clone()),
             Self::ReplicaTLSErrorCert(x) => Some(x.clone()),
             Self::ReplicaErrorOther(x) => Some(x.clone()),
+            Self::ReplicaInvalidSignature(x) => Some(x.clone()),
             _ => None,
         }
     }
@@ -227,6 +230,7 @@ impl fmt::Display for ErrorCause {
             Self::ReplicaTLSErrorOther(_) => write!(f, "replica_tls_error"),
             Self::ReplicaTLSErrorCert(_) => write!(f, "replica_tls_error_cert"),
             Self::ReplicaErrorOther(_) => write!(f, "replica_error_other"),
+            Self::ReplicaInvalidSignature(_) => write!(f, "replica_invalid_signature"),
             Self::RateLimited(x) => write!(f, "rate_limited_{x}"),
         }
     }
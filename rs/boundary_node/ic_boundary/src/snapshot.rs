@@ -19,7 +19,7 @@ use ic_registry_client_helpers::{
     subnet::{SubnetListRegistry, SubnetRegistry},
 };
 use ic_registry_subnet_type::SubnetType;
-use ic_types::{NodeId, PrincipalId, RegistryVersion, SubnetId};
+use ic_types::{crypto::KeyPurpose, NodeId, PrincipalId, RegistryVersion, SubnetId};
 use tokio::sync::watch;
 use tracing::{debug, warn};
 use url::{ParseError, Url};
@@ -43,6 +43,9 @@ pub struct Node {
     pub addr: IpAddr,
     pub port: u16,
     pub tls_certificate: Vec<u8>,
+    // Raw Ed25519 node signing key, used e.g. to verify query response signatures.
+    // None if the keys are not fetched or the key of this node is not available.
+    pub signing_public_key: Option<Vec<u8>>,
     pub avg_latency_secs: f64,
 }
 
@@ -149,6 +152,7 @@ pub struct Snapshotter {
     last_version_change: Instant,
     min_version_age: Duration,
     persister: Option<SnapshotPersister>,
+    fetch_node_signing_keys: bool,
 }
 
 pub struct SnapshotInfo {
@@ -185,6 +189,7 @@ impl Snapshotter {
             last_version_change: Instant::now(),
             min_version_age,
             persister: None,
+            fetch_node_signing_keys: false,
         }
     }
 
@@ -192,6 +197,36 @@ impl Snapshotter {
         self.persister = Some(persister);
     }
 
+    // Makes the snapshots include the node signing keys, needed only to verify query signatures
+    pub fn set_fetch_node_signing_keys(&mut self, fetch: bool) {
+        self.fetch_node_signing_keys = fetch;
+    }
+
+    // Returns the node signing key if fetching them is enabled.
+    // A node whose key is not available is kept in the snapshot without a key,
+    // so that the responses it serves fail verification instead of the whole snapshot failing.
+    fn get_node_signing_key(&self, node_id: NodeId, version: RegistryVersion) -> Option<Vec<u8>> {
+        if !self.fetch_node_signing_keys {
+            return None;
+        }
+
+        match self.registry_client.get_crypto_key_for_node(
+            node_id,
+            KeyPurpose::NodeSigning,
+            version,
+        ) {
+            Ok(Some(key)) => Some(key.key_value),
+            Ok(None) => {
+                warn!("Node signing key of {node_id} is not available");
+                None
+            }
+            Err(e) => {
+                warn!("Unable to get node signing key of {node_id}: {e}");
+                None
+            }
+        }
+    }
+
     // Creates a snapshot of the registry for given version
     fn get_snapshot(&self, version: RegistryVersion) -> Result<RegistrySnapshot, Error> {
         // Get routing table with canister ranges
@@ -299,6 +334,7 @@ impl Snapshotter {
                                 .context("unable to parse IP address")?,
                             port: http_endpoint.port as u16, // Port is u16 anyway
                             tls_certificate: cert.certificate_der,
+                            signing_public_key: self.get_node_signing_key(node_id, version),
                         };
                         let node = Arc::new(node);
 
@@ -482,6 +518,7 @@ pub fn generate_stub_subnet(nodes: Vec<SocketAddr>) -> Subnet {
                 addr: x.ip(),
                 port: x.port(),
                 tls_certificate: vec![],
+                signing_public_key: None,
             })
         })
         .collect::<Vec<_>>();
@@ -1,6 +1,52 @@
 use super::*;
 
-use crate::test_utils::{create_fake_registry_client, valid_tls_certificate_and_validation_time};
+use ic_interfaces_registry::{RegistryClientVersionedResult, RegistryVersionedRecord};
+use ic_registry_client_fake::FakeRegistryClient;
+use ic_registry_keys::make_crypto_node_key;
+use ic_types::{registry::RegistryClientError, Time};
+
+use crate::test_utils::{
+    create_fake_registry_client, node_signing_key, valid_tls_certificate_and_validation_time,
+};
+
+// Registry client that hides the node signing key of the given node
+struct RegistryWithoutSigningKey {
+    inner: FakeRegistryClient,
+    node_id: NodeId,
+}
+
+impl RegistryClient for RegistryWithoutSigningKey {
+    fn get_versioned_value(
+        &self,
+        key: &str,
+        version: RegistryVersion,
+    ) -> RegistryClientVersionedResult<Vec<u8>> {
+        if key == make_crypto_node_key(self.node_id, KeyPurpose::NodeSigning) {
+            return Ok(RegistryVersionedRecord {
+                key: key.to_string(),
+                version,
+                value: None,
+            });
+        }
+        self.inner.get_versioned_value(key, version)
+    }
+
+    fn get_key_family(
+        &self,
+        key_prefix: &str,
+        version: RegistryVersion,
+    ) -> Result<Vec<String>, RegistryClientError> {
+        self.inner.get_key_family(key_prefix, version)
+    }
+
+    fn get_latest_version(&self) -> RegistryVersion {
+        self.inner.get_latest_version()
+    }
+
+    fn get_version_timestamp(&self, registry_version: RegistryVersion) -> Option<Time> {
+        self.inner.get_version_timestamp(registry_version)
+    }
+}
 
 #[tokio::test]
 async fn test_routing_table() -> Result<(), Error> {
@@ -12,6 +58,7 @@ async fn test_routing_table() -> Result<(), Error> {
     let (channel_send, _) = watch::channel(None);
     let mut snapshotter =
         Snapshotter::new(Arc::clone(&snapshot), channel_send, reg, Duration::ZERO);
+    snapshotter.set_fetch_node_signing_keys(true);
     snapshotter.snapshot()?;
     let snapshot = snapshot.load_full().unwrap();
 
@@ -39,6 +86,68 @@ async fn test_routing_table() -> Result<(), Error> {
                 .0
                 .certificate_der,
         );
+
+        assert_eq!(
+            sn.nodes[0].signing_public_key,
+            Some(
+                node_signing_key(nodes[i].0)
+                    .public_key()
+                    .serialize_raw()
+                    .to_vec()
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_node_signing_keys_not_fetched_by_default() -> Result<(), Error> {
+    let snapshot = Arc::new(ArcSwapOption::empty());
+
+    let (reg, _, _) = create_fake_registry_client(4, 1, None);
+    let reg = Arc::new(reg);
+
+    let (channel_send, _) = watch::channel(None);
+    let mut snapshotter =
+        Snapshotter::new(Arc::clone(&snapshot), channel_send, reg, Duration::ZERO);
+    snapshotter.snapshot()?;
+    let snapshot = snapshot.load_full().unwrap();
+
+    assert!(snapshot
+        .nodes
+        .values()
+        .all(|node| node.signing_public_key.is_none()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_missing_node_signing_key_does_not_fail_snapshot() -> Result<(), Error> {
+    let snapshot = Arc::new(ArcSwapOption::empty());
+
+    let (reg, nodes, _) = create_fake_registry_client(2, 2, None);
+    let node_without_key = nodes[0].0;
+    let reg = Arc::new(RegistryWithoutSigningKey {
+        inner: reg,
+        node_id: node_without_key,
+    });
+
+    let (channel_send, _) = watch::channel(None);
+    let mut snapshotter =
+        Snapshotter::new(Arc::clone(&snapshot), channel_send, reg, Duration::ZERO);
+    snapshotter.set_fetch_node_signing_keys(true);
+    snapshotter.snapshot()?;
+    let snapshot = snapshot.load_full().unwrap();
+
+    // The node is kept, but without a key, so its query responses fail verification
+    assert_eq!(snapshot.nodes.len(), 4);
+    for (node_id, _) in nodes {
+        let node = &snapshot.nodes[&node_id.to_string()];
+        assert_eq!(
+            node.signing_public_key.is_some(),
+            node_id != node_without_key
+        );
     }
 
     Ok(())
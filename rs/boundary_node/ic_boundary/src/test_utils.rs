@@ -13,22 +13,24 @@ use ic_certification_test_utils::CertificateBuilder;
 use ic_certification_test_utils::CertificateData::*;
 use ic_crypto_tree_hash::Digest;
 use ic_protobuf::registry::{
-    crypto::v1::{PublicKey as PublicKeyProto, X509PublicKeyCert},
+    crypto::v1::{AlgorithmId, PublicKey as PublicKeyProto, X509PublicKeyCert},
     node::v1::{ConnectionEndpoint, NodeRecord},
     routing_table::v1::RoutingTable as PbRoutingTable,
     subnet::v1::{SubnetListRecord, SubnetRecord},
 };
 use ic_registry_client_fake::FakeRegistryClient;
 use ic_registry_keys::{
-    make_crypto_threshold_signing_pubkey_key, make_crypto_tls_cert_key, make_node_record_key,
-    make_routing_table_record_key, make_subnet_list_record_key, make_subnet_record_key,
-    ROOT_SUBNET_ID_KEY,
+    make_crypto_node_key, make_crypto_threshold_signing_pubkey_key, make_crypto_tls_cert_key,
+    make_node_record_key, make_routing_table_record_key, make_subnet_list_record_key,
+    make_subnet_record_key, ROOT_SUBNET_ID_KEY,
 };
 use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
 use ic_registry_routing_table::{CanisterIdRange, RoutingTable as RoutingTableIC};
 use ic_registry_subnet_type::SubnetType;
 use ic_types::{
-    crypto::threshold_sig::ThresholdSigPublicKey, replica_version::ReplicaVersion, time::Time,
+    crypto::{threshold_sig::ThresholdSigPublicKey, KeyPurpose},
+    replica_version::ReplicaVersion,
+    time::Time,
     CanisterId, RegistryVersion, SubnetId,
 };
 use prometheus::Registry;
@@ -70,6 +72,11 @@ fn new_random_certified_data() -> Digest {
     Digest(random_certified_data)
 }
 
+// Deterministic node signing key derived from the node id
+pub fn node_signing_key(node_id: NodeId) -> ic_crypto_ed25519::PrivateKey {
+    ic_crypto_ed25519::PrivateKey::generate_from_seed(node_id.get().as_slice())
+}
+
 pub fn valid_tls_certificate_and_validation_time() -> (X509PublicKeyCert, Time) {
     /// converted to seconds since `UNIX_EPOCH` by hand
     const NOT_BEFORE: u64 = 1667585534;
@@ -125,6 +132,7 @@ pub fn test_subnet_record() -> SubnetRecord {
         ssh_backup_access: vec![],
         ecdsa_config: None,
         chain_key_config: None,
+        idle_block_config: None,
     }
 }
 
@@ -205,6 +213,24 @@ pub fn create_fake_registry_client(
                     Some(valid_tls_certificate_and_validation_time().0),
                 )
                 .expect("failed to add TLS certificate to registry");
+
+            // Add node signing key
+            data_provider
+                .add(
+                    &make_crypto_node_key(node_id, KeyPurpose::NodeSigning),
+                    reg_ver,
+                    Some(PublicKeyProto {
+                        algorithm: AlgorithmId::Ed25519 as i32,
+                        key_value: node_signing_key(node_id)
+                            .public_key()
+                            .serialize_raw()
+                            .to_vec(),
+                        version: 0,
+                        proof_data: None,
+                        timestamp: None,
+                    }),
+                )
+                .expect("failed to add node signing key to registry");
         }
 
         // Add subnet
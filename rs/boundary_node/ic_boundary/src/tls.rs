@@ -503,6 +503,7 @@ fn prepare_static_provisioner(loader: Loader) -> Result<Box<dyn Provision>, Erro
 pub async fn prepare_tls(
     cli: &Cli,
     registry: &Registry,
+    http3_endpoint: Option<quinn::Endpoint>,
 ) -> Result<(impl Run, CustomAcceptor, Arc<TokenOwner>), Error> {
     // TLS Certificates Loader (Ingress)
     let tls_loader = Loader {
@@ -550,7 +551,8 @@ pub async fn prepare_tls(
     // TLS (Ingress) Configurator
     let tls_acceptor = Arc::new(ArcSwapOption::new(None));
 
-    let tls_configurator = TlsConfigurator::new(tls_acceptor.clone(), tls_provisioner);
+    let tls_configurator =
+        TlsConfigurator::new(tls_acceptor.clone(), http3_endpoint, tls_provisioner);
     let tls_configurator = WithMetrics(
         tls_configurator,
         MetricParams::new(registry, "configure_tls"),
@@ -27,6 +27,11 @@ impl sandbox_service::SandboxService for DummySandboxService {
         rpc::Call::new_resolved(Ok(sbxsvc::TerminateReply {}))
     }
 
+    fn ready(&self, _req: sbxsvc::ReadyRequest) -> rpc::Call<sbxsvc::ReadyReply> {
+        println!("Sandbox: Received 'ready' request");
+        rpc::Call::new_resolved(Ok(sbxsvc::ReadyReply {}))
+    }
+
     fn open_wasm(&self, _req: sbxsvc::OpenWasmRequest) -> rpc::Call<sbxsvc::OpenWasmReply> {
         println!("Sandbox: Received 'open_wasm' request");
         rpc::Call::new_resolved(Ok(sbxsvc::OpenWasmReply(Ok((
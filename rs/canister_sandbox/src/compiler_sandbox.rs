@@ -14,10 +14,11 @@ use ic_wasm_types::WasmEngineError;
 fn compile_and_serialize(
     embedder: &WasmtimeEmbedder,
     wasm_src: Vec<u8>,
+    canister_profiling: bool,
 ) -> HypervisorResult<(CompilationResult, SerializedModule)> {
     let wasm =
         wasm_utils::decoding::decode_wasm(embedder.config().wasm_max_size, Arc::new(wasm_src))?;
-    let (_cache, res) = wasm_utils::compile(embedder, &wasm);
+    let (_cache, res) = wasm_utils::compile_with_profiling(embedder, &wasm, canister_profiling);
     res
 }
 
@@ -29,6 +30,8 @@ fn unexpected(desc: &str) -> HypervisorError {
 struct PlainWasm {
     #[serde(with = "serde_bytes")]
     pub wasm_src: Vec<u8>,
+    /// Whether to inject the canister profiling hooks.
+    pub canister_profiling: bool,
 }
 
 impl crate::fdenum::EnumerateInnerFileDescriptors for PlainWasm {
@@ -136,8 +139,12 @@ impl WasmCompilerProxy {
     pub fn compile(
         &self,
         wasm_src: Vec<u8>,
+        canister_profiling: bool,
     ) -> HypervisorResult<(CompilationResult, SerializedModule)> {
-        let req = PlainWasm { wasm_src };
+        let req = PlainWasm {
+            wasm_src,
+            canister_profiling,
+        };
         match self.rpc.call(req, Ok).sync() {
             Ok(compiled_wasm) => compiled_wasm.result,
             Err(_rpc_err) => {
@@ -197,7 +204,7 @@ pub fn compiler_sandbox_main() {
         move |message: WireMessage<PlainWasm, CompiledWasm>| match message.msg {
             Message::Request(w) => {
                 trace!(log, "Compile request received. Cookie: {}", message.cookie);
-                let result = compile_and_serialize(&embedder, w.wasm_src);
+                let result = compile_and_serialize(&embedder, w.wasm_src, w.canister_profiling);
                 let cw = CompiledWasm { result };
                 let call = rpc::Call::new_resolved(Ok(cw));
                 let call = rpc::Call::new_wrap(call, |x| x);
@@ -15,8 +15,8 @@ use crate::{
         self,
         ctllaunchersvc::SandboxExitedRequest,
         launchersvc::{
-            LaunchCompilerReply, LaunchCompilerRequest, LaunchSandboxReply, LaunchSandboxRequest,
-            TerminateReply, TerminateRequest,
+            BindSandboxReply, BindSandboxRequest, LaunchCompilerReply, LaunchCompilerRequest,
+            LaunchSandboxReply, LaunchSandboxRequest, TerminateReply, TerminateRequest,
         },
     },
     rpc,
@@ -135,7 +135,9 @@ impl LauncherServer {
                         let process_info = info_map.remove(&pid);
                         eprintln!(
                             "Sandbox pid {} for canister {:?} exited unexpectedly with status {:?}",
-                            pid, process_info, status
+                            pid,
+                            process_info.as_ref().and_then(|x| x.canister_id),
+                            status
                         );
 
                         let should_panic = process_info
@@ -143,10 +145,13 @@ impl LauncherServer {
                             .map(|x| x.panic_on_failure)
                             .unwrap_or(true);
                         if should_panic {
-                            // If we have a canister id, tell the replica process to print its history.
-                            if let Some(canister_id) = process_info.and_then(|x| x.canister_id) {
+                            // If this is a known sandbox, tell the replica process to print
+                            // its history.
+                            if process_info.is_some() {
                                 controller
-                                    .sandbox_exited(SandboxExitedRequest { canister_id })
+                                    .sandbox_exited(SandboxExitedRequest {
+                                        pid: pid.as_raw() as u32,
+                                    })
                                     .sync()
                                     .unwrap();
                             }
@@ -192,7 +197,7 @@ impl LauncherService for LauncherServer {
                 info_map.insert(
                     Pid::from_raw(pid as i32),
                     ProcessInfo {
-                        canister_id: Some(canister_id),
+                        canister_id,
                         panic_on_failure: true,
                     },
                 );
@@ -206,6 +211,22 @@ impl LauncherService for LauncherServer {
         }
     }
 
+    fn bind_sandbox(
+        &self,
+        BindSandboxRequest { pid, canister_id }: BindSandboxRequest,
+    ) -> rpc::Call<BindSandboxReply> {
+        let mut info_map = self.pid_to_process_info.lock().unwrap();
+        let success = match info_map.get_mut(&Pid::from_raw(pid as i32)) {
+            Some(process_info) => {
+                process_info.canister_id = Some(canister_id);
+                true
+            }
+            // The process has already exited.
+            None => false,
+        };
+        rpc::Call::new_resolved(Ok(BindSandboxReply { success }))
+    }
+
     fn launch_compiler(
         &self,
         LaunchCompilerRequest {
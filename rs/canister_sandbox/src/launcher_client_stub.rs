@@ -23,6 +23,16 @@ impl LauncherService for LauncherClientStub {
         Call::new(cell)
     }
 
+    fn bind_sandbox(&self, req: BindSandboxRequest) -> Call<BindSandboxReply> {
+        let cell = self
+            .channel
+            .call(Request::BindSandbox(req), |rep| match rep {
+                Reply::BindSandbox(rep) => Ok(rep),
+                _ => Err(Error::ServerError),
+            });
+        Call::new(cell)
+    }
+
     fn launch_compiler(&self, req: LaunchCompilerRequest) -> Call<LaunchCompilerReply> {
         let cell = self
             .channel
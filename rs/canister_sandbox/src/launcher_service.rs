@@ -5,6 +5,9 @@ pub trait LauncherService: Send + Sync {
     /// Launch a new sandboxed process.
     fn launch_sandbox(&self, req: LaunchSandboxRequest) -> Call<LaunchSandboxReply>;
 
+    /// Bind a sandboxed process launched without a canister to a canister.
+    fn bind_sandbox(&self, req: BindSandboxRequest) -> Call<BindSandboxReply>;
+
     /// Launch a new compiler process.
     fn launch_compiler(&self, req: LaunchCompilerRequest) -> Call<LaunchCompilerReply>;
 
@@ -18,6 +21,7 @@ impl<Svc: LauncherService + Send + Sync> DemuxServer<Request, Reply> for Svc {
             Request::LaunchSandbox(req) => {
                 Call::new_wrap(self.launch_sandbox(req), Reply::LaunchSandbox)
             }
+            Request::BindSandbox(req) => Call::new_wrap(self.bind_sandbox(req), Reply::BindSandbox),
             Request::LaunchCompiler(req) => {
                 Call::new_wrap(self.launch_compiler(req), Reply::LaunchCompiler)
             }
@@ -1,13 +1,14 @@
 //! A service provided by the controller to the launcher.
 
-use ic_types::CanisterId;
 use serde::{Deserialize, Serialize};
 
 use crate::fdenum::EnumerateInnerFileDescriptors;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SandboxExitedRequest {
-    pub canister_id: CanisterId,
+    /// The process id of the sandbox. It identifies the canister because
+    /// processes from the pool are bound to a canister after they are spawned.
+    pub pid: u32,
 }
 
 impl EnumerateInnerFileDescriptors for SandboxExitedRequest {
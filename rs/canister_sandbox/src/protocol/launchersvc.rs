@@ -13,7 +13,9 @@ use crate::fdenum::EnumerateInnerFileDescriptors;
 pub struct LaunchSandboxRequest {
     pub sandbox_exec_path: String,
     pub argv: Vec<String>,
-    pub canister_id: CanisterId,
+    /// The canister the process is spawned for, or `None` if it is spawned
+    /// ahead of time into the pool of idle processes.
+    pub canister_id: Option<CanisterId>,
     pub socket: RawFd,
 }
 
@@ -28,6 +30,19 @@ pub struct LaunchSandboxReply {
     pub pid: u32,
 }
 
+/// Binds a sandbox process taken from the pool of idle processes to the
+/// canister it now serves.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BindSandboxRequest {
+    pub pid: u32,
+    pub canister_id: CanisterId,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BindSandboxReply {
+    pub success: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LaunchCompilerRequest {
     pub exec_path: String,
@@ -57,6 +72,7 @@ pub struct TerminateReply {}
 #[derive(Serialize, Deserialize, Clone)]
 pub enum Request {
     LaunchSandbox(LaunchSandboxRequest),
+    BindSandbox(BindSandboxRequest),
     LaunchCompiler(LaunchCompilerRequest),
     Terminate(TerminateRequest),
 }
@@ -66,7 +82,7 @@ impl EnumerateInnerFileDescriptors for Request {
         match self {
             Request::LaunchSandbox(req) => req.enumerate_fds(fds),
             Request::LaunchCompiler(req) => req.enumerate_fds(fds),
-            Request::Terminate(_req) => {}
+            Request::BindSandbox(_) | Request::Terminate(_) => {}
         }
     }
 }
@@ -75,6 +91,7 @@ impl EnumerateInnerFileDescriptors for Request {
 #[derive(Serialize, Deserialize, Clone)]
 pub enum Reply {
     LaunchSandbox(LaunchSandboxReply),
+    BindSandbox(BindSandboxReply),
     LaunchCompiler(LaunchCompilerReply),
     Terminate(TerminateReply),
 }
@@ -37,6 +37,16 @@ pub struct TerminateRequest {}
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct TerminateReply {}
 
+/// Ask the sandbox process to confirm that it has finished its start-up
+/// initialization and is ready to serve requests. Used to keep only fully
+/// initialized processes in the pool of idle processes.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReadyRequest {}
+
+/// Ack signal to the controller that the sandbox process is ready.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReadyReply {}
+
 /// Register wasm for a canister that can be executed in the sandbox.
 /// Multiple wasms can be registered to the same sandbox (in order to
 /// support multiple code states e.g. during upgrades). A single wasm
@@ -319,6 +329,7 @@ pub struct CreateExecutionStateSerializedReply(
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum Request {
     Terminate(TerminateRequest),
+    Ready(ReadyRequest),
     OpenWasm(OpenWasmRequest),
     OpenWasmSerialized(OpenWasmSerializedRequest),
     CloseWasm(CloseWasmRequest),
@@ -338,6 +349,7 @@ impl EnumerateInnerFileDescriptors for Request {
             Request::CreateExecutionState(request) => request.enumerate_fds(fds),
             Request::CreateExecutionStateSerialized(request) => request.enumerate_fds(fds),
             Request::Terminate(_)
+            | Request::Ready(_)
             | Request::OpenWasm(_)
             | Request::OpenWasmSerialized(_)
             | Request::CloseWasm(_)
@@ -354,6 +366,7 @@ impl EnumerateInnerFileDescriptors for Request {
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum Reply {
     Terminate(TerminateReply),
+    Ready(ReadyReply),
     OpenWasm(OpenWasmReply),
     OpenWasmSerialized(OpenWasmSerializedReply),
     CloseWasm(CloseWasmReply),
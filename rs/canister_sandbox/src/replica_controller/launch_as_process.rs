@@ -80,7 +80,7 @@ pub fn spawn_launcher_process(
 pub fn spawn_canister_sandbox_process(
     exec_path: &str,
     argv: &[String],
-    canister_id: CanisterId,
+    canister_id: Option<CanisterId>,
     controller_service: Arc<super::controller_service_impl::ControllerServiceImpl>,
     launcher: &dyn LauncherService,
 ) -> std::io::Result<(Arc<dyn SandboxService>, u32, std::thread::JoinHandle<()>)> {
@@ -147,10 +147,29 @@ pub fn create_sandbox_process(
     let (sandbox_handle, pid, _recv_thread_handle) = spawn_canister_sandbox_process(
         &argv[0],
         &argv[1..],
-        canister_id,
+        Some(canister_id),
         controller_service,
         launcher_service,
     )
     .expect("Failed to start sandbox process");
     Ok((sandbox_handle, pid))
 }
+
+/// Spawns a sandbox process that is not bound to any canister yet, to be kept
+/// in the pool of idle processes.
+pub fn create_pooled_sandbox_process(
+    controller_service: Arc<super::controller_service_impl::ControllerServiceImpl>,
+    launcher_service: &dyn LauncherService,
+    argv: Vec<String>,
+) -> std::io::Result<(Arc<dyn SandboxService>, u32)> {
+    assert!(!argv.is_empty());
+
+    let (sandbox_handle, pid, _recv_thread_handle) = spawn_canister_sandbox_process(
+        &argv[0],
+        &argv[1..],
+        None,
+        controller_service,
+        launcher_service,
+    )?;
+    Ok((sandbox_handle, pid))
+}
@@ -0,0 +1,262 @@
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+/// The upper bound on the number of idle processes kept in the pool,
+/// regardless of the eviction limits.
+const MAX_SANDBOX_PROCESS_POOL_SIZE: usize = 16;
+
+/// The percentage of `max_sandbox_count` reserved for idle processes.
+const SANDBOX_PROCESS_POOL_PERCENT: usize = 1;
+
+/// The delay before retrying after the first failed spawn. The delay doubles
+/// with every consecutive failure up to `MAX_SPAWN_RETRY_BACKOFF`.
+const MIN_SPAWN_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_SPAWN_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Returns the number of idle processes to keep in the pool given the
+/// maximum number of sandbox processes. The idle processes count towards
+/// that maximum, so the caller must lower the eviction threshold for active
+/// processes by the returned value.
+pub(crate) fn sandbox_process_pool_size(max_sandbox_count: usize) -> usize {
+    (max_sandbox_count * SANDBOX_PROCESS_POOL_PERCENT / 100).min(MAX_SANDBOX_PROCESS_POOL_SIZE)
+}
+
+struct PoolState<P> {
+    idle: VecDeque<P>,
+    stopped: bool,
+}
+
+/// A pool of pre-spawned idle sandbox processes that are not yet bound to any
+/// canister. Taking a process out of the pool avoids the round trip through
+/// the launcher on the critical path of a canister cold start.
+///
+/// The pool is refilled by a dedicated thread running [`Self::refill`].
+pub(crate) struct SandboxProcessPool<P> {
+    target_size: usize,
+    state: Mutex<PoolState<P>>,
+    refill_needed: Condvar,
+}
+
+impl<P> SandboxProcessPool<P> {
+    pub(crate) fn new(target_size: usize) -> Self {
+        Self {
+            target_size,
+            state: Mutex::new(PoolState {
+                idle: VecDeque::with_capacity(target_size),
+                stopped: false,
+            }),
+            refill_needed: Condvar::new(),
+        }
+    }
+
+    /// Takes the oldest idle process out of the pool, if there is any, and
+    /// wakes up the refill thread.
+    pub(crate) fn take(&self) -> Option<P> {
+        let process = self.state.lock().unwrap().idle.pop_front();
+        if process.is_some() {
+            self.refill_needed.notify_one();
+        }
+        process
+    }
+
+    /// Returns the number of idle processes in the pool.
+    pub(crate) fn len(&self) -> usize {
+        self.state.lock().unwrap().idle.len()
+    }
+
+    /// Drops all idle processes and makes [`Self::refill`] return. Processes
+    /// that are spawned afterwards are dropped immediately.
+    pub(crate) fn stop(&self) {
+        let idle = {
+            let mut state = self.state.lock().unwrap();
+            state.stopped = true;
+            std::mem::take(&mut state.idle)
+        };
+        self.refill_needed.notify_all();
+        drop(idle);
+    }
+
+    /// Keeps the pool filled up to its target size using `spawn` until
+    /// [`Self::stop`] is called. Spawning happens without holding the lock, so
+    /// `take()` never waits for a process to start.
+    ///
+    /// If `spawn` fails, `on_error` is called with the error and the delay
+    /// before the next attempt, which grows exponentially with consecutive
+    /// failures. [`Self::stop`] interrupts the delay.
+    pub(crate) fn refill<E>(
+        &self,
+        spawn: impl Fn() -> Result<P, E>,
+        on_error: impl Fn(E, Duration),
+    ) {
+        let mut backoff = MIN_SPAWN_RETRY_BACKOFF;
+        loop {
+            {
+                let state = self
+                    .refill_needed
+                    .wait_while(self.state.lock().unwrap(), |state| {
+                        !state.stopped && state.idle.len() >= self.target_size
+                    })
+                    .unwrap();
+                if state.stopped {
+                    return;
+                }
+            }
+            match spawn() {
+                Ok(process) => {
+                    backoff = MIN_SPAWN_RETRY_BACKOFF;
+                    let mut state = self.state.lock().unwrap();
+                    if state.stopped {
+                        return;
+                    }
+                    state.idle.push_back(process);
+                }
+                Err(err) => {
+                    on_error(err, backoff);
+                    let (state, _) = self
+                        .refill_needed
+                        .wait_timeout_while(self.state.lock().unwrap(), backoff, |state| {
+                            !state.stopped
+                        })
+                        .unwrap();
+                    if state.stopped {
+                        return;
+                    }
+                    backoff = (backoff * 2).min(MAX_SPAWN_RETRY_BACKOFF);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    const WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    fn wait_until(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + WAIT_TIMEOUT;
+        while !condition() {
+            assert!(
+                Instant::now() < deadline,
+                "Condition not met within {:?}",
+                WAIT_TIMEOUT
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn pool_size_is_bounded() {
+        assert_eq!(sandbox_process_pool_size(0), 0);
+        assert_eq!(sandbox_process_pool_size(99), 0);
+        assert_eq!(sandbox_process_pool_size(1_000), 10);
+        assert_eq!(
+            sandbox_process_pool_size(100_000),
+            MAX_SANDBOX_PROCESS_POOL_SIZE
+        );
+    }
+
+    #[test]
+    fn take_from_empty_pool_returns_none() {
+        let pool = SandboxProcessPool::<usize>::new(0);
+        assert_eq!(pool.take(), None);
+    }
+
+    #[test]
+    fn pool_is_refilled_after_take() {
+        let pool = Arc::new(SandboxProcessPool::new(3));
+        let spawned = Arc::new(AtomicUsize::new(0));
+
+        let refill_thread = {
+            let pool = Arc::clone(&pool);
+            let spawned = Arc::clone(&spawned);
+            std::thread::spawn(move || {
+                pool.refill(
+                    || Ok::<_, ()>(spawned.fetch_add(1, Ordering::SeqCst)),
+                    |_, _| panic!("spawn cannot fail"),
+                )
+            })
+        };
+
+        wait_until(|| pool.len() == 3);
+        assert_eq!(spawned.load(Ordering::SeqCst), 3);
+
+        // Processes are handed out in the order they were spawned.
+        assert_eq!(pool.take(), Some(0));
+        assert_eq!(pool.take(), Some(1));
+        wait_until(|| pool.len() == 3);
+        assert_eq!(spawned.load(Ordering::SeqCst), 5);
+
+        pool.stop();
+        refill_thread.join().unwrap();
+        assert_eq!(pool.len(), 0);
+        assert_eq!(pool.take(), None);
+    }
+
+    #[test]
+    fn refill_retries_with_backoff_after_spawn_error() {
+        let pool = Arc::new(SandboxProcessPool::new(1));
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let backoffs = Arc::new(Mutex::new(vec![]));
+
+        let refill_thread = {
+            let pool = Arc::clone(&pool);
+            let attempts = Arc::clone(&attempts);
+            let backoffs = Arc::clone(&backoffs);
+            std::thread::spawn(move || {
+                pool.refill(
+                    || match attempts.fetch_add(1, Ordering::SeqCst) {
+                        0 | 1 => Err("launcher is busy"),
+                        attempt => Ok(attempt),
+                    },
+                    |err, backoff| {
+                        assert_eq!(err, "launcher is busy");
+                        backoffs.lock().unwrap().push(backoff);
+                    },
+                )
+            })
+        };
+
+        wait_until(|| pool.len() == 1);
+        assert_eq!(pool.take(), Some(2));
+        assert_eq!(
+            *backoffs.lock().unwrap(),
+            vec![MIN_SPAWN_RETRY_BACKOFF, MIN_SPAWN_RETRY_BACKOFF * 2]
+        );
+
+        pool.stop();
+        refill_thread.join().unwrap();
+    }
+
+    #[test]
+    fn stop_interrupts_spawn_retry_backoff() {
+        let pool = Arc::new(SandboxProcessPool::<usize>::new(1));
+        let failures = Arc::new(AtomicUsize::new(0));
+
+        let refill_thread = {
+            let pool = Arc::clone(&pool);
+            let failures = Arc::clone(&failures);
+            std::thread::spawn(move || {
+                pool.refill(
+                    || Err("launcher is gone"),
+                    |_, _| {
+                        failures.fetch_add(1, Ordering::SeqCst);
+                    },
+                )
+            })
+        };
+
+        // After the fourth failure the refill thread waits for 800ms.
+        wait_until(|| failures.load(Ordering::SeqCst) == 4);
+        let start = Instant::now();
+        pool.stop();
+        refill_thread.join().unwrap();
+        assert!(start.elapsed() < MIN_SPAWN_RETRY_BACKOFF * 8);
+        assert_eq!(pool.len(), 0);
+    }
+}
@@ -16,9 +16,7 @@ use ic_embedders::{
     wasm_utils::WasmImportsDetails, CompilationCache, CompilationResult, WasmExecutionInput,
 };
 use ic_interfaces::execution_environment::{HypervisorError, HypervisorResult};
-#[cfg(target_os = "linux")]
-use ic_logger::warn;
-use ic_logger::{error, info, ReplicaLogger};
+use ic_logger::{error, info, warn, ReplicaLogger};
 use ic_metrics::buckets::decimal_buckets_with_zero;
 use ic_metrics::MetricsRegistry;
 use ic_replicated_state::canister_state::execution_state::{
@@ -29,9 +27,7 @@ use ic_types::ingress::WasmResult;
 use ic_types::methods::{FuncRef, WasmMethod};
 use ic_types::{CanisterId, NumInstructions};
 use ic_wasm_types::CanisterModule;
-#[cfg(target_os = "linux")]
-use prometheus::IntGauge;
-use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec};
+use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge};
 use std::collections::{HashMap, VecDeque};
 #[cfg(target_os = "linux")]
 use std::convert::TryInto;
@@ -45,13 +41,16 @@ use std::time::{Duration, Instant};
 
 use super::active_execution_state_registry::{ActiveExecutionStateRegistry, CompletionResult};
 use super::controller_service_impl::ControllerServiceImpl;
-use super::launch_as_process::{create_sandbox_process, spawn_launcher_process};
+use super::launch_as_process::{
+    create_pooled_sandbox_process, create_sandbox_process, spawn_launcher_process,
+};
 use super::process_exe_and_args::{
     create_compiler_sandbox_argv, create_launcher_argv, create_sandbox_argv,
 };
 #[cfg(target_os = "linux")]
 use super::process_os_metrics;
 use super::sandbox_process_eviction::{self, EvictionCandidate};
+use super::sandbox_process_pool::{sandbox_process_pool_size, SandboxProcessPool};
 use ic_replicated_state::page_map::PageAllocatorFileDescriptor;
 
 const SANDBOX_PROCESS_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
@@ -61,6 +60,8 @@ const SANDBOX_PROCESS_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
 const SANDBOX_PROCESS_EVICTION_PERCENT: usize = 20;
 
 const SANDBOXED_EXECUTION_INVALID_MEMORY_SIZE: &str = "sandboxed_execution_invalid_memory_size";
+const SANDBOXED_EXECUTION_PROCESS_POOL_SPAWN_FAILED: &str =
+    "sandboxed_execution_process_pool_spawn_failed";
 
 // Metric labels for the different outcomes of a wasm cache lookup. Stored in
 // the metric
@@ -72,6 +73,12 @@ const COMPILATION_CACHE_HIT: &str = "compilation_cache_hit";
 const COMPILATION_CACHE_HIT_COMPILATION_ERROR: &str = "compilation_cache_hit_compilation_error";
 const CACHE_MISS: &str = "cache_miss";
 
+// Metric labels for the outcomes of taking a process from the sandbox process
+// pool. Stored in the metric
+// [`SandboxedExecutionMetrics::sandboxed_execution_process_pool_lookups`].
+const PROCESS_POOL_HIT: &str = "hit";
+const PROCESS_POOL_MISS: &str = "miss";
+
 struct SandboxedExecutionMetrics {
     sandboxed_execution_replica_execute_duration: HistogramVec,
     sandboxed_execution_replica_execute_prepare_duration: HistogramVec,
@@ -80,6 +87,9 @@ struct SandboxedExecutionMetrics {
     sandboxed_execution_sandbox_execute_duration: HistogramVec,
     sandboxed_execution_sandbox_execute_run_duration: HistogramVec,
     sandboxed_execution_spawn_process: Histogram,
+    sandboxed_execution_process_pool_spawn: Histogram,
+    sandboxed_execution_process_pool_lookups: IntCounterVec,
+    sandboxed_execution_process_pool_size: IntGauge,
     #[cfg(target_os = "linux")]
     sandboxed_execution_subprocess_anon_rss_total: IntGauge,
     #[cfg(target_os = "linux")]
@@ -93,6 +103,7 @@ struct SandboxedExecutionMetrics {
     sandboxed_execution_subprocess_active_last_used: Histogram,
     sandboxed_execution_subprocess_evicted_last_used: Histogram,
     sandboxed_execution_critical_error_invalid_memory_size: IntCounter,
+    sandboxed_execution_critical_error_process_pool_spawn_failed: IntCounter,
     sandboxed_execution_replica_create_exe_state_duration: Histogram,
     sandboxed_execution_replica_create_exe_state_wait_compile_duration: Histogram,
     sandboxed_execution_replica_create_exe_state_wait_deserialize_duration: Histogram,
@@ -159,6 +170,20 @@ impl SandboxedExecutionMetrics {
                 "The time to spawn a sandbox process",
                 decimal_buckets_with_zero(-4, 1),
             ),
+            sandboxed_execution_process_pool_spawn: metrics_registry.histogram(
+                "sandboxed_execution_process_pool_spawn_duration_seconds",
+                "The time to spawn an idle sandbox process for the process pool",
+                decimal_buckets_with_zero(-4, 1),
+            ),
+            sandboxed_execution_process_pool_lookups: metrics_registry.int_counter_vec(
+                "sandboxed_execution_process_pool_lookups_total",
+                "Results from taking a process from the sandbox process pool for a canister",
+                &["result"],
+            ),
+            sandboxed_execution_process_pool_size: metrics_registry.int_gauge(
+                "sandboxed_execution_process_pool_size",
+                "The number of idle sandbox processes in the process pool",
+            ),
             #[cfg(target_os = "linux")]
             sandboxed_execution_subprocess_anon_rss_total: metrics_registry.int_gauge(
                 "sandboxed_execution_subprocess_anon_rss_total_kib",
@@ -199,6 +224,8 @@ impl SandboxedExecutionMetrics {
             ),
             sandboxed_execution_critical_error_invalid_memory_size: metrics_registry.error_counter(
                 SANDBOXED_EXECUTION_INVALID_MEMORY_SIZE),
+            sandboxed_execution_critical_error_process_pool_spawn_failed: metrics_registry.error_counter(
+                SANDBOXED_EXECUTION_PROCESS_POOL_SPAWN_FAILED),
             sandboxed_execution_replica_create_exe_state_duration: metrics_registry.histogram(
                 "sandboxed_execution_replica_create_exe_state_duration_seconds",
                 "The total create execution state duration in the replica controller",
@@ -634,7 +661,14 @@ pub struct SandboxedExecutionController {
     /// - An entry is removed from the registry only if it is in the `evicted`
     ///   state and the strong reference count reaches zero.
     backends: Arc<Mutex<HashMap<CanisterId, Backend>>>,
+    /// Idle sandbox processes that are not bound to any canister yet. They
+    /// are taken instead of spawning a new process when a canister without a
+    /// sandbox process starts an execution.
+    sandbox_process_pool: Arc<SandboxProcessPool<SandboxProcess>>,
     min_sandbox_count: usize,
+    /// The maximum number of active sandbox processes. It excludes the idle
+    /// processes in the pool, so that their total stays within
+    /// `EmbeddersConfig::max_sandbox_count`.
     max_sandbox_count: usize,
     max_sandbox_idle_time: Duration,
     trace_execution: FlagStatus,
@@ -643,7 +677,7 @@ pub struct SandboxedExecutionController {
     /// the same for all canisters.
     sandbox_exec_argv: Vec<String>,
     metrics: Arc<SandboxedExecutionMetrics>,
-    launcher_service: Box<dyn LauncherService>,
+    launcher_service: Arc<dyn LauncherService>,
     fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
     stop_monitoring_thread: std::sync::mpsc::Sender<bool>,
 }
@@ -654,6 +688,9 @@ impl Drop for SandboxedExecutionController {
         // can be done.
         let _ = self.stop_monitoring_thread.send(true);
 
+        // Terminate the idle sandbox processes and stop refilling the pool.
+        self.sandbox_process_pool.stop();
+
         // Evict all the sandbox processes.
         let mut guard = self.backends.lock().unwrap();
         evict_sandbox_processes(&mut guard, 0, 0, Duration::default());
@@ -713,6 +750,7 @@ impl WasmExecutor for SandboxedExecutionController {
             &sandbox_process,
             &*self.launcher_service,
             &execution_state.wasm_binary,
+            sandbox_safe_system_state.canister_profiling(),
             compilation_cache,
             &self.metrics,
             &self.logger,
@@ -822,6 +860,7 @@ impl WasmExecutor for SandboxedExecutionController {
         canister_module: CanisterModule,
         canister_root: PathBuf,
         canister_id: CanisterId,
+        canister_profiling: bool,
         compilation_cache: Arc<CompilationCache>,
     ) -> HypervisorResult<(ExecutionState, NumInstructions, Option<CompilationResult>)> {
         let _create_exe_state_timer = self
@@ -841,8 +880,14 @@ impl WasmExecutor for SandboxedExecutionController {
 
         let stable_memory_page_map = PageMap::new(Arc::clone(&self.fd_factory));
 
+        // Modules with profiling hooks are not shared with other canisters.
+        let cached = if canister_profiling {
+            None
+        } else {
+            compilation_cache.get(&wasm_binary.binary)
+        };
         let (memory_modifications, exported_globals, serialized_module, compilation_result) =
-            match compilation_cache.get(&wasm_binary.binary) {
+            match cached {
                 None => {
                     self.metrics.inc_cache_lookup(CACHE_MISS);
                     let _compilation_timer = self
@@ -864,20 +909,27 @@ impl WasmExecutor for SandboxedExecutionController {
                         &compiler_command[0],
                         &compiler_command[1..],
                     )?;
-                    let reply = compiler.compile(wasm_binary.binary.as_slice().to_vec());
+                    let reply = compiler
+                        .compile(wasm_binary.binary.as_slice().to_vec(), canister_profiling);
                     // Let the compiler proxy know that it can start shutting down, since
                     // we are not planning to send any addtional requests to it.
                     compiler.initiate_stop();
 
                     match reply {
                         Err(err) => {
-                            compilation_cache.insert(&wasm_binary.binary, Err(err.clone()));
+                            if !canister_profiling {
+                                compilation_cache.insert(&wasm_binary.binary, Err(err.clone()));
+                            }
                             return Err(err);
                         }
                         Ok((compilation_result, serialized_module)) => {
                             let serialized_module = Arc::new(serialized_module);
-                            compilation_cache
-                                .insert(&wasm_binary.binary, Ok(Arc::clone(&serialized_module)));
+                            if !canister_profiling {
+                                compilation_cache.insert(
+                                    &wasm_binary.binary,
+                                    Ok(Arc::clone(&serialized_module)),
+                                );
+                            }
 
                             sandbox_process.history.record(format!(
                                 "CreateExecutionStateSerialized(wasm_id={}, next_wasm_memory_id={})",
@@ -1053,15 +1105,19 @@ impl SandboxedExecutionController {
         let launcher_exec_argv =
             create_launcher_argv(embedder_config).expect("No sandbox_launcher binary found");
         let min_sandbox_count = embedder_config.min_sandbox_count;
-        let max_sandbox_count = embedder_config.max_sandbox_count;
+        let sandbox_process_pool_size =
+            sandbox_process_pool_size(embedder_config.max_sandbox_count);
+        let max_sandbox_count = embedder_config.max_sandbox_count - sandbox_process_pool_size;
         let max_sandbox_idle_time = embedder_config.max_sandbox_idle_time;
         let trace_execution = embedder_config.trace_execution;
         let sandbox_exec_argv =
             create_sandbox_argv(embedder_config).expect("No canister_sandbox binary found");
         let backends = Arc::new(Mutex::new(HashMap::new()));
         let metrics = Arc::new(SandboxedExecutionMetrics::new(metrics_registry));
+        let sandbox_process_pool = Arc::new(SandboxProcessPool::new(sandbox_process_pool_size));
 
         let backends_copy = Arc::clone(&backends);
+        let sandbox_process_pool_copy = Arc::clone(&sandbox_process_pool);
         let metrics_copy = Arc::clone(&metrics);
         let logger_copy = logger.clone();
         let (tx, rx) = std::sync::mpsc::channel();
@@ -1070,6 +1126,7 @@ impl SandboxedExecutionController {
             SandboxedExecutionController::monitor_and_evict_sandbox_processes(
                 logger_copy,
                 backends_copy,
+                sandbox_process_pool_copy,
                 metrics_copy,
                 min_sandbox_count,
                 max_sandbox_count,
@@ -1098,8 +1155,58 @@ impl SandboxedExecutionController {
             panic_due_to_exit(output, pid);
         });
 
+        let launcher_service: Arc<dyn LauncherService> = Arc::from(launcher_service);
+
+        if sandbox_process_pool_size > 0 {
+            let sandbox_process_pool = Arc::clone(&sandbox_process_pool);
+            let launcher_service = Arc::clone(&launcher_service);
+            let sandbox_exec_argv = sandbox_exec_argv.clone();
+            let metrics = Arc::clone(&metrics);
+            let logger = logger.clone();
+            thread::spawn(move || {
+                sandbox_process_pool.refill(
+                    || {
+                        let _timer = metrics.sandboxed_execution_process_pool_spawn.start_timer();
+                        let reg = Arc::new(ActiveExecutionStateRegistry::new());
+                        let controller_service =
+                            ControllerServiceImpl::new(Arc::clone(&reg), logger.clone());
+                        let (sandbox_service, pid) = create_pooled_sandbox_process(
+                            controller_service,
+                            &*launcher_service,
+                            sandbox_exec_argv.clone(),
+                        )?;
+                        // Only count the process as idle once it has finished
+                        // its start-up, so that binding it is instantaneous.
+                        sandbox_service
+                            .ready(protocol::sbxsvc::ReadyRequest {})
+                            .sync()?;
+                        Ok::<_, std::io::Error>(SandboxProcess {
+                            execution_states: reg,
+                            sandbox_service,
+                            pid,
+                            history: SandboxProcessRequestHistory::new(),
+                        })
+                    },
+                    |err, backoff| {
+                        error!(
+                            logger,
+                            "{}: Failed to spawn a sandbox process for the process pool, \
+                             retrying in {:?}: {}",
+                            SANDBOXED_EXECUTION_PROCESS_POOL_SPAWN_FAILED,
+                            backoff,
+                            err
+                        );
+                        metrics
+                            .sandboxed_execution_critical_error_process_pool_spawn_failed
+                            .inc();
+                    },
+                );
+            });
+        }
+
         Ok(Self {
             backends,
+            sandbox_process_pool,
             min_sandbox_count,
             max_sandbox_count,
             max_sandbox_idle_time,
@@ -1120,6 +1227,7 @@ impl SandboxedExecutionController {
         // `logger` isn't used on MacOS.
         #[allow(unused_variables)] logger: ReplicaLogger,
         backends: Arc<Mutex<HashMap<CanisterId, Backend>>>,
+        sandbox_process_pool: Arc<SandboxProcessPool<SandboxProcess>>,
         metrics: Arc<SandboxedExecutionMetrics>,
         min_sandbox_count: usize,
         max_sandbox_count: usize,
@@ -1128,6 +1236,9 @@ impl SandboxedExecutionController {
     ) {
         loop {
             let sandbox_processes = get_sandbox_process_stats(&backends);
+            metrics
+                .sandboxed_execution_process_pool_size
+                .set(sandbox_process_pool.len() as i64);
 
             #[cfg(target_os = "linux")]
             {
@@ -1235,6 +1346,35 @@ impl SandboxedExecutionController {
         }
     }
 
+    /// Tells the launcher that the given process from the pool now serves the
+    /// given canister, so that the launcher reports the canister if the
+    /// process exits. Returns `false` if the process is gone.
+    fn bind_sandbox_process(
+        &self,
+        sandbox_process: &SandboxProcess,
+        canister_id: CanisterId,
+    ) -> bool {
+        let reply = self
+            .launcher_service
+            .bind_sandbox(protocol::launchersvc::BindSandboxRequest {
+                pid: sandbox_process.pid,
+                canister_id,
+            })
+            .sync();
+        match reply {
+            Ok(reply) if reply.success => true,
+            Ok(_) | Err(_) => {
+                warn!(
+                    self.logger,
+                    "Failed to bind pooled sandbox process {} to canister {}",
+                    sandbox_process.pid,
+                    canister_id
+                );
+                false
+            }
+        }
+    }
+
     fn get_sandbox_process(&self, canister_id: CanisterId) -> Arc<SandboxProcess> {
         let mut guard = self.backends.lock().unwrap();
 
@@ -1280,24 +1420,45 @@ impl SandboxedExecutionController {
             );
         }
 
-        // No sandbox process found for this canister. Start a new one and register it.
-        let reg = Arc::new(ActiveExecutionStateRegistry::new());
-        let controller_service = ControllerServiceImpl::new(Arc::clone(&reg), self.logger.clone());
-
-        let (sandbox_service, pid) = create_sandbox_process(
-            controller_service,
-            &*self.launcher_service,
-            canister_id,
-            self.sandbox_exec_argv.clone(),
-        )
-        .unwrap();
-
-        let sandbox_process = Arc::new(SandboxProcess {
-            execution_states: reg,
-            sandbox_service,
-            pid,
-            history: SandboxProcessRequestHistory::new(),
-        });
+        // No sandbox process found for this canister. Bind an idle one from the
+        // pool or start a new one and register it.
+        let pooled_sandbox_process = self
+            .sandbox_process_pool
+            .take()
+            .filter(|sandbox_process| self.bind_sandbox_process(sandbox_process, canister_id));
+        let sandbox_process = match pooled_sandbox_process {
+            Some(sandbox_process) => {
+                self.metrics
+                    .sandboxed_execution_process_pool_lookups
+                    .with_label_values(&[PROCESS_POOL_HIT])
+                    .inc();
+                Arc::new(sandbox_process)
+            }
+            None => {
+                self.metrics
+                    .sandboxed_execution_process_pool_lookups
+                    .with_label_values(&[PROCESS_POOL_MISS])
+                    .inc();
+                let reg = Arc::new(ActiveExecutionStateRegistry::new());
+                let controller_service =
+                    ControllerServiceImpl::new(Arc::clone(&reg), self.logger.clone());
+
+                let (sandbox_service, pid) = create_sandbox_process(
+                    controller_service,
+                    &*self.launcher_service,
+                    canister_id,
+                    self.sandbox_exec_argv.clone(),
+                )
+                .unwrap();
+
+                Arc::new(SandboxProcess {
+                    execution_states: reg,
+                    sandbox_service,
+                    pid,
+                    history: SandboxProcessRequestHistory::new(),
+                })
+            }
+        };
 
         let now = std::time::Instant::now();
         let backend = Backend::Active {
@@ -1482,11 +1643,12 @@ fn cache_errored_wasm(embedder_cache: &mut Option<EmbedderCache>, err: Hyperviso
 }
 
 // Get compiled wasm object in sandbox. Ask cache first, upload + compile if
-// needed.
+// needed. Modules with profiling hooks bypass the shared compilation cache.
 fn open_wasm(
     sandbox_process: &Arc<SandboxProcess>,
     launcher: &dyn LauncherService,
     wasm_binary: &WasmBinary,
+    canister_profiling: bool,
     compilation_cache: Arc<CompilationCache>,
     metrics: &SandboxedExecutionMetrics,
     log: &ReplicaLogger,
@@ -1513,7 +1675,12 @@ fn open_wasm(
     }
 
     let wasm_id = WasmId::new();
-    match compilation_cache.get(&wasm_binary.binary) {
+    let cached = if canister_profiling {
+        None
+    } else {
+        compilation_cache.get(&wasm_binary.binary)
+    };
+    match cached {
         None => {
             metrics.inc_cache_lookup(CACHE_MISS);
             let compiler_command = create_compiler_sandbox_argv().ok_or_else(|| {
@@ -1528,7 +1695,8 @@ fn open_wasm(
                 &compiler_command[0],
                 &compiler_command[1..],
             )?;
-            let result = compiler.compile(wasm_binary.binary.as_slice().to_vec());
+            let result =
+                compiler.compile(wasm_binary.binary.as_slice().to_vec(), canister_profiling);
             // Let the compiler proxy know that it can start shutting down, since
             // we are not planning to send any addtional requests to it.
             compiler.initiate_stop();
@@ -1547,11 +1715,16 @@ fn open_wasm(
                         .on_completion(|_| ());
                     cache_opened_wasm(&mut embedder_cache, sandbox_process, wasm_id);
                     observe_metrics(metrics, &serialized_module.imports_details);
-                    compilation_cache.insert(&wasm_binary.binary, Ok(Arc::new(serialized_module)));
+                    if !canister_profiling {
+                        compilation_cache
+                            .insert(&wasm_binary.binary, Ok(Arc::new(serialized_module)));
+                    }
                     Ok((wasm_id, Some(compilation_result)))
                 }
                 Err(err) => {
-                    compilation_cache.insert(&wasm_binary.binary, Err(err.clone()));
+                    if !canister_profiling {
+                        compilation_cache.insert(&wasm_binary.binary, Err(err.clone()));
+                    }
                     cache_errored_wasm(&mut embedder_cache, err.clone());
                     Err(err)
                 }
@@ -1772,22 +1945,22 @@ impl ControllerLauncherService for ExitWatcher {
         req: protocol::ctllaunchersvc::SandboxExitedRequest,
     ) -> crate::rpc::Call<protocol::ctllaunchersvc::SandboxExitedReply> {
         let guard = self.backends.lock().unwrap();
-        let sandbox_process = match guard.get(&req.canister_id).unwrap_or_else(|| {
-            panic!(
-                "Sandbox exited for unrecognized canister id {}",
-                req.canister_id,
-            )
-        }) {
-            Backend::Active {
-                sandbox_process, ..
-            } => sandbox_process,
-            Backend::Evicted { .. } | Backend::Empty => {
-                return rpc::Call::new_resolved(Ok(protocol::ctllaunchersvc::SandboxExitedReply));
-            }
-        };
-        sandbox_process
-            .history
-            .replay(&self.logger, req.canister_id, sandbox_process.pid);
+        // Only active sandbox processes have a history worth replaying. Idle
+        // processes from the pool are not bound to any canister yet.
+        let active_sandbox_process =
+            guard
+                .iter()
+                .find_map(|(canister_id, backend)| match backend {
+                    Backend::Active {
+                        sandbox_process, ..
+                    } if sandbox_process.pid == req.pid => Some((canister_id, sandbox_process)),
+                    Backend::Active { .. } | Backend::Evicted { .. } | Backend::Empty => None,
+                });
+        if let Some((canister_id, sandbox_process)) = active_sandbox_process {
+            sandbox_process
+                .history
+                .replay(&self.logger, *canister_id, sandbox_process.pid);
+        }
         rpc::Call::new_resolved(Ok(protocol::ctllaunchersvc::SandboxExitedReply))
     }
 }
@@ -1860,6 +2033,7 @@ mod tests {
                 canister_module,
                 PathBuf::new(),
                 canister_id,
+                false,
                 Arc::new(CompilationCache::new(MAX_COMPILATION_CACHE_SIZE)),
             )
             .unwrap();
@@ -1890,4 +2064,63 @@ mod tests {
             canister_id, sandbox_pid
         )));
     }
+
+    #[test]
+    fn sandbox_process_is_taken_from_pool_if_available() {
+        use ic_replicated_state::page_map::TestPageAllocatorFileDescriptorImpl;
+        // Keeps one idle process in the pool.
+        let embedder_config = EmbeddersConfig {
+            max_sandbox_count: 100,
+            ..EmbeddersConfig::default()
+        };
+        let controller = SandboxedExecutionController::new(
+            no_op_logger(),
+            &MetricsRegistry::new(),
+            &embedder_config,
+            Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+        )
+        .unwrap();
+        let lookups = |result| {
+            controller
+                .metrics
+                .sandboxed_execution_process_pool_lookups
+                .with_label_values(&[result])
+                .get()
+        };
+
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while controller.sandbox_process_pool.len() < 1 {
+            assert!(Instant::now() < deadline, "the pool was not filled in time");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // The pooled process is bound to the canister and is ready to serve it.
+        let sandbox_process = controller.get_sandbox_process(canister_test_id(0));
+        assert_eq!(
+            (lookups(PROCESS_POOL_HIT), lookups(PROCESS_POOL_MISS)),
+            (1, 0)
+        );
+        sandbox_process
+            .sandbox_service
+            .ready(protocol::sbxsvc::ReadyRequest {})
+            .sync()
+            .unwrap();
+
+        // A canister with a sandbox process does not take one from the pool.
+        let same_process = controller.get_sandbox_process(canister_test_id(0));
+        assert_eq!(same_process.pid, sandbox_process.pid);
+        assert_eq!(
+            (lookups(PROCESS_POOL_HIT), lookups(PROCESS_POOL_MISS)),
+            (1, 0)
+        );
+
+        // Without idle processes, a new process is spawned for the canister.
+        controller.sandbox_process_pool.stop();
+        let other_process = controller.get_sandbox_process(canister_test_id(1));
+        assert_ne!(other_process.pid, sandbox_process.pid);
+        assert_eq!(
+            (lookups(PROCESS_POOL_HIT), lookups(PROCESS_POOL_MISS)),
+            (1, 1)
+        );
+    }
 }
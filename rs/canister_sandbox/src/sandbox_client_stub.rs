@@ -24,6 +24,14 @@ impl SandboxService for SandboxClientStub {
         Call::new(cell)
     }
 
+    fn ready(&self, req: ReadyRequest) -> Call<ReadyReply> {
+        let cell = self.channel.call(Request::Ready(req), |rep| match rep {
+            Reply::Ready(rep) => Ok(rep),
+            _ => Err(Error::ServerError),
+        });
+        Call::new(cell)
+    }
+
     fn open_wasm(&self, req: OpenWasmRequest) -> Call<OpenWasmReply> {
         let cell = self.channel.call(Request::OpenWasm(req), |rep| match rep {
             Reply::OpenWasm(rep) => Ok(rep),
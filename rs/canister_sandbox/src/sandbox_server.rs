@@ -32,6 +32,12 @@ impl SandboxService for SandboxServer {
         std::process::exit(0);
     }
 
+    fn ready(&self, _req: ReadyRequest) -> rpc::Call<ReadyReply> {
+        // The sandbox manager is created before the server starts handling
+        // requests, so replying is enough to signal that it is initialized.
+        rpc::Call::new_resolved(Ok(ReadyReply {}))
+    }
+
     fn open_wasm(&self, req: OpenWasmRequest) -> rpc::Call<OpenWasmReply> {
         let result = self
             .manager
@@ -220,11 +226,15 @@ mod tests {
             BTreeMap::new(),
             0,
             ic00_aliases,
+            BTreeMap::new(),
+            vec![],
             SMALL_APP_SUBNET_MAX_SIZE,
             SchedulerConfig::application_subnet().dirty_page_overhead,
             CanisterTimer::Inactive,
             0,
             BTreeSet::from([controller]),
+            BTreeMap::new(),
+            false,
             RequestMetadata::new(0, Time::from_nanos_since_unix_epoch(0)),
             caller,
             0,
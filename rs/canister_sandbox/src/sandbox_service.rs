@@ -6,6 +6,9 @@ pub trait SandboxService: Send + Sync {
     /// Terminate the sandbox.
     fn terminate(&self, req: TerminateRequest) -> Call<TerminateReply>;
 
+    /// Replies once the sandbox has finished its start-up initialization.
+    fn ready(&self, req: ReadyRequest) -> Call<ReadyReply>;
+
     /// Creates a canister Wasm code object. The wasm code itself or
     /// the path to it is passed as the RPC payload.
     fn open_wasm(&self, req: OpenWasmRequest) -> Call<OpenWasmReply>;
@@ -60,6 +63,7 @@ impl<Svc: SandboxService + Send + Sync> DemuxServer<Request, Reply> for Svc {
     fn dispatch(&self, req: Request) -> Call<Reply> {
         match req {
             Request::Terminate(req) => Call::new_wrap(self.terminate(req), Reply::Terminate),
+            Request::Ready(req) => Call::new_wrap(self.ready(req), Reply::Ready),
             Request::OpenWasm(req) => Call::new_wrap(self.open_wasm(req), Reply::OpenWasm),
             Request::OpenWasmSerialized(req) => {
                 Call::new_wrap(self.open_wasm_serialized(req), Reply::OpenWasmSerialized)
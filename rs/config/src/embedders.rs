@@ -108,6 +108,15 @@ pub struct FeatureFlags {
     // TODO(IC-1674): remove this flag once the feature is enabled by default.
     /// Indicates whether the best-effort responses feature is enabled.
     pub best_effort_responses: FlagStatus,
+    /// Indicates whether canisters can enable function-level execution
+    /// profiling via their settings.
+    pub canister_profiling: FlagStatus,
+    /// Indicates whether the Wasm tail call and extended constant expressions
+    /// proposals are enabled.
+    pub wasm_tail_call_and_extended_const: FlagStatus,
+    /// Indicates whether the Wasm typed function references proposal is
+    /// enabled.
+    pub wasm_function_references: FlagStatus,
 }
 
 impl FeatureFlags {
@@ -118,6 +127,9 @@ impl FeatureFlags {
             wasm_native_stable_memory: FlagStatus::Enabled,
             wasm64: FlagStatus::Disabled,
             best_effort_responses: FlagStatus::Disabled,
+            canister_profiling: FlagStatus::Disabled,
+            wasm_tail_call_and_extended_const: FlagStatus::Disabled,
+            wasm_function_references: FlagStatus::Disabled,
         }
     }
 }
@@ -1,6 +1,19 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+/// Default fraction of messages whose spans are exported.
+pub const DEFAULT_SAMPLING_RATIO: f64 = 0.01;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
 pub struct Config {
+    /// Address of the OTLP collector (e.g. Jaeger or a local OpenTelemetry
+    /// collector) that spans are exported to. Export is disabled if unset.
     pub jaeger_addr: Option<String>,
+    /// Fraction of messages whose spans are exported. Defaults to
+    /// [`DEFAULT_SAMPLING_RATIO`].
+    #[serde(default)]
+    pub sampling_ratio: Option<f64>,
+    /// Hex encoded IDs of messages whose spans are always exported, so that
+    /// operators can trace individual requests.
+    #[serde(default)]
+    pub traced_message_ids: Vec<String>,
 }
@@ -7,7 +7,7 @@ use ic_consensus_utils::{
 };
 use ic_interfaces::{
     certification::{CertificationPool, ChangeAction, ChangeSet, Verifier, VerifierError},
-    consensus_pool::ConsensusPoolCache,
+    consensus_pool::{ConsensusBlockCache, ConsensusPoolCache},
     p2p::consensus::{ChangeSetProducer, Priority, PriorityFn, PriorityFnFactory},
     validation::ValidationError,
 };
@@ -31,6 +31,7 @@ use ic_types::{
 use prometheus::{Histogram, IntCounter, IntGauge};
 use std::{cell::RefCell, sync::Arc, time::Instant};
 use tokio::sync::watch;
+use tracing::{info_span, Span};
 
 /// The Certification component, processing the changes on the certification
 /// pool and submitting the corresponding change sets.
@@ -40,6 +41,7 @@ pub struct CertifierImpl {
     crypto: Arc<dyn CertificationCrypto>,
     state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
     consensus_pool_cache: Arc<dyn ConsensusPoolCache>,
+    consensus_block_cache: Arc<dyn ConsensusBlockCache>,
     metrics: CertifierMetrics,
     /// The highest height that has been purged. Used to avoid redundant purging.
     highest_purged_height: RefCell<Height>,
@@ -94,6 +96,7 @@ pub fn setup(
     crypto: Arc<dyn CertificationCrypto>,
     state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
     consensus_pool_cache: Arc<dyn ConsensusPoolCache>,
+    consensus_block_cache: Arc<dyn ConsensusBlockCache>,
     metrics_registry: MetricsRegistry,
     log: ReplicaLogger,
     max_certified_height_tx: watch::Sender<Height>,
@@ -105,6 +108,7 @@ pub fn setup(
             crypto,
             state_manager,
             consensus_pool_cache.clone(),
+            consensus_block_cache,
             metrics_registry,
             log,
             max_certified_height_tx,
@@ -165,6 +169,10 @@ impl<T: CertificationPool> ChangeSetProducer<T> for CertifierImpl {
                     // if we have a valid certification, deliver it to the state manager and skip
                     // the pair
                     Some(certification) => {
+                        let _span =
+                            info_span!("deliver_state_certification", height = height.get())
+                                .entered();
+                        let _ingress_spans = self.ingress_spans(height);
                         // TODO[NET-1711]: Remove deliver_state_certification(), and include them in the
                         // change set for the artifact processor to handle.
                         self.state_manager
@@ -280,6 +288,7 @@ impl CertifierImpl {
         crypto: Arc<dyn CertificationCrypto>,
         state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
         consensus_pool_cache: Arc<dyn ConsensusPoolCache>,
+        consensus_block_cache: Arc<dyn ConsensusBlockCache>,
         metrics_registry: MetricsRegistry,
         log: ReplicaLogger,
         max_certified_height_tx: watch::Sender<Height>,
@@ -296,6 +305,7 @@ impl CertifierImpl {
             crypto,
             state_manager,
             consensus_pool_cache,
+            consensus_block_cache,
             metrics: CertifierMetrics {
                 shares_created: metrics_registry.int_counter(
                     "certification_shares_created",
@@ -321,6 +331,35 @@ impl CertifierImpl {
         }
     }
 
+    /// Returns one span per ingress message in the block at the given height, so
+    /// that the certification of the resulting state appears in the trace of
+    /// each message.
+    fn ingress_spans(&self, height: Height) -> Vec<Span> {
+        let chain = self.consensus_block_cache.finalized_chain();
+        let Ok(block) = chain.get_block_by_height(height) else {
+            return Vec::new();
+        };
+        if block.payload.is_summary() {
+            return Vec::new();
+        }
+        block
+            .payload
+            .as_ref()
+            .as_data()
+            .batch
+            .ingress
+            .message_ids()
+            .iter()
+            .map(|id| {
+                info_span!(
+                    "ingress_certify",
+                    message_id = %id.message_id,
+                    height = height.get()
+                )
+            })
+            .collect()
+    }
+
     // Gets height/hash pairs and creates certification shares for them.
     fn sign(
         &self,
@@ -739,6 +778,7 @@ mod tests {
                     crypto,
                     state_manager.clone(),
                     pool.get_cache(),
+                    pool.get_block_cache(),
                     metrics_registry,
                     log,
                     max_certified_height_tx,
@@ -805,6 +845,7 @@ mod tests {
                     crypto,
                     state_manager.clone(),
                     pool.get_cache(),
+                    pool.get_block_cache(),
                     metrics_registry,
                     log,
                     max_certified_height_tx,
@@ -940,6 +981,7 @@ mod tests {
                     crypto,
                     state_manager.clone(),
                     pool.get_cache(),
+                    pool.get_block_cache(),
                     metrics_registry,
                     log,
                     max_certified_height_tx,
@@ -1019,6 +1061,7 @@ mod tests {
                     crypto,
                     state_manager.clone(),
                     pool.get_cache(),
+                    pool.get_block_cache(),
                     metrics_registry,
                     log,
                     max_certified_height_tx,
@@ -1090,6 +1133,7 @@ mod tests {
                     crypto,
                     state_manager,
                     pool.get_cache(),
+                    pool.get_block_cache(),
                     metrics_registry,
                     log,
                     max_certified_height_tx,
@@ -1156,6 +1200,7 @@ mod tests {
                     crypto,
                     state_manager.clone(),
                     pool.get_cache(),
+                    pool.get_block_cache(),
                     metrics_registry.clone(),
                     log,
                     max_certified_height_tx,
@@ -1222,6 +1267,7 @@ mod tests {
                     crypto,
                     state_manager,
                     pool.get_cache(),
+                    pool.get_block_cache(),
                     MetricsRegistry::new(),
                     log,
                     max_certified_height_tx,
@@ -1283,6 +1329,7 @@ mod tests {
                     crypto,
                     state_manager.clone(),
                     pool.get_cache(),
+                    pool.get_block_cache(),
                     metrics_registry,
                     log,
                     max_certified_height_tx,
@@ -1393,6 +1440,7 @@ mod tests {
                     crypto,
                     state_manager.clone(),
                     pool.get_cache(),
+                    pool.get_block_cache(),
                     metrics_registry,
                     log,
                     max_certified_height_tx,
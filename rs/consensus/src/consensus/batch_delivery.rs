@@ -38,6 +38,7 @@ use ic_types::{
     Height, PrincipalId, Randomness, ReplicaVersion, SubnetId,
 };
 use std::collections::BTreeMap;
+use tracing::info_span;
 
 /// Deliver all finalized blocks from
 /// `message_routing.expected_batch_height` to `finalized_height` via
@@ -242,7 +243,21 @@ pub fn deliver_batches(
             batch_stats.batch_height,
             block_stats.block_hash
         );
+        // One span per ingress message, covering its hand-over to message routing.
+        let ingress_spans: Vec<_> = batch
+            .messages
+            .signed_ingress_msgs
+            .iter()
+            .map(|msg| {
+                info_span!(
+                    "ingress_deliver_batch",
+                    message_id = %msg.id(),
+                    height = height.get()
+                )
+            })
+            .collect();
         let result = message_routing.deliver_batch(batch);
+        drop(ingress_spans);
         if let Some(f) = result_processor {
             f(&result, block_stats, batch_stats);
         }
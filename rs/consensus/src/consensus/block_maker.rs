@@ -9,7 +9,7 @@ use crate::{
     idkg::{self, metrics::IDkgPayloadMetrics},
 };
 use ic_consensus_utils::{
-    find_lowest_ranked_non_disqualified_proposals, get_block_hash_string,
+    find_lowest_ranked_non_disqualified_proposals, get_block_hash_string, get_block_maker_delay,
     get_notarization_delay_settings, get_subnet_record, is_time_to_make_block,
     membership::Membership, pool_reader::PoolReader,
 };
@@ -20,6 +20,7 @@ use ic_interfaces_registry::RegistryClient;
 use ic_interfaces_state_manager::StateManager;
 use ic_logger::{debug, error, trace, warn, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
+use ic_protobuf::registry::subnet::v1::SubnetRecord;
 use ic_replicated_state::ReplicatedState;
 use ic_types::{
     batch::{BatchPayload, ValidationContext},
@@ -29,12 +30,13 @@ use ic_types::{
     },
     replica_config::ReplicaConfig,
     time::current_time,
-    CountBytes, Height, NodeId, RegistryVersion,
+    CanisterTimer, CountBytes, Height, NodeId, RegistryVersion, Time,
 };
 use std::{
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
     time::Duration,
 };
+use tracing::{info_span, instrument};
 
 pub(crate) fn subnet_records_for_registry_version(
     block_maker: &BlockMaker,
@@ -59,6 +61,16 @@ pub(crate) fn subnet_records_for_registry_version(
     })
 }
 
+/// Number of messages executed by the subnet up to the given state height,
+/// whether any messages were executed since the previous such observation, and
+/// the earliest deadline of a canister global timer in the state at that height.
+struct ExecutionActivity {
+    height: Height,
+    update_transactions_total: u64,
+    active: bool,
+    next_global_timer: Option<Time>,
+}
+
 /// A consensus subcomponent that is responsible for creating block proposals.
 pub struct BlockMaker {
     time_source: Arc<dyn TimeSource>,
@@ -77,6 +89,8 @@ pub struct BlockMaker {
     // block. The older is the version, the higher is the probability, that it's universally
     // available across the subnet.
     stable_registry_version_age: Duration,
+    // The last observed execution activity, used to tell whether the subnet is idle.
+    execution_activity: Mutex<Option<ExecutionActivity>>,
 }
 
 impl BlockMaker {
@@ -110,6 +124,7 @@ impl BlockMaker {
             metrics: BlockMakerMetrics::new(metrics_registry.clone()),
             idkg_payload_metrics: IDkgPayloadMetrics::new(metrics_registry),
             stable_registry_version_age,
+            execution_activity: Mutex::new(None),
         }
     }
 
@@ -210,6 +225,14 @@ impl BlockMaker {
         // Get the subnet records that are relevant to making a block
         let subnet_records =
             subnet_records_for_registry_version(self, registry_version, stable_registry_version)?;
+        // On idle subnets, blocks with an empty payload are held back for a while.
+        let defer_empty_block = self.should_defer_empty_block(
+            pool,
+            &subnet_records.membership_version,
+            &parent,
+            rank,
+            registry_version,
+        );
 
         // The monotonic_block_increment is used as the minimum timestamp increment over
         // the parent for block proposals. Technically we only need this delta to be 1ns
@@ -280,12 +303,145 @@ impl BlockMaker {
             rank,
             registry_version,
             &subnet_records,
+            defer_empty_block,
         )
     }
 
+    /// Return true if the subnet record enables the reduced block rate, the subnet
+    /// is idle, and the extended block maker delay for idle subnets hasn't passed yet.
+    ///
+    /// Subnets holding threshold keys always make blocks at the regular rate. Other
+    /// subnets are considered idle if the parent and the last `empty_blocks_threshold`
+    /// finalized blocks are data blocks without batch, DKG or IDKG payload, no
+    /// messages are waiting for the subnet, and no messages (including heartbeats
+    /// and global timers) were executed since the state was last looked at.
+    ///
+    /// The deferral never extends past the earliest global timer deadline of any
+    /// canister, so that timers fire on time on idle subnets.
+    fn should_defer_empty_block(
+        &self,
+        pool: &PoolReader<'_>,
+        subnet_record: &SubnetRecord,
+        parent: &HashedBlock,
+        rank: Rank,
+        registry_version: RegistryVersion,
+    ) -> bool {
+        let Some(config) = subnet_record.idle_block_config.as_ref() else {
+            return false;
+        };
+        let holds_threshold_keys = subnet_record
+            .chain_key_config
+            .as_ref()
+            .is_some_and(|config| !config.key_configs.is_empty())
+            || subnet_record
+                .ecdsa_config
+                .as_ref()
+                .is_some_and(|config| !config.key_ids.is_empty());
+        if holds_threshold_keys {
+            return false;
+        }
+
+        let is_empty = |block: &Block| {
+            !block.payload.is_summary() && {
+                let data = block.payload.as_ref().as_data();
+                data.batch.is_empty() && data.dealings.messages.is_empty() && data.idkg.is_none()
+            }
+        };
+
+        if !is_empty(parent.as_ref()) {
+            return false;
+        }
+        let threshold = config.empty_blocks_threshold as usize;
+        let empty_finalized_blocks = pool
+            .chain_iterator(pool.get_finalized_tip())
+            .take(threshold)
+            .take_while(is_empty)
+            .count();
+        if empty_finalized_blocks < threshold {
+            return false;
+        }
+
+        let now = self.time_source.get_relative_time();
+        if self.has_pending_work(now) {
+            return false;
+        }
+
+        // Like in `is_time_to_make_block`, the idle delay is counted from the start
+        // of the round and is increased by the regular block maker delay of the rank,
+        // so that block makers of different ranks don't propose at the same time.
+        let height = parent.height().increment();
+        let Some(block_maker_delay) = get_block_maker_delay(
+            &self.log,
+            self.registry_client.as_ref(),
+            self.replica_config.subnet_id,
+            registry_version,
+            rank,
+        ) else {
+            return false;
+        };
+        let delay = Duration::from_millis(config.idle_block_delay_millis) + block_maker_delay;
+        let idle_delay_passed = pool
+            .get_round_start_time(height)
+            .is_some_and(|start_time| now >= start_time + delay)
+            || pool
+                .get_round_start_instant(height, self.time_source.get_origin_instant())
+                .is_some_and(|start_instant| {
+                    self.time_source.get_instant() >= start_instant + delay
+                });
+
+        !idle_delay_passed
+    }
+
+    /// Return true if messages are waiting for the subnet, if messages were
+    /// executed between the two latest observed state heights, or if a canister
+    /// global timer is due at time `now`. Without a previous observation, the
+    /// subnet is assumed to have pending work.
+    fn has_pending_work(&self, now: Time) -> bool {
+        let state = self.state_manager.get_latest_state();
+        let height = state.height();
+        let state = state.take();
+        if !state.consensus_queue.is_empty() || state.subnet_queues().has_input() {
+            return true;
+        }
+
+        let mut execution_activity = self.execution_activity.lock().unwrap();
+        if !execution_activity
+            .as_ref()
+            .is_some_and(|activity| activity.height == height)
+        {
+            let update_transactions_total = state.metadata.subnet_metrics.update_transactions_total;
+            let active = match execution_activity.as_ref() {
+                Some(activity) if activity.height < height => {
+                    update_transactions_total > activity.update_transactions_total
+                }
+                _ => true,
+            };
+            let next_global_timer = state
+                .canisters_iter()
+                .filter_map(|canister| match canister.system_state.global_timer {
+                    CanisterTimer::Active(deadline) => Some(deadline),
+                    CanisterTimer::Inactive => None,
+                })
+                .min();
+            *execution_activity = Some(ExecutionActivity {
+                height,
+                update_transactions_total,
+                active,
+                next_global_timer,
+            });
+        }
+        let activity = execution_activity.as_ref().unwrap();
+        activity.active
+            || activity
+                .next_global_timer
+                .is_some_and(|deadline| now >= deadline)
+    }
+
     /// Construct a block proposal with specified validation context, parent
     /// block, rank, and batch payload. This function completes the block by
     /// adding a DKG payload and signs the block to obtain a block proposal.
+    /// If `defer_empty_block` is set, no proposal is made in case the block
+    /// would be a data block without any payload.
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn construct_block_proposal(
         &self,
@@ -297,6 +453,7 @@ impl BlockMaker {
         rank: Rank,
         registry_version: RegistryVersion,
         subnet_records: &SubnetRecords,
+        defer_empty_block: bool,
     ) -> Option<BlockProposal> {
         let max_dealings_per_block =
             subnet_records.membership_version.dkg_dealings_per_block as usize;
@@ -386,6 +543,15 @@ impl BlockMaker {
                             .ok()
                             .flatten();
 
+                            if defer_empty_block
+                                && batch_payload.is_empty()
+                                && dealings.messages.is_empty()
+                                && idkg_data.is_none()
+                            {
+                                self.metrics.idle_block_proposals_deferred.inc();
+                                return None;
+                            }
+
                             (batch_payload, dealings, idkg_data)
                         }
                     };
@@ -422,6 +588,7 @@ impl BlockMaker {
     }
 
     #[allow(clippy::too_many_arguments)]
+    #[instrument(skip_all, fields(height = height.get()))]
     fn build_batch_payload(
         &self,
         pool: &PoolReader<'_>,
@@ -462,6 +629,12 @@ impl BlockMaker {
         };
 
         for message_id in batch.ingress.message_ids() {
+            let _span = info_span!(
+                "ingress_insert_into_block",
+                message_id = %message_id,
+                height = block.height.get(),
+            )
+            .entered();
             debug!(
                 self.log,
                 "ingress_message_insert_into_block";
@@ -519,19 +692,29 @@ mod tests {
     use crate::idkg::test_utils::create_idkg_pool;
 
     use super::*;
+    use ic_config::artifact_pool::ArtifactPoolConfig;
     use ic_consensus_mocks::{dependencies_with_subnet_params, Dependencies, MockPayloadBuilder};
     use ic_consensus_utils::get_block_maker_delay;
     use ic_interfaces::consensus_pool::ConsensusPool;
     use ic_logger::replica_logger::no_op_logger;
+    use ic_management_canister_types::{EcdsaCurve, EcdsaKeyId, MasterPublicKeyId};
     use ic_metrics::MetricsRegistry;
+    use ic_protobuf::registry::subnet::v1::IdleBlockConfig;
+    use ic_registry_subnet_features::{ChainKeyConfig, KeyConfig};
+    use ic_test_artifact_pool::consensus_pool::TestConsensusPool;
     use ic_test_utilities_registry::{add_subnet_record, SubnetRecordBuilder};
-    use ic_test_utilities_types::ids::{node_test_id, subnet_test_id};
+    use ic_test_utilities_state::CanisterStateBuilder;
+    use ic_test_utilities_time::FastForwardTimeSource;
+    use ic_test_utilities_types::ids::{canister_test_id, node_test_id, subnet_test_id};
     use ic_types::{
         consensus::{dkg, HasHeight, HasVersion},
         crypto::CryptoHash,
         *,
     };
-    use std::sync::{Arc, RwLock};
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    };
 
     #[test]
     fn test_block_maker() {
@@ -867,6 +1050,229 @@ mod tests {
         })
     }
 
+    /// Creates a block maker for a subnet with the given record, whose latest state
+    /// height increases every time the state is looked at. If `executes_messages`
+    /// is set, the subnet executes a message in every round. If `global_timer` is
+    /// set, the state holds a canister whose global timer is due after that time.
+    fn idle_subnet_block_maker(
+        pool_config: ArtifactPoolConfig,
+        record: SubnetRecord,
+        executes_messages: bool,
+        global_timer: Option<Duration>,
+    ) -> (BlockMaker, TestConsensusPool, Arc<FastForwardTimeSource>) {
+        let Dependencies {
+            pool,
+            registry,
+            crypto,
+            time_source,
+            replica_config,
+            state_manager,
+            dkg_pool,
+            idkg_pool,
+            ..
+        } = dependencies_with_subnet_params(pool_config, subnet_test_id(0), vec![(1, record)]);
+
+        let mut state = ic_test_utilities_state::get_initial_state(0, 0);
+        if let Some(global_timer) = global_timer {
+            let mut canister = CanisterStateBuilder::new()
+                .with_canister_id(canister_test_id(0))
+                .build();
+            canister.system_state.global_timer =
+                CanisterTimer::Active(time_source.get_relative_time() + global_timer);
+            state.put_canister_state(canister);
+        }
+        state_manager
+            .get_mut()
+            .expect_latest_certified_height()
+            .return_const(Height::from(0));
+        state_manager
+            .get_mut()
+            .expect_get_state_at()
+            .return_const(Ok(ic_interfaces_state_manager::Labeled::new(
+                Height::new(0),
+                Arc::new(state.clone()),
+            )));
+        let next_height = AtomicU64::new(0);
+        state_manager
+            .get_mut()
+            .expect_get_latest_state()
+            .returning(move || {
+                let height = next_height.fetch_add(1, Ordering::SeqCst);
+                let mut state = state.clone();
+                if executes_messages {
+                    state.metadata.subnet_metrics.update_transactions_total = height;
+                }
+                ic_interfaces_state_manager::Labeled::new(Height::new(height), Arc::new(state))
+            });
+
+        let mut payload_builder = MockPayloadBuilder::new();
+        payload_builder
+            .expect_get_payload()
+            .return_const(BatchPayload::default());
+        let membership = Arc::new(Membership::new(
+            pool.get_cache(),
+            registry.clone(),
+            replica_config.subnet_id,
+        ));
+
+        let block_maker = BlockMaker::new(
+            Arc::clone(&time_source) as Arc<_>,
+            replica_config,
+            Arc::clone(&registry) as Arc<dyn RegistryClient>,
+            membership,
+            crypto,
+            Arc::new(payload_builder),
+            dkg_pool,
+            idkg_pool,
+            state_manager,
+            Duration::from_millis(0),
+            MetricsRegistry::new(),
+            no_op_logger(),
+        );
+        (block_maker, pool, time_source)
+    }
+
+    fn idle_block_config(idle_block_delay: Duration) -> IdleBlockConfig {
+        IdleBlockConfig {
+            empty_blocks_threshold: 3,
+            idle_block_delay_millis: idle_block_delay.as_millis() as u64,
+        }
+    }
+
+    #[test]
+    fn test_idle_subnet_defers_empty_blocks() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let idle_block_delay = Duration::from_secs(10);
+            let record = SubnetRecordBuilder::from(&[node_test_id(0)])
+                .with_dkg_interval_length(99)
+                .with_idle_block_config(idle_block_config(idle_block_delay))
+                .build();
+            let (block_maker, mut pool, time_source) =
+                idle_subnet_block_maker(pool_config, record, false, None);
+
+            // Not enough empty blocks have been finalized yet, so an empty block is
+            // proposed right away.
+            pool.advance_round_normal_operation();
+            assert!(block_maker
+                .on_state_change(&PoolReader::new(&pool))
+                .is_some());
+
+            // Without an earlier look at the state, the subnet is assumed to be busy.
+            pool.advance_round_normal_operation_n(2);
+            assert!(block_maker
+                .on_state_change(&PoolReader::new(&pool))
+                .is_some());
+
+            // Once the subnet is idle, empty blocks are held back until the idle
+            // delay has passed.
+            pool.advance_round_normal_operation();
+            assert!(block_maker
+                .on_state_change(&PoolReader::new(&pool))
+                .is_none());
+            assert_eq!(block_maker.metrics.idle_block_proposals_deferred.get(), 1);
+
+            time_source.advance_time(idle_block_delay);
+            let proposal = block_maker
+                .on_state_change(&PoolReader::new(&pool))
+                .expect("Expected a new block proposal");
+            assert!(proposal
+                .as_ref()
+                .payload
+                .as_ref()
+                .as_data()
+                .batch
+                .is_empty());
+        })
+    }
+
+    #[test]
+    fn test_idle_subnet_defers_empty_blocks_until_global_timer_is_due() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let idle_block_delay = Duration::from_secs(10);
+            let timer_delay = Duration::from_secs(3);
+            let record = SubnetRecordBuilder::from(&[node_test_id(0)])
+                .with_dkg_interval_length(99)
+                .with_idle_block_config(idle_block_config(idle_block_delay))
+                .build();
+            let (block_maker, mut pool, time_source) =
+                idle_subnet_block_maker(pool_config, record, false, Some(timer_delay));
+
+            pool.advance_round_normal_operation_n(4);
+            assert!(block_maker
+                .on_state_change(&PoolReader::new(&pool))
+                .is_some());
+
+            // The subnet is idle and the global timer isn't due yet.
+            pool.advance_round_normal_operation();
+            assert!(block_maker
+                .on_state_change(&PoolReader::new(&pool))
+                .is_none());
+            assert_eq!(block_maker.metrics.idle_block_proposals_deferred.get(), 1);
+
+            // Once the global timer is due, an empty block is proposed before the
+            // idle delay has passed.
+            time_source.advance_time(timer_delay);
+            assert!(block_maker
+                .on_state_change(&PoolReader::new(&pool))
+                .is_some());
+            assert_eq!(block_maker.metrics.idle_block_proposals_deferred.get(), 1);
+        })
+    }
+
+    #[test]
+    fn test_busy_subnet_does_not_defer_empty_blocks() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let record = SubnetRecordBuilder::from(&[node_test_id(0)])
+                .with_dkg_interval_length(99)
+                .with_idle_block_config(idle_block_config(Duration::from_secs(10)))
+                .build();
+            let (block_maker, mut pool, _) =
+                idle_subnet_block_maker(pool_config, record, true, None);
+
+            // Blocks are empty, but the subnet keeps executing messages, e.g. heartbeats.
+            for _ in 0..3 {
+                pool.advance_round_normal_operation_n(3);
+                assert!(block_maker
+                    .on_state_change(&PoolReader::new(&pool))
+                    .is_some());
+            }
+            assert_eq!(block_maker.metrics.idle_block_proposals_deferred.get(), 0);
+        })
+    }
+
+    #[test]
+    fn test_subnet_with_threshold_keys_does_not_defer_empty_blocks() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let record = SubnetRecordBuilder::from(&[node_test_id(0)])
+                .with_dkg_interval_length(99)
+                .with_idle_block_config(idle_block_config(Duration::from_secs(10)))
+                .with_chain_key_config(ChainKeyConfig {
+                    key_configs: vec![KeyConfig {
+                        key_id: MasterPublicKeyId::Ecdsa(EcdsaKeyId {
+                            curve: EcdsaCurve::Secp256k1,
+                            name: "some_key".to_string(),
+                        }),
+                        pre_signatures_to_create_in_advance: 1,
+                        max_queue_size: 3,
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: None,
+                    }],
+                    ..ChainKeyConfig::default()
+                })
+                .build();
+            let (block_maker, mut pool, _) =
+                idle_subnet_block_maker(pool_config, record, false, None);
+
+            for _ in 0..3 {
+                pool.advance_round_normal_operation_n(3);
+                assert!(block_maker
+                    .on_state_change(&PoolReader::new(&pool))
+                    .is_some());
+            }
+            assert_eq!(block_maker.metrics.idle_block_proposals_deferred.get(), 0);
+        })
+    }
+
     #[test]
     fn test_stable_registry_version() {
         ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
@@ -189,6 +189,7 @@ fn maliciously_propose_empty_block(
         rank,
         registry_version,
         &subnet_records,
+        /*defer_empty_block=*/ false,
     )
 }
 
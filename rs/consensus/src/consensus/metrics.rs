@@ -35,6 +35,7 @@ pub(crate) const CRITICAL_ERROR_SUBNET_RECORD_ISSUE: &str = "consensus_subnet_re
 pub struct BlockMakerMetrics {
     pub get_payload_calls: IntCounterVec,
     pub block_size_bytes_estimate: IntGaugeVec,
+    pub idle_block_proposals_deferred: IntCounter,
 }
 
 impl BlockMakerMetrics {
@@ -48,7 +49,11 @@ impl BlockMakerMetrics {
             block_size_bytes_estimate: metrics_registry.int_gauge_vec(
                 "consensus_block_size_bytes_estimate",
                 "An estimate about the block size produced by the block maker.",
-                &["payload_type"])
+                &["payload_type"]),
+            idle_block_proposals_deferred: metrics_registry.int_counter(
+                "consensus_idle_block_proposals_deferred",
+                "The number of times an empty block proposal was held back because the subnet is idle.",
+            ),
         }
     }
 
@@ -7,7 +7,7 @@ use ic_metrics::{
 };
 use ic_types::consensus::idkg::{HasMasterPublicKeyId, IDkgPayload};
 use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 pub const KEY_ID_LABEL: &str = "key_id";
 
@@ -229,6 +229,21 @@ impl IDkgPayloadMetrics {
         );
     }
 
+    pub(crate) fn report_pre_signature_demand(
+        &self,
+        valid_keys: &BTreeSet<MasterPublicKeyId>,
+        demand_per_key_id: &CounterPerMasterPublicKeyId,
+    ) {
+        let demand = valid_keys
+            .iter()
+            .map(|key_id| {
+                let demand = demand_per_key_id.get(key_id).copied().unwrap_or_default();
+                (key_id.clone(), demand)
+            })
+            .collect();
+        self.payload_metrics_set("pre_signature_demand", demand);
+    }
+
     fn payload_metrics_set_without_key_id_label(&self, label: &str, value: usize) {
         self.payload_metrics
             .with_label_values(&[label, /*key_id=*/ ""])
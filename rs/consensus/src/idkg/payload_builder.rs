@@ -625,10 +625,18 @@ pub(crate) fn create_data_payload_helper_2(
         }
     }
 
+    // The size of the pre-signature pipeline follows the recent demand.
+    let pre_signature_demand_per_key_id =
+        pre_signatures::pre_signature_demand_per_key_id(all_signing_requests, context_time);
+    if let Some(metrics) = idkg_payload_metrics {
+        metrics.report_pre_signature_demand(valid_keys, &pre_signature_demand_per_key_id);
+    }
+
     pre_signatures::make_new_pre_signatures_if_needed(
         chain_key_config,
         idkg_payload,
         &matched_pre_signatures_per_key_id,
+        &pre_signature_demand_per_key_id,
     );
 
     let new_transcripts = [
@@ -877,6 +885,8 @@ mod tests {
                 key_id: valid_key_id.clone(),
                 pre_signatures_to_create_in_advance: PRE_SIGNATURES_TO_CREATE_IN_ADVANCE,
                 max_queue_size: 1,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }],
             ..ChainKeyConfig::default()
         };
@@ -2116,6 +2126,8 @@ mod tests {
                     key_id: key_id.clone(),
                     pre_signatures_to_create_in_advance: 1,
                     max_queue_size: 1,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 signature_request_timeout_ns: Some(100000),
                 ..ChainKeyConfig::default()
@@ -2208,6 +2220,8 @@ mod tests {
                     key_id: key_id.clone(),
                     pre_signatures_to_create_in_advance: 1,
                     max_queue_size: 1,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 signature_request_timeout_ns: Some(100000),
                 ..ChainKeyConfig::default()
@@ -2356,6 +2370,8 @@ mod tests {
                     key_id: key_id.clone(),
                     pre_signatures_to_create_in_advance: 1,
                     max_queue_size: 1,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 signature_request_timeout_ns: Some(100000),
                 ..ChainKeyConfig::default()
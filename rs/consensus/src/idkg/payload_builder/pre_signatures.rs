@@ -3,7 +3,7 @@ use super::IDkgPayloadError;
 use crate::idkg::{pre_signer::IDkgTranscriptBuilder, utils::algorithm_for_key_id};
 use ic_logger::{debug, error, ReplicaLogger};
 use ic_management_canister_types::MasterPublicKeyId;
-use ic_registry_subnet_features::ChainKeyConfig;
+use ic_registry_subnet_features::{ChainKeyConfig, KeyConfig};
 use ic_replicated_state::metadata_state::subnet_call_context_manager::SignWithThresholdContext;
 use ic_types::{
     consensus::idkg::{
@@ -16,10 +16,18 @@ use ic_types::{
     },
     crypto::canister_threshold_sig::idkg::IDkgTranscript,
     messages::CallbackId,
+    time::Time,
     Height, NodeId, RegistryVersion,
 };
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+/// Signature requests that were received within this window before the block
+/// time are considered recent and contribute to the pre-signature demand.
+pub(super) const PRE_SIGNATURE_DEMAND_WINDOW: Duration = Duration::from_secs(60);
 
 /// Update the pre-signatures in the payload by:
 /// - making new configs when pre-conditions are met;
@@ -307,13 +315,55 @@ pub(super) fn purge_old_key_pre_signatures(
     });
 }
 
+/// Estimate the demand for pre-signatures per key from the signature request contexts
+/// in the replicated state.
+///
+/// The demand of a key is the number of its requests that are still waiting to be
+/// matched with a pre-signature, plus the number of its requests that were received
+/// within [`PRE_SIGNATURE_DEMAND_WINDOW`] before `context_time`. The latter accounts for
+/// the request rate: during a burst, more requests are likely to follow shortly.
+pub(super) fn pre_signature_demand_per_key_id(
+    all_signing_requests: &BTreeMap<CallbackId, SignWithThresholdContext>,
+    context_time: Time,
+) -> BTreeMap<MasterPublicKeyId, usize> {
+    let window_start = context_time.saturating_sub(PRE_SIGNATURE_DEMAND_WINDOW);
+    let mut demand_per_key_id = BTreeMap::new();
+    for context in all_signing_requests.values() {
+        let demand = usize::from(context.matched_pre_signature.is_none())
+            + usize::from(context.batch_time >= window_start);
+        if demand > 0 {
+            *demand_per_key_id.entry(context.key_id()).or_insert(0) += demand;
+        }
+    }
+    demand_per_key_id
+}
+
+/// Return the number of unmatched pre-signatures (available or in creation) that
+/// should exist for the given key, given the current demand.
+///
+/// The result is bounded by `min_pre_signatures_to_create` and
+/// `max_pre_signatures_to_create`, which both default to
+/// `pre_signatures_to_create_in_advance`. Unless these bounds are set in the registry, a
+/// fixed number of pre-signatures is created, regardless of the demand.
+pub(super) fn pre_signatures_to_create_for_demand(key_config: &KeyConfig, demand: usize) -> usize {
+    let in_advance = key_config.pre_signatures_to_create_in_advance;
+    let min = key_config
+        .min_pre_signatures_to_create
+        .unwrap_or(in_advance) as usize;
+    let max = key_config
+        .max_pre_signatures_to_create
+        .unwrap_or(in_advance) as usize;
+    demand.clamp(min, max.max(min))
+}
+
 /// Creating new pre-signatures if necessary by updating pre_signatures_in_creation,
-/// considering currently available pre-signatures, pre-signatures in creation, and
-/// chain key configs.
+/// considering currently available pre-signatures, pre-signatures in creation, the
+/// demand for pre-signatures and chain key configs.
 pub(super) fn make_new_pre_signatures_if_needed(
     chain_key_config: &ChainKeyConfig,
     idkg_payload: &mut idkg::IDkgPayload,
     matched_pre_signatures_per_key_id: &BTreeMap<MasterPublicKeyId, usize>,
+    demand_per_key_id: &BTreeMap<MasterPublicKeyId, usize>,
 ) {
     for (key_id, key_transcript) in &idkg_payload.key_transcripts {
         let Some(key_transcript) = key_transcript.current.as_ref() else {
@@ -338,6 +388,7 @@ pub(super) fn make_new_pre_signatures_if_needed(
             key_id,
             &mut idkg_payload.uid_generator,
             unassigned_pre_signatures,
+            demand_per_key_id.get(key_id).copied().unwrap_or_default(),
         );
 
         idkg_payload
@@ -353,6 +404,7 @@ fn make_new_pre_signatures_if_needed_helper(
     key_id: &MasterPublicKeyId,
     uid_generator: &mut IDkgUIDGenerator,
     unassigned_pre_signatures: usize,
+    demand: usize,
 ) -> BTreeMap<PreSigId, PreSignatureInCreation> {
     let mut new_pre_signatures = BTreeMap::new();
 
@@ -360,7 +412,7 @@ fn make_new_pre_signatures_if_needed_helper(
         .key_configs
         .iter()
         .find(|key_config| &key_config.key_id == key_id)
-        .map(|key_config| key_config.pre_signatures_to_create_in_advance as usize)
+        .map(|key_config| pre_signatures_to_create_for_demand(key_config, demand))
     else {
         return new_pre_signatures;
     };
@@ -553,6 +605,7 @@ pub(super) mod tests {
     use ic_types::{
         consensus::idkg::{common::PreSignatureRef, IDkgPayload, UnmaskedTranscript},
         crypto::canister_threshold_sig::idkg::IDkgTranscriptId,
+        time::UNIX_EPOCH,
         SubnetId,
     };
     use idkg::IDkgTranscriptOperationRef;
@@ -595,6 +648,8 @@ pub(super) mod tests {
                     key_id: key_id.clone(),
                     pre_signatures_to_create_in_advance,
                     max_queue_size: 1,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 ..ChainKeyConfig::default()
             };
@@ -606,6 +661,7 @@ pub(super) mod tests {
                 key_id,
                 &mut uid_generator,
                 unassigned,
+                /*demand=*/ 0,
             )
         };
 
@@ -660,6 +716,8 @@ pub(super) mod tests {
                 key_id: key_id.clone(),
                 pre_signatures_to_create_in_advance,
                 max_queue_size: 1,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }],
             ..ChainKeyConfig::default()
         };
@@ -681,6 +739,7 @@ pub(super) mod tests {
             &chain_key_config,
             &mut idkg_payload,
             &BTreeMap::from([(key_id.clone(), pre_signature_already_matched)]),
+            &BTreeMap::new(),
         );
 
         assert_eq!(
@@ -1213,4 +1272,255 @@ pub(super) mod tests {
             pre_sig_ids[0]
         );
     }
+
+    #[test]
+    fn test_pre_signature_demand_per_key_id() {
+        let ecdsa_key_id = fake_ecdsa_master_public_key_id();
+        let schnorr_key_id = fake_schnorr_master_public_key_id(SchnorrAlgorithm::Ed25519);
+        let context_time = UNIX_EPOCH + Duration::from_secs(1000);
+        let recent = context_time.saturating_sub(PRE_SIGNATURE_DEMAND_WINDOW);
+        let old = recent.saturating_sub(Duration::from_nanos(1));
+
+        let contexts = [
+            // Unmatched & recent: counts twice
+            (1, ecdsa_key_id.clone(), None, recent),
+            // Unmatched & old: counts once
+            (2, ecdsa_key_id.clone(), None, old),
+            // Matched & recent: counts once
+            (3, ecdsa_key_id.clone(), Some(PreSigId(3)), context_time),
+            // Matched & old: doesn't count
+            (4, schnorr_key_id.clone(), Some(PreSigId(4)), old),
+        ]
+        .into_iter()
+        .map(|(id, key_id, pre_sig_id, batch_time)| {
+            let (callback_id, mut context) =
+                fake_signature_request_context_with_pre_sig(id, key_id, pre_sig_id);
+            context.batch_time = batch_time;
+            (callback_id, context)
+        })
+        .collect();
+
+        let demand = pre_signature_demand_per_key_id(&contexts, context_time);
+        assert_eq!(demand, BTreeMap::from([(ecdsa_key_id, 4)]));
+    }
+
+    #[test]
+    fn test_pre_signatures_to_create_for_demand() {
+        let key_config = |min_pre_signatures_to_create, max_pre_signatures_to_create| KeyConfig {
+            key_id: fake_ecdsa_master_public_key_id(),
+            pre_signatures_to_create_in_advance: 4,
+            max_queue_size: 20,
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
+        };
+
+        // Without bounds in the registry, the number of pre-signatures is fixed
+        for demand in [0, 4, 50] {
+            assert_eq!(
+                pre_signatures_to_create_for_demand(&key_config(None, None), demand),
+                4
+            );
+        }
+        // The demand is bounded by the registry settings
+        let bounded = key_config(Some(2), Some(10));
+        assert_eq!(pre_signatures_to_create_for_demand(&bounded, 0), 2);
+        assert_eq!(pre_signatures_to_create_for_demand(&bounded, 5), 5);
+        assert_eq!(pre_signatures_to_create_for_demand(&bounded, 50), 10);
+        // A missing bound defaults to the number of pre-signatures to create in advance
+        assert_eq!(
+            pre_signatures_to_create_for_demand(&key_config(None, Some(10)), 0),
+            4
+        );
+        assert_eq!(
+            pre_signatures_to_create_for_demand(&key_config(Some(2), None), 50),
+            4
+        );
+        // A maximum below the minimum results in a fixed number of pre-signatures
+        assert_eq!(
+            pre_signatures_to_create_for_demand(&key_config(Some(6), Some(3)), 50),
+            6
+        );
+        // Disabled pre-signature creation
+        assert_eq!(
+            pre_signatures_to_create_for_demand(&key_config(Some(0), Some(0)), 50),
+            0
+        );
+    }
+
+    #[test]
+    fn test_make_new_pre_signatures_if_needed_follows_demand_all_algorithms() {
+        for key_id in fake_master_public_key_ids_for_all_algorithms() {
+            println!("Running test for key ID {key_id}");
+            test_make_new_pre_signatures_if_needed_follows_demand(key_id);
+        }
+    }
+
+    fn test_make_new_pre_signatures_if_needed_follows_demand(key_id: MasterPublicKeyId) {
+        let mut rng = reproducible_rng();
+        let subnet_id = subnet_test_id(1);
+        let height = Height::new(10);
+        let (mut idkg_payload, _env, _block_reader) =
+            set_up(&mut rng, subnet_id, vec![key_id.clone()], height);
+
+        let chain_key_config = ChainKeyConfig {
+            key_configs: vec![KeyConfig {
+                key_id: key_id.clone(),
+                pre_signatures_to_create_in_advance: 2,
+                max_queue_size: 10,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: Some(10),
+            }],
+            ..ChainKeyConfig::default()
+        };
+
+        // Without demand, only the minimum number of pre-signatures is created
+        make_new_pre_signatures_if_needed(
+            &chain_key_config,
+            &mut idkg_payload,
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+        );
+        assert_eq!(idkg_payload.pre_signatures_in_creation.len(), 2);
+
+        // The pipeline grows with the demand
+        make_new_pre_signatures_if_needed(
+            &chain_key_config,
+            &mut idkg_payload,
+            &BTreeMap::new(),
+            &BTreeMap::from([(key_id.clone(), 7)]),
+        );
+        assert_eq!(idkg_payload.pre_signatures_in_creation.len(), 7);
+
+        // But not beyond the maximum
+        make_new_pre_signatures_if_needed(
+            &chain_key_config,
+            &mut idkg_payload,
+            &BTreeMap::new(),
+            &BTreeMap::from([(key_id.clone(), 100)]),
+        );
+        assert_eq!(idkg_payload.pre_signatures_in_creation.len(), 10);
+
+        // Pre-signatures in creation are not dropped when the demand decreases
+        make_new_pre_signatures_if_needed(
+            &chain_key_config,
+            &mut idkg_payload,
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+        );
+        assert_eq!(idkg_payload.pre_signatures_in_creation.len(), 10);
+    }
+
+    /// Serve a burst of Schnorr signature requests by driving the payload through
+    /// [`update_pre_signatures_in_creation`] and [`make_new_pre_signatures_if_needed`]
+    /// round by round, and return the number of rounds until all requests were matched
+    /// with a pre-signature. The transcript of a pre-signature is completed
+    /// `creation_rounds` rounds after its config was created.
+    fn rounds_to_serve_burst(
+        rng: &mut ReproducibleRng,
+        key_config: KeyConfig,
+        burst: u8,
+        creation_rounds: u64,
+    ) -> u64 {
+        let key_id = key_config.key_id.clone();
+        let (mut payload, env, block_reader) =
+            set_up(rng, subnet_test_id(1), vec![key_id.clone()], Height::new(1));
+        let chain_key_config = ChainKeyConfig {
+            key_configs: vec![key_config],
+            ..ChainKeyConfig::default()
+        };
+        let transcript_builder = TestIDkgTranscriptBuilder::new();
+        let mut waiting: BTreeMap<_, _> = (0..burst)
+            .map(|id| fake_signature_request_context_with_pre_sig(id, key_id.clone(), None))
+            .collect();
+        let mut created_at = BTreeMap::new();
+        // Running the protocol for every transcript is slow, so all completed
+        // transcripts are copies of the first one.
+        let mut completed_transcript: Option<IDkgTranscript> = None;
+
+        let mut round = 0;
+        while !waiting.is_empty() {
+            round += 1;
+            let height = Height::new(round);
+            payload.uid_generator.update_height(height).unwrap();
+
+            for (pre_sig_id, pre_signature) in &payload.pre_signatures_in_creation {
+                let PreSignatureInCreation::Schnorr(transcript) = pre_signature else {
+                    panic!("Expected Schnorr pre-signature");
+                };
+                if created_at[pre_sig_id] + creation_rounds != round {
+                    continue;
+                }
+                let config = transcript.blinder_unmasked_config.as_ref();
+                let mut transcript = completed_transcript
+                    .get_or_insert_with(|| {
+                        env.nodes.run_idkg_and_create_and_verify_transcript(
+                            &config.translate(&block_reader).unwrap(),
+                            rng,
+                        )
+                    })
+                    .clone();
+                transcript.transcript_id = config.transcript_id;
+                transcript_builder.add_transcript(config.transcript_id, transcript);
+            }
+            update_pre_signatures_in_creation(
+                &mut payload,
+                &transcript_builder,
+                height,
+                &no_op_logger(),
+            )
+            .unwrap();
+
+            // Requests are matched with the available pre-signatures and consume them.
+            let matched: Vec<_> = payload
+                .available_pre_signatures
+                .keys()
+                .copied()
+                .zip(waiting.keys().copied())
+                .collect();
+            for (pre_sig_id, callback_id) in matched {
+                payload.available_pre_signatures.remove(&pre_sig_id);
+                waiting.remove(&callback_id);
+            }
+
+            let demand =
+                pre_signature_demand_per_key_id(&waiting, UNIX_EPOCH + Duration::from_secs(round));
+            make_new_pre_signatures_if_needed(
+                &chain_key_config,
+                &mut payload,
+                &BTreeMap::new(),
+                &demand,
+            );
+            for pre_sig_id in payload.pre_signatures_in_creation.keys() {
+                created_at.entry(*pre_sig_id).or_insert(round);
+            }
+        }
+        round
+    }
+
+    #[test]
+    fn test_demand_driven_pre_signatures_reduce_latency_under_burst() {
+        let mut rng = reproducible_rng();
+        let key_config = |max_pre_signatures_to_create| KeyConfig {
+            key_id: fake_schnorr_master_public_key_id(SchnorrAlgorithm::Ed25519),
+            pre_signatures_to_create_in_advance: 2,
+            max_queue_size: 30,
+            min_pre_signatures_to_create: None,
+            max_pre_signatures_to_create,
+        };
+        let (burst, creation_rounds) = (30, 5);
+
+        // Without a maximum in the registry, the pipeline has a fixed size, so the burst
+        // is served two requests per `creation_rounds`.
+        let fixed = rounds_to_serve_burst(&mut rng, key_config(None), burst, creation_rounds);
+        assert!(fixed >= u64::from(burst) / 2 * creation_rounds);
+
+        // Following the demand, the whole burst is served within a few creation rounds.
+        let demand_driven =
+            rounds_to_serve_burst(&mut rng, key_config(Some(30)), burst, creation_rounds);
+        assert!(
+            demand_driven <= 3 * creation_rounds,
+            "demand-driven: {demand_driven} rounds"
+        );
+        assert!(demand_driven < fixed);
+    }
 }
@@ -1962,6 +1962,7 @@ mod tests {
                     batch_time: UNIX_EPOCH,
                     matched_pre_signature: Some((pre_sig_id, req_id.height)),
                     nonce: Some([2; 32]),
+                    batch: None,
                 };
                 let sig_inputs = generate_tecdsa_protocol_inputs(
                     &env,
@@ -2099,6 +2100,7 @@ mod tests {
                     batch_time: UNIX_EPOCH,
                     matched_pre_signature: Some((pre_sig_id, req_id.height)),
                     nonce: Some([2; 32]),
+                    batch: None,
                 };
                 let sig_inputs = generate_tschnorr_protocol_inputs(
                     &env,
@@ -112,6 +112,7 @@ pub fn fake_signature_request_context(
         pseudo_random_id,
         matched_pre_signature: None,
         nonce: None,
+        batch: None,
     }
 }
 
@@ -128,6 +129,7 @@ pub fn fake_signature_request_context_with_pre_sig(
         pseudo_random_id: [id; 32],
         matched_pre_signature: pre_signature.map(|pid| (pid, Height::from(1))),
         nonce: None,
+        batch: None,
     };
     (CallbackId::from(id as u64), context)
 }
@@ -163,6 +165,7 @@ pub fn fake_signature_request_context_from_id(
         pseudo_random_id: request_id.pseudo_random_id,
         matched_pre_signature: Some((pre_sig_id, height)),
         nonce: Some([0; 32]),
+        batch: None,
     };
     (callback_id, context)
 }
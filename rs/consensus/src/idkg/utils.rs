@@ -777,6 +777,8 @@ mod tests {
                     ),
                     pre_signatures_to_create_in_advance: 1,
                     max_queue_size: 3,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 ..ChainKeyConfig::default()
             };
@@ -800,6 +802,8 @@ mod tests {
                 ),
                 pre_signatures_to_create_in_advance: 1,
                 max_queue_size: 3,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             };
             let key_config_2 = KeyConfig {
                 key_id: MasterPublicKeyId::Schnorr(
@@ -807,6 +811,8 @@ mod tests {
                 ),
                 pre_signatures_to_create_in_advance: 1,
                 max_queue_size: 3,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             };
 
             let chain_key_config_with_two_keys = ChainKeyConfig {
@@ -840,6 +846,8 @@ mod tests {
                     ),
                     pre_signatures_to_create_in_advance: 0,
                     max_queue_size: 3,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 ..ChainKeyConfig::default()
             };
@@ -68,6 +68,8 @@ pub fn setup_subnet<R: Rng + CryptoRng>(
                     key_id: key_id.clone(),
                     pre_signatures_to_create_in_advance: 4,
                     max_queue_size: 40,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 })
                 .collect(),
             ..ChainKeyConfig::default()
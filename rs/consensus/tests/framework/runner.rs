@@ -193,6 +193,7 @@ impl<'a> ConsensusRunner<'a> {
             certification_crypto,
             deps.state_manager.clone(),
             deps.consensus_pool.read().unwrap().get_cache(),
+            deps.consensus_pool.read().unwrap().get_block_cache(),
             deps.metrics_registry.clone(),
             replica_logger.clone(),
             watch::channel(Height::from(0)).0,
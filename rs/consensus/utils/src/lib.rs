@@ -893,6 +893,7 @@ mod tests {
             pseudo_random_id: [0; 32],
             matched_pre_signature: pre_signature_id.map(|qid| (qid, Height::from(0))),
             nonce: None,
+            batch: None,
             batch_time: UNIX_EPOCH,
         }
     }
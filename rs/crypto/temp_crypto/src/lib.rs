@@ -1064,11 +1064,14 @@ impl EcdsaSubnetConfig {
                         }),
                         pre_signatures_to_create_in_advance: Some(1),
                         max_queue_size: Some(20),
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: None,
                     }],
                     signature_request_timeout_ns: None,
                     idkg_key_rotation_period_ms: key_rotation_period
                         .map(|key_rotation_period| key_rotation_period.as_millis() as u64),
                 }),
+                idle_block_config: None,
             },
         }
     }
@@ -2,7 +2,7 @@
 
 use crate::message::{msg_stream_from_file, Message};
 use hex::encode;
-use ic_config::{subnet_config::SubnetConfig, Config};
+use ic_config::{flag_status::FlagStatus, subnet_config::SubnetConfig, Config};
 use ic_crypto_test_utils_ni_dkg::dummy_initial_dkg_transcript_with_master_key;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::{ErrorCode, UserError};
@@ -12,6 +12,7 @@ use ic_interfaces::{
     execution_environment::{IngressHistoryReader, QueryExecutionError},
     messaging::MessageRouting,
 };
+use ic_management_canister_types::{FetchCanisterProfileResponse, Payload};
 use ic_messaging::MessageRoutingImpl;
 use ic_metrics::MetricsRegistry;
 use ic_protobuf::registry::{
@@ -49,7 +50,7 @@ use rand::SeedableRng;
 use slog::{Drain, Logger};
 use std::collections::BTreeMap;
 use std::fs::OpenOptions;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::{thread::sleep, time::Duration};
 use tower::util::ServiceExt;
@@ -69,6 +70,8 @@ pub struct DrunOptions {
     pub log_file: Option<PathBuf>,
     pub instruction_limit: Option<u64>,
     pub subnet_type: SubnetType,
+    /// Enables canister profiling on the subnet and for all created canisters.
+    pub canister_profiling: bool,
 }
 
 /// Deliver a single message to the Message Routing layer
@@ -168,6 +171,7 @@ pub async fn run_drun(uo: DrunOptions) -> Result<(), String> {
         log_file,
         instruction_limit,
         subnet_type,
+        canister_profiling,
     } = uo;
     // Hardcoded magic values to create a ReplicaConfig that parses.
     let mut subnet_config = SubnetConfig::new(subnet_type);
@@ -185,6 +189,13 @@ pub async fn run_drun(uo: DrunOptions) -> Result<(), String> {
         cfg.hypervisor.max_query_call_graph_instructions = instruction_limit;
     }
 
+    if canister_profiling {
+        cfg.hypervisor
+            .embedders_config
+            .feature_flags
+            .canister_profiling = FlagStatus::Enabled;
+    }
+
     let subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(0));
     let root_subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(1));
     let replica_config = ReplicaConfig {
@@ -192,7 +203,7 @@ pub async fn run_drun(uo: DrunOptions) -> Result<(), String> {
         subnet_id,
     };
 
-    let msg_stream = msg_stream_from_file(&msg_filename)?;
+    let msg_stream = msg_stream_from_file(&msg_filename, canister_profiling)?;
     let log = match log_file {
         Some(log_file) => setup_logger(log_file),
         None => slog::Logger::root(slog::Discard, slog::o!()),
@@ -305,6 +316,23 @@ pub async fn run_drun(uo: DrunOptions) -> Result<(), String> {
                     extra_batches,
                 );
             }
+
+            Message::Profile(q, output_file) => {
+                let (_ni_dkg_transcript, secret_key) =
+                    dummy_initial_dkg_transcript_with_master_key(&mut StdRng::seed_from_u64(42));
+                certify_latest_state_helper(
+                    state_manager.clone(),
+                    &secret_key,
+                    replica_config.subnet_id,
+                );
+                let query_result = match query_handler.clone().oneshot((q, None)).await.unwrap() {
+                    Ok((result, _)) => result,
+                    Err(QueryExecutionError::CertifiedStateUnavailable) => {
+                        panic!("Certified state unavailable for query call.")
+                    }
+                };
+                print_profile_result(query_result, &output_file);
+            }
         }
     }
     Ok(())
@@ -320,6 +348,28 @@ fn print_query_result(res: Result<WasmResult, UserError>) {
     }
 }
 
+fn print_profile_result(res: Result<WasmResult, UserError>, output_file: &Path) {
+    let profile = match res {
+        Ok(WasmResult::Reply(payload)) => FetchCanisterProfileResponse::decode(&payload),
+        Ok(WasmResult::Reject(e)) => {
+            println!("profile Reject: {}", e);
+            return;
+        }
+        Err(e) => Err(e),
+    };
+    match profile {
+        Ok(profile) => match std::fs::write(output_file, profile.to_folded_stacks()) {
+            Ok(()) => println!("profile Ok: written to {}", output_file.display()),
+            Err(e) => println!(
+                "profile Err: failed to write {}: {}",
+                output_file.display(),
+                e
+            ),
+        },
+        Err(e) => println!("profile Err: {}", e),
+    }
+}
+
 fn print_ingress_result(message_id: &MessageId, ingress_hist_reader: &dyn IngressHistoryReader) {
     let status = (ingress_hist_reader.get_latest_status())(message_id);
     print!("ingress ");
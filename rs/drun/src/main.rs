@@ -17,6 +17,7 @@ const ARG_MESSAGES: &str = "messages";
 const ARG_EXTRA_BATCHES: &str = "extra-batches";
 const ARG_INSTRUCTION_LIMIT: &str = "instruction-limit";
 const ARG_SUBNET_TYPE: &str = "subnet-type";
+const ARG_CANISTER_PROFILING: &str = "canister-profiling";
 
 fn main() -> Result<(), String> {
     // Check if `drun` is running in the canister sandbox mode where it waits
@@ -96,6 +97,7 @@ async fn drun_main() -> Result<(), String> {
             log_file,
             instruction_limit,
             subnet_type,
+            canister_profiling: matches.is_present(ARG_CANISTER_PROFILING),
         };
         run_drun(uo).await
     })
@@ -159,5 +161,11 @@ fn get_arg_matches() -> ArgMatches {
                 .value_name("Subnet Type")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new(ARG_CANISTER_PROFILING)
+                .long(ARG_CANISTER_PROFILING)
+                .help("Enable function-level execution profiling for all created canisters.")
+                .takes_value(false),
+        )
         .get_matches()
 }
@@ -3,7 +3,8 @@ use super::CanisterId;
 use hex::decode;
 use ic_execution_environment::execution::upgrade::ENHANCED_ORTHOGONAL_PERSISTENCE_SECTION;
 use ic_management_canister_types::{
-    self as ic00, CanisterInstallModeV2, CanisterUpgradeOptions, Payload, WasmMemoryPersistence,
+    self as ic00, CanisterInstallModeV2, CanisterSettingsArgsBuilder, CanisterUpgradeOptions,
+    FetchCanisterProfileRequest, Payload, WasmMemoryPersistence,
 };
 use ic_types::{
     messages::{Query, QuerySource, SignedIngress},
@@ -15,6 +16,7 @@ use std::{
     fmt,
     fs::File,
     io::{self, Read},
+    path::PathBuf,
     str::Chars,
     string::FromUtf8Error,
 };
@@ -25,6 +27,9 @@ pub(crate) enum Message {
     Query(Query),
     Install(SignedIngress),
     Create(SignedIngress),
+    /// Fetches the execution profile of a canister and writes it to the given
+    /// file in the folded stacks format.
+    Profile(Query, PathBuf),
 }
 
 #[derive(Debug)]
@@ -126,6 +131,7 @@ impl<R: Read> Iterator for LineIterator<R> {
 
 pub(crate) fn msg_stream_from_file(
     filename: &str,
+    canister_profiling: bool,
 ) -> Result<impl Iterator<Item = Result<Message, String>>, String> {
     let f = File::open(filename).map_err(|e| e.to_string())?;
     let line_iterator = LineIterator::new(f);
@@ -138,14 +144,13 @@ pub(crate) fn msg_stream_from_file(
             _ => true,
         })
         .map(|(i, line)| match line {
-            Ok(line) => {
-                parse_message(&line, i as u64).map_err(|e| format!("Line {}: {}", i + 1, e))
-            }
+            Ok(line) => parse_message(&line, i as u64, canister_profiling)
+                .map_err(|e| format!("Line {}: {}", i + 1, e)),
             Err(e) => Err(format!("Error while reading line {}: {}", i, e)),
         }))
 }
 
-fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
+fn parse_message(s: &str, nonce: u64, canister_profiling: bool) -> Result<Message, String> {
     let s = s.trim_end();
     let tokens: Vec<&str> = s.splitn(4, char::is_whitespace).collect();
 
@@ -178,7 +183,21 @@ fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
             method_name: validate_method_name(method_name)?,
             method_payload: parse_octet_string(payload)?,
         })),
-        ["create"] => parse_create(nonce),
+        ["create"] => parse_create(nonce, canister_profiling),
+        ["profile", canister_id, output_file] => Ok(Message::Profile(
+            Query {
+                source: QuerySource::User {
+                    user_id: UserId::from(PrincipalId::new_anonymous()),
+                    ingress_expiry: expiry_time_from_now().as_nanos_since_unix_epoch(),
+                    nonce: Some(nonce.to_le_bytes().to_vec()),
+                },
+                receiver: ic00::IC_00,
+                method_name: ic00::QueryMethod::FetchCanisterProfile.to_string(),
+                method_payload: FetchCanisterProfileRequest::new(parse_canister_id(canister_id)?)
+                    .encode(),
+            },
+            PathBuf::from(output_file),
+        )),
         ["install", canister_id, wasm_file, payload] => {
             parse_install(nonce, canister_id, payload, wasm_file, "install")
         }
@@ -206,13 +225,21 @@ fn parse_canister_id(canister_id: &str) -> Result<CanisterId, String> {
     }
 }
 
-fn parse_create(nonce: u64) -> Result<Message, String> {
+fn parse_create(nonce: u64, canister_profiling: bool) -> Result<Message, String> {
     use ic_test_utilities_types::messages::SignedIngressBuilder;
 
+    let mut args = ic00::ProvisionalCreateCanisterWithCyclesArgs::new(None, None);
+    if canister_profiling {
+        args.settings = Some(
+            CanisterSettingsArgsBuilder::new()
+                .with_profiling(true)
+                .build(),
+        );
+    }
     let signed_ingress = SignedIngressBuilder::new()
         .method_name(ic00::Method::ProvisionalCreateCanisterWithCycles)
         .canister_id(ic00::IC_00)
-        .method_payload(ic00::ProvisionalCreateCanisterWithCyclesArgs::new(None, None).encode())
+        .method_payload(args.encode())
         .nonce(nonce)
         .build();
 
@@ -416,7 +443,7 @@ mod tests {
             "ingress {} write \"payload \\x0a\\b00010001\"",
             APP_CANISTER_URL
         );
-        let parsed_message = parse_message(s, 0).unwrap();
+        let parsed_message = parse_message(s, 0, false).unwrap();
         let expiry_time = match &parsed_message {
             Message::Ingress(signed_ingress) => signed_ingress.expiry_time(),
             _ => panic!(
@@ -439,7 +466,7 @@ mod tests {
     #[test]
     fn test_parse_message_hex_payload_succeeds() {
         let s = &format!("ingress {} write 0x010203", APP_CANISTER_URL);
-        let parsed_message = parse_message(s, 0).unwrap();
+        let parsed_message = parse_message(s, 0, false).unwrap();
         let expiry_time = match &parsed_message {
             Message::Ingress(signed_ingress) => signed_ingress.expiry_time(),
             _ => panic!(
@@ -460,7 +487,7 @@ mod tests {
 
         let s = &format!("query {} read 0x010203", APP_CANISTER_URL);
         let nonce: u64 = 0;
-        let parsed_message = parse_message(s, 0).unwrap();
+        let parsed_message = parse_message(s, 0, false).unwrap();
         let ingress_expiry = match &parsed_message {
             Message::Query(query) => match query.source {
                 QuerySource::User { ingress_expiry, .. } => ingress_expiry,
@@ -484,28 +511,49 @@ mod tests {
         assert_eq!(expected, parsed_message);
     }
 
+    #[test]
+    fn test_parse_message_profile_succeeds() {
+        let s = &format!("profile {} profile.folded", APP_CANISTER_URL);
+        let (query, output_file) = match parse_message(s, 0, true).unwrap() {
+            Message::Profile(query, output_file) => (query, output_file),
+            other => panic!(
+                "parse_message() returned an unexpected message type: {:?}",
+                other
+            ),
+        };
+        assert_eq!(query.receiver, ic00::IC_00);
+        assert_eq!(query.method_name, "fetch_canister_profile");
+        assert_eq!(
+            FetchCanisterProfileRequest::decode(&query.method_payload)
+                .unwrap()
+                .get_canister_id(),
+            canister_test_id(APP_CANISTER_ID)
+        );
+        assert_eq!(output_file, PathBuf::from("profile.folded"));
+    }
+
     #[test]
     fn test_parse_message_invalid_escapes_fails() {
         let s = &format!("query {} read \"\\xzz\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, false).is_err());
 
         let s = &format!("query {} read \"\\b01\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, false).is_err());
 
         let s = &format!("query {} read \"\\x1\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, false).is_err());
 
         let s = &format!("query {} read \"\\b2\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, false).is_err());
     }
 
     #[test]
     fn test_illegal_method_name_must_fail() {
         let s = &format!("query {} 0read \"\\xzz\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, false).is_err());
 
         let s = &format!("query {} üread \"\\xzz\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, false).is_err());
     }
 
     #[test]
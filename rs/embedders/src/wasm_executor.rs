@@ -15,7 +15,7 @@ use wasmtime::Module;
 
 use crate::wasmtime_embedder::CanisterMemoryType;
 use crate::{
-    wasm_utils::{compile, decoding::decode_wasm, Segments, WasmImportsDetails},
+    wasm_utils::{compile_with_profiling, decoding::decode_wasm, Segments, WasmImportsDetails},
     wasmtime_embedder::WasmtimeInstance,
     CompilationCache, CompilationResult, SerializedModule, WasmExecutionInput, WasmtimeEmbedder,
 };
@@ -52,11 +52,14 @@ pub trait WasmExecutor: Send + Sync {
         execution_state: &ExecutionState,
     ) -> (Option<CompilationResult>, WasmExecutionResult);
 
+    /// `canister_profiling` indicates whether the canister has profiling
+    /// enabled, in which case the module is compiled with profiling hooks.
     fn create_execution_state(
         &self,
         canister_module: CanisterModule,
         canister_root: PathBuf,
         canister_id: CanisterId,
+        canister_profiling: bool,
         compilation_cache: Arc<CompilationCache>,
     ) -> HypervisorResult<(ExecutionState, NumInstructions, Option<CompilationResult>)>;
 }
@@ -193,7 +196,11 @@ impl WasmExecutor for WasmExecutorImpl {
             cache: embedder_cache,
             serialized_module,
             compilation_result,
-        } = match self.get_embedder_cache(&execution_state.wasm_binary, compilation_cache) {
+        } = match self.get_embedder_cache(
+            &execution_state.wasm_binary,
+            sandbox_safe_system_state.canister_profiling(),
+            compilation_cache,
+        ) {
             Ok(cache_result) => cache_result,
             Err(err) => {
                 return (
@@ -271,6 +278,7 @@ impl WasmExecutor for WasmExecutorImpl {
         canister_module: CanisterModule,
         canister_root: PathBuf,
         canister_id: CanisterId,
+        canister_profiling: bool,
         compilation_cache: Arc<CompilationCache>,
     ) -> HypervisorResult<(ExecutionState, NumInstructions, Option<CompilationResult>)> {
         // Compile Wasm binary and cache it.
@@ -279,7 +287,7 @@ impl WasmExecutor for WasmExecutorImpl {
             cache: embedder_cache,
             serialized_module: Some(serialized_module),
             compilation_result,
-        } = self.get_embedder_cache(&wasm_binary, compilation_cache)?
+        } = self.get_embedder_cache(&wasm_binary, canister_profiling, compilation_cache)?
         else {
             panic!("Newly created WasmBinary must be compiled or deserialized.")
         };
@@ -365,9 +373,12 @@ impl WasmExecutorImpl {
         }
     }
 
+    /// Modules with profiling hooks are neither looked up in nor added to the
+    /// `compilation_cache`, which is shared by all canisters.
     fn get_embedder_cache(
         &self,
         wasm_binary: &WasmBinary,
+        canister_profiling: bool,
         compilation_cache: Arc<CompilationCache>,
     ) -> HypervisorResult<CacheLookup> {
         let mut guard = wasm_binary.embedder_cache.lock().unwrap();
@@ -378,7 +389,12 @@ impl WasmExecutorImpl {
                 compilation_result: None,
             })
         } else {
-            match compilation_cache.get(&wasm_binary.binary) {
+            let cached = if canister_profiling {
+                None
+            } else {
+                compilation_cache.get(&wasm_binary.binary)
+            };
+            match cached {
                 Some(Ok(serialized_module)) => {
                     let instance_pre = self
                         .wasm_embedder
@@ -405,12 +421,18 @@ impl WasmExecutorImpl {
                         self.wasm_embedder.config().wasm_max_size,
                         wasm_binary.binary.to_shared_vec(),
                     )?);
-                    let (cache, result) = compile(&self.wasm_embedder, decoded_wasm.as_ref());
+                    let (cache, result) = compile_with_profiling(
+                        &self.wasm_embedder,
+                        decoded_wasm.as_ref(),
+                        canister_profiling,
+                    );
                     *guard = Some(cache.clone());
                     let (compilation_result, serialized_module) = result?;
                     let serialized_module = Arc::new(serialized_module);
-                    compilation_cache
-                        .insert(&wasm_binary.binary, Ok(Arc::clone(&serialized_module)));
+                    if !canister_profiling {
+                        compilation_cache
+                            .insert(&wasm_binary.binary, Ok(Arc::clone(&serialized_module)));
+                    }
                     Ok(CacheLookup {
                         cache,
                         serialized_module: Some(serialized_module),
@@ -3,7 +3,7 @@ use std::{
     time::Instant,
 };
 
-use ic_config::embedders::Config as EmbeddersConfig;
+use ic_config::{embedders::Config as EmbeddersConfig, flag_status::FlagStatus};
 use ic_interfaces::execution_environment::HypervisorResult;
 use ic_replicated_state::{
     canister_state::{execution_state::WasmMetadata, WASM_PAGE_SIZE_IN_BYTES},
@@ -199,8 +199,14 @@ pub struct InstrumentationOutput {
 fn validate_and_instrument(
     wasm: &BinaryEncodedWasm,
     config: &EmbeddersConfig,
+    canister_profiling: bool,
 ) -> HypervisorResult<(WasmValidationDetails, InstrumentationOutput)> {
     let (wasm_validation_details, module) = validate_wasm_binary(wasm, config)?;
+    // Only canisters that enabled profiling pay for the profiling hooks.
+    let canister_profiling = match config.feature_flags.canister_profiling {
+        FlagStatus::Enabled if canister_profiling => FlagStatus::Enabled,
+        _ => FlagStatus::Disabled,
+    };
     let instrumentation_output = instrument(
         module,
         config.cost_to_compile_wasm_instruction,
@@ -211,27 +217,30 @@ fn validate_and_instrument(
         config.dirty_page_overhead,
         config.max_wasm_memory_size,
         config.max_stable_memory_size,
+        canister_profiling,
     )?;
     Ok((wasm_validation_details, instrumentation_output))
 }
 
 /// Only exposed for tests that need to inspect the instrumented wasm or
-/// validation details.
+/// validation details. The profiling hooks are injected if canister
+/// profiling is enabled in the config.
 #[doc(hidden)]
 pub fn validate_and_instrument_for_testing(
     embedder: &WasmtimeEmbedder,
     wasm: &BinaryEncodedWasm,
 ) -> HypervisorResult<(WasmValidationDetails, InstrumentationOutput)> {
-    validate_and_instrument(wasm, embedder.config())
+    validate_and_instrument(wasm, embedder.config(), true)
 }
 
 fn compile_inner(
     embedder: &WasmtimeEmbedder,
     wasm: &BinaryEncodedWasm,
+    canister_profiling: bool,
 ) -> HypervisorResult<(InstancePre<StoreData>, CompilationResult, SerializedModule)> {
     let timer = Instant::now();
     let (wasm_validation_details, instrumentation_output) =
-        validate_and_instrument(wasm, embedder.config())?;
+        validate_and_instrument(wasm, embedder.config(), canister_profiling)?;
     let module = embedder.compile(&instrumentation_output.binary)?;
     let instance_pre = embedder.pre_instantiate(&module)?;
     let largest_function_instruction_count =
@@ -257,7 +266,24 @@ pub fn compile(
     EmbedderCache,
     HypervisorResult<(CompilationResult, SerializedModule)>,
 ) {
-    let (cache, result) = match compile_inner(embedder, wasm) {
+    compile_with_profiling(embedder, wasm, false)
+}
+
+/// Same as [`compile`], but additionally injects the profiling hooks if
+/// `canister_profiling` is set (i.e., the canister enabled profiling) and
+/// canister profiling is enabled in the embedder config.
+///
+/// Modules compiled with profiling hooks must not be shared with canisters
+/// that do not have profiling enabled.
+pub fn compile_with_profiling(
+    embedder: &WasmtimeEmbedder,
+    wasm: &BinaryEncodedWasm,
+    canister_profiling: bool,
+) -> (
+    EmbedderCache,
+    HypervisorResult<(CompilationResult, SerializedModule)>,
+) {
+    let (cache, result) = match compile_inner(embedder, wasm, canister_profiling) {
         Ok((module, result, serialized)) => (Ok(module), Ok((result, serialized))),
         Err(err) => (Err(err.clone()), Err(err)),
     };
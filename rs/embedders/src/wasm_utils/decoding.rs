@@ -1,7 +1,9 @@
 use ic_types::NumBytes;
 use ic_wasm_types::{BinaryEncodedWasm, WasmValidationError};
+use std::collections::BTreeMap;
 use std::io::Read;
 use std::sync::Arc;
+use wasmparser::{Name, NameSectionReader, Parser, Payload};
 
 enum WasmEncoding {
     Wasm,
@@ -111,3 +113,32 @@ pub fn decode_wasm(
         }
     }
 }
+
+/// Returns the function names from the `name` custom section of the given
+/// (uncompressed) Wasm module, keyed by function index.
+///
+/// Names are best effort: a missing or malformed `name` section results in
+/// fewer or no names rather than an error.
+pub fn decode_function_names(wasm: &BinaryEncodedWasm) -> BTreeMap<u32, String> {
+    let mut names = BTreeMap::new();
+    for payload in Parser::new(0).parse_all(wasm.as_slice()) {
+        let Ok(payload) = payload else {
+            break;
+        };
+        let Payload::CustomSection(reader) = payload else {
+            continue;
+        };
+        if reader.name() != "name" {
+            continue;
+        }
+        for subsection in NameSectionReader::new(reader.data(), reader.data_offset()) {
+            let Ok(Name::Function(function_names)) = subsection else {
+                continue;
+            };
+            for naming in function_names.into_iter().flatten() {
+                names.insert(naming.index, naming.name.to_string());
+            }
+        }
+    }
+    names
+}
@@ -98,6 +98,18 @@
 //! bound by the length of the longest execution path consisting of
 //! non-reentrant basic blocks.
 //!
+//! A tail call (`return_call`, `return_call_indirect` or `return_call_ref`)
+//! ends its basic block like `return` does: its cost is charged together with
+//! the rest of the block before the call, and the callee charges its own body
+//! on entry. Hence a tail call is metered the same as a regular call followed
+//! by a `return`, except for the cost of the `return` itself.
+//!
+//! Similarly, the conditional branches of the typed function references
+//! proposal (`br_on_null` and `br_on_non_null`) end their basic block like
+//! `br_if`. Function references never outlive a message execution because
+//! globals of reference types are rejected and tables cannot be modified, so
+//! they need no special treatment in snapshots and upgrades.
+//!
 //! # Wasm-native stable memory
 //!
 //! Two additional memories are inserted for stable memory. One is the actual
@@ -109,9 +121,43 @@
 //! (memory (export "stable_memory_bytemap") i32 (i64.const STABLE_BYTEMAP_SIZE) (i64.const STABLE_BYTEMAP_SIZE))
 //! ```
 //!
+//! # Canister profiling
+//!
+//! If canister profiling is enabled on the subnet and the canister enabled
+//! profiling in its settings, two more functions are imported after all other
+//! imports:
+//!
+//! ```wasm
+//! (import "__" "profile_enter" (func (param i32)))
+//! (import "__" "profile_exit" (func (param i32)))
+//! ```
+//!
+//! Every function defined in the original module gets a wrapper with the same
+//! type that reports entering and leaving the function, identified by its index
+//! in the original module:
+//!
+//! ```wasm
+//! (func (;wrapper;) (param i32 i64)
+//!   i32.const 7        # the index of the function in the original module
+//!   call $profile_enter
+//!   local.get 0
+//!   local.get 1
+//!   call 7             # the (shifted) index of the original function
+//!   i32.const 7
+//!   call $profile_exit)
+//! ```
+//!
+//! All references to the original function (calls, exports, tables, etc) are
+//! redirected to the wrapper. The wrappers themselves are not metered, but
+//! every call of `profile_enter` and `profile_exit` is charged a fixed number
+//! of instructions like a System API call.
+//! Note that a tail call to a profiled function only replaces the frame of the
+//! caller with the frame of the wrapper, so deep tail recursion can exhaust
+//! the Wasm stack while profiling is enabled.
+//!
 
 use super::system_api_replacements::replacement_functions;
-use super::validation::API_VERSION_IC0;
+use super::validation::{eval_const_expr, ConstValue, API_VERSION_IC0};
 use super::{InstrumentationOutput, Segments, SystemApiFunc};
 use ic_config::embedders::MeteringType;
 use ic_config::flag_status::FlagStatus;
@@ -491,6 +537,9 @@ pub fn instruction_to_cost_wasm32(i: &Operator) -> u64 {
         // The cost is adjusted to 5 and 10 after benchmarking with real canisters.
         Operator::Call { .. } | Operator::ReturnCall { .. } => 5,
         Operator::CallIndirect { .. } | Operator::ReturnCallIndirect { .. } => 10,
+        // A call through a typed function reference is an indirect call without
+        // the table lookup and signature check, but with a null check.
+        Operator::CallRef { .. } | Operator::ReturnCallRef { .. } => 10,
 
         // Return, drop, unreachable and nop instructions are of cost 1.
         Operator::Return { .. } | Operator::Drop | Operator::Unreachable | Operator::Nop => 1,
@@ -514,6 +563,8 @@ pub fn instruction_to_cost_wasm32(i: &Operator) -> u64 {
         // Checking for null references is the same as branching
         //but with an added complexity of memory manipulation. Validated in benchmarks.
         Operator::RefIsNull { .. } => 5,
+        // A null check followed by a trap or a branch.
+        Operator::RefAsNonNull | Operator::BrOnNull { .. } | Operator::BrOnNonNull { .. } => 5,
         // Function pointers are heavy because they get
         // translated to memory manipulation. Validated in benchmarks.
         Operator::RefFunc { .. } => 130,
@@ -768,6 +819,8 @@ const TRY_GROW_WASM_MEMORY_FUN_NAME: &str = "try_grow_wasm_memory";
 const TRY_GROW_STABLE_MEMORY_FUN_NAME: &str = "try_grow_stable_memory";
 const INTERNAL_TRAP_FUN_NAME: &str = "internal_trap";
 const STABLE_READ_FIRST_ACCESS_NAME: &str = "stable_read_first_access";
+const PROFILE_ENTER_FUN_NAME: &str = "profile_enter";
+const PROFILE_EXIT_FUN_NAME: &str = "profile_exit";
 const TABLE_STR: &str = "table";
 pub(crate) const INSTRUCTIONS_COUNTER_GLOBAL_NAME: &str = "canister counter_instructions";
 pub(crate) const DIRTY_PAGES_COUNTER_GLOBAL_NAME: &str = "canister counter_dirty_pages";
@@ -831,7 +884,7 @@ fn mutate_function_indices(module: &mut Module, f: impl Fn(u32) -> u32) {
                 }
             }
             ic_wasm_transform::ElementItems::ConstExprs { ty: _, exprs } => {
-                for op in exprs {
+                for op in exprs.iter_mut().flatten() {
                     mutate_instruction(&f, op)
                 }
             }
@@ -839,7 +892,9 @@ fn mutate_function_indices(module: &mut Module, f: impl Fn(u32) -> u32) {
     }
 
     for global in &mut module.globals {
-        mutate_instruction(&f, &mut global.init_expr)
+        for op in &mut global.init_expr {
+            mutate_instruction(&f, op)
+        }
     }
 
     for data_segment in &mut module.data {
@@ -849,7 +904,9 @@ fn mutate_function_indices(module: &mut Module, f: impl Fn(u32) -> u32) {
                 memory_index: _,
                 offset_expr,
             } => {
-                mutate_instruction(&f, offset_expr);
+                for op in offset_expr {
+                    mutate_instruction(&f, op);
+                }
             }
         }
     }
@@ -986,8 +1043,15 @@ pub(super) fn instrument(
     dirty_page_overhead: NumInstructions,
     max_wasm_memory_size: NumBytes,
     max_stable_memory_size: NumBytes,
+    canister_profiling: FlagStatus,
 ) -> Result<InstrumentationOutput, WasmInstrumentationError> {
     let main_memory_type = main_memory_type(&module);
+    let num_original_imported_functions = module
+        .imports
+        .iter()
+        .filter(|imp| matches!(imp.ty, TypeRef::Func(_)))
+        .count() as u32;
+    let num_original_functions = module.functions.len();
     let stable_memory_index;
     let mut module = inject_helper_functions(module, wasm_native_stable_memory, main_memory_type);
     module = export_table(module);
@@ -1101,6 +1165,14 @@ pub(super) fn instrument(
         )
     }
 
+    if canister_profiling == FlagStatus::Enabled {
+        inject_profiling(
+            &mut module,
+            num_original_imported_functions,
+            num_original_functions,
+        )?;
+    }
+
     let exported_functions = module
         .exports
         .iter()
@@ -1138,12 +1210,8 @@ pub(super) fn instrument(
         wasm_instruction_count += body.instructions.len() as u64;
     }
     for global in &module.globals {
-        // Each global has a single instruction initializer and an `End`
-        // instruction will be added during encoding.
-        // We statically assert this is the case to ensure this calculation is
-        // adjusted if we add support for longer initialization expressions.
-        let _: &Operator = &global.init_expr;
-        wasm_instruction_count += 2;
+        // An `End` instruction will be added to the initializer during encoding.
+        wasm_instruction_count += global.init_expr.len() as u64 + 1;
     }
 
     let result = module.encode().map_err(|err| {
@@ -1216,6 +1284,92 @@ fn replace_system_api_functions(
     });
 }
 
+/// Wraps the first `num_original_functions` defined functions of the module
+/// (i.e. the functions of the original module) into functions that report
+/// entering and leaving them to the `profile_enter` and `profile_exit` imports.
+/// See the module documentation for details.
+fn inject_profiling(
+    module: &mut Module<'_>,
+    num_original_imported_functions: u32,
+    num_original_functions: usize,
+) -> Result<(), WasmInstrumentationError> {
+    let profile_type_idx = add_func_type(module, FuncType::new([ValType::I32], []));
+    let num_imported_functions = module
+        .imports
+        .iter()
+        .filter(|imp| matches!(imp.ty, TypeRef::Func(_)))
+        .count() as u32;
+    let profile_enter_fn = num_imported_functions;
+    let profile_exit_fn = num_imported_functions + 1;
+    module.imports.push(Import {
+        module: INSTRUMENTED_FUN_MODULE,
+        name: PROFILE_ENTER_FUN_NAME,
+        ty: TypeRef::Func(profile_type_idx),
+    });
+    module.imports.push(Import {
+        module: INSTRUMENTED_FUN_MODULE,
+        name: PROFILE_EXIT_FUN_NAME,
+        ty: TypeRef::Func(profile_type_idx),
+    });
+
+    // Shift all defined functions by the two new imports and redirect all
+    // references to the original functions to their wrappers, which are
+    // appended after all other functions.
+    let first_defined_fn = num_imported_functions;
+    let end_original_fn = first_defined_fn + num_original_functions as u32;
+    let first_wrapper_fn = profile_exit_fn + 1 + module.functions.len() as u32;
+    mutate_function_indices(module, |idx| {
+        if idx < first_defined_fn {
+            idx
+        } else if idx < end_original_fn {
+            first_wrapper_fn + (idx - first_defined_fn)
+        } else {
+            idx + 2
+        }
+    });
+
+    for i in 0..num_original_functions {
+        let type_idx = module.functions[i];
+        let num_params = match &module.types[type_idx as usize].composite_type {
+            CompositeType::Func(ty) => ty.params().len() as u32,
+            other => {
+                return Err(WasmInstrumentationError::InvalidFunctionType(format!(
+                    "Function has type which is not a function type. Found type: {:?}",
+                    other
+                )));
+            }
+        };
+        let original_fn_idx = num_original_imported_functions + i as u32;
+        let mut instructions = vec![
+            Operator::I32Const {
+                value: original_fn_idx as i32,
+            },
+            Operator::Call {
+                function_index: profile_enter_fn,
+            },
+        ];
+        instructions.extend((0..num_params).map(|local_index| Operator::LocalGet { local_index }));
+        instructions.extend([
+            Operator::Call {
+                function_index: profile_exit_fn + 1 + i as u32,
+            },
+            Operator::I32Const {
+                value: original_fn_idx as i32,
+            },
+            Operator::Call {
+                function_index: profile_exit_fn,
+            },
+            Operator::End,
+        ]);
+        module.functions.push(type_idx);
+        module.code_sections.push(ic_wasm_transform::Body {
+            locals: vec![],
+            instructions,
+        });
+    }
+    Ok(())
+}
+
 // Helper function used by instrumentation to export additional symbols.
 //
 // Returns the new module or panics in debug mode if a symbol is not reserved.
@@ -1416,7 +1570,7 @@ fn export_additional_symbols<'a>(
             content_type: ValType::I64,
             mutable: true,
         },
-        init_expr: Operator::I64Const { value: 0 },
+        init_expr: vec![Operator::I64Const { value: 0 }],
     });
 
     if wasm_native_stable_memory == FlagStatus::Enabled {
@@ -1426,7 +1580,7 @@ fn export_additional_symbols<'a>(
                 content_type: ValType::I64,
                 mutable: true,
             },
-            init_expr: Operator::I64Const { value: 0 },
+            init_expr: vec![Operator::I64Const { value: 0 }],
         });
         // push the accessed page counter
         module.globals.push(Global {
@@ -1434,7 +1588,7 @@ fn export_additional_symbols<'a>(
                 content_type: ValType::I64,
                 mutable: true,
             },
-            init_expr: Operator::I64Const { value: 0 },
+            init_expr: vec![Operator::I64Const { value: 0 }],
         });
     }
 
@@ -1891,7 +2045,12 @@ fn injections(code: &[Operator], mem_type: WasmMemoryType) -> Vec<InjectionPoint
                     InjectionPoint::new_static_cost(position + 1, Scope::NonReentrantBlockStart, 0);
             }
             // End of a code block but still more code left.
-            Else | Br { .. } | BrIf { .. } | BrTable { .. } => {
+            Else
+            | Br { .. }
+            | BrIf { .. }
+            | BrTable { .. }
+            | BrOnNull { .. }
+            | BrOnNonNull { .. } => {
                 res.push(curr);
                 curr = InjectionPoint::new_static_cost(position + 1, Scope::BlockEnd, 0);
             }
@@ -1899,7 +2058,11 @@ fn injections(code: &[Operator], mem_type: WasmMemoryType) -> Vec<InjectionPoint
                 res.push(curr);
                 curr = InjectionPoint::new_static_cost(position + 1, Scope::BlockEnd, 0);
             }
-            Return | Unreachable | ReturnCall { .. } | ReturnCallIndirect { .. } => {
+            Return
+            | Unreachable
+            | ReturnCall { .. }
+            | ReturnCallIndirect { .. }
+            | ReturnCallRef { .. } => {
                 res.push(curr);
                 // This injection point will be unreachable itself (most likely empty)
                 // but we create it to keep the algorithm uniform
@@ -1954,10 +2117,10 @@ fn get_data(
                 ic_wasm_transform::DataSegmentKind::Active {
                     memory_index: _,
                     offset_expr,
-                } => match offset_expr {
-                    Operator::I32Const { value } => *value as usize,
-                    Operator::I64Const { value } => *value as usize,
-                    _ => return Some(Err(WasmInstrumentationError::WasmDeserializeError(WasmError::new(
+                } => match eval_const_expr(offset_expr) {
+                    Some(ConstValue::I32(value)) => value as u32 as usize,
+                    Some(ConstValue::I64(value)) => value as u64 as usize,
+                    None => return Some(Err(WasmInstrumentationError::WasmDeserializeError(WasmError::new(
                         "complex initialization expressions for data segments are not supported!".into()
                     )))),
                 },
@@ -8,7 +8,7 @@ use ic_replicated_state::canister_state::execution_state::{
     CustomSection, CustomSectionType, WasmMetadata,
 };
 use ic_types::{NumBytes, NumInstructions, MAX_STABLE_MEMORY_IN_BYTES};
-use ic_wasm_transform::{Body, DataSegment, DataSegmentKind, Module};
+use ic_wasm_transform::{Body, DataSegment, DataSegmentKind, ElementKind, Module};
 use ic_wasm_types::{BinaryEncodedWasm, WasmValidationError};
 use std::{
     cmp,
@@ -323,6 +323,107 @@ fn get_valid_system_apis_common(I: ValType) -> HashMap<String, HashMap<String, F
                 },
             )],
         ),
+        (
+            "subnet_self_size",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![],
+                    return_type: vec![I],
+                },
+            )],
+        ),
+        (
+            "subnet_self_copy",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![I, I, I],
+                    return_type: vec![],
+                },
+            )],
+        ),
+        (
+            "root_key_size",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![],
+                    return_type: vec![I],
+                },
+            )],
+        ),
+        (
+            "root_key_copy",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![I, I, I],
+                    return_type: vec![],
+                },
+            )],
+        ),
+        // Environment variables
+        (
+            "env_var_count",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![],
+                    return_type: vec![I],
+                },
+            )],
+        ),
+        (
+            "env_var_name_size",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![I],
+                    return_type: vec![I],
+                },
+            )],
+        ),
+        (
+            "env_var_name_copy",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![I, I, I, I],
+                    return_type: vec![],
+                },
+            )],
+        ),
+        (
+            "env_var_name_exists",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![I, I],
+                    return_type: vec![ValType::I32],
+                },
+            )],
+        ),
+        (
+            "env_var_value_size",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![I, I],
+                    return_type: vec![I],
+                },
+            )],
+        ),
+        (
+            "env_var_value_copy",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![I, I, I, I, I],
+                    return_type: vec![],
+                },
+            )],
+        ),
         // Inter-canister method calls
         (
             "call_new",
@@ -605,6 +706,56 @@ fn get_valid_system_apis_common(I: ValType) -> HashMap<String, HashMap<String, F
                 },
             )],
         ),
+        (
+            "cost_call",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValType::I64, ValType::I64, I],
+                    return_type: vec![],
+                },
+            )],
+        ),
+        (
+            "cost_create_canister",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![I],
+                    return_type: vec![],
+                },
+            )],
+        ),
+        (
+            "cost_http_request",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValType::I64, ValType::I64, I],
+                    return_type: vec![],
+                },
+            )],
+        ),
+        (
+            "cost_sign_with_ecdsa",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![I, I, ValType::I32, I],
+                    return_type: vec![ValType::I32],
+                },
+            )],
+        ),
+        (
+            "cost_sign_with_schnorr",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![I, I, ValType::I32, I],
+                    return_type: vec![ValType::I32],
+                },
+            )],
+        ),
         (
             "call_with_best_effort_response",
             vec![(
@@ -959,9 +1110,108 @@ fn validate_export_section(
     Ok(())
 }
 
+/// The value of a constant expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum ConstValue {
+    I32(i32),
+    I64(i64),
+}
+
+impl ConstValue {
+    fn into_operator<'a>(self) -> Operator<'a> {
+        match self {
+            ConstValue::I32(value) => Operator::I32Const { value },
+            ConstValue::I64(value) => Operator::I64Const { value },
+        }
+    }
+}
+
+/// Evaluates a constant expression consisting of integer constants and
+/// (with the extended constant expressions proposal) integer additions,
+/// subtractions, and multiplications. Returns `None` for any other expression,
+/// e.g., one reading a global.
+pub(super) fn eval_const_expr(expr: &[Operator]) -> Option<ConstValue> {
+    let mut stack = vec![];
+    for op in expr {
+        let value = match op {
+            Operator::I32Const { value } => ConstValue::I32(*value),
+            Operator::I64Const { value } => ConstValue::I64(*value),
+            Operator::I32Add | Operator::I32Sub | Operator::I32Mul => {
+                let (Some(ConstValue::I32(rhs)), Some(ConstValue::I32(lhs))) =
+                    (stack.pop(), stack.pop())
+                else {
+                    return None;
+                };
+                ConstValue::I32(match op {
+                    Operator::I32Add => lhs.wrapping_add(rhs),
+                    Operator::I32Sub => lhs.wrapping_sub(rhs),
+                    _ => lhs.wrapping_mul(rhs),
+                })
+            }
+            Operator::I64Add | Operator::I64Sub | Operator::I64Mul => {
+                let (Some(ConstValue::I64(rhs)), Some(ConstValue::I64(lhs))) =
+                    (stack.pop(), stack.pop())
+                else {
+                    return None;
+                };
+                ConstValue::I64(match op {
+                    Operator::I64Add => lhs.wrapping_add(rhs),
+                    Operator::I64Sub => lhs.wrapping_sub(rhs),
+                    _ => lhs.wrapping_mul(rhs),
+                })
+            }
+            _ => return None,
+        };
+        stack.push(value);
+    }
+    match stack.as_slice() {
+        [value] => Some(*value),
+        _ => None,
+    }
+}
+
+// Replaces the constant expressions of more than one operator in global
+// initializers and in offsets of active data and element segments by the
+// integer constant they evaluate to. Expressions that cannot be evaluated, e.g.,
+// ones reading a global, are left as they are for Wasmtime to reject. Returns
+// whether any expression was replaced.
+//
+// Wasmtime does not implement the extended constant expressions proposal yet,
+// so the folded module is what gets validated, instrumented and compiled.
+fn fold_extended_const_exprs(module: &mut Module) -> bool {
+    fn fold(expr: &mut Vec<Operator>) -> bool {
+        if expr.len() <= 1 {
+            return false;
+        }
+        match eval_const_expr(expr) {
+            Some(value) => {
+                *expr = vec![value.into_operator()];
+                true
+            }
+            None => false,
+        }
+    }
+
+    let mut folded = false;
+    for global in module.globals.iter_mut() {
+        folded |= fold(&mut global.init_expr);
+    }
+    for segment in module.data.iter_mut() {
+        if let DataSegmentKind::Active { offset_expr, .. } = &mut segment.kind {
+            folded |= fold(offset_expr);
+        }
+    }
+    for (kind, _) in module.elements.iter_mut() {
+        if let ElementKind::Active { offset_expr, .. } = kind {
+            folded |= fold(offset_expr);
+        }
+    }
+    folded
+}
+
 // Checks that offset-expressions in active data segments consist of only one constant
-// expression. Required because of OP. See also:
-// instrumentation.rs
+// expression. Extended constant expressions have already been folded into one
+// constant at this point. Required because of OP. See also: instrumentation.rs
 fn validate_data_section(module: &Module) -> Result<(), WasmValidationError> {
     fn validate_segment(
         s: &DataSegment,
@@ -975,8 +1225,8 @@ fn validate_data_section(module: &Module) -> Result<(), WasmValidationError> {
                     offset_expr,
                 },
                 WasmMemoryType::Wasm32,
-            ) => match offset_expr {
-                Operator::I32Const { .. } => Ok(()),
+            ) => match eval_const_expr(offset_expr) {
+                Some(ConstValue::I32(_)) => Ok(()),
                 _ => Err(WasmValidationError::InvalidDataSection(format!(
                     "Invalid offset expression in data segment for 32bit memory: {:?}",
                     offset_expr
@@ -988,8 +1238,8 @@ fn validate_data_section(module: &Module) -> Result<(), WasmValidationError> {
                     offset_expr,
                 },
                 WasmMemoryType::Wasm64,
-            ) => match offset_expr {
-                Operator::I64Const { .. } => Ok(()),
+            ) => match eval_const_expr(offset_expr) {
+                Some(ConstValue::I64(_)) => Ok(()),
                 _ => Err(WasmValidationError::InvalidDataSection(format!(
                     "Invalid offset expression in data segment for 64bit memory: {:?}",
                     offset_expr
@@ -1202,6 +1452,12 @@ fn wasm_function_complexity(
             | BrTable { .. }
             | Call { .. }
             | CallIndirect { .. }
+            | ReturnCall { .. }
+            | ReturnCallIndirect { .. }
+            | CallRef { .. }
+            | ReturnCallRef { .. }
+            | BrOnNull { .. }
+            | BrOnNonNull { .. }
             | MemoryGrow { .. } => 50,
             TableGrow { .. } => {
                 return Err(WasmValidationError::UnsupportedWasmInstruction {
@@ -1217,7 +1473,7 @@ fn wasm_function_complexity(
                     instruction: "table.set".into(),
                 });
             }
-            RefIsNull => 6,
+            RefIsNull | RefAsNonNull => 6,
             TableFill { .. } => {
                 return Err(WasmValidationError::UnsupportedWasmInstruction {
                     index,
@@ -1398,7 +1654,14 @@ pub fn wasmtime_validation_config(embedders_config: &EmbeddersConfig) -> wasmtim
     config.wasm_backtrace(false);
     config.wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Disable);
     config.wasm_bulk_memory(true);
-    config.wasm_function_references(false);
+    // Wasmtime does not implement extended constant expressions. If they are
+    // enabled, they are folded before the module is validated by Wasmtime.
+    config.wasm_function_references(
+        embedders_config.feature_flags.wasm_function_references
+            == ic_config::flag_status::FlagStatus::Enabled,
+    );
+    // The GC proposal stays disabled: this version of Wasmtime cannot compile
+    // struct and array instructions yet.
     config.wasm_gc(false);
     if embedders_config.feature_flags.wasm64 == ic_config::flag_status::FlagStatus::Enabled {
         config.wasm_memory64(true);
@@ -1412,8 +1675,12 @@ pub fn wasmtime_validation_config(embedders_config: &EmbeddersConfig) -> wasmtim
     config.wasm_reference_types(true);
     // The relaxed SIMD instructions are disable for determinism.
     config.wasm_relaxed_simd(false);
-    // Tail calls may be enabled in the future.
-    config.wasm_tail_call(false);
+    config.wasm_tail_call(
+        embedders_config
+            .feature_flags
+            .wasm_tail_call_and_extended_const
+            == ic_config::flag_status::FlagStatus::Enabled,
+    );
 
     config
         // The maximum size in bytes where a linear memory is considered
@@ -1451,6 +1718,33 @@ fn can_compile(
     })
 }
 
+fn parse_module(wasm: &BinaryEncodedWasm) -> Result<Module<'_>, WasmValidationError> {
+    Module::parse(wasm.as_slice(), false)
+        .map_err(|err| WasmValidationError::DecodingError(format!("{}", err)))
+}
+
+// Parses the module and folds its extended constant expressions. Since
+// Wasmtime does not support these expressions, it validates the folded
+// module instead of the original one.
+fn parse_and_fold_const_exprs<'a>(
+    wasm: &'a BinaryEncodedWasm,
+    config: &EmbeddersConfig,
+) -> Result<Module<'a>, WasmValidationError> {
+    let mut module = parse_module(wasm)?;
+    if !fold_extended_const_exprs(&mut module) {
+        can_compile(wasm, config)?;
+        return Ok(module);
+    }
+    // Encoding consumes the module, so the copy for Wasmtime is parsed again.
+    let mut folded = parse_module(wasm)?;
+    fold_extended_const_exprs(&mut folded);
+    let folded = folded
+        .encode()
+        .map_err(|err| WasmValidationError::DecodingError(format!("{}", err)))?;
+    can_compile(&BinaryEncodedWasm::new(folded), config)?;
+    Ok(module)
+}
+
 fn check_code_section_size(wasm: &BinaryEncodedWasm) -> Result<(), WasmValidationError> {
     let parser = wasmparser::Parser::new(0);
     let payloads = parser.parse_all(wasm.as_slice());
@@ -1495,9 +1789,14 @@ pub(super) fn validate_wasm_binary<'a>(
     config: &EmbeddersConfig,
 ) -> Result<(WasmValidationDetails, Module<'a>), WasmValidationError> {
     check_code_section_size(wasm)?;
-    can_compile(wasm, config)?;
-    let module = Module::parse(wasm.as_slice(), false)
-        .map_err(|err| WasmValidationError::DecodingError(format!("{}", err)))?;
+    let module = if config.feature_flags.wasm_tail_call_and_extended_const
+        == ic_config::flag_status::FlagStatus::Enabled
+    {
+        parse_and_fold_const_exprs(wasm, config)?
+    } else {
+        can_compile(wasm, config)?;
+        parse_module(wasm)?
+    };
     let imports_details = validate_import_section(&module)?;
     validate_export_section(
         &module,
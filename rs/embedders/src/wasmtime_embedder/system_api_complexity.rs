@@ -35,10 +35,21 @@ pub mod overhead {
     pub const CERTIFIED_DATA_SET: NumInstructions = NumInstructions::new(500);
     pub const CONTROLLER_COPY: NumInstructions = NumInstructions::new(500);
     pub const CONTROLLER_SIZE: NumInstructions = NumInstructions::new(500);
+    pub const COST_CALL: NumInstructions = NumInstructions::new(500);
+    pub const COST_CREATE_CANISTER: NumInstructions = NumInstructions::new(500);
+    pub const COST_HTTP_REQUEST: NumInstructions = NumInstructions::new(500);
+    pub const COST_SIGN_WITH_ECDSA: NumInstructions = NumInstructions::new(500);
+    pub const COST_SIGN_WITH_SCHNORR: NumInstructions = NumInstructions::new(500);
     pub const DATA_CERTIFICATE_COPY: NumInstructions = NumInstructions::new(500);
     pub const DATA_CERTIFICATE_PRESENT: NumInstructions = NumInstructions::new(500);
     pub const DATA_CERTIFICATE_SIZE: NumInstructions = NumInstructions::new(500);
     pub const DEBUG_PRINT: NumInstructions = NumInstructions::new(100);
+    pub const ENV_VAR_COUNT: NumInstructions = NumInstructions::new(500);
+    pub const ENV_VAR_NAME_COPY: NumInstructions = NumInstructions::new(500);
+    pub const ENV_VAR_NAME_EXISTS: NumInstructions = NumInstructions::new(500);
+    pub const ENV_VAR_NAME_SIZE: NumInstructions = NumInstructions::new(500);
+    pub const ENV_VAR_VALUE_COPY: NumInstructions = NumInstructions::new(500);
+    pub const ENV_VAR_VALUE_SIZE: NumInstructions = NumInstructions::new(500);
     pub const GLOBAL_TIMER_SET: NumInstructions = NumInstructions::new(500);
     pub const IS_CONTROLLER: NumInstructions = NumInstructions::new(1_000);
     pub const IN_REPLICATED_EXECUTION: NumInstructions = NumInstructions::new(500);
@@ -62,6 +73,10 @@ pub mod overhead {
     pub const MSG_REPLY_DATA_APPEND: NumInstructions = NumInstructions::new(500);
     pub const MSG_REPLY: NumInstructions = NumInstructions::new(500);
     pub const PERFORMANCE_COUNTER: NumInstructions = NumInstructions::new(200);
+    pub const PROFILE_ENTER: NumInstructions = NumInstructions::new(200);
+    pub const PROFILE_EXIT: NumInstructions = NumInstructions::new(200);
+    pub const ROOT_KEY_COPY: NumInstructions = NumInstructions::new(500);
+    pub const ROOT_KEY_SIZE: NumInstructions = NumInstructions::new(500);
     pub const STABLE_GROW: NumInstructions = NumInstructions::new(500);
     pub const STABLE_READ: NumInstructions = NumInstructions::new(20);
     pub const STABLE_SIZE: NumInstructions = NumInstructions::new(20);
@@ -70,6 +85,8 @@ pub mod overhead {
     pub const STABLE64_READ: NumInstructions = NumInstructions::new(20);
     pub const STABLE64_SIZE: NumInstructions = NumInstructions::new(20);
     pub const STABLE64_WRITE: NumInstructions = NumInstructions::new(20);
+    pub const SUBNET_SELF_COPY: NumInstructions = NumInstructions::new(500);
+    pub const SUBNET_SELF_SIZE: NumInstructions = NumInstructions::new(500);
     pub const TIME: NumInstructions = NumInstructions::new(500);
     pub const TRAP: NumInstructions = NumInstructions::new(500);
 }
@@ -161,7 +161,7 @@ fn test_wasmtime_system_api() {
 
 #[test]
 fn test_initial_wasmtime_config() {
-    // The following proposals should be disabled: tail_call, simd, relaxed_simd,
+    // The following proposals should be disabled by default: tail_call, simd, relaxed_simd,
     // threads, multi_memory, exceptions, memory64, extended_const, component_model,
     // function_references, memory_control, gc
     for (proposal, _url, wat, expected_err_msg) in [
@@ -237,3 +237,85 @@ fn test_initial_wasmtime_config() {
         );
     }
 }
+
+#[test]
+fn test_wasm_tail_call_and_extended_const_enabled_by_feature_flag() {
+    let mut config = EmbeddersConfig::default();
+    config.feature_flags.wasm_tail_call_and_extended_const = FlagStatus::Enabled;
+    let embedder = WasmtimeEmbedder::new(config, no_op_logger());
+    for (proposal, wat) in [
+        (
+            "tail_call",
+            r#"(module
+                (type $t (func))
+                (table 1 funcref)
+                (func $f1 return_call $f2)
+                (func $f2 (return_call_indirect (type $t) (i32.const 0))))"#,
+        ),
+        (
+            "extended_const",
+            "(module (global i32 (i32.add (i32.const 1) (i32.mul (i32.const 2) (i32.const 3)))))",
+        ),
+    ] {
+        let wasm_binary = BinaryEncodedWasm::new(wat::parse_str(wat).unwrap_or_else(|err| {
+            panic!("Error parsing proposal `{proposal}` code snippet: {err}")
+        }));
+        validate_and_instrument_for_testing(&embedder, &wasm_binary).unwrap_or_else(|err| {
+            panic!("Error having `{proposal}` proposal disabled in the `wasmtime` config: {err:?}")
+        });
+    }
+}
+
+/// Returns a module that calls a typed function reference. It is built with
+/// `wasm-encoder` because the version of `wat` in use emits a pre-standard
+/// encoding for typed references.
+fn call_ref_module() -> BinaryEncodedWasm {
+    use wasm_encoder::{
+        CodeSection, Function, FunctionSection, HeapType, Instruction, RefType, TypeSection,
+        ValType,
+    };
+
+    let mut types = TypeSection::new();
+    types.function([ValType::I32], [ValType::I32]);
+    types.function(
+        [ValType::Ref(RefType {
+            nullable: true,
+            heap_type: HeapType::Concrete(0),
+        })],
+        [ValType::I32],
+    );
+    let mut functions = FunctionSection::new();
+    functions.function(0).function(1);
+    let mut code = CodeSection::new();
+    let mut identity = Function::new([]);
+    identity
+        .instruction(&Instruction::LocalGet(0))
+        .instruction(&Instruction::End);
+    code.function(&identity);
+    let mut call = Function::new([]);
+    call.instruction(&Instruction::I32Const(1))
+        .instruction(&Instruction::LocalGet(0))
+        .instruction(&Instruction::CallRef(0))
+        .instruction(&Instruction::End);
+    code.function(&call);
+
+    let mut module = wasm_encoder::Module::new();
+    module.section(&types).section(&functions).section(&code);
+    BinaryEncodedWasm::new(module.finish())
+}
+
+#[test]
+fn test_wasm_function_references_enabled_by_feature_flag() {
+    let wasm_binary = call_ref_module();
+    validate_and_instrument_for_testing(
+        &WasmtimeEmbedder::new(EmbeddersConfig::default(), no_op_logger()),
+        &wasm_binary,
+    )
+    .expect_err("Function references should be disabled by default");
+
+    let mut config = EmbeddersConfig::default();
+    config.feature_flags.wasm_function_references = FlagStatus::Enabled;
+    let embedder = WasmtimeEmbedder::new(config, no_op_logger());
+    validate_and_instrument_for_testing(&embedder, &wasm_binary)
+        .expect("Function references should be enabled by the feature flag");
+}
@@ -270,6 +270,79 @@ fn test_exports_only_reserved_symbols() {
     }
 }
 
+#[test]
+#[allow(clippy::field_reassign_with_default)]
+fn test_canister_profiling_wraps_original_functions() {
+    let wasm = wat::parse_str(
+        r#"
+        (module
+            (import "ic0" "msg_reply" (func $msg_reply))
+            (func $helper (param i32) (result i32)
+                (local.get 0)
+            )
+            (func (export "canister_update test")
+                (drop (call $helper (i32.const 1)))
+                (call $msg_reply)
+            )
+        )"#,
+    )
+    .map(BinaryEncodedWasm::new)
+    .unwrap();
+
+    let mut config = EmbeddersConfig::default();
+    config.feature_flags.canister_profiling = FlagStatus::Enabled;
+    let (_, instrumentation_details) =
+        validate_and_instrument_for_testing(&WasmtimeEmbedder::new(config, no_op_logger()), &wasm)
+            .unwrap();
+    let module = Module::parse(instrumentation_details.binary.as_slice(), true).unwrap();
+
+    let num_imports = module.imports.len();
+    assert_eq!(module.imports[num_imports - 2].name, "profile_enter");
+    assert_eq!(module.imports[num_imports - 1].name, "profile_exit");
+    let profile_enter = (num_imports - 2) as u32;
+    let profile_exit = (num_imports - 1) as u32;
+
+    // Returns the body of the wrapper of the function with the given index in
+    // the original module.
+    let wrapper_body = |function_index: u32| {
+        let wrappers = &module.code_sections[module.code_sections.len() - 2..];
+        wrappers[(function_index - 1) as usize].instructions.clone()
+    };
+
+    let update = module
+        .exports
+        .iter()
+        .find(|export| export.name == "canister_update test")
+        .unwrap();
+    let update_body = &module.code_sections[update.index as usize - num_imports];
+    // `wasmparser::Operator` does not implement `PartialEq`.
+    assert_eq!(
+        format!("{:?}", update_body.instructions),
+        format!("{:?}", wrapper_body(2))
+    );
+    assert!(matches!(
+        update_body.instructions[..2],
+        [
+            wasmparser::Operator::I32Const { value: 2 },
+            wasmparser::Operator::Call { function_index },
+        ] if function_index == profile_enter
+    ));
+
+    let helper_body = wrapper_body(1);
+    assert!(matches!(
+        helper_body[2],
+        wasmparser::Operator::LocalGet { local_index: 0 }
+    ));
+    assert!(matches!(
+        helper_body[4..],
+        [
+            wasmparser::Operator::I32Const { value: 1 },
+            wasmparser::Operator::Call { function_index },
+            wasmparser::Operator::End,
+        ] if function_index == profile_exit
+    ));
+}
+
 fn instr_used(instance: &mut WasmtimeInstance) -> u64 {
     let instruction_counter = instance.instruction_counter();
     let system_api = instance.store_data().system_api().unwrap();
@@ -429,6 +502,220 @@ fn metering_plain() {
     assert_eq!(instructions_used, 1 + cost_a(10) + ctrap);
 }
 
+#[allow(clippy::field_reassign_with_default)]
+fn new_instance_with_tail_call_and_extended_const(
+    wat: &str,
+    instruction_limit: u64,
+) -> WasmtimeInstance {
+    let mut config = EmbeddersConfig::default();
+    config.dirty_page_overhead = SchedulerConfig::application_subnet().dirty_page_overhead;
+    config.feature_flags.wasm_tail_call_and_extended_const = FlagStatus::Enabled;
+    WasmtimeInstanceBuilder::new()
+        .with_config(config)
+        .with_wat(wat)
+        .with_num_instructions(NumInstructions::new(instruction_limit))
+        .build()
+}
+
+#[test]
+fn metering_tail_call() {
+    let wat = format!(
+        r#"
+        (module
+            (global $g1 (export "g1") (mut i64) (i64.const 0))
+            (func $add
+                global.get $g1
+                {body}
+                global.set $g1
+            )
+            (func $test (export "canister_update test")
+                global.get $g1
+                {body}
+                global.set $g1
+                return_call $add
+            )
+        )"#,
+        body = add_one().repeat(10)
+    );
+    let mut instance = new_instance_with_tail_call_and_extended_const(&wat, 1000);
+    let res = instance.run(func_ref("test")).unwrap();
+
+    let g = &res.exported_globals;
+    assert_eq!(g[0], Global::I64(20));
+
+    let instructions_used = instr_used(&mut instance);
+    let crc = instruction_to_cost(
+        &wasmparser::Operator::ReturnCall { function_index: 0 },
+        WasmMemoryType::Wasm32,
+    );
+    // Both functions are 1 instruction.
+    assert_eq!(instructions_used, 1 + cost_a(10) + crc + 1 + cost_a(10));
+
+    // A tail call is metered the same as a call followed by a return.
+    let wat_with_call = wat.replace("return_call $add", "call $add return");
+    let mut instance = new_instance_with_tail_call_and_extended_const(&wat_with_call, 1000);
+    instance.run(func_ref("test")).unwrap();
+    let cret = instruction_to_cost(&wasmparser::Operator::Return, WasmMemoryType::Wasm32);
+    assert_eq!(instr_used(&mut instance), instructions_used + cret);
+
+    // Now run the same with insufficient instructions
+    let mut instance = new_instance_with_tail_call_and_extended_const(&wat, instructions_used - 1);
+    let err = instance.run(func_ref("test")).unwrap_err();
+    assert_eq!(
+        err,
+        HypervisorError::InstructionLimitExceeded(NumInstructions::from(instructions_used - 1))
+    );
+}
+
+#[test]
+fn extended_const_global_initializer() {
+    let wat = r#"
+        (module
+            (global $g1 (export "g1") (mut i64) (i64.add (i64.const 40) (i64.mul (i64.const 1) (i64.const 2))))
+            (func $test (export "canister_update test")
+                global.get $g1
+                (i64.add (i64.const 1))
+                global.set $g1
+            )
+        )"#;
+    let mut instance = new_instance_with_tail_call_and_extended_const(wat, 1000);
+    let res = instance.run(func_ref("test")).unwrap();
+
+    let g = &res.exported_globals;
+    assert_eq!(g[0], Global::I64(43));
+}
+
+#[test]
+fn extended_const_data_segment_offset() {
+    let wat = r#"
+        (module
+            (memory 1)
+            (data (i32.add (i32.const 8) (i32.mul (i32.const 2) (i32.const 3))) "\2a")
+        )"#;
+    let mut config = EmbeddersConfig::default();
+    config.feature_flags.wasm_tail_call_and_extended_const = FlagStatus::Enabled;
+    let output = validate_and_instrument_for_testing(
+        &WasmtimeEmbedder::new(config, no_op_logger()),
+        &BinaryEncodedWasm::new(wat::parse_str(wat).unwrap()),
+    )
+    .unwrap()
+    .1;
+    assert_eq!(output.data.into_slice(), vec![(14, vec![42])]);
+}
+
+#[allow(clippy::field_reassign_with_default)]
+fn new_instance_with_function_references(
+    wasm: Vec<u8>,
+    instruction_limit: u64,
+) -> WasmtimeInstance {
+    let mut config = EmbeddersConfig::default();
+    config.dirty_page_overhead = SchedulerConfig::application_subnet().dirty_page_overhead;
+    config.feature_flags.wasm_function_references = FlagStatus::Enabled;
+    WasmtimeInstanceBuilder::new()
+        .with_config(config)
+        .with_wasm(wasm)
+        .with_num_instructions(NumInstructions::new(instruction_limit))
+        .build()
+}
+
+/// Returns a module whose update method takes a `br_on_null` that does not
+/// branch and then adds one to a global through `call_ref`. It is built
+/// with `wasm-encoder` because the version of `wat` in use emits a
+/// pre-standard encoding for typed references.
+fn function_references_module() -> Vec<u8> {
+    use wasm_encoder::{
+        BlockType, CodeSection, ConstExpr, ElementSection, Elements, ExportKind, ExportSection,
+        Function, FunctionSection, GlobalSection, GlobalType, Instruction as I, TypeSection,
+        ValType,
+    };
+
+    let mut types = TypeSection::new();
+    types.function([ValType::I64], [ValType::I64]);
+    types.function([], []);
+    let mut functions = FunctionSection::new();
+    functions.function(0).function(1);
+    let mut globals = GlobalSection::new();
+    globals.global(
+        GlobalType {
+            val_type: ValType::I64,
+            mutable: true,
+        },
+        &ConstExpr::i64_const(0),
+    );
+    let mut exports = ExportSection::new();
+    exports
+        .export("g1", ExportKind::Global, 0)
+        .export("canister_update test", ExportKind::Func, 1);
+    let mut elements = ElementSection::new();
+    elements.declared(Elements::Functions(&[0]));
+
+    let mut code = CodeSection::new();
+    let mut add = Function::new([]);
+    for instruction in [I::LocalGet(0), I::I64Const(1), I::I64Add, I::End] {
+        add.instruction(&instruction);
+    }
+    code.function(&add);
+    let mut test = Function::new([]);
+    for instruction in [
+        I::Block(BlockType::Empty),
+        I::RefFunc(0),
+        I::BrOnNull(0),
+        I::Drop,
+        I::End,
+        I::GlobalGet(0),
+        I::RefFunc(0),
+        I::CallRef(0),
+        I::GlobalSet(0),
+        I::End,
+    ] {
+        test.instruction(&instruction);
+    }
+    code.function(&test);
+
+    let mut module = wasm_encoder::Module::new();
+    module
+        .section(&types)
+        .section(&functions)
+        .section(&globals)
+        .section(&exports)
+        .section(&elements)
+        .section(&code);
+    module.finish()
+}
+
+#[test]
+fn metering_function_references() {
+    use wasmparser::Operator::*;
+
+    let mut instance = new_instance_with_function_references(function_references_module(), 1000);
+    let res = instance.run(func_ref("test")).unwrap();
+
+    let g = &res.exported_globals;
+    assert_eq!(g[0], Global::I64(1));
+
+    let c = |op: wasmparser::Operator| instruction_to_cost(&op, WasmMemoryType::Wasm32);
+    let test_cost = 1
+        + c(GlobalGet { global_index: 0 })
+        + c(RefFunc { function_index: 0 })
+        + c(CallRef { type_index: 0 })
+        + c(GlobalSet { global_index: 0 })
+        + c(RefFunc { function_index: 0 })
+        + c(BrOnNull { relative_depth: 0 })
+        + c(Drop);
+    let add_cost = 1 + c(LocalGet { local_index: 0 }) + c(I64Const { value: 1 }) + c(I64Add);
+    let instructions_used = instr_used(&mut instance);
+    assert_eq!(instructions_used, test_cost + add_cost);
+
+    // Now run the same with insufficient instructions
+    let mut instance =
+        new_instance_with_function_references(function_references_module(), instructions_used - 1);
+    let err = instance.run(func_ref("test")).unwrap_err();
+    assert_eq!(
+        err,
+        HypervisorError::InstructionLimitExceeded(NumInstructions::from(instructions_used - 1))
+    );
+}
+
 #[test]
 fn metering_block() {
     let wat = format!(
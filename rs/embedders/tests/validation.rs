@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 
 use assert_matches::assert_matches;
-use ic_config::embedders::Config as EmbeddersConfig;
+use ic_config::{embedders::Config as EmbeddersConfig, flag_status::FlagStatus};
 use ic_embedders::{
     wasm_utils::{
         validate_and_instrument_for_testing,
@@ -505,6 +505,49 @@ fn can_validate_valid_data_section() {
     );
 }
 
+#[test]
+fn can_validate_extended_const_offset_expression_in_data_section() {
+    let wasm = wat2wasm(
+        r#"
+                (module
+                    (memory (;0;) 1)
+                    (data (i32.add (i32.const 8) (i32.const 2)) "abcd")
+                )
+            "#,
+    )
+    .unwrap();
+    assert_matches!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Err(WasmValidationError::WasmtimeValidation(_))
+    );
+
+    let mut config = EmbeddersConfig::default();
+    config.feature_flags.wasm_tail_call_and_extended_const = FlagStatus::Enabled;
+    assert_eq!(
+        validate_wasm_binary(&wasm, &config),
+        Ok(WasmValidationDetails::default())
+    );
+}
+
+#[test]
+fn can_validate_extended_const_expression_reading_global() {
+    let wasm = wat2wasm(
+        r#"
+                (module
+                    (global (;0;) i32 (i32.const 1))
+                    (global (;1;) i32 (i32.add (global.get 0) (i32.const 2)))
+                )
+            "#,
+    )
+    .unwrap();
+    let mut config = EmbeddersConfig::default();
+    config.feature_flags.wasm_tail_call_and_extended_const = FlagStatus::Enabled;
+    assert_matches!(
+        validate_wasm_binary(&wasm, &config),
+        Err(WasmValidationError::WasmtimeValidation(_))
+    );
+}
+
 #[test]
 // this test passes currently not because of a correct validation that we're not
 // using a global in data offset expression, but because we terminate the
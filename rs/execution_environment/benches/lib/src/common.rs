@@ -94,6 +94,7 @@ where
             CanisterModule::new(wat::parse_str(wat.as_ref()).unwrap()),
             canister_root,
             canister_id,
+            false,
             &mut round_limits,
             CompilationCostHandling::CountFullAmount,
         )
@@ -23,8 +23,9 @@ use ic_interfaces::execution_environment::{
 use ic_logger::{error, fatal, info, ReplicaLogger};
 use ic_management_canister_types::{
     CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallModeV2, CanisterSnapshotResponse,
-    CanisterStatusResultV2, CanisterStatusType, ChunkHash, InstallChunkedCodeArgs,
-    InstallCodeArgsV2, Method as Ic00Method, StoredChunksReply, UploadChunkReply,
+    CanisterStatusResultV2, CanisterStatusType, ChunkHash, EnvironmentVariable,
+    InstallChunkedCodeArgs, InstallCodeArgsV2, Method as Ic00Method, StoredChunksReply,
+    UploadChunkReply,
 };
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
@@ -32,7 +33,7 @@ use ic_replicated_state::canister_state::system_state::ReservationError;
 use ic_replicated_state::{
     canister_snapshots::{CanisterSnapshot, CanisterSnapshotError},
     canister_state::{
-        execution_state::Memory,
+        execution_state::{Memory, WasmBinary},
         system_state::{
             wasm_chunk_store::{self, WasmChunkStore},
             CyclesUseCase,
@@ -46,6 +47,7 @@ use ic_replicated_state::{
 };
 use ic_system_api::ExecutionParameters;
 use ic_types::{
+    canister_profile::CanisterProfile,
     ingress::{IngressState, IngressStatus},
     messages::{
         CanisterCall, MessageId, Payload, RejectContext, Response as CanisterResponse,
@@ -458,9 +460,11 @@ impl CanisterManager {
             | Ok(Ic00Method::ECDSAPublicKey)
             | Ok(Ic00Method::SetupInitialDKG)
             | Ok(Ic00Method::SignWithECDSA)
+            | Ok(Ic00Method::SignWithECDSABatch)
             | Ok(Ic00Method::ComputeInitialIDkgDealings)
             | Ok(Ic00Method::SchnorrPublicKey)
             | Ok(Ic00Method::SignWithSchnorr)
+            | Ok(Ic00Method::SignWithSchnorrBatch)
             // "DepositCycles" can be called by anyone however as ingress message
             // cannot carry cycles, it does not make sense to allow them from users.
             | Ok(Ic00Method::DepositCycles)
@@ -474,7 +478,9 @@ impl CanisterManager {
             | Ok(Ic00Method::BitcoinSendTransaction)
             | Ok(Ic00Method::BitcoinSendTransactionInternal)
             | Ok(Ic00Method::BitcoinGetCurrentFeePercentiles)
-            | Ok(Ic00Method::NodeMetricsHistory) => Err(UserError::new(
+            | Ok(Ic00Method::NodeMetricsHistory)
+            // Only ever sent by subnets.
+            | Ok(Ic00Method::ImportCanisterState) => Err(UserError::new(
                 ErrorCode::CanisterRejectedMessage,
                 format!("Only canisters can call ic00 method {}", method_name),
             )),
@@ -496,7 +502,8 @@ impl CanisterManager {
             | Ok(Ic00Method::TakeCanisterSnapshot)
             | Ok(Ic00Method::LoadCanisterSnapshot)
             | Ok(Ic00Method::ListCanisterSnapshots)
-            | Ok(Ic00Method::DeleteCanisterSnapshot) => {
+            | Ok(Ic00Method::DeleteCanisterSnapshot)
+            | Ok(Ic00Method::MigrateCanister) => {
                 match effective_canister_id {
                     Some(canister_id) => {
                         let canister = state.canister_state(&canister_id).ok_or_else(|| UserError::new(
@@ -529,6 +536,14 @@ impl CanisterManager {
                 ),
             )),
 
+            Ok(Ic00Method::FetchCanisterProfile) => Err(UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!(
+                    "{} API is only accessible in non-replicated mode",
+                    Ic00Method::FetchCanisterProfile
+                ),
+            )),
+
             Ok(Ic00Method::ProvisionalCreateCanisterWithCycles)
             | Ok(Ic00Method::BitcoinGetSuccessors)
             | Ok(Ic00Method::ProvisionalTopUpCanister) => {
@@ -630,6 +645,20 @@ impl CanisterManager {
         if let Some(wasm_memory_limit) = settings.wasm_memory_limit() {
             canister.system_state.wasm_memory_limit = Some(wasm_memory_limit);
         }
+        if let Some(environment_variables) = settings.environment_variables() {
+            canister.system_state.environment_variables = environment_variables.clone();
+        }
+        if let Some(profiling) = settings.profiling() {
+            if profiling != canister.system_state.canister_profile.is_some() {
+                canister.system_state.canister_profile = profiling.then(CanisterProfile::default);
+                // Only canisters with profiling enabled are compiled with the
+                // profiling hooks, so the module must be compiled again.
+                if let Some(execution_state) = canister.execution_state.as_mut() {
+                    execution_state.wasm_binary =
+                        WasmBinary::new(execution_state.wasm_binary.binary.clone());
+                }
+            }
+        }
     }
 
     /// Tries to apply the requested settings on the canister identified by
@@ -1165,6 +1194,12 @@ impl CanisterManager {
         let reserved_cycles_limit = canister.system_state.reserved_balance_limit();
         let log_visibility = canister.system_state.log_visibility.clone();
         let wasm_memory_limit = canister.system_state.wasm_memory_limit;
+        let environment_variables = canister
+            .system_state
+            .environment_variables
+            .iter()
+            .map(|(name, value)| EnvironmentVariable::new(name, value))
+            .collect();
 
         Ok(CanisterStatusResultV2::new(
             canister.status(),
@@ -1202,6 +1237,8 @@ impl CanisterManager {
                 .total_query_stats
                 .egress_payload_size,
             wasm_memory_limit.map(|x| x.get()),
+            environment_variables,
+            canister.system_state.canister_profile.is_some(),
         ))
     }
 
@@ -2121,6 +2158,7 @@ impl CanisterManager {
                 execution_snapshot.wasm_binary.clone(),
                 "NOT_USED".into(),
                 canister_id,
+                system_state.canister_profile.is_some(),
                 round_limits,
                 compilation_cost_handling,
             );
@@ -2413,6 +2451,17 @@ pub(crate) enum CanisterManagerError {
     InvalidUpgradeOptionError {
         message: String,
     },
+    MigrateCanisterNotStopped(CanisterId),
+    MigrateCanisterQueueNotEmpty(CanisterId),
+    CanisterMigrationNotPrepared {
+        canister_id: CanisterId,
+        target_subnet_id: SubnetId,
+    },
+    CanisterMigrationInProgress(CanisterId),
+    CanisterMigrationFailed {
+        canister_id: CanisterId,
+        message: String,
+    },
 }
 
 impl AsErrorHelp for CanisterManagerError {
@@ -2455,7 +2504,12 @@ impl AsErrorHelp for CanisterManagerError {
             | CanisterManagerError::CanisterSnapshotNotEnoughCycles { .. }
             | CanisterManagerError::LongExecutionAlreadyInProgress { .. }
             | CanisterManagerError::MissingUpgradeOptionError { .. }
-            | CanisterManagerError::InvalidUpgradeOptionError { .. } => ErrorHelp::UserError {
+            | CanisterManagerError::InvalidUpgradeOptionError { .. }
+            | CanisterManagerError::MigrateCanisterNotStopped(_)
+            | CanisterManagerError::MigrateCanisterQueueNotEmpty(_)
+            | CanisterManagerError::CanisterMigrationNotPrepared { .. }
+            | CanisterManagerError::CanisterMigrationInProgress(_)
+            | CanisterManagerError::CanisterMigrationFailed { .. } => ErrorHelp::UserError {
                 suggestion: "".to_string(),
                 doc_link: "".to_string(),
             },
@@ -2782,6 +2836,51 @@ impl From<CanisterManagerError> for UserError {
                     )
                 )
             }
+            MigrateCanisterNotStopped(canister_id) => {
+                Self::new(
+                    ErrorCode::CanisterNotStopped,
+                    format!(
+                        "Canister {} must be stopped before it is migrated.{additional_help}",
+                        canister_id,
+                    )
+                )
+            }
+            MigrateCanisterQueueNotEmpty(canister_id) => {
+                Self::new(
+                    ErrorCode::CanisterQueueNotEmpty,
+                    format!(
+                        "Canister {} has messages in its queues and cannot be migrated now.{additional_help}",
+                        canister_id,
+                    )
+                )
+            }
+            CanisterMigrationNotPrepared { canister_id, target_subnet_id } => {
+                Self::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    format!(
+                        "Canister {} is not registered for migration to subnet {}.{additional_help}",
+                        canister_id, target_subnet_id,
+                    )
+                )
+            }
+            CanisterMigrationInProgress(canister_id) => {
+                Self::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    format!(
+                        "Canister {} is being migrated to another subnet.{additional_help}",
+                        canister_id,
+                    )
+                )
+            }
+            CanisterMigrationFailed { canister_id, message } => {
+                Self::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    format!(
+                        "Migration of canister {} failed: {}{additional_help}",
+                        canister_id, message,
+                    )
+                )
+            }
         }
     }
 }
@@ -2828,6 +2927,9 @@ pub fn uninstall_canister(
     // Clear log.
     canister.clear_log();
 
+    // Clear the execution profile.
+    canister.clear_profile();
+
     // Clear the Wasm chunk store.
     canister.system_state.wasm_chunk_store = WasmChunkStore::new(fd_factory);
 
@@ -2936,5 +3038,7 @@ pub(crate) trait PausedInstallCodeExecution: Send + std::fmt::Debug {
     fn abort(self: Box<Self>, log: &ReplicaLogger) -> (CanisterCall, InstallCodeCallId, Cycles);
 }
 
+mod migration;
+
 #[cfg(test)]
 pub(crate) mod tests;
@@ -0,0 +1,1000 @@
+//! Migration of a canister to another subnet.
+//!
+//! A controller calls `migrate_canister` on a stopped canister that the
+//! registry lists as migrating from this subnet to the target subnet. The state
+//! of the canister is then streamed to the target subnet with a series of
+//! `import_canister_state` requests, one step at a time:
+//!
+//! 1. the settings and history of the canister, and its cycles;
+//! 2. each of its snapshots, in the order of their IDs;
+//! 3. its current Wasm module, memories, chunk store, globals and certified
+//!    data;
+//! 4. its cycles once more, resent until they no longer change.
+//!
+//! Once the target subnet acknowledged the last step, the canister is deleted
+//! on this subnet and the `migrate_canister` call is replied to. The canister
+//! keeps its ID and remains stopped on the target subnet. Until the routing
+//! table is updated, messages addressed to the canister are rejected with a
+//! transient error, so that senders retry them against the target subnet. If
+//! the target subnet rejects any step, the migration fails and the canister
+//! stays on this subnet.
+
+use super::{CanisterManager, CanisterManagerError};
+use crate::execution::install_code::validate_controller;
+use crate::execution_environment::{CompilationCostHandling, RoundLimits};
+use ic_base_types::{NumBytes, NumSeconds, PrincipalId, SnapshotId, SubnetId};
+use ic_logger::info;
+use ic_management_canister_types::{
+    CanisterMigrationMetadata, CanisterMigrationStep, CanisterStatusType, EnvironmentVariable,
+    ImportCanisterStateArgs, Method as Ic00Method, MigratedCycles, MigratedExecutionState,
+    MigratedGlobal, MigratedModule, MigratedPage, MigratedSnapshot, Payload as Ic00Payload,
+};
+use ic_replicated_state::{
+    canister_snapshots::{CanisterSnapshot, ExecutionStateSnapshot, PageMemory},
+    canister_state::{
+        system_state::{
+            wasm_chunk_store::{self, WasmChunkHash, WasmChunkStore},
+            CanisterHistory,
+        },
+        WASM_PAGE_SIZE_IN_BYTES,
+    },
+    metadata_state::subnet_call_context_manager::CanisterMigrationContext,
+    page_map::PAGE_SIZE,
+    CanisterState, CanisterStatus, Global, Memory, NumWasmPages, PageIndex, PageMap,
+    ReplicatedState, SchedulerState, SystemState,
+};
+use ic_types::{
+    messages::{CanisterCall, Payload, Request, NO_DEADLINE},
+    CanisterId, CanisterTimer, ComputeAllocation, Cycles, MemoryAllocation, NumInstructions, Time,
+};
+use ic_wasm_types::{CanisterModule, WasmHash};
+use serde_bytes::ByteBuf;
+use std::sync::Arc;
+
+/// Maximum number of host pages of a memory sent in a single step.
+const PAGES_PER_STEP: u64 = 256;
+
+/// One step of the migration plan of a canister. The plan is derived from the
+/// canister and its snapshots, which do not change while the canister is being
+/// migrated, so it is recomputed for every step instead of being stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PlannedStep {
+    Metadata,
+    Cycles,
+    ModuleChunk { image: usize, index: usize },
+    Module { image: usize },
+    WasmMemoryPages { image: usize, batch: u64 },
+    StableMemoryPages { image: usize, batch: u64 },
+    ChunkStoreChunk { image: usize, index: usize },
+    Snapshot { image: usize },
+    ExecutionState,
+}
+
+/// The parts of a canister that are imported together on the target subnet:
+/// either one of its snapshots or its current state.
+enum Image<'a> {
+    Snapshot(SnapshotId, &'a CanisterSnapshot),
+    Current(&'a CanisterState),
+}
+
+impl<'a> Image<'a> {
+    fn module(&self) -> Option<&'a CanisterModule> {
+        match self {
+            Image::Snapshot(_, snapshot) => Some(snapshot.canister_module()),
+            Image::Current(canister) => canister
+                .execution_state
+                .as_ref()
+                .map(|execution_state| &execution_state.wasm_binary.binary),
+        }
+    }
+
+    fn wasm_memory(&self) -> Option<(&'a PageMap, NumWasmPages)> {
+        match self {
+            Image::Snapshot(_, snapshot) => {
+                let memory = &snapshot.execution_snapshot().wasm_memory;
+                Some((&memory.page_map, memory.size))
+            }
+            Image::Current(canister) => canister.execution_state.as_ref().map(|execution_state| {
+                let memory = &execution_state.wasm_memory;
+                (&memory.page_map, memory.size)
+            }),
+        }
+    }
+
+    fn stable_memory(&self) -> Option<(&'a PageMap, NumWasmPages)> {
+        match self {
+            Image::Snapshot(_, snapshot) => {
+                let memory = &snapshot.execution_snapshot().stable_memory;
+                Some((&memory.page_map, memory.size))
+            }
+            Image::Current(canister) => canister.execution_state.as_ref().map(|execution_state| {
+                let memory = &execution_state.stable_memory;
+                (&memory.page_map, memory.size)
+            }),
+        }
+    }
+
+    fn chunk_store(&self) -> &'a WasmChunkStore {
+        match self {
+            Image::Snapshot(_, snapshot) => snapshot.chunk_store(),
+            Image::Current(canister) => &canister.system_state.wasm_chunk_store,
+        }
+    }
+}
+
+/// Returns the snapshots of `canister` in the order of their IDs, followed by
+/// its current state.
+fn images<'a>(state: &'a ReplicatedState, canister: &'a CanisterState) -> Vec<Image<'a>> {
+    let mut snapshot_ids: Vec<_> = state
+        .canister_snapshots
+        .list_snapshots(canister.canister_id())
+        .into_iter()
+        .map(|(snapshot_id, _)| snapshot_id)
+        .collect();
+    snapshot_ids.sort();
+    let mut images: Vec<_> = snapshot_ids
+        .into_iter()
+        .filter_map(|snapshot_id| {
+            state
+                .canister_snapshots
+                .get(snapshot_id)
+                .map(|snapshot| Image::Snapshot(snapshot_id, snapshot.as_ref()))
+        })
+        .collect();
+    images.push(Image::Current(canister));
+    images
+}
+
+fn module_piece_size() -> usize {
+    wasm_chunk_store::chunk_size().get() as usize
+}
+
+fn num_batches(page_map: &PageMap) -> u64 {
+    (page_map.num_host_pages() as u64).div_ceil(PAGES_PER_STEP)
+}
+
+fn plan(images: &[Image]) -> Vec<PlannedStep> {
+    let mut steps = vec![PlannedStep::Metadata, PlannedStep::Cycles];
+    for (image_index, image) in images.iter().enumerate() {
+        if let Some(module) = image.module() {
+            let num_pieces = module.len().div_ceil(module_piece_size());
+            steps.extend((0..num_pieces).map(|index| PlannedStep::ModuleChunk {
+                image: image_index,
+                index,
+            }));
+            steps.push(PlannedStep::Module { image: image_index });
+        }
+        if let Some((page_map, _)) = image.wasm_memory() {
+            steps.extend(
+                (0..num_batches(page_map)).map(|batch| PlannedStep::WasmMemoryPages {
+                    image: image_index,
+                    batch,
+                }),
+            );
+        }
+        if let Some((page_map, _)) = image.stable_memory() {
+            steps.extend(
+                (0..num_batches(page_map)).map(|batch| PlannedStep::StableMemoryPages {
+                    image: image_index,
+                    batch,
+                }),
+            );
+        }
+        steps.extend((0..image.chunk_store().keys().count()).map(|index| {
+            PlannedStep::ChunkStoreChunk {
+                image: image_index,
+                index,
+            }
+        }));
+        steps.push(match image {
+            Image::Snapshot(..) => PlannedStep::Snapshot { image: image_index },
+            Image::Current(_) => PlannedStep::ExecutionState,
+        });
+    }
+    steps.push(PlannedStep::Cycles);
+    steps
+}
+
+/// Returns the non-zero pages of the given batch of `page_map`.
+fn memory_pages(page_map: &PageMap, batch: u64) -> Vec<MigratedPage> {
+    let end = (page_map.num_host_pages() as u64).min((batch + 1) * PAGES_PER_STEP);
+    (batch * PAGES_PER_STEP..end)
+        .filter_map(|index| {
+            let bytes = page_map.get_page(PageIndex::new(index));
+            bytes.iter().any(|byte| *byte != 0).then(|| MigratedPage {
+                index,
+                bytes: ByteBuf::from(bytes.to_vec()),
+            })
+        })
+        .collect()
+}
+
+fn module_chunk_hashes(module: &CanisterModule) -> Vec<ByteBuf> {
+    module
+        .as_slice()
+        .chunks(module_piece_size())
+        .map(|piece| ByteBuf::from(ic_crypto_sha2::Sha256::hash(piece).to_vec()))
+        .collect()
+}
+
+fn metadata(canister: &CanisterState) -> CanisterMigrationMetadata {
+    let system_state = &canister.system_state;
+    let history = system_state.get_canister_history();
+    CanisterMigrationMetadata {
+        controllers: system_state.controllers.iter().copied().collect(),
+        compute_allocation: canister.scheduler_state.compute_allocation.as_percent(),
+        memory_allocation: system_state.memory_allocation.bytes().get(),
+        freezing_threshold: system_state.freeze_threshold.get(),
+        reserved_cycles_limit: system_state
+            .reserved_balance_limit()
+            .map(|limit| limit.get()),
+        wasm_memory_limit: system_state.wasm_memory_limit.map(|limit| limit.get()),
+        wasm_memory_threshold: system_state.wasm_memory_threshold.get(),
+        log_visibility: system_state.log_visibility.clone(),
+        environment_variables: system_state
+            .environment_variables
+            .iter()
+            .map(|(name, value)| EnvironmentVariable {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .collect(),
+        global_timer: match system_state.global_timer {
+            CanisterTimer::Inactive => None,
+            CanisterTimer::Active(time) => Some(time.as_nanos_since_unix_epoch()),
+        },
+        canister_version: system_state.canister_version,
+        next_snapshot_id: system_state.next_snapshot_id,
+        total_num_changes: history.get_total_num_changes(),
+        changes: history
+            .get_changes(usize::MAX)
+            .map(|change| change.as_ref().clone())
+            .collect(),
+    }
+}
+
+fn balances(canister: &CanisterState) -> (Cycles, Cycles) {
+    (
+        canister.system_state.debited_balance(),
+        canister.system_state.reserved_balance(),
+    )
+}
+
+fn materialize(
+    step: PlannedStep,
+    canister: &CanisterState,
+    images: &[Image],
+) -> CanisterMigrationStep {
+    match step {
+        PlannedStep::Metadata => CanisterMigrationStep::Metadata(metadata(canister)),
+        PlannedStep::Cycles => {
+            let (balance, reserved_balance) = balances(canister);
+            CanisterMigrationStep::Cycles(MigratedCycles {
+                balance: balance.get(),
+                reserved_balance: reserved_balance.get(),
+            })
+        }
+        PlannedStep::ModuleChunk { image, index } => {
+            let module = images[image].module().unwrap().as_slice();
+            let start = index * module_piece_size();
+            let end = module.len().min(start + module_piece_size());
+            CanisterMigrationStep::ModuleChunk(ByteBuf::from(module[start..end].to_vec()))
+        }
+        PlannedStep::Module { image } => {
+            let image = &images[image];
+            CanisterMigrationStep::Module(MigratedModule {
+                chunk_hashes: module_chunk_hashes(image.module().unwrap()),
+                wasm_memory_size: image.wasm_memory().unwrap().1.get() as u64,
+                stable_memory_size: image.stable_memory().unwrap().1.get() as u64,
+            })
+        }
+        PlannedStep::WasmMemoryPages { image, batch } => CanisterMigrationStep::WasmMemoryPages(
+            memory_pages(images[image].wasm_memory().unwrap().0, batch),
+        ),
+        PlannedStep::StableMemoryPages { image, batch } => {
+            CanisterMigrationStep::StableMemoryPages(memory_pages(
+                images[image].stable_memory().unwrap().0,
+                batch,
+            ))
+        }
+        PlannedStep::ChunkStoreChunk { image, index } => {
+            let chunk_store = images[image].chunk_store();
+            let hash = chunk_store.keys().nth(index).unwrap();
+            let data: Vec<u8> = chunk_store
+                .get_chunk_data(hash)
+                .unwrap()
+                .flat_map(|piece| piece.iter().copied())
+                .collect();
+            CanisterMigrationStep::ChunkStoreChunk(ByteBuf::from(data))
+        }
+        PlannedStep::Snapshot { image } => match &images[image] {
+            Image::Snapshot(snapshot_id, snapshot) => {
+                CanisterMigrationStep::Snapshot(MigratedSnapshot {
+                    snapshot_id: ByteBuf::from(snapshot_id.to_vec()),
+                    taken_at_timestamp: snapshot.taken_at_timestamp().as_nanos_since_unix_epoch(),
+                    canister_version: snapshot.canister_version(),
+                    certified_data: ByteBuf::from(snapshot.certified_data().clone()),
+                    size: snapshot.size().get(),
+                })
+            }
+            Image::Current(_) => unreachable!("The current state is not a snapshot"),
+        },
+        PlannedStep::ExecutionState => {
+            CanisterMigrationStep::ExecutionState(MigratedExecutionState {
+                certified_data: ByteBuf::from(canister.system_state.certified_data.clone()),
+                exported_globals: canister.execution_state.as_ref().map(|execution_state| {
+                    execution_state
+                        .exported_globals
+                        .iter()
+                        .map(|global| match global {
+                            Global::I32(value) => MigratedGlobal::I32(*value),
+                            Global::I64(value) => MigratedGlobal::I64(*value),
+                            Global::F32(value) => MigratedGlobal::F32(*value),
+                            Global::F64(value) => MigratedGlobal::F64(*value),
+                            Global::V128(value) => MigratedGlobal::V128(*value),
+                        })
+                        .collect()
+                }),
+            })
+        }
+    }
+}
+
+fn import_global(global: MigratedGlobal) -> Global {
+    match global {
+        MigratedGlobal::I32(value) => Global::I32(value),
+        MigratedGlobal::I64(value) => Global::I64(value),
+        MigratedGlobal::F32(value) => Global::F32(value),
+        MigratedGlobal::F64(value) => Global::F64(value),
+        MigratedGlobal::V128(value) => Global::V128(value),
+    }
+}
+
+/// Returns true if the registry lists `canister_id` as migrating directly
+/// from `source` to `target`.
+fn is_migrating(
+    state: &ReplicatedState,
+    canister_id: CanisterId,
+    source: SubnetId,
+    target: SubnetId,
+) -> bool {
+    source != target
+        && state
+            .metadata
+            .network_topology
+            .canister_migrations
+            .lookup(canister_id)
+            .is_some_and(|trace| trace.windows(2).any(|hop| hop == [source, target]))
+}
+
+fn import_failed(canister_id: CanisterId, message: impl Into<String>) -> CanisterManagerError {
+    CanisterManagerError::CanisterMigrationFailed {
+        canister_id,
+        message: message.into(),
+    }
+}
+
+fn import_pages(
+    canister_id: CanisterId,
+    memory: &mut Memory,
+    pages: Vec<MigratedPage>,
+) -> Result<(), CanisterManagerError> {
+    let max_pages = (memory.size.get() * (WASM_PAGE_SIZE_IN_BYTES / PAGE_SIZE)) as u64;
+    let mut updates = Vec::with_capacity(pages.len());
+    for page in &pages {
+        if page.index >= max_pages {
+            return Err(import_failed(
+                canister_id,
+                format!("Page {} is out of bounds", page.index),
+            ));
+        }
+        let bytes = page.bytes.as_slice().try_into().map_err(|_| {
+            import_failed(
+                canister_id,
+                format!("Page {} has an invalid size", page.index),
+            )
+        })?;
+        updates.push((PageIndex::new(page.index), bytes));
+    }
+    memory.page_map.update(&updates);
+    Ok(())
+}
+
+impl CanisterManager {
+    /// Starts migrating a stopped canister to `target_subnet_id`.
+    ///
+    /// The `migrate_canister` call is replied to once the migration is over.
+    pub(crate) fn migrate_canister(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        target_subnet_id: SubnetId,
+        call: &CanisterCall,
+        state: &mut ReplicatedState,
+    ) -> Result<(), CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        validate_controller(canister, &sender)?;
+        if canister.status() != CanisterStatusType::Stopped {
+            return Err(CanisterManagerError::MigrateCanisterNotStopped(canister_id));
+        }
+        if canister.has_input() || canister.has_output() {
+            return Err(CanisterManagerError::MigrateCanisterQueueNotEmpty(
+                canister_id,
+            ));
+        }
+        if state
+            .metadata
+            .subnet_call_context_manager
+            .canister_migration_context(canister_id)
+            .is_some()
+        {
+            return Err(CanisterManagerError::CanisterMigrationInProgress(
+                canister_id,
+            ));
+        }
+        if !is_migrating(
+            state,
+            canister_id,
+            self.config.own_subnet_id,
+            target_subnet_id,
+        ) {
+            return Err(CanisterManagerError::CanisterMigrationNotPrepared {
+                canister_id,
+                target_subnet_id,
+            });
+        }
+
+        info!(
+            self.log,
+            "Migrating canister {} to subnet {}.", canister_id, target_subnet_id
+        );
+        let time = state.time();
+        self.send_canister_migration_step(
+            CanisterMigrationContext {
+                call: call.clone(),
+                canister_id,
+                target_subnet_id,
+                step: 0,
+                sent_balances: None,
+                time,
+            },
+            state,
+        )
+    }
+
+    /// Handles the response of the target subnet to the step of the migration
+    /// described by `context`.
+    ///
+    /// Returns the `migrate_canister` call together with its result once the
+    /// migration is over.
+    pub(crate) fn on_canister_migration_response(
+        &self,
+        mut context: CanisterMigrationContext,
+        response: &Payload,
+        state: &mut ReplicatedState,
+    ) -> Option<(CanisterCall, Result<(), CanisterManagerError>)> {
+        let canister_id = context.canister_id;
+        if let Payload::Reject(reject) = response {
+            info!(
+                self.log,
+                "Migration of canister {} to subnet {} failed: {}",
+                canister_id,
+                context.target_subnet_id,
+                reject.message()
+            );
+            return Some((
+                context.call,
+                Err(import_failed(canister_id, reject.message().clone())),
+            ));
+        }
+        let Some(canister) = state.canister_state(&canister_id) else {
+            return Some((
+                context.call,
+                Err(CanisterManagerError::CanisterNotFound(canister_id)),
+            ));
+        };
+
+        match context.sent_balances {
+            Some(sent_balances) if sent_balances == balances(canister) => {
+                state.take_canister_state(&canister_id);
+                state.canister_snapshots.delete_snapshots(canister_id);
+                info!(
+                    self.log,
+                    "Migrated canister {} to subnet {}.", canister_id, context.target_subnet_id
+                );
+                return Some((context.call, Ok(())));
+            }
+            // The balances changed while the last step was in flight: send
+            // them again.
+            Some(_) => {}
+            None => context.step += 1,
+        }
+        context.sent_balances = None;
+
+        let call = context.call.clone();
+        self.send_canister_migration_step(context, state)
+            .err()
+            .map(|err| (call, Err(err)))
+    }
+
+    /// Sends the step of the migration described by `context` to the target
+    /// subnet and records the context until the response arrives.
+    fn send_canister_migration_step(
+        &self,
+        mut context: CanisterMigrationContext,
+        state: &mut ReplicatedState,
+    ) -> Result<(), CanisterManagerError> {
+        let canister_id = context.canister_id;
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        let images = images(state, canister);
+        let plan = plan(&images);
+        let step = plan[context.step as usize];
+        let payload = materialize(step, canister, &images);
+        if context.step as usize + 1 == plan.len() {
+            context.sent_balances = Some(balances(canister));
+        }
+        let payload = ImportCanisterStateArgs {
+            canister_id: canister_id.get(),
+            step: payload,
+        }
+        .encode();
+
+        let sender = CanisterId::from(self.config.own_subnet_id);
+        let receiver = CanisterId::from(context.target_subnet_id);
+        let callback_id = state
+            .metadata
+            .subnet_call_context_manager
+            .push_canister_migration_context(context);
+        let request = Request {
+            receiver,
+            sender,
+            sender_reply_callback: callback_id,
+            payment: Cycles::zero(),
+            method_name: Ic00Method::ImportCanisterState.to_string(),
+            method_payload: payload,
+            metadata: None,
+            deadline: NO_DEADLINE,
+        };
+        let time = state.time();
+        state
+            .push_subnet_output_request(Arc::new(request), time)
+            .map_err(|(err, _)| {
+                state
+                    .metadata
+                    .subnet_call_context_manager
+                    .canister_migration_contexts
+                    .remove(&callback_id);
+                import_failed(canister_id, err.to_string())
+            })
+    }
+
+    /// Rejects management canister calls that would change `canister_id`
+    /// while it is being migrated to another subnet.
+    pub(crate) fn validate_no_migration_in_progress(
+        &self,
+        method: Ic00Method,
+        canister_id: Option<CanisterId>,
+        state: &ReplicatedState,
+    ) -> Result<(), CanisterManagerError> {
+        let Some(canister_id) = canister_id else {
+            return Ok(());
+        };
+        match method {
+            Ic00Method::InstallCode
+            | Ic00Method::InstallChunkedCode
+            | Ic00Method::UninstallCode
+            | Ic00Method::UpdateSettings
+            | Ic00Method::StartCanister
+            | Ic00Method::DeleteCanister
+            | Ic00Method::UploadChunk
+            | Ic00Method::ClearChunkStore
+            | Ic00Method::TakeCanisterSnapshot
+            | Ic00Method::LoadCanisterSnapshot
+            | Ic00Method::DeleteCanisterSnapshot
+            | Ic00Method::MigrateCanister
+                if state
+                    .metadata
+                    .subnet_call_context_manager
+                    .canister_migration_context(canister_id)
+                    .is_some() =>
+            {
+                Err(CanisterManagerError::CanisterMigrationInProgress(
+                    canister_id,
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies a step of the import of a canister migrated from the subnet
+    /// `sender`. Anything imported so far is discarded if the step fails.
+    ///
+    /// Returns the number of instructions used.
+    pub(crate) fn import_canister_state(
+        &self,
+        sender: PrincipalId,
+        args: ImportCanisterStateArgs,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> (Result<(), CanisterManagerError>, NumInstructions) {
+        let canister_id = args.get_canister_id();
+        let own_subnet_id = self.config.own_subnet_id;
+        let source = SubnetId::from(sender);
+        if state
+            .metadata
+            .network_topology
+            .routing_table
+            .route(canister_id.get())
+            != Some(source)
+            || !is_migrating(state, canister_id, source, own_subnet_id)
+        {
+            return (
+                Err(CanisterManagerError::CanisterMigrationNotPrepared {
+                    canister_id,
+                    target_subnet_id: own_subnet_id,
+                }),
+                NumInstructions::from(0),
+            );
+        }
+
+        let result = match args.step {
+            CanisterMigrationStep::Metadata(metadata) => self
+                .import_canister_metadata(canister_id, metadata, state, round_limits)
+                .map(|()| NumInstructions::from(0)),
+            step => self.import_canister_step(canister_id, step, state, round_limits),
+        };
+        match result {
+            Ok(instructions_used) => (Ok(()), instructions_used),
+            Err(err) => {
+                self.discard_imported_canister(canister_id, state, round_limits);
+                (Err(err), NumInstructions::from(0))
+            }
+        }
+    }
+
+    /// Removes a partially imported canister and its snapshots.
+    fn discard_imported_canister(
+        &self,
+        canister_id: CanisterId,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) {
+        if let Some(canister) = state.take_canister_state(&canister_id) {
+            round_limits.subnet_available_memory.increment(
+                canister
+                    .memory_allocation()
+                    .bytes()
+                    .max(canister.memory_usage()),
+                NumBytes::from(0),
+                NumBytes::from(0),
+            );
+            round_limits.compute_allocation_used = round_limits
+                .compute_allocation_used
+                .saturating_sub(canister.compute_allocation().as_percent());
+        }
+        state.canister_snapshots.delete_snapshots(canister_id);
+    }
+
+    fn import_canister_metadata(
+        &self,
+        canister_id: CanisterId,
+        metadata: CanisterMigrationMetadata,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<(), CanisterManagerError> {
+        self.discard_imported_canister(canister_id, state, round_limits);
+
+        let compute_allocation = ComputeAllocation::try_from(metadata.compute_allocation)
+            .map_err(|err| import_failed(canister_id, format!("{:?}", err)))?;
+        let available_compute_allocation = self
+            .config
+            .compute_capacity
+            .saturating_sub(round_limits.compute_allocation_used)
+            .saturating_sub(1);
+        if compute_allocation.as_percent() > available_compute_allocation {
+            return Err(CanisterManagerError::SubnetComputeCapacityOverSubscribed {
+                requested: compute_allocation,
+                available: available_compute_allocation,
+            });
+        }
+        let memory_allocation =
+            MemoryAllocation::try_from(NumBytes::new(metadata.memory_allocation))
+                .map_err(|err| import_failed(canister_id, format!("{:?}", err)))?;
+
+        let mut system_state = SystemState::new_running(
+            canister_id,
+            canister_id.get(),
+            Cycles::zero(),
+            NumSeconds::new(metadata.freezing_threshold),
+            Arc::clone(&self.fd_factory),
+        );
+        system_state.status = CanisterStatus::Stopped;
+        system_state.controllers = metadata.controllers.into_iter().collect();
+        system_state.memory_allocation = memory_allocation;
+        if let Some(limit) = metadata.reserved_cycles_limit {
+            system_state.set_reserved_balance_limit(Cycles::new(limit));
+        }
+        system_state.wasm_memory_limit = metadata.wasm_memory_limit.map(NumBytes::new);
+        system_state.wasm_memory_threshold = NumBytes::new(metadata.wasm_memory_threshold);
+        system_state.log_visibility = metadata.log_visibility;
+        system_state.environment_variables = metadata
+            .environment_variables
+            .into_iter()
+            .map(|variable| (variable.name, variable.value))
+            .collect();
+        system_state.global_timer = match metadata.global_timer {
+            None => CanisterTimer::Inactive,
+            Some(nanos) => CanisterTimer::Active(Time::from_nanos_since_unix_epoch(nanos)),
+        };
+        system_state.canister_version = metadata.canister_version;
+        system_state.next_snapshot_id = metadata.next_snapshot_id;
+        system_state.set_migrated_canister_history(CanisterHistory::new(
+            metadata.changes,
+            metadata.total_num_changes,
+        ));
+
+        let mut scheduler_state = SchedulerState::new(state.metadata.batch_time);
+        scheduler_state.compute_allocation = compute_allocation;
+        let canister = CanisterState::new(system_state, None, scheduler_state);
+
+        let memory = canister
+            .memory_allocation()
+            .bytes()
+            .max(canister.memory_usage());
+        self.reserve_imported_memory(memory, round_limits)?;
+        round_limits.compute_allocation_used = round_limits
+            .compute_allocation_used
+            .saturating_add(compute_allocation.as_percent());
+        state.put_canister_state(canister);
+        Ok(())
+    }
+
+    fn import_canister_step(
+        &self,
+        canister_id: CanisterId,
+        step: CanisterMigrationStep,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<NumInstructions, CanisterManagerError> {
+        let mut canister = state
+            .take_canister_state(&canister_id)
+            .ok_or(CanisterManagerError::CanisterNotFound(canister_id))?;
+        let memory_before = canister
+            .memory_allocation()
+            .bytes()
+            .max(canister.memory_usage());
+        // The canister is put back below, reserving the memory it uses then.
+        // On error it is dropped, as is the rest of the import.
+        round_limits.subnet_available_memory.increment(
+            memory_before,
+            NumBytes::from(0),
+            NumBytes::from(0),
+        );
+
+        let result = self
+            .apply_import_step(&mut canister, step, state, round_limits)
+            .and_then(|instructions_used| {
+                let memory_after = canister
+                    .memory_allocation()
+                    .bytes()
+                    .max(canister.memory_usage());
+                self.reserve_imported_memory(memory_after, round_limits)?;
+                Ok(instructions_used)
+            });
+        match result {
+            Ok(_) => state.put_canister_state(canister),
+            // The memory of the dropped canister was released above.
+            Err(_) => {
+                round_limits.compute_allocation_used = round_limits
+                    .compute_allocation_used
+                    .saturating_sub(canister.compute_allocation().as_percent())
+            }
+        }
+        result
+    }
+
+    fn apply_import_step(
+        &self,
+        canister: &mut CanisterState,
+        step: CanisterMigrationStep,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<NumInstructions, CanisterManagerError> {
+        let canister_id = canister.canister_id();
+        let mut instructions_used = NumInstructions::from(0);
+        match step {
+            CanisterMigrationStep::Metadata(_) => unreachable!("Handled by the caller"),
+            CanisterMigrationStep::Cycles(cycles) => canister.system_state.set_migrated_balances(
+                Cycles::new(cycles.balance),
+                Cycles::new(cycles.reserved_balance),
+            ),
+            CanisterMigrationStep::ModuleChunk(bytes)
+            | CanisterMigrationStep::ChunkStoreChunk(bytes) => {
+                canister
+                    .system_state
+                    .wasm_chunk_store
+                    .insert_chunk(NumBytes::new(u64::MAX), &bytes)
+                    .map_err(|message| CanisterManagerError::WasmChunkStoreError { message })?;
+            }
+            CanisterMigrationStep::Module(module) => {
+                instructions_used = self.import_module(canister, module, state, round_limits)?;
+            }
+            CanisterMigrationStep::WasmMemoryPages(pages) => {
+                let execution_state = canister
+                    .execution_state
+                    .as_mut()
+                    .ok_or_else(|| import_failed(canister_id, "No Wasm module was imported"))?;
+                import_pages(canister_id, &mut execution_state.wasm_memory, pages)?;
+            }
+            CanisterMigrationStep::StableMemoryPages(pages) => {
+                let execution_state = canister
+                    .execution_state
+                    .as_mut()
+                    .ok_or_else(|| import_failed(canister_id, "No Wasm module was imported"))?;
+                import_pages(canister_id, &mut execution_state.stable_memory, pages)?;
+            }
+            CanisterMigrationStep::Snapshot(snapshot) => {
+                self.import_snapshot(canister, snapshot, state)?;
+            }
+            CanisterMigrationStep::ExecutionState(execution_state) => {
+                canister.system_state.certified_data = execution_state.certified_data.into_vec();
+                match (
+                    canister.execution_state.as_mut(),
+                    execution_state.exported_globals,
+                ) {
+                    (Some(execution_state), Some(globals)) => {
+                        let globals: Vec<_> = globals.into_iter().map(import_global).collect();
+                        if globals.len() != execution_state.exported_globals.len()
+                            || globals
+                                .iter()
+                                .zip(execution_state.exported_globals.iter())
+                                .any(|(new, old)| new.type_name() != old.type_name())
+                        {
+                            return Err(import_failed(
+                                canister_id,
+                                "The exported globals do not match the Wasm module",
+                            ));
+                        }
+                        execution_state.exported_globals = globals;
+                    }
+                    (None, None) => {}
+                    _ => {
+                        return Err(import_failed(
+                            canister_id,
+                            "The exported globals do not match the Wasm module",
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(instructions_used)
+    }
+
+    fn reserve_imported_memory(
+        &self,
+        memory: NumBytes,
+        round_limits: &mut RoundLimits,
+    ) -> Result<(), CanisterManagerError> {
+        round_limits
+            .subnet_available_memory
+            .try_decrement(memory, NumBytes::from(0), NumBytes::from(0))
+            .map_err(
+                |_| CanisterManagerError::SubnetMemoryCapacityOverSubscribed {
+                    requested: memory,
+                    available: NumBytes::from(
+                        round_limits
+                            .subnet_available_memory
+                            .get_execution_memory()
+                            .max(0) as u64,
+                    ),
+                },
+            )
+    }
+
+    fn import_module(
+        &self,
+        canister: &mut CanisterState,
+        module: MigratedModule,
+        state: &ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<NumInstructions, CanisterManagerError> {
+        let canister_id = canister.canister_id();
+        if canister.execution_state.is_some() {
+            return Err(import_failed(
+                canister_id,
+                "A Wasm module was already imported",
+            ));
+        }
+        let mut bytes = vec![];
+        let chunk_store = &canister.system_state.wasm_chunk_store;
+        for hash in module.chunk_hashes {
+            let hash: WasmChunkHash = hash
+                .as_slice()
+                .try_into()
+                .map_err(|_| import_failed(canister_id, "Invalid module chunk hash"))?;
+            let data = chunk_store
+                .get_chunk_data(&hash)
+                .ok_or_else(|| import_failed(canister_id, "Missing module chunk"))?;
+            data.for_each(|piece| bytes.extend_from_slice(piece));
+        }
+        canister.system_state.wasm_chunk_store = WasmChunkStore::new(Arc::clone(&self.fd_factory));
+
+        let module_bytes = CanisterModule::new(bytes);
+        let compilation_cost_handling = if state
+            .metadata
+            .expected_compiled_wasms
+            .contains(&WasmHash::from(&module_bytes))
+        {
+            CompilationCostHandling::CountReducedAmount
+        } else {
+            CompilationCostHandling::CountFullAmount
+        };
+        let (instructions_used, execution_state) = self.hypervisor.create_execution_state(
+            module_bytes,
+            "NOT_USED".into(),
+            canister_id,
+            canister.system_state.canister_profile.is_some(),
+            round_limits,
+            compilation_cost_handling,
+        );
+        let mut execution_state =
+            execution_state.map_err(|err| CanisterManagerError::from((canister_id, err)))?;
+        execution_state.wasm_memory = Memory::new(
+            PageMap::new(Arc::clone(&self.fd_factory)),
+            NumWasmPages::new(module.wasm_memory_size as usize),
+        );
+        execution_state.stable_memory = Memory::new(
+            PageMap::new(Arc::clone(&self.fd_factory)),
+            NumWasmPages::new(module.stable_memory_size as usize),
+        );
+        canister.execution_state = Some(execution_state);
+        Ok(instructions_used)
+    }
+
+    fn import_snapshot(
+        &self,
+        canister: &mut CanisterState,
+        snapshot: MigratedSnapshot,
+        state: &mut ReplicatedState,
+    ) -> Result<(), CanisterManagerError> {
+        let canister_id = canister.canister_id();
+        let snapshot_id = SnapshotId::try_from(&snapshot.snapshot_id.into_vec())
+            .map_err(|_| import_failed(canister_id, "Invalid snapshot ID"))?;
+        if snapshot_id.get_canister_id() != canister_id
+            || state.canister_snapshots.get(snapshot_id).is_some()
+        {
+            return Err(import_failed(
+                canister_id,
+                format!("Unexpected snapshot {}", snapshot_id),
+            ));
+        }
+        let execution_state = canister
+            .execution_state
+            .take()
+            .ok_or_else(|| import_failed(canister_id, "No Wasm module was imported"))?;
+        let chunk_store = std::mem::replace(
+            &mut canister.system_state.wasm_chunk_store,
+            WasmChunkStore::new(Arc::clone(&self.fd_factory)),
+        );
+        let size = NumBytes::new(snapshot.size);
+        let new_snapshot = CanisterSnapshot::new(
+            canister_id,
+            Time::from_nanos_since_unix_epoch(snapshot.taken_at_timestamp),
+            snapshot.canister_version,
+            snapshot.certified_data.into_vec(),
+            chunk_store,
+            ExecutionStateSnapshot {
+                wasm_binary: execution_state.wasm_binary.binary.clone(),
+                stable_memory: PageMemory::from(&execution_state.stable_memory),
+                wasm_memory: PageMemory::from(&execution_state.wasm_memory),
+            },
+            size,
+        );
+        state
+            .canister_snapshots
+            .push(snapshot_id, Arc::new(new_snapshot));
+        canister.system_state.snapshots_memory_usage += size;
+        Ok(())
+    }
+}
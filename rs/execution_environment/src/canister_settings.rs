@@ -2,12 +2,13 @@ use ic_base_types::{NumBytes, NumSeconds};
 use ic_cycles_account_manager::{CyclesAccountManager, ResourceSaturation};
 use ic_error_types::{ErrorCode, UserError};
 use ic_interfaces::execution_environment::SubnetAvailableMemory;
-use ic_management_canister_types::{CanisterSettingsArgs, LogVisibilityV2};
+use ic_management_canister_types::{CanisterSettingsArgs, EnvironmentVariable, LogVisibilityV2};
 use ic_types::{
     ComputeAllocation, Cycles, InvalidComputeAllocationError, InvalidMemoryAllocationError,
     MemoryAllocation, PrincipalId,
 };
 use num_traits::cast::ToPrimitive;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 
 use crate::canister_manager::CanisterManagerError;
@@ -15,6 +16,12 @@ use crate::canister_manager::CanisterManagerError;
 /// These limit comes from the spec and is not expected to change,
 /// which is why it is not part of the replica config.
 const MAX_WASM_MEMORY_LIMIT: u64 = 1 << 48;
+/// Maximum number of environment variables a canister can have.
+pub(crate) const MAX_ENVIRONMENT_VARIABLES: usize = 20;
+/// Maximum length in bytes of an environment variable name.
+pub(crate) const MAX_ENVIRONMENT_VARIABLE_NAME_LENGTH: usize = 128;
+/// Maximum length in bytes of an environment variable value.
+pub(crate) const MAX_ENVIRONMENT_VARIABLE_VALUE_LENGTH: usize = 128;
 /// Struct used for decoding CanisterSettingsArgs
 #[derive(Default)]
 pub(crate) struct CanisterSettings {
@@ -27,9 +34,12 @@ pub(crate) struct CanisterSettings {
     pub(crate) reserved_cycles_limit: Option<Cycles>,
     pub(crate) log_visibility: Option<LogVisibilityV2>,
     pub(crate) wasm_memory_limit: Option<NumBytes>,
+    pub(crate) environment_variables: Option<BTreeMap<String, String>>,
+    pub(crate) profiling: Option<bool>,
 }
 
 impl CanisterSettings {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         controllers: Option<Vec<PrincipalId>>,
         compute_allocation: Option<ComputeAllocation>,
@@ -39,6 +49,8 @@ impl CanisterSettings {
         reserved_cycles_limit: Option<Cycles>,
         log_visibility: Option<LogVisibilityV2>,
         wasm_memory_limit: Option<NumBytes>,
+        environment_variables: Option<BTreeMap<String, String>>,
+        profiling: Option<bool>,
     ) -> Self {
         Self {
             controllers,
@@ -49,6 +61,8 @@ impl CanisterSettings {
             reserved_cycles_limit,
             log_visibility,
             wasm_memory_limit,
+            environment_variables,
+            profiling,
         }
     }
 
@@ -83,6 +97,45 @@ impl CanisterSettings {
     pub fn wasm_memory_limit(&self) -> Option<NumBytes> {
         self.wasm_memory_limit
     }
+
+    pub fn environment_variables(&self) -> Option<&BTreeMap<String, String>> {
+        self.environment_variables.as_ref()
+    }
+
+    pub fn profiling(&self) -> Option<bool> {
+        self.profiling
+    }
+}
+
+/// Checks the size limits of the given environment variables and converts
+/// them into a map. Variable names must be unique.
+fn validate_environment_variables(
+    environment_variables: Vec<EnvironmentVariable>,
+) -> Result<BTreeMap<String, String>, UpdateSettingsError> {
+    if environment_variables.len() > MAX_ENVIRONMENT_VARIABLES {
+        return Err(UpdateSettingsError::TooManyEnvironmentVariables {
+            provided: environment_variables.len(),
+        });
+    }
+    let mut result = BTreeMap::new();
+    for EnvironmentVariable { name, value } in environment_variables {
+        if name.len() > MAX_ENVIRONMENT_VARIABLE_NAME_LENGTH {
+            return Err(UpdateSettingsError::EnvironmentVariableNameTooLong {
+                provided: name.len(),
+            });
+        }
+        if value.len() > MAX_ENVIRONMENT_VARIABLE_VALUE_LENGTH {
+            return Err(UpdateSettingsError::EnvironmentVariableValueTooLong {
+                name,
+                provided: value.len(),
+            });
+        }
+        if result.contains_key(&name) {
+            return Err(UpdateSettingsError::DuplicateEnvironmentVariable { name });
+        }
+        result.insert(name, value);
+    }
+    Ok(result)
 }
 
 impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
@@ -146,6 +199,11 @@ impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
             None => None,
         };
 
+        let environment_variables = input
+            .environment_variables
+            .map(validate_environment_variables)
+            .transpose()?;
+
         Ok(CanisterSettings::new(
             input
                 .controllers
@@ -157,6 +215,8 @@ impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
             reserved_cycles_limit,
             input.log_visibility,
             wasm_memory_limit,
+            environment_variables,
+            input.profiling,
         ))
     }
 }
@@ -181,6 +241,8 @@ pub(crate) struct CanisterSettingsBuilder {
     reserved_cycles_limit: Option<Cycles>,
     log_visibility: Option<LogVisibilityV2>,
     wasm_memory_limit: Option<NumBytes>,
+    environment_variables: Option<BTreeMap<String, String>>,
+    profiling: Option<bool>,
 }
 
 #[allow(dead_code)]
@@ -195,6 +257,8 @@ impl CanisterSettingsBuilder {
             reserved_cycles_limit: None,
             log_visibility: None,
             wasm_memory_limit: None,
+            environment_variables: None,
+            profiling: None,
         }
     }
 
@@ -208,6 +272,8 @@ impl CanisterSettingsBuilder {
             reserved_cycles_limit: self.reserved_cycles_limit,
             log_visibility: self.log_visibility,
             wasm_memory_limit: self.wasm_memory_limit,
+            environment_variables: self.environment_variables,
+            profiling: self.profiling,
         }
     }
 
@@ -266,6 +332,23 @@ impl CanisterSettingsBuilder {
             ..self
         }
     }
+
+    pub fn with_environment_variables(
+        self,
+        environment_variables: BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            environment_variables: Some(environment_variables),
+            ..self
+        }
+    }
+
+    pub fn with_profiling(self, profiling: bool) -> Self {
+        Self {
+            profiling: Some(profiling),
+            ..self
+        }
+    }
 }
 
 pub enum UpdateSettingsError {
@@ -275,6 +358,10 @@ pub enum UpdateSettingsError {
     ReservedCyclesLimitOutOfRange { provided: candid::Nat },
     WasmMemoryLimitOutOfRange { provided: candid::Nat },
     WasmMemoryThresholdOutOfRange { provided: candid::Nat },
+    TooManyEnvironmentVariables { provided: usize },
+    EnvironmentVariableNameTooLong { provided: usize },
+    EnvironmentVariableValueTooLong { name: String, provided: usize },
+    DuplicateEnvironmentVariable { name: String },
 }
 
 impl From<UpdateSettingsError> for UserError {
@@ -324,6 +411,33 @@ impl From<UpdateSettingsError> for UserError {
                     provided
                 ),
             ),
+            UpdateSettingsError::TooManyEnvironmentVariables { provided } => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!(
+                    "Number of environment variables expected to be at most {}, got {}",
+                    MAX_ENVIRONMENT_VARIABLES, provided
+                ),
+            ),
+            UpdateSettingsError::EnvironmentVariableNameTooLong { provided } => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!(
+                    "Environment variable name expected to be at most {} bytes long, got {}",
+                    MAX_ENVIRONMENT_VARIABLE_NAME_LENGTH, provided
+                ),
+            ),
+            UpdateSettingsError::EnvironmentVariableValueTooLong { name, provided } => {
+                UserError::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!(
+                        "Value of environment variable '{}' expected to be at most {} bytes long, got {}",
+                        name, MAX_ENVIRONMENT_VARIABLE_VALUE_LENGTH, provided
+                    ),
+                )
+            }
+            UpdateSettingsError::DuplicateEnvironmentVariable { name } => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!("Duplicate environment variable name '{}'", name),
+            ),
         }
     }
 }
@@ -350,6 +464,8 @@ pub(crate) struct ValidatedCanisterSettings {
     reservation_cycles: Cycles,
     log_visibility: Option<LogVisibilityV2>,
     wasm_memory_limit: Option<NumBytes>,
+    environment_variables: Option<BTreeMap<String, String>>,
+    profiling: Option<bool>,
 }
 
 impl ValidatedCanisterSettings {
@@ -388,6 +504,14 @@ impl ValidatedCanisterSettings {
     pub fn wasm_memory_limit(&self) -> Option<NumBytes> {
         self.wasm_memory_limit
     }
+
+    pub fn environment_variables(&self) -> Option<&BTreeMap<String, String>> {
+        self.environment_variables.as_ref()
+    }
+
+    pub fn profiling(&self) -> Option<bool> {
+        self.profiling
+    }
 }
 
 /// Validates the new canisters settings:
@@ -595,5 +719,7 @@ pub(crate) fn validate_canister_settings(
         reservation_cycles,
         log_visibility: settings.log_visibility().cloned(),
         wasm_memory_limit: settings.wasm_memory_limit(),
+        profiling: settings.profiling(),
+        environment_variables: settings.environment_variables().cloned(),
     })
 }
@@ -113,10 +113,12 @@ pub(crate) fn execute_install(
         }
     };
     let module_hash = wasm_module.module_hash();
+    let canister_profiling = helper.canister().system_state.canister_profile.is_some();
     let (instructions_from_compilation, result) = round.hypervisor.create_execution_state(
         wasm_module,
         layout.raw_path(),
         canister_id,
+        canister_profiling,
         round_limits,
         original.compilation_cost_handling,
     );
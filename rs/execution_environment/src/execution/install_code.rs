@@ -475,6 +475,8 @@ impl InstallCodeHelper {
         if original.mode == CanisterInstallModeV2::Reinstall {
             self.canister.clear_log();
         }
+        // The profile refers to functions of the previous module.
+        self.canister.clear_profile();
 
         DtsInstallCodeResult::Finished {
             canister: self.canister,
@@ -513,6 +515,8 @@ impl InstallCodeHelper {
                 reserved_cycles_limit: None,
                 log_visibility: None,
                 wasm_memory_limit: None,
+                environment_variables: None,
+                profiling: None,
             },
             self.canister.memory_usage(),
             self.canister.message_memory_usage(),
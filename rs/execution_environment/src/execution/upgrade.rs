@@ -285,10 +285,12 @@ fn upgrade_stage_2_and_3a_create_execution_state_and_call_start(
     // Replace the execution state of the canister with a new execution state, but
     // persist the stable memory (if it exists).
     let layout = canister_layout(&original.canister_layout_path, &canister_id);
+    let canister_profiling = helper.canister().system_state.canister_profile.is_some();
     let (instructions_from_compilation, result) = round.hypervisor.create_execution_state(
         wasm_module,
         layout.raw_path(),
         canister_id,
+        canister_profiling,
         round_limits,
         original.compilation_cost_handling,
     );
@@ -14,6 +14,7 @@ use crate::{
     },
     hypervisor::Hypervisor,
     ic00_permissions::Ic00MethodPermissions,
+    message_execution_tracer::{MessageExecutionTracer, TracedResponse},
     metrics::{CallTreeMetrics, CallTreeMetricsImpl, IngressFilterMetrics},
 };
 use candid::Encode;
@@ -35,12 +36,14 @@ use ic_management_canister_types::{
     CanisterChangeOrigin, CanisterHttpRequestArgs, CanisterIdRecord, CanisterInfoRequest,
     CanisterInfoResponse, CanisterStatusType, ClearChunkStoreArgs, ComputeInitialIDkgDealingsArgs,
     CreateCanisterArgs, DeleteCanisterSnapshotArgs, ECDSAPublicKeyArgs, ECDSAPublicKeyResponse,
-    EmptyBlob, InstallChunkedCodeArgs, InstallCodeArgsV2, ListCanisterSnapshotArgs,
-    LoadCanisterSnapshotArgs, MasterPublicKeyId, Method as Ic00Method, NodeMetricsHistoryArgs,
-    Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
-    SchnorrPublicKeyArgs, SchnorrPublicKeyResponse, SetupInitialDKGArgs, SignWithECDSAArgs,
-    SignWithSchnorrArgs, StoredChunksArgs, TakeCanisterSnapshotArgs, UninstallCodeArgs,
-    UpdateSettingsArgs, UploadChunkArgs, IC_00,
+    EmptyBlob, ImportCanisterStateArgs, InstallChunkedCodeArgs, InstallCodeArgsV2,
+    ListCanisterSnapshotArgs, LoadCanisterSnapshotArgs, MasterPublicKeyId, Method as Ic00Method,
+    MigrateCanisterArgs, NodeMetricsHistoryArgs, Payload as Ic00Payload,
+    ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs, SchnorrPublicKeyArgs,
+    SchnorrPublicKeyResponse, SetupInitialDKGArgs, SignWithECDSAArgs, SignWithECDSABatchArgs,
+    SignWithECDSABatchReply, SignWithECDSAReply, SignWithSchnorrArgs, SignWithSchnorrBatchArgs,
+    SignWithSchnorrBatchReply, SignWithSchnorrReply, StoredChunksArgs, TakeCanisterSnapshotArgs,
+    UninstallCodeArgs, UpdateSettingsArgs, UploadChunkArgs, IC_00,
 };
 use ic_metrics::MetricsRegistry;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
@@ -86,6 +89,7 @@ use std::{
     time::{Duration, Instant},
 };
 use strum::ParseError;
+use tracing::{info_span, Span};
 
 #[cfg(test)]
 mod tests;
@@ -333,6 +337,8 @@ pub struct ExecutionEnvironment {
     // parallel and potentially reserving resources. It should be initialized to
     // the number of scheduler cores.
     resource_saturation_scaling: usize,
+    // Records message executions if enabled (disabled by default).
+    message_execution_tracer: Arc<MessageExecutionTracer>,
 }
 
 /// This is a helper enum that indicates whether the current DTS execution of
@@ -418,6 +424,7 @@ impl ExecutionEnvironment {
             own_subnet_type,
             paused_execution_registry: Default::default(),
             resource_saturation_scaling,
+            message_execution_tracer: Default::default(),
         }
     }
 
@@ -471,9 +478,41 @@ impl ExecutionEnvironment {
         round_limits: &mut RoundLimits,
     ) -> (ReplicatedState, Option<NumInstructions>) {
         let since = Instant::now(); // Start logging execution time.
+        let _span = match &msg {
+            CanisterMessage::Ingress(ingress) => {
+                info_span!("ingress_execute_subnet_message", message_id = %ingress.message_id)
+            }
+            CanisterMessage::Request(_) | CanisterMessage::Response(_) => Span::none(),
+        }
+        .entered();
 
         let mut msg = match msg {
             CanisterMessage::Response(response) => {
+                if let Some(context) = state
+                    .metadata
+                    .subnet_call_context_manager
+                    .canister_migration_contexts
+                    .remove(&response.originator_reply_callback)
+                {
+                    if let Some((call, result)) =
+                        self.canister_manager.on_canister_migration_response(
+                            context,
+                            &response.response_payload,
+                            &mut state,
+                        )
+                    {
+                        let refund = call.cycles();
+                        let result = ExecuteSubnetMessageResult::Finished {
+                            response: result
+                                .map(|()| EmptyBlob.encode())
+                                .map_err(|err| err.into()),
+                            refund,
+                        };
+                        state = self.finish_subnet_message_execution(state, call, result, since);
+                    }
+                    return (state, Some(NumInstructions::from(0)));
+                }
+
                 let context = state
                     .metadata
                     .subnet_call_context_manager
@@ -507,13 +546,38 @@ impl ExecutionEnvironment {
                                 .or_default() += 1;
                         }
 
+                        let response_payload = match &context {
+                            SubnetCallContext::SignWithThreshold(
+                                threshold_context @ SignWithThresholdContext {
+                                    batch: Some(entry),
+                                    ..
+                                },
+                            ) => {
+                                match state
+                                    .metadata
+                                    .subnet_call_context_manager
+                                    .complete_sign_with_threshold_batch_entry(
+                                        *entry,
+                                        response.response_payload.clone(),
+                                    ) {
+                                    // Other messages of the batch are still being signed.
+                                    None => return (state, Some(NumInstructions::from(0))),
+                                    Some(replies) => combine_sign_with_threshold_batch_replies(
+                                        threshold_context.is_ecdsa(),
+                                        replies,
+                                    ),
+                                }
+                            }
+                            _ => response.response_payload.clone(),
+                        };
+
                         state.push_subnet_output_response(
                             Response {
                                 originator: request.sender,
                                 respondent: CanisterId::from(self.own_subnet_id),
                                 originator_reply_callback: request.sender_reply_callback,
                                 refund: request.payment,
-                                response_payload: response.response_payload.clone(),
+                                response_payload,
                                 deadline: request.deadline,
                             }
                             .into(),
@@ -548,6 +612,30 @@ impl ExecutionEnvironment {
             }
         }
 
+        if let Ok(method) = method {
+            let effective_canister_id = match &msg {
+                CanisterCall::Request(request) => request.extract_effective_canister_id(),
+                CanisterCall::Ingress(ingress) => ingress.effective_canister_id,
+            };
+            if let Err(err) = self.canister_manager.validate_no_migration_in_progress(
+                method,
+                effective_canister_id,
+                &state,
+            ) {
+                let refund = msg.take_cycles();
+                let state = self.finish_subnet_message_execution(
+                    state,
+                    msg,
+                    ExecuteSubnetMessageResult::Finished {
+                        response: Err(err.into()),
+                        refund,
+                    },
+                    since,
+                );
+                return (state, Some(NumInstructions::from(0)));
+            }
+        }
+
         let result: ExecuteSubnetMessageResult = match method {
             Ok(Ic00Method::InstallCode) => {
                 // Tail call is needed for deterministic time slicing here to
@@ -657,6 +745,65 @@ impl ExecutionEnvironment {
                 }
             },
 
+            Ok(Ic00Method::SignWithECDSABatch) => match &msg {
+                CanisterCall::Request(request) => {
+                    let result = SignWithECDSABatchArgs::decode(payload).and_then(|args| {
+                        let key_id = MasterPublicKeyId::Ecdsa(args.key_id.clone());
+                        get_master_public_key(
+                            idkg_subnet_public_keys,
+                            self.own_subnet_id,
+                            &key_id,
+                        )?;
+                        let messages = args
+                            .messages
+                            .get()
+                            .iter()
+                            .map(|entry| {
+                                (
+                                    ThresholdArguments::Ecdsa(EcdsaArguments {
+                                        key_id: args.key_id.clone(),
+                                        message_hash: entry.message_hash,
+                                    }),
+                                    entry.derivation_path.clone().into_inner(),
+                                )
+                            })
+                            .collect();
+                        let settings = registry_settings.chain_key_settings.get(&key_id);
+                        self.sign_with_threshold_batch(
+                            (**request).clone(),
+                            messages,
+                            settings
+                                .map(|setting| setting.max_queue_size)
+                                .unwrap_or_default(),
+                            settings
+                                .map(|setting| setting.max_pre_signatures)
+                                .unwrap_or_default(),
+                            &mut state,
+                            rng,
+                            registry_settings.subnet_size,
+                        )
+                    });
+                    match result {
+                        Err(err) => ExecuteSubnetMessageResult::Finished {
+                            response: Err(err),
+                            refund: msg.take_cycles(),
+                        },
+                        Ok(()) => {
+                            self.metrics.observe_message_with_label(
+                                &request.method_name,
+                                since.elapsed().as_secs_f64(),
+                                SUBMITTED_OUTCOME_LABEL.into(),
+                                SUCCESS_STATUS_LABEL.into(),
+                            );
+                            ExecuteSubnetMessageResult::Processing
+                        }
+                    }
+                }
+                CanisterCall::Ingress(_) => {
+                    self.reject_unexpected_ingress(Ic00Method::SignWithECDSABatch)
+                }
+            },
+
             Ok(Ic00Method::CreateCanister) => {
                 match &mut msg {
                     CanisterCall::Ingress(_) => {
@@ -1210,6 +1357,68 @@ impl ExecutionEnvironment {
                 },
             },
 
+            Ok(Ic00Method::SignWithSchnorrBatch) => match self.config.ic00_sign_with_schnorr {
+                FlagStatus::Disabled => Self::reject_due_to_api_not_implemented(&mut msg),
+                FlagStatus::Enabled => match &msg {
+                    CanisterCall::Request(request) => {
+                        let result = SignWithSchnorrBatchArgs::decode(payload).and_then(|args| {
+                            let key_id = MasterPublicKeyId::Schnorr(args.key_id.clone());
+                            get_master_public_key(
+                                idkg_subnet_public_keys,
+                                self.own_subnet_id,
+                                &key_id,
+                            )?;
+                            let messages = args
+                                .messages
+                                .get()
+                                .iter()
+                                .map(|entry| {
+                                    (
+                                        ThresholdArguments::Schnorr(SchnorrArguments {
+                                            key_id: args.key_id.clone(),
+                                            message: Arc::new(entry.message.clone()),
+                                        }),
+                                        entry.derivation_path.clone().into_inner(),
+                                    )
+                                })
+                                .collect();
+                            let settings = registry_settings.chain_key_settings.get(&key_id);
+                            self.sign_with_threshold_batch(
+                                (**request).clone(),
+                                messages,
+                                settings
+                                    .map(|setting| setting.max_queue_size)
+                                    .unwrap_or_default(),
+                                settings
+                                    .map(|setting| setting.max_pre_signatures)
+                                    .unwrap_or_default(),
+                                &mut state,
+                                rng,
+                                registry_settings.subnet_size,
+                            )
+                        });
+                        match result {
+                            Err(err) => ExecuteSubnetMessageResult::Finished {
+                                response: Err(err),
+                                refund: msg.take_cycles(),
+                            },
+                            Ok(()) => {
+                                self.metrics.observe_message_with_label(
+                                    &request.method_name,
+                                    since.elapsed().as_secs_f64(),
+                                    SUBMITTED_OUTCOME_LABEL.into(),
+                                    SUCCESS_STATUS_LABEL.into(),
+                                );
+                                ExecuteSubnetMessageResult::Processing
+                            }
+                        }
+                    }
+                    CanisterCall::Ingress(_) => {
+                        self.reject_unexpected_ingress(Ic00Method::SignWithSchnorrBatch)
+                    }
+                },
+            },
+
             Ok(Ic00Method::ProvisionalCreateCanisterWithCycles) => {
                 let res =
                     ProvisionalCreateCanisterWithCyclesArgs::decode(payload).and_then(|args| {
@@ -1375,6 +1584,17 @@ impl ExecutionEnvironment {
                 refund: msg.take_cycles(),
             },
 
+            Ok(Ic00Method::FetchCanisterProfile) => ExecuteSubnetMessageResult::Finished {
+                response: Err(UserError::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    format!(
+                        "{} API is only accessible in non-replicated mode",
+                        Ic00Method::FetchCanisterProfile
+                    ),
+                )),
+                refund: msg.take_cycles(),
+            },
+
             Ok(Ic00Method::TakeCanisterSnapshot) => match self.config.canister_snapshots {
                 FlagStatus::Enabled => match TakeCanisterSnapshotArgs::decode(payload) {
                     Err(err) => ExecuteSubnetMessageResult::Finished {
@@ -1493,6 +1713,50 @@ impl ExecutionEnvironment {
                 }
             },
 
+            Ok(Ic00Method::MigrateCanister) => match MigrateCanisterArgs::decode(payload) {
+                Err(err) => ExecuteSubnetMessageResult::Finished {
+                    response: Err(err),
+                    refund: msg.take_cycles(),
+                },
+                Ok(args) => match self.canister_manager.migrate_canister(
+                    *msg.sender(),
+                    args.get_canister_id(),
+                    args.get_target_subnet_id(),
+                    &msg,
+                    &mut state,
+                ) {
+                    Ok(()) => ExecuteSubnetMessageResult::Processing,
+                    Err(err) => ExecuteSubnetMessageResult::Finished {
+                        response: Err(err.into()),
+                        refund: msg.take_cycles(),
+                    },
+                },
+            },
+
+            Ok(Ic00Method::ImportCanisterState) => {
+                let (res, instructions_used) = match ImportCanisterStateArgs::decode(payload) {
+                    Err(err) => (Err(err), NumInstructions::from(0)),
+                    Ok(args) => {
+                        let (res, instructions_used) = self.canister_manager.import_canister_state(
+                            *msg.sender(),
+                            args,
+                            &mut state,
+                            round_limits,
+                        );
+                        (
+                            res.map(|()| EmptyBlob.encode()).map_err(|err| err.into()),
+                            instructions_used,
+                        )
+                    }
+                };
+                let result = ExecuteSubnetMessageResult::Finished {
+                    response: res,
+                    refund: msg.take_cycles(),
+                };
+                let state = self.finish_subnet_message_execution(state, msg, result, since);
+                return (state, Some(instructions_used));
+            }
+
             Err(ParseError::VariantNotFound) => {
                 let res = Err(UserError::new(
                     ErrorCode::CanisterMethodNotFound,
@@ -1511,6 +1775,7 @@ impl ExecutionEnvironment {
         //   - `TakeCanisterSnapshot`
         //   - `LoadCanisterSnapshot`
         //   - `SignWithECDSA`
+        //   - `ImportCanisterState`
         // If you modify code below, please also update
         // these cases.
         let state = self.finish_subnet_message_execution(state, msg, result, since);
@@ -1537,8 +1802,11 @@ impl ExecutionEnvironment {
         since: Instant,
     ) -> ReplicatedState {
         match &result {
-            ExecuteSubnetMessageResult::Processing => {}
-            ExecuteSubnetMessageResult::Finished { response, .. } => {
+            ExecuteSubnetMessageResult::Processing => {
+                self.message_execution_tracer
+                    .record_subnet_message(&message, Cycles::zero(), None);
+            }
+            ExecuteSubnetMessageResult::Finished { response, refund } => {
                 // Request has been executed. Observe metrics and respond.
                 let method_name = String::from(message.method_name());
                 self.metrics.observe_subnet_message(
@@ -1546,6 +1814,15 @@ impl ExecutionEnvironment {
                     since.elapsed().as_secs_f64(),
                     &response.as_ref().map_err(|err| err.code()),
                 );
+                let traced_response = match response {
+                    Ok(_) => TracedResponse::Reply,
+                    Err(err) => TracedResponse::Reject(err.description().to_string()),
+                };
+                self.message_execution_tracer.record_subnet_message(
+                    &message,
+                    *refund,
+                    Some(traced_response),
+                );
             }
         }
         self.output_subnet_response(message, state, result)
@@ -2622,12 +2899,108 @@ impl ExecutionEnvironment {
         state: &mut ReplicatedState,
         rng: &mut dyn RngCore,
         subnet_size: usize,
+    ) -> Result<(), UserError> {
+        self.charge_and_check_threshold_signatures(
+            &mut request,
+            &args,
+            1,
+            max_queue_size,
+            state,
+            subnet_size,
+        )?;
+        let context =
+            self.new_sign_with_threshold_context(request, args, derivation_path, state, rng);
+        state
+            .metadata
+            .subnet_call_context_manager
+            .push_context(SubnetCallContext::SignWithThreshold(context));
+        Ok(())
+    }
+
+    /// Handles a batched threshold signing request: one context is created per
+    /// message, each of them charged and counted against the signature queue
+    /// like an individual request. Either all messages are accepted or none.
+    ///
+    /// The contexts of a batch are matched with pre-signatures as a unit, so a
+    /// batch larger than `max_pre_signatures` is rejected, as it could never be
+    /// matched.
+    #[allow(clippy::too_many_arguments)]
+    fn sign_with_threshold_batch(
+        &self,
+        mut request: Request,
+        messages: Vec<(ThresholdArguments, Vec<Vec<u8>>)>,
+        max_queue_size: u32,
+        max_pre_signatures: u32,
+        state: &mut ReplicatedState,
+        rng: &mut dyn RngCore,
+        subnet_size: usize,
+    ) -> Result<(), UserError> {
+        let Some((args, _)) = messages.first() else {
+            return Err(UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!(
+                    "{} request must contain at least one message.",
+                    request.method_name
+                ),
+            ));
+        };
+        if messages.len() > max_pre_signatures as usize {
+            return Err(UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!(
+                    "{} request failed: batch of {} messages exceeds the maximum of {} \
+                    pre-signatures for key {}.",
+                    request.method_name,
+                    messages.len(),
+                    max_pre_signatures,
+                    args.key_id()
+                ),
+            ));
+        }
+        self.charge_and_check_threshold_signatures(
+            &mut request,
+            args,
+            messages.len(),
+            max_queue_size,
+            state,
+            subnet_size,
+        )?;
+        let contexts = messages
+            .into_iter()
+            .map(|(args, derivation_path)| {
+                self.new_sign_with_threshold_context(
+                    request.clone(),
+                    args,
+                    derivation_path,
+                    state,
+                    rng,
+                )
+            })
+            .collect();
+        state
+            .metadata
+            .subnet_call_context_manager
+            .push_sign_with_threshold_batch(contexts);
+        Ok(())
+    }
+
+    /// Charges `request` for `num_signatures` threshold signatures (unless it
+    /// comes from the NNS) and checks that the key can be used for signing and
+    /// that its signature queue has room for all of them.
+    fn charge_and_check_threshold_signatures(
+        &self,
+        request: &mut Request,
+        args: &ThresholdArguments,
+        num_signatures: usize,
+        max_queue_size: u32,
+        state: &mut ReplicatedState,
+        subnet_size: usize,
     ) -> Result<(), UserError> {
         let topology = &state.metadata.network_topology;
         // If the request isn't from the NNS, then we need to charge for it.
         let source_subnet = topology.routing_table.route(request.sender.get());
         if source_subnet != Some(state.metadata.network_topology.nns_subnet_id) {
-            let signature_fee = self.calculate_signature_fee(&args, subnet_size);
+            let signature_fee = self.calculate_signature_fee(args, subnet_size) * num_signatures;
             if request.payment < signature_fee {
                 return Err(UserError::new(
                     ErrorCode::CanisterRejectedMessage,
@@ -2670,12 +3043,13 @@ impl ExecutionEnvironment {
             ));
         }
 
-        // Check if the queue is full.
+        // Check if the queue has room for all signatures.
         if state
             .metadata
             .subnet_call_context_manager
             .sign_with_threshold_contexts_count(&threshold_key)
-            >= max_queue_size as usize
+            + num_signatures
+            > max_queue_size as usize
         {
             return Err(UserError::new(
                 ErrorCode::CanisterRejectedMessage,
@@ -2685,7 +3059,17 @@ impl ExecutionEnvironment {
                 ),
             ));
         }
+        Ok(())
+    }
 
+    fn new_sign_with_threshold_context(
+        &self,
+        request: Request,
+        args: ThresholdArguments,
+        derivation_path: Vec<Vec<u8>>,
+        state: &ReplicatedState,
+        rng: &mut dyn RngCore,
+    ) -> SignWithThresholdContext {
         let mut pseudo_random_id = [0u8; 32];
         rng.fill_bytes(&mut pseudo_random_id);
 
@@ -2697,18 +3081,16 @@ impl ExecutionEnvironment {
             request.sender(),
         );
 
-        state.metadata.subnet_call_context_manager.push_context(
-            SubnetCallContext::SignWithThreshold(SignWithThresholdContext {
-                request,
-                args,
-                derivation_path,
-                pseudo_random_id,
-                batch_time: state.metadata.batch_time,
-                matched_pre_signature: None,
-                nonce: None,
-            }),
-        );
-        Ok(())
+        SignWithThresholdContext {
+            request,
+            args,
+            derivation_path,
+            pseudo_random_id,
+            batch_time: state.metadata.batch_time,
+            matched_pre_signature: None,
+            nonce: None,
+            batch: None,
+        }
     }
 
     fn compute_initial_idkg_dealings(
@@ -3253,6 +3635,8 @@ impl ExecutionEnvironment {
                 heap_delta,
                 call_duration,
             } => {
+                self.message_execution_tracer
+                    .finish(&canister, instructions_used, &response);
                 let ingress_status = match response {
                     ExecutionResponse::Ingress(ingress_status) => Some(ingress_status),
                     ExecutionResponse::Request(response) => {
@@ -3546,6 +3930,11 @@ impl ExecutionEnvironment {
     pub fn clear_compilation_cache_for_testing(&self) {
         (*self.hypervisor).clear_compilation_cache_for_testing()
     }
+
+    /// Returns the tracer recording message executions (disabled by default).
+    pub fn message_execution_tracer(&self) -> Arc<MessageExecutionTracer> {
+        Arc::clone(&self.message_execution_tracer)
+    }
 }
 
 /// Indicates whether the full time spent compiling this canister or a reduced
@@ -3637,6 +4026,16 @@ fn execute_canister_input(
     subnet_size: usize,
 ) -> ExecuteCanisterResult {
     let info = input.to_string();
+    let _span = match &input {
+        CanisterMessageOrTask::Message(CanisterMessage::Ingress(ingress)) => info_span!(
+            "ingress_execute",
+            message_id = %ingress.message_id,
+            canister_id = %ingress.receiver,
+        ),
+        _ => Span::none(),
+    }
+    .entered();
+    exec_env.message_execution_tracer.start(&canister, &input);
     let result = exec_env.execute_canister_input(
         canister,
         instruction_limits,
@@ -3766,6 +4165,39 @@ pub fn execute_canister(
     )
 }
 
+/// Combines the replies to all messages of a batched threshold signing request
+/// into the reply to the batch. If any of the messages could not be signed, the
+/// batch is rejected with the first such error.
+fn combine_sign_with_threshold_batch_replies(is_ecdsa: bool, replies: Vec<Payload>) -> Payload {
+    let mut signatures = Vec::with_capacity(replies.len());
+    for reply in replies {
+        match reply {
+            Payload::Data(data) => signatures.push(data),
+            reject @ Payload::Reject(_) => return reject,
+        }
+    }
+    let reply = if is_ecdsa {
+        signatures
+            .iter()
+            .map(|data| SignWithECDSAReply::decode(data))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|signatures| SignWithECDSABatchReply { signatures }.encode())
+    } else {
+        signatures
+            .iter()
+            .map(|data| SignWithSchnorrReply::decode(data))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|signatures| SignWithSchnorrBatchReply { signatures }.encode())
+    };
+    match reply {
+        Ok(data) => Payload::Data(data),
+        Err(err) => Payload::Reject(RejectContext::new(
+            RejectCode::CanisterError,
+            err.description(),
+        )),
+    }
+}
+
 fn get_master_public_key<'a>(
     idkg_subnet_public_keys: &'a BTreeMap<MasterPublicKeyId, MasterPublicKey>,
     subnet_id: SubnetId,
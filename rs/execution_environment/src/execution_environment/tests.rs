@@ -1,3 +1,7 @@
+use crate::canister_settings::{
+    MAX_ENVIRONMENT_VARIABLES, MAX_ENVIRONMENT_VARIABLE_NAME_LENGTH,
+    MAX_ENVIRONMENT_VARIABLE_VALUE_LENGTH,
+};
 use assert_matches::assert_matches;
 use candid::{Decode, Encode};
 use ic_base_types::{NumBytes, NumSeconds};
@@ -213,6 +217,40 @@ fn sign_with_threshold_key_payload(method: Method, key_id: MasterPublicKeyId) ->
     }
 }
 
+fn sign_with_threshold_key_batch_payload(
+    method: Method,
+    key_id: MasterPublicKeyId,
+    batch_size: usize,
+) -> Vec<u8> {
+    match method {
+        Method::SignWithECDSABatch => ic00::SignWithECDSABatchArgs {
+            messages: ic00::SignWithECDSABatchEntries::new(
+                (0..batch_size)
+                    .map(|i| ic00::SignWithECDSABatchEntry {
+                        message_hash: [i as u8; 32],
+                        derivation_path: DerivationPath::new(vec![]),
+                    })
+                    .collect(),
+            ),
+            key_id: into_inner_ecdsa(key_id),
+        }
+        .encode(),
+        Method::SignWithSchnorrBatch => ic00::SignWithSchnorrBatchArgs {
+            messages: ic00::SignWithSchnorrBatchEntries::new(
+                (0..batch_size)
+                    .map(|i| ic00::SignWithSchnorrBatchEntry {
+                        message: vec![i as u8; 32],
+                        derivation_path: DerivationPath::new(vec![]),
+                    })
+                    .collect(),
+            ),
+            key_id: into_inner_schnorr(key_id),
+        }
+        .encode(),
+        _ => panic!("unexpected method"),
+    }
+}
+
 #[test]
 fn ingress_can_produce_output_request() {
     let mut test = ExecutionTestBuilder::new().with_manual_execution().build();
@@ -2805,6 +2843,81 @@ fn test_sign_with_threshold_key_queue_fills_up() {
     }
 }
 
+#[test]
+fn test_sign_with_threshold_key_batch_larger_than_pre_signatures_is_rejected() {
+    let test_cases = vec![
+        (Method::SignWithECDSABatch, make_ecdsa_key("some_key")),
+        (Method::SignWithSchnorrBatch, make_schnorr_key("some_key")),
+    ];
+    for (method, key_id) in test_cases {
+        let mut test = ExecutionTestBuilder::new()
+            .with_subnet_type(SubnetType::System)
+            .with_own_subnet_id(subnet_test_id(1))
+            .with_nns_subnet_id(subnet_test_id(2))
+            .with_ecdsa_signature_fee(1_000_000)
+            .with_schnorr_signature_fee(1_000_000)
+            .with_idkg_key(key_id.clone())
+            .with_ic00_sign_with_schnorr(FlagStatus::Enabled)
+            .build();
+        // As configured for all keys by `ExecutionTestBuilder`.
+        let max_pre_signatures = 5;
+        let canister_id = test.universal_canister().unwrap();
+        let run = |batch_size| {
+            wasm()
+                .call_with_cycles(
+                    ic00::IC_00,
+                    method,
+                    call_args()
+                        .other_side(sign_with_threshold_key_batch_payload(
+                            method,
+                            key_id.clone(),
+                            batch_size,
+                        ))
+                        .on_reject(wasm().reject_message().reject()),
+                    Cycles::from(10_000_000u128),
+                )
+                .build()
+        };
+
+        // A batch that can never be matched with pre-signatures as a unit is rejected.
+        let result = test
+            .ingress(canister_id, "update", run(max_pre_signatures + 1))
+            .unwrap();
+        assert_eq!(
+            result,
+            WasmResult::Reject(format!(
+                "{} request failed: batch of {} messages exceeds the maximum of {} \
+                pre-signatures for key {}.",
+                method,
+                max_pre_signatures + 1,
+                max_pre_signatures,
+                key_id,
+            ))
+        );
+
+        // A batch of the maximum size is accepted.
+        let (_, ingress_status) = test.ingress_raw(canister_id, "update", run(max_pre_signatures));
+        assert_eq!(
+            ingress_status,
+            IngressStatus::Known {
+                receiver: canister_id.get(),
+                user_id: test.user_id(),
+                time: test.time(),
+                state: IngressState::Processing,
+            }
+        );
+        let subnet_call_context_manager = &test.state().metadata.subnet_call_context_manager;
+        let contexts = match method {
+            Method::SignWithECDSABatch => subnet_call_context_manager.sign_with_ecdsa_contexts(),
+            Method::SignWithSchnorrBatch => {
+                subnet_call_context_manager.sign_with_schnorr_contexts()
+            }
+            _ => panic!("Unexpected method"),
+        };
+        assert_eq!(contexts.len(), max_pre_signatures);
+    }
+}
+
 #[test]
 fn canister_output_queue_does_not_overflow_when_calling_ic00() {
     let own_subnet = subnet_test_id(1);
@@ -3567,6 +3680,113 @@ fn test_canister_settings_log_visibility_set_to_public() {
     );
 }
 
+fn update_environment_variables(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    environment_variables: Vec<ic00::EnvironmentVariable>,
+) -> Result<WasmResult, UserError> {
+    let payload = ic00::UpdateSettingsArgs {
+        canister_id: canister_id.into(),
+        settings: ic00::CanisterSettingsArgsBuilder::new()
+            .with_environment_variables(environment_variables)
+            .build(),
+        sender_canister_version: None,
+    }
+    .encode();
+    test.subnet_message(Method::UpdateSettings, payload)
+}
+
+#[test]
+fn test_canister_settings_environment_variables() {
+    // Arrange.
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    let memory_usage_before = test.canister_state(canister_id).memory_usage();
+    // Act.
+    update_environment_variables(
+        &mut test,
+        canister_id,
+        vec![
+            ic00::EnvironmentVariable::new("NETWORK", "staging"),
+            ic00::EnvironmentVariable::new("LOG_LEVEL", "debug"),
+        ],
+    )
+    .unwrap();
+    let result = test.canister_status(canister_id);
+    let canister_status = CanisterStatusResultV2::decode(&get_reply(result)).unwrap();
+    // Assert.
+    assert_eq!(
+        canister_status.settings().environment_variables(),
+        &[
+            ic00::EnvironmentVariable::new("LOG_LEVEL", "debug"),
+            ic00::EnvironmentVariable::new("NETWORK", "staging"),
+        ]
+    );
+    let environment_variables_size =
+        "NETWORK".len() + "staging".len() + "LOG_LEVEL".len() + "debug".len();
+    assert_eq!(
+        test.canister_state(canister_id)
+            .environment_variables_memory_usage(),
+        NumBytes::from(environment_variables_size as u64)
+    );
+    assert!(test.canister_state(canister_id).memory_usage() > memory_usage_before);
+
+    // Setting the environment variables replaces the existing ones.
+    update_environment_variables(&mut test, canister_id, vec![]).unwrap();
+    assert!(test
+        .canister_state(canister_id)
+        .system_state
+        .environment_variables
+        .is_empty());
+}
+
+#[test]
+fn test_canister_settings_environment_variables_limits() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+
+    let too_many = (0..=MAX_ENVIRONMENT_VARIABLES)
+        .map(|i| ic00::EnvironmentVariable::new(format!("VAR_{}", i), "value"))
+        .collect();
+    let err = update_environment_variables(&mut test, canister_id, too_many).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+
+    let long_name = "N".repeat(MAX_ENVIRONMENT_VARIABLE_NAME_LENGTH + 1);
+    let err = update_environment_variables(
+        &mut test,
+        canister_id,
+        vec![ic00::EnvironmentVariable::new(long_name, "value")],
+    )
+    .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+
+    let long_value = "V".repeat(MAX_ENVIRONMENT_VARIABLE_VALUE_LENGTH + 1);
+    let err = update_environment_variables(
+        &mut test,
+        canister_id,
+        vec![ic00::EnvironmentVariable::new("NAME", long_value)],
+    )
+    .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+
+    let err = update_environment_variables(
+        &mut test,
+        canister_id,
+        vec![
+            ic00::EnvironmentVariable::new("NAME", "a"),
+            ic00::EnvironmentVariable::new("NAME", "b"),
+        ],
+    )
+    .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+
+    assert!(test
+        .canister_state(canister_id)
+        .system_state
+        .environment_variables
+        .is_empty());
+}
+
 #[test]
 fn test_fetch_canister_logs_should_accept_ingress_message() {
     // Arrange.
@@ -194,6 +194,7 @@ impl ExecutionEnvironmentMetrics {
                     | ic00::Method::BitcoinGetCurrentFeePercentiles
                     | ic00::Method::NodeMetricsHistory
                     | ic00::Method::FetchCanisterLogs
+                    | ic00::Method::FetchCanisterProfile
                     | ic00::Method::ProvisionalCreateCanisterWithCycles
                     | ic00::Method::ProvisionalTopUpCanister
                     | ic00::Method::UploadChunk
@@ -202,7 +203,8 @@ impl ExecutionEnvironmentMetrics {
                     | ic00::Method::TakeCanisterSnapshot
                     | ic00::Method::LoadCanisterSnapshot
                     | ic00::Method::ListCanisterSnapshots
-                    | ic00::Method::DeleteCanisterSnapshot => String::from("fast"),
+                    | ic00::Method::DeleteCanisterSnapshot
+                    | ic00::Method::ImportCanisterState => String::from("fast"),
 
                     // "Slow" management methods that might require several execution
                     // rounds to be completed, either due to using DTS or due to
@@ -214,10 +216,13 @@ impl ExecutionEnvironmentMetrics {
                     | ic00::Method::StopCanister
                     | ic00::Method::HttpRequest
                     | ic00::Method::SignWithECDSA
+                    | ic00::Method::SignWithECDSABatch
                     | ic00::Method::SignWithSchnorr
+                    | ic00::Method::SignWithSchnorrBatch
                     | ic00::Method::ComputeInitialIDkgDealings
                     | ic00::Method::BitcoinSendTransactionInternal
-                    | ic00::Method::BitcoinGetSuccessors => String::from("slow"),
+                    | ic00::Method::BitcoinGetSuccessors
+                    | ic00::Method::MigrateCanister => String::from("slow"),
                 };
                 (format!("ic00_{}", method_name), speed_label)
             }
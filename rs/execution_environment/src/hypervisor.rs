@@ -220,6 +220,7 @@ impl Hypervisor {
         canister_module: CanisterModule,
         canister_root: PathBuf,
         canister_id: CanisterId,
+        canister_profiling: bool,
         round_limits: &mut RoundLimits,
         compilation_cost_handling: CompilationCostHandling,
     ) -> (NumInstructions, HypervisorResult<ExecutionState>) {
@@ -244,6 +245,7 @@ impl Hypervisor {
             canister_module,
             canister_root,
             canister_id,
+            canister_profiling,
             Arc::clone(&self.compilation_cache),
         );
         match creation_result {
@@ -18,7 +18,7 @@ pub(crate) struct Ic00MethodPermissions {
 impl Ic00MethodPermissions {
     pub fn new(method: Ic00Method) -> Self {
         match method {
-            Ic00Method::SignWithECDSA => Self {
+            Ic00Method::SignWithECDSA | Ic00Method::SignWithECDSABatch => Self {
                 method,
                 allow_remote_subnet_sender: true,
                 allow_only_nns_subnet_sender: false,
@@ -108,7 +108,7 @@ impl Ic00MethodPermissions {
                 allow_remote_subnet_sender: true,
                 allow_only_nns_subnet_sender: false,
             },
-            Ic00Method::SignWithSchnorr => Self {
+            Ic00Method::SignWithSchnorr | Ic00Method::SignWithSchnorrBatch => Self {
                 method,
                 allow_remote_subnet_sender: true,
                 allow_only_nns_subnet_sender: false,
@@ -160,6 +160,12 @@ impl Ic00MethodPermissions {
                 allow_remote_subnet_sender: false,
                 allow_only_nns_subnet_sender: false,
             },
+            Ic00Method::FetchCanisterProfile => Self {
+                method,
+                // Like `FetchCanisterLogs`, only allowed for messages sent by users.
+                allow_remote_subnet_sender: false,
+                allow_only_nns_subnet_sender: false,
+            },
             Ic00Method::ProvisionalCreateCanisterWithCycles => Self {
                 method,
                 allow_remote_subnet_sender: true,
@@ -185,6 +191,11 @@ impl Ic00MethodPermissions {
                 allow_remote_subnet_sender: true,
                 allow_only_nns_subnet_sender: false,
             },
+            Ic00Method::MigrateCanister | Ic00Method::ImportCanisterState => Self {
+                method,
+                allow_remote_subnet_sender: true,
+                allow_only_nns_subnet_sender: false,
+            },
         }
     }
 
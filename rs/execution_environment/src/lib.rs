@@ -8,6 +8,7 @@ mod history;
 mod hypervisor;
 mod ic00_permissions;
 mod ingress_filter;
+mod message_execution_tracer;
 mod metrics;
 mod query_handler;
 mod scheduler;
@@ -39,6 +40,10 @@ use ic_types::{
     messages::{CallContextId, MessageId},
     Height, SubnetId,
 };
+pub use message_execution_tracer::{
+    MessageExecutionRecord, MessageExecutionTracer, TracedCall, TracedCallOrigin, TracedInput,
+    TracedResponse, MAX_MESSAGE_EXECUTION_RECORDS,
+};
 pub use metrics::IngressFilterMetrics;
 pub use query_handler::InternalHttpQueryHandler;
 use query_handler::{HttpQueryHandler, QueryScheduler, QuerySchedulerFlag};
@@ -86,6 +91,7 @@ pub struct ExecutionServices {
     pub query_execution_service: QueryExecutionService,
     pub scheduler: Box<dyn Scheduler<State = ReplicatedState>>,
     pub query_stats_payload_builder: QueryStatsPayloadBuilderParams,
+    pub message_execution_tracer: Arc<MessageExecutionTracer>,
 }
 
 impl ExecutionServices {
@@ -163,6 +169,8 @@ impl ExecutionServices {
             QuerySchedulerFlag::UseNewSchedulingAlgorithm,
         );
 
+        let message_execution_tracer = exec_env.message_execution_tracer();
+
         let ingress_filter_metrics: Arc<_> = IngressFilterMetrics::new(metrics_registry).into();
 
         // Creating the async services require that a tokio runtime context is available.
@@ -201,6 +209,7 @@ impl ExecutionServices {
             query_execution_service,
             scheduler,
             query_stats_payload_builder,
+            message_execution_tracer,
         }
     }
 
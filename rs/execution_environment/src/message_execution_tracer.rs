@@ -0,0 +1,334 @@
+//! Opt-in tracing of message executions.
+//!
+//! The tracer records every execution of a canister message (ingress message,
+//! inter-canister request or response) or task so that testing environments
+//! (e.g., PocketIC) can reconstruct inter-canister call graphs. Management
+//! canister (subnet) messages are recorded as executions of the management
+//! canister. Tracing is disabled by default and a disabled tracer does not
+//! inspect any messages.
+
+use crate::execution_environment::ExecutionResponse;
+use ic_replicated_state::{CallOrigin, CanisterState};
+use ic_types::{
+    ingress::{IngressState, IngressStatus, WasmResult},
+    messages::{
+        CallbackId, CanisterCall, CanisterMessage, CanisterMessageOrTask, MessageId, Payload,
+    },
+    CanisterId, Cycles, ExecutionRound, NumInstructions, PrincipalId,
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// The call (context) in which a message execution happened.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TracedCallOrigin {
+    /// The call was made by an ingress message.
+    Ingress(MessageId),
+    /// The call was made by a canister and the caller is going to execute
+    /// the callback with the given ID once the call completes.
+    Canister {
+        caller: CanisterId,
+        callback_id: CallbackId,
+    },
+    /// Heartbeats, global timers, and other tasks.
+    SystemTask,
+}
+
+impl From<&CallOrigin> for TracedCallOrigin {
+    fn from(call_origin: &CallOrigin) -> Self {
+        match call_origin {
+            CallOrigin::Ingress(_, message_id) => TracedCallOrigin::Ingress(message_id.clone()),
+            CallOrigin::CanisterUpdate(caller, callback_id, _)
+            | CallOrigin::CanisterQuery(caller, callback_id) => TracedCallOrigin::Canister {
+                caller: *caller,
+                callback_id: *callback_id,
+            },
+            CallOrigin::Query(_) | CallOrigin::SystemTask => TracedCallOrigin::SystemTask,
+        }
+    }
+}
+
+/// The executed message or task.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TracedInput {
+    Ingress {
+        source: PrincipalId,
+        method_name: String,
+    },
+    Request {
+        sender: CanisterId,
+        method_name: String,
+        payment: Cycles,
+    },
+    Response {
+        respondent: CanisterId,
+        callback_id: CallbackId,
+        refund: Cycles,
+        response: TracedResponse,
+    },
+    Task(String),
+}
+
+/// A reply or reject.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TracedResponse {
+    Reply,
+    Reject(String),
+}
+
+/// A call made by a message execution.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TracedCall {
+    pub callee: CanisterId,
+    pub callback_id: CallbackId,
+    pub cycles: Cycles,
+}
+
+/// The maximum number of records kept by the tracer until they are taken.
+/// The oldest records are dropped first.
+pub const MAX_MESSAGE_EXECUTION_RECORDS: usize = 100_000;
+
+/// A single (completed) message execution.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageExecutionRecord {
+    /// The round in which the execution started.
+    pub round: ExecutionRound,
+    /// The receiver of the management canister call for subnet messages.
+    pub canister_id: CanisterId,
+    pub origin: TracedCallOrigin,
+    pub input: TracedInput,
+    /// Zero for subnet messages.
+    pub instructions_used: NumInstructions,
+    /// The response to the call `origin` produced by this execution (if any).
+    pub response: Option<TracedResponse>,
+    /// The calls made by this execution.
+    pub calls: Vec<TracedCall>,
+}
+
+/// An execution that has started, but not finished yet
+/// (possibly spanning multiple rounds due to DTS).
+struct PendingExecution {
+    round: ExecutionRound,
+    origin: TracedCallOrigin,
+    input: TracedInput,
+    next_callback_id: u64,
+}
+
+/// Collects `MessageExecutionRecord`s if enabled.
+#[derive(Default)]
+pub struct MessageExecutionTracer {
+    enabled: AtomicBool,
+    round: AtomicU64,
+    // At most one execution per canister can be pending at any time.
+    pending: Mutex<BTreeMap<CanisterId, PendingExecution>>,
+    records: Mutex<VecDeque<MessageExecutionRecord>>,
+}
+
+impl MessageExecutionTracer {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.pending.lock().unwrap().clear();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Returns all records collected since the last call of this function
+    /// (at most `MAX_MESSAGE_EXECUTION_RECORDS` most recent ones).
+    pub fn take_records(&self) -> Vec<MessageExecutionRecord> {
+        std::mem::take(&mut *self.records.lock().unwrap()).into()
+    }
+
+    fn push_record(&self, record: MessageExecutionRecord) {
+        let mut records = self.records.lock().unwrap();
+        if records.len() == MAX_MESSAGE_EXECUTION_RECORDS {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    pub(crate) fn set_round(&self, round: ExecutionRound) {
+        self.round.store(round.get(), Ordering::Relaxed);
+    }
+
+    /// Must be called before executing the given input on the given canister.
+    pub(crate) fn start(&self, canister: &CanisterState, input: &CanisterMessageOrTask) {
+        if !self.is_enabled() {
+            return;
+        }
+        let call_context_manager = canister.system_state.call_context_manager();
+        let (origin, input) = match input {
+            CanisterMessageOrTask::Message(CanisterMessage::Ingress(ingress)) => (
+                TracedCallOrigin::Ingress(ingress.message_id.clone()),
+                TracedInput::Ingress {
+                    source: ingress.source.get(),
+                    method_name: ingress.method_name.clone(),
+                },
+            ),
+            CanisterMessageOrTask::Message(CanisterMessage::Request(request)) => (
+                TracedCallOrigin::Canister {
+                    caller: request.sender,
+                    callback_id: request.sender_reply_callback,
+                },
+                TracedInput::Request {
+                    sender: request.sender,
+                    method_name: request.method_name.clone(),
+                    payment: request.payment,
+                },
+            ),
+            CanisterMessageOrTask::Message(CanisterMessage::Response(response)) => {
+                // The callback is still registered before the response is executed.
+                let origin = call_context_manager
+                    .and_then(|ccm| {
+                        let callback = ccm.callback(response.originator_reply_callback)?;
+                        ccm.call_origin(callback.call_context_id)
+                    })
+                    .map(|call_origin| TracedCallOrigin::from(&call_origin))
+                    .unwrap_or(TracedCallOrigin::SystemTask);
+                let traced_response = match &response.response_payload {
+                    Payload::Data(_) => TracedResponse::Reply,
+                    Payload::Reject(context) => TracedResponse::Reject(context.message().clone()),
+                };
+                (
+                    origin,
+                    TracedInput::Response {
+                        respondent: response.respondent,
+                        callback_id: response.originator_reply_callback,
+                        refund: response.refund,
+                        response: traced_response,
+                    },
+                )
+            }
+            CanisterMessageOrTask::Task(task) => (
+                TracedCallOrigin::SystemTask,
+                TracedInput::Task(format!("{:?}", task)),
+            ),
+        };
+        let pending = PendingExecution {
+            round: ExecutionRound::from(self.round.load(Ordering::Relaxed)),
+            origin,
+            input,
+            next_callback_id: call_context_manager
+                .map(|ccm| ccm.next_callback_id())
+                .unwrap_or_default(),
+        };
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(canister.canister_id(), pending);
+    }
+
+    /// Must be called once the execution of the last started input on the given canister finished.
+    pub(crate) fn finish(
+        &self,
+        canister: &CanisterState,
+        instructions_used: NumInstructions,
+        response: &ExecutionResponse,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let canister_id = canister.canister_id();
+        let Some(pending) = self.pending.lock().unwrap().remove(&canister_id) else {
+            return;
+        };
+        // Callback IDs are allocated in increasing order and thus
+        // all callbacks registered during the execution have IDs
+        // of at least `pending.next_callback_id`.
+        let calls = canister
+            .system_state
+            .call_context_manager()
+            .map(|ccm| {
+                ccm.callbacks()
+                    .range(CallbackId::from(pending.next_callback_id)..)
+                    .map(|(callback_id, callback)| TracedCall {
+                        callee: callback.respondent,
+                        callback_id: *callback_id,
+                        cycles: callback.cycles_sent,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let response = match response {
+            ExecutionResponse::Ingress((_, IngressStatus::Known { state, .. })) => match state {
+                IngressState::Completed(WasmResult::Reply(_)) => Some(TracedResponse::Reply),
+                IngressState::Completed(WasmResult::Reject(msg)) => {
+                    Some(TracedResponse::Reject(msg.clone()))
+                }
+                IngressState::Failed(err) => {
+                    Some(TracedResponse::Reject(err.description().to_string()))
+                }
+                IngressState::Received | IngressState::Processing | IngressState::Done => None,
+            },
+            ExecutionResponse::Ingress((_, IngressStatus::Unknown)) => None,
+            ExecutionResponse::Request(response) => match &response.response_payload {
+                Payload::Data(_) => Some(TracedResponse::Reply),
+                Payload::Reject(context) => Some(TracedResponse::Reject(context.message().clone())),
+            },
+            ExecutionResponse::Empty => None,
+        };
+        self.push_record(MessageExecutionRecord {
+            round: pending.round,
+            canister_id,
+            origin: pending.origin,
+            input: pending.input,
+            instructions_used,
+            response,
+            calls,
+        });
+    }
+
+    /// Must be called once the execution of the given subnet message finished
+    /// or is left in progress (e.g., waiting for consensus), in which case the
+    /// `response` is `None`. The `refund` is the part of the payment returned
+    /// to the caller, which is no longer attached to the message.
+    pub(crate) fn record_subnet_message(
+        &self,
+        message: &CanisterCall,
+        refund: Cycles,
+        response: Option<TracedResponse>,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let (canister_id, origin, input) = match message {
+            CanisterCall::Ingress(ingress) => (
+                ingress.receiver,
+                TracedCallOrigin::Ingress(ingress.message_id.clone()),
+                TracedInput::Ingress {
+                    source: ingress.source.get(),
+                    method_name: ingress.method_name.clone(),
+                },
+            ),
+            CanisterCall::Request(request) => (
+                request.receiver,
+                TracedCallOrigin::Canister {
+                    caller: request.sender,
+                    callback_id: request.sender_reply_callback,
+                },
+                TracedInput::Request {
+                    sender: request.sender,
+                    method_name: request.method_name.clone(),
+                    payment: request.payment + refund,
+                },
+            ),
+        };
+        self.push_record(MessageExecutionRecord {
+            round: ExecutionRound::from(self.round.load(Ordering::Relaxed)),
+            canister_id,
+            origin,
+            input,
+            instructions_used: NumInstructions::from(0),
+            response,
+            calls: vec![],
+        });
+    }
+}
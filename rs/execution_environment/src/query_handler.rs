@@ -18,6 +18,7 @@ use ic_config::execution_environment::Config;
 use ic_config::flag_status::FlagStatus;
 use ic_crypto_tree_hash::{flatmap, Label, LabeledTree, LabeledTree::SubTree};
 use ic_cycles_account_manager::CyclesAccountManager;
+use ic_embedders::wasm_utils::decoding::{decode_function_names, decode_wasm};
 use ic_error_types::{ErrorCode, UserError};
 use ic_interfaces::execution_environment::{
     QueryExecutionError, QueryExecutionResponse, QueryExecutionService,
@@ -33,16 +34,20 @@ use ic_types::QueryStatsEpoch;
 use ic_types::{
     ingress::WasmResult,
     messages::{Blob, Certificate, CertificateDelegation, Query},
-    CanisterId, NumInstructions, PrincipalId,
+    CanisterId, CountBytes, NumBytes, NumInstructions, PrincipalId,
 };
+use ic_utils_lru_cache::LruCache;
+use ic_wasm_types::WasmHash;
 use prometheus::Histogram;
 use serde::Serialize;
 use std::convert::Infallible;
 use std::str::FromStr;
 use std::{
+    collections::BTreeMap,
     future::Future,
+    mem::size_of,
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 use tokio::sync::oneshot;
@@ -50,7 +55,9 @@ use tower::{util::BoxCloneService, Service};
 
 pub(crate) use self::query_scheduler::{QueryScheduler, QuerySchedulerFlag};
 use ic_management_canister_types::{
-    FetchCanisterLogsRequest, FetchCanisterLogsResponse, LogVisibilityV2, Payload, QueryMethod,
+    CallStackProfileRecord, FetchCanisterLogsRequest, FetchCanisterLogsResponse,
+    FetchCanisterProfileRequest, FetchCanisterProfileResponse, FunctionProfileRecord,
+    LogVisibilityV2, Payload, QueryMethod,
 };
 
 /// Convert an object into CBOR binary.
@@ -106,6 +113,24 @@ pub struct InternalHttpQueryHandler {
     cycles_account_manager: Arc<CyclesAccountManager>,
     local_query_execution_stats: QueryStatsCollector,
     query_cache: query_cache::QueryCache,
+    function_names_cache: Mutex<LruCache<WasmHash, FunctionNames>>,
+}
+
+/// The capacity of the cache of function names used to resolve the function
+/// indices in canister profiles.
+const FUNCTION_NAMES_CACHE_CAPACITY: NumBytes = NumBytes::new(10 * 1024 * 1024);
+
+/// The function names from the `name` section of a canister module.
+#[derive(Clone, Default)]
+struct FunctionNames(Arc<BTreeMap<u32, String>>);
+
+impl CountBytes for FunctionNames {
+    fn count_bytes(&self) -> usize {
+        self.0
+            .values()
+            .map(|name| size_of::<u32>() + name.len())
+            .sum()
+    }
 }
 
 #[derive(Clone)]
@@ -163,6 +188,7 @@ impl InternalHttpQueryHandler {
                 query_max_expiry_time,
                 query_data_certificate_expiry_time,
             ),
+            function_names_cache: Mutex::new(LruCache::new(FUNCTION_NAMES_CACHE_CAPACITY)),
         }
     }
 
@@ -204,6 +230,15 @@ impl InternalHttpQueryHandler {
                         FetchCanisterLogsRequest::decode(&query.method_payload)?,
                     );
                 }
+                Ok(QueryMethod::FetchCanisterProfile) => {
+                    return fetch_canister_profile(
+                        query.source(),
+                        state.get_ref(),
+                        &self.config,
+                        &self.function_names_cache,
+                        FetchCanisterProfileRequest::decode(&query.method_payload)?,
+                    );
+                }
                 Err(_) => {
                     return Err(UserError::new(
                         ErrorCode::CanisterMethodNotFound,
@@ -333,6 +368,112 @@ fn fetch_canister_logs(
     Ok(WasmResult::Reply(Encode!(&response).unwrap()))
 }
 
+fn fetch_canister_profile(
+    sender: PrincipalId,
+    state: &ReplicatedState,
+    config: &Config,
+    function_names_cache: &Mutex<LruCache<WasmHash, FunctionNames>>,
+    args: FetchCanisterProfileRequest,
+) -> Result<WasmResult, UserError> {
+    if config.embedders_config.feature_flags.canister_profiling == FlagStatus::Disabled {
+        return Err(UserError::new(
+            ErrorCode::CanisterRejectedMessage,
+            format!(
+                "{} API is not enabled on this subnet",
+                QueryMethod::FetchCanisterProfile
+            ),
+        ));
+    }
+
+    let canister_id = args.get_canister_id();
+    let canister = state.canister_state(&canister_id).ok_or_else(|| {
+        UserError::new(
+            ErrorCode::CanisterNotFound,
+            format!("Canister {canister_id} not found"),
+        )
+    })?;
+
+    if !canister.controllers().contains(&sender) {
+        return Err(UserError::new(
+            ErrorCode::CanisterRejectedMessage,
+            format!(
+                "Caller {} is not allowed to query ic00 method {}",
+                sender,
+                QueryMethod::FetchCanisterProfile
+            ),
+        ));
+    }
+
+    let profile = canister
+        .system_state
+        .canister_profile
+        .as_ref()
+        .ok_or_else(|| {
+            UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!("Canister {canister_id} does not have profiling enabled"),
+            )
+        })?;
+
+    // Resolve the function names from the `name` section of the module. The
+    // module was validated on installation, so decoding is not expected to
+    // fail, but names are best effort anyway. Decoding is expensive for large
+    // modules, so the names are cached by module hash.
+    let function_names = match canister.execution_state.as_ref() {
+        Some(execution_state) => {
+            let module = &execution_state.wasm_binary.binary;
+            let wasm_hash = WasmHash::from(module);
+            let cached = function_names_cache
+                .lock()
+                .unwrap()
+                .get(&wasm_hash)
+                .cloned();
+            match cached {
+                Some(function_names) => function_names,
+                None => {
+                    let function_names = FunctionNames(Arc::new(
+                        decode_wasm(
+                            config.embedders_config.wasm_max_size,
+                            module.to_shared_vec(),
+                        )
+                        .map(|wasm| decode_function_names(&wasm))
+                        .unwrap_or_default(),
+                    ));
+                    function_names_cache
+                        .lock()
+                        .unwrap()
+                        .push(wasm_hash, function_names.clone());
+                    function_names
+                }
+            }
+        }
+        None => FunctionNames::default(),
+    };
+
+    let response = FetchCanisterProfileResponse {
+        functions: profile
+            .functions()
+            .iter()
+            .map(|(function_index, function)| FunctionProfileRecord {
+                function_index: *function_index,
+                function_name: function_names.0.get(function_index).cloned(),
+                calls: function.calls,
+                inclusive_instructions: function.inclusive_instructions,
+                exclusive_instructions: function.exclusive_instructions,
+            })
+            .collect(),
+        call_stacks: profile
+            .call_stacks()
+            .iter()
+            .map(|(function_indices, instructions)| CallStackProfileRecord {
+                function_indices: function_indices.clone(),
+                instructions: *instructions,
+            })
+            .collect(),
+    };
+    Ok(WasmResult::Reply(Encode!(&response).unwrap()))
+}
+
 impl HttpQueryHandler {
     pub(crate) fn new_service(
         internal: Arc<InternalHttpQueryHandler>,
@@ -1478,11 +1478,22 @@ fn query_cache_future_proof_test() {
         | SystemApiCallId::CanisterStatus
         | SystemApiCallId::CanisterVersion
         | SystemApiCallId::CertifiedDataSet
+        | SystemApiCallId::CostCall
+        | SystemApiCallId::CostCreateCanister
+        | SystemApiCallId::CostHttpRequest
+        | SystemApiCallId::CostSignWithEcdsa
+        | SystemApiCallId::CostSignWithSchnorr
         | SystemApiCallId::CyclesBurn128
         | SystemApiCallId::DataCertificateCopy
         | SystemApiCallId::DataCertificatePresent
         | SystemApiCallId::DataCertificateSize
         | SystemApiCallId::DebugPrint
+        | SystemApiCallId::EnvVarCount
+        | SystemApiCallId::EnvVarNameCopy
+        | SystemApiCallId::EnvVarNameExists
+        | SystemApiCallId::EnvVarNameSize
+        | SystemApiCallId::EnvVarValueCopy
+        | SystemApiCallId::EnvVarValueSize
         | SystemApiCallId::GlobalTimerSet
         | SystemApiCallId::InReplicatedExecution
         | SystemApiCallId::IsController
@@ -1508,6 +1519,8 @@ fn query_cache_future_proof_test() {
         | SystemApiCallId::MsgReplyDataAppend
         | SystemApiCallId::OutOfInstructions
         | SystemApiCallId::PerformanceCounter
+        | SystemApiCallId::RootKeyCopy
+        | SystemApiCallId::RootKeySize
         | SystemApiCallId::Stable64Grow
         | SystemApiCallId::Stable64Read
         | SystemApiCallId::Stable64Size
@@ -1516,6 +1529,8 @@ fn query_cache_future_proof_test() {
         | SystemApiCallId::StableRead
         | SystemApiCallId::StableSize
         | SystemApiCallId::StableWrite
+        | SystemApiCallId::SubnetSelfCopy
+        | SystemApiCallId::SubnetSelfSize
         | SystemApiCallId::Time
         | SystemApiCallId::Trap
         | SystemApiCallId::TryGrowWasmMemory => {
@@ -47,6 +47,7 @@ use std::{
     sync::Arc,
 };
 use strum::IntoEnumIterator;
+use tracing::instrument;
 
 mod scheduler_metrics;
 use scheduler_metrics::*;
@@ -1418,6 +1419,7 @@ impl SchedulerImpl {
 impl Scheduler for SchedulerImpl {
     type State = ReplicatedState;
 
+    #[instrument(skip_all, fields(round = current_round.get()))]
     fn execute_round(
         &self,
         mut state: ReplicatedState,
@@ -1451,6 +1453,9 @@ impl Scheduler for SchedulerImpl {
                 state.metadata.heap_delta_estimate,
             );
             self.metrics.execute_round_called.inc();
+            self.exec_env
+                .message_execution_tracer()
+                .set_round(current_round);
             observe_replicated_state_metrics(
                 self.own_subnet_id,
                 &state,
@@ -2367,9 +2372,11 @@ fn get_instructions_limits_for_subnet_message(
             | HttpRequest
             | SetupInitialDKG
             | SignWithECDSA
+            | SignWithECDSABatch
             | ComputeInitialIDkgDealings
             | SchnorrPublicKey
             | SignWithSchnorr
+            | SignWithSchnorrBatch
             | StartCanister
             | StopCanister
             | UninstallCode
@@ -2383,6 +2390,7 @@ fn get_instructions_limits_for_subnet_message(
             | BitcoinGetSuccessors
             | NodeMetricsHistory
             | FetchCanisterLogs
+            | FetchCanisterProfile
             | ProvisionalCreateCanisterWithCycles
             | ProvisionalTopUpCanister
             | UploadChunk
@@ -2391,7 +2399,9 @@ fn get_instructions_limits_for_subnet_message(
             | TakeCanisterSnapshot
             | LoadCanisterSnapshot
             | ListCanisterSnapshots
-            | DeleteCanisterSnapshot => default_limits,
+            | DeleteCanisterSnapshot
+            | MigrateCanister
+            | ImportCanisterState => default_limits,
             InstallCode | InstallChunkedCode => InstructionLimits::new(
                 dts,
                 config.max_instructions_per_install_code,
@@ -808,6 +808,7 @@ impl SchedulerTestBuilder {
                 ChainKeySettings {
                     max_queue_size: 20,
                     pre_signatures_to_create_in_advance: 5,
+                    max_pre_signatures: 5,
                 },
             );
         }
@@ -1096,6 +1097,7 @@ impl WasmExecutor for TestWasmExecutor {
         canister_module: CanisterModule,
         _canister_root: PathBuf,
         canister_id: CanisterId,
+        _canister_profiling: bool,
         _compilation_cache: Arc<CompilationCache>,
     ) -> HypervisorResult<(ExecutionState, NumInstructions, Option<CompilationResult>)> {
         let mut guard = self.core.lock().unwrap();
@@ -84,18 +84,39 @@ fn match_pre_signatures_by_key_id(
     }
 
     // Assign pre-signatures to unmatched contexts until `max_ongoing_signatures` is reached.
-    for context in contexts.iter_mut() {
-        if !(context.matched_pre_signature.is_none() && context.key_id() == key_id) {
+    // The contexts of a batched request are matched as a unit, in order for all of its
+    // signatures to be produced together. A batch that is larger than `max_ongoing_signatures`
+    // may only be matched while no other signature is ongoing.
+    let mut i = 0;
+    while i < contexts.len() {
+        if !(contexts[i].matched_pre_signature.is_none() && contexts[i].key_id() == key_id) {
+            i += 1;
             continue;
         }
-        if matched >= max_ongoing_signatures {
+        let unit = match contexts[i].batch {
+            None => 1,
+            Some(entry) => contexts[i..]
+                .iter()
+                .take_while(|context| {
+                    context
+                        .batch
+                        .is_some_and(|other| other.batch_id == entry.batch_id)
+                })
+                .count(),
+        };
+        let fits = matched + unit <= max_ongoing_signatures
+            || (matched == 0 && max_ongoing_signatures > 0);
+        if !fits || pre_sig_ids.len() < unit {
             break;
         }
-        let Some(pre_sig_id) = pre_sig_ids.pop_first() else {
-            break;
-        };
-        let _ = context.matched_pre_signature.insert((pre_sig_id, height));
-        matched += 1;
+        for context in contexts[i..i + unit].iter_mut() {
+            let pre_sig_id = pre_sig_ids
+                .pop_first()
+                .expect("Enough pre-signatures are available for the unit");
+            let _ = context.matched_pre_signature.insert((pre_sig_id, height));
+        }
+        matched += unit;
+        i += unit;
     }
 }
 
@@ -106,7 +127,8 @@ mod tests {
     use super::*;
     use ic_management_canister_types::{EcdsaCurve, EcdsaKeyId, SchnorrAlgorithm, SchnorrKeyId};
     use ic_replicated_state::metadata_state::subnet_call_context_manager::{
-        EcdsaArguments, SchnorrArguments, SignWithThresholdContext, ThresholdArguments,
+        EcdsaArguments, SchnorrArguments, SignWithThresholdContext, SignatureBatchEntry,
+        ThresholdArguments,
     };
     use ic_test_utilities_types::messages::RequestBuilder;
     use ic_types::{messages::CallbackId, time::UNIX_EPOCH};
@@ -149,11 +171,30 @@ mod tests {
             batch_time: UNIX_EPOCH,
             matched_pre_signature: matched_pre_signature.map(|(id, h)| (PreSigId(id), h)),
             nonce: None,
+            batch: None,
         };
 
         (callback_id, context)
     }
 
+    fn fake_batch_contexts(
+        ids: std::ops::Range<u64>,
+        key_id: &MasterPublicKeyId,
+    ) -> Vec<(CallbackId, SignWithThresholdContext)> {
+        let size = ids.end - ids.start;
+        ids.clone()
+            .map(|id| {
+                let (callback_id, mut context) = fake_context(id, key_id, None);
+                context.batch = Some(SignatureBatchEntry {
+                    batch_id: CallbackId::from(ids.start),
+                    index: (id - ids.start) as u32,
+                    size: size as u32,
+                });
+                (callback_id, context)
+            })
+            .collect()
+    }
+
     fn match_pre_signatures_basic_test(
         key_id: &MasterPublicKeyId,
         pre_sig_ids: BTreeSet<PreSigId>,
@@ -327,6 +368,67 @@ mod tests {
         match_pre_signatures_basic_test(key_id, ids, contexts, 5, height, 4);
     }
 
+    #[test]
+    fn test_match_pre_signatures_matches_batch_as_unit_all() {
+        test_match_pre_signatures_matches_batch_as_unit(&ecdsa_key_id(1));
+        test_match_pre_signatures_matches_batch_as_unit(&schnorr_key_id(2));
+    }
+
+    fn test_match_pre_signatures_matches_batch_as_unit(key_id: &MasterPublicKeyId) {
+        // 4 pre-signatures for key 1
+        let ids = BTreeSet::from_iter((1..5).map(PreSigId));
+        // 1 context followed by a batch of 3 and another context
+        let mut contexts = BTreeMap::from_iter([fake_context(1, key_id, None)]);
+        contexts.extend(fake_batch_contexts(2..5, key_id));
+        contexts.extend([fake_context(5, key_id, None)]);
+        // The single context and the whole batch should be matched
+        match_pre_signatures_basic_test(key_id, ids, contexts, 5, Height::from(1), 4);
+    }
+
+    #[test]
+    fn test_match_pre_signatures_doesnt_split_batch_all() {
+        test_match_pre_signatures_doesnt_split_batch(&ecdsa_key_id(1));
+        test_match_pre_signatures_doesnt_split_batch(&schnorr_key_id(2));
+    }
+
+    fn test_match_pre_signatures_doesnt_split_batch(key_id: &MasterPublicKeyId) {
+        // 3 pre-signatures for key 1
+        let ids = BTreeSet::from_iter((1..4).map(PreSigId));
+        // 1 context followed by a batch of 3 and another context
+        let mut contexts = BTreeMap::from_iter([fake_context(1, key_id, None)]);
+        contexts.extend(fake_batch_contexts(2..5, key_id));
+        contexts.extend([fake_context(5, key_id, None)]);
+        // Only the first context should be matched, as there are not enough pre-signatures
+        // for the batch, and requests after the batch must wait for it.
+        match_pre_signatures_basic_test(
+            key_id,
+            ids.clone(),
+            contexts.clone(),
+            5,
+            Height::from(1),
+            1,
+        );
+        // The same applies if the batch would exceed max_ongoing_signatures.
+        match_pre_signatures_basic_test(key_id, ids, contexts, 3, Height::from(1), 1);
+    }
+
+    #[test]
+    fn test_match_pre_signatures_matches_oversized_batch_alone_all() {
+        test_match_pre_signatures_matches_oversized_batch_alone(&ecdsa_key_id(1));
+        test_match_pre_signatures_matches_oversized_batch_alone(&schnorr_key_id(2));
+    }
+
+    fn test_match_pre_signatures_matches_oversized_batch_alone(key_id: &MasterPublicKeyId) {
+        // 5 pre-signatures for key 1
+        let ids = BTreeSet::from_iter((1..6).map(PreSigId));
+        // A batch of 4 followed by another context
+        let mut contexts = BTreeMap::from_iter(fake_batch_contexts(1..5, key_id));
+        contexts.extend([fake_context(5, key_id, None)]);
+        // The batch exceeds max_ongoing_signatures, but should still be matched since no other
+        // signature is ongoing. Nothing may be matched after it.
+        match_pre_signatures_basic_test(key_id, ids, contexts, 2, Height::from(1), 4);
+    }
+
     #[test]
     fn test_match_pre_signatures_doesnt_update_heightn_all() {
         test_match_pre_signatures_doesnt_update_height(&ecdsa_key_id(1));
@@ -0,0 +1,257 @@
+use ic_config::execution_environment::Config as ExecutionConfig;
+use ic_config::flag_status::FlagStatus;
+use ic_config::subnet_config::SubnetConfig;
+use ic_management_canister_types::{
+    CanisterInstallMode, CanisterSettingsArgsBuilder, FetchCanisterProfileRequest,
+    FetchCanisterProfileResponse, FunctionProfileRecord, Payload,
+};
+use ic_registry_subnet_type::SubnetType;
+use ic_state_machine_tests::{
+    ErrorCode, PrincipalId, StateMachine, StateMachineBuilder, StateMachineConfig, UserError,
+};
+use ic_test_utilities_execution_environment::get_reply;
+use ic_types::{ingress::WasmResult, CanisterId, Cycles};
+
+// Function indices: `msg_reply` is 0, `inner` is 1, `outer` is 2 and `go` is 3.
+const PROFILED_WAT: &str = r#"
+    (module
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (func $inner (param i32) (result i32)
+            (i32.add (local.get 0) (i32.const 1))
+        )
+        (func $outer
+            (drop (call $inner (i32.const 1)))
+            (drop (call $inner (i32.const 2)))
+        )
+        (func $go (export "canister_update go")
+            (call $outer)
+            (call $msg_reply)
+        )
+        (memory 1)
+    )"#;
+
+fn setup(canister_profiling: FlagStatus) -> (StateMachine, CanisterId, PrincipalId) {
+    let subnet_type = SubnetType::Application;
+    let mut execution_config = ExecutionConfig::default();
+    execution_config
+        .embedders_config
+        .feature_flags
+        .canister_profiling = canister_profiling;
+    let config = StateMachineConfig::new(SubnetConfig::new(subnet_type), execution_config);
+    let env = StateMachineBuilder::new()
+        .with_config(Some(config))
+        .with_subnet_type(subnet_type)
+        .with_checkpoints_enabled(false)
+        .build();
+    let controller = PrincipalId::new_user_test_id(42);
+    let canister_id = install_canister(&env, controller, true);
+    (env, canister_id, controller)
+}
+
+fn install_canister(env: &StateMachine, controller: PrincipalId, profiling: bool) -> CanisterId {
+    let canister_id = env.create_canister_with_cycles(
+        None,
+        Cycles::from(100_000_000_000_u128),
+        Some(
+            CanisterSettingsArgsBuilder::new()
+                .with_controllers(vec![controller])
+                .with_profiling(profiling)
+                .build(),
+        ),
+    );
+    env.install_wasm_in_mode(
+        canister_id,
+        CanisterInstallMode::Install,
+        wat::parse_str(PROFILED_WAT).unwrap(),
+        vec![],
+    )
+    .unwrap();
+    canister_id
+}
+
+fn cycles_consumed_by_go(
+    env: &StateMachine,
+    canister_id: CanisterId,
+    controller: PrincipalId,
+) -> u128 {
+    let balance_before = env.cycle_balance(canister_id);
+    env.execute_ingress_as(controller, canister_id, "go", vec![])
+        .unwrap();
+    balance_before - env.cycle_balance(canister_id)
+}
+
+fn fetch_canister_profile(
+    env: &StateMachine,
+    sender: PrincipalId,
+    canister_id: CanisterId,
+) -> Result<WasmResult, UserError> {
+    env.query_as(
+        sender,
+        CanisterId::ic_00(),
+        "fetch_canister_profile",
+        FetchCanisterProfileRequest::new(canister_id).encode(),
+    )
+}
+
+fn function<'a>(
+    response: &'a FetchCanisterProfileResponse,
+    name: &str,
+) -> &'a FunctionProfileRecord {
+    response
+        .functions
+        .iter()
+        .find(|function| function.function_name.as_deref() == Some(name))
+        .unwrap()
+}
+
+#[test]
+fn test_fetch_canister_profile_after_update_call() {
+    let (env, canister_id, controller) = setup(FlagStatus::Enabled);
+    env.execute_ingress_as(controller, canister_id, "go", vec![])
+        .unwrap();
+
+    let result = fetch_canister_profile(&env, controller, canister_id);
+    let response = FetchCanisterProfileResponse::decode(&get_reply(result)).unwrap();
+
+    let (inner, outer, go) = (
+        function(&response, "inner"),
+        function(&response, "outer"),
+        function(&response, "go"),
+    );
+    assert_eq!((inner.function_index, inner.calls), (1, 2));
+    assert_eq!((outer.function_index, outer.calls), (2, 1));
+    assert_eq!((go.function_index, go.calls), (3, 1));
+    assert!(inner.exclusive_instructions > 0);
+    assert_eq!(inner.inclusive_instructions, inner.exclusive_instructions);
+    assert_eq!(
+        outer.inclusive_instructions,
+        outer.exclusive_instructions + inner.inclusive_instructions
+    );
+    assert_eq!(
+        go.inclusive_instructions,
+        go.exclusive_instructions + outer.inclusive_instructions
+    );
+
+    let folded = response.to_folded_stacks();
+    assert!(folded.contains(&format!(
+        "go;outer;inner {}\n",
+        inner.exclusive_instructions
+    )));
+}
+
+#[test]
+fn test_profiling_hooks_are_charged_only_for_canisters_with_profiling_enabled() {
+    let (env, profiled, controller) = setup(FlagStatus::Enabled);
+    let not_profiled = install_canister(&env, controller, false);
+
+    let profiled_cost = cycles_consumed_by_go(&env, profiled, controller);
+    let not_profiled_cost = cycles_consumed_by_go(&env, not_profiled, controller);
+    assert!(profiled_cost > not_profiled_cost);
+
+    // Disabling profiling drops the hooks from the compiled module.
+    env.update_settings(
+        &profiled,
+        CanisterSettingsArgsBuilder::new()
+            .with_profiling(false)
+            .build(),
+    )
+    .unwrap();
+    assert_eq!(
+        cycles_consumed_by_go(&env, profiled, controller),
+        not_profiled_cost
+    );
+
+    // Enabling profiling again adds them back.
+    env.update_settings(
+        &profiled,
+        CanisterSettingsArgsBuilder::new()
+            .with_profiling(true)
+            .build(),
+    )
+    .unwrap();
+    assert_eq!(
+        cycles_consumed_by_go(&env, profiled, controller),
+        profiled_cost
+    );
+}
+
+#[test]
+fn test_canister_profile_counts_towards_memory_usage() {
+    let (env, canister_id, controller) = setup(FlagStatus::Enabled);
+    env.execute_ingress_as(controller, canister_id, "go", vec![])
+        .unwrap();
+
+    let state = env.get_latest_state();
+    let canister = state.canister_state(&canister_id).unwrap();
+    let profile_memory_usage = canister.canister_profile_memory_usage();
+    assert!(profile_memory_usage.get() > 0);
+    let memory_usage = canister.memory_usage();
+
+    env.update_settings(
+        &canister_id,
+        CanisterSettingsArgsBuilder::new()
+            .with_profiling(false)
+            .build(),
+    )
+    .unwrap();
+    let state = env.get_latest_state();
+    let canister = state.canister_state(&canister_id).unwrap();
+    assert_eq!(canister.canister_profile_memory_usage().get(), 0);
+    assert_eq!(canister.memory_usage(), memory_usage - profile_memory_usage);
+}
+
+#[test]
+fn test_canister_profile_is_cleared_on_upgrade() {
+    let (env, canister_id, controller) = setup(FlagStatus::Enabled);
+    env.execute_ingress_as(controller, canister_id, "go", vec![])
+        .unwrap();
+    env.install_wasm_in_mode(
+        canister_id,
+        CanisterInstallMode::Upgrade,
+        wat::parse_str(PROFILED_WAT).unwrap(),
+        vec![],
+    )
+    .unwrap();
+
+    let result = fetch_canister_profile(&env, controller, canister_id);
+    let response = FetchCanisterProfileResponse::decode(&get_reply(result)).unwrap();
+    assert_eq!(response, FetchCanisterProfileResponse::default());
+}
+
+#[test]
+fn test_fetch_canister_profile_only_allowed_for_controllers() {
+    let (env, canister_id, _controller) = setup(FlagStatus::Enabled);
+    let not_a_controller = PrincipalId::new_user_test_id(7);
+
+    let err = fetch_canister_profile(&env, not_a_controller, canister_id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterRejectedMessage);
+}
+
+#[test]
+fn test_fetch_canister_profile_fails_if_profiling_disabled() {
+    let (env, canister_id, controller) = setup(FlagStatus::Enabled);
+    env.update_settings(
+        &canister_id,
+        CanisterSettingsArgsBuilder::new()
+            .with_profiling(false)
+            .build(),
+    )
+    .unwrap();
+
+    let err = fetch_canister_profile(&env, controller, canister_id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterRejectedMessage);
+    assert!(err
+        .description()
+        .contains("does not have profiling enabled"));
+}
+
+#[test]
+fn test_fetch_canister_profile_fails_if_not_enabled_on_subnet() {
+    let (env, canister_id, controller) = setup(FlagStatus::Disabled);
+    env.execute_ingress_as(controller, canister_id, "go", vec![])
+        .unwrap();
+
+    let err = fetch_canister_profile(&env, controller, canister_id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterRejectedMessage);
+    assert!(err.description().contains("not enabled on this subnet"));
+}
@@ -785,6 +785,74 @@ fn take_canister_snapshot_request_fails_when_subnet_capacity_reached() {
     assert_eq!(error.code(), ErrorCode::SubnetOversubscribed);
 }
 
+#[test]
+fn move_canister_state_moves_canister_snapshots() {
+    let new_env = |subnet_seed: u8| {
+        StateMachineBuilder::new()
+            .with_config(Some(StateMachineConfig::new(
+                SubnetConfig::new(SubnetType::Application),
+                HypervisorConfig {
+                    canister_snapshots: FlagStatus::Enabled,
+                    ..Default::default()
+                },
+            )))
+            .with_subnet_seed([subnet_seed; 32])
+            .with_checkpoints_enabled(true)
+            .build()
+    };
+    let source_env = new_env(1);
+    let destination_env = new_env(2);
+
+    let canister_id = create_universal_canister_with_cycles(
+        &source_env,
+        Some(CanisterSettingsArgsBuilder::new().build()),
+        INITIAL_CYCLES_BALANCE,
+    );
+    source_env
+        .execute_ingress(
+            canister_id,
+            "update",
+            wasm().stable_grow(1).reply_data(&[42]).build(),
+        )
+        .unwrap();
+    let snapshot_id = source_env
+        .take_canister_snapshot(TakeCanisterSnapshotArgs::new(canister_id, None))
+        .unwrap()
+        .snapshot_id();
+
+    source_env
+        .move_canister_state_to(&destination_env, canister_id)
+        .unwrap();
+
+    let source_state = source_env.get_latest_state();
+    assert!(source_state.canister_state(&canister_id).is_none());
+    assert_eq!(
+        source_state
+            .canister_snapshots
+            .snapshots_count(&canister_id),
+        0
+    );
+
+    // The snapshot is part of the next checkpoint of the destination subnet.
+    destination_env.checkpointed_tick();
+    let destination_state = destination_env.get_latest_state();
+    let canister = destination_state.canister_state(&canister_id).unwrap();
+    let snapshots = destination_state
+        .canister_snapshots
+        .list_snapshots(canister_id);
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].0, snapshot_id);
+    assert_eq!(
+        snapshots[0].1.stable_memory().size,
+        canister
+            .execution_state
+            .as_ref()
+            .unwrap()
+            .stable_memory
+            .size
+    );
+}
+
 fn assert_replied(result: Result<WasmResult, UserError>) {
     match result {
         Ok(wasm_result) => match wasm_result {
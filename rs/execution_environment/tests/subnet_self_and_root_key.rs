@@ -0,0 +1,75 @@
+use ic_management_canister_types::CanisterInstallMode;
+use ic_registry_subnet_type::SubnetType;
+use ic_state_machine_tests::{StateMachine, StateMachineBuilder, UserError, WasmResult};
+use ic_test_utilities::universal_canister::{wasm, UNIVERSAL_CANISTER_WASM};
+use ic_types::{CanisterId, Cycles};
+
+fn setup() -> (StateMachine, CanisterId) {
+    let env = StateMachineBuilder::new()
+        .with_subnet_type(SubnetType::Application)
+        .with_checkpoints_enabled(false)
+        .build();
+    let canister_id =
+        env.create_canister_with_cycles(None, Cycles::from(100_000_000_000_u128), None);
+    env.install_wasm_in_mode(
+        canister_id,
+        CanisterInstallMode::Install,
+        UNIVERSAL_CANISTER_WASM.to_vec(),
+        vec![],
+    )
+    .unwrap();
+
+    (env, canister_id)
+}
+
+fn expect_reply(result: Result<WasmResult, UserError>) -> Vec<u8> {
+    match result {
+        Ok(WasmResult::Reply(bytes)) => bytes,
+        Ok(WasmResult::Reject(msg)) => panic!("Unexpected reject: {}", msg),
+        Err(err) => panic!("Unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_subnet_self_returns_own_subnet_id_in_update() {
+    let (env, canister_id) = setup();
+    let result = env.execute_ingress(
+        canister_id,
+        "update",
+        wasm().subnet_self().append_and_reply().build(),
+    );
+    assert_eq!(expect_reply(result), env.get_subnet_id().get().to_vec());
+}
+
+#[test]
+fn test_subnet_self_returns_own_subnet_id_in_query() {
+    let (env, canister_id) = setup();
+    let result = env.query(
+        canister_id,
+        "query",
+        wasm().subnet_self().append_and_reply().build(),
+    );
+    assert_eq!(expect_reply(result), env.get_subnet_id().get().to_vec());
+}
+
+#[test]
+fn test_root_key_returns_der_encoded_nns_public_key_in_update() {
+    let (env, canister_id) = setup();
+    let result = env.execute_ingress(
+        canister_id,
+        "update",
+        wasm().root_key().append_and_reply().build(),
+    );
+    assert_eq!(expect_reply(result), env.root_key_der());
+}
+
+#[test]
+fn test_root_key_returns_der_encoded_nns_public_key_in_query() {
+    let (env, canister_id) = setup();
+    let result = env.query(
+        canister_id,
+        "query",
+        wasm().root_key().append_and_reply().build(),
+    );
+    assert_eq!(expect_reply(result), env.root_key_der());
+}
@@ -2,7 +2,8 @@ use candid::Decode;
 use ic_management_canister_types::{
     self as ic00, CanisterInstallMode, DerivationPath, ECDSAPublicKeyResponse, EcdsaCurve,
     EcdsaKeyId, MasterPublicKeyId, Method, Payload as Ic00Payload, SchnorrAlgorithm, SchnorrKeyId,
-    SchnorrPublicKeyResponse, SignWithECDSAReply, SignWithSchnorrReply,
+    SchnorrPublicKeyResponse, SignWithECDSABatchReply, SignWithECDSAReply,
+    SignWithSchnorrBatchReply, SignWithSchnorrReply,
 };
 use ic_registry_subnet_type::SubnetType;
 use ic_state_machine_tests::{PrincipalId, StateMachine, StateMachineBuilder, UserError};
@@ -87,6 +88,40 @@ fn sign_with_threshold_key_payload(method: Method, key_id: MasterPublicKeyId) ->
     }
 }
 
+fn sign_with_threshold_key_batch_payload(
+    method: Method,
+    key_id: MasterPublicKeyId,
+    batch_size: usize,
+) -> Vec<u8> {
+    match method {
+        Method::SignWithECDSABatch => ic00::SignWithECDSABatchArgs {
+            messages: ic00::SignWithECDSABatchEntries::new(
+                (0..batch_size)
+                    .map(|i| ic00::SignWithECDSABatchEntry {
+                        message_hash: [i as u8; 32],
+                        derivation_path: DerivationPath::new(vec![]),
+                    })
+                    .collect(),
+            ),
+            key_id: into_inner_ecdsa(key_id),
+        }
+        .encode(),
+        Method::SignWithSchnorrBatch => ic00::SignWithSchnorrBatchArgs {
+            messages: ic00::SignWithSchnorrBatchEntries::new(
+                (0..batch_size)
+                    .map(|i| ic00::SignWithSchnorrBatchEntry {
+                        message: vec![i as u8; 32],
+                        derivation_path: DerivationPath::new(vec![]),
+                    })
+                    .collect(),
+            ),
+            key_id: into_inner_schnorr(key_id),
+        }
+        .encode(),
+        _ => panic!("unexpected method"),
+    }
+}
+
 fn threshold_public_key_payload(method: Method, key_id: MasterPublicKeyId) -> Vec<u8> {
     match method {
         Method::ECDSAPublicKey => ic00::ECDSAPublicKeyArgs {
@@ -721,3 +756,224 @@ fn test_sign_with_threshold_key_queue_fills_up() {
         );
     }
 }
+
+#[test]
+fn test_sign_with_threshold_key_batch_fee_charged_per_signature() {
+    let test_cases = vec![
+        (Method::SignWithECDSABatch, make_ecdsa_key("some_key")),
+        (Method::SignWithSchnorrBatch, make_schnorr_key("some_key")),
+    ];
+    for (method, key_id) in test_cases {
+        let fee = 1_000_000;
+        let payment = 5_000_000;
+        let batch_size = 3;
+        let own_subnet = subnet_test_id(1);
+        let nns_subnet = subnet_test_id(2);
+        let mut env = StateMachineBuilder::new()
+            .with_checkpoints_enabled(false)
+            .with_subnet_id(own_subnet)
+            .with_nns_subnet_id(nns_subnet)
+            .with_ecdsa_signature_fee(fee)
+            .with_schnorr_signature_fee(fee)
+            .with_idkg_key(key_id.clone())
+            .build();
+
+        let canister_id = create_universal_canister(&env);
+        let msg_id = env.send_ingress(
+            PrincipalId::new_anonymous(),
+            canister_id,
+            "update",
+            wasm()
+                .call_with_cycles(
+                    ic00::IC_00,
+                    method,
+                    call_args().other_side(sign_with_threshold_key_batch_payload(
+                        method, key_id, batch_size,
+                    )),
+                    Cycles::new(payment),
+                )
+                .build(),
+        );
+
+        // Disable automatic signing to be able to inspect the contexts.
+        env.set_ecdsa_signing_enabled(false);
+        env.set_schnorr_signing_enabled(false);
+        env.tick();
+
+        // One context per message, each carrying the payment minus the fee for the whole batch.
+        let contexts = match method {
+            Method::SignWithECDSABatch => env.sign_with_ecdsa_contexts(),
+            Method::SignWithSchnorrBatch => env.sign_with_schnorr_contexts(),
+            _ => panic!("Unexpected method"),
+        };
+        assert_eq!(contexts.len(), batch_size);
+        for (index, context) in contexts.values().enumerate() {
+            assert_eq!(
+                context.request.payment.get(),
+                payment - fee * batch_size as u128
+            );
+            let entry = context.batch.unwrap();
+            assert_eq!(entry.index as usize, index);
+            assert_eq!(entry.size as usize, batch_size);
+        }
+
+        // Enable automatic signing to complete the request.
+        env.set_ecdsa_signing_enabled(true);
+        env.set_schnorr_signing_enabled(true);
+        let result = env.await_ingress(msg_id, 100);
+        let signatures: Vec<_> = match method {
+            Method::SignWithECDSABatch => expect_reply::<SignWithECDSABatchReply>(result)
+                .signatures
+                .into_iter()
+                .map(|reply| reply.signature)
+                .collect(),
+            Method::SignWithSchnorrBatch => expect_reply::<SignWithSchnorrBatchReply>(result)
+                .signatures
+                .into_iter()
+                .map(|reply| reply.signature)
+                .collect(),
+            _ => panic!("Unexpected method"),
+        };
+        // Expect one distinct, non-empty signature per message.
+        assert_eq!(signatures.len(), batch_size);
+        assert!(signatures.iter().all(|signature| !signature.is_empty()));
+        assert!(signatures.iter().all_unique());
+    }
+}
+
+#[test]
+fn test_sign_with_threshold_key_batch_rejected_without_fee() {
+    let test_cases = vec![
+        (Method::SignWithECDSABatch, make_ecdsa_key("some_key")),
+        (Method::SignWithSchnorrBatch, make_schnorr_key("some_key")),
+    ];
+    for (method, key_id) in test_cases {
+        let fee = 1_000_000;
+        let batch_size = 3;
+        let env = StateMachineBuilder::new()
+            .with_checkpoints_enabled(false)
+            .with_subnet_id(subnet_test_id(1))
+            .with_nns_subnet_id(subnet_test_id(2))
+            .with_ecdsa_signature_fee(fee)
+            .with_schnorr_signature_fee(fee)
+            .with_idkg_key(key_id.clone())
+            .build();
+
+        let canister_id = create_universal_canister(&env);
+        // Enough to pay for one signature, but not for the whole batch.
+        let payment = 2 * fee;
+        let result = env.execute_ingress(
+            canister_id,
+            "update",
+            wasm()
+                .call_with_cycles(
+                    ic00::IC_00,
+                    method,
+                    call_args()
+                        .other_side(sign_with_threshold_key_batch_payload(
+                            method, key_id, batch_size,
+                        ))
+                        .on_reject(wasm().reject_message().reject()),
+                    Cycles::new(payment),
+                )
+                .build(),
+        );
+
+        assert_eq!(
+            result,
+            Ok(WasmResult::Reject(format!(
+                "{method} request sent with 2_000_000 cycles, but 3_000_000 cycles are required."
+            )))
+        );
+    }
+}
+
+#[test]
+fn test_sign_with_threshold_key_batch_counts_against_queue() {
+    let test_cases = vec![
+        (
+            Method::SignWithECDSA,
+            Method::SignWithECDSABatch,
+            make_ecdsa_key("some_key"),
+            20,
+        ),
+        (
+            Method::SignWithSchnorr,
+            Method::SignWithSchnorrBatch,
+            make_schnorr_key("some_key"),
+            20,
+        ),
+    ];
+    for (method, batch_method, key_id, max_queue_size) in test_cases {
+        let env = StateMachineBuilder::new()
+            .with_checkpoints_enabled(false)
+            .with_subnet_type(SubnetType::System)
+            .with_subnet_id(subnet_test_id(1))
+            .with_nns_subnet_id(subnet_test_id(2))
+            .with_idkg_key(key_id.clone())
+            // Turn off automatic signatures to fill up the queue.
+            .with_ecdsa_signing_enabled(false)
+            .with_schnorr_signing_enabled(false)
+            .build();
+
+        let canister_id = create_universal_canister(&env);
+        let call = |method: Method, payload: Vec<u8>| {
+            wasm()
+                .call_with_cycles(
+                    ic00::IC_00,
+                    method,
+                    call_args()
+                        .other_side(payload)
+                        .on_reject(wasm().reject_message().reject()),
+                    Cycles::from(2_000_000u128),
+                )
+                .build()
+        };
+        for _i in 0..max_queue_size - 2 {
+            let _msg_id = env.send_ingress(
+                PrincipalId::new_anonymous(),
+                canister_id,
+                "update",
+                call(
+                    method,
+                    sign_with_threshold_key_payload(method, key_id.clone()),
+                ),
+            );
+        }
+
+        // A batch of 3 doesn't fit into the 2 remaining slots.
+        let result = env.execute_ingress(
+            canister_id,
+            "update",
+            call(
+                batch_method,
+                sign_with_threshold_key_batch_payload(batch_method, key_id.clone(), 3),
+            ),
+        );
+        assert_eq!(
+            result,
+            Ok(WasmResult::Reject(format!(
+                "{} request failed: signature queue for key {} is full.",
+                batch_method, key_id,
+            )))
+        );
+
+        // A batch of 2 fills up the queue.
+        let _msg_id = env.send_ingress(
+            PrincipalId::new_anonymous(),
+            canister_id,
+            "update",
+            call(
+                batch_method,
+                sign_with_threshold_key_batch_payload(batch_method, key_id.clone(), 2),
+            ),
+        );
+        env.tick();
+        let contexts = match method {
+            Method::SignWithECDSA => env.sign_with_ecdsa_contexts(),
+            Method::SignWithSchnorr => env.sign_with_schnorr_contexts(),
+            _ => panic!("Unexpected method"),
+        };
+        assert_eq!(contexts.len(), max_queue_size);
+    }
+}
@@ -38,6 +38,7 @@ use std::sync::Mutex;
 use std::sync::{Arc, RwLock};
 use tokio::sync::mpsc::UnboundedSender;
 use tower::ServiceExt;
+use tracing::{info_span, Instrument};
 
 pub struct IngressValidatorBuilder {
     log: Option<ReplicaLogger>,
@@ -228,6 +229,7 @@ impl IngressValidator {
         }
 
         let message_id = msg.id();
+        let span = info_span!("http_call_validate", message_id = %message_id);
         let registry_version = registry_client.get_latest_version();
         let (ingress_registry_settings, provisional_whitelist) =
             get_registry_data(&log, subnet_id, registry_version, registry_client.as_ref())?;
@@ -248,7 +250,9 @@ impl IngressValidator {
         // Since spawn blocking requires 'static we can't use any references
         let request_c = msg.as_ref().clone();
 
+        let validation_span = span.clone();
         tokio::task::spawn_blocking(move || {
+            let _span = validation_span.entered();
             validator.validate_request(&request_c, current_time(), &root_of_trust_provider)
         })
         .await
@@ -264,6 +268,7 @@ impl IngressValidator {
 
         match ingress_filter
             .oneshot((provisional_whitelist, msg.content().clone()))
+            .instrument(span)
             .await
         {
             Err(_) => panic!("Can't panic on Infallible"),
@@ -301,6 +306,7 @@ impl IngressMessageSubmitter {
             node_id,
             message,
         } = self;
+        let _span = info_span!("http_call_submit", message_id = %message.id()).entered();
 
         // Submission will fail if P2P is not running, meaning there is
         // no receiver for the ingress message.
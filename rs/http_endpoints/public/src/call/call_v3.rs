@@ -42,6 +42,7 @@ use std::{
 };
 use tokio_util::time::FutureExt;
 use tower::{util::BoxCloneService, ServiceBuilder};
+use tracing::{info_span, Instrument};
 
 const LOG_EVERY_N_SECONDS: i32 = 10;
 
@@ -256,6 +257,10 @@ async fn call_sync_v3(
 
     match certification_subscriber
         .wait_for_certification()
+        .instrument(info_span!(
+            "ingress_wait_for_certification",
+            message_id = %message_id
+        ))
         .timeout(Duration::from_secs(
             ingress_message_certificate_timeout_seconds,
         ))
@@ -11,6 +11,7 @@ use ic_interfaces::{
 };
 use ic_logger::{debug, warn};
 use ic_types::{artifact::IngressMessageId, ingress::IngressStatus, CountBytes};
+use tracing::info_span;
 
 impl<T: IngressPool> ChangeSetProducer<T> for IngressManager {
     type ChangeSet = ChangeSet;
@@ -54,6 +55,8 @@ impl<T: IngressPool> ChangeSetProducer<T> for IngressManager {
             .get_all_by_expiry_range(expiry_range.clone());
         change_set.extend(unvalidated_artifacts.map(|artifact| {
             let ingress_object = &artifact.message;
+            let _span =
+                info_span!("ingress_validate", message_id = %ingress_object.message_id).entered();
             let ingress_message = &ingress_object.signed_ingress;
             let max_ingress_bytes_per_message =
                 ingress_message_settings.max_ingress_bytes_per_message;
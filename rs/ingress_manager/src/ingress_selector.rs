@@ -28,6 +28,7 @@ use ic_types::{
 };
 use ic_validator::RequestValidationError;
 use std::{collections::BTreeMap, collections::HashMap, sync::Arc};
+use tracing::info_span;
 
 /// Number of round-robin iterations that need to happen, before we weaken the selection
 /// rule #2. This weakening helps the ingress selector progress when the quota is either
@@ -162,6 +163,8 @@ impl IngressSelector for IngressManager {
                 // For a given canister, add valid ingress messsages until quota is met
                 let queue = &mut canister_queues.get_mut(&canister_id).unwrap();
                 while let Some(msg) = queue.msgs.last() {
+                    let _span =
+                        info_span!("ingress_select", message_id = %msg.msg.message_id).entered();
                     let ingress = &msg.msg.signed_ingress;
                     let result = self.validate_ingress(
                         IngressMessageId::from(ingress),
@@ -338,6 +341,11 @@ impl IngressSelector for IngressManager {
             let (ingress_id, ingress) = payload
                 .get(i)
                 .map_err(InvalidIngressPayloadReason::IngressPayloadError)?;
+            let _span = info_span!(
+                "ingress_validate_payload",
+                message_id = %MessageId::from(&ingress_id)
+            )
+            .entered();
 
             self.validate_ingress(
                 ingress_id.clone(),
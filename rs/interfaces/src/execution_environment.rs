@@ -155,6 +155,16 @@ pub enum SystemApiCallId {
     CanisterVersion,
     /// Tracker for `ic0.certified_data_set()`
     CertifiedDataSet,
+    /// Tracker for `ic0.cost_call()`
+    CostCall,
+    /// Tracker for `ic0.cost_create_canister()`
+    CostCreateCanister,
+    /// Tracker for `ic0.cost_http_request()`
+    CostHttpRequest,
+    /// Tracker for `ic0.cost_sign_with_ecdsa()`
+    CostSignWithEcdsa,
+    /// Tracker for `ic0.cost_sign_with_schnorr()`
+    CostSignWithSchnorr,
     /// Tracker for `ic0.cycles_burn128()`
     CyclesBurn128,
     /// Tracker for `ic0.data_certificate_copy()`
@@ -165,6 +175,18 @@ pub enum SystemApiCallId {
     DataCertificateSize,
     /// Tracker for `ic0.debug_print()`
     DebugPrint,
+    /// Tracker for `ic0.env_var_count()`
+    EnvVarCount,
+    /// Tracker for `ic0.env_var_name_copy()`
+    EnvVarNameCopy,
+    /// Tracker for `ic0.env_var_name_exists()`
+    EnvVarNameExists,
+    /// Tracker for `ic0.env_var_name_size()`
+    EnvVarNameSize,
+    /// Tracker for `ic0.env_var_value_copy()`
+    EnvVarValueCopy,
+    /// Tracker for `ic0.env_var_value_size()`
+    EnvVarValueSize,
     /// Tracker for `ic0.global_timer_set()`
     GlobalTimerSet,
     /// Tracker for `ic0.in_replicated_execution()`
@@ -215,6 +237,10 @@ pub enum SystemApiCallId {
     OutOfInstructions,
     /// Tracker for `ic0.performance_counter()`
     PerformanceCounter,
+    /// Tracker for `ic0.root_key_copy()`
+    RootKeyCopy,
+    /// Tracker for `ic0.root_key_size()`
+    RootKeySize,
     /// Tracker for `ic0.stable64_grow()`
     Stable64Grow,
     /// Tracker for `ic0.stable64_read()`
@@ -231,6 +257,10 @@ pub enum SystemApiCallId {
     StableSize,
     /// Tracker for `ic0.stable_write())`
     StableWrite,
+    /// Tracker for `ic0.subnet_self_copy()`
+    SubnetSelfCopy,
+    /// Tracker for `ic0.subnet_self_size()`
+    SubnetSelfSize,
     /// Tracker for `ic0.time()`
     Time,
     /// Tracker for `ic0.trap()`
@@ -712,6 +742,91 @@ pub trait SystemApi {
         heap: &mut [u8],
     ) -> HypervisorResult<()>;
 
+    /// Returns the size of the blob corresponding to the id of the subnet
+    /// on which the canister is running.
+    fn ic0_subnet_self_size(&self) -> HypervisorResult<usize>;
+
+    /// Copies `size` bytes starting from `offset` in the id blob of the
+    /// subnet on which the canister is running to heap[dst..dst+size].
+    fn ic0_subnet_self_copy(
+        &self,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()>;
+
+    /// Returns the size of the DER-encoded public key of the IC root of trust
+    /// (i.e., the public key of the NNS subnet).
+    fn ic0_root_key_size(&self) -> HypervisorResult<usize>;
+
+    /// Copies `size` bytes starting from `offset` in the DER-encoded public key
+    /// of the IC root of trust to heap[dst..dst+size].
+    fn ic0_root_key_copy(
+        &self,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()>;
+
+    /// Returns the number of environment variables of the canister.
+    fn ic0_env_var_count(&self) -> HypervisorResult<usize>;
+
+    /// Returns the size of the name of the environment variable at position
+    /// `index`, where variables are ordered by name.
+    ///
+    /// Traps if `index` is not smaller than the number of variables.
+    fn ic0_env_var_name_size(&self, index: usize) -> HypervisorResult<usize>;
+
+    /// Copies `size` bytes starting from `offset` in the name of the
+    /// environment variable at position `index` to heap[dst..dst+size].
+    ///
+    /// Traps if `index` is not smaller than the number of variables.
+    fn ic0_env_var_name_copy(
+        &self,
+        index: usize,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()>;
+
+    /// Returns 1 if an environment variable with the name stored in
+    /// heap[name_src..name_src+name_size] exists, 0 otherwise.
+    fn ic0_env_var_name_exists(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        heap: &[u8],
+    ) -> HypervisorResult<i32>;
+
+    /// Returns the size of the value of the environment variable with the
+    /// name stored in heap[name_src..name_src+name_size].
+    ///
+    /// Traps if no such variable exists.
+    fn ic0_env_var_value_size(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        heap: &[u8],
+    ) -> HypervisorResult<usize>;
+
+    /// Copies `size` bytes starting from `offset` in the value of the
+    /// environment variable with the name stored in
+    /// heap[name_src..name_src+name_size] to heap[dst..dst+size].
+    ///
+    /// Traps if no such variable exists.
+    fn ic0_env_var_value_copy(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()>;
+
     /// Outputs the specified bytes on the heap as a string on STDOUT.
     fn ic0_debug_print(&self, src: usize, size: usize, heap: &[u8]) -> HypervisorResult<()>;
 
@@ -1151,6 +1266,73 @@ pub trait SystemApi {
         dst: usize,
         heap: &mut [u8],
     ) -> HypervisorResult<()>;
+
+    /// Copies to `dst` the amount of cycles (as a 128-bit value) charged for
+    /// performing a call with the given sizes of the method name and payload.
+    /// The amount does not include the cycles attached to the call.
+    ///
+    /// This system call traps if dst+16 exceeds the size of the WebAssembly memory.
+    fn ic0_cost_call(
+        &self,
+        method_name_size: u64,
+        payload_size: u64,
+        dst: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()>;
+
+    /// Copies to `dst` the amount of cycles (as a 128-bit value) charged for
+    /// creating a canister on this subnet.
+    ///
+    /// This system call traps if dst+16 exceeds the size of the WebAssembly memory.
+    fn ic0_cost_create_canister(&self, dst: usize, heap: &mut [u8]) -> HypervisorResult<()>;
+
+    /// Copies to `dst` the amount of cycles (as a 128-bit value) charged for
+    /// an HTTPS outcall with the given request size and maximum response size.
+    ///
+    /// This system call traps if dst+16 exceeds the size of the WebAssembly memory.
+    fn ic0_cost_http_request(
+        &self,
+        request_size: u64,
+        max_res_bytes: u64,
+        dst: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()>;
+
+    /// Copies to `dst` the amount of cycles (as a 128-bit value) charged for
+    /// `sign_with_ecdsa` with the key identified by the name in src/size
+    /// and the given curve (0 = secp256k1).
+    ///
+    /// Returns 0 on success, 1 if the curve is unknown, and 2 if no subnet
+    /// is enabled to sign with the key. Nothing is copied in case of an error.
+    ///
+    /// This system call traps if src+size or dst+16 exceeds the size of
+    /// the WebAssembly memory.
+    fn ic0_cost_sign_with_ecdsa(
+        &self,
+        src: usize,
+        size: usize,
+        curve: u32,
+        dst: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<u32>;
+
+    /// Copies to `dst` the amount of cycles (as a 128-bit value) charged for
+    /// `sign_with_schnorr` with the key identified by the name in src/size
+    /// and the given algorithm (0 = bip340secp256k1, 1 = ed25519).
+    ///
+    /// Returns 0 on success, 1 if the algorithm is unknown, and 2 if no subnet
+    /// is enabled to sign with the key. Nothing is copied in case of an error.
+    ///
+    /// This system call traps if src+size or dst+16 exceeds the size of
+    /// the WebAssembly memory.
+    fn ic0_cost_sign_with_schnorr(
+        &self,
+        src: usize,
+        size: usize,
+        algorithm: u32,
+        dst: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<u32>;
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -1191,6 +1373,10 @@ pub struct RegistryExecutionSettings {
 pub struct ChainKeySettings {
     pub max_queue_size: u32,
     pub pre_signatures_to_create_in_advance: u32,
+    /// The maximum number of pre-signatures that can be available for the key
+    /// at the same time. Batches of signature requests larger than this can
+    /// never be matched as a unit.
+    pub max_pre_signatures: u32,
 }
 
 pub trait Scheduler: Send {
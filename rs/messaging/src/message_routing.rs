@@ -746,12 +746,24 @@ impl BatchProcessorImpl {
                 .key_configs
                 .iter()
                 .map(|key_config| {
+                    // Consensus creates up to `max_pre_signatures_to_create` pre-signatures
+                    // (but at least `min_pre_signatures_to_create`), both of which default
+                    // to `pre_signatures_to_create_in_advance`.
+                    let in_advance = key_config.pre_signatures_to_create_in_advance;
+                    let max_pre_signatures = key_config
+                        .max_pre_signatures_to_create
+                        .unwrap_or(in_advance)
+                        .max(
+                            key_config
+                                .min_pre_signatures_to_create
+                                .unwrap_or(in_advance),
+                        );
                     (
                         key_config.key_id.clone(),
                         ChainKeySettings {
                             max_queue_size: key_config.max_queue_size,
-                            pre_signatures_to_create_in_advance: key_config
-                                .pre_signatures_to_create_in_advance,
+                            pre_signatures_to_create_in_advance: in_advance,
+                            max_pre_signatures,
                         },
                     )
                 })
@@ -1077,7 +1089,7 @@ impl BatchProcessorImpl {
 }
 
 impl BatchProcessor for BatchProcessorImpl {
-    #[instrument(skip_all)]
+    #[instrument(skip_all, fields(height = batch.batch_number.get()))]
     fn process_batch(&self, batch: Batch) {
         let _process_batch_start = Instant::now();
         let since = Instant::now();
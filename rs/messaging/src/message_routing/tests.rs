@@ -741,6 +741,8 @@ fn try_read_registry_succeeds_with_fully_specified_registry_records() {
                         }),
                         pre_signatures_to_create_in_advance: 891,
                         max_queue_size: 891,
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: None,
                     },
                     KeyConfig {
                         key_id: MasterPublicKeyId::Ecdsa(EcdsaKeyId {
@@ -749,6 +751,8 @@ fn try_read_registry_succeeds_with_fully_specified_registry_records() {
                         }),
                         pre_signatures_to_create_in_advance: 891,
                         max_queue_size: 891,
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: None,
                     },
                 ],
                 ..ChainKeyConfig::default()
@@ -961,6 +965,14 @@ fn try_read_registry_succeeds_with_fully_specified_registry_records() {
                     .unwrap()
                     .max_queue_size
             );
+            assert_eq!(
+                key_config.pre_signatures_to_create_in_advance,
+                registry_execution_settings
+                    .chain_key_settings
+                    .get(&key_config.key_id)
+                    .unwrap()
+                    .max_pre_signatures
+            );
         }
 
         assert_eq!(
@@ -787,6 +787,32 @@ impl StreamHandlerImpl {
         }
     }
 
+    /// Turns a `CanisterNotFound` error for a canister that was already migrated
+    /// from this subnet to the next subnet in its migration trace (but not yet
+    /// rerouted in the routing table) into a `CanisterMigrating` error, so that
+    /// the sender gets a transient reject and can retry after rerouting.
+    fn canister_migrated_away(&self, err: StateError, state: &ReplicatedState) -> StateError {
+        match err {
+            StateError::CanisterNotFound(canister_id) => {
+                let next_host = migration_trace(state, canister_id).and_then(|trace| {
+                    trace
+                        .iter()
+                        .skip_while(|subnet_id| **subnet_id != self.subnet_id)
+                        .nth(1)
+                        .cloned()
+                });
+                match next_host {
+                    Some(host_subnet) => StateError::CanisterMigrating {
+                        canister_id,
+                        host_subnet,
+                    },
+                    None => StateError::CanisterNotFound(canister_id),
+                }
+            }
+            err => err,
+        }
+    }
+
     fn induct_message_impl(
         &self,
         msg: RequestOrResponse,
@@ -814,6 +840,7 @@ impl StreamHandlerImpl {
 
                     // Message not inducted.
                     Err((err, msg)) => {
+                        let err = self.canister_migrated_away(err, state);
                         self.observe_inducted_message_status(msg_type, err.to_label_value());
 
                         match msg {
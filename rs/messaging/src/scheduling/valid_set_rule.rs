@@ -27,6 +27,7 @@ use ic_types::{
 };
 use prometheus::{Histogram, HistogramVec, IntCounterVec, IntGauge};
 use std::sync::Arc;
+use tracing::info_span;
 
 struct VsrMetrics {
     /// Counts of ingress message induction attempts, by status.
@@ -336,6 +337,7 @@ impl ValidSetRule for ValidSetRuleImpl {
             .unwrap_or(SMALL_APP_SUBNET_MAX_SIZE);
         for msg in msgs {
             let message_id = msg.id();
+            let _span = info_span!("ingress_induct", message_id = %message_id).entered();
             if !self.is_duplicate(state, &msg) {
                 self.induct_message(state, msg, subnet_size);
             } else {
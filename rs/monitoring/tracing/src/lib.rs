@@ -1,10 +1,11 @@
 use tracing_subscriber::{layer::Layer, reload::Handle, Registry};
 
+pub mod otlp;
 pub mod utils;
 
 // We use dynamic dispatch here to make the ReloadHandles struct work with different
 // layers.
-type BoxedRegistryLayer = Box<dyn Layer<Registry> + Send + Sync>;
+pub type BoxedRegistryLayer = Box<dyn Layer<Registry> + Send + Sync>;
 
 /// Queue of tracing reload handles.
 #[derive(Clone)]
@@ -0,0 +1,329 @@
+//! Export of tracing spans to an OpenTelemetry (OTLP) collector.
+//!
+//! Spans carrying a [`MESSAGE_ID_FIELD`] are sampled by message ID rather than
+//! by trace ID: the spans that the different components record for the same
+//! message are then either all exported or all dropped, so that the lifecycle
+//! of individual requests can be followed end to end.
+//!
+//! In addition, such spans are moved into a trace derived from the message ID,
+//! so that the spans of a message, recorded by different components and on
+//! different replicas, form a single trace. The span they would otherwise have
+//! been a child of is kept as a link.
+
+use crate::BoxedRegistryLayer;
+use opentelemetry::{
+    trace::{
+        Link, SamplingDecision, SamplingResult, SpanContext, SpanId, SpanKind, TraceContextExt,
+        TraceError, TraceFlags, TraceId, TraceState,
+    },
+    Context, KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    runtime,
+    trace::{self, Sampler, ShouldSample},
+    Resource,
+};
+use std::{collections::BTreeSet, fmt};
+use tracing::{
+    field::{Field, Visit},
+    span,
+};
+use tracing_opentelemetry::OtelData;
+use tracing_subscriber::{layer, registry::LookupSpan, Layer};
+
+/// Name of the span field holding the ID of the message that a span is about.
+pub const MESSAGE_ID_FIELD: &str = "message_id";
+
+/// Sampling controls of the OTLP exporter.
+#[derive(Clone, Debug, Default)]
+pub struct SamplingConfig {
+    /// Fraction of messages (or of traces, for spans not tied to a message)
+    /// that are exported.
+    pub ratio: f64,
+    /// Hex encoded IDs of messages that are exported irrespective of `ratio`.
+    pub message_ids: BTreeSet<String>,
+}
+
+/// Samples spans by their message ID, if any, and by their parent or trace ID
+/// otherwise.
+#[derive(Clone, Debug)]
+struct MessageIdSampler {
+    upper_bound: u64,
+    message_ids: BTreeSet<String>,
+    fallback: Sampler,
+}
+
+impl MessageIdSampler {
+    fn new(config: SamplingConfig) -> Self {
+        Self {
+            // Same computation as in `Sampler::TraceIdRatioBased`.
+            upper_bound: (config.ratio.max(0.0) * (1u64 << 63) as f64) as u64,
+            message_ids: config.message_ids,
+            fallback: Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(config.ratio))),
+        }
+    }
+
+    fn sample_message(&self, message_id: &str) -> bool {
+        if self.message_ids.contains(message_id) {
+            return true;
+        }
+        // Message IDs are hashes, hence their prefix is uniformly distributed.
+        message_id
+            .get(..16)
+            .and_then(|prefix| u64::from_str_radix(prefix, 16).ok())
+            .map_or(false, |prefix| prefix >> 1 < self.upper_bound)
+    }
+}
+
+impl ShouldSample for MessageIdSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&Context>,
+        trace_id: TraceId,
+        name: &str,
+        span_kind: &SpanKind,
+        attributes: &[KeyValue],
+        links: &[Link],
+    ) -> SamplingResult {
+        let message_id = attributes
+            .iter()
+            .find(|attribute| attribute.key.as_str() == MESSAGE_ID_FIELD);
+        match message_id {
+            Some(attribute) => SamplingResult {
+                decision: if self.sample_message(&attribute.value.as_str()) {
+                    SamplingDecision::RecordAndSample
+                } else {
+                    SamplingDecision::Drop
+                },
+                attributes: Vec::new(),
+                trace_state: parent_context
+                    .map(|cx| cx.span().span_context().trace_state().clone())
+                    .unwrap_or_default(),
+            },
+            None => self.fallback.should_sample(
+                parent_context,
+                trace_id,
+                name,
+                span_kind,
+                attributes,
+                links,
+            ),
+        }
+    }
+}
+
+/// Returns the context of the (virtual) root span of the trace of the message
+/// with the given hex encoded ID, or `None` if `message_id` is not a valid ID.
+///
+/// The trace and span IDs are taken from the message ID (its first 16 and last 8
+/// bytes, respectively), so that every
+/// component and replica derives the same trace for the same message.
+pub fn message_trace_context(message_id: &str) -> Option<SpanContext> {
+    let trace_id = u128::from_str_radix(message_id.get(..32)?, 16).ok()?;
+    let span_id = u64::from_str_radix(message_id.get(48..64)?, 16).ok()?;
+    let span_context = SpanContext::new(
+        TraceId::from_bytes(trace_id.to_be_bytes()),
+        SpanId::from_bytes(span_id.to_be_bytes()),
+        TraceFlags::SAMPLED,
+        true,
+        TraceState::default(),
+    );
+    span_context.is_valid().then_some(span_context)
+}
+
+/// Extracts the value of the [`MESSAGE_ID_FIELD`] of a span.
+#[derive(Default)]
+struct MessageIdVisitor(Option<String>);
+
+impl Visit for MessageIdVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == MESSAGE_ID_FIELD {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == MESSAGE_ID_FIELD {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// Moves spans carrying a [`MESSAGE_ID_FIELD`] into the trace of their message,
+/// see [`message_trace_context`]. Must be layered on top of the
+/// `OpenTelemetryLayer`, which creates the span data this layer rewrites.
+struct MessageTraceLayer;
+
+impl<S> Layer<S> for MessageTraceLayer
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: layer::Context<'_, S>) {
+        let mut visitor = MessageIdVisitor::default();
+        attrs.record(&mut visitor);
+        let Some(message_context) = visitor.0.as_deref().and_then(message_trace_context) else {
+            return;
+        };
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(data) = extensions.get_mut::<OtelData>() else {
+            return;
+        };
+
+        let parent = data.parent_cx.span().span_context().clone();
+        if parent.is_valid() && parent.trace_id() == message_context.trace_id() {
+            // Already part of the trace of the message, e.g. nested in another
+            // span of the same message.
+            return;
+        }
+        if parent.is_valid() {
+            data.builder
+                .links
+                .get_or_insert_with(Vec::new)
+                .push(Link::with_context(parent));
+        }
+        data.parent_cx = Context::new().with_remote_span_context(message_context);
+        // The trace ID is taken from the parent.
+        data.builder.trace_id = None;
+    }
+}
+
+/// Creates a layer that exports spans via gRPC to the OTLP collector listening
+/// on `endpoint` (e.g. `http://localhost:4317` for a local collector).
+///
+/// Spans are exported in batches by a background task, so this must be called
+/// from within a Tokio runtime.
+pub fn otlp_layer(
+    endpoint: &str,
+    service_name: &'static str,
+    sampling: SamplingConfig,
+) -> Result<BoxedRegistryLayer, TraceError> {
+    let span_exporter = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(endpoint)
+        .with_protocol(opentelemetry_otlp::Protocol::Grpc);
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_trace_config(
+            trace::config()
+                .with_sampler(MessageIdSampler::new(sampling))
+                .with_resource(Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    service_name,
+                )])),
+        )
+        .with_exporter(span_exporter)
+        .install_batch(runtime::Tokio)?;
+
+    Ok(tracing_opentelemetry::OpenTelemetryLayer::new(tracer)
+        .and_then(MessageTraceLayer)
+        .boxed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE_ID: &str = "c0ffee00000000000000000000000000000000000000000000000000000000aa";
+
+    fn sample(sampler: &MessageIdSampler, attributes: &[KeyValue]) -> SamplingDecision {
+        sampler
+            .should_sample(
+                None,
+                TraceId::from_bytes(1u128.to_be_bytes()),
+                "span",
+                &SpanKind::Internal,
+                attributes,
+                &[],
+            )
+            .decision
+    }
+
+    #[test]
+    fn message_spans_are_sampled_by_message_id() {
+        let message_id = [KeyValue::new(MESSAGE_ID_FIELD, MESSAGE_ID)];
+
+        let all = MessageIdSampler::new(SamplingConfig {
+            ratio: 1.0,
+            ..Default::default()
+        });
+        assert_eq!(sample(&all, &message_id), SamplingDecision::RecordAndSample);
+
+        let none = MessageIdSampler::new(SamplingConfig::default());
+        assert_eq!(sample(&none, &message_id), SamplingDecision::Drop);
+        assert_eq!(sample(&none, &[]), SamplingDecision::Drop);
+
+        let selected = MessageIdSampler::new(SamplingConfig {
+            ratio: 0.0,
+            message_ids: BTreeSet::from([MESSAGE_ID.to_string()]),
+        });
+        assert_eq!(
+            sample(&selected, &message_id),
+            SamplingDecision::RecordAndSample
+        );
+    }
+
+    #[test]
+    fn message_sampling_is_consistent_with_ratio() {
+        let sampler = MessageIdSampler::new(SamplingConfig {
+            ratio: 0.5,
+            ..Default::default()
+        });
+        assert!(sampler.sample_message("0fffffffffffffff"));
+        assert!(!sampler.sample_message("8000000000000000"));
+        assert!(!sampler.sample_message("not a message id"));
+    }
+
+    #[test]
+    fn message_trace_context_is_derived_from_message_id() {
+        let context = message_trace_context(MESSAGE_ID).unwrap();
+        assert_eq!(
+            context.trace_id(),
+            TraceId::from_hex("c0ffee00000000000000000000000000").unwrap()
+        );
+        assert_eq!(
+            context.span_id(),
+            SpanId::from_hex("00000000000000aa").unwrap()
+        );
+
+        assert_eq!(message_trace_context("c0ffee"), None);
+        assert_eq!(message_trace_context(&"0".repeat(64)), None);
+    }
+
+    #[test]
+    fn message_spans_are_moved_into_the_trace_of_the_message() {
+        use opentelemetry::trace::TracerProvider as _;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        // The tracer only holds a weak reference to its provider.
+        let provider = trace::TracerProvider::builder().build();
+        let tracer = provider.tracer("test");
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_opentelemetry::layer()
+                .with_tracer(tracer)
+                .and_then(MessageTraceLayer),
+        );
+        let trace_id = |span: &tracing::Span| span.context().span().span_context().trace_id();
+        let message_trace_id = message_trace_context(MESSAGE_ID).unwrap().trace_id();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let block = tracing::info_span!("block");
+            let message =
+                block.in_scope(|| tracing::info_span!("message", message_id = %MESSAGE_ID));
+            assert_ne!(trace_id(&block), message_trace_id);
+            assert_eq!(trace_id(&message), message_trace_id);
+
+            // Spans nested in a span of the message stay in its trace.
+            let nested = message.in_scope(|| tracing::info_span!("nested"));
+            assert_eq!(trace_id(&nested), message_trace_id);
+            let same_message =
+                message.in_scope(|| tracing::info_span!("same_message", message_id = %MESSAGE_ID));
+            assert_eq!(trace_id(&same_message), message_trace_id);
+        });
+    }
+}
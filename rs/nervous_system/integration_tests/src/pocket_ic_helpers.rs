@@ -7,8 +7,9 @@ use ic_nervous_system_common_test_keys::{TEST_NEURON_1_ID, TEST_NEURON_1_OWNER_P
 use ic_nervous_system_root::change_canister::ChangeCanisterRequest;
 use ic_nns_common::pb::v1::{NeuronId, ProposalId};
 use ic_nns_constants::{
-    self, ALL_NNS_CANISTER_IDS, GOVERNANCE_CANISTER_ID, LEDGER_CANISTER_ID, LIFELINE_CANISTER_ID,
-    REGISTRY_CANISTER_ID, ROOT_CANISTER_ID, SNS_WASM_CANISTER_ID,
+    self, ALL_NNS_CANISTER_IDS, CYCLES_MINTING_CANISTER_ID, GOVERNANCE_CANISTER_ID,
+    LEDGER_CANISTER_ID, LIFELINE_CANISTER_ID, REGISTRY_CANISTER_ID, ROOT_CANISTER_ID,
+    SNS_WASM_CANISTER_ID,
 };
 use ic_nns_governance_api::pb::v1::{
     manage_neuron, manage_neuron_response, proposal, CreateServiceNervousSystem,
@@ -18,7 +19,7 @@ use ic_nns_governance_api::pb::v1::{
 };
 use ic_nns_test_utils::{
     common::{
-        build_governance_wasm, build_ledger_wasm, build_lifeline_wasm,
+        build_cmc_wasm, build_governance_wasm, build_ledger_wasm, build_lifeline_wasm,
         build_mainnet_governance_wasm, build_mainnet_ledger_wasm, build_mainnet_lifeline_wasm,
         build_mainnet_registry_wasm, build_mainnet_root_wasm, build_mainnet_sns_wasms_wasm,
         build_registry_wasm, build_root_wasm, build_sns_wasms_wasm, NnsInitPayloadsBuilder,
@@ -259,116 +260,280 @@ pub fn install_nns_canisters(
     custom_initial_registry_mutations: Option<Vec<RegistryAtomicMutateRequest>>,
     neurons_fund_hotkeys: Vec<PrincipalId>,
 ) -> Vec<PrincipalId> {
-    let topology = pocket_ic.topology();
+    let mut nns_installer = NnsInstaller::default();
+    nns_installer
+        .with_initial_balances(initial_balances)
+        .with_neurons_fund_hotkeys(neurons_fund_hotkeys)
+        .without_cycles_minting_canister();
+    if with_mainnet_nns_canister_versions {
+        nns_installer.with_mainnet_nns_canister_versions();
+    }
+    if let Some(custom_initial_registry_mutations) = custom_initial_registry_mutations {
+        nns_installer.with_custom_registry_mutations(custom_initial_registry_mutations);
+    }
+    nns_installer.install(pocket_ic)
+}
 
-    let sns_subnet_id = topology.get_sns().expect("No SNS subnet found");
-    let sns_subnet_id = PrincipalId::from(sns_subnet_id);
-    let sns_subnet_id = SubnetId::from(sns_subnet_id);
-    println!("sns_subnet_id = {:?}", sns_subnet_id);
+/// The ICP/XDR conversion rate set in the CMC by default: 1 ICP = 10 XDR.
+pub const DEFAULT_XDR_PERMYRIAD_PER_ICP: u64 = 100_000;
 
-    let mut nns_init_payload_builder = NnsInitPayloadsBuilder::new();
+/// Builder for a consistent set of NNS canisters (registry, root, governance, lifeline,
+/// ICP ledger, cycles minting canister, and SNS-W) pre-provisioned into a PocketIC instance,
+/// so that canisters can be created via the CMC and SNSs can be launched out of the box.
+///
+/// Example:
+/// ```ignore
+/// let pocket_ic = NnsInstaller::default()
+///     .with_initial_balances(vec![(account, Tokens::from_tokens(100).unwrap())])
+///     .with_test_neurons()
+///     .build_pocket_ic(PocketIcBuilder::new().with_application_subnet());
+/// ```
+#[derive(Clone, Debug)]
+pub struct NnsInstaller {
+    initial_balances: Vec<(AccountIdentifier, Tokens)>,
+    with_test_neurons: bool,
+    neurons_fund_hotkeys: Vec<PrincipalId>,
+    custom_registry_mutations: Option<Vec<RegistryAtomicMutateRequest>>,
+    with_mainnet_nns_canister_versions: bool,
+    with_cycles_minting_canister: bool,
+    xdr_permyriad_per_icp: u64,
+    authorized_subnets: Option<Vec<SubnetId>>,
+}
 
-    if let Some(custom_initial_registry_mutations) = custom_initial_registry_mutations {
-        nns_init_payload_builder.with_initial_mutations(custom_initial_registry_mutations);
-    } else {
-        nns_init_payload_builder.with_initial_invariant_compliant_mutations();
+impl Default for NnsInstaller {
+    fn default() -> Self {
+        Self {
+            initial_balances: vec![],
+            with_test_neurons: true,
+            neurons_fund_hotkeys: vec![],
+            custom_registry_mutations: None,
+            with_mainnet_nns_canister_versions: false,
+            with_cycles_minting_canister: true,
+            xdr_permyriad_per_icp: DEFAULT_XDR_PERMYRIAD_PER_ICP,
+            authorized_subnets: None,
+        }
     }
-    let maturity_equivalent_icp_e8s = 1_500_000 * E8;
-    nns_init_payload_builder
-        .with_test_neurons_fund_neurons_with_hotkeys(
-            neurons_fund_hotkeys,
-            maturity_equivalent_icp_e8s,
-        )
-        .with_sns_dedicated_subnets(vec![sns_subnet_id])
-        .with_sns_wasm_access_controls(true);
+}
 
-    for (test_user_icp_ledger_account, test_user_icp_ledger_initial_balance) in initial_balances {
-        nns_init_payload_builder.with_ledger_account(
-            test_user_icp_ledger_account,
-            test_user_icp_ledger_initial_balance,
-        );
+impl NnsInstaller {
+    /// Adds `(test_user_icp_ledger_account, test_user_icp_ledger_initial_balance)` pairs
+    /// to the initial ICP distribution.
+    pub fn with_initial_balances(
+        &mut self,
+        initial_balances: Vec<(AccountIdentifier, Tokens)>,
+    ) -> &mut Self {
+        self.initial_balances.extend(initial_balances);
+        self
     }
 
-    let nns_init_payload = nns_init_payload_builder.build();
+    /// Installs NNS Governance with the test neurons (including the whale neuron with
+    /// `TEST_NEURON_1_ID` and a Neurons' Fund-participating neuron). This is the default.
+    pub fn with_test_neurons(&mut self) -> &mut Self {
+        self.with_test_neurons = true;
+        self
+    }
 
-    let (governance_wasm, ledger_wasm, root_wasm, lifeline_wasm, sns_wasm_wasm, registry_wasm) =
-        if with_mainnet_nns_canister_versions {
-            (
-                build_mainnet_governance_wasm(),
-                build_mainnet_ledger_wasm(),
-                build_mainnet_root_wasm(),
-                build_mainnet_lifeline_wasm(),
-                build_mainnet_sns_wasms_wasm(),
-                build_mainnet_registry_wasm(),
-            )
+    /// Installs NNS Governance without any neurons.
+    pub fn without_test_neurons(&mut self) -> &mut Self {
+        self.with_test_neurons = false;
+        self
+    }
+
+    /// Hotkeys of the 1st NNS (Neurons' Fund-participating) test neuron.
+    pub fn with_neurons_fund_hotkeys(
+        &mut self,
+        neurons_fund_hotkeys: Vec<PrincipalId>,
+    ) -> &mut Self {
+        self.neurons_fund_hotkeys = neurons_fund_hotkeys;
+        self
+    }
+
+    /// Custom mutations for the initial Registry. These mutations should comply with
+    /// Registry invariants, otherwise the installation fails.
+    pub fn with_custom_registry_mutations(
+        &mut self,
+        custom_registry_mutations: Vec<RegistryAtomicMutateRequest>,
+    ) -> &mut Self {
+        self.custom_registry_mutations = Some(custom_registry_mutations);
+        self
+    }
+
+    /// Installs the mainnet (rather than tip-of-this-branch) WASM versions. There is no
+    /// mainnet WASM of the CMC available to tests, so the CMC is always built from this branch.
+    pub fn with_mainnet_nns_canister_versions(&mut self) -> &mut Self {
+        self.with_mainnet_nns_canister_versions = true;
+        self
+    }
+
+    /// Skips the installation of the cycles minting canister.
+    pub fn without_cycles_minting_canister(&mut self) -> &mut Self {
+        self.with_cycles_minting_canister = false;
+        self
+    }
+
+    /// The ICP/XDR conversion rate set in the CMC
+    /// (defaults to `DEFAULT_XDR_PERMYRIAD_PER_ICP`).
+    pub fn with_xdr_permyriad_per_icp(&mut self, xdr_permyriad_per_icp: u64) -> &mut Self {
+        self.xdr_permyriad_per_icp = xdr_permyriad_per_icp;
+        self
+    }
+
+    /// The default list of subnets on which the CMC creates canisters
+    /// (defaults to all application subnets of the PocketIC instance).
+    pub fn with_authorized_subnets(&mut self, authorized_subnets: Vec<SubnetId>) -> &mut Self {
+        self.authorized_subnets = Some(authorized_subnets);
+        self
+    }
+
+    /// Creates a new PocketIC instance from the given builder with (at least) an NNS and
+    /// an SNS subnet, and installs the NNS canisters onto it.
+    pub fn build_pocket_ic(&self, pocket_ic_builder: PocketIcBuilder) -> PocketIc {
+        let pocket_ic = pocket_ic_builder
+            .with_nns_subnet()
+            .with_sns_subnet()
+            .build();
+        self.install(&pocket_ic);
+        pocket_ic
+    }
+
+    /// Installs the NNS canisters onto the NNS subnet of the given PocketIC instance.
+    /// Requires PocketIC to have at least an NNS and an SNS subnet.
+    ///
+    /// Returns
+    /// 1. A list of `controller_principal_id`s of pre-configured NNS neurons.
+    pub fn install(&self, pocket_ic: &PocketIc) -> Vec<PrincipalId> {
+        let topology = pocket_ic.topology();
+
+        let sns_subnet_id = topology.get_sns().expect("No SNS subnet found");
+        let sns_subnet_id = PrincipalId::from(sns_subnet_id);
+        let sns_subnet_id = SubnetId::from(sns_subnet_id);
+
+        let mut nns_init_payload_builder = NnsInitPayloadsBuilder::new();
+
+        if let Some(custom_registry_mutations) = self.custom_registry_mutations.clone() {
+            nns_init_payload_builder.with_initial_mutations(custom_registry_mutations);
         } else {
-            (
-                build_governance_wasm(),
-                build_ledger_wasm(),
-                build_root_wasm(),
-                build_lifeline_wasm(),
-                build_sns_wasms_wasm(),
-                build_registry_wasm(),
-            )
-        };
+            nns_init_payload_builder.with_initial_invariant_compliant_mutations();
+        }
+        if self.with_test_neurons {
+            let maturity_equivalent_icp_e8s = 1_500_000 * E8;
+            nns_init_payload_builder.with_test_neurons_fund_neurons_with_hotkeys(
+                self.neurons_fund_hotkeys.clone(),
+                maturity_equivalent_icp_e8s,
+            );
+        }
+        nns_init_payload_builder
+            .with_sns_dedicated_subnets(vec![sns_subnet_id])
+            .with_sns_wasm_access_controls(true);
 
-    install_canister(
-        pocket_ic,
-        "ICP Ledger",
-        LEDGER_CANISTER_ID,
-        Encode!(&nns_init_payload.ledger).unwrap(),
-        ledger_wasm,
-        Some(ROOT_CANISTER_ID.get()),
-    );
-    install_canister(
-        pocket_ic,
-        "NNS Root",
-        ROOT_CANISTER_ID,
-        Encode!(&nns_init_payload.root).unwrap(),
-        root_wasm,
-        Some(LIFELINE_CANISTER_ID.get()),
-    );
-    install_canister(
-        pocket_ic,
-        "NNS Governance",
-        GOVERNANCE_CANISTER_ID,
-        nns_init_payload.governance.encode_to_vec(),
-        governance_wasm,
-        Some(ROOT_CANISTER_ID.get()),
-    );
-    install_canister(
-        pocket_ic,
-        "Lifeline",
-        LIFELINE_CANISTER_ID,
-        Encode!(&nns_init_payload.lifeline).unwrap(),
-        lifeline_wasm,
-        Some(ROOT_CANISTER_ID.get()),
-    );
-    install_canister(
-        pocket_ic,
-        "NNS SNS-W",
-        SNS_WASM_CANISTER_ID,
-        Encode!(&nns_init_payload.sns_wasms).unwrap(),
-        sns_wasm_wasm,
-        Some(ROOT_CANISTER_ID.get()),
-    );
-    install_canister(
-        pocket_ic,
-        "Registry",
-        REGISTRY_CANISTER_ID,
-        Encode!(&nns_init_payload.registry).unwrap(),
-        registry_wasm,
-        Some(ROOT_CANISTER_ID.get()),
-    );
+        for (test_user_icp_ledger_account, test_user_icp_ledger_initial_balance) in
+            &self.initial_balances
+        {
+            nns_init_payload_builder.with_ledger_account(
+                *test_user_icp_ledger_account,
+                *test_user_icp_ledger_initial_balance,
+            );
+        }
+
+        let nns_init_payload = nns_init_payload_builder.build();
+
+        let (governance_wasm, ledger_wasm, root_wasm, lifeline_wasm, sns_wasm_wasm, registry_wasm) =
+            if self.with_mainnet_nns_canister_versions {
+                (
+                    build_mainnet_governance_wasm(),
+                    build_mainnet_ledger_wasm(),
+                    build_mainnet_root_wasm(),
+                    build_mainnet_lifeline_wasm(),
+                    build_mainnet_sns_wasms_wasm(),
+                    build_mainnet_registry_wasm(),
+                )
+            } else {
+                (
+                    build_governance_wasm(),
+                    build_ledger_wasm(),
+                    build_root_wasm(),
+                    build_lifeline_wasm(),
+                    build_sns_wasms_wasm(),
+                    build_registry_wasm(),
+                )
+            };
 
-    let nns_neurons = nns_init_payload
-        .governance
-        .neurons
-        .values()
-        .map(|neuron| neuron.controller.unwrap())
-        .collect();
+        install_canister(
+            pocket_ic,
+            "ICP Ledger",
+            LEDGER_CANISTER_ID,
+            Encode!(&nns_init_payload.ledger).unwrap(),
+            ledger_wasm,
+            Some(ROOT_CANISTER_ID.get()),
+        );
+        install_canister(
+            pocket_ic,
+            "NNS Root",
+            ROOT_CANISTER_ID,
+            Encode!(&nns_init_payload.root).unwrap(),
+            root_wasm,
+            Some(LIFELINE_CANISTER_ID.get()),
+        );
+        install_canister(
+            pocket_ic,
+            "NNS Governance",
+            GOVERNANCE_CANISTER_ID,
+            nns_init_payload.governance.encode_to_vec(),
+            governance_wasm,
+            Some(ROOT_CANISTER_ID.get()),
+        );
+        install_canister(
+            pocket_ic,
+            "Lifeline",
+            LIFELINE_CANISTER_ID,
+            Encode!(&nns_init_payload.lifeline).unwrap(),
+            lifeline_wasm,
+            Some(ROOT_CANISTER_ID.get()),
+        );
+        install_canister(
+            pocket_ic,
+            "NNS SNS-W",
+            SNS_WASM_CANISTER_ID,
+            Encode!(&nns_init_payload.sns_wasms).unwrap(),
+            sns_wasm_wasm,
+            Some(ROOT_CANISTER_ID.get()),
+        );
+        install_canister(
+            pocket_ic,
+            "Registry",
+            REGISTRY_CANISTER_ID,
+            Encode!(&nns_init_payload.registry).unwrap(),
+            registry_wasm,
+            Some(ROOT_CANISTER_ID.get()),
+        );
 
-    nns_neurons
+        if self.with_cycles_minting_canister {
+            install_canister(
+                pocket_ic,
+                "CMC",
+                CYCLES_MINTING_CANISTER_ID,
+                Encode!(&nns_init_payload.cycles_minting).unwrap(),
+                build_cmc_wasm(),
+                Some(ROOT_CANISTER_ID.get()),
+            );
+            let authorized_subnets = self.authorized_subnets.clone().unwrap_or_else(|| {
+                topology
+                    .get_app_subnets()
+                    .into_iter()
+                    .map(|subnet_id| SubnetId::from(PrincipalId::from(subnet_id)))
+                    .collect()
+            });
+            nns::cmc::set_authorized_subnetwork_list(pocket_ic, None, authorized_subnets);
+            nns::cmc::set_icp_xdr_conversion_rate(pocket_ic, self.xdr_permyriad_per_icp);
+        }
+
+        nns_init_payload
+            .governance
+            .neurons
+            .values()
+            .map(|neuron| neuron.controller.unwrap())
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -909,6 +1074,88 @@ pub mod nns {
         }
     }
 
+    pub mod cmc {
+        use super::*;
+        use cycles_minting_canister::{
+            IcpXdrConversionRate, IcpXdrConversionRateCertifiedResponse,
+            SetAuthorizedSubnetworkListArgs,
+        };
+        use ic_nns_common::types::UpdateIcpXdrConversionRatePayload;
+
+        /// Sets the list of subnets on which `who` (or anyone, if `who` is `None`)
+        /// can create canisters via the CMC.
+        pub fn set_authorized_subnetwork_list(
+            pocket_ic: &PocketIc,
+            who: Option<PrincipalId>,
+            subnets: Vec<SubnetId>,
+        ) {
+            let result = pocket_ic
+                .update_call(
+                    CYCLES_MINTING_CANISTER_ID.into(),
+                    GOVERNANCE_CANISTER_ID.get().0,
+                    "set_authorized_subnetwork_list",
+                    Encode!(&SetAuthorizedSubnetworkListArgs { who, subnets }).unwrap(),
+                )
+                .unwrap();
+            match result {
+                WasmResult::Reply(_) => (),
+                WasmResult::Reject(s) => {
+                    panic!("Call to set_authorized_subnetwork_list failed: {:#?}", s)
+                }
+            }
+        }
+
+        /// Sets the ICP/XDR conversion rate of the CMC as of the current PocketIC time.
+        pub fn set_icp_xdr_conversion_rate(pocket_ic: &PocketIc, xdr_permyriad_per_icp: u64) {
+            let timestamp_seconds = pocket_ic
+                .get_time()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let payload = UpdateIcpXdrConversionRatePayload {
+                data_source: "PocketIC".to_string(),
+                timestamp_seconds,
+                xdr_permyriad_per_icp,
+                reason: None,
+            };
+            let result = pocket_ic
+                .update_call(
+                    CYCLES_MINTING_CANISTER_ID.into(),
+                    GOVERNANCE_CANISTER_ID.get().0,
+                    "set_icp_xdr_conversion_rate",
+                    Encode!(&payload).unwrap(),
+                )
+                .unwrap();
+            let result = match result {
+                WasmResult::Reply(result) => result,
+                WasmResult::Reject(s) => {
+                    panic!("Call to set_icp_xdr_conversion_rate failed: {:#?}", s)
+                }
+            };
+            Decode!(&result, Result<(), String>).unwrap().unwrap();
+        }
+
+        pub fn get_icp_xdr_conversion_rate(pocket_ic: &PocketIc) -> IcpXdrConversionRate {
+            let result = pocket_ic
+                .query_call(
+                    CYCLES_MINTING_CANISTER_ID.into(),
+                    Principal::anonymous(),
+                    "get_icp_xdr_conversion_rate",
+                    Encode!().unwrap(),
+                )
+                .unwrap();
+            let result = match result {
+                WasmResult::Reply(result) => result,
+                WasmResult::Reject(s) => {
+                    panic!("Call to get_icp_xdr_conversion_rate failed: {:#?}", s)
+                }
+            };
+            Decode!(&result, IcpXdrConversionRateCertifiedResponse)
+                .unwrap()
+                .data
+        }
+    }
+
     pub mod ledger {
         use super::*;
         use icp_ledger::{Memo, TransferArgs};
@@ -0,0 +1,87 @@
+use candid::{Decode, Encode, Principal};
+use cycles_minting_canister::{NotifyCreateCanister, NotifyError, MEMO_CREATE_CANISTER};
+use ic_base_types::{CanisterId, PrincipalId};
+use ic_ledger_core::Tokens;
+use ic_nervous_system_integration_tests::pocket_ic_helpers::{nns, NnsInstaller};
+use ic_nns_constants::{CYCLES_MINTING_CANISTER_ID, LEDGER_CANISTER_ID};
+use icp_ledger::{AccountIdentifier, BlockIndex, Subaccount, TransferArgs, TransferError};
+use pocket_ic::{PocketIcBuilder, WasmResult};
+
+#[test]
+fn test_canister_creation_via_cmc_with_pre_provisioned_nns() {
+    let user = PrincipalId::new_user_test_id(42);
+    let user_account = AccountIdentifier::new(user, None);
+    let xdr_permyriad_per_icp = 50_000;
+
+    let pocket_ic = NnsInstaller::default()
+        .with_initial_balances(vec![(user_account, Tokens::from_tokens(100).unwrap())])
+        .with_xdr_permyriad_per_icp(xdr_permyriad_per_icp)
+        .build_pocket_ic(PocketIcBuilder::new().with_application_subnet());
+
+    assert_eq!(
+        nns::ledger::account_balance(&pocket_ic, &user_account),
+        Tokens::from_tokens(100).unwrap()
+    );
+    assert_eq!(
+        nns::cmc::get_icp_xdr_conversion_rate(&pocket_ic).xdr_permyriad_per_icp,
+        xdr_permyriad_per_icp
+    );
+
+    // Create a canister by sending ICP to the CMC and notifying the CMC.
+    let transfer_args = TransferArgs {
+        memo: MEMO_CREATE_CANISTER,
+        amount: Tokens::from_tokens(10).unwrap(),
+        fee: Tokens::from_e8s(10_000),
+        from_subaccount: None,
+        to: AccountIdentifier::new(
+            CYCLES_MINTING_CANISTER_ID.get(),
+            Some(Subaccount::from(&user)),
+        )
+        .to_address(),
+        created_at_time: None,
+    };
+    let result = pocket_ic
+        .update_call(
+            LEDGER_CANISTER_ID.into(),
+            user.0,
+            "transfer",
+            Encode!(&transfer_args).unwrap(),
+        )
+        .unwrap();
+    let WasmResult::Reply(reply) = result else {
+        panic!("Call to transfer failed: {:#?}", result);
+    };
+    let block_index = Decode!(&reply, Result<BlockIndex, TransferError>)
+        .unwrap()
+        .unwrap();
+
+    #[allow(deprecated)]
+    let notify_args = NotifyCreateCanister {
+        block_index,
+        controller: user,
+        subnet_type: None,
+        subnet_selection: None,
+        settings: None,
+    };
+    let result = pocket_ic
+        .update_call(
+            CYCLES_MINTING_CANISTER_ID.into(),
+            user.0,
+            "notify_create_canister",
+            Encode!(&notify_args).unwrap(),
+        )
+        .unwrap();
+    let WasmResult::Reply(reply) = result else {
+        panic!("Call to notify_create_canister failed: {:#?}", result);
+    };
+    let canister_id = Decode!(&reply, Result<CanisterId, NotifyError>)
+        .unwrap()
+        .unwrap();
+
+    // The canister is created on the (only) authorized application subnet.
+    let app_subnet = pocket_ic.topology().get_app_subnets()[0];
+    assert_eq!(
+        pocket_ic.get_subnet(Principal::from(canister_id)),
+        Some(app_subnet)
+    );
+}
@@ -51,6 +51,7 @@ fn test_submit_and_accept_update_subnet_proposal() {
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
                 chain_key_config: None,
+                idle_block_config: None,
             };
 
             let key = make_subnet_record_key(subnet_id);
@@ -92,6 +93,7 @@ fn test_submit_and_accept_update_subnet_proposal() {
                 max_number_of_canisters: Some(200),
                 ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
                 ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+                idle_block_config: None,
                 chain_key_config: None,
                 chain_key_signing_enable: None,
                 chain_key_signing_disable: None,
@@ -172,6 +174,7 @@ fn test_submit_and_accept_update_subnet_proposal() {
                     ssh_backup_access: vec!["pub_key_1".to_string()],
                     ecdsa_config: None,
                     chain_key_config: None,
+                    idle_block_config: None,
                 }
             );
             Ok(())
@@ -22,6 +22,7 @@ fn main() {
 struct Dashboard<'a> {{
     height: Height,
     canisters: &'a Vec<(&'a ic_replicated_state::CanisterState, SubnetId)>,
+    call_graphs: &'a Vec<crate::call_graph::CallGraphRow>,
 }}
     "#,
             std::fs::read_to_string("templates/dashboard.html").unwrap()
@@ -0,0 +1,176 @@
+//! Reconstruction of inter-canister call graphs from the message executions
+//! recorded by the message execution tracer of the subnets' `StateMachine`s.
+//!
+//! Every message execution belongs to a call (context) identified by the
+//! executing canister and the origin of the call (an ingress message or a
+//! calling canister together with the ID of its callback). The executions
+//! of a call comprise the execution of the call itself and the executions
+//! of the responses to the downstream calls made while handling the call.
+
+use ic_state_machine_tests::{
+    MessageExecutionRecord, TracedCallOrigin, TracedInput, TracedResponse,
+};
+use ic_types::{messages::MessageId, CanisterId, PrincipalId};
+use pocket_ic::common::rest::{
+    CallOutcome, RawCallGraphExecution, RawCallGraphNode, RawCanisterId,
+};
+
+fn call_outcome(response: &TracedResponse) -> CallOutcome {
+    match response {
+        TracedResponse::Reply => CallOutcome::Reply,
+        TracedResponse::Reject(msg) => CallOutcome::Reject(msg.clone()),
+    }
+}
+
+/// Returns the call graph of the ingress message with the given ID
+/// or `None` if the execution of the ingress message has not been traced.
+pub(crate) fn call_graph(
+    records: &[MessageExecutionRecord],
+    message_id: &MessageId,
+) -> Option<RawCallGraphNode> {
+    let origin = TracedCallOrigin::Ingress(message_id.clone());
+    records.iter().find_map(|record| match &record.input {
+        TracedInput::Ingress { source, .. } if record.origin == origin => Some(call_graph_node(
+            records,
+            *source,
+            record.canister_id,
+            &origin,
+            0,
+            None,
+        )),
+        _ => None,
+    })
+}
+
+/// Returns the call graphs of all traced ingress messages
+/// in the order in which the ingress messages were executed.
+pub(crate) fn ingress_call_graphs(records: &[MessageExecutionRecord]) -> Vec<RawCallGraphNode> {
+    records
+        .iter()
+        .filter_map(|record| match (&record.input, &record.origin) {
+            (TracedInput::Ingress { source, .. }, origin @ TracedCallOrigin::Ingress(_)) => Some(
+                call_graph_node(records, *source, record.canister_id, origin, 0, None),
+            ),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Builds the call graph node of the call with the given `origin` on the given `callee`.
+/// The `outcome` is the response observed by the caller (if any) which takes precedence
+/// over the response produced by the callee (e.g., if the callee trapped).
+fn call_graph_node(
+    records: &[MessageExecutionRecord],
+    caller: PrincipalId,
+    callee: CanisterId,
+    origin: &TracedCallOrigin,
+    cycles: u128,
+    outcome: Option<CallOutcome>,
+) -> RawCallGraphNode {
+    let mut executions: Vec<_> = records
+        .iter()
+        .filter(|record| record.canister_id == callee && &record.origin == origin)
+        .collect();
+    executions.sort_by_key(|record| record.round);
+
+    let method_name = executions.iter().find_map(|record| match &record.input {
+        TracedInput::Ingress { method_name, .. } | TracedInput::Request { method_name, .. } => {
+            Some(method_name.clone())
+        }
+        TracedInput::Response { .. } | TracedInput::Task(_) => None,
+    });
+    let outcome = outcome.or_else(|| {
+        executions
+            .iter()
+            .find_map(|record| record.response.as_ref().map(call_outcome))
+    });
+    let calls = executions
+        .iter()
+        .flat_map(|record| record.calls.iter())
+        .map(|call| {
+            // The response to a downstream call is executed by the callee of this call.
+            let response_outcome = executions.iter().find_map(|record| match &record.input {
+                TracedInput::Response {
+                    callback_id,
+                    response,
+                    ..
+                } if *callback_id == call.callback_id => Some(call_outcome(response)),
+                _ => None,
+            });
+            call_graph_node(
+                records,
+                callee.get(),
+                call.callee,
+                &TracedCallOrigin::Canister {
+                    caller: callee,
+                    callback_id: call.callback_id,
+                },
+                call.cycles.get(),
+                response_outcome,
+            )
+        })
+        .collect();
+
+    RawCallGraphNode {
+        caller: caller.to_vec(),
+        callee: RawCanisterId {
+            canister_id: callee.get().to_vec(),
+        },
+        method_name,
+        cycles,
+        outcome,
+        executions: executions
+            .iter()
+            .map(|record| RawCallGraphExecution {
+                round: record.round.get(),
+                respondent: match &record.input {
+                    TracedInput::Response { respondent, .. } => Some(RawCanisterId {
+                        canister_id: respondent.get().to_vec(),
+                    }),
+                    _ => None,
+                },
+                instructions_used: record.instructions_used.get(),
+            })
+            .collect(),
+        calls,
+    }
+}
+
+/// A call graph node as a row of a table on the PocketIC dashboard.
+pub(crate) struct CallGraphRow {
+    pub depth: usize,
+    pub caller: String,
+    pub callee: String,
+    pub method_name: String,
+    pub cycles: u128,
+    pub instructions_used: u64,
+    pub outcome: String,
+}
+
+/// Flattens the given call graph into rows (depth-first, pre-order).
+pub(crate) fn call_graph_rows(node: &RawCallGraphNode, depth: usize, rows: &mut Vec<CallGraphRow>) {
+    rows.push(CallGraphRow {
+        depth,
+        caller: PrincipalId::try_from(node.caller.as_slice())
+            .map(|p| p.to_string())
+            .unwrap_or_default(),
+        callee: PrincipalId::try_from(node.callee.canister_id.as_slice())
+            .map(|p| p.to_string())
+            .unwrap_or_default(),
+        method_name: node.method_name.clone().unwrap_or_default(),
+        cycles: node.cycles,
+        instructions_used: node
+            .executions
+            .iter()
+            .map(|execution| execution.instructions_used)
+            .sum(),
+        outcome: match &node.outcome {
+            Some(CallOutcome::Reply) => "reply".to_string(),
+            Some(CallOutcome::Reject(msg)) => format!("reject: {}", msg),
+            None => "pending".to_string(),
+        },
+    });
+    for call in &node.calls {
+        call_graph_rows(call, depth + 1, rows);
+    }
+}
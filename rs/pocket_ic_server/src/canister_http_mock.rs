@@ -0,0 +1,114 @@
+//! Rule-based mocking and forwarding of canister HTTP outcalls.
+
+use ic_state_machine_tests::RejectCode;
+use pocket_ic::common::rest::{CanisterHttpMockRule, CanisterHttpRequest, CanisterHttpResponse};
+use regex::Regex;
+use std::time::Duration;
+
+/// A `CanisterHttpMockRule` with compiled regular expressions.
+pub(crate) struct CanisterHttpMockRuleMatcher {
+    rule: CanisterHttpMockRule,
+    url_regex: Regex,
+    body_regex: Option<Regex>,
+}
+
+/// Compiles a regular expression that must match the entire input.
+fn anchored_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(&format!("^(?:{})$", pattern))
+        .map_err(|e| format!("Invalid regular expression {}: {}", pattern, e))
+}
+
+fn validate_response(response: &CanisterHttpResponse) -> Result<(), String> {
+    match response {
+        CanisterHttpResponse::CanisterHttpReply(_) => Ok(()),
+        CanisterHttpResponse::CanisterHttpReject(reject) => {
+            RejectCode::try_from(reject.reject_code)
+                .map(|_| ())
+                .map_err(|_| format!("Invalid reject code {}", reject.reject_code))
+        }
+    }
+}
+
+impl TryFrom<CanisterHttpMockRule> for CanisterHttpMockRuleMatcher {
+    type Error = String;
+
+    fn try_from(rule: CanisterHttpMockRule) -> Result<Self, Self::Error> {
+        let url_regex = anchored_regex(&rule.url_pattern)?;
+        let body_regex = rule
+            .body_pattern
+            .as_deref()
+            .map(anchored_regex)
+            .transpose()?;
+        validate_response(&rule.response)?;
+        for response in &rule.divergent_responses {
+            validate_response(response)?;
+        }
+        Ok(Self {
+            rule,
+            url_regex,
+            body_regex,
+        })
+    }
+}
+
+impl CanisterHttpMockRuleMatcher {
+    pub(crate) fn matches(&self, request: &CanisterHttpRequest) -> bool {
+        self.rule
+            .http_method
+            .as_ref()
+            .map_or(true, |http_method| *http_method == request.http_method)
+            && self.url_regex.is_match(&request.url)
+            && self.rule.headers.iter().all(|expected| {
+                request.headers.iter().any(|header| {
+                    header.name.eq_ignore_ascii_case(&expected.name)
+                        && header.value == expected.value
+                })
+            })
+            && self.body_regex.as_ref().map_or(true, |body_regex| {
+                body_regex.is_match(&String::from_utf8_lossy(&request.body))
+            })
+    }
+
+    pub(crate) fn delay(&self) -> Duration {
+        Duration::from_millis(self.rule.delay_ms.unwrap_or_default())
+    }
+
+    /// The responses of the individual replicas:
+    /// the last response is returned by all remaining replicas.
+    pub(crate) fn responses(&self) -> Vec<CanisterHttpResponse> {
+        self.rule
+            .divergent_responses
+            .iter()
+            .chain(std::iter::once(&self.rule.response))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Checks that the given forwarding target is the base URL of an HTTP(S) server.
+pub(crate) fn validate_forward_to(forward_to: &str) -> Result<(), String> {
+    match forward_to
+        .strip_prefix("http://")
+        .or_else(|| forward_to.strip_prefix("https://"))
+    {
+        Some(authority)
+            if !authority.is_empty() && !authority.trim_end_matches('/').contains('/') =>
+        {
+            Ok(())
+        }
+        _ => Err(format!(
+            "Invalid forwarding target {}: expected a base URL such as http://localhost:8080",
+            forward_to
+        )),
+    }
+}
+
+/// Replaces the scheme and authority of the given URL by the forwarding target
+/// (keeping the path and query of the URL).
+pub(crate) fn forwarded_url(url: &str, forward_to: &str) -> String {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path_and_query = without_scheme
+        .find(['/', '?'])
+        .map_or("", |i| &without_scheme[i..]);
+    format!("{}{}", forward_to.trim_end_matches('/'), path_and_query)
+}
@@ -34,6 +34,9 @@
 //! The start state is a dedicated state that always exists independent of which computations have
 //! been carried out. A state which has no outcoming computations is called a leaf.
 
+mod call_graph;
+mod canister_http_mock;
+mod nns_canisters;
 pub mod pocket_ic;
 pub mod state_api;
 
@@ -92,6 +95,32 @@ pub fn copy_dir(
     Ok(())
 }
 
+/// Copies a directory like `copy_dir`, but hard-links read-only files
+/// (e.g., the files of a checkpoint) instead of copying them.
+/// Read-only files are never modified in place and thus
+/// the hard links behave like copy-on-write copies.
+/// Falls back to copying if a hard link cannot be created
+/// (e.g., if `src` and `dst` are on different file systems).
+pub fn link_or_copy_dir(
+    src: impl AsRef<std::path::Path>,
+    dst: impl AsRef<std::path::Path>,
+) -> std::io::Result<()> {
+    std::fs::create_dir_all(&dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let ty = entry.file_type()?;
+        let dst_path = dst.as_ref().join(entry.file_name());
+        if ty.is_dir() {
+            link_or_copy_dir(entry.path(), dst_path)?;
+        } else if !entry.metadata()?.permissions().readonly()
+            || std::fs::hard_link(entry.path(), &dst_path).is_err()
+        {
+            std::fs::copy(entry.path(), dst_path)?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
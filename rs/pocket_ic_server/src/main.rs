@@ -27,7 +27,9 @@ use ic_crypto_utils_threshold_sig_der::parse_threshold_sig_key_from_der;
 use pocket_ic::common::rest::{BinaryBlob, BlobCompression, BlobId, RawVerifyCanisterSigArg};
 use pocket_ic_server::state_api::routes::{handler_read_graph, timeout_or_default};
 use pocket_ic_server::state_api::{
-    routes::{http_gateway_routes, instances_routes, status, AppState, RouterExt},
+    routes::{
+        http_gateway_routes, instances_routes, snapshots_routes, status, AppState, RouterExt,
+    },
     state::PocketIcApiStateBuilder,
 };
 use pocket_ic_server::BlobStore;
@@ -197,6 +199,8 @@ async fn start(runtime: Arc<Runtime>) {
         .nest("/instances", instances_routes::<AppState>())
         // All HTTP gateway routes.
         .nest("/http_gateway", http_gateway_routes::<AppState>())
+        // All snapshot routes.
+        .nest("/snapshots", snapshots_routes::<AppState>())
         .layer(DefaultBodyLimit::disable())
         .route_layer(middleware::from_fn_with_state(
             app_state.clone(),
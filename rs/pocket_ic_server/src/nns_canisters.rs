@@ -0,0 +1,115 @@
+//! Installation of the NNS canisters on the NNS subnet of a new PocketIC instance.
+
+use candid::{Decode, Encode};
+use cycles_minting_canister::SetAuthorizedSubnetworkListArgs;
+use ic_nns_common::types::UpdateIcpXdrConversionRatePayload;
+use ic_nns_constants::{CYCLES_MINTING_CANISTER_ID, GOVERNANCE_CANISTER_ID};
+use ic_nns_test_utils::{
+    common::NnsInitPayloadsBuilder, state_test_helpers::setup_nns_canisters_with_features,
+};
+use ic_state_machine_tests::{StateMachine, WasmResult};
+use ic_types::{PrincipalId, SubnetId};
+use icp_ledger::{AccountIdentifier, Tokens};
+use pocket_ic::common::rest::NnsCanistersConfig;
+use std::time::UNIX_EPOCH;
+
+/// The maturity of the Neurons' Fund test neuron in ICP e8s.
+const NEURONS_FUND_MATURITY_EQUIVALENT_ICP_E8S: u64 = 1_500_000 * 100_000_000;
+
+fn initial_balances(
+    nns_canisters: &NnsCanistersConfig,
+) -> Result<Vec<(AccountIdentifier, Tokens)>, String> {
+    nns_canisters
+        .initial_balances
+        .iter()
+        .map(|(account, e8s)| {
+            let account = AccountIdentifier::from_hex(account)
+                .map_err(|e| format!("Invalid ICP ledger account {}: {}", account, e))?;
+            Ok((account, Tokens::from_e8s(*e8s)))
+        })
+        .collect()
+}
+
+/// Checks that the NNS canisters can be installed with the given configuration.
+pub(crate) fn validate(nns_canisters: &NnsCanistersConfig) -> Result<(), String> {
+    initial_balances(nns_canisters).map(|_| ())
+}
+
+fn call_as_governance(nns_subnet: &StateMachine, method: &str, payload: Vec<u8>) -> Vec<u8> {
+    match nns_subnet
+        .execute_ingress_as(
+            GOVERNANCE_CANISTER_ID.get(),
+            CYCLES_MINTING_CANISTER_ID,
+            method,
+            payload,
+        )
+        .unwrap_or_else(|e| panic!("Call to {} failed: {}", method, e))
+    {
+        WasmResult::Reply(reply) => reply,
+        WasmResult::Reject(reject) => panic!("Call to {} was rejected: {}", method, reject),
+    }
+}
+
+/// Installs the NNS canisters on the given NNS subnet. SNS-W deploys SNSs to the
+/// SNS subnet, if there is one, and the cycles minting canister creates canisters
+/// on the authorized subnets, which default to the given application subnets.
+///
+/// The NNS subnet must not contain any canisters yet, because the NNS canisters
+/// are installed at their mainnet canister IDs.
+pub(crate) fn install(
+    nns_subnet: &StateMachine,
+    sns_subnet_id: Option<SubnetId>,
+    app_subnet_ids: Vec<SubnetId>,
+    nns_canisters: &NnsCanistersConfig,
+) {
+    let mut nns_init_payload_builder = NnsInitPayloadsBuilder::new();
+    nns_init_payload_builder
+        .with_initial_invariant_compliant_mutations()
+        .with_sns_dedicated_subnets(sns_subnet_id.into_iter().collect())
+        .with_sns_wasm_access_controls(true);
+    if nns_canisters.test_neurons {
+        nns_init_payload_builder
+            .with_test_neurons_fund_neurons(NEURONS_FUND_MATURITY_EQUIVALENT_ICP_E8S);
+    }
+    for (account, balance) in initial_balances(nns_canisters).unwrap() {
+        nns_init_payload_builder.with_ledger_account(account, balance);
+    }
+    setup_nns_canisters_with_features(nns_subnet, nns_init_payload_builder.build(), &[]);
+
+    let authorized_subnets = match nns_canisters.authorized_subnets {
+        Some(ref subnets) => subnets
+            .iter()
+            .map(|subnet| SubnetId::new(PrincipalId(subnet.clone().into())))
+            .collect(),
+        None => app_subnet_ids,
+    };
+    call_as_governance(
+        nns_subnet,
+        "set_authorized_subnetwork_list",
+        Encode!(&SetAuthorizedSubnetworkListArgs {
+            who: None,
+            subnets: authorized_subnets,
+        })
+        .unwrap(),
+    );
+
+    let timestamp_seconds = nns_subnet
+        .time()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let reply = call_as_governance(
+        nns_subnet,
+        "set_icp_xdr_conversion_rate",
+        Encode!(&UpdateIcpXdrConversionRatePayload {
+            data_source: "PocketIC".to_string(),
+            timestamp_seconds,
+            xdr_permyriad_per_icp: nns_canisters.xdr_permyriad_per_icp,
+            reason: None,
+        })
+        .unwrap(),
+    );
+    Decode!(&reply, Result<(), String>)
+        .unwrap()
+        .expect("Failed to set the ICP/XDR conversion rate");
+}
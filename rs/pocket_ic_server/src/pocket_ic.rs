@@ -1,8 +1,10 @@
 use crate::async_trait;
-use crate::state_api::state::{HasStateLabel, OpOut, PocketIcError, StateLabel};
+use crate::call_graph::{call_graph, call_graph_rows, ingress_call_graphs};
+use crate::canister_http_mock::{forwarded_url, validate_forward_to, CanisterHttpMockRuleMatcher};
+use crate::state_api::state::{ApiState, HasStateLabel, OpOut, PocketIcError, StateLabel};
 use crate::OpId;
 use crate::Operation;
-use crate::{copy_dir, BlobStore};
+use crate::{copy_dir, link_or_copy_dir, BlobStore};
 use askama::Template;
 use axum::{
     extract::State,
@@ -42,13 +44,17 @@ use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
 use ic_registry_routing_table::{CanisterIdRange, RoutingTable, CANISTER_IDS_PER_SUBNET};
 use ic_registry_subnet_type::SubnetType;
 use ic_state_machine_tests::{
-    finalize_registry, IngressState, IngressStatus, RejectCode, StateMachine, StateMachineBuilder,
-    StateMachineConfig, StateMachineStateDir, SubmitIngressError, Time,
+    finalize_registry, IngressState, IngressStatus, MessageExecutionRecord, RejectCode,
+    StateMachine, StateMachineBuilder, StateMachineConfig, StateMachineStateDir,
+    SubmitIngressError, Time, MAX_MESSAGE_EXECUTION_RECORDS,
 };
 use ic_test_utilities_registry::add_subnet_list_record;
 use ic_types::{
     artifact::UnvalidatedArtifactMutation,
-    canister_http::{CanisterHttpReject, CanisterHttpRequestId, CanisterHttpResponseContent},
+    canister_http::{
+        CanisterHttpReject, CanisterHttpRequestContext, CanisterHttpRequestId,
+        CanisterHttpResponseContent,
+    },
     crypto::{BasicSig, BasicSigOf, CryptoResult, Signable},
     messages::{
         CertificateDelegation, HttpCallContent, HttpRequestEnvelope, MessageId as OtherMessageId,
@@ -60,8 +66,9 @@ use ic_types::{
 use ic_validator_ingress_message::StandaloneIngressSigVerifier;
 use itertools::Itertools;
 use pocket_ic::common::rest::{
-    self, BinaryBlob, BlobCompression, CanisterHttpHeader, CanisterHttpMethod, CanisterHttpRequest,
-    CanisterHttpResponse, DtsFlag, ExtendedSubnetConfigSet, MockCanisterHttpResponse, RawAddCycles,
+    self, BinaryBlob, BlobCompression, CanisterHttpHeader, CanisterHttpMethod,
+    CanisterHttpMockRule, CanisterHttpRequest, CanisterHttpResponse, DtsFlag,
+    ExtendedSubnetConfigSet, MockCanisterHttpResponse, NnsCanistersConfig, RawAddCycles,
     RawCanisterCall, RawEffectivePrincipal, RawMessageId, RawSetStableMemory,
     SubnetInstructionConfig, SubnetKind, SubnetSpec, Topology,
 };
@@ -73,10 +80,10 @@ use std::hash::Hash;
 use std::str::FromStr;
 use std::{
     cmp::max,
-    collections::BTreeMap,
+    collections::{BTreeMap, VecDeque},
     fs::File,
     io::{BufReader, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex, RwLock},
     time::{Duration, SystemTime},
 };
@@ -139,6 +146,10 @@ struct TopologyInternal(pub BTreeMap<[u8; 32], SubnetConfigInternal>);
 struct SubnetConfigInternal {
     pub subnet_id: SubnetId,
     pub subnet_kind: SubnetKind,
+    /// Zero in topologies written before the subnet size could be changed:
+    /// such subnets have the default size of their kind.
+    #[serde(default)]
+    pub subnet_size: u64,
     pub instruction_config: SubnetInstructionConfig,
     pub dts_flag: DtsFlag,
     pub ranges: Vec<CanisterIdRange>,
@@ -163,6 +174,14 @@ pub struct PocketIc {
     registry_data_provider: Arc<ProtoRegistryDataProvider>,
     runtime: Arc<Runtime>,
     nonmainnet_features: bool,
+    // Inter-canister call graph tracing is opt-in
+    // and the most recent recorded executions are only kept in memory.
+    call_graph_tracing: bool,
+    execution_records: VecDeque<MessageExecutionRecord>,
+    // Pending canister HTTP outcalls are answered automatically
+    // if they match a mock rule or if forwarding is enabled.
+    canister_http_mock_rules: Vec<CanisterHttpMockRuleMatcher>,
+    canister_http_forward_to: Option<String>,
 }
 
 impl Drop for PocketIc {
@@ -175,26 +194,7 @@ impl Drop for PocketIc {
             for subnet in subnets.values() {
                 subnet.await_state_hash();
             }
-            let mut topology_file = File::create(state_dir.join("topology.json")).unwrap();
-            let raw_topology: RawTopologyInternal = RawTopologyInternal(
-                self.topology
-                    .0
-                    .clone()
-                    .into_iter()
-                    .map(|(seed, config)| {
-                        let time = subnets.get(&config.subnet_id).unwrap().time();
-                        (
-                            hex::encode(seed),
-                            RawSubnetConfigInternal {
-                                subnet_config: config,
-                                time,
-                            },
-                        )
-                    })
-                    .collect(),
-            );
-            let topology_json = serde_json::to_string(&raw_topology).unwrap();
-            topology_file.write_all(topology_json.as_bytes()).unwrap();
+            write_topology_file(&self.topology, &subnets, state_dir).unwrap();
         }
         for subnet in subnets.values() {
             subnet.drop_payload_builder();
@@ -202,6 +202,35 @@ impl Drop for PocketIc {
     }
 }
 
+/// Writes the topology of a PocketIC instance (including the current time of every subnet)
+/// into the file `topology.json` in the given directory.
+fn write_topology_file(
+    topology: &TopologyInternal,
+    subnets: &BTreeMap<SubnetId, Arc<StateMachine>>,
+    dir: &Path,
+) -> std::io::Result<()> {
+    let raw_topology: RawTopologyInternal = RawTopologyInternal(
+        topology
+            .0
+            .clone()
+            .into_iter()
+            .map(|(seed, config)| {
+                let time = subnets.get(&config.subnet_id).unwrap().time();
+                (
+                    hex::encode(seed),
+                    RawSubnetConfigInternal {
+                        subnet_config: config,
+                        time,
+                    },
+                )
+            })
+            .collect(),
+    );
+    let topology_json = serde_json::to_string(&raw_topology).unwrap();
+    let mut topology_file = File::create(dir.join("topology.json"))?;
+    topology_file.write_all(topology_json.as_bytes())
+}
+
 impl PocketIc {
     pub(crate) fn topology(&self) -> Topology {
         let mut topology = Topology(BTreeMap::new());
@@ -244,13 +273,15 @@ impl PocketIc {
         runtime: Arc<Runtime>,
         subnet_kind: SubnetKind,
         subnet_seed: [u8; 32],
+        subnet_size: u64,
+        subnet_id: Option<SubnetId>,
         instruction_config: SubnetInstructionConfig,
+        dts_flag: DtsFlag,
         registry_data_provider: Arc<ProtoRegistryDataProvider>,
         time: SystemTime,
         nonmainnet_features: bool,
     ) -> StateMachineBuilder {
         let subnet_type = conv_type(subnet_kind);
-        let subnet_size = subnet_size(subnet_kind);
         let mut subnet_config = SubnetConfig::new(subnet_type);
         let mut hypervisor_config = if nonmainnet_features {
             ic_starter::hypervisor_config(true)
@@ -285,7 +316,7 @@ impl PocketIc {
             .unwrap()
             .as_nanos() as u64;
         let time = Time::from_nanos_since_unix_epoch(t);
-        StateMachineBuilder::new()
+        let mut builder = StateMachineBuilder::new()
             .with_runtime(runtime)
             .with_config(Some(state_machine_config))
             .with_subnet_seed(subnet_seed)
@@ -293,14 +324,45 @@ impl PocketIc {
             .with_subnet_type(subnet_type)
             .with_time(time)
             .with_state_machine_state_dir(state_machine_state_dir)
-            .with_registry_data_provider(registry_data_provider.clone())
+            .with_registry_data_provider(registry_data_provider.clone());
+
+        if let DtsFlag::Disabled = dts_flag {
+            builder = builder.no_dts();
+        };
+
+        if subnet_kind == SubnetKind::NNS {
+            builder = builder.with_root_subnet_config();
+        }
+
+        if let Some(subnet_id) = subnet_id {
+            builder = builder.with_subnet_id(subnet_id);
+        }
+
+        if subnet_kind == SubnetKind::II {
+            builder = builder.with_idkg_key(MasterPublicKeyId::Ecdsa(EcdsaKeyId {
+                curve: EcdsaCurve::Secp256k1,
+                name: "dfx_test_key1".to_string(),
+            }));
+            builder = builder.with_idkg_key(MasterPublicKeyId::Ecdsa(EcdsaKeyId {
+                curve: EcdsaCurve::Secp256k1,
+                name: "test_key_1".to_string(),
+            }));
+            builder = builder.with_idkg_key(MasterPublicKeyId::Ecdsa(EcdsaKeyId {
+                curve: EcdsaCurve::Secp256k1,
+                name: "key_1".to_string(),
+            }));
+        }
+
+        builder
     }
 
     pub(crate) fn new(
         runtime: Arc<Runtime>,
         subnet_configs: ExtendedSubnetConfigSet,
         state_dir: Option<PathBuf>,
+        snapshot_dir: Option<PathBuf>,
         nonmainnet_features: bool,
+        nns_canisters: Option<NnsCanistersConfig>,
     ) -> Self {
         let mut range_gen = RangeGen::new();
         let mut routing_table = RoutingTable::new();
@@ -309,8 +371,10 @@ impl PocketIc {
                 .map(|y| SubnetId::new(PrincipalId(y.into())))
         });
 
-        let topology: Option<RawTopologyInternal> = if let Some(ref state_dir) = state_dir {
-            let topology_file_path = state_dir.join("topology.json");
+        // A PocketIC instance created from a snapshot uses the topology stored in the snapshot.
+        let topology_dir = snapshot_dir.as_ref().or(state_dir.as_ref());
+        let topology: Option<RawTopologyInternal> = if let Some(topology_dir) = topology_dir {
+            let topology_file_path = topology_dir.join("topology.json");
             File::open(topology_file_path).ok().map(|file| {
                 let reader = BufReader::new(file);
                 serde_json::from_reader(reader).unwrap()
@@ -319,22 +383,46 @@ impl PocketIc {
             None
         };
 
+        // The NNS canisters are only installed on new instances.
+        let nns_canisters = nns_canisters.filter(|_| topology.is_none());
+
         let subnet_config_info: Vec<SubnetConfigInfo> = if let Some(topology) = topology {
             topology
                 .0
                 .into_iter()
-                .map(|(subnet_seed, config)| SubnetConfigInfo {
-                    state_machine_state_dir: Box::new(
-                        state_dir.as_ref().unwrap().join(subnet_seed.clone()),
-                    ),
-                    subnet_id: Some(config.subnet_config.subnet_id),
-                    ranges: config.subnet_config.ranges,
-                    alloc_range: config.subnet_config.alloc_range,
-                    subnet_kind: config.subnet_config.subnet_kind,
-                    subnet_seed: hex::decode(subnet_seed).unwrap().try_into().unwrap(),
-                    instruction_config: config.subnet_config.instruction_config,
-                    dts_flag: config.subnet_config.dts_flag,
-                    time: config.time,
+                .map(|(subnet_seed, config)| {
+                    let state_machine_state_dir: Box<dyn StateMachineStateDir> =
+                        if let Some(ref snapshot_dir) = snapshot_dir {
+                            // The checkpoints in the snapshot are read-only and thus
+                            // they can be shared by all instances created from the snapshot.
+                            let seed: [u8; 32] =
+                                hex::decode(&subnet_seed).unwrap().try_into().unwrap();
+                            let state_machine_state_dir =
+                                Self::create_state_machine_state_dir(&state_dir, &seed);
+                            link_or_copy_dir(
+                                snapshot_dir.join(&subnet_seed),
+                                state_machine_state_dir.path(),
+                            )
+                            .expect("Failed to copy snapshot");
+                            state_machine_state_dir
+                        } else {
+                            Box::new(state_dir.as_ref().unwrap().join(subnet_seed.clone()))
+                        };
+                    SubnetConfigInfo {
+                        state_machine_state_dir,
+                        subnet_id: Some(config.subnet_config.subnet_id),
+                        ranges: config.subnet_config.ranges,
+                        alloc_range: config.subnet_config.alloc_range,
+                        subnet_kind: config.subnet_config.subnet_kind,
+                        subnet_size: match config.subnet_config.subnet_size {
+                            0 => subnet_size(config.subnet_config.subnet_kind),
+                            size => size,
+                        },
+                        subnet_seed: hex::decode(subnet_seed).unwrap().try_into().unwrap(),
+                        instruction_config: config.subnet_config.instruction_config,
+                        dts_flag: config.subnet_config.dts_flag,
+                        time: config.time,
+                    }
                 })
                 .collect()
         } else {
@@ -389,6 +477,7 @@ impl PocketIc {
                     ranges,
                     alloc_range,
                     subnet_kind,
+                    subnet_size: subnet_size(subnet_kind),
                     subnet_seed,
                     instruction_config,
                     dts_flag,
@@ -411,50 +500,27 @@ impl PocketIc {
             ranges,
             alloc_range,
             subnet_kind,
+            subnet_size,
             subnet_seed,
             instruction_config,
             dts_flag,
             time,
         } in subnet_config_info.into_iter()
         {
-            let mut builder = Self::state_machine_builder(
+            let builder = Self::state_machine_builder(
                 state_machine_state_dir,
                 runtime.clone(),
                 subnet_kind,
                 subnet_seed,
+                subnet_size,
+                subnet_id,
                 instruction_config.clone(),
+                dts_flag,
                 registry_data_provider.clone(),
                 time,
                 nonmainnet_features,
             );
 
-            if let DtsFlag::Disabled = dts_flag {
-                builder = builder.no_dts();
-            };
-
-            if subnet_kind == SubnetKind::NNS {
-                builder = builder.with_root_subnet_config();
-            }
-
-            if let Some(subnet_id) = subnet_id {
-                builder = builder.with_subnet_id(subnet_id);
-            }
-
-            if subnet_kind == SubnetKind::II {
-                builder = builder.with_idkg_key(MasterPublicKeyId::Ecdsa(EcdsaKeyId {
-                    curve: EcdsaCurve::Secp256k1,
-                    name: "dfx_test_key1".to_string(),
-                }));
-                builder = builder.with_idkg_key(MasterPublicKeyId::Ecdsa(EcdsaKeyId {
-                    curve: EcdsaCurve::Secp256k1,
-                    name: "test_key_1".to_string(),
-                }));
-                builder = builder.with_idkg_key(MasterPublicKeyId::Ecdsa(EcdsaKeyId {
-                    curve: EcdsaCurve::Secp256k1,
-                    name: "key_1".to_string(),
-                }));
-            }
-
             let sm = builder.build_with_subnets(subnets.clone());
             let subnet_id = sm.get_subnet_id();
 
@@ -474,6 +540,7 @@ impl PocketIc {
             let subnet_config_internal = SubnetConfigInternal {
                 subnet_id,
                 subnet_kind,
+                subnet_size,
                 instruction_config,
                 ranges,
                 alloc_range,
@@ -520,9 +587,33 @@ impl PocketIc {
             subnet.execute_round();
         }
 
+        if let Some(ref nns_canisters) = nns_canisters {
+            let subnet_ids_of_kind = |subnet_kind| {
+                topology
+                    .0
+                    .values()
+                    .filter(move |config| config.subnet_kind == subnet_kind)
+                    .map(|config| config.subnet_id)
+            };
+            let nns_subnet = subnets
+                .read()
+                .unwrap()
+                .get(&nns_subnet_id.expect("NNS canisters require an NNS subnet"))
+                .unwrap()
+                .clone();
+            crate::nns_canisters::install(
+                &nns_subnet,
+                subnet_ids_of_kind(SubnetKind::SNS).next(),
+                subnet_ids_of_kind(SubnetKind::Application).collect(),
+                nns_canisters,
+            );
+        }
+
         let mut hasher = Sha256::new();
         let subnet_configs_string = format!("{:?}", subnet_configs);
         hasher.write(subnet_configs_string.as_bytes());
+        let nns_canisters_string = format!("{:?}", nns_canisters);
+        hasher.write(nns_canisters_string.as_bytes());
         let initial_state_hash = compute_state_label(
             &hasher.finish(),
             subnets.read().unwrap().values().cloned().collect(),
@@ -540,6 +631,10 @@ impl PocketIc {
             registry_data_provider,
             runtime,
             nonmainnet_features,
+            call_graph_tracing: false,
+            execution_records: VecDeque::new(),
+            canister_http_mock_rules: vec![],
+            canister_http_forward_to: None,
         }
     }
 
@@ -623,6 +718,301 @@ impl PocketIc {
             nns_subnet.get_delegation_for_subnet(subnet_id).ok()
         }
     }
+
+    /// Creates a new subnet with the given canister ranges
+    /// and inserts it into the routing table, topology, and registry.
+    fn create_subnet(
+        &mut self,
+        subnet_kind: SubnetKind,
+        ranges: Vec<CanisterIdRange>,
+        alloc_range: Option<CanisterIdRange>,
+        instruction_config: SubnetInstructionConfig,
+        dts_flag: DtsFlag,
+    ) -> Arc<StateMachine> {
+        // We retrieve the PocketIC instace time (consistent across all subnets) from one subnet.
+        let time = self.any_subnet().time();
+        // Compute the subnet seed.
+        let subnet_seed = compute_subnet_seed(ranges.clone(), alloc_range);
+        let subnet_size = subnet_size(subnet_kind);
+        // We build the `StateMachine` of the new subnet.
+        let builder = PocketIc::state_machine_builder(
+            PocketIc::create_state_machine_state_dir(&self.state_dir, &subnet_seed),
+            self.runtime.clone(),
+            subnet_kind,
+            subnet_seed,
+            subnet_size,
+            None,
+            instruction_config.clone(),
+            dts_flag,
+            self.registry_data_provider.clone(),
+            time,
+            self.nonmainnet_features,
+        );
+        let sm = builder.build_with_subnets(self.subnets.clone());
+        sm.set_message_execution_tracing(self.call_graph_tracing);
+        // We insert the new subnet into the routing table.
+        let subnet_id = sm.get_subnet_id();
+        for range in &ranges {
+            self.routing_table.insert(*range, subnet_id).unwrap();
+        }
+        if let Some(alloc_range) = alloc_range {
+            self.routing_table.insert(alloc_range, subnet_id).unwrap();
+        }
+        // We insert the new subnet into the topology.
+        let subnet_config_internal = SubnetConfigInternal {
+            subnet_id,
+            subnet_kind,
+            subnet_size,
+            instruction_config,
+            ranges,
+            alloc_range,
+            dts_flag,
+        };
+        self.topology.0.insert(subnet_seed, subnet_config_internal);
+        // We update the registry by creating a new registry version
+        // and inserting new records at that new registry version.
+        let registry_version = self.registry_data_provider.latest_version();
+        let pb_routing_table = PbRoutingTable::from(self.routing_table.clone());
+        self.registry_data_provider
+            .add(
+                &make_routing_table_record_key(),
+                registry_version,
+                Some(pb_routing_table),
+            )
+            .unwrap();
+        let subnet_list = self
+            .topology()
+            .0
+            .keys()
+            .map(|p| PrincipalId(*p).into())
+            .collect();
+        add_subnet_list_record(
+            &self.registry_data_provider,
+            registry_version.get(),
+            subnet_list,
+        );
+        self.reload_registry();
+        // We need to execute a round on the new subnet to make its state certified.
+        // To keep the PocketIC instance time in sync, we execute a round on all subnets.
+        for subnet in self.subnets.read().unwrap().values() {
+            subnet.execute_round();
+        }
+        sm
+    }
+
+    /// Adds a new subnet of the given kind to a running PocketIC instance.
+    /// NNS and II subnets cannot be added at runtime because the NNS subnet is the root subnet
+    /// and both NNS and II subnets on the IC mainnet do not have a single canister range.
+    pub(crate) fn add_subnet(
+        &mut self,
+        subnet_kind: SubnetKind,
+        subnet_spec: &SubnetSpec,
+    ) -> Result<SubnetId, String> {
+        if matches!(subnet_kind, SubnetKind::NNS) || matches!(subnet_kind, SubnetKind::II) {
+            return Err(format!("A subnet of kind {:?} cannot be added to a running PocketIC instance: please set up your PocketIC instance with a subnet of that `SubnetKind`.", subnet_kind));
+        }
+        if subnet_spec.get_subnet_id().is_some() {
+            return Err(
+                "Only subnets with a new state can be added to a running PocketIC instance."
+                    .to_string(),
+            );
+        }
+        // Named subnets (other than application and system subnets) have fixed canister ranges
+        // on the IC mainnet and thus there can be at most one subnet of such a kind.
+        if !matches!(subnet_kind, SubnetKind::Application | SubnetKind::System)
+            && self
+                .topology
+                .0
+                .values()
+                .any(|config| config.subnet_kind == subnet_kind)
+        {
+            return Err(format!(
+                "The PocketIC instance already contains a subnet of kind {:?}.",
+                subnet_kind
+            ));
+        }
+        let RangeConfig {
+            canister_id_ranges,
+            canister_allocation_range,
+        } = get_range_config(subnet_kind, &mut self.range_gen, false);
+        let sm = self.create_subnet(
+            subnet_kind,
+            canister_id_ranges,
+            canister_allocation_range,
+            subnet_spec.get_instruction_config(),
+            subnet_spec.get_dts_flag(),
+        );
+        Ok(sm.get_subnet_id())
+    }
+
+    /// Restarts the subnet with the given ID from a checkpoint of its latest state
+    /// and optionally changes the number of its nodes.
+    /// The checkpoint is taken right before the restart so that no state is lost
+    /// and the XNet streams of the restarted subnet stay consistent with the other subnets.
+    pub(crate) fn restart_subnet(
+        &mut self,
+        subnet_id: SubnetId,
+        subnet_size: Option<u64>,
+    ) -> Result<(), PocketIcError> {
+        let Some(sm) = self.get_subnet_with_id(subnet_id) else {
+            return Err(PocketIcError::SubnetNotFound(subnet_id.get().0));
+        };
+        // The `StateMachine` must be unwrapped to restart the subnet and thus
+        // it must not be referenced anywhere else than in `sm` and `self.subnets`.
+        if Arc::strong_count(&sm) > 2 {
+            return Err(PocketIcError::InvalidTopologyChange(format!(
+                "Subnet {} is still in use and cannot be restarted.",
+                subnet_id
+            )));
+        }
+        let Some((subnet_seed, config)) = self
+            .topology
+            .0
+            .iter_mut()
+            .find(|(_, config)| config.subnet_id == subnet_id)
+        else {
+            return Err(PocketIcError::SubnetNotFound(subnet_id.get().0));
+        };
+        if let Some(subnet_size) = subnet_size {
+            if subnet_size == 0 {
+                return Err(PocketIcError::InvalidTopologyChange(
+                    "A subnet must consist of at least one node.".to_string(),
+                ));
+            }
+            config.subnet_size = subnet_size;
+        }
+        let subnet_seed = *subnet_seed;
+        let config = config.clone();
+
+        sm.checkpointed_tick();
+        sm.await_state_hash();
+        let time = sm.time();
+        // The recorded executions would be lost with the `StateMachine`.
+        self.add_execution_records(sm.take_message_execution_records());
+        // The payload builder contains an `Arc` of the `StateMachine`
+        // and thus it must be dropped before the `StateMachine` can be unwrapped.
+        sm.drop_payload_builder();
+        self.subnets.write().unwrap().remove(&subnet_id);
+        let state_machine_state_dir = Arc::try_unwrap(sm)
+            .unwrap_or_else(|_| unreachable!("Subnet {} is not in use.", subnet_id))
+            .into_state_dir();
+
+        let builder = PocketIc::state_machine_builder(
+            state_machine_state_dir,
+            self.runtime.clone(),
+            config.subnet_kind,
+            subnet_seed,
+            config.subnet_size,
+            Some(subnet_id),
+            config.instruction_config,
+            config.dts_flag,
+            self.registry_data_provider.clone(),
+            time,
+            self.nonmainnet_features,
+        );
+        let sm = builder.build_with_subnets(self.subnets.clone());
+        sm.set_message_execution_tracing(self.call_graph_tracing);
+        // The new `StateMachine` created a new registry version with its node records.
+        self.reload_registry();
+
+        // The restarted subnet executed an extra round to write a checkpoint
+        // and thus we sync the time on all subnets.
+        let mut max_time = GENESIS;
+        for subnet in self.subnets.read().unwrap().values() {
+            max_time = max(max_time, subnet.get_time());
+        }
+        for subnet in self.subnets.read().unwrap().values() {
+            subnet.set_time(max_time.into());
+            subnet.execute_round();
+        }
+        Ok(())
+    }
+
+    /// Writes a snapshot of the latest state of this PocketIC instance into the given directory
+    /// (which must not exist yet). The snapshot consists of the latest checkpoint of every subnet
+    /// and the topology of the instance. The topology file is written last so that
+    /// its presence marks the snapshot as complete.
+    pub(crate) fn create_snapshot(&self, snapshot_dir: &Path) -> Result<(), PocketIcError> {
+        // Fails if the snapshot already exists.
+        std::fs::create_dir(snapshot_dir).map_err(|e| {
+            PocketIcError::SnapshotError(format!(
+                "Failed to create snapshot directory {}: {}",
+                snapshot_dir.display(),
+                e
+            ))
+        })?;
+        self.write_snapshot(snapshot_dir).map_err(|e| {
+            // Clean up the partially written snapshot.
+            let _ = std::fs::remove_dir_all(snapshot_dir);
+            PocketIcError::SnapshotError(e.to_string())
+        })
+    }
+
+    fn write_snapshot(&self, snapshot_dir: &Path) -> std::io::Result<()> {
+        let subnets = self.subnets.read().unwrap();
+        for subnet in subnets.values() {
+            subnet.checkpointed_tick();
+        }
+        for subnet in subnets.values() {
+            subnet.await_state_hash();
+        }
+        for (subnet_seed, config) in self.topology.0.iter() {
+            let subnet = subnets.get(&config.subnet_id).unwrap();
+            link_or_copy_dir(
+                subnet.state_dir_path().join("checkpoints"),
+                snapshot_dir
+                    .join(hex::encode(subnet_seed))
+                    .join("checkpoints"),
+            )?;
+        }
+        write_topology_file(&self.topology, &subnets, snapshot_dir)
+    }
+
+    /// Enables or disables inter-canister call graph tracing on all subnets.
+    /// Disabling tracing keeps the executions recorded so far.
+    pub(crate) fn set_call_graph_tracing(&mut self, enabled: bool) {
+        self.call_graph_tracing = enabled;
+        for subnet in self.subnets.read().unwrap().values() {
+            subnet.set_message_execution_tracing(enabled);
+        }
+    }
+
+    fn add_execution_records(&mut self, records: Vec<MessageExecutionRecord>) {
+        self.execution_records.extend(records);
+        let excess = self
+            .execution_records
+            .len()
+            .saturating_sub(MAX_MESSAGE_EXECUTION_RECORDS);
+        self.execution_records.drain(..excess);
+    }
+
+    /// Returns the most recent (at most `MAX_MESSAGE_EXECUTION_RECORDS`)
+    /// message executions recorded on any subnet so far.
+    fn execution_records(&mut self) -> &[MessageExecutionRecord] {
+        let records: Vec<_> = self
+            .subnets
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|subnet| subnet.take_message_execution_records())
+            .collect();
+        self.add_execution_records(records);
+        self.execution_records.make_contiguous()
+    }
+
+    fn reload_registry(&self) {
+        for subnet in self.subnets.read().unwrap().values() {
+            // Reload registry on the state machines to make sure
+            // all the state machines have a consistent view of the registry.
+            subnet.reload_registry();
+        }
+        // Update the registry file on disk.
+        if let Some(ref state_dir) = self.state_dir {
+            let registry_proto_path = PathBuf::from(state_dir).join("registry.proto");
+            self.registry_data_provider
+                .write_to_file(registry_proto_path);
+        }
+    }
 }
 
 impl Default for PocketIc {
@@ -807,6 +1197,7 @@ struct SubnetConfigInfo {
     pub ranges: Vec<CanisterIdRange>,
     pub alloc_range: Option<CanisterIdRange>,
     pub subnet_kind: SubnetKind,
+    pub subnet_size: u64,
     pub subnet_seed: [u8; 32],
     pub instruction_config: SubnetInstructionConfig,
     pub dts_flag: DtsFlag,
@@ -891,20 +1282,38 @@ fn http_header_from(
     }
 }
 
+fn canister_http_request_from(
+    subnet_id: SubnetId,
+    request_id: CanisterHttpRequestId,
+    context: &CanisterHttpRequestContext,
+) -> CanisterHttpRequest {
+    CanisterHttpRequest {
+        subnet_id: subnet_id.get().0,
+        request_id: request_id.get(),
+        http_method: http_method_from(&context.http_method),
+        url: context.url.clone(),
+        headers: context.headers.iter().map(http_header_from).collect(),
+        body: context.body.clone().unwrap_or_default(),
+        max_response_bytes: context.max_response_bytes.map(|b| b.get()),
+    }
+}
+
+/// Returns all pending canister HTTP outcalls that are not answered automatically
+/// (by a mock rule or by forwarding them to a test server).
 fn get_canister_http_requests(pic: &PocketIc) -> Vec<CanisterHttpRequest> {
+    if pic.canister_http_forward_to.is_some() {
+        return vec![];
+    }
     let mut canister_http = vec![];
     for subnet in pic.subnets.read().unwrap().values() {
         let mut cur: Vec<_> = subnet
             .canister_http_request_contexts()
             .into_iter()
-            .map(|(id, c)| CanisterHttpRequest {
-                subnet_id: subnet.get_subnet_id().get().0,
-                request_id: id.get(),
-                http_method: http_method_from(&c.http_method),
-                url: c.url,
-                headers: c.headers.iter().map(http_header_from).collect(),
-                body: c.body.unwrap_or_default(),
-                max_response_bytes: c.max_response_bytes.map(|b| b.get()),
+            .map(|(id, c)| canister_http_request_from(subnet.get_subnet_id(), id, &c))
+            .filter(|request| {
+                !pic.canister_http_mock_rules
+                    .iter()
+                    .any(|rule| rule.matches(request))
             })
             .collect();
         canister_http.append(&mut cur);
@@ -1004,9 +1413,58 @@ fn process_mock_canister_https_response(
             canister_http_request_id,
         )));
     };
+    mock_canister_http_responses(
+        pic,
+        &subnet,
+        canister_http_request_id,
+        context,
+        &[mock_canister_http_response.response.clone()],
+    );
+    OpOut::NoOutput
+}
+
+/// Answers the given canister HTTP outcall with the given responses (one per node
+/// with the last response being used for all remaining nodes).
+/// Replies are transformed by the transform function of the request (if any).
+fn mock_canister_http_responses(
+    pic: &PocketIc,
+    subnet: &StateMachine,
+    canister_http_request_id: CanisterHttpRequestId,
+    context: &CanisterHttpRequestContext,
+    responses: &[CanisterHttpResponse],
+) {
     let timeout = context.time + Duration::from_secs(5 * 60);
     let canister_id = context.request.sender;
-    let content = match &mock_canister_http_response.response {
+    let contents = responses
+        .iter()
+        .map(|response| {
+            canister_http_response_content(
+                pic,
+                subnet,
+                canister_http_request_id,
+                context,
+                timeout,
+                response,
+            )
+        })
+        .collect();
+    subnet.mock_canister_http_response_per_node(
+        canister_http_request_id.get(),
+        timeout,
+        canister_id,
+        contents,
+    );
+}
+
+fn canister_http_response_content(
+    pic: &PocketIc,
+    subnet: &StateMachine,
+    canister_http_request_id: CanisterHttpRequestId,
+    context: &CanisterHttpRequestContext,
+    timeout: Time,
+    response: &CanisterHttpResponse,
+) -> CanisterHttpResponseContent {
+    match response {
         CanisterHttpResponse::CanisterHttpReply(reply) => {
             let grpc_channel =
                 pic.runtime
@@ -1064,14 +1522,58 @@ fn process_mock_canister_https_response(
                 message: reject.message.clone(),
             })
         }
-    };
-    subnet.mock_canister_http_response(
-        mock_canister_http_response.request_id,
-        timeout,
-        canister_id,
-        content,
-    );
-    OpOut::NoOutput
+    }
+}
+
+/// Answers all pending canister HTTP outcalls matching a mock rule (once the delay
+/// of the rule has elapsed) and forwards all remaining pending canister HTTP outcalls
+/// to the test server if forwarding is enabled.
+fn process_canister_http_mocks(pic: &PocketIc) {
+    if pic.canister_http_mock_rules.is_empty() && pic.canister_http_forward_to.is_none() {
+        return;
+    }
+    for subnet in pic.subnets.read().unwrap().values() {
+        let now = subnet.get_time();
+        for (request_id, context) in subnet.canister_http_request_contexts() {
+            let request = canister_http_request_from(subnet.get_subnet_id(), request_id, &context);
+            let responses = match pic
+                .canister_http_mock_rules
+                .iter()
+                .find(|rule| rule.matches(&request))
+            {
+                Some(rule) => {
+                    if context.time + rule.delay() > now {
+                        continue;
+                    }
+                    rule.responses()
+                }
+                None => match pic.canister_http_forward_to {
+                    Some(ref forward_to) => {
+                        vec![forward_canister_http_request(pic, request, forward_to)]
+                    }
+                    None => continue,
+                },
+            };
+            mock_canister_http_responses(pic, subnet, request_id, &context, &responses);
+        }
+    }
+}
+
+fn forward_canister_http_request(
+    pic: &PocketIc,
+    mut request: CanisterHttpRequest,
+    forward_to: &str,
+) -> CanisterHttpResponse {
+    request.url = forwarded_url(&request.url, forward_to);
+    match pic.runtime.block_on(ApiState::make_http_request(request)) {
+        Ok(reply) => CanisterHttpResponse::CanisterHttpReply(reply),
+        Err((reject_code, message)) => {
+            CanisterHttpResponse::CanisterHttpReject(rest::CanisterHttpReject {
+                reject_code: reject_code as u64,
+                message,
+            })
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -1092,6 +1594,94 @@ impl Operation for MockCanisterHttp {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct AddCanisterHttpMockRule {
+    pub rule: CanisterHttpMockRule,
+}
+
+impl Operation for AddCanisterHttpMockRule {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        match CanisterHttpMockRuleMatcher::try_from(self.rule.clone()) {
+            Ok(rule) => {
+                pic.canister_http_mock_rules.push(rule);
+                OpOut::NoOutput
+            }
+            Err(e) => OpOut::Error(PocketIcError::InvalidCanisterHttpMock(e)),
+        }
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!("add_canister_http_mock_rule({:?})", self.rule))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ClearCanisterHttpMockRules;
+
+impl Operation for ClearCanisterHttpMockRules {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        pic.canister_http_mock_rules.clear();
+        OpOut::NoOutput
+    }
+
+    fn id(&self) -> OpId {
+        OpId("clear_canister_http_mock_rules".to_string())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SetCanisterHttpForwarding {
+    pub forward_to: Option<String>,
+}
+
+impl Operation for SetCanisterHttpForwarding {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        if let Some(ref forward_to) = self.forward_to {
+            if let Err(e) = validate_forward_to(forward_to) {
+                return OpOut::Error(PocketIcError::InvalidCanisterHttpMock(e));
+            }
+        }
+        pic.canister_http_forward_to = self.forward_to.clone();
+        OpOut::NoOutput
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "set_canister_http_forwarding({:?})",
+            self.forward_to
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SetCallGraphTracing {
+    pub enabled: bool,
+}
+
+impl Operation for SetCallGraphTracing {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        pic.set_call_graph_tracing(self.enabled);
+        OpOut::NoOutput
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!("set_call_graph_tracing({})", self.enabled))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GetCallGraph(pub MessageId);
+
+impl Operation for GetCallGraph {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        OpOut::CallGraph(call_graph(pic.execution_records(), &self.0.msg_id))
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!("get_call_graph_{}", self.0.msg_id))
+    }
+}
+
 #[derive(Clone, Debug, Copy)]
 pub struct PubKey {
     pub subnet_id: SubnetId,
@@ -1116,6 +1706,7 @@ pub struct Tick;
 
 impl Operation for Tick {
     fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        process_canister_http_mocks(pic);
         for subnet in pic.subnets.read().unwrap().values() {
             subnet.execute_round();
         }
@@ -1134,6 +1725,9 @@ impl Operation for AdvanceTimeAndTick {
     fn compute(&self, pic: &mut PocketIc) -> OpOut {
         for subnet in pic.subnets.read().unwrap().values() {
             subnet.advance_time(self.0);
+        }
+        process_canister_http_mocks(pic);
+        for subnet in pic.subnets.read().unwrap().values() {
             subnet.execute_round();
         }
         OpOut::NoOutput
@@ -1241,6 +1835,7 @@ impl Operation for AwaitIngressMessage {
                         }
                         _ => {}
                     }
+                    process_canister_http_mocks(pic);
                     for subnet_ in pic.subnets.read().unwrap().values() {
                         subnet_.execute_round();
                     }
@@ -1286,6 +1881,7 @@ impl Operation for ExecuteIngressMessage {
                         // Now, we execute on all subnets until we have the result
                         let max_rounds = 100;
                         for _i in 0..max_rounds {
+                            process_canister_http_mocks(pic);
                             for subnet_ in pic.subnets.read().unwrap().values() {
                                 subnet_.execute_round();
                             }
@@ -1357,6 +1953,11 @@ pub struct DashboardRequest {}
 
 impl Operation for DashboardRequest {
     fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        let mut call_graphs = vec![];
+        for call_graph in ingress_call_graphs(pic.execution_records()) {
+            call_graph_rows(&call_graph, 0, &mut call_graphs);
+        }
+
         let subnets = pic.subnets.read().unwrap();
 
         // All PocketIC subnets have the same height and thus we fetch the height from an arbitrary subnet.
@@ -1386,6 +1987,7 @@ impl Operation for DashboardRequest {
         let dashboard = Dashboard {
             height,
             canisters: &canisters,
+            call_graphs: &call_graphs,
         };
 
         let resp = match dashboard.render() {
@@ -2028,6 +2630,86 @@ impl Operation for GetSubnet {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct CreateSnapshot {
+    pub snapshot_dir: PathBuf,
+}
+
+impl Operation for CreateSnapshot {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        match pic.create_snapshot(&self.snapshot_dir) {
+            Ok(()) => OpOut::NoOutput,
+            Err(e) => OpOut::Error(e),
+        }
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!("create_snapshot({})", self.snapshot_dir.display()))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AddSubnet {
+    pub subnet_kind: SubnetKind,
+    pub subnet_spec: SubnetSpec,
+}
+
+impl Operation for AddSubnet {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        match pic.add_subnet(self.subnet_kind, &self.subnet_spec) {
+            Ok(subnet_id) => OpOut::MaybeSubnetId(Some(subnet_id)),
+            Err(e) => OpOut::Error(PocketIcError::InvalidTopologyChange(e)),
+        }
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "add_subnet({:?},{:?})",
+            self.subnet_kind, self.subnet_spec
+        ))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SetSubnetSize {
+    pub subnet_id: SubnetId,
+    pub num_nodes: u64,
+}
+
+impl Operation for SetSubnetSize {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        match pic.restart_subnet(self.subnet_id, Some(self.num_nodes)) {
+            Ok(()) => OpOut::NoOutput,
+            Err(e) => OpOut::Error(e),
+        }
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "set_subnet_size({},{})",
+            self.subnet_id, self.num_nodes
+        ))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RestartSubnet {
+    pub subnet_id: SubnetId,
+}
+
+impl Operation for RestartSubnet {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        match pic.restart_subnet(self.subnet_id, None) {
+            Ok(()) => OpOut::NoOutput,
+            Err(e) => OpOut::Error(e),
+        }
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!("restart_subnet({})", self.subnet_id))
+    }
+}
+
 /// Add cycles to a given canister.
 ///
 /// # Panics
@@ -2135,8 +2817,6 @@ fn route(
             Some(subnet) => Ok(subnet),
             None => {
                 if is_provisional_create_canister {
-                    // We retrieve the PocketIC instace time (consistent across all subnets) from one subnet.
-                    let time = pic.subnets.read().unwrap().values().next().unwrap().time();
                     // We create a new subnet with the IC mainnet configuration containing the effective canister ID.
                     // NNS and II subnets cannot be created at this point though because NNS is the root subnet
                     // and both NNS and II subnets on the IC mainnet do not have a single canister range
@@ -2172,76 +2852,13 @@ fn route(
                     // and all existing canister ranges within the PocketIC instance and thus we use
                     // `RangeGen::next_range()` to produce such a canister range.
                     let canister_allocation_range = pic.range_gen.next_range();
-                    // Compute the subnet seed.
-                    let subnet_seed =
-                        compute_subnet_seed(vec![range], Some(canister_allocation_range));
-                    // We build the `StateMachine` of the new subnet.
-                    let builder = PocketIc::state_machine_builder(
-                        PocketIc::create_state_machine_state_dir(&pic.state_dir, &subnet_seed),
-                        pic.runtime.clone(),
-                        subnet_kind,
-                        subnet_seed,
-                        instruction_config.clone(),
-                        pic.registry_data_provider.clone(),
-                        time,
-                        pic.nonmainnet_features,
-                    );
-                    let sm = builder.build_with_subnets(pic.subnets.clone());
-                    // We insert the new subnet into the routing table.
-                    let subnet_id = sm.get_subnet_id();
-                    pic.routing_table.insert(range, subnet_id).unwrap();
-                    pic.routing_table
-                        .insert(canister_allocation_range, subnet_id)
-                        .unwrap();
-                    // We insert the new subnet into the topology.
-                    let subnet_config_internal = SubnetConfigInternal {
-                        subnet_id,
+                    Ok(pic.create_subnet(
                         subnet_kind,
+                        vec![range],
+                        Some(canister_allocation_range),
                         instruction_config,
-                        ranges: vec![range],
-                        alloc_range: Some(canister_allocation_range),
                         dts_flag,
-                    };
-                    pic.topology.0.insert(subnet_seed, subnet_config_internal);
-                    // We update the registry by creating a new registry version
-                    // and inserting new records at that new registry version.
-                    let registry_version = pic.registry_data_provider.latest_version();
-                    let pb_routing_table = PbRoutingTable::from(pic.routing_table.clone());
-                    pic.registry_data_provider
-                        .add(
-                            &make_routing_table_record_key(),
-                            registry_version,
-                            Some(pb_routing_table),
-                        )
-                        .unwrap();
-                    let subnet_list = pic
-                        .topology()
-                        .0
-                        .keys()
-                        .map(|p| PrincipalId(*p).into())
-                        .collect();
-                    add_subnet_list_record(
-                        &pic.registry_data_provider,
-                        registry_version.get(),
-                        subnet_list,
-                    );
-                    for subnet in pic.subnets.read().unwrap().values() {
-                        // Reload registry on the state machines to make sure
-                        // all the state machines have a consistent view of the registry.
-                        subnet.reload_registry();
-                    }
-                    // Update the registry file on disk.
-                    if let Some(ref state_dir) = pic.state_dir {
-                        let registry_proto_path = PathBuf::from(state_dir).join("registry.proto");
-                        pic.registry_data_provider
-                            .write_to_file(registry_proto_path);
-                    }
-                    // We need to execute a round on the new subnet to make its state certified.
-                    // To keep the PocketIC instance time in sync, we execute a round on all subnets.
-                    for subnet in pic.subnets.read().unwrap().values() {
-                        subnet.execute_round();
-                    }
-                    Ok(sm)
+                    ))
                 } else {
                     // If the request is not an update call to create a canister using the provisional API,
                     // we return an error (since such an update call to a newly created subnet would fail anyway).
@@ -2367,6 +2984,32 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_restart_subnet_in_use() {
+        let mut pic = PocketIc::default();
+        let sm = pic.any_subnet();
+        let subnet_id = sm.get_subnet_id();
+
+        let err = pic.restart_subnet(subnet_id, None).unwrap_err();
+        assert!(matches!(err, PocketIcError::InvalidTopologyChange(_)));
+        // The subnet is still operational after the failed restart.
+        sm.execute_round();
+
+        drop(sm);
+        pic.restart_subnet(subnet_id, None).unwrap();
+    }
+
+    #[test]
+    fn test_subnet_size_missing_in_topology() {
+        let pic = PocketIc::default();
+        let config = pic.topology.0.values().next().unwrap();
+        let mut json = serde_json::to_value(config).unwrap();
+        json.as_object_mut().unwrap().remove("subnet_size");
+
+        let config: SubnetConfigInternal = serde_json::from_value(json).unwrap();
+        assert_eq!(config.subnet_size, 0);
+    }
+
     #[test]
     fn test_execute_message() {
         let (mut pic, canister_id) = new_pic_counter_installed();
@@ -2477,7 +3120,9 @@ mod tests {
                 ..Default::default()
             },
             None,
+            None,
             false,
+            None,
         );
         let canister_id = pic.any_subnet().create_canister(None);
 
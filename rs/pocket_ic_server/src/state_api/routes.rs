@@ -6,10 +6,12 @@
 ///
 use super::state::{ApiState, OpOut, PocketIcError, StateLabel, UpdateReply};
 use crate::pocket_ic::{
-    AddCycles, AwaitIngressMessage, CallRequest, CallRequestVersion, DashboardRequest,
-    ExecuteIngressMessage, GetCanisterHttp, GetCyclesBalance, GetStableMemory, GetSubnet, GetTime,
-    GetTopology, MockCanisterHttp, PubKey, Query, QueryRequest, ReadStateRequest, SetStableMemory,
-    SetTime, StatusRequest, SubmitIngressMessage, Tick,
+    AddCanisterHttpMockRule, AddCycles, AddSubnet, AwaitIngressMessage, CallRequest,
+    CallRequestVersion, ClearCanisterHttpMockRules, CreateSnapshot, DashboardRequest,
+    ExecuteIngressMessage, GetCallGraph, GetCanisterHttp, GetCyclesBalance, GetStableMemory,
+    GetSubnet, GetTime, GetTopology, MockCanisterHttp, PubKey, Query, QueryRequest,
+    ReadStateRequest, RestartSubnet, SetCallGraphTracing, SetCanisterHttpForwarding,
+    SetStableMemory, SetSubnetSize, SetTime, StatusRequest, SubmitIngressMessage, Tick,
 };
 use crate::{async_trait, pocket_ic::PocketIc, BlobStore, InstanceId, OpId, Operation};
 use aide::{
@@ -34,11 +36,13 @@ use hyper::header;
 use ic_http_endpoints_public::cors_layer;
 use ic_types::CanisterId;
 use pocket_ic::common::rest::{
-    self, ApiResponse, AutoProgressConfig, ExtendedSubnetConfigSet, HttpGatewayConfig,
-    HttpGatewayDetails, InstanceConfig, MockCanisterHttpResponse, RawAddCycles, RawCanisterCall,
-    RawCanisterHttpRequest, RawCanisterId, RawCanisterResult, RawCycles, RawMessageId,
-    RawMockCanisterHttpResponse, RawSetStableMemory, RawStableMemory, RawSubmitIngressResult,
-    RawSubnetId, RawTime, RawWasmResult, Topology,
+    self, ApiResponse, AutoProgressConfig, CanisterHttpMockRule, ExtendedSubnetConfigSet,
+    HttpGatewayConfig, HttpGatewayDetails, InstanceConfig, MockCanisterHttpResponse, RawAddCycles,
+    RawAddSubnet, RawCallGraphNode, RawCallGraphTracing, RawCanisterCall,
+    RawCanisterHttpForwarding, RawCanisterHttpRequest, RawCanisterId, RawCanisterResult,
+    RawCreateSnapshot, RawCycles, RawMessageId, RawMockCanisterHttpResponse, RawSetStableMemory,
+    RawSetSubnetSize, RawStableMemory, RawSubmitIngressResult, RawSubnetId, RawTime, RawWasmResult,
+    Topology,
 };
 use pocket_ic::WasmResult;
 use serde::Serialize;
@@ -76,6 +80,7 @@ where
         .directory_route("/get_stable_memory", post(handler_get_stable_memory))
         .directory_route("/get_subnet", post(handler_get_subnet))
         .directory_route("/pub_key", post(handler_pub_key))
+        .directory_route("/get_call_graph", post(handler_get_call_graph))
 }
 
 pub fn instance_update_routes<S>() -> ApiRouter<S>
@@ -101,6 +106,26 @@ where
         .directory_route("/set_stable_memory", post(handler_set_stable_memory))
         .directory_route("/tick", post(handler_tick))
         .directory_route("/mock_canister_http", post(handler_mock_canister_http))
+        .directory_route(
+            "/add_canister_http_mock_rule",
+            post(handler_add_canister_http_mock_rule),
+        )
+        .directory_route(
+            "/clear_canister_http_mock_rules",
+            post(handler_clear_canister_http_mock_rules),
+        )
+        .directory_route(
+            "/set_canister_http_forwarding",
+            post(handler_set_canister_http_forwarding),
+        )
+        .directory_route("/add_subnet", post(handler_add_subnet))
+        .directory_route("/set_subnet_size", post(handler_set_subnet_size))
+        .directory_route("/restart_subnet", post(handler_restart_subnet))
+        .directory_route("/create_snapshot", post(handler_create_snapshot))
+        .directory_route(
+            "/set_call_graph_tracing",
+            post(handler_set_call_graph_tracing),
+        )
 }
 
 pub fn instance_api_v2_routes<S>() -> ApiRouter<S>
@@ -193,6 +218,18 @@ where
         .layer(cors_layer())
 }
 
+pub fn snapshots_routes<S>() -> ApiRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+    AppState: extract::FromRef<S>,
+{
+    ApiRouter::new()
+        // List all (complete) snapshots.
+        .api_route("/", get(list_snapshots))
+        // Deletes a snapshot.
+        .directory_route("/:name", delete(delete_snapshot))
+}
+
 pub fn http_gateway_routes<S>() -> ApiRouter<S>
 where
     S: Clone + Send + Sync + 'static,
@@ -429,6 +466,18 @@ impl TryFrom<OpOut> for Option<RawSubnetId> {
     }
 }
 
+impl TryFrom<OpOut> for RawSubnetId {
+    type Error = OpConversionError;
+    fn try_from(value: OpOut) -> Result<Self, Self::Error> {
+        match value {
+            OpOut::MaybeSubnetId(Some(subnet_id)) => Ok(RawSubnetId {
+                subnet_id: subnet_id.get().to_vec(),
+            }),
+            _ => Err(OpConversionError),
+        }
+    }
+}
+
 impl TryFrom<OpOut> for Vec<u8> {
     type Error = OpConversionError;
     fn try_from(value: OpOut) -> Result<Self, Self::Error> {
@@ -468,6 +517,16 @@ impl TryFrom<OpOut> for Vec<RawCanisterHttpRequest> {
     }
 }
 
+impl TryFrom<OpOut> for Option<RawCallGraphNode> {
+    type Error = OpConversionError;
+    fn try_from(value: OpOut) -> Result<Self, Self::Error> {
+        match value {
+            OpOut::CallGraph(call_graph) => Ok(call_graph),
+            _ => Err(OpConversionError),
+        }
+    }
+}
+
 #[async_trait]
 impl FromOpOut for PocketHttpResponse {
     async fn from(value: OpOut) -> (StatusCode, ApiResponse<PocketHttpResponse>) {
@@ -570,6 +629,43 @@ pub async fn handler_mock_canister_http(
     (code, Json(response))
 }
 
+pub async fn handler_add_canister_http_mock_rule(
+    State(AppState { api_state, .. }): State<AppState>,
+    headers: HeaderMap,
+    Path(instance_id): Path<InstanceId>,
+    axum::extract::Json(rule): axum::extract::Json<CanisterHttpMockRule>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    let op = AddCanisterHttpMockRule { rule };
+    let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(response))
+}
+
+pub async fn handler_clear_canister_http_mock_rules(
+    State(AppState { api_state, .. }): State<AppState>,
+    headers: HeaderMap,
+    Path(instance_id): Path<InstanceId>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    let op = ClearCanisterHttpMockRules;
+    let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(response))
+}
+
+pub async fn handler_set_canister_http_forwarding(
+    State(AppState { api_state, .. }): State<AppState>,
+    headers: HeaderMap,
+    Path(instance_id): Path<InstanceId>,
+    axum::extract::Json(RawCanisterHttpForwarding { forward_to }): axum::extract::Json<
+        RawCanisterHttpForwarding,
+    >,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    let op = SetCanisterHttpForwarding { forward_to };
+    let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(response))
+}
+
 pub async fn handler_get_cycles(
     State(AppState { api_state, .. }): State<AppState>,
     Path(instance_id): Path<InstanceId>,
@@ -825,6 +921,13 @@ async fn op_out_to_response(op_out: OpOut) -> Response {
             )),
         )
             .into_response(),
+        opout @ OpOut::CallGraph(_) => (
+            StatusCode::OK,
+            Json(ApiResponse::Success(
+                Option::<RawCallGraphNode>::try_from(opout).unwrap(),
+            )),
+        )
+            .into_response(),
         OpOut::RawResponse(fut) => {
             let (status, headers, bytes) = fut.await;
             let code = StatusCode::from_u16(status).unwrap();
@@ -925,6 +1028,28 @@ pub async fn handler_await_ingress_message(
     }
 }
 
+pub async fn handler_get_call_graph(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(raw_message_id): extract::Json<RawMessageId>,
+) -> (StatusCode, Json<ApiResponse<Option<RawCallGraphNode>>>) {
+    let timeout = timeout_or_default(headers);
+    match crate::pocket_ic::MessageId::try_from(raw_message_id) {
+        Ok(message_id) => {
+            let op = GetCallGraph(message_id);
+            let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+            (code, Json(response))
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::Error {
+                message: format!("{:?}", e),
+            }),
+        ),
+    }
+}
+
 pub async fn handler_execute_ingress_message(
     State(AppState { api_state, .. }): State<AppState>,
     Path(instance_id): Path<InstanceId>,
@@ -1019,6 +1144,103 @@ pub async fn handler_tick(
     (code, Json(res))
 }
 
+pub async fn handler_add_subnet(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(RawAddSubnet {
+        subnet_kind,
+        subnet_spec,
+    }): extract::Json<RawAddSubnet>,
+) -> (StatusCode, Json<ApiResponse<RawSubnetId>>) {
+    let timeout = timeout_or_default(headers);
+    let op = AddSubnet {
+        subnet_kind,
+        subnet_spec,
+    };
+    let (code, res) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(res))
+}
+
+pub async fn handler_set_subnet_size(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(RawSetSubnetSize {
+        subnet_id,
+        num_nodes,
+    }): extract::Json<RawSetSubnetSize>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    let subnet_id = ic_types::SubnetId::new(ic_types::PrincipalId(candid::Principal::from_slice(
+        &subnet_id.subnet_id,
+    )));
+    let op = SetSubnetSize {
+        subnet_id,
+        num_nodes,
+    };
+    let (code, res) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(res))
+}
+
+pub async fn handler_restart_subnet(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(RawSubnetId { subnet_id }): extract::Json<RawSubnetId>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    let subnet_id = ic_types::SubnetId::new(ic_types::PrincipalId(candid::Principal::from_slice(
+        &subnet_id,
+    )));
+    let op = RestartSubnet { subnet_id };
+    let (code, res) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(res))
+}
+
+pub async fn handler_set_call_graph_tracing(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(RawCallGraphTracing { enabled }): extract::Json<RawCallGraphTracing>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    let op = SetCallGraphTracing { enabled };
+    let (code, res) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(res))
+}
+
+/// Snapshot names are used as directory names on the server
+/// and thus they are restricted to a safe set of characters.
+fn is_valid_snapshot_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub async fn handler_create_snapshot(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(RawCreateSnapshot { name }): extract::Json<RawCreateSnapshot>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    if !is_valid_snapshot_name(&name) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::Error {
+                message: format!("Invalid snapshot name: {}", name),
+            }),
+        );
+    }
+    let op = CreateSnapshot {
+        snapshot_dir: api_state.snapshot_dir(&name),
+    };
+    let (code, res) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(res))
+}
+
 // ----------------------------------------------------------------------------------------------------------------- //
 // Other handlers
 
@@ -1054,15 +1276,31 @@ pub async fn create_instance(
     extract::Json(instance_config): extract::Json<InstanceConfig>,
 ) -> (StatusCode, Json<rest::CreateInstanceResponse>) {
     let subnet_configs = instance_config.subnet_config_set;
-    if (instance_config.state_dir.is_none()
-        || File::open(
-            instance_config
-                .state_dir
-                .clone()
-                .unwrap()
-                .join("topology.json"),
-        )
-        .is_err())
+    let snapshot_dir = match instance_config.snapshot {
+        Some(ref snapshot) => {
+            let snapshot_dir = api_state.snapshot_dir(snapshot);
+            if !is_valid_snapshot_name(snapshot) || !snapshot_dir.join("topology.json").exists() {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(rest::CreateInstanceResponse::Error {
+                        message: format!("Snapshot {} does not exist", snapshot),
+                    }),
+                );
+            }
+            Some(snapshot_dir)
+        }
+        None => None,
+    };
+    if snapshot_dir.is_none()
+        && (instance_config.state_dir.is_none()
+            || File::open(
+                instance_config
+                    .state_dir
+                    .clone()
+                    .unwrap()
+                    .join("topology.json"),
+            )
+            .is_err())
         && subnet_configs.validate().is_err()
     {
         return (
@@ -1081,13 +1319,29 @@ pub async fn create_instance(
             }),
         );
     }
+    if let Some(ref nns_canisters) = instance_config.nns_canisters {
+        let nns_canisters_error = match subnet_configs.nns {
+            Some(ref nns) if nns.get_subnet_id().is_none() => {
+                crate::nns_canisters::validate(nns_canisters).err()
+            }
+            _ => Some("NNS canisters require a new NNS subnet".to_string()),
+        };
+        if let Some(message) = nns_canisters_error {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(rest::CreateInstanceResponse::Error { message }),
+            );
+        }
+    }
 
     let pocket_ic = tokio::task::spawn_blocking(move || {
         PocketIc::new(
             runtime,
             subnet_configs,
             instance_config.state_dir,
+            snapshot_dir,
             instance_config.nonmainnet_features,
+            instance_config.nns_canisters,
         )
     })
     .await
@@ -1119,6 +1373,33 @@ pub async fn delete_instance(
     StatusCode::OK
 }
 
+pub async fn list_snapshots(
+    State(AppState { api_state, .. }): State<AppState>,
+) -> Json<Vec<String>> {
+    Json(api_state.list_snapshots())
+}
+
+pub async fn delete_snapshot(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(name): Path<String>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    if !is_valid_snapshot_name(&name) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::Error {
+                message: format!("Invalid snapshot name: {}", name),
+            }),
+        );
+    }
+    match api_state.delete_snapshot(&name) {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::Success(()))),
+        Err(message) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::Error { message }),
+        ),
+    }
+}
+
 pub async fn list_http_gateways(
     State(AppState { api_state, .. }): State<AppState>,
 ) -> Json<Vec<HttpGatewayDetails>> {
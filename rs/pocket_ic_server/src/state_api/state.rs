@@ -48,11 +48,12 @@ use ic_types::{
 use pocket_ic::common::rest::{
     CanisterHttpHeader, CanisterHttpMethod, CanisterHttpReject, CanisterHttpReply,
     CanisterHttpRequest, CanisterHttpResponse, HttpGatewayBackend, HttpGatewayConfig,
-    HttpGatewayDetails, HttpGatewayInfo, MockCanisterHttpResponse, Topology,
+    HttpGatewayDetails, HttpGatewayInfo, MockCanisterHttpResponse, RawCallGraphNode, Topology,
 };
 use pocket_ic::{ErrorCode, UserError, WasmResult};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt, path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+use tempfile::TempDir;
 use tokio::{
     sync::mpsc::error::TryRecvError,
     sync::mpsc::Receiver,
@@ -123,6 +124,8 @@ pub struct ApiState {
     port: Option<u16>,
     // HTTP gateway infos (`None` = stopped)
     http_gateways: Arc<RwLock<Vec<Option<HttpGatewayDetails>>>>,
+    // named snapshots of IC instances (one subdirectory per snapshot)
+    snapshots_dir: TempDir,
 }
 
 #[derive(Default)]
@@ -186,6 +189,7 @@ impl PocketIcApiStateBuilder {
             sync_wait_time,
             port: self.port,
             http_gateways: Arc::new(RwLock::new(Vec::new())),
+            snapshots_dir: TempDir::new().expect("Failed to create snapshots directory"),
         })
     }
 }
@@ -206,6 +210,7 @@ pub enum OpOut {
     MessageId((EffectivePrincipal, Vec<u8>)),
     Topology(Topology),
     CanisterHttp(Vec<CanisterHttpRequest>),
+    CallGraph(Option<RawCallGraphNode>),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
@@ -213,8 +218,11 @@ pub enum PocketIcError {
     CanisterNotFound(CanisterId),
     BadIngressMessage(String),
     SubnetNotFound(candid::Principal),
+    SnapshotError(String),
     RequestRoutingError(String),
     InvalidCanisterHttpRequestId((SubnetId, CanisterHttpRequestId)),
+    InvalidTopologyChange(String),
+    InvalidCanisterHttpMock(String),
 }
 
 impl From<Result<ic_state_machine_tests::WasmResult, ic_state_machine_tests::UserError>> for OpOut {
@@ -270,6 +278,9 @@ impl std::fmt::Debug for OpOut {
             OpOut::Error(PocketIcError::SubnetNotFound(sid)) => {
                 write!(f, "SubnetNotFound({})", sid)
             }
+            OpOut::Error(PocketIcError::SnapshotError(msg)) => {
+                write!(f, "SnapshotError({})", msg)
+            }
             OpOut::Error(PocketIcError::RequestRoutingError(msg)) => {
                 write!(f, "RequestRoutingError({:?})", msg)
             }
@@ -283,6 +294,12 @@ impl std::fmt::Debug for OpOut {
                     subnet_id, canister_http_request_id
                 )
             }
+            OpOut::Error(PocketIcError::InvalidTopologyChange(msg)) => {
+                write!(f, "InvalidTopologyChange({})", msg)
+            }
+            OpOut::Error(PocketIcError::InvalidCanisterHttpMock(msg)) => {
+                write!(f, "InvalidCanisterHttpMock({})", msg)
+            }
             OpOut::Bytes(bytes) => write!(f, "Bytes({})", base64::encode(bytes)),
             OpOut::StableMemBytes(bytes) => write!(f, "StableMemory({})", base64::encode(bytes)),
             OpOut::MaybeSubnetId(Some(subnet_id)) => write!(f, "SubnetId({})", subnet_id),
@@ -311,6 +328,7 @@ impl std::fmt::Debug for OpOut {
             OpOut::CanisterHttp(canister_http_reqeusts) => {
                 write!(f, "CanisterHttp({:?})", canister_http_reqeusts)
             }
+            OpOut::CallGraph(call_graph) => write!(f, "CallGraph({:?})", call_graph),
         }
     }
 }
@@ -983,7 +1001,7 @@ impl ApiState {
         }
     }
 
-    async fn make_http_request(
+    pub(crate) async fn make_http_request(
         canister_http_request: CanisterHttpRequest,
     ) -> Result<CanisterHttpReply, (RejectCode, String)> {
         // Socks client setup
@@ -1209,6 +1227,39 @@ impl ApiState {
         res
     }
 
+    /// Returns the directory of the snapshot with the given name.
+    /// The directory does not necessarily exist.
+    pub fn snapshot_dir(&self, name: &str) -> PathBuf {
+        self.snapshots_dir.path().join(name)
+    }
+
+    /// Returns the names of all complete snapshots, i.e., snapshots
+    /// whose topology file has already been written.
+    pub fn list_snapshots(&self) -> Vec<String> {
+        let mut res: Vec<String> = std::fs::read_dir(self.snapshots_dir.path())
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| entry.path().join("topology.json").exists())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        res.sort();
+        res
+    }
+
+    /// Deletes the snapshot with the given name.
+    /// Instances created from the snapshot are not affected.
+    pub fn delete_snapshot(&self, name: &str) -> Result<(), String> {
+        let snapshot_dir = self.snapshot_dir(name);
+        if !snapshot_dir.join("topology.json").exists() {
+            return Err(format!("Snapshot {} does not exist.", name));
+        }
+        std::fs::remove_dir_all(snapshot_dir)
+            .map_err(|e| format!("Failed to delete snapshot {}: {}", name, e))
+    }
+
     pub async fn list_http_gateways(&self) -> Vec<HttpGatewayDetails> {
         self.http_gateways
             .read()
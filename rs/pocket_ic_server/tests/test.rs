@@ -1,13 +1,14 @@
 mod common;
 
 use crate::common::raw_canister_id_range_into;
-use candid::{Encode, Principal};
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
 use ic_agent::agent::{http_transport::ReqwestTransport, CallResponse};
 use ic_management_canister_types::ProvisionalCreateCanisterWithCyclesArgs;
 use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
 use ic_utils::interfaces::ManagementCanister;
 use pocket_ic::common::rest::{
-    HttpGatewayBackend, HttpGatewayDetails, HttpsConfig, InstanceConfig, SubnetConfigSet,
+    HttpGatewayBackend, HttpGatewayDetails, HttpsConfig, InstanceConfig, NnsCanistersConfig,
+    SubnetConfigSet,
 };
 use pocket_ic::{PocketIc, PocketIcBuilder, WasmResult};
 use rcgen::{CertificateParams, KeyPair};
@@ -97,7 +98,9 @@ fn test_creation_of_instance_extended() {
         }
         .into(),
         state_dir: None,
+        snapshot: None,
         nonmainnet_features: false,
+        nns_canisters: None,
     };
     let response = client
         .post(url.join("instances").unwrap())
@@ -911,3 +914,74 @@ fn test_query_stats_live() {
         }
     })
 }
+
+#[derive(CandidType)]
+struct AccountBalanceArgs {
+    account: String,
+}
+
+#[derive(Deserialize)]
+struct Tokens {
+    e8s: u64,
+}
+
+#[derive(Deserialize)]
+struct IcpXdrConversionRate {
+    xdr_permyriad_per_icp: u64,
+}
+
+#[derive(Deserialize)]
+struct IcpXdrConversionRateResponse {
+    data: IcpXdrConversionRate,
+}
+
+#[test]
+fn nns_canisters() {
+    // The account of the anonymous principal.
+    let account = "1c7a48ba6a562aa9eaa2481a9049cdf0433b9738c992d698c31d8abf89cadc79";
+    let pic = PocketIcBuilder::new()
+        .with_nns_canisters(NnsCanistersConfig {
+            initial_balances: vec![(account.to_string(), 1_000_000_000)],
+            xdr_permyriad_per_icp: 42_000,
+            ..Default::default()
+        })
+        .with_application_subnet()
+        .build();
+
+    let topology = pic.topology();
+    assert!(topology.get_sns().is_some());
+
+    let ledger = Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap();
+    assert_eq!(pic.get_subnet(ledger), topology.get_nns());
+    let res = pic
+        .query_call(
+            ledger,
+            Principal::anonymous(),
+            "account_balance_dfx",
+            Encode!(&AccountBalanceArgs {
+                account: account.to_string(),
+            })
+            .unwrap(),
+        )
+        .unwrap();
+    let balance = match res {
+        WasmResult::Reply(data) => Decode!(&data, Tokens).unwrap(),
+        WasmResult::Reject(err) => panic!("Unexpected reject: {}", err),
+    };
+    assert_eq!(balance.e8s, 1_000_000_000);
+
+    let cmc = Principal::from_text("rkp4c-7iaaa-aaaaa-aaaca-cai").unwrap();
+    let res = pic
+        .query_call(
+            cmc,
+            Principal::anonymous(),
+            "get_icp_xdr_conversion_rate",
+            Encode!(&()).unwrap(),
+        )
+        .unwrap();
+    let rate = match res {
+        WasmResult::Reply(data) => Decode!(&data, IcpXdrConversionRateResponse).unwrap(),
+        WasmResult::Reject(err) => panic!("Unexpected reject: {}", err),
+    };
+    assert_eq!(rate.data.xdr_permyriad_per_icp, 42_000);
+}
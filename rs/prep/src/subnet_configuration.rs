@@ -318,6 +318,7 @@ impl SubnetConfig {
             ssh_backup_access: self.ssh_backup_access,
             ecdsa_config: None,
             chain_key_config: self.chain_key_config,
+            idle_block_config: None,
         };
 
         let dkg_dealing_encryption_pubkeys: BTreeMap<_, _> = initialized_nodes
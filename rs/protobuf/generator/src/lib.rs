@@ -229,6 +229,10 @@ fn build_registry_proto(def: &Path, out: &Path) {
         ".registry.subnet.v1.SubnetFeatures",
         "#[derive(candid::CandidType, Eq)]",
     );
+    config.type_attribute(
+        ".registry.subnet.v1.IdleBlockConfig",
+        "#[derive(candid::CandidType, Eq)]",
+    );
     config.type_attribute(
         ".registry.replica_version",
         "#[derive(serde::Serialize, serde::Deserialize)]",
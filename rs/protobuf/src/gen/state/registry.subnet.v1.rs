@@ -77,6 +77,11 @@ pub struct SubnetRecord {
     /// key. If the removed key is not held by another subnet, it will be lost.
     #[prost(message, optional, tag = "29")]
     pub chain_key_config: ::core::option::Option<ChainKeyConfig>,
+    /// If set, block makers slow down the block rate while the subnet is idle, i.e. when the
+    /// recent finalized blocks carried no payload. If not set, blocks are made at the regular rate.
+    /// Subnets holding threshold keys always make blocks at the regular rate.
+    #[prost(message, optional, tag = "30")]
+    pub idle_block_config: ::core::option::Option<IdleBlockConfig>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -349,6 +354,29 @@ pub struct KeyConfig {
     /// The maximum number of signature requests that can be enqueued at once.
     #[prost(uint32, optional, tag = "4")]
     pub max_queue_size: ::core::option::Option<u32>,
+    /// Minimum number of pre-signatures to keep available or in creation when
+    /// following the demand for signatures. Defaults to
+    /// `pre_signatures_to_create_in_advance`.
+    #[prost(uint32, optional, tag = "5")]
+    pub min_pre_signatures_to_create: ::core::option::Option<u32>,
+    /// Maximum number of pre-signatures to keep available or in creation when
+    /// following the demand for signatures. Defaults to
+    /// `pre_signatures_to_create_in_advance`, i.e. a fixed number of pre-signatures.
+    #[prost(uint32, optional, tag = "6")]
+    pub max_pre_signatures_to_create: ::core::option::Option<u32>,
+}
+/// Configuration of the reduced block rate on idle subnets.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IdleBlockConfig {
+    /// Number of consecutive finalized blocks with an empty payload after which the subnet
+    /// is considered idle.
+    #[prost(uint64, tag = "1")]
+    pub empty_blocks_threshold: u64,
+    /// Delay (in milliseconds) since the start of a round after which an empty block
+    /// is proposed on an idle subnet. Only has an effect if larger than the initial notary delay.
+    #[prost(uint64, tag = "2")]
+    pub idle_block_delay_millis: u64,
 }
 /// Per-subnet chain key configuration
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -596,6 +596,35 @@ pub struct SnapshotId {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FunctionProfile {
+    #[prost(uint32, tag = "1")]
+    pub function_index: u32,
+    #[prost(uint64, tag = "2")]
+    pub calls: u64,
+    #[prost(uint64, tag = "3")]
+    pub inclusive_instructions: u64,
+    #[prost(uint64, tag = "4")]
+    pub exclusive_instructions: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CallStackProfile {
+    /// Function indices of the call stack, outermost frame first.
+    #[prost(uint32, repeated, tag = "1")]
+    pub function_indices: ::prost::alloc::vec::Vec<u32>,
+    #[prost(uint64, tag = "2")]
+    pub instructions: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterProfile {
+    #[prost(message, repeated, tag = "1")]
+    pub functions: ::prost::alloc::vec::Vec<FunctionProfile>,
+    #[prost(message, repeated, tag = "2")]
+    pub call_stacks: ::prost::alloc::vec::Vec<CallStackProfile>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CanisterStateBits {
     #[prost(uint64, tag = "2")]
     pub last_full_execution_round: u64,
@@ -703,6 +732,15 @@ pub struct CanisterStateBits {
     pub long_execution_mode: i32,
     #[prost(uint64, optional, tag = "50")]
     pub wasm_memory_threshold: ::core::option::Option<u64>,
+    /// Environment variables of the canister, set via canister settings.
+    #[prost(btree_map = "string, string", tag = "53")]
+    pub environment_variables: ::prost::alloc::collections::BTreeMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// Execution profile of the canister. Only set if profiling is enabled.
+    #[prost(message, optional, tag = "54")]
+    pub canister_profile: ::core::option::Option<CanisterProfile>,
     #[prost(oneof = "canister_state_bits::CanisterStatus", tags = "11, 12, 13")]
     pub canister_status: ::core::option::Option<canister_state_bits::CanisterStatus>,
 }
@@ -145,6 +145,18 @@ pub struct SignWithThresholdContext {
     pub height: ::core::option::Option<u64>,
     #[prost(bytes = "vec", optional, tag = "8")]
     pub nonce: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(message, optional, tag = "9")]
+    pub batch: ::core::option::Option<SignatureBatchEntry>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SignatureBatchEntry {
+    #[prost(uint64, tag = "1")]
+    pub batch_id: u64,
+    #[prost(uint32, tag = "2")]
+    pub index: u32,
+    #[prost(uint32, tag = "3")]
+    pub size: u32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -156,6 +168,30 @@ pub struct SignWithThresholdContextTree {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SignWithThresholdBatchReply {
+    #[prost(uint64, tag = "1")]
+    pub batch_id: u64,
+    #[prost(uint32, tag = "2")]
+    pub index: u32,
+    #[prost(
+        oneof = "sign_with_threshold_batch_reply::ResponsePayload",
+        tags = "3, 4"
+    )]
+    pub response_payload: ::core::option::Option<sign_with_threshold_batch_reply::ResponsePayload>,
+}
+/// Nested message and enum types in `SignWithThresholdBatchReply`.
+pub mod sign_with_threshold_batch_reply {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum ResponsePayload {
+        #[prost(bytes, tag = "3")]
+        Data(::prost::alloc::vec::Vec<u8>),
+        #[prost(message, tag = "4")]
+        Reject(super::super::super::queues::v1::RejectContext),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct HttpHeader {
     #[prost(string, tag = "1")]
     pub name: ::prost::alloc::string::String,
@@ -332,6 +368,43 @@ pub struct StopCanisterCallTree {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterMigrationContext {
+    #[prost(message, optional, tag = "3")]
+    pub time: ::core::option::Option<Time>,
+    #[prost(message, optional, tag = "4")]
+    pub canister_id: ::core::option::Option<super::super::super::types::v1::CanisterId>,
+    #[prost(message, optional, tag = "5")]
+    pub target_subnet_id: ::core::option::Option<super::super::super::types::v1::SubnetId>,
+    #[prost(uint64, tag = "6")]
+    pub step: u64,
+    #[prost(message, optional, tag = "7")]
+    pub sent_cycles_balance: ::core::option::Option<super::super::queues::v1::Cycles>,
+    #[prost(message, optional, tag = "8")]
+    pub sent_reserved_balance: ::core::option::Option<super::super::queues::v1::Cycles>,
+    #[prost(oneof = "canister_migration_context::CanisterCall", tags = "1, 2")]
+    pub canister_call: ::core::option::Option<canister_migration_context::CanisterCall>,
+}
+/// Nested message and enum types in `CanisterMigrationContext`.
+pub mod canister_migration_context {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum CanisterCall {
+        #[prost(message, tag = "1")]
+        Request(super::super::super::queues::v1::Request),
+        #[prost(message, tag = "2")]
+        Ingress(super::super::super::ingress::v1::Ingress),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterMigrationContextTree {
+    #[prost(uint64, tag = "1")]
+    pub callback_id: u64,
+    #[prost(message, optional, tag = "2")]
+    pub context: ::core::option::Option<CanisterMigrationContext>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RawRandContext {
     #[prost(message, optional, tag = "1")]
     pub request: ::core::option::Option<super::super::queues::v1::Request>,
@@ -371,6 +444,10 @@ pub struct SubnetCallContextManager {
     pub idkg_dealings_contexts: ::prost::alloc::vec::Vec<IDkgDealingsContextTree>,
     #[prost(message, repeated, tag = "18")]
     pub sign_with_threshold_contexts: ::prost::alloc::vec::Vec<SignWithThresholdContextTree>,
+    #[prost(message, repeated, tag = "19")]
+    pub sign_with_threshold_batch_replies: ::prost::alloc::vec::Vec<SignWithThresholdBatchReply>,
+    #[prost(message, repeated, tag = "20")]
+    pub canister_migration_contexts: ::prost::alloc::vec::Vec<CanisterMigrationContextTree>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
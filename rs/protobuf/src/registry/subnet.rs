@@ -35,6 +35,8 @@ impl From<EcdsaConfigPb> for ChainKeyConfigPb {
                     key_id,
                     pre_signatures_to_create_in_advance,
                     max_queue_size,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }
             })
             .collect();
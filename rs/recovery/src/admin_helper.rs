@@ -462,6 +462,8 @@ mod tests {
                     }),
                     pre_signatures_to_create_in_advance: 77,
                     max_queue_size: 30,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 },
                 KeyConfig {
                     key_id: MasterPublicKeyId::Schnorr(SchnorrKeyId {
@@ -470,6 +472,8 @@ mod tests {
                     }),
                     pre_signatures_to_create_in_advance: 12,
                     max_queue_size: 32,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 },
             ],
             signature_request_timeout_ns: Some(123_456),
@@ -10,7 +10,9 @@ use ic_canister_client::{Agent, Sender};
 use ic_management_canister_types::MasterPublicKeyId;
 use ic_nns_common::types::NeuronId;
 use ic_prep_lib::subnet_configuration::get_default_config_params;
-use ic_protobuf::registry::subnet::v1::SubnetFeatures as SubnetFeaturesPb;
+use ic_protobuf::registry::subnet::v1::{
+    IdleBlockConfig as IdleBlockConfigPb, SubnetFeatures as SubnetFeaturesPb,
+};
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
 use ic_types::{NodeId, PrincipalId, ReplicaVersion};
@@ -92,6 +94,12 @@ pub(crate) struct ProposeToCreateSubnetCmd {
     /// key_id: Master public key ID formatted as "Scheme:AlgorithmID:KeyName".
     /// pre_signatures_to_create_in_advance: Non-negative integer value.
     /// max_queue_size: Integer value greater than or equal 1.
+    /// min_pre_signatures_to_create (optional): Minimum number of pre-signatures to keep
+    ///     available or in creation when following the demand for signatures. Defaults to
+    ///     pre_signatures_to_create_in_advance.
+    /// max_pre_signatures_to_create (optional): Maximum number of pre-signatures to keep
+    ///     available or in creation when following the demand for signatures. Defaults to
+    ///     pre_signatures_to_create_in_advance.
     /// subnet_id: Principal ID of a subnet holding the requested key.
     ///
     /// Example (note that all values, including integers, are represented as strings):
@@ -140,6 +148,18 @@ pub(crate) struct ProposeToCreateSubnetCmd {
     #[clap(long)]
     pub max_number_of_canisters: Option<u64>,
 
+    /// Configuration of the reduced block rate on idle subnets:
+    /// number of consecutive empty blocks after which the subnet is idle.
+    /// Must be specified together with `--idle-block-delay-millis`.
+    #[clap(long)]
+    pub idle_empty_blocks_threshold: Option<u64>,
+
+    /// Configuration of the reduced block rate on idle subnets:
+    /// delay in milliseconds after which an empty block is proposed on an idle subnet.
+    /// Must be specified together with `--idle-empty-blocks-threshold`.
+    #[clap(long)]
+    pub idle_block_delay_millis: Option<u64>,
+
     /// The features that are enabled and disabled on the subnet.
     #[clap(long)]
     pub features: Option<SubnetFeatures>,
@@ -183,10 +203,20 @@ fn parse_key_config_requests_option(
                 .map(|x| x.parse::<u32>().expect("max_queue_size must be a u32"))
                 .expect("Each element of the JSON object must specify a 'max_queue_size'."));
 
+            let min_pre_signatures_to_create = btree
+                .get("min_pre_signatures_to_create")
+                .map(|x| x.parse::<u32>().expect("min_pre_signatures_to_create must be a u32."));
+
+            let max_pre_signatures_to_create = btree
+                .get("max_pre_signatures_to_create")
+                .map(|x| x.parse::<u32>().expect("max_pre_signatures_to_create must be a u32."));
+
             let key_config = Some(do_create_subnet::KeyConfig {
                 key_id,
                 pre_signatures_to_create_in_advance,
-                max_queue_size
+                max_queue_size,
+                min_pre_signatures_to_create,
+                max_pre_signatures_to_create,
             });
 
             do_create_subnet::KeyConfigRequest { key_config, subnet_id }
@@ -259,6 +289,23 @@ impl ProposeToCreateSubnetCmd {
             })
         };
 
+        let idle_block_config = match (
+            self.idle_empty_blocks_threshold,
+            self.idle_block_delay_millis,
+        ) {
+            (None, None) => None,
+            (Some(empty_blocks_threshold), Some(idle_block_delay_millis)) => {
+                Some(IdleBlockConfigPb {
+                    empty_blocks_threshold,
+                    idle_block_delay_millis,
+                })
+            }
+            _ => panic!(
+                "--idle-empty-blocks-threshold and --idle-block-delay-millis must be \
+                    specified together."
+            ),
+        };
+
         do_create_subnet::CreateSubnetPayload {
             node_ids,
             subnet_id_override: self.subnet_id_override,
@@ -283,6 +330,7 @@ impl ProposeToCreateSubnetCmd {
             ssh_backup_access: self.ssh_backup_access.clone(),
             max_number_of_canisters: self.max_number_of_canisters.unwrap_or_default(),
             chain_key_config,
+            idle_block_config,
 
             // Deprecated fields.
             ecdsa_config: None,
@@ -351,6 +399,8 @@ mod tests {
             signature_request_timeout_ns: None,
             idkg_key_rotation_period_ms: None,
             max_number_of_canisters: None,
+            idle_empty_blocks_threshold: None,
+            idle_block_delay_millis: None,
             features: None,
         }
     }
@@ -401,6 +451,8 @@ mod tests {
                                 })),
                                 pre_signatures_to_create_in_advance: Some(99),
                                 max_queue_size: Some(155),
+                                min_pre_signatures_to_create: None,
+                                max_pre_signatures_to_create: None,
                             }),
                             subnet_id: Some(
                                 PrincipalId::from_str("gxevo-lhkam-aaaaa-aaaap-yai").unwrap()
@@ -414,6 +466,8 @@ mod tests {
                                 })),
                                 pre_signatures_to_create_in_advance: Some(98),
                                 max_queue_size: Some(154),
+                                min_pre_signatures_to_create: None,
+                                max_pre_signatures_to_create: None,
                             }),
                             subnet_id: Some(
                                 PrincipalId::from_str("gxevo-lhkam-aaaaa-aaaap-yai").unwrap()
@@ -61,6 +61,12 @@ pub(crate) struct ProposeToUpdateRecoveryCupCmd {
     /// key_id: Master public key ID formatted as "Scheme:AlgorithmID:KeyName".
     /// pre_signatures_to_create_in_advance: Non-negative integer value.
     /// max_queue_size: Integer value greater than or equal 1.
+    /// min_pre_signatures_to_create (optional): Minimum number of pre-signatures to keep
+    ///     available or in creation when following the demand for signatures. Defaults to
+    ///     pre_signatures_to_create_in_advance.
+    /// max_pre_signatures_to_create (optional): Maximum number of pre-signatures to keep
+    ///     available or in creation when following the demand for signatures. Defaults to
+    ///     pre_signatures_to_create_in_advance.
     /// subnet_id: Principal ID of a subnet holding the requested key.
     ///
     /// Example (note that all values, including integers, are represented as strings):
@@ -148,10 +154,20 @@ fn parse_key_config_requests_option(
                 .map(|x| x.parse::<u32>().expect("max_queue_size must be a u32"))
                 .expect("Each element of the JSON object must specify a 'max_queue_size'."));
 
+            let min_pre_signatures_to_create = btree
+                .get("min_pre_signatures_to_create")
+                .map(|x| x.parse::<u32>().expect("min_pre_signatures_to_create must be a u32."));
+
+            let max_pre_signatures_to_create = btree
+                .get("max_pre_signatures_to_create")
+                .map(|x| x.parse::<u32>().expect("max_pre_signatures_to_create must be a u32."));
+
             let key_config = Some(do_recover_subnet::KeyConfig {
                 key_id,
                 pre_signatures_to_create_in_advance,
-                max_queue_size
+                max_queue_size,
+                min_pre_signatures_to_create,
+                max_pre_signatures_to_create,
             });
 
             do_recover_subnet::KeyConfigRequest { key_config, subnet_id }
@@ -322,6 +338,8 @@ mod tests {
                                 })),
                                 pre_signatures_to_create_in_advance: Some(99),
                                 max_queue_size: Some(155),
+                                min_pre_signatures_to_create: None,
+                                max_pre_signatures_to_create: None,
                             }),
                             subnet_id: Some(
                                 PrincipalId::from_str("gxevo-lhkam-aaaaa-aaaap-yai").unwrap()
@@ -335,6 +353,8 @@ mod tests {
                                 })),
                                 pre_signatures_to_create_in_advance: Some(98),
                                 max_queue_size: Some(154),
+                                min_pre_signatures_to_create: None,
+                                max_pre_signatures_to_create: None,
                             }),
                             subnet_id: Some(
                                 PrincipalId::from_str("gxevo-lhkam-aaaaa-aaaap-yai").unwrap()
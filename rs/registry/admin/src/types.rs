@@ -9,7 +9,7 @@ use ic_nns_governance_api::pb::v1::ProposalActionRequest;
 use ic_protobuf::registry::{
     node::v1::IPv4InterfaceConfig,
     provisional_whitelist::v1::ProvisionalWhitelist as ProvisionalWhitelistProto,
-    subnet::v1::SubnetRecord as SubnetRecordProto,
+    subnet::v1::{IdleBlockConfig as IdleBlockConfigProto, SubnetRecord as SubnetRecordProto},
 };
 use ic_registry_nns_data_provider::registry::RegistryCanister;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
@@ -75,6 +75,7 @@ pub(crate) struct SubnetRecord {
     pub ssh_backup_access: Vec<String>,
     pub ecdsa_config: Option<EcdsaConfig>,
     pub chain_key_config: Option<ChainKeyConfig>,
+    pub idle_block_config: Option<IdleBlockConfigProto>,
 }
 
 impl SubnetRecord {
@@ -130,6 +131,7 @@ impl From<&SubnetRecordProto> for SubnetRecord {
                 .chain_key_config
                 .as_ref()
                 .map(|c| c.clone().try_into().unwrap()),
+            idle_block_config: value.idle_block_config.clone(),
         }
     }
 }
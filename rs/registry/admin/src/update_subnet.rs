@@ -10,6 +10,7 @@ use ic_admin_derive::derive_common_proposal_fields;
 use ic_canister_client::{Agent, Sender};
 use ic_management_canister_types::MasterPublicKeyId;
 use ic_nns_common::types::NeuronId;
+use ic_protobuf::registry::subnet::v1::IdleBlockConfig as IdleBlockConfigPb;
 use ic_registry_nns_data_provider::registry::RegistryCanister;
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_types::SubnetId;
@@ -92,6 +93,12 @@ pub(crate) struct ProposeToUpdateSubnetCmd {
     /// key_id: master public key ID formatted as "Scheme:AlgorithmID:KeyName".
     /// pre_signatures_to_create_in_advance: Non-negative integer value.
     /// max_queue_size: integer value greater than or equal 1.
+    /// min_pre_signatures_to_create (optional): Minimum number of pre-signatures to keep
+    ///     available or in creation when following the demand for signatures. Defaults to
+    ///     pre_signatures_to_create_in_advance.
+    /// max_pre_signatures_to_create (optional): Maximum number of pre-signatures to keep
+    ///     available or in creation when following the demand for signatures. Defaults to
+    ///     pre_signatures_to_create_in_advance.
     ///
     /// Example (note that all values, including integers, are represented as strings):
     ///
@@ -158,6 +165,20 @@ pub(crate) struct ProposeToUpdateSubnetCmd {
     /// of this field.
     #[clap(long)]
     pub max_number_of_canisters: Option<u64>,
+
+    /// Configuration of the reduced block rate on idle subnets:
+    /// number of consecutive empty blocks after which the subnet is idle.
+    /// If the subnet has no such configuration yet, `--idle-block-delay-millis`
+    /// must be specified as well.
+    #[clap(long)]
+    pub idle_empty_blocks_threshold: Option<u64>,
+
+    /// Configuration of the reduced block rate on idle subnets:
+    /// delay in milliseconds after which an empty block is proposed on an idle subnet.
+    /// If the subnet has no such configuration yet, `--idle-empty-blocks-threshold`
+    /// must be specified as well.
+    #[clap(long)]
+    pub idle_block_delay_millis: Option<u64>,
 }
 
 impl ProposalTitle for ProposeToUpdateSubnetCmd {
@@ -202,7 +223,21 @@ fn parse_chain_key_configs_option(
                 .map(|x| x.parse::<u32>().expect("max_queue_size must be a u32"))
                 .expect("Each element of the JSON object must specify a 'max_queue_size'."));
 
-            do_update_subnet::KeyConfig { key_id, pre_signatures_to_create_in_advance, max_queue_size }
+            let min_pre_signatures_to_create = btree
+                .get("min_pre_signatures_to_create")
+                .map(|x| x.parse::<u32>().expect("min_pre_signatures_to_create must be a u32."));
+
+            let max_pre_signatures_to_create = btree
+                .get("max_pre_signatures_to_create")
+                .map(|x| x.parse::<u32>().expect("max_pre_signatures_to_create must be a u32."));
+
+            do_update_subnet::KeyConfig {
+                key_id,
+                pre_signatures_to_create_in_advance,
+                max_queue_size,
+                min_pre_signatures_to_create,
+                max_pre_signatures_to_create,
+            }
         })
         .collect()
 }
@@ -295,6 +330,24 @@ impl ProposeToUpdateSubnetCmd {
             }
         }
 
+        let idle_block_config = if self.idle_empty_blocks_threshold.is_none()
+            && self.idle_block_delay_millis.is_none()
+        {
+            None
+        } else {
+            let current = subnet_record.idle_block_config.as_ref();
+            Some(IdleBlockConfigPb {
+                empty_blocks_threshold: self
+                    .idle_empty_blocks_threshold
+                    .or(current.map(|c| c.empty_blocks_threshold))
+                    .expect("--idle-empty-blocks-threshold must be specified."),
+                idle_block_delay_millis: self
+                    .idle_block_delay_millis
+                    .or(current.map(|c| c.idle_block_delay_millis))
+                    .expect("--idle-block-delay-millis must be specified."),
+            })
+        };
+
         do_update_subnet::UpdateSubnetPayload {
             subnet_id,
             max_ingress_bytes_per_message: self.max_ingress_bytes_per_message,
@@ -317,6 +370,7 @@ impl ProposeToUpdateSubnetCmd {
             ssh_readonly_access: self.ssh_readonly_access.clone(),
             ssh_backup_access: self.ssh_backup_access.clone(),
             max_number_of_canisters: self.max_number_of_canisters,
+            idle_block_config,
 
             chain_key_config,
             chain_key_signing_enable,
@@ -387,6 +441,7 @@ mod tests {
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            idle_block_config: None,
             chain_key_config: None,
             chain_key_signing_enable: None,
             chain_key_signing_disable: None,
@@ -423,6 +478,8 @@ mod tests {
             ssh_readonly_access: None,
             ssh_backup_access: None,
             max_number_of_canisters: None,
+            idle_empty_blocks_threshold: None,
+            idle_block_delay_millis: None,
         }
     }
 
@@ -440,6 +497,8 @@ mod tests {
                         }),
                         pre_signatures_to_create_in_advance: 555,
                         max_queue_size: 444,
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: Some(500),
                     },
                     KeyConfig {
                         key_id: MasterPublicKeyId::Ecdsa(EcdsaKeyId {
@@ -448,6 +507,8 @@ mod tests {
                         }),
                         pre_signatures_to_create_in_advance: 999,
                         max_queue_size: 888,
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: None,
                     },
                 ],
                 signature_request_timeout_ns: Some(111_111),
@@ -465,7 +526,9 @@ mod tests {
             {
                 "key_id": "schnorr:Bip340Secp256k1:some_key_name_2",
                 "pre_signatures_to_create_in_advance": "98",
-                "max_queue_size": "154"
+                "max_queue_size": "154",
+                "min_pre_signatures_to_create": "10",
+                "max_pre_signatures_to_create": "150"
             }]"#
         .to_string();
         let chain_key_configs_to_generate = Some(chain_key_configs_to_generate);
@@ -502,6 +565,8 @@ mod tests {
                             })),
                             pre_signatures_to_create_in_advance: Some(99),
                             max_queue_size: Some(155),
+                            min_pre_signatures_to_create: None,
+                            max_pre_signatures_to_create: None,
                         },
                         // Existed before, now being enabled.
                         do_update_subnet::KeyConfig {
@@ -511,6 +576,8 @@ mod tests {
                             })),
                             pre_signatures_to_create_in_advance: Some(555),
                             max_queue_size: Some(444),
+                            min_pre_signatures_to_create: None,
+                            max_pre_signatures_to_create: Some(500),
                         },
                         // Note that `some_key_name_4` is still here, although it is being disabled.
                         do_update_subnet::KeyConfig {
@@ -520,6 +587,8 @@ mod tests {
                             })),
                             pre_signatures_to_create_in_advance: Some(999),
                             max_queue_size: Some(888),
+                            min_pre_signatures_to_create: None,
+                            max_pre_signatures_to_create: None,
                         },
                         // Another new config, now being added.
                         do_update_subnet::KeyConfig {
@@ -529,6 +598,8 @@ mod tests {
                             })),
                             pre_signatures_to_create_in_advance: Some(98),
                             max_queue_size: Some(154),
+                            min_pre_signatures_to_create: Some(10),
+                            max_pre_signatures_to_create: Some(150),
                         },
                     ],
                     signature_request_timeout_ns: Some(222_222),
@@ -593,6 +664,8 @@ mod tests {
                             })),
                             pre_signatures_to_create_in_advance: Some(99),
                             max_queue_size: Some(155),
+                            min_pre_signatures_to_create: None,
+                            max_pre_signatures_to_create: None,
                         },
                         do_update_subnet::KeyConfig {
                             key_id: Some(MasterPublicKeyId::Schnorr(SchnorrKeyId {
@@ -601,6 +674,8 @@ mod tests {
                             })),
                             pre_signatures_to_create_in_advance: Some(98),
                             max_queue_size: Some(154),
+                            min_pre_signatures_to_create: None,
+                            max_pre_signatures_to_create: None,
                         },
                     ],
                     signature_request_timeout_ns: Some(111),
@@ -624,6 +699,8 @@ mod tests {
                     }),
                     pre_signatures_to_create_in_advance: 111_111,
                     max_queue_size: 222_222,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 signature_request_timeout_ns: Some(888_888),
                 idkg_key_rotation_period_ms: Some(999_999),
@@ -669,6 +746,8 @@ mod tests {
                             })),
                             pre_signatures_to_create_in_advance: Some(111),
                             max_queue_size: Some(222),
+                            min_pre_signatures_to_create: None,
+                            max_pre_signatures_to_create: None,
                         },
                         // New config, now being added.
                         do_update_subnet::KeyConfig {
@@ -678,6 +757,8 @@ mod tests {
                             })),
                             pre_signatures_to_create_in_advance: Some(333),
                             max_queue_size: Some(444),
+                            min_pre_signatures_to_create: None,
+                            max_pre_signatures_to_create: None,
                         },
                     ],
                     signature_request_timeout_ns: Some(888),
@@ -687,4 +768,44 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn cli_to_payload_conversion_works_for_editing_existing_idle_block_config() {
+        let subnet_id = SubnetId::from(PrincipalId::new_user_test_id(1));
+        let subnet_record = SubnetRecord {
+            idle_block_config: Some(IdleBlockConfigPb {
+                empty_blocks_threshold: 10,
+                idle_block_delay_millis: 5_000,
+            }),
+            ..Default::default()
+        };
+
+        let cmd = ProposeToUpdateSubnetCmd {
+            idle_block_delay_millis: Some(3_000),
+            ..empty_propose_to_update_subnet_cmd(subnet_id)
+        };
+
+        assert_eq!(
+            cmd.new_payload_for_subnet(subnet_id, subnet_record),
+            do_update_subnet::UpdateSubnetPayload {
+                idle_block_config: Some(IdleBlockConfigPb {
+                    empty_blocks_threshold: 10,
+                    idle_block_delay_millis: 3_000,
+                }),
+                ..make_empty_update_payload(subnet_id)
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "--idle-empty-blocks-threshold must be specified.")]
+    fn cli_to_payload_conversion_requires_complete_new_idle_block_config() {
+        let subnet_id = SubnetId::from(PrincipalId::new_user_test_id(1));
+        let cmd = ProposeToUpdateSubnetCmd {
+            idle_block_delay_millis: Some(3_000),
+            ..empty_propose_to_update_subnet_cmd(subnet_id)
+        };
+
+        cmd.new_payload_for_subnet(subnet_id, SubnetRecord::default());
+    }
 }
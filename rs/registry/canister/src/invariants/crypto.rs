@@ -302,6 +302,22 @@ fn check_chain_key_configs(snapshot: &RegistrySnapshot) -> Result<(), InvariantC
                     source: None,
                 });
             }
+            let min_pre_signatures = key_config
+                .min_pre_signatures_to_create
+                .unwrap_or(key_config.pre_signatures_to_create_in_advance);
+            let max_pre_signatures = key_config
+                .max_pre_signatures_to_create
+                .unwrap_or(key_config.pre_signatures_to_create_in_advance);
+            if min_pre_signatures > max_pre_signatures {
+                return Err(InvariantCheckError {
+                    msg: format!(
+                        "The minimum number of pre-signatures to create ({}) of subnet {:} \
+                         exceeds the maximum ({}).",
+                        min_pre_signatures, subnet_id, max_pre_signatures,
+                    ),
+                    source: None,
+                });
+            }
             if !key_ids.insert(key_config.key_id.clone()) {
                 return Err(InvariantCheckError {
                     msg: format!(
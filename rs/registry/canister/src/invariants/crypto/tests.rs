@@ -631,6 +631,8 @@ mod ecdsa_signing_subnet_lists {
                     }),
                     pre_signatures_to_create_in_advance: Some(456),
                     max_queue_size: Some(100),
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 },
                 KeyConfigPb {
                     key_id: Some(MasterPublicKeyIdPb {
@@ -641,6 +643,8 @@ mod ecdsa_signing_subnet_lists {
                     }),
                     pre_signatures_to_create_in_advance: Some(456),
                     max_queue_size: Some(100),
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 },
             ],
             signature_request_timeout_ns: Some(10_000),
@@ -688,6 +692,24 @@ mod ecdsa_signing_subnet_lists {
         check_chain_key_config_invariant(config);
     }
 
+    #[test]
+    fn should_succeed_with_pre_signature_bounds() {
+        let mut config = invariant_compliant_chain_key_config();
+        config.key_configs[0].min_pre_signatures_to_create = Some(10);
+        config.key_configs[0].max_pre_signatures_to_create = Some(1000);
+        check_chain_key_config_invariant(config);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The minimum number of pre-signatures to create (456) of subnet ya35z-hhham-aaaaa-aaaap-yai exceeds the maximum (10)."
+    )]
+    fn should_fail_if_min_pre_signatures_exceeds_max() {
+        let mut config = invariant_compliant_chain_key_config();
+        config.key_configs[0].max_pre_signatures_to_create = Some(10);
+        check_chain_key_config_invariant(config);
+    }
+
     #[test]
     #[should_panic(expected = "Missing required struct field: KeyConfig::max_queue_size")]
     fn should_fail_if_missing_queue_size() {
@@ -1018,6 +1040,8 @@ mod ecdsa_signing_subnet_lists {
                             key_id: key_id.clone(),
                             pre_signatures_to_create_in_advance: Default::default(),
                             max_queue_size: Default::default(),
+                            min_pre_signatures_to_create: None,
+                            max_pre_signatures_to_create: None,
                         })
                         .collect();
                     let ecdsa_config = ChainKeyConfig {
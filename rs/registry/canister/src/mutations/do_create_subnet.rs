@@ -12,7 +12,7 @@ use ic_protobuf::registry::{
     node::v1::NodeRecord,
     subnet::v1::{
         CatchUpPackageContents, ChainKeyConfig as ChainKeyConfigPb, EcdsaConfig as EcdsaConfigPb,
-        SubnetFeatures as SubnetFeaturesPb, SubnetRecord,
+        IdleBlockConfig as IdleBlockConfigPb, SubnetFeatures as SubnetFeaturesPb, SubnetRecord,
     },
 };
 use ic_registry_keys::{
@@ -318,6 +318,8 @@ pub struct CreateSubnetPayload {
     pub ssh_readonly_access: Vec<String>,
     pub ssh_backup_access: Vec<String>,
 
+    pub idle_block_config: Option<IdleBlockConfigPb>,
+
     // Deprecated. Please use `chain_key_config` instead.
     //
     // TODO[NNS1-3022]: Make this field obsolete.
@@ -414,6 +416,8 @@ pub struct KeyConfig {
     pub key_id: Option<MasterPublicKeyId>,
     pub pre_signatures_to_create_in_advance: Option<u32>,
     pub max_queue_size: Option<u32>,
+    pub min_pre_signatures_to_create: Option<u32>,
+    pub max_pre_signatures_to_create: Option<u32>,
 }
 
 impl From<KeyConfigInternal> for KeyConfig {
@@ -422,12 +426,16 @@ impl From<KeyConfigInternal> for KeyConfig {
             key_id,
             pre_signatures_to_create_in_advance,
             max_queue_size,
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         } = src;
 
         Self {
             key_id: Some(key_id),
             pre_signatures_to_create_in_advance: Some(pre_signatures_to_create_in_advance),
             max_queue_size: Some(max_queue_size),
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         }
     }
 }
@@ -440,6 +448,8 @@ impl TryFrom<KeyConfig> for KeyConfigInternal {
             key_id,
             pre_signatures_to_create_in_advance,
             max_queue_size,
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         } = src;
 
         let Some(key_id) = key_id else {
@@ -460,6 +470,8 @@ impl TryFrom<KeyConfig> for KeyConfigInternal {
             key_id,
             pre_signatures_to_create_in_advance,
             max_queue_size,
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         })
     }
 }
@@ -538,6 +550,8 @@ impl TryFrom<EcdsaInitialConfig> for InitialChainKeyConfigInternal {
                         key_id: MasterPublicKeyId::Ecdsa(key_id),
                         pre_signatures_to_create_in_advance,
                         max_queue_size,
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: None,
                     },
                     subnet_id,
                 })
@@ -642,6 +656,7 @@ impl From<CreateSubnetPayload> for SubnetRecord {
                 })
                 .map(ChainKeyConfigPb::from),
             ecdsa_config: None, // obsolete (chain_key_config is used instead now)
+            idle_block_config: val.idle_block_config,
         }
     }
 }
@@ -658,6 +673,22 @@ mod test {
     use ic_registry_subnet_features::ChainKeyConfig;
     use ic_types::ReplicaVersion;
 
+    #[test]
+    fn subnet_record_takes_idle_block_config_from_payload() {
+        let idle_block_config = IdleBlockConfigPb {
+            empty_blocks_threshold: 5,
+            idle_block_delay_millis: 2_000,
+        };
+        let payload = CreateSubnetPayload {
+            idle_block_config: Some(idle_block_config.clone()),
+            ..Default::default()
+        };
+
+        let subnet_record = SubnetRecord::from(payload);
+
+        assert_eq!(subnet_record.idle_block_config, Some(idle_block_config));
+    }
+
     // Note: this can only be unit-tested b/c it fails before we hit inter-canister calls
     // for DKG + ECDSA
     #[test]
@@ -889,6 +920,8 @@ mod test {
                         key_id: Some(MasterPublicKeyId::Ecdsa(key_id)),
                         pre_signatures_to_create_in_advance: Some(1),
                         max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: None,
                     }),
                     subnet_id: Some(*TEST_USER2_PRINCIPAL),
                 }],
@@ -385,6 +385,8 @@ pub struct KeyConfig {
     pub key_id: Option<MasterPublicKeyId>,
     pub pre_signatures_to_create_in_advance: Option<u32>,
     pub max_queue_size: Option<u32>,
+    pub min_pre_signatures_to_create: Option<u32>,
+    pub max_pre_signatures_to_create: Option<u32>,
 }
 
 impl From<KeyConfigInternal> for KeyConfig {
@@ -393,12 +395,16 @@ impl From<KeyConfigInternal> for KeyConfig {
             key_id,
             pre_signatures_to_create_in_advance,
             max_queue_size,
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         } = src;
 
         Self {
             key_id: Some(key_id),
             pre_signatures_to_create_in_advance: Some(pre_signatures_to_create_in_advance),
             max_queue_size: Some(max_queue_size),
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         }
     }
 }
@@ -411,6 +417,8 @@ impl TryFrom<KeyConfig> for KeyConfigInternal {
             key_id,
             pre_signatures_to_create_in_advance,
             max_queue_size,
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         } = src;
 
         let Some(key_id) = key_id else {
@@ -431,6 +439,8 @@ impl TryFrom<KeyConfig> for KeyConfigInternal {
             key_id,
             pre_signatures_to_create_in_advance,
             max_queue_size,
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         })
     }
 }
@@ -845,6 +855,8 @@ mod test {
                     key_id: Some(MasterPublicKeyId::Ecdsa(key_id)),
                     pre_signatures_to_create_in_advance: Some(1),
                     max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }),
                 subnet_id: Some(subnet_id_holding_key.get()),
             }],
@@ -307,6 +307,8 @@ mod test {
             key_id,
             pre_signatures_to_create_in_advance: 1,
             max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+            min_pre_signatures_to_create: None,
+            max_pre_signatures_to_create: None,
         };
         subnet_record.chain_key_config = Some(ChainKeyConfigPb::from(ChainKeyConfig {
             key_configs: vec![key_config],
@@ -363,6 +365,8 @@ mod test {
             key_id,
             pre_signatures_to_create_in_advance: 1,
             max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+            min_pre_signatures_to_create: None,
+            max_pre_signatures_to_create: None,
         };
         subnet_record.chain_key_config = Some(ChainKeyConfigPb::from(ChainKeyConfig {
             key_configs: vec![key_config],
@@ -429,6 +433,8 @@ mod test {
             key_id,
             pre_signatures_to_create_in_advance: 1,
             max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+            min_pre_signatures_to_create: None,
+            max_pre_signatures_to_create: None,
         };
         subnet_record.chain_key_config = Some(ChainKeyConfigPb::from(ChainKeyConfig {
             key_configs: vec![key_config],
@@ -4,7 +4,8 @@ use dfn_core::println;
 use ic_base_types::{subnet_id_into_protobuf, SubnetId};
 use ic_management_canister_types::{EcdsaKeyId, MasterPublicKeyId};
 use ic_protobuf::registry::subnet::v1::{
-    SubnetFeatures as SubnetFeaturesPb, SubnetRecord as SubnetRecordPb,
+    IdleBlockConfig as IdleBlockConfigPb, SubnetFeatures as SubnetFeaturesPb,
+    SubnetRecord as SubnetRecordPb,
 };
 use ic_registry_keys::{make_chain_key_signing_subnet_list_key, make_subnet_record_key};
 use ic_registry_subnet_features::{
@@ -357,6 +358,8 @@ pub struct UpdateSubnetPayload {
     pub ssh_readonly_access: Option<Vec<String>>,
     pub ssh_backup_access: Option<Vec<String>>,
 
+    pub idle_block_config: Option<IdleBlockConfigPb>,
+
     // TODO(NNS1-2444): The fields below are deprecated and they are not read anywhere.
     pub max_artifact_streams_per_peer: Option<u32>,
     pub max_chunk_wait_ms: Option<u32>,
@@ -391,10 +394,14 @@ impl From<ChainKeyConfigInternal> for ChainKeyConfig {
                      key_id,
                      pre_signatures_to_create_in_advance,
                      max_queue_size,
+                     min_pre_signatures_to_create,
+                     max_pre_signatures_to_create,
                  }| KeyConfig {
                     key_id: Some(key_id),
                     pre_signatures_to_create_in_advance: Some(pre_signatures_to_create_in_advance),
                     max_queue_size: Some(max_queue_size),
+                    min_pre_signatures_to_create,
+                    max_pre_signatures_to_create,
                 },
             )
             .collect();
@@ -449,6 +456,8 @@ pub struct KeyConfig {
     pub key_id: Option<MasterPublicKeyId>,
     pub pre_signatures_to_create_in_advance: Option<u32>,
     pub max_queue_size: Option<u32>,
+    pub min_pre_signatures_to_create: Option<u32>,
+    pub max_pre_signatures_to_create: Option<u32>,
 }
 
 impl From<KeyConfigInternal> for KeyConfig {
@@ -457,12 +466,16 @@ impl From<KeyConfigInternal> for KeyConfig {
             key_id,
             pre_signatures_to_create_in_advance,
             max_queue_size,
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         } = src;
 
         Self {
             key_id: Some(key_id),
             pre_signatures_to_create_in_advance: Some(pre_signatures_to_create_in_advance),
             max_queue_size: Some(max_queue_size),
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         }
     }
 }
@@ -475,6 +488,8 @@ impl TryFrom<KeyConfig> for KeyConfigInternal {
             key_id,
             pre_signatures_to_create_in_advance,
             max_queue_size,
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         } = src;
 
         let Some(key_id) = key_id else {
@@ -493,6 +508,8 @@ impl TryFrom<KeyConfig> for KeyConfigInternal {
             key_id,
             pre_signatures_to_create_in_advance,
             max_queue_size,
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         })
     }
 }
@@ -550,6 +567,7 @@ fn merge_subnet_record(
         max_number_of_canisters,
         ssh_readonly_access,
         ssh_backup_access,
+        idle_block_config,
         // Deprecated/unused values follow
         max_artifact_streams_per_peer: _,
         max_chunk_wait_ms: _,
@@ -601,6 +619,8 @@ fn merge_subnet_record(
     maybe_set!(subnet_record, ssh_readonly_access);
     maybe_set!(subnet_record, ssh_backup_access);
 
+    maybe_set_option!(subnet_record, idle_block_config);
+
     subnet_record
 }
 
@@ -655,6 +675,7 @@ mod tests {
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            idle_block_config: None,
             chain_key_config: None,
             chain_key_signing_enable: None,
             chain_key_signing_disable: None,
@@ -693,6 +714,7 @@ mod tests {
             ssh_backup_access: vec![],
             ecdsa_config: None,
             chain_key_config: None,
+            idle_block_config: None,
         };
 
         let ecdsa_config = Some(EcdsaConfig {
@@ -738,6 +760,10 @@ mod tests {
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+            idle_block_config: Some(IdleBlockConfigPb {
+                empty_blocks_threshold: 5,
+                idle_block_delay_millis: 2_000,
+            }),
             chain_key_config: None,
             chain_key_signing_enable: None,
             chain_key_signing_disable: None,
@@ -779,6 +805,10 @@ mod tests {
                 ),
                 chain_key_config: chain_key_config_pb,
                 ecdsa_config: None, // obsolete (chain_key_config is used instead now)
+                idle_block_config: Some(IdleBlockConfigPb {
+                    empty_blocks_threshold: 5,
+                    idle_block_delay_millis: 2_000,
+                }),
                 max_number_of_canisters: 10,
                 ssh_readonly_access: vec!["pub_key_0".to_string()],
                 ssh_backup_access: vec!["pub_key_1".to_string()],
@@ -808,6 +838,7 @@ mod tests {
             ssh_backup_access: vec![],
             ecdsa_config: None,
             chain_key_config: None,
+            idle_block_config: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -835,6 +866,7 @@ mod tests {
             max_number_of_canisters: Some(50),
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            idle_block_config: None,
             chain_key_config: None,
             chain_key_signing_enable: None,
             chain_key_signing_disable: None,
@@ -871,6 +903,7 @@ mod tests {
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
                 chain_key_config: None,
+                idle_block_config: None,
             }
         );
     }
@@ -1364,6 +1397,8 @@ mod tests {
             key_id: Some(key_id.clone()),
             pre_signatures_to_create_in_advance: Some(1),
             max_queue_size: Some(2),
+            min_pre_signatures_to_create: None,
+            max_pre_signatures_to_create: None,
         };
 
         // Give it the keys.
@@ -1433,6 +1468,8 @@ mod tests {
                     key_id: Some(MasterPublicKeyId::Ecdsa(key_id)),
                     pre_signatures_to_create_in_advance: Some(111),
                     max_queue_size: Some(222),
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 signature_request_timeout_ns: Some(333),
                 idkg_key_rotation_period_ms: Some(444),
@@ -1575,6 +1612,8 @@ mod tests {
                     ))),
                     pre_signatures_to_create_in_advance: Some(111),
                     max_queue_size: Some(222),
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 signature_request_timeout_ns: Some(333),
                 idkg_key_rotation_period_ms: Some(444),
@@ -1626,6 +1665,8 @@ mod tests {
                     key_id: Some(MasterPublicKeyIdPb::from(&key_id)),
                     pre_signatures_to_create_in_advance: Some(111),
                     max_queue_size: Some(222),
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 signature_request_timeout_ns: Some(333),
                 idkg_key_rotation_period_ms: Some(444),
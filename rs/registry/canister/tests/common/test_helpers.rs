@@ -90,6 +90,8 @@ pub fn get_subnet_holding_chain_keys(
                 key_id,
                 pre_signatures_to_create_in_advance: 1,
                 max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             })
             .collect(),
         signature_request_timeout_ns: None,
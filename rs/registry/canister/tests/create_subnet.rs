@@ -267,6 +267,8 @@ fn test_accepted_proposal_with_ecdsa_gets_keys_from_other_subnet_legacy() {
                 key_id: MasterPublicKeyId::Ecdsa(key_1.clone()),
                 pre_signatures_to_create_in_advance: 100,
                 max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }],
             signature_request_timeout_ns: None,
             idkg_key_rotation_period_ms: None,
@@ -360,6 +362,8 @@ fn test_accepted_proposal_with_ecdsa_gets_keys_from_other_subnet_legacy() {
                 key_id: Some(MasterPublicKeyIdPb::from(&MasterPublicKeyId::Ecdsa(key_1))),
                 pre_signatures_to_create_in_advance: Some(101),
                 max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }],
         );
     });
@@ -417,6 +421,8 @@ fn test_accepted_proposal_with_chain_key_gets_keys_from_other_subnet(key_id: Mas
                 key_id: key_id.clone(),
                 pre_signatures_to_create_in_advance: 100,
                 max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }],
             signature_request_timeout_ns: None,
             idkg_key_rotation_period_ms: None,
@@ -456,6 +462,8 @@ fn test_accepted_proposal_with_chain_key_gets_keys_from_other_subnet(key_id: Mas
                         key_id: Some(key_id.clone()),
                         pre_signatures_to_create_in_advance: Some(101),
                         max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: None,
                     }),
                     subnet_id: Some(*system_subnet_principal),
                 }],
@@ -505,6 +513,8 @@ fn test_accepted_proposal_with_chain_key_gets_keys_from_other_subnet(key_id: Mas
                 key_id,
                 pre_signatures_to_create_in_advance: 101,
                 max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }],
         );
     });
@@ -549,6 +559,7 @@ fn make_create_subnet_payload(node_ids: Vec<NodeId>) -> CreateSubnetPayload {
         max_number_of_canisters: 0,
         ssh_readonly_access: vec![],
         ssh_backup_access: vec![],
+        idle_block_config: None,
         ecdsa_config: None,
         chain_key_config: None,
         // Unused section follows
@@ -295,6 +295,8 @@ fn test_recover_subnet_gets_ecdsa_keys_when_needed_legacy() {
                 key_id: MasterPublicKeyId::Ecdsa(key_1.clone()),
                 pre_signatures_to_create_in_advance: 100,
                 max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }],
             signature_request_timeout_ns: None,
             idkg_key_rotation_period_ms: None,
@@ -433,6 +435,8 @@ fn test_recover_subnet_gets_ecdsa_keys_when_needed_legacy() {
                 key_id: Some(MasterPublicKeyIdPb::from(&MasterPublicKeyId::Ecdsa(key_1))),
                 pre_signatures_to_create_in_advance: Some(1),
                 max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }]
         );
     });
@@ -510,6 +514,8 @@ fn test_recover_subnet_gets_chain_keys_when_needed(key_id: MasterPublicKeyId) {
                 key_id: key_id.clone(),
                 pre_signatures_to_create_in_advance: 100,
                 max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }],
             signature_request_timeout_ns: None,
             idkg_key_rotation_period_ms: None,
@@ -599,6 +605,8 @@ fn test_recover_subnet_gets_chain_keys_when_needed(key_id: MasterPublicKeyId) {
                         key_id: Some(key_id.clone()),
                         pre_signatures_to_create_in_advance: Some(1),
                         max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: None,
                     }),
                     subnet_id: Some(system_subnet_id.get()),
                 }],
@@ -646,6 +654,8 @@ fn test_recover_subnet_gets_chain_keys_when_needed(key_id: MasterPublicKeyId) {
                 key_id: Some(MasterPublicKeyIdPb::from(&key_id)),
                 pre_signatures_to_create_in_advance: Some(1),
                 max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }]
         );
     });
@@ -941,6 +951,8 @@ fn test_recover_subnet_without_chain_key_removes_it_from_signing_list(key_id: Ma
                     key_id: Some(MasterPublicKeyIdPb::from(&key_id)),
                     pre_signatures_to_create_in_advance: Some(1),
                     max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 signature_request_timeout_ns: None,
                 idkg_key_rotation_period_ms: None,
@@ -1389,6 +1401,8 @@ fn test_recover_subnet_resets_cup_contents() {
                 key_id: key_id.clone(),
                 pre_signatures_to_create_in_advance: 100,
                 max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }],
             signature_request_timeout_ns: None,
             idkg_key_rotation_period_ms: None,
@@ -1505,6 +1519,8 @@ fn test_recover_subnet_resets_cup_contents() {
                         key_id: Some(key_id.clone()),
                         pre_signatures_to_create_in_advance: Some(1),
                         max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: None,
                     }),
                     subnet_id: Some(system_subnet_id.get()),
                 }],
@@ -1556,6 +1572,8 @@ fn test_recover_subnet_resets_cup_contents() {
                 key_id: Some(MasterPublicKeyIdPb::from(&key_id)),
                 pre_signatures_to_create_in_advance: Some(1),
                 max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }]
         );
     });
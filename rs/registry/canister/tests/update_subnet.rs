@@ -83,6 +83,7 @@ fn test_the_anonymous_user_cannot_update_a_subnets_configuration() {
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+            idle_block_config: None,
             chain_key_config: None,
             chain_key_signing_enable: None,
             chain_key_signing_disable: None,
@@ -163,6 +164,7 @@ fn test_a_canister_other_than_the_governance_canister_cannot_update_a_subnets_co
             ssh_backup_access: vec![],
             ecdsa_config: None,
             chain_key_config: None,
+            idle_block_config: None,
         };
 
         // An attacker got a canister that is trying to pass for the governance
@@ -210,6 +212,7 @@ fn test_a_canister_other_than_the_governance_canister_cannot_update_a_subnets_co
             max_number_of_canisters: Some(100),
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            idle_block_config: None,
             chain_key_config: None,
             chain_key_signing_enable: None,
             chain_key_signing_disable: None,
@@ -289,6 +292,7 @@ fn test_the_governance_canister_can_update_a_subnets_configuration() {
                             ssh_backup_access: vec![],
                             ecdsa_config: None,
                             chain_key_config: None,
+                            idle_block_config: None,
                         }
                         .encode_to_vec(),
                     )],
@@ -328,6 +332,7 @@ fn test_the_governance_canister_can_update_a_subnets_configuration() {
             max_number_of_canisters: Some(42),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+            idle_block_config: None,
             chain_key_config: None,
             chain_key_signing_enable: None,
             chain_key_signing_disable: None,
@@ -383,6 +388,7 @@ fn test_the_governance_canister_can_update_a_subnets_configuration() {
                 ssh_backup_access: vec!["pub_key_1".to_string()],
                 ecdsa_config: None,
                 chain_key_config: None,
+                idle_block_config: None,
             }
         );
 
@@ -437,6 +443,7 @@ fn test_subnets_configuration_ecdsa_fields_are_updated_correctly_legacy() {
             ssh_backup_access: vec![],
             ecdsa_config: None,
             chain_key_config: None,
+            idle_block_config: None,
         };
 
         // Just create the registry canister and wait until the subnet_handler ID is
@@ -686,6 +693,7 @@ fn test_subnets_configuration_chain_key_fields_are_updated_correctly(key_id: Mas
             ssh_backup_access: vec![],
             ecdsa_config: None,
             chain_key_config: None,
+            idle_block_config: None,
         };
 
         // Just create the registry canister and wait until the subnet_handler ID is
@@ -723,6 +731,8 @@ fn test_subnets_configuration_chain_key_fields_are_updated_correctly(key_id: Mas
                 key_id: Some(key_id.clone()),
                 pre_signatures_to_create_in_advance: Some(10),
                 max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             }],
             signature_request_timeout_ns,
             idkg_key_rotation_period_ms,
@@ -898,6 +908,7 @@ fn empty_update_subnet_payload(subnet_id: SubnetId) -> UpdateSubnetPayload {
         max_number_of_canisters: None,
         ssh_readonly_access: None,
         ssh_backup_access: None,
+        idle_block_config: None,
         ecdsa_config: None,
         ecdsa_key_signing_enable: None,
         ecdsa_key_signing_disable: None,
@@ -131,6 +131,8 @@ pub struct KeyConfig {
     pub key_id: MasterPublicKeyId,
     pub pre_signatures_to_create_in_advance: u32,
     pub max_queue_size: u32,
+    pub min_pre_signatures_to_create: Option<u32>,
+    pub max_pre_signatures_to_create: Option<u32>,
 }
 
 impl From<KeyConfig> for pb::KeyConfig {
@@ -139,6 +141,8 @@ impl From<KeyConfig> for pb::KeyConfig {
             key_id,
             pre_signatures_to_create_in_advance,
             max_queue_size,
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         } = src;
 
         let key_id = Some(crypto_pb::MasterPublicKeyId::from(&key_id));
@@ -149,6 +153,8 @@ impl From<KeyConfig> for pb::KeyConfig {
             key_id,
             pre_signatures_to_create_in_advance,
             max_queue_size: Some(max_queue_size),
+            min_pre_signatures_to_create,
+            max_pre_signatures_to_create,
         }
     }
 }
@@ -167,6 +173,8 @@ impl TryFrom<pb::KeyConfig> for KeyConfig {
                 value.max_queue_size,
                 "KeyConfig::max_queue_size",
             )?,
+            min_pre_signatures_to_create: value.min_pre_signatures_to_create,
+            max_pre_signatures_to_create: value.max_pre_signatures_to_create,
         })
     }
 }
@@ -243,6 +251,8 @@ impl From<EcdsaConfig> for ChainKeyConfig {
                 key_id: MasterPublicKeyId::Ecdsa(key_id),
                 pre_signatures_to_create_in_advance: quadruples_to_create_in_advance,
                 max_queue_size: max_queue_size.unwrap_or(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                min_pre_signatures_to_create: None,
+                max_pre_signatures_to_create: None,
             })
             .collect();
 
@@ -303,6 +313,8 @@ mod tests {
                     }),
                     pre_signatures_to_create_in_advance: 77,
                     max_queue_size: 30,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 signature_request_timeout_ns: Some(123_456),
                 idkg_key_rotation_period_ms: Some(321_654),
@@ -338,6 +350,8 @@ mod tests {
                     }),
                     pre_signatures_to_create_in_advance: Some(77),
                     max_queue_size: Some(30),
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }],
                 signature_request_timeout_ns: Some(123_456),
                 idkg_key_rotation_period_ms: Some(321_654),
@@ -356,6 +370,8 @@ mod tests {
                 }),
                 pre_signatures_to_create_in_advance: 77,
                 max_queue_size: 30,
+                min_pre_signatures_to_create: Some(5),
+                max_pre_signatures_to_create: Some(100),
             }],
             signature_request_timeout_ns: Some(123_456),
             idkg_key_rotation_period_ms: Some(321_654),
@@ -376,6 +392,8 @@ mod tests {
                 }),
                 pre_signatures_to_create_in_advance: Some(77),
                 max_queue_size: Some(30),
+                min_pre_signatures_to_create: Some(5),
+                max_pre_signatures_to_create: Some(100),
             }],
             signature_request_timeout_ns: Some(123_456),
             idkg_key_rotation_period_ms: Some(321_654),
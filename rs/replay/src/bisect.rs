@@ -0,0 +1,489 @@
+//! Finds the first height at which replaying the backup with two different
+//! replica binaries or configurations produces different states.
+//!
+//! Each side of the comparison is replayed by a child `ic-replay` process
+//! running the `replay-lockstep` subcommand on its own copy of the initial
+//! state. After each height, a child reports the root hash of the canonical
+//! tree of its state on the standard output and waits for an instruction on
+//! the standard input: either to continue with the next height or to write
+//! the canonical tree to a file and exit. This way both replays advance in
+//! lockstep and each height is only executed once per side. Once the hashes
+//! differ, the trees of both sides are diffed the same way `state_tool cdiff`
+//! diffs checkpoints.
+
+use crate::{
+    cmd::{BisectDeterminismCmd, ReplayLockstepCmd},
+    player::{Player, ReplayError, ReplayResult, StateParams},
+};
+use ic_config::Config;
+use ic_crypto_tree_hash::HashTree;
+use ic_state_manager::tree_diff::{diff, Changes, PrettyPrintedChanges};
+use ic_types::{Height, ReplicaVersion, SubnetId};
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+use tempfile::TempDir;
+
+// Prefix of the lines on which a replay reports the hash of its state.
+const STATE_HASH_PREFIX: &str = "lockstep_state_hash";
+// Instructs a replay to continue with the next height.
+const CONTINUE: &str = "continue";
+// Instructs a replay to write its canonical tree to the given file and exit.
+const DUMP: &str = "dump";
+
+/// One side of the comparison, replaying the backup height by height.
+trait LockstepReplay {
+    /// Returns the next replayed height and the hash of the state at that
+    /// height, or `None` if the replay finished.
+    fn next_state(&mut self) -> Result<Option<(Height, String)>, String>;
+
+    /// Lets the replay continue with the next height.
+    fn resume(&mut self) -> Result<(), String>;
+
+    /// Returns the canonical tree of the state at the last reported height
+    /// and stops the replay.
+    fn dump_tree(&mut self) -> Result<HashTree, String>;
+}
+
+/// Advances both replays in lockstep until the hashes of their states differ.
+/// Returns the first height with different states together with the
+/// differences of the canonical trees, or `None` if both replays finished
+/// with identical states.
+fn find_divergence<A: LockstepReplay, B: LockstepReplay>(
+    a: &mut A,
+    b: &mut B,
+) -> Result<Option<(Height, Changes)>, String> {
+    loop {
+        match (a.next_state()?, b.next_state()?) {
+            (None, None) => return Ok(None),
+            (Some((height, hash)), Some((other_height, other_hash))) => {
+                if height != other_height {
+                    return Err(format!(
+                        "The replays are out of step: heights {} and {}",
+                        height, other_height
+                    ));
+                }
+                if hash != other_hash {
+                    let tree = a.dump_tree()?;
+                    let other_tree = b.dump_tree()?;
+                    return Ok(Some((height, diff(&tree, &other_tree))));
+                }
+                a.resume()?;
+                b.resume()?;
+            }
+            (Some((height, _)), None) | (None, Some((height, _))) => {
+                return Err(format!(
+                    "One of the replays finished before height {}",
+                    height
+                ));
+            }
+        }
+    }
+}
+
+/// A child `ic-replay` process running the `replay-lockstep` subcommand.
+struct ChildReplay {
+    name: &'static str,
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    // Holds the copy of the state the child replays on.
+    tmp_dir: TempDir,
+}
+
+impl ChildReplay {
+    /// Spawns `binary` replaying the backup with the given config on a copy
+    /// of the state found at `state_root`.
+    fn spawn(
+        name: &'static str,
+        binary: &Path,
+        config: &Path,
+        state_root: &Path,
+        cmd: &BisectDeterminismCmd,
+        subnet_id: SubnetId,
+    ) -> Result<Self, String> {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("replay_lockstep_")
+            .tempdir()
+            .map_err(|err| format!("Couldn't create a temporary directory: {}", err))?;
+        copy_dir(state_root, &tmp_dir.path().join("ic_state"))
+            .map_err(|err| format!("Couldn't copy the state from {:?}: {}", state_root, err))?;
+
+        let mut child = Command::new(binary)
+            .arg(config)
+            .arg("--subnet-id")
+            .arg(subnet_id.to_string())
+            .arg("--data-root")
+            .arg(tmp_dir.path())
+            .arg("replay-lockstep")
+            .arg(&cmd.registry_local_store_path)
+            .arg(&cmd.backup_spool_path)
+            .arg(&cmd.replica_version)
+            .arg(cmd.start_height.to_string())
+            .arg(cmd.end_height.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("Couldn't start {:?}: {}", binary, err))?;
+        let stdin = child.stdin.take().expect("The stdin of the child is piped");
+        let stdout = BufReader::new(
+            child
+                .stdout
+                .take()
+                .expect("The stdout of the child is piped"),
+        );
+
+        Ok(Self {
+            name,
+            child,
+            stdin,
+            stdout,
+            tmp_dir,
+        })
+    }
+
+    fn send(&mut self, instruction: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", instruction)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|err| format!("Couldn't instruct the {} replay: {}", self.name, err))
+    }
+}
+
+impl LockstepReplay for ChildReplay {
+    fn next_state(&mut self) -> Result<Option<(Height, String)>, String> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = self
+                .stdout
+                .read_line(&mut line)
+                .map_err(|err| format!("Couldn't read from the {} replay: {}", self.name, err))?;
+            if read == 0 {
+                let status = self.child.wait().map_err(|err| err.to_string())?;
+                if !status.success() {
+                    return Err(format!("The {} replay failed: {}", self.name, status));
+                }
+                return Ok(None);
+            }
+            match parse_state_hash(&line) {
+                Some(state) => return Ok(Some(state)),
+                // Forward everything else the replay prints.
+                None => print!("[{}] {}", self.name, line),
+            }
+        }
+    }
+
+    fn resume(&mut self) -> Result<(), String> {
+        self.send(CONTINUE)
+    }
+
+    fn dump_tree(&mut self) -> Result<HashTree, String> {
+        let path = self.tmp_dir.path().join("canonical_tree.cbor");
+        self.send(&format!("{} {}", DUMP, path.display()))?;
+        // Forward the rest of the output until the child exits.
+        while self.next_state()?.is_some() {}
+        let bytes = fs::read(&path)
+            .map_err(|err| format!("Couldn't read the {} tree: {}", self.name, err))?;
+        serde_cbor::from_slice(&bytes)
+            .map_err(|err| format!("Couldn't decode the {} tree: {}", self.name, err))
+    }
+}
+
+impl Drop for ChildReplay {
+    fn drop(&mut self) {
+        // The child has usually exited already; make sure it doesn't outlive
+        // the comparison otherwise.
+        let _ = self.child.kill();
+    }
+}
+
+fn parse_state_hash(line: &str) -> Option<(Height, String)> {
+    let mut parts = line.split_whitespace();
+    if parts.next() != Some(STATE_HASH_PREFIX) {
+        return None;
+    }
+    let height = parts.next()?.parse::<u64>().ok()?;
+    let hash = parts.next()?;
+    Some((Height::from(height), hash.to_string()))
+}
+
+/// Replays the backup with both sides of `cmd` in lockstep and finds the
+/// first height at which their states differ. The canonical tree paths that
+/// differ and the messages included in the batch of that height are printed.
+///
+/// Both replays start from a copy of the state found in the state root of
+/// `cfg`, so neither of them modifies the original state.
+pub(crate) fn bisect_determinism(
+    cfg: Config,
+    config_path: &Path,
+    cmd: &BisectDeterminismCmd,
+    subnet_id: SubnetId,
+) -> ReplayResult {
+    assert!(
+        cmd.end_height > cmd.start_height,
+        "The end height must be greater than the start height"
+    );
+    let binary = std::env::current_exe().expect("Couldn't determine the path of ic-replay");
+    let other_binary = cmd.other_binary.clone().unwrap_or_else(|| binary.clone());
+    let other_config = cmd
+        .other_config
+        .clone()
+        .unwrap_or_else(|| config_path.to_path_buf());
+    let state_root = cfg.state_manager.state_root();
+
+    let spawn = |name: &'static str, binary: &Path, config: &Path| {
+        ChildReplay::spawn(name, binary, config, &state_root, cmd, subnet_id)
+            .unwrap_or_else(|err| panic!("{}", err))
+    };
+    let mut replay = spawn("main", &binary, config_path);
+    let mut other_replay = spawn("other", &other_binary, &other_config);
+
+    match find_divergence(&mut replay, &mut other_replay) {
+        Ok(None) => {
+            println!(
+                "No divergence found between heights {} and {}",
+                cmd.start_height, cmd.end_height
+            );
+            Ok(StateParams::default())
+        }
+        Ok(Some((height, changes))) => {
+            println!("First divergent height: {}", height);
+            println!("Differing canonical tree paths:");
+            print!("{}", PrettyPrintedChanges(&changes));
+            Err(ReplayError::StateDivergence(height))
+        }
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Replays the backup height by height. After each height, the hash of the
+/// canonical tree of the state is printed and the replay waits for the
+/// instruction to either continue or to write the tree to a file and exit.
+pub(crate) fn replay_lockstep(
+    cfg: Config,
+    cmd: &ReplayLockstepCmd,
+    subnet_id: SubnetId,
+) -> ReplayResult {
+    let mut player = Player::new_for_backup(
+        cfg,
+        ReplicaVersion::try_from(cmd.restore.replica_version.as_str())
+            .expect("Couldn't parse the replica version"),
+        &cmd.restore.backup_spool_path,
+        &cmd.restore.registry_local_store_path,
+        subnet_id,
+        cmd.restore.start_height,
+    )
+    .with_replay_target_height(Some(cmd.end_height))
+    .with_state_observer(Box::new(report_state));
+    player.restore(cmd.restore.start_height + 1)
+}
+
+// Reports the hash of the state at the given height and follows the next
+// instruction read from the standard input.
+fn report_state(player: &Player, height: Height) {
+    let tree = player.latest_state_tree();
+    println!(
+        "{} {} {}",
+        STATE_HASH_PREFIX,
+        height,
+        hex::encode(tree.digest().0)
+    );
+
+    let mut instruction = String::new();
+    if std::io::stdin().read_line(&mut instruction).unwrap_or(0) == 0 {
+        println!("The standard input was closed, stopping the replay");
+        std::process::exit(1);
+    }
+    let instruction = instruction.trim();
+    if instruction == CONTINUE {
+        return;
+    }
+    let Some(path) = instruction.strip_prefix(DUMP) else {
+        panic!("Unknown instruction: {}", instruction);
+    };
+    let bytes = serde_cbor::to_vec(&tree).expect("Couldn't encode the canonical tree");
+    fs::write(path.trim(), bytes).expect("Couldn't write the canonical tree");
+    print_batch_messages(player, height);
+    std::process::exit(0);
+}
+
+// Prints the canisters and messages that were included in the batch of the
+// given height.
+fn print_batch_messages(player: &Player, height: Height) {
+    let Some(payload) = player.get_finalized_batch_payload(height) else {
+        println!("The block at height {} carries no batch payload", height);
+        return;
+    };
+    let messages = match payload.into_messages() {
+        Ok(messages) => messages,
+        Err(err) => {
+            println!("Failed to decode the batch at height {}: {:?}", height, err);
+            return;
+        }
+    };
+    println!("Messages in the batch at height {}:", height);
+    for ingress in messages.signed_ingress_msgs {
+        println!(
+            "  ingress {} -> canister {} method {}",
+            ingress.id(),
+            ingress.canister_id(),
+            ingress.method_name()
+        );
+    }
+    for (subnet_id, slice) in messages.certified_stream_slices {
+        println!(
+            "  stream slice from subnet {} ({} bytes)",
+            subnet_id,
+            slice.payload.len()
+        );
+    }
+    if !messages.bitcoin_adapter_responses.is_empty() {
+        println!(
+            "  {} bitcoin adapter responses",
+            messages.bitcoin_adapter_responses.len()
+        );
+    }
+}
+
+// Copies the directory `src` into `dst` recursively.
+fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let dst_entry = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dst_entry)?;
+        } else {
+            fs::copy(entry.path(), &dst_entry)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_registry_subnet_type::SubnetType;
+    use ic_replicated_state::ReplicatedState;
+    use ic_state_manager::tree_hash::hash_state;
+    use ic_test_utilities_types::ids::subnet_test_id;
+    use ic_types::time::UNIX_EPOCH;
+    use std::time::Duration;
+
+    // Replays a fixed sequence of states.
+    struct FakeReplay {
+        states: Vec<(Height, HashTree)>,
+        next: usize,
+        resumed: usize,
+        dumped: bool,
+    }
+
+    impl FakeReplay {
+        fn new(states: Vec<(u64, HashTree)>) -> Self {
+            Self {
+                states: states
+                    .into_iter()
+                    .map(|(height, tree)| (Height::from(height), tree))
+                    .collect(),
+                next: 0,
+                resumed: 0,
+                dumped: false,
+            }
+        }
+    }
+
+    impl LockstepReplay for FakeReplay {
+        fn next_state(&mut self) -> Result<Option<(Height, String)>, String> {
+            assert_eq!(self.next, self.resumed, "next state before resuming");
+            let state = self
+                .states
+                .get(self.next)
+                .map(|(height, tree)| (*height, hex::encode(tree.digest().0)));
+            self.next += 1;
+            Ok(state)
+        }
+
+        fn resume(&mut self) -> Result<(), String> {
+            self.resumed += 1;
+            Ok(())
+        }
+
+        fn dump_tree(&mut self) -> Result<HashTree, String> {
+            self.dumped = true;
+            Ok(self.states[self.next - 1].1.clone())
+        }
+    }
+
+    // Returns the canonical tree of a state with the given batch time.
+    fn tree_with_batch_time(secs: u64) -> HashTree {
+        let mut state = ReplicatedState::new(subnet_test_id(1), SubnetType::Application);
+        state.metadata.batch_time = UNIX_EPOCH + Duration::from_secs(secs);
+        hash_state(&state)
+    }
+
+    #[test]
+    fn test_find_divergence_reports_first_divergent_height() {
+        let mut replay = FakeReplay::new(vec![
+            (11, tree_with_batch_time(1)),
+            (12, tree_with_batch_time(2)),
+            (13, tree_with_batch_time(3)),
+            (14, tree_with_batch_time(4)),
+        ]);
+        let mut other_replay = FakeReplay::new(vec![
+            (11, tree_with_batch_time(1)),
+            (12, tree_with_batch_time(2)),
+            (13, tree_with_batch_time(7)),
+            (14, tree_with_batch_time(8)),
+        ]);
+
+        let (height, changes) = find_divergence(&mut replay, &mut other_replay)
+            .unwrap()
+            .expect("the replays diverge");
+
+        assert_eq!(height, Height::from(13));
+        assert_eq!(changes.len(), 1);
+        assert!(PrettyPrintedChanges(&changes).to_string().contains("time"));
+        // Each height was replayed once and the replays stopped at the
+        // divergent height.
+        for replay in [&replay, &other_replay] {
+            assert_eq!(replay.resumed, 2);
+            assert!(replay.dumped);
+        }
+    }
+
+    #[test]
+    fn test_find_divergence_without_divergence() {
+        let states = vec![(11, tree_with_batch_time(1)), (12, tree_with_batch_time(2))];
+        let mut replay = FakeReplay::new(states.clone());
+        let mut other_replay = FakeReplay::new(states);
+
+        assert!(find_divergence(&mut replay, &mut other_replay)
+            .unwrap()
+            .is_none());
+        assert!(!replay.dumped && !other_replay.dumped);
+    }
+
+    #[test]
+    fn test_find_divergence_fails_if_replays_are_out_of_step() {
+        let mut replay = FakeReplay::new(vec![(11, tree_with_batch_time(1))]);
+        let mut other_replay = FakeReplay::new(vec![(12, tree_with_batch_time(1))]);
+        assert!(find_divergence(&mut replay, &mut other_replay).is_err());
+
+        let mut replay = FakeReplay::new(vec![(11, tree_with_batch_time(1))]);
+        let mut other_replay = FakeReplay::new(vec![]);
+        assert!(find_divergence(&mut replay, &mut other_replay).is_err());
+    }
+
+    #[test]
+    fn test_parse_state_hash() {
+        assert_eq!(
+            parse_state_hash("lockstep_state_hash 42 abcd\n"),
+            Some((Height::from(42), "abcd".to_string()))
+        );
+        assert_eq!(parse_state_hash("Latest state height is 42\n"), None);
+        assert_eq!(parse_state_hash("lockstep_state_hash x abcd\n"), None);
+    }
+}
@@ -63,6 +63,21 @@ pub enum SubCommand {
     /// Restore from the backup.
     RestoreFromBackup(RestoreFromBackupCmd),
 
+    /// Restore from the backup and execute a query call against the restored
+    /// state without modifying it.
+    QueryFromBackup(QueryFromBackupCmd),
+
+    /// Replay the backup with two replica binaries or configurations in
+    /// lockstep and find the first height at which the resulting states
+    /// differ.
+    BisectDeterminism(BisectDeterminismCmd),
+
+    /// Restore from the backup as one side of `bisect-determinism`: the hash
+    /// of the state is reported after each height and the replay only
+    /// continues once instructed to on the standard input.
+    #[clap(hide = true)]
+    ReplayLockstep(ReplayLockstepCmd),
+
     /// The replay will add a test Neuron to the Governance canister
     /// and the corresponding account in the ledger.
     WithNeuronForTests(WithNeuronCmd),
@@ -116,6 +131,51 @@ pub struct RestoreFromBackupCmd {
     pub start_height: u64,
 }
 
+#[derive(Clone, Parser)]
+pub struct QueryFromBackupCmd {
+    #[clap(flatten)]
+    pub restore: RestoreFromBackupCmd,
+    /// Id of the canister to query
+    pub canister_id: CanisterId,
+    /// Name of the query method
+    pub method_name: String,
+    /// Argument of the query in Candid text format
+    #[clap(default_value = "()")]
+    pub arg: String,
+}
+
+#[derive(Clone, Parser)]
+pub struct BisectDeterminismCmd {
+    /// Registry local store path
+    pub registry_local_store_path: PathBuf,
+    /// Backup spool path
+    pub backup_spool_path: PathBuf,
+    /// The replica version to be restored
+    pub replica_version: String,
+    /// Height from which the restoration should happen
+    pub start_height: u64,
+    /// Last height to replay
+    pub end_height: u64,
+    /// Path to the replica configuration file of the second replay; the main
+    /// configuration is used if not specified. Both replays start from the
+    /// state found in the data root of the main configuration.
+    #[clap(long)]
+    pub other_config: Option<PathBuf>,
+    /// Path to the `ic-replay` binary of the second replay; this binary is
+    /// used if not specified. The binary needs to support the
+    /// `replay-lockstep` subcommand.
+    #[clap(long)]
+    pub other_binary: Option<PathBuf>,
+}
+
+#[derive(Clone, Parser)]
+pub struct ReplayLockstepCmd {
+    #[clap(flatten)]
+    pub restore: RestoreFromBackupCmd,
+    /// Last height to replay
+    pub end_height: u64,
+}
+
 #[derive(Clone, Parser)]
 pub struct RestoreFromBackup2Cmd {
     /// Registry local store path
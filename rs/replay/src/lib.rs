@@ -28,6 +28,7 @@ use prost::Message;
 use std::{cell::RefCell, convert::TryFrom, rc::Rc};
 
 mod backup;
+mod bisect;
 pub mod cmd;
 pub mod ingress;
 mod mocks;
@@ -73,10 +74,11 @@ pub fn replay(args: ReplayToolArgs) -> ReplayResult {
     Config::run_with_temp_config(|default_config| {
         let subcmd = &args.subcmd;
 
-        let source = ConfigSource::File(args.config.unwrap_or_else(|| {
+        let config_path = args.config.unwrap_or_else(|| {
             println!("Config file is required!");
             std::process::exit(1);
-        }));
+        });
+        let source = ConfigSource::File(config_path.clone());
         let mut cfg = Config::load_with_default(&source, default_config).unwrap_or_else(|err| {
             println!("Failed to load config:\n  {}", err);
             std::process::exit(1);
@@ -108,7 +110,24 @@ pub fn replay(args: ReplayToolArgs) -> ReplayResult {
             }
         }
 
-        if let Some(SubCommand::RestoreFromBackup(cmd)) = subcmd {
+        if let Some(SubCommand::BisectDeterminism(cmd)) = subcmd {
+            *res_clone.borrow_mut() = bisect::bisect_determinism(cfg, &config_path, cmd, subnet_id);
+            return;
+        }
+
+        if let Some(SubCommand::ReplayLockstep(cmd)) = subcmd {
+            let _enter_guard = rt.enter();
+
+            *res_clone.borrow_mut() = bisect::replay_lockstep(cfg, cmd, subnet_id);
+            return;
+        }
+
+        let restore_cmd = match subcmd {
+            Some(SubCommand::RestoreFromBackup(cmd)) => Some(cmd),
+            Some(SubCommand::QueryFromBackup(cmd)) => Some(&cmd.restore),
+            _ => None,
+        };
+        if let Some(cmd) = restore_cmd {
             let _enter_guard = rt.enter();
 
             let mut player = Player::new_for_backup(
@@ -121,7 +140,14 @@ pub fn replay(args: ReplayToolArgs) -> ReplayResult {
                 cmd.start_height,
             )
             .with_replay_target_height(target_height);
-            *res_clone.borrow_mut() = player.restore(cmd.start_height + 1);
+            let result = player.restore(cmd.start_height + 1);
+            if let (Ok(_), Some(SubCommand::QueryFromBackup(cmd))) = (&result, subcmd) {
+                if let Err(err) = cmd_query(&player, cmd) {
+                    println!("{}", err);
+                    std::process::exit(1);
+                }
+            }
+            *res_clone.borrow_mut() = result;
             return;
         }
 
@@ -222,6 +248,34 @@ pub fn consent_given(question: &str) -> bool {
     matches!(s.as_str(), "\n" | "y\n" | "Y\n")
 }
 
+// Executes the query of the given command against the restored state and
+// prints the decoded reply. Returns an error if the query fails or is rejected.
+fn cmd_query(
+    player: &crate::player::Player,
+    cmd: &crate::cmd::QueryFromBackupCmd,
+) -> Result<(), String> {
+    use candid::IDLArgs;
+    use ic_types::ingress::WasmResult;
+
+    let arg = candid_parser::parse_idl_args(&cmd.arg)
+        .map_err(|err| format!("Failed to parse the query argument: {}", err))?
+        .to_bytes()
+        .map_err(|err| format!("Failed to encode the query argument: {}", err))?;
+    let ingress_expiry = ic_types::time::current_time() + std::time::Duration::from_secs(60);
+    match player.query(cmd.canister_id, &cmd.method_name, arg, ingress_expiry)? {
+        WasmResult::Reply(bytes) => match IDLArgs::from_bytes(&bytes) {
+            Ok(reply) => println!("Reply: {}", reply),
+            Err(err) => println!(
+                "Reply (failed to decode as Candid: {}): {}",
+                err,
+                hex::encode(bytes)
+            ),
+        },
+        WasmResult::Reject(msg) => return Err(format!("Reject: {}", msg)),
+    }
+    Ok(())
+}
+
 // Creates a recovery CUP by using the latest CUP and overriding the height and
 // the state hash.
 fn cmd_get_recovery_cup(
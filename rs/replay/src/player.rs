@@ -18,6 +18,7 @@ use ic_crypto_for_verification_only::CryptoComponentForVerificationOnly;
 use ic_crypto_test_utils_ni_dkg::{
     dummy_initial_dkg_transcript_with_master_key, sign_message, SecretKeyBytes,
 };
+use ic_crypto_tree_hash::HashTree;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_execution_environment::ExecutionServices;
 use ic_interfaces::{
@@ -51,9 +52,9 @@ use ic_registry_transport::{
     deserialize_get_value_response, serialize_get_changes_since_request,
     serialize_get_value_request,
 };
-use ic_state_manager::StateManagerImpl;
+use ic_state_manager::{tree_hash::hash_state, StateManagerImpl};
 use ic_types::{
-    batch::{Batch, BatchMessages, BlockmakerMetrics},
+    batch::{Batch, BatchMessages, BatchPayload, BlockmakerMetrics},
     consensus::{
         certification::{Certification, CertificationContent, CertificationShare},
         CatchUpContentProtobufBytes, CatchUpPackage, HasHeight, HasVersion,
@@ -67,8 +68,8 @@ use ic_types::{
     messages::{CertificateDelegation, Query, QuerySource},
     signature::ThresholdSignature,
     time::current_time,
-    CryptoHashOfPartialState, CryptoHashOfState, Height, NodeId, PrincipalId, Randomness,
-    RegistryVersion, ReplicaVersion, SubnetId, Time, UserId,
+    CanisterId, CryptoHashOfPartialState, CryptoHashOfState, Height, NodeId, PrincipalId,
+    Randomness, RegistryVersion, ReplicaVersion, SubnetId, Time, UserId,
 };
 use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
@@ -111,6 +112,10 @@ pub enum ReplayError {
 
 pub type ReplayResult = Result<StateParams, ReplayError>;
 
+/// Callback invoked with the player after the state of each delivered batch
+/// was committed.
+pub type StateObserver = Box<dyn Fn(&Player, Height)>;
+
 /// The main ic-replay component that sets up consensus and execution
 /// environment to replay past blocks.
 pub struct Player {
@@ -136,6 +141,9 @@ pub struct Player {
     // The target height until which the state will be replayed.
     // None means finalized height.
     replay_target_height: Option<u64>,
+    // If set, batches are delivered one by one and the observer is called
+    // after each of them.
+    state_observer: Option<StateObserver>,
     runtime: Runtime,
 }
 
@@ -385,6 +393,7 @@ impl Player {
             _async_log_guard,
             tmp_dir: None,
             replay_target_height: None,
+            state_observer: None,
             runtime,
         }
     }
@@ -395,6 +404,13 @@ impl Player {
         self
     }
 
+    /// Set an observer that is called after the state of each delivered batch
+    /// was committed. Batches are delivered one by one then.
+    pub fn with_state_observer(mut self, state_observer: StateObserver) -> Self {
+        self.state_observer = Some(state_observer);
+        self
+    }
+
     /// In case a consensus pool was supplied, replay past finalized but
     /// un-executed blocks by delivering ingress messages for execution,
     /// and make a full checkpoint of the latest state when they all finish.
@@ -696,6 +712,49 @@ impl Player {
         pool: &PoolReader<'_>,
         membership: &Membership,
         replay_target_height: Option<Height>,
+    ) -> Height {
+        let expected_batch_height = message_routing.expected_batch_height();
+        let state_observer = match &self.state_observer {
+            Some(state_observer) if expected_batch_height > Height::from(0) => state_observer,
+            _ => {
+                return self.deliver_batches_until(
+                    message_routing,
+                    pool,
+                    membership,
+                    replay_target_height,
+                )
+            }
+        };
+
+        let mut last_batch_height = expected_batch_height.decrement();
+        let target_height = replay_target_height
+            .unwrap_or_else(|| pool.get_finalized_height())
+            .min(pool.get_finalized_height());
+        while last_batch_height < target_height {
+            let height = self.deliver_batches_until(
+                message_routing,
+                pool,
+                membership,
+                Some(last_batch_height.increment()),
+            );
+            if height == last_batch_height {
+                break;
+            }
+            self.wait_for_state(height);
+            state_observer(self, height);
+            last_batch_height = height;
+        }
+        last_batch_height
+    }
+
+    // Deliver finalized batches since last expected batch height up to the
+    // given height.
+    fn deliver_batches_until(
+        &self,
+        message_routing: &dyn MessageRouting,
+        pool: &PoolReader<'_>,
+        membership: &Membership,
+        replay_target_height: Option<Height>,
     ) -> Height {
         let expected_batch_height = message_routing.expected_batch_height();
         let last_batch_height = loop {
@@ -954,11 +1013,60 @@ impl Player {
         }
     }
 
+    /// Execute a query call against the latest state and return its result.
+    /// The state is not modified by the query.
+    pub fn query(
+        &self,
+        canister_id: CanisterId,
+        method_name: &str,
+        method_payload: Vec<u8>,
+        ingress_expiry: Time,
+    ) -> Result<WasmResult, String> {
+        let query = Query {
+            source: QuerySource::User {
+                user_id: UserId::from(PrincipalId::new_anonymous()),
+                ingress_expiry: ingress_expiry.as_nanos_since_unix_epoch(),
+                nonce: None,
+            },
+            receiver: canister_id,
+            method_name: method_name.to_string(),
+            method_payload,
+        };
+        self.certify_state_with_dummy_certification();
+        match self
+            .runtime
+            .block_on(self.query_handler.clone().oneshot((query, None)))
+            .unwrap()
+        {
+            Ok((Ok(wasm_result), _)) => Ok(wasm_result),
+            Ok((Err(err), _)) => Err(format!("Query failed: {:?}", err)),
+            Err(QueryExecutionError::CertifiedStateUnavailable) => {
+                panic!("Certified state unavailable for query call.")
+            }
+        }
+    }
+
     /// Return the highest CatchUpPackage
     pub fn get_highest_catch_up_package(&self) -> CatchUpPackage {
         PoolReader::new(self.consensus_pool.as_ref().unwrap()).get_highest_catch_up_package()
     }
 
+    /// Return the batch payload of the finalized block at the given height, if
+    /// the block is known and is not a summary block.
+    pub fn get_finalized_batch_payload(&self, height: Height) -> Option<BatchPayload> {
+        let block = PoolReader::new(self.consensus_pool.as_ref()?).get_finalized_block(height)?;
+        let payload = block.payload.as_ref();
+        if payload.is_summary() {
+            return None;
+        }
+        Some(payload.as_data().batch.clone())
+    }
+
+    /// Return the hash tree of the canonical representation of the latest state.
+    pub fn latest_state_tree(&self) -> HashTree {
+        hash_state(self.state_manager.get_latest_state().get_ref())
+    }
+
     /// Query the registry canister and return registry records since the given
     /// version.
     pub fn get_changes_since(
@@ -1449,7 +1557,11 @@ fn get_state_hash<T>(
 
 #[cfg(test)]
 mod tests {
+    use ic_config::flag_status::FlagStatus;
+    use ic_interfaces_registry::RegistryDataProvider;
     use ic_logger::replica_logger::no_op_logger;
+    use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
+    use ic_state_machine_tests::StateMachineBuilder;
     use ic_test_utilities_consensus::fake::FakeSigner;
     use ic_test_utilities_types::ids::node_test_id;
     use ic_types::{
@@ -1578,4 +1690,64 @@ mod tests {
             f
         ));
     }
+
+    #[test]
+    fn test_query_against_restored_state() {
+        use ic_universal_canister::{wasm, UNIVERSAL_CANISTER_WASM};
+
+        // Create a checkpoint with a canister holding some data.
+        let registry_data_provider = Arc::new(ProtoRegistryDataProvider::new());
+        let env = StateMachineBuilder::new()
+            .with_checkpoints_enabled(true)
+            .with_registry_data_provider(registry_data_provider.clone())
+            .build();
+        let subnet_id = env.get_subnet_id();
+        let canister_id = env
+            .install_canister(UNIVERSAL_CANISTER_WASM.to_vec(), vec![], None)
+            .unwrap();
+        env.execute_ingress(
+            canister_id,
+            "update",
+            wasm().set_global_data(b"replayed").reply().build(),
+        )
+        .unwrap();
+        env.checkpointed_tick();
+        env.await_state_hash();
+        let state_dir = env.into_state_dir();
+
+        let tmp = tempfile::tempdir().unwrap();
+        let local_store_path = tmp.path().join("local_store");
+        let zero_version = RegistryVersion::from(0);
+        write_records_to_local_store(
+            &local_store_path,
+            zero_version,
+            registry_data_provider
+                .get_updates_since(zero_version)
+                .unwrap(),
+        );
+
+        // Restore the state and run queries against it.
+        let mut cfg = Config::new(tmp.path().to_path_buf());
+        cfg.state_manager = ic_config::state_manager::Config::new(state_dir.path());
+        cfg.registry_client.local_store = local_store_path;
+        cfg.hypervisor.canister_sandboxing_flag = FlagStatus::Disabled;
+        let player = Player::new(cfg, subnet_id);
+        let ingress_expiry = current_time() + Duration::from_secs(60);
+
+        let result = player.query(
+            canister_id,
+            "query",
+            wasm().get_global_data().append_and_reply().build(),
+            ingress_expiry,
+        );
+        assert_eq!(result, Ok(WasmResult::Reply(b"replayed".to_vec())));
+
+        let result = player.query(
+            canister_id,
+            "query",
+            wasm().push_bytes(b"rejected").reject().build(),
+            ingress_expiry,
+        );
+        assert_eq!(result, Ok(WasmResult::Reject("rejected".to_string())));
+    }
 }
@@ -387,6 +387,7 @@ fn start_consensus(
             Arc::clone(&certifier_crypto),
             Arc::clone(&state_manager) as Arc<_>,
             Arc::clone(&consensus_pool_cache) as Arc<_>,
+            Arc::clone(&consensus_block_cache),
             metrics_registry.clone(),
             log.clone(),
             max_certified_height_tx,
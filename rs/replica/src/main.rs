@@ -1,22 +1,22 @@
 //! Replica -- Internet Computer
 
 use ic_async_utils::{abort_on_panic, shutdown_signal};
-use ic_config::Config;
+use ic_config::{tracing::DEFAULT_SAMPLING_RATIO, Config};
 use ic_crypto_sha2::Sha256;
 use ic_http_endpoints_metrics::MetricsHttpEndpoint;
 use ic_logger::{info, new_replica_logger_from_config};
 use ic_metrics::MetricsRegistry;
 use ic_replica::setup;
 use ic_sys::PAGE_SIZE;
-use ic_tracing::ReloadHandles;
+use ic_tracing::{
+    otlp::{otlp_layer, SamplingConfig},
+    ReloadHandles,
+};
 use ic_types::{
     consensus::CatchUpPackage, replica_version::REPLICA_BINARY_HASH, PrincipalId, ReplicaVersion,
     SubnetId,
 };
 use nix::unistd::{setpgid, Pid};
-use opentelemetry::KeyValue;
-use opentelemetry_otlp::WithExportConfig;
-use opentelemetry_sdk::{trace, Resource};
 use std::{env, fs, io, path::PathBuf, str::FromStr, sync::Arc, time::Duration};
 use tokio::signal::unix::{signal, SignalKind};
 use tracing_subscriber::layer::SubscriberExt;
@@ -243,27 +243,16 @@ fn main() -> io::Result<()> {
         Some(jaeger_collector_addr) if !jaeger_collector_addr.is_empty() => {
             let _rt_guard = rt_main.enter();
 
-            let span_exporter = opentelemetry_otlp::new_exporter()
-                .tonic()
-                .with_endpoint(jaeger_collector_addr)
-                .with_protocol(opentelemetry_otlp::Protocol::Grpc);
-
-            match opentelemetry_otlp::new_pipeline()
-                .tracing()
-                .with_trace_config(
-                    trace::config()
-                        .with_sampler(opentelemetry_sdk::trace::Sampler::TraceIdRatioBased(0.01))
-                        .with_resource(Resource::new(vec![KeyValue::new(
-                            "service.name",
-                            "replica",
-                        )])),
-                )
-                .with_exporter(span_exporter)
-                .install_batch(opentelemetry_sdk::runtime::Tokio)
-            {
-                Ok(tracer) => {
-                    let otel_layer = tracing_opentelemetry::OpenTelemetryLayer::new(tracer);
-                    tracing_layers.push(otel_layer.boxed());
+            let sampling = SamplingConfig {
+                ratio: config
+                    .tracing
+                    .sampling_ratio
+                    .unwrap_or(DEFAULT_SAMPLING_RATIO),
+                message_ids: config.tracing.traced_message_ids.iter().cloned().collect(),
+            };
+            match otlp_layer(jaeger_collector_addr, "replica", sampling) {
+                Ok(otel_layer) => {
+                    tracing_layers.push(otel_layer);
                 }
                 Err(err) => {
                     tracing::warn!("Failed to create the opentelemetry tracer: {:#?}", err);
@@ -720,6 +720,8 @@ fn can_get_canister_information() {
                 0u128,
                 0u128,
                 Some(0),
+                vec![],
+                false,
             )
         );
 
@@ -777,7 +779,9 @@ fn can_get_canister_information() {
                     0u128,
                     0u128,
                     0u128,
-                    Some(0)
+                    Some(0),
+                    vec![],
+                    false,
                 ),
                 CanisterStatusResultV2::decode(&res).unwrap(),
                 2 * BALANCE_EPSILON,
@@ -66,6 +66,19 @@ impl CanisterSnapshots {
         snapshot_id
     }
 
+    /// Adds a snapshot of a canister migrated from another subnet to the collection.
+    ///
+    /// Unlike `push`, no backup operation is added to the `unflushed_changes`,
+    /// since the snapshot was not taken from the canister's current state: the
+    /// files of the snapshot must already be present in the tip.
+    pub fn insert_migrated(&mut self, snapshot_id: SnapshotId, snapshot: Arc<CanisterSnapshot>) {
+        let canister_id = snapshot.canister_id();
+        self.memory_usage += snapshot.size();
+        self.snapshots.insert(snapshot_id, snapshot);
+        let snapshot_ids = self.snapshot_ids.entry(canister_id).or_default();
+        snapshot_ids.insert(snapshot_id);
+    }
+
     /// Returns a reference of the canister snapshot identified by `snapshot_id`.
     pub fn get(&self, snapshot_id: SnapshotId) -> Option<&Arc<CanisterSnapshot>> {
         self.snapshots.get(&snapshot_id)
@@ -126,7 +139,8 @@ impl CanisterSnapshots {
         if let Some(snapshot_ids) = self.snapshot_ids.remove(&canister_id) {
             for snapshot_id in snapshot_ids {
                 debug_assert!(self.snapshots.contains_key(&snapshot_id));
-                self.snapshots.remove(&snapshot_id).unwrap();
+                let snapshot = self.snapshots.remove(&snapshot_id).unwrap();
+                self.memory_usage -= snapshot.size();
                 self.unflushed_changes
                     .push(SnapshotOperation::Delete(snapshot_id));
             }
@@ -599,4 +613,69 @@ mod tests {
             NumBytes::from(0)
         );
     }
+
+    #[test]
+    fn test_memory_usage_correctly_updated_while_deleting_all_snapshots_of_canister() {
+        let canister_id = canister_test_id(0);
+        let other_canister_id = canister_test_id(1);
+        let (first_snapshot_id, first_snapshot) = fake_canister_snapshot(canister_id, 1);
+        let (second_snapshot_id, second_snapshot) = fake_canister_snapshot(canister_id, 2);
+        let (other_snapshot_id, other_snapshot) = fake_canister_snapshot(other_canister_id, 3);
+        let other_snapshot_size = other_snapshot.size();
+        let mut snapshot_manager = CanisterSnapshots::default();
+        snapshot_manager.push(first_snapshot_id, Arc::new(first_snapshot));
+        snapshot_manager.push(second_snapshot_id, Arc::new(second_snapshot));
+        snapshot_manager.push(other_snapshot_id, Arc::new(other_snapshot));
+        snapshot_manager.take_unflushed_changes();
+
+        // Deleting all snapshots of a canister releases their memory, but
+        // not the memory of other canisters' snapshots.
+        snapshot_manager.delete_snapshots(canister_id);
+        assert_eq!(snapshot_manager.snapshots.len(), 1);
+        assert_eq!(snapshot_manager.memory_taken(), other_snapshot_size);
+        assert_eq!(
+            snapshot_manager.compute_memory_usage_by_canister(canister_id),
+            NumBytes::from(0)
+        );
+        assert_eq!(
+            snapshot_manager.take_unflushed_changes(),
+            vec![
+                SnapshotOperation::Delete(first_snapshot_id),
+                SnapshotOperation::Delete(second_snapshot_id)
+            ]
+        );
+
+        snapshot_manager.delete_snapshots(other_canister_id);
+        assert_eq!(snapshot_manager.memory_taken(), NumBytes::from(0));
+    }
+
+    #[test]
+    fn test_insert_migrated_and_delete_snapshots() {
+        let canister_id = canister_test_id(0);
+        let (first_snapshot_id, first_snapshot) = fake_canister_snapshot(canister_id, 1);
+        let (second_snapshot_id, second_snapshot) = fake_canister_snapshot(canister_id, 2);
+        let snapshots_size = first_snapshot.size() + second_snapshot.size();
+        let mut snapshot_manager = CanisterSnapshots::default();
+
+        // Inserting migrated snapshots does not add any `unflushed_changes`.
+        snapshot_manager.insert_migrated(first_snapshot_id, Arc::new(first_snapshot));
+        snapshot_manager.insert_migrated(second_snapshot_id, Arc::new(second_snapshot));
+        assert_eq!(snapshot_manager.snapshots.len(), 2);
+        assert!(snapshot_manager.is_unflushed_changes_empty());
+        assert_eq!(snapshot_manager.snapshots_count(&canister_id), 2);
+        assert_eq!(snapshot_manager.memory_taken(), snapshots_size);
+
+        // Deleting all snapshots of the canister updates the `memory_usage`.
+        snapshot_manager.delete_snapshots(canister_id);
+        assert_eq!(snapshot_manager.snapshots.len(), 0);
+        assert_eq!(snapshot_manager.snapshots_count(&canister_id), 0);
+        assert_eq!(snapshot_manager.memory_taken(), NumBytes::from(0));
+        assert_eq!(
+            snapshot_manager.take_unflushed_changes(),
+            vec![
+                SnapshotOperation::Delete(first_snapshot_id),
+                SnapshotOperation::Delete(second_snapshot_id)
+            ]
+        );
+    }
 }
@@ -370,8 +370,8 @@ impl CanisterState {
     /// The amount of memory currently being used by the canister.
     ///
     /// This includes execution memory (heap, stable, globals, Wasm),
-    /// canister history memory, wasm chunk storage and snapshots that
-    /// belong to this canister.
+    /// canister history memory, environment variables, wasm chunk storage
+    /// and snapshots that belong to this canister.
     ///
     /// This amount is used to periodically charge the canister for the memory
     /// resources it consumes and can be used to calculate the canister's
@@ -379,6 +379,8 @@ impl CanisterState {
     pub fn memory_usage(&self) -> NumBytes {
         self.execution_memory_usage()
             + self.canister_history_memory_usage()
+            + self.environment_variables_memory_usage()
+            + self.canister_profile_memory_usage()
             + self.wasm_chunk_store_memory_usage()
             + self.system_state.snapshots_memory_usage
     }
@@ -410,6 +412,16 @@ impl CanisterState {
         self.system_state.canister_history_memory_usage()
     }
 
+    /// Returns the amount of memory used by environment variables in bytes.
+    pub fn environment_variables_memory_usage(&self) -> NumBytes {
+        self.system_state.environment_variables_memory_usage()
+    }
+
+    /// Returns the amount of memory used by the execution profile in bytes.
+    pub fn canister_profile_memory_usage(&self) -> NumBytes {
+        self.system_state.canister_profile_memory_usage()
+    }
+
     /// Returns the memory usage of the wasm chunk store in bytes.
     pub(super) fn wasm_chunk_store_memory_usage(&self) -> NumBytes {
         self.system_state.wasm_chunk_store.memory_usage()
@@ -584,6 +596,13 @@ impl CanisterState {
         self.system_state.canister_log.clear();
     }
 
+    /// Clears the execution profile of the canister, if profiling is enabled.
+    pub fn clear_profile(&mut self) {
+        if let Some(profile) = self.system_state.canister_profile.as_mut() {
+            profile.clear();
+        }
+    }
+
     /// Sets the new canister log.
     pub fn set_log(&mut self, other: CanisterLog) {
         self.system_state.canister_log = other;
@@ -17,6 +17,7 @@ use ic_management_canister_types::{
 use ic_protobuf::proxy::{try_from_option_field, ProxyDecodeError};
 use ic_protobuf::state::canister_state_bits::v1 as pb;
 use ic_registry_subnet_type::SubnetType;
+use ic_types::canister_profile::CanisterProfile;
 use ic_types::messages::{
     CanisterCall, CanisterMessage, CanisterMessageOrTask, CanisterTask, Ingress, RejectContext,
     Request, RequestOrResponse, Response, StopCanisterContext,
@@ -251,6 +252,18 @@ impl CanisterHistory {
         self.changes.range((num_all_changes - num_changes)..)
     }
 
+    /// Creates a canister history from the given changes, e.g. for a canister
+    /// migrated from another subnet. At most the `MAX_CANISTER_HISTORY_CHANGES`
+    /// most recent changes are retained.
+    pub fn new(changes: Vec<CanisterChange>, total_num_changes: u64) -> Self {
+        let mut history = Self::default();
+        for change in changes {
+            history.add_canister_change(change);
+        }
+        history.total_num_changes = total_num_changes.max(history.total_num_changes);
+        history
+    }
+
     pub fn get_total_num_changes(&self) -> u64 {
         self.total_num_changes
     }
@@ -354,6 +367,21 @@ pub struct SystemState {
     /// See the interface specification for more information.
     pub wasm_memory_limit: Option<NumBytes>,
 
+    /// Environment variables of the canister. This is a field in
+    /// developer-visible canister settings that can be read by the canister
+    /// through the `ic0.env_var_*` System API.
+    ///
+    /// The names and values contribute to the total `memory_usage` of the
+    /// canister as reported by `CanisterState::memory_usage`.
+    pub environment_variables: BTreeMap<String, String>,
+
+    /// Execution profile of the canister: per-function instruction totals
+    /// and call stacks. `None` if profiling is disabled for the canister,
+    /// which is the default. Controllers enable it via canister settings.
+    /// The profile counts towards the memory usage of the canister as
+    /// reported by `CanisterState::memory_usage`.
+    pub canister_profile: Option<CanisterProfile>,
+
     /// Next local snapshot id.
     pub next_snapshot_id: u64,
 
@@ -731,6 +759,8 @@ impl SystemState {
             log_visibility: Default::default(),
             canister_log: Default::default(),
             wasm_memory_limit: None,
+            environment_variables: BTreeMap::new(),
+            canister_profile: None,
             next_snapshot_id: 0,
             snapshots_memory_usage: NumBytes::from(0),
         }
@@ -760,6 +790,8 @@ impl SystemState {
         log_visibility: LogVisibilityV2,
         canister_log: CanisterLog,
         wasm_memory_limit: Option<NumBytes>,
+        environment_variables: BTreeMap<String, String>,
+        canister_profile: Option<CanisterProfile>,
         next_snapshot_id: u64,
         snapshots_memory_usage: NumBytes,
     ) -> Self {
@@ -788,6 +820,8 @@ impl SystemState {
             log_visibility,
             canister_log,
             wasm_memory_limit,
+            environment_variables,
+            canister_profile,
             next_snapshot_id,
             snapshots_memory_usage,
         }
@@ -1224,6 +1258,27 @@ impl SystemState {
         self.canister_history.get_memory_usage()
     }
 
+    /// Returns the memory currently in use by the `SystemState`
+    /// for environment variables.
+    pub fn environment_variables_memory_usage(&self) -> NumBytes {
+        let bytes: usize = self
+            .environment_variables
+            .iter()
+            .map(|(name, value)| name.len() + value.len())
+            .sum();
+        NumBytes::from(bytes as u64)
+    }
+
+    /// Returns the memory currently in use by the `SystemState`
+    /// for the execution profile (if profiling is enabled).
+    pub fn canister_profile_memory_usage(&self) -> NumBytes {
+        let bytes = self
+            .canister_profile
+            .as_ref()
+            .map_or(0, |profile| profile.memory_usage());
+        NumBytes::from(bytes as u64)
+    }
+
     /// Sets the (transient) size in bytes of responses from this canister
     /// routed into streams and not yet garbage collected.
     pub(super) fn set_stream_responses_size_bytes(&mut self, size_bytes: usize) {
@@ -1466,6 +1521,20 @@ impl SystemState {
         &self.canister_history
     }
 
+    /// Replaces the canister history of a canister migrated from another subnet
+    /// with the one it had on that subnet.
+    pub fn set_migrated_canister_history(&mut self, canister_history: CanisterHistory) {
+        self.canister_history = canister_history;
+    }
+
+    /// Replaces the cycles balances of a canister migrated from another subnet
+    /// with the ones it had on that subnet.
+    pub fn set_migrated_balances(&mut self, balance: Cycles, reserved_balance: Cycles) {
+        self.cycles_balance = balance;
+        self.ingress_induction_cycles_debit = Cycles::zero();
+        self.reserved_balance = reserved_balance;
+    }
+
     /// Checks the invariants that should hold at the end of each consensus round.
     pub fn check_invariants(&self) -> Result<(), String> {
         // Callbacks still awaiting a (potentially already enqueued) response.
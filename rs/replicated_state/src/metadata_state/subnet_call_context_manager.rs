@@ -11,9 +11,10 @@ use ic_types::{
     canister_http::CanisterHttpRequestContext,
     consensus::idkg::PreSigId,
     crypto::threshold_sig::ni_dkg::{id::ni_dkg_target_id, NiDkgTargetId},
-    messages::{CallbackId, CanisterCall, Request, StopCanisterCallId},
-    node_id_into_protobuf, node_id_try_from_option, CanisterId, ExecutionRound, Height, NodeId,
-    RegistryVersion, Time,
+    messages::{CallbackId, CanisterCall, Payload, Request, StopCanisterCallId},
+    node_id_into_protobuf, node_id_try_from_option, subnet_id_into_protobuf,
+    subnet_id_try_from_protobuf, CanisterId, Cycles, ExecutionRound, Height, NodeId,
+    RegistryVersion, SubnetId, Time,
 };
 use phantom_newtype::Id;
 use std::{
@@ -217,6 +218,13 @@ pub struct SubnetCallContextManager {
         BTreeMap<CallbackId, BitcoinSendTransactionInternalContext>,
     canister_management_calls: CanisterManagementCalls,
     pub raw_rand_contexts: VecDeque<RawRandContext>,
+    /// Replies to the entries of batched threshold signing requests that have
+    /// already been answered by consensus, keyed by batch id and entry index.
+    /// The response to the batch is only produced once all entries are done.
+    pub sign_with_threshold_batch_replies: BTreeMap<CallbackId, BTreeMap<u32, Payload>>,
+    /// Ongoing migrations of local canisters to other subnets, keyed by the
+    /// callback of the `import_canister_state` request currently in flight.
+    pub canister_migration_contexts: BTreeMap<CallbackId, CanisterMigrationContext>,
 }
 
 impl SubnetCallContextManager {
@@ -338,6 +346,29 @@ impl SubnetCallContextManager {
             })
     }
 
+    /// Records that the next step of a canister migration was sent to the
+    /// target subnet and returns the callback to use for the request.
+    pub fn push_canister_migration_context(
+        &mut self,
+        context: CanisterMigrationContext,
+    ) -> CallbackId {
+        let callback_id = CallbackId::new(self.next_callback_id);
+        self.next_callback_id += 1;
+        self.canister_migration_contexts
+            .insert(callback_id, context);
+        callback_id
+    }
+
+    /// Returns the context of the ongoing migration of `canister_id`, if any.
+    pub fn canister_migration_context(
+        &self,
+        canister_id: CanisterId,
+    ) -> Option<&CanisterMigrationContext> {
+        self.canister_migration_contexts
+            .values()
+            .find(|context| context.canister_id == canister_id)
+    }
+
     pub fn push_install_code_call(&mut self, call: InstallCodeCall) -> InstallCodeCallId {
         self.canister_management_calls.push_install_code_call(call)
     }
@@ -422,6 +453,50 @@ impl SubnetCallContextManager {
         removed
     }
 
+    /// Pushes one `SignWithThresholdContext` per message of a batched threshold
+    /// signing request, under consecutive callback ids. Every context is tagged
+    /// with its position in the batch and with the batch id, which is the
+    /// callback id of the first context.
+    pub fn push_sign_with_threshold_batch(
+        &mut self,
+        contexts: Vec<SignWithThresholdContext>,
+    ) -> CallbackId {
+        let batch_id = self.next_callback_id();
+        let size = contexts.len() as u32;
+        for (index, mut context) in contexts.into_iter().enumerate() {
+            context.batch = Some(SignatureBatchEntry {
+                batch_id,
+                index: index as u32,
+                size,
+            });
+            self.push_context(SubnetCallContext::SignWithThreshold(context));
+        }
+        batch_id
+    }
+
+    /// Records the reply to one entry of a batched threshold signing request.
+    ///
+    /// Once the replies to all entries of the batch are known, they are removed
+    /// and returned in the order of the messages in the batch. Returns `None`
+    /// while some entries are still pending.
+    pub fn complete_sign_with_threshold_batch_entry(
+        &mut self,
+        entry: SignatureBatchEntry,
+        payload: Payload,
+    ) -> Option<Vec<Payload>> {
+        let replies = self
+            .sign_with_threshold_batch_replies
+            .entry(entry.batch_id)
+            .or_default();
+        replies.insert(entry.index, payload);
+        if replies.len() < entry.size as usize {
+            return None;
+        }
+        self.sign_with_threshold_batch_replies
+            .remove(&entry.batch_id)
+            .map(|replies| replies.into_values().collect())
+    }
+
     /// Returns the number of `sign_with_threshold_contexts` per key id.
     pub fn sign_with_threshold_contexts_count(&self, key_id: &MasterPublicKeyId) -> usize {
         self.sign_with_threshold_contexts
@@ -554,6 +629,40 @@ impl From<&SubnetCallContextManager> for pb_metadata::SubnetCallContextManager {
                     },
                 )
                 .collect(),
+            sign_with_threshold_batch_replies: item
+                .sign_with_threshold_batch_replies
+                .iter()
+                .flat_map(|(batch_id, replies)| {
+                    replies.iter().map(|(index, payload)| {
+                        pb_metadata::SignWithThresholdBatchReply {
+                            batch_id: batch_id.get(),
+                            index: *index,
+                            response_payload: Some(match payload {
+                                Payload::Data(data) => {
+                                    pb_metadata::sign_with_threshold_batch_reply::ResponsePayload::Data(
+                                        data.clone(),
+                                    )
+                                }
+                                Payload::Reject(reject) => {
+                                    pb_metadata::sign_with_threshold_batch_reply::ResponsePayload::Reject(
+                                        reject.into(),
+                                    )
+                                }
+                            }),
+                        }
+                    })
+                })
+                .collect(),
+            canister_migration_contexts: item
+                .canister_migration_contexts
+                .iter()
+                .map(
+                    |(callback_id, context)| pb_metadata::CanisterMigrationContextTree {
+                        callback_id: callback_id.get(),
+                        context: Some(context.into()),
+                    },
+                )
+                .collect(),
         }
     }
 }
@@ -655,6 +764,35 @@ impl TryFrom<(Time, pb_metadata::SubnetCallContextManager)> for SubnetCallContex
             raw_rand_contexts.push_back(context);
         }
 
+        let mut sign_with_threshold_batch_replies =
+            BTreeMap::<CallbackId, BTreeMap<u32, Payload>>::new();
+        for entry in item.sign_with_threshold_batch_replies {
+            let payload = match try_from_option_field(
+                entry.response_payload,
+                "SignWithThresholdBatchReply::response_payload",
+            )? {
+                pb_metadata::sign_with_threshold_batch_reply::ResponsePayload::Data(data) => {
+                    Payload::Data(data)
+                }
+                pb_metadata::sign_with_threshold_batch_reply::ResponsePayload::Reject(reject) => {
+                    Payload::Reject(reject.try_into()?)
+                }
+            };
+            sign_with_threshold_batch_replies
+                .entry(CallbackId::new(entry.batch_id))
+                .or_default()
+                .insert(entry.index, payload);
+        }
+
+        let mut canister_migration_contexts =
+            BTreeMap::<CallbackId, CanisterMigrationContext>::new();
+        for entry in item.canister_migration_contexts {
+            let pb_context =
+                try_from_option_field(entry.context, "SystemMetadata::CanisterMigrationContext")?;
+            let context = CanisterMigrationContext::try_from((time, pb_context))?;
+            canister_migration_contexts.insert(CallbackId::new(entry.callback_id), context);
+        }
+
         Ok(Self {
             next_callback_id: item.next_callback_id,
             setup_initial_dkg_contexts,
@@ -668,6 +806,8 @@ impl TryFrom<(Time, pb_metadata::SubnetCallContextManager)> for SubnetCallContex
             },
             raw_rand_contexts,
             idkg_dealings_contexts,
+            sign_with_threshold_batch_replies,
+            canister_migration_contexts,
         })
     }
 }
@@ -861,6 +1001,8 @@ pub struct SignWithThresholdContext {
     pub batch_time: Time,
     pub matched_pre_signature: Option<(PreSigId, Height)>,
     pub nonce: Option<[u8; NONCE_SIZE]>,
+    /// Set if this context is one entry of a batched signing request.
+    pub batch: Option<SignatureBatchEntry>,
 }
 
 impl SignWithThresholdContext {
@@ -914,6 +1056,7 @@ impl From<&SignWithThresholdContext> for pb_metadata::SignWithThresholdContext {
             pre_signature_id: context.matched_pre_signature.as_ref().map(|q| q.0.id()),
             height: context.matched_pre_signature.as_ref().map(|q| q.1.get()),
             nonce: context.nonce.map(|n| n.to_vec()),
+            batch: context.batch.as_ref().map(|batch| batch.into()),
         }
     }
 }
@@ -937,10 +1080,46 @@ impl TryFrom<pb_metadata::SignWithThresholdContext> for SignWithThresholdContext
                 .zip(context.height)
                 .map(|(q, h)| (q, Height::from(h))),
             nonce: context.nonce.map(try_into_array_nonce).transpose()?,
+            batch: context.batch.map(SignatureBatchEntry::from),
         })
     }
 }
 
+/// Identifies one entry of a batched threshold signing request.
+///
+/// All entries of a batch are stored as separate `SignWithThresholdContext`s
+/// under consecutive callback ids, so that each of them is accounted for in
+/// the signing queue and gets its own pre-signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignatureBatchEntry {
+    /// The callback id of the first context of the batch.
+    pub batch_id: CallbackId,
+    /// The position of this entry's message in the batch.
+    pub index: u32,
+    /// The number of messages in the batch.
+    pub size: u32,
+}
+
+impl From<&SignatureBatchEntry> for pb_metadata::SignatureBatchEntry {
+    fn from(entry: &SignatureBatchEntry) -> Self {
+        Self {
+            batch_id: entry.batch_id.get(),
+            index: entry.index,
+            size: entry.size,
+        }
+    }
+}
+
+impl From<pb_metadata::SignatureBatchEntry> for SignatureBatchEntry {
+    fn from(entry: pb_metadata::SignatureBatchEntry) -> Self {
+        Self {
+            batch_id: CallbackId::new(entry.batch_id),
+            index: entry.index,
+            size: entry.size,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct IDkgDealingsContext {
     pub request: Request,
@@ -1210,6 +1389,85 @@ impl TryFrom<(Time, pb_metadata::StopCanisterCall)> for StopCanisterCall {
     }
 }
 
+/// Tracks the migration of a canister to another subnet, from the
+/// `migrate_canister` call until the target subnet acknowledged the last step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CanisterMigrationContext {
+    /// The `migrate_canister` call, replied to once the migration is over.
+    pub call: CanisterCall,
+    pub canister_id: CanisterId,
+    pub target_subnet_id: SubnetId,
+    /// Index of the step sent with the `import_canister_state` request in flight.
+    pub step: u64,
+    /// The balances sent to the target subnet with the last step, if it was
+    /// already sent. Used to resend them if they changed in the meantime.
+    pub sent_balances: Option<(Cycles, Cycles)>,
+    pub time: Time,
+}
+
+impl From<&CanisterMigrationContext> for pb_metadata::CanisterMigrationContext {
+    fn from(context: &CanisterMigrationContext) -> Self {
+        use pb_metadata::canister_migration_context::CanisterCall as PbCanisterCall;
+        let call = match &context.call {
+            CanisterCall::Request(request) => PbCanisterCall::Request(request.as_ref().into()),
+            CanisterCall::Ingress(ingress) => PbCanisterCall::Ingress(ingress.as_ref().into()),
+        };
+        Self {
+            canister_call: Some(call),
+            time: Some(pb_metadata::Time {
+                time_nanos: context.time.as_nanos_since_unix_epoch(),
+            }),
+            canister_id: Some(context.canister_id.into()),
+            target_subnet_id: Some(subnet_id_into_protobuf(context.target_subnet_id)),
+            step: context.step,
+            sent_cycles_balance: context.sent_balances.map(|(balance, _)| balance.into()),
+            sent_reserved_balance: context
+                .sent_balances
+                .map(|(_, reserved_balance)| reserved_balance.into()),
+        }
+    }
+}
+
+impl TryFrom<(Time, pb_metadata::CanisterMigrationContext)> for CanisterMigrationContext {
+    type Error = ProxyDecodeError;
+    fn try_from(
+        (time, context): (Time, pb_metadata::CanisterMigrationContext),
+    ) -> Result<Self, Self::Error> {
+        use pb_metadata::canister_migration_context::CanisterCall as PbCanisterCall;
+        let call = match try_from_option_field(
+            context.canister_call,
+            "CanisterMigrationContext::canister_call",
+        )? {
+            PbCanisterCall::Request(request) => {
+                CanisterCall::Request(Arc::new(request.try_into()?))
+            }
+            PbCanisterCall::Ingress(ingress) => {
+                CanisterCall::Ingress(Arc::new(ingress.try_into()?))
+            }
+        };
+        let canister_id =
+            try_from_option_field(context.canister_id, "CanisterMigrationContext::canister_id")?;
+        let sent_balances = match (context.sent_cycles_balance, context.sent_reserved_balance) {
+            (Some(balance), Some(reserved_balance)) => {
+                Some((balance.into(), reserved_balance.into()))
+            }
+            _ => None,
+        };
+        Ok(CanisterMigrationContext {
+            call,
+            canister_id,
+            target_subnet_id: subnet_id_try_from_protobuf(context.target_subnet_id.ok_or(
+                ProxyDecodeError::MissingField("CanisterMigrationContext::target_subnet_id"),
+            )?)?,
+            step: context.step,
+            sent_balances,
+            time: context
+                .time
+                .map_or(time, |t| Time::from_nanos_since_unix_epoch(t.time_nanos)),
+        })
+    }
+}
+
 /// Struct for tracking the required information needed for creating a response.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct RawRandContext {
@@ -1276,12 +1534,14 @@ mod testing {
             next_callback_id: 0,
             setup_initial_dkg_contexts: Default::default(),
             sign_with_threshold_contexts: Default::default(),
+            sign_with_threshold_batch_replies: Default::default(),
             canister_http_request_contexts: Default::default(),
             idkg_dealings_contexts: Default::default(),
             bitcoin_get_successors_contexts: Default::default(),
             bitcoin_send_transaction_internal_contexts: Default::default(),
             canister_management_calls,
             raw_rand_contexts: Default::default(),
+            canister_migration_contexts: Default::default(),
         };
     }
 }
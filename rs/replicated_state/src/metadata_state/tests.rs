@@ -1,10 +1,11 @@
 use super::*;
 use crate::metadata_state::subnet_call_context_manager::{
-    InstallCodeCall, RawRandContext, StopCanisterCall, SubnetCallContext, SubnetCallContextManager,
+    EcdsaArguments, InstallCodeCall, RawRandContext, SignWithThresholdContext, StopCanisterCall,
+    SubnetCallContext, SubnetCallContextManager, ThresholdArguments,
 };
 use assert_matches::assert_matches;
 use ic_constants::MAX_INGRESS_TTL;
-use ic_error_types::{ErrorCode, UserError};
+use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_management_canister_types::{EcdsaCurve, EcdsaKeyId, MasterPublicKeyId, IC_00};
 use ic_registry_routing_table::CanisterIdRange;
 use ic_test_utilities_types::{
@@ -19,7 +20,7 @@ use ic_types::{
     batch::BlockmakerMetrics,
     canister_http::{CanisterHttpMethod, CanisterHttpRequestContext},
     ingress::WasmResult,
-    messages::{CallbackId, CanisterCall, Payload, Request, RequestMetadata},
+    messages::{CallbackId, CanisterCall, Payload, RejectContext, Request, RequestMetadata},
     time::CoarseTime,
     Cycles, ExecutionRound,
 };
@@ -802,6 +803,85 @@ fn subnet_call_contexts_deserialization() {
     )
 }
 
+#[test]
+fn sign_with_threshold_batch_replies_are_collected_in_order() {
+    let mut subnet_call_context_manager = SubnetCallContextManager::default();
+    let request = RequestBuilder::default()
+        .sender(canister_test_id(1))
+        .receiver(IC_00)
+        .build();
+    let contexts = (0..3u8)
+        .map(|i| SignWithThresholdContext {
+            request: request.clone(),
+            args: ThresholdArguments::Ecdsa(EcdsaArguments {
+                key_id: make_key_id(),
+                message_hash: [i; 32],
+            }),
+            derivation_path: vec![],
+            pseudo_random_id: [0; 32],
+            batch_time: UNIX_EPOCH,
+            matched_pre_signature: None,
+            nonce: None,
+            batch: None,
+        })
+        .collect();
+    let batch_id = subnet_call_context_manager.push_sign_with_threshold_batch(contexts);
+    assert_eq!(batch_id, CallbackId::from(0));
+    assert_eq!(
+        subnet_call_context_manager
+            .sign_with_threshold_contexts_count(&MasterPublicKeyId::Ecdsa(make_key_id())),
+        3
+    );
+
+    // Complete the entries out of order.
+    let entries: Vec<_> = subnet_call_context_manager
+        .sign_with_threshold_contexts
+        .values()
+        .map(|context| context.batch.unwrap())
+        .collect();
+    assert_eq!(
+        entries.iter().map(|e| e.index).collect::<Vec<_>>(),
+        vec![0, 1, 2]
+    );
+    let reject = Payload::Reject(RejectContext::new(
+        RejectCode::CanisterReject,
+        "Signature request expired",
+    ));
+    assert_eq!(
+        subnet_call_context_manager
+            .complete_sign_with_threshold_batch_entry(entries[2], Payload::Data(vec![2])),
+        None
+    );
+    assert_eq!(
+        subnet_call_context_manager
+            .complete_sign_with_threshold_batch_entry(entries[1], reject.clone()),
+        None
+    );
+
+    // Pending replies survive a round trip through the protobuf representation.
+    let proto: ic_protobuf::state::system_metadata::v1::SubnetCallContextManager =
+        (&subnet_call_context_manager).into();
+    let mut subnet_call_context_manager =
+        SubnetCallContextManager::try_from((UNIX_EPOCH, proto)).unwrap();
+    assert_eq!(
+        subnet_call_context_manager
+            .sign_with_threshold_contexts
+            .values()
+            .map(|context| context.batch.unwrap())
+            .collect::<Vec<_>>(),
+        entries
+    );
+
+    assert_eq!(
+        subnet_call_context_manager
+            .complete_sign_with_threshold_batch_entry(entries[0], Payload::Data(vec![0])),
+        Some(vec![Payload::Data(vec![0]), reject, Payload::Data(vec![2])])
+    );
+    assert!(subnet_call_context_manager
+        .sign_with_threshold_batch_replies
+        .is_empty());
+}
+
 #[test]
 fn empty_network_topology() {
     let network_topology = NetworkTopology {
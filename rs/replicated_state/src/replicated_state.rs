@@ -27,7 +27,9 @@ use ic_registry_subnet_type::SubnetType;
 use ic_types::{
     batch::{ConsensusResponse, RawQueryStats},
     ingress::IngressStatus,
-    messages::{CallbackId, CanisterMessage, Ingress, MessageId, RequestOrResponse, Response},
+    messages::{
+        CallbackId, CanisterMessage, Ingress, MessageId, Request, RequestOrResponse, Response,
+    },
     time::CoarseTime,
     CanisterId, MemoryAllocation, NumBytes, SubnetId, Time,
 };
@@ -589,6 +591,8 @@ impl ReplicatedState {
             mut guaranteed_response_message_memory_taken,
             wasm_custom_sections_memory_taken,
             canister_history_memory_taken,
+            environment_variables_memory_taken,
+            canister_profile_memory_taken,
             wasm_chunk_store_memory_usage,
         ) = self
             .canisters_iter()
@@ -603,6 +607,8 @@ impl ReplicatedState {
                         .guaranteed_response_message_memory_usage(),
                     canister.wasm_custom_sections_memory_usage(),
                     canister.canister_history_memory_usage(),
+                    canister.environment_variables_memory_usage(),
+                    canister.canister_profile_memory_usage(),
                     canister.wasm_chunk_store_memory_usage(),
                 )
             })
@@ -613,6 +619,8 @@ impl ReplicatedState {
                     accum.2 + val.2,
                     accum.3 + val.3,
                     accum.4 + val.4,
+                    accum.5 + val.5,
+                    accum.6 + val.6,
                 )
             })
             .unwrap_or_default();
@@ -625,6 +633,8 @@ impl ReplicatedState {
         MemoryTaken {
             execution: raw_memory_taken
                 + canister_history_memory_taken
+                + environment_variables_memory_taken
+                + canister_profile_memory_taken
                 + wasm_chunk_store_memory_usage
                 + canister_snapshots_memory_taken,
             guaranteed_response_messages: guaranteed_response_message_memory_taken,
@@ -772,6 +782,17 @@ impl ReplicatedState {
         self.subnet_queues.push_output_response(msg)
     }
 
+    /// Pushes a `Request` sent by the subnet itself (e.g. to stream the state of
+    /// a migrating canister) into the relevant subnet output queue, reserving a
+    /// slot for the response.
+    pub fn push_subnet_output_request(
+        &mut self,
+        msg: Arc<Request>,
+        time: Time,
+    ) -> Result<(), (StateError, Arc<Request>)> {
+        self.subnet_queues.push_output_request(msg, time)
+    }
+
     /// Returns a circular iterator that consumes messages from all canisters'
     /// and the subnet's output queues.
     ///
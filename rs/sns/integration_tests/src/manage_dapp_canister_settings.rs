@@ -107,6 +107,8 @@ fn test_manage_dapp_canister_settings_successful() {
             Some(1_000_000_000_000),
             ic_management_canister_types::LogVisibilityV2::Public,
             Some(1_000_000_000),
+            vec![],
+            false,
         ),
     );
 
@@ -160,6 +162,8 @@ fn test_manage_dapp_canister_settings_successful() {
             Some(0),
             ic_management_canister_types::LogVisibilityV2::Controllers,
             Some(2_000_000_000),
+            vec![],
+            false,
         ),
     );
 }
@@ -237,6 +241,8 @@ fn test_manage_dapp_canister_settings_failure() {
             Some(1_000_000_000_000),
             ic_management_canister_types::LogVisibilityV2::Public,
             Some(1_000_000_000),
+            vec![],
+            false,
         ),
     );
 
@@ -310,6 +316,8 @@ fn test_manage_dapp_canister_settings_failure() {
             Some(1_000_000_000_000),
             ic_management_canister_types::LogVisibilityV2::Public,
             Some(1_000_000_000),
+            vec![],
+            false,
         ),
     );
 
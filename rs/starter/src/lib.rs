@@ -16,6 +16,9 @@ pub fn hypervisor_config(canister_sandboxing: bool) -> HypervisorConfig {
                 rate_limiting_of_debug_prints: FlagStatus::Disabled,
                 best_effort_responses: FlagStatus::Enabled,
                 wasm64: FlagStatus::Enabled,
+                canister_profiling: FlagStatus::Enabled,
+                wasm_tail_call_and_extended_const: FlagStatus::Enabled,
+                wasm_function_references: FlagStatus::Enabled,
                 ..FeatureFlags::default()
             },
             ..EmbeddersConfig::default()
@@ -102,6 +102,8 @@ fn main() -> Result<()> {
                         .map(|key_id| KeyConfig {
                             key_id: key_id.clone(),
                             max_queue_size: 64,
+                            min_pre_signatures_to_create: None,
+                            max_pre_signatures_to_create: None,
                             pre_signatures_to_create_in_advance: 1,
                         })
                         .collect(),
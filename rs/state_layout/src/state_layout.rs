@@ -24,9 +24,9 @@ use ic_replicated_state::{
 };
 use ic_sys::{fs::sync_path, mmap::ScopedMmap};
 use ic_types::{
-    batch::TotalQueryStats, nominal_cycles::NominalCycles, AccumulatedPriority, CanisterId,
-    CanisterLog, ComputeAllocation, Cycles, ExecutionRound, Height, LongExecutionMode,
-    MemoryAllocation, NumInstructions, PrincipalId, SnapshotId, Time,
+    batch::TotalQueryStats, canister_profile::CanisterProfile, nominal_cycles::NominalCycles,
+    AccumulatedPriority, CanisterId, CanisterLog, ComputeAllocation, Cycles, ExecutionRound,
+    Height, LongExecutionMode, MemoryAllocation, NumInstructions, PrincipalId, SnapshotId, Time,
 };
 use ic_utils::thread::parallel_map;
 use ic_wasm_types::{CanisterModule, WasmHash};
@@ -173,6 +173,8 @@ pub struct CanisterStateBits {
     pub log_visibility: LogVisibilityV2,
     pub canister_log: CanisterLog,
     pub wasm_memory_limit: Option<NumBytes>,
+    pub environment_variables: BTreeMap<String, String>,
+    pub canister_profile: Option<CanisterProfile>,
     pub next_snapshot_id: u64,
     pub snapshots_memory_usage: NumBytes,
 }
@@ -2282,6 +2284,8 @@ impl From<CanisterStateBits> for pb_canister_state_bits::CanisterStateBits {
                 .collect(),
             next_canister_log_record_idx: item.canister_log.next_idx(),
             wasm_memory_limit: item.wasm_memory_limit.map(|v| v.get()),
+            environment_variables: item.environment_variables,
+            canister_profile: item.canister_profile.as_ref().map(|profile| profile.into()),
             next_snapshot_id: item.next_snapshot_id,
             snapshots_memory_usage: item.snapshots_memory_usage.get(),
         }
@@ -2457,6 +2461,8 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
                     .collect(),
             ),
             wasm_memory_limit: value.wasm_memory_limit.map(NumBytes::from),
+            environment_variables: value.environment_variables,
+            canister_profile: value.canister_profile.map(CanisterProfile::from),
             next_snapshot_id: value.next_snapshot_id,
             snapshots_memory_usage: NumBytes::from(value.snapshots_memory_usage),
         })
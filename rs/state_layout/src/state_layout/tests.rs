@@ -56,6 +56,8 @@ fn default_canister_state_bits() -> CanisterStateBits {
         log_visibility: Default::default(),
         canister_log: Default::default(),
         wasm_memory_limit: None,
+        environment_variables: BTreeMap::new(),
+        canister_profile: None,
         next_snapshot_id: 0,
         snapshots_memory_usage: NumBytes::from(0),
     }
@@ -118,6 +120,28 @@ fn test_encode_decode_non_empty_controllers() {
     assert_eq!(canister_state_bits.controllers, expected_controllers);
 }
 
+#[test]
+fn test_encode_decode_environment_variables() {
+    let environment_variables = BTreeMap::from([
+        ("LOG_LEVEL".to_string(), "debug".to_string()),
+        ("NETWORK".to_string(), "staging".to_string()),
+    ]);
+
+    // A canister state with environment variables.
+    let canister_state_bits = CanisterStateBits {
+        environment_variables: environment_variables.clone(),
+        ..default_canister_state_bits()
+    };
+
+    let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+    let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+    assert_eq!(
+        canister_state_bits.environment_variables,
+        environment_variables
+    );
+}
+
 #[test]
 fn test_encode_decode_empty_history() {
     let canister_history = CanisterHistory::default();
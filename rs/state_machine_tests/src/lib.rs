@@ -17,6 +17,10 @@ use ic_crypto_utils_threshold_sig_der::threshold_sig_public_key_to_der;
 use ic_cycles_account_manager::CyclesAccountManager;
 pub use ic_error_types::{ErrorCode, UserError};
 use ic_execution_environment::{ExecutionServices, IngressHistoryReaderImpl};
+pub use ic_execution_environment::{
+    MessageExecutionRecord, MessageExecutionTracer, TracedCall, TracedCallOrigin, TracedInput,
+    TracedResponse, MAX_MESSAGE_EXECUTION_RECORDS,
+};
 use ic_http_endpoints_public::{metrics::HttpHandlerMetrics, IngressWatcher, IngressWatcherHandle};
 use ic_https_outcalls_consensus::payload_builder::CanisterHttpPayloadBuilderImpl;
 use ic_ingress_manager::{IngressManager, RandomStateKind};
@@ -40,7 +44,7 @@ use ic_interfaces_state_manager::{CertificationScope, StateHashError, StateManag
 use ic_logger::{error, ReplicaLogger};
 use ic_management_canister_types::{
     self as ic00, CanisterIdRecord, InstallCodeArgs, LoadCanisterSnapshotArgs, MasterPublicKeyId,
-    Method, Payload,
+    Method, Payload, MAXIMUM_SIGNATURE_BATCH_SIZE,
 };
 pub use ic_management_canister_types::{
     CanisterHttpResponsePayload, CanisterInstallMode, CanisterSettingsArgs,
@@ -127,6 +131,7 @@ use ic_types::{
     time::GENESIS,
     xnet::{CertifiedStreamSlice, StreamIndex},
     CanisterLog, CountBytes, CryptoHashOfPartialState, Height, NodeId, Randomness, RegistryVersion,
+    SnapshotId,
 };
 pub use ic_types::{
     canister_http::{
@@ -336,6 +341,10 @@ fn make_nodes_registry(
                     key_id: key_id.clone(),
                     pre_signatures_to_create_in_advance: 1,
                     max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+                    min_pre_signatures_to_create: None,
+                    // Signatures are produced without pre-signatures here, so batches of
+                    // any admissible size must be accepted.
+                    max_pre_signatures_to_create: Some(MAXIMUM_SIGNATURE_BATCH_SIZE as u32),
                 })
                 .collect(),
             signature_request_timeout_ns: None,
@@ -384,6 +393,32 @@ fn into_cbor<R: Serialize>(r: &R) -> Vec<u8> {
     ser.into_inner()
 }
 
+/// Copies all files of the directory `src` (which must only contain files) into
+/// the directory `dst`, creating `dst` if needed and making the copies writeable.
+fn copy_dir_as_writeable(src: &Path, dst: &Path) {
+    std::fs::create_dir_all(dst).expect("Failed to create checkpoint dir");
+    for entry in std::fs::read_dir(src).expect("failed to read_dir") {
+        let entry = entry.expect("failed to get directory entry");
+        let src_file = entry.path();
+        let dst_file = dst.join(entry.file_name());
+        assert!(
+            src_file.is_file(),
+            "Canister layout contains only files, but {} is not a file.",
+            src_file.display()
+        );
+        std::fs::copy(&src_file, &dst_file).expect("failed to copy file");
+        let file = std::fs::File::open(&dst_file).expect("failed to open file");
+        let mut permissions = file
+            .metadata()
+            .expect("failed to get file permission")
+            .permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        file.set_permissions(permissions)
+            .expect("failed to set file persmission");
+    }
+}
+
 fn replica_logger() -> ReplicaLogger {
     use slog::Drain;
     let log_level = std::env::var("RUST_LOG")
@@ -754,6 +789,7 @@ pub struct StateMachine {
     /// A drop guard to gracefully cancel the ingress watcher task.
     _ingress_watcher_drop_guard: tokio_util::sync::DropGuard,
     query_stats_payload_builder: Arc<PocketQueryStatsPayloadBuilderImpl>,
+    message_execution_tracer: Arc<MessageExecutionTracer>,
     // This field must be the last one so that the temporary directory is deleted at the very end.
     state_dir: Box<dyn StateMachineStateDir>,
     // DO NOT PUT ANY FIELDS AFTER `state_dir`!!!
@@ -1676,6 +1712,7 @@ impl StateMachine {
                 .block_on(async { TowerBuffer::new(execution_services.ingress_filter, 1) }),
             payload_builder: Arc::new(RwLock::new(None)), // set by `StateMachineBuilder::build_with_subnets`
             ingress_history_reader: execution_services.ingress_history_reader,
+            message_execution_tracer: execution_services.message_execution_tracer,
             message_routing,
             metrics_registry: metrics_registry.clone(),
             query_handler: runtime.block_on(async {
@@ -1713,6 +1750,33 @@ impl StateMachine {
         )
     }
 
+    /// Enables or disables recording of all message executions on this subnet.
+    /// Recording is disabled by default.
+    pub fn set_message_execution_tracing(&self, enabled: bool) {
+        self.message_execution_tracer.set_enabled(enabled);
+    }
+
+    /// Returns all message executions recorded since the last call of this function.
+    pub fn take_message_execution_records(&self) -> Vec<MessageExecutionRecord> {
+        self.message_execution_tracer.take_records()
+    }
+
+    /// Returns the path of the state directory of this `StateMachine`.
+    /// The checkpoints in this directory are only complete after running
+    /// `state_machine.checkpointed_tick()` followed by `state_machine.await_state_hash()`.
+    pub fn state_dir_path(&self) -> PathBuf {
+        self.state_dir.path()
+    }
+
+    /// Consumes this `StateMachine` and returns its state directory
+    /// so that a new `StateMachine` can be built from its latest checkpoint.
+    /// To preserve the latest state, you need to run `state_machine.checkpointed_tick()`
+    /// followed by `state_machine.await_state_hash()` before calling this function.
+    pub fn into_state_dir(self) -> Box<dyn StateMachineStateDir> {
+        let (state_dir, _nonce, _time, _checkpoint_interval_length) = self.into_components();
+        state_dir
+    }
+
     /// Emulates a node restart, including checkpoint recovery.
     pub fn restart_node(self) -> Self {
         // We must drop self before setup_form_dir so that we don't have two StateManagers pointing
@@ -1962,7 +2026,25 @@ impl StateMachine {
         canister_id: CanisterId,
         content: CanisterHttpResponseContent,
     ) {
-        for node in &self.nodes {
+        self.mock_canister_http_response_per_node(request_id, timeout, canister_id, vec![content])
+    }
+
+    /// Like `mock_canister_http_response`, but the nodes respond with (possibly) different
+    /// contents, e.g., to test how a canister handles divergent responses. The contents are
+    /// assigned to the nodes in order and the last content is used for all remaining nodes.
+    pub fn mock_canister_http_response_per_node(
+        &self,
+        request_id: u64,
+        timeout: Time,
+        canister_id: CanisterId,
+        contents: Vec<CanisterHttpResponseContent>,
+    ) {
+        assert!(
+            !contents.is_empty(),
+            "At least one response content must be provided."
+        );
+        for (i, node) in self.nodes.iter().enumerate() {
+            let content = contents.get(i).unwrap_or(contents.last().unwrap());
             let registry_version = self.registry_client.get_latest_version();
             let response = CanisterHttpResponse {
                 id: CanisterHttpRequestId::from(request_id),
@@ -2427,7 +2509,23 @@ impl StateMachine {
         canister_directory: P,
         canister_id: CanisterId,
     ) {
-        let canister_directory = canister_directory.as_ref();
+        self.import_canister_state_and_snapshots(canister_directory.as_ref(), canister_id, vec![]);
+    }
+
+    /// Imports a directory containing a canister state and the directories
+    /// containing the canister's snapshots (as stored in a checkpoint) into
+    /// the state machine in a single round.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if loading the canister state or any of the
+    /// snapshots fails.
+    fn import_canister_state_and_snapshots(
+        &self,
+        canister_directory: &Path,
+        canister_id: CanisterId,
+        snapshot_directories: Vec<(SnapshotId, PathBuf)>,
+    ) {
         assert!(
             canister_directory.is_dir(),
             "canister state at {} must be a directory",
@@ -2442,34 +2540,7 @@ impl StateMachine {
         let tip_canister_layout = tip
             .canister(&canister_id)
             .expect("failed to obtain canister layout");
-        std::fs::create_dir_all(tip_canister_layout.raw_path())
-            .expect("Failed to create checkpoint dir");
-
-        fn copy_as_writeable(src: &Path, dst: &Path) {
-            assert!(
-                src.is_file(),
-                "Canister layout contains only files, but {} is not a file.",
-                src.display()
-            );
-            std::fs::copy(src, dst).expect("failed to copy file");
-            let file = std::fs::File::open(dst).expect("failed to open file");
-            let mut permissions = file
-                .metadata()
-                .expect("failed to get file permission")
-                .permissions();
-            #[allow(clippy::permissions_set_readonly_false)]
-            permissions.set_readonly(false);
-            file.set_permissions(permissions)
-                .expect("failed to set file persmission");
-        }
-
-        for entry in std::fs::read_dir(canister_directory).expect("failed to read_dir") {
-            let entry = entry.expect("failed to get directory entry");
-            copy_as_writeable(
-                &entry.path(),
-                &tip_canister_layout.raw_path().join(entry.file_name()),
-            );
-        }
+        copy_dir_as_writeable(canister_directory, &tip_canister_layout.raw_path());
 
         // A `CheckpointLoadingMetrics` that panics on broken soft invariants.
         struct StrictCheckpointLoadingMetrics;
@@ -2495,8 +2566,36 @@ impl StateMachine {
         })
         .0;
 
+        let mut snapshots = vec![];
+        for (snapshot_id, snapshot_directory) in snapshot_directories {
+            let tip_snapshot_layout = tip
+                .snapshot(&snapshot_id)
+                .expect("failed to obtain snapshot layout");
+            copy_dir_as_writeable(&snapshot_directory, &tip_snapshot_layout.raw_path());
+            let snapshot = ic_state_manager::checkpoint::load_snapshot(
+                &tip_snapshot_layout,
+                &snapshot_id,
+                ic_types::Height::new(0),
+                self.state_manager.get_fd_factory(),
+            )
+            .unwrap_or_else(|e| {
+                panic!(
+                    "failed to load canister snapshot from {}: {}",
+                    snapshot_directory.display(),
+                    e
+                )
+            })
+            .0;
+            snapshots.push((snapshot_id, Arc::new(snapshot)));
+        }
+
         let (h, mut state) = self.state_manager.take_tip();
         state.put_canister_state(canister_state);
+        for (snapshot_id, snapshot) in snapshots {
+            state
+                .canister_snapshots
+                .insert_migrated(snapshot_id, snapshot);
+        }
         self.state_manager.commit_and_certify(
             state,
             h.increment(),
@@ -2538,9 +2637,10 @@ impl StateMachine {
         self.state_manager.remove_states_below(h);
     }
 
-    /// Removes a canister state from this state machine and migrates it to another state machine.
-    /// This is done by writing a checkpoint and then removing the canister state from `self`;
-    /// then importing the canister state into `other_env` from the checkpoint.
+    /// Removes a canister state and the canister's snapshots from this state machine and
+    /// migrates them to another state machine.
+    /// This is done by writing a checkpoint and then removing the canister state and snapshots
+    /// from `self`; then importing them into `other_env` from the checkpoint.
     pub fn move_canister_state_to(
         &self,
         other_env: &StateMachine,
@@ -2550,6 +2650,13 @@ impl StateMachine {
 
         let (height, mut state) = self.state_manager.take_tip();
         if state.take_canister_state(&canister_id).is_some() {
+            let snapshot_ids: Vec<SnapshotId> = state
+                .canister_snapshots
+                .list_snapshots(canister_id)
+                .into_iter()
+                .map(|(snapshot_id, _)| snapshot_id)
+                .collect();
+            state.canister_snapshots.delete_snapshots(canister_id);
             self.state_manager.commit_and_certify(
                 state,
                 height.increment(),
@@ -2558,15 +2665,22 @@ impl StateMachine {
             );
             self.state_manager.flush_tip_channel();
 
-            other_env.import_canister_state(
-                self.state_manager
-                    .state_layout()
-                    .checkpoint_verified(height)
-                    .unwrap()
-                    .canister(&canister_id)
-                    .unwrap()
-                    .raw_path(),
+            let checkpoint = self
+                .state_manager
+                .state_layout()
+                .checkpoint_verified(height)
+                .unwrap();
+            let snapshot_directories = snapshot_ids
+                .into_iter()
+                .map(|snapshot_id| {
+                    let snapshot_directory = checkpoint.snapshot(&snapshot_id).unwrap().raw_path();
+                    (snapshot_id, snapshot_directory)
+                })
+                .collect();
+            other_env.import_canister_state_and_snapshots(
+                &checkpoint.canister(&canister_id).unwrap().raw_path(),
                 canister_id,
+                snapshot_directories,
             );
 
             return Ok(());
@@ -1,4 +1,10 @@
-use ic_config::{execution_environment::Config as HypervisorConfig, subnet_config::SubnetConfig};
+use ic_config::{
+    execution_environment::Config as HypervisorConfig, flag_status::FlagStatus,
+    subnet_config::SubnetConfig,
+};
+use ic_management_canister_types::{
+    self as ic00, MigrateCanisterArgs, Payload, TakeCanisterSnapshotArgs,
+};
 use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
 use ic_registry_routing_table::{CanisterIdRange, RoutingTable, CANISTER_IDS_PER_SUBNET};
 use ic_registry_subnet_type::SubnetType;
@@ -7,7 +13,7 @@ use ic_state_machine_tests::{
 };
 use ic_test_utilities_types::ids::user_test_id;
 use ic_types::{
-    ingress::{IngressStatus, WasmResult},
+    ingress::{IngressState, IngressStatus, WasmResult},
     CanisterId, Cycles, SubnetId,
 };
 use ic_universal_canister::{wasm, CallArgs, UNIVERSAL_CANISTER_WASM};
@@ -379,3 +385,187 @@ fn counter_canister_call_test() {
         _ => panic!("unreachable"),
     };
 }
+
+#[test]
+fn migrate_canister_to_another_subnet() {
+    const MAX_ROUNDS: usize = 200;
+    let user_id = user_test_id(1).get();
+
+    let registry_data_provider = Arc::new(ProtoRegistryDataProvider::new());
+    let subnets = Arc::new(RwLock::new(BTreeMap::new()));
+    let setup = |subnet_seed| {
+        let hypervisor_config = HypervisorConfig {
+            canister_snapshots: FlagStatus::Enabled,
+            ..Default::default()
+        };
+        let config = StateMachineConfig::new(
+            SubnetConfig::new(SubnetType::Application),
+            hypervisor_config,
+        );
+        StateMachineBuilder::new()
+            .with_config(Some(config))
+            .with_subnet_seed([subnet_seed; 32])
+            .with_registry_data_provider(registry_data_provider.clone())
+            .build_with_subnets(subnets.clone())
+    };
+    let env1 = setup(1);
+    let env2 = setup(2);
+
+    let subnet_id1 = env1.get_subnet_id();
+    let subnet_id2 = env2.get_subnet_id();
+    let mut routing_table = RoutingTable::new();
+    routing_table
+        .insert(
+            CanisterIdRange {
+                start: CanisterId::from_u64(0),
+                end: CanisterId::from_u64(CANISTER_IDS_PER_SUBNET - 1),
+            },
+            subnet_id1,
+        )
+        .unwrap();
+    routing_table
+        .insert(
+            CanisterIdRange {
+                start: CanisterId::from_u64(CANISTER_IDS_PER_SUBNET),
+                end: CanisterId::from_u64(2 * CANISTER_IDS_PER_SUBNET - 1),
+            },
+            subnet_id2,
+        )
+        .unwrap();
+    finalize_registry(
+        subnet_id1,
+        routing_table,
+        vec![subnet_id1, subnet_id2],
+        registry_data_provider,
+    );
+    env1.reload_registry();
+    env2.reload_registry();
+
+    // Install a canister on the 1st subnet with some state and a snapshot.
+    let canister_id = env1
+        .install_canister_with_cycles(
+            UNIVERSAL_CANISTER_WASM.to_vec(),
+            vec![],
+            None,
+            INITIAL_CYCLES_BALANCE,
+        )
+        .unwrap();
+    let controller = *env1
+        .get_latest_state()
+        .canister_state(&canister_id)
+        .unwrap()
+        .controllers()
+        .iter()
+        .next()
+        .unwrap();
+    let update = |env: &StateMachine, payload: Vec<u8>| match env.execute_ingress_as(
+        user_id,
+        canister_id,
+        "update",
+        payload,
+    ) {
+        Ok(WasmResult::Reply(bytes)) => bytes,
+        result => panic!("Unexpected result: {:?}", result),
+    };
+    update(
+        &env1,
+        wasm()
+            .set_global_data(b"before snapshot")
+            .stable_grow(1)
+            .stable_write(0, b"stable data")
+            .reply()
+            .build(),
+    );
+    let snapshot_id = env1
+        .take_canister_snapshot(TakeCanisterSnapshotArgs::new(canister_id, None))
+        .unwrap()
+        .snapshot_id();
+    update(
+        &env1,
+        wasm()
+            .set_global_data(b"after snapshot")
+            .certified_data_set(b"certified")
+            .reply()
+            .build(),
+    );
+    env1.stop_canister_as(controller, canister_id).unwrap();
+
+    // Registry lists the canister as migrating from the 1st to the 2nd subnet.
+    env1.prepare_canister_migrations(canister_id..=canister_id, subnet_id1, subnet_id2);
+    env2.reload_registry();
+
+    let balance = env1.cycle_balance(canister_id);
+    let msg_id = env1
+        .submit_ingress_as(
+            controller,
+            CanisterId::ic_00(),
+            ic00::Method::MigrateCanister,
+            MigrateCanisterArgs::new(canister_id, subnet_id2).encode(),
+        )
+        .unwrap();
+    let mut rounds = 0;
+    let result = loop {
+        env1.execute_round();
+        env2.execute_round();
+        if let IngressStatus::Known { state, .. } = env1.ingress_status(&msg_id) {
+            match state {
+                IngressState::Completed(result) => break Ok(result),
+                IngressState::Failed(err) => break Err(err),
+                _ => {}
+            }
+        }
+        rounds += 1;
+        assert!(rounds < MAX_ROUNDS, "Migration did not complete");
+    };
+    assert_eq!(result, Ok(WasmResult::Reply(ic00::EmptyBlob.encode())));
+    assert!(!env1.canister_exists(canister_id));
+    assert!(env2.canister_exists(canister_id));
+    // The cycles are migrated, minus what was charged for storage in the meantime.
+    let migrated_balance = env2.cycle_balance(canister_id);
+    assert!(migrated_balance <= balance);
+    assert!(migrated_balance > balance - 1_000_000_000);
+
+    // Route the canister to the 2nd subnet and complete the migration.
+    env1.reroute_canister_range(canister_id..=canister_id, subnet_id2);
+    env1.complete_canister_migrations(canister_id..=canister_id, vec![subnet_id1, subnet_id2]);
+    env2.reload_registry();
+
+    // The canister is stopped on the 2nd subnet and keeps its state.
+    let state = env2.get_latest_state();
+    let canister = state.canister_state(&canister_id).unwrap();
+    assert_eq!(canister.controllers().iter().next(), Some(&controller));
+    assert_eq!(canister.system_state.certified_data, b"certified".to_vec());
+    assert_eq!(
+        state
+            .canister_snapshots
+            .list_snapshots(canister_id)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>(),
+        vec![snapshot_id]
+    );
+    env2.start_canister_as(controller, canister_id).unwrap();
+    assert_eq!(
+        update(&env2, wasm().get_global_data().append_and_reply().build()),
+        b"after snapshot".to_vec()
+    );
+    assert_eq!(
+        update(&env2, wasm().stable_read(0, 11).append_and_reply().build()),
+        b"stable data".to_vec()
+    );
+
+    // The snapshot was migrated as well.
+    env2.stop_canister_as(controller, canister_id).unwrap();
+    env2.execute_ingress_as(
+        controller,
+        CanisterId::ic_00(),
+        ic00::Method::LoadCanisterSnapshot,
+        ic00::LoadCanisterSnapshotArgs::new(canister_id, snapshot_id, None).encode(),
+    )
+    .unwrap();
+    env2.start_canister_as(controller, canister_id).unwrap();
+    assert_eq!(
+        update(&env2, wasm().get_global_data().append_and_reply().build()),
+        b"before snapshot".to_vec()
+    );
+}
@@ -485,6 +485,8 @@ pub fn load_canister_state(
         canister_state_bits.log_visibility,
         canister_state_bits.canister_log,
         canister_state_bits.wasm_memory_limit,
+        canister_state_bits.environment_variables,
+        canister_state_bits.canister_profile,
         canister_state_bits.next_snapshot_id,
         canister_state_bits.snapshots_memory_usage,
     );
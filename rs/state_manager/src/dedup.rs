@@ -0,0 +1,239 @@
+//! Deduplication of checkpoint data that is identical across files, e.g. the
+//! memories of canisters installed from the same Wasm module.
+//!
+//! Identical content is found through the manifest: whole files by their file
+//! hash and ranges of pages by their chunk hash. The same content addressing is
+//! used by state sync to fetch shared chunks only once, see
+//! `manifest::deduplicate_fetch_chunks`.
+
+use crate::{
+    manifest::{filter_out_zero_chunks, group_chunks_by_content},
+    state_sync::types::Manifest,
+    StateManagerMetrics,
+};
+use ic_logger::{info, warn, ReplicaLogger};
+use ic_sys::{fs::FileCloneError, PAGE_SIZE};
+use ic_types::Height;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::os::unix::prelude::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+#[cfg(test)]
+mod tests;
+
+/// Files smaller than this are not worth an extra `hard_link` and `rename`.
+const MIN_DEDUPLICATED_FILE_SIZE: u64 = PAGE_SIZE as u64;
+
+/// Deduplicates the checkpoint at `checkpoint_root` described by `manifest`:
+/// identical files are hardlinked first (see `deduplicate_checkpoint_files`),
+/// then identical chunks of the remaining files share their storage (see
+/// `deduplicate_checkpoint_chunks`). Used both for checkpoints created locally
+/// and for checkpoints completed by state sync.
+pub(crate) fn deduplicate_checkpoint(
+    log: &ReplicaLogger,
+    metrics: &StateManagerMetrics,
+    tmp_dir: &Path,
+    checkpoint_root: &Path,
+    manifest: &Manifest,
+    height: Height,
+) {
+    let state_size_bytes: u64 = manifest.file_table.iter().map(|f| f.size_bytes).sum();
+
+    let start = Instant::now();
+    let file_bytes = deduplicate_checkpoint_files(log, tmp_dir, checkpoint_root, manifest);
+    let files_elapsed = start.elapsed();
+    metrics
+        .checkpoint_op_duration
+        .with_label_values(&["dedup_files"])
+        .observe(files_elapsed.as_secs_f64());
+
+    let start = Instant::now();
+    let chunk_bytes = deduplicate_checkpoint_chunks(log, checkpoint_root, manifest);
+    let chunks_elapsed = start.elapsed();
+    metrics
+        .checkpoint_op_duration
+        .with_label_values(&["dedup_chunks"])
+        .observe(chunks_elapsed.as_secs_f64());
+
+    metrics
+        .checkpoint_deduplicated_bytes
+        .with_label_values(&["file"])
+        .set(file_bytes as i64);
+    metrics
+        .checkpoint_deduplicated_bytes
+        .with_label_values(&["chunk"])
+        .set(chunk_bytes as i64);
+    if state_size_bytes > 0 {
+        let deduplicated_bytes = (file_bytes + chunk_bytes).min(state_size_bytes);
+        metrics
+            .checkpoint_dedup_ratio
+            .set(state_size_bytes as f64 / (state_size_bytes - deduplicated_bytes).max(1) as f64);
+    }
+
+    info!(
+        log,
+        "Deduplicated {} bytes of files and {} bytes of chunks out of {} bytes in checkpoint @{} \
+         in {:?}",
+        file_bytes,
+        chunk_bytes,
+        state_size_bytes,
+        height,
+        files_elapsed + chunks_elapsed
+    );
+}
+
+/// Replaces files of the checkpoint at `checkpoint_root` that are identical
+/// according to `manifest` by hardlinks of a single inode. Returns the number
+/// of bytes that no longer take up separate disk space.
+///
+/// This is safe because checkpoint files are read-only and never modified in
+/// place: mutable files are copied rather than hardlinked into the tip. Each
+/// file is replaced atomically by renaming a hardlink created in `tmp_dir`,
+/// so concurrent readers observe either copy of the same content and a crash
+/// leaves at most a stray file in `tmp_dir`, which is cleaned on restart.
+///
+/// Deduplication is best effort: failures are logged and the affected file
+/// is left as is. With `StateSyncVersion::V3` and later the file hash does not
+/// include the path, so for older manifests no files are deduplicated.
+pub(crate) fn deduplicate_checkpoint_files(
+    log: &ReplicaLogger,
+    tmp_dir: &Path,
+    checkpoint_root: &Path,
+    manifest: &Manifest,
+) -> u64 {
+    let mut originals: HashMap<([u8; 32], u64), (PathBuf, std::fs::Metadata)> = HashMap::new();
+    let mut deduplicated_bytes = 0;
+
+    for (index, file_info) in manifest.file_table.iter().enumerate() {
+        if file_info.size_bytes < MIN_DEDUPLICATED_FILE_SIZE {
+            continue;
+        }
+        let path = checkpoint_root.join(&file_info.relative_path);
+        let metadata = match path.metadata() {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                warn!(log, "Failed to get metadata of {}: {}", path.display(), err);
+                continue;
+            }
+        };
+        let key = (file_info.hash, file_info.size_bytes);
+        let Some((original, original_metadata)) = originals.get(&key) else {
+            originals.insert(key, (path, metadata));
+            continue;
+        };
+
+        if metadata.ino() != original_metadata.ino() || metadata.dev() != original_metadata.dev() {
+            let tmp_path = tmp_dir.join(format!("dedup_{}", index));
+            let result = std::fs::create_dir_all(tmp_dir)
+                .and_then(|_| std::fs::hard_link(original, &tmp_path))
+                .and_then(|_| std::fs::rename(&tmp_path, &path));
+            if let Err(err) = result {
+                let _ = std::fs::remove_file(&tmp_path);
+                warn!(
+                    log,
+                    "Failed to replace {} by a hardlink of {}: {}",
+                    path.display(),
+                    original.display(),
+                    err
+                );
+                continue;
+            }
+        }
+        deduplicated_bytes += file_info.size_bytes;
+    }
+
+    deduplicated_bytes
+}
+
+/// Makes the pages of each chunk of the checkpoint at `checkpoint_root` share
+/// their storage with the pages of the first chunk with the same content
+/// according to `manifest`, across all files of the checkpoint. Returns the
+/// number of bytes that no longer take up separate disk space, not counting
+/// chunks of files that are already hardlinks of each other.
+///
+/// Chunks start at multiples of the chunk size, so the shared ranges are page
+/// aligned; the trailing partial page of a chunk, if any, is not shared. All-zero
+/// chunks are skipped because they are usually holes already.
+///
+/// This relies on the filesystem's deduplication support, which compares the
+/// contents before sharing them and never changes what the files read as, so it
+/// is safe for read-only files that are concurrently read or mapped. On
+/// filesystems without such support nothing is deduplicated. Like
+/// `deduplicate_checkpoint_files`, this is best effort.
+pub(crate) fn deduplicate_checkpoint_chunks(
+    log: &ReplicaLogger,
+    checkpoint_root: &Path,
+    manifest: &Manifest,
+) -> u64 {
+    let duplicate_chunks = group_chunks_by_content(manifest, filter_out_zero_chunks(manifest));
+
+    // Open each file with duplicate chunks once, along with its device and inode numbers.
+    let mut files: HashMap<u32, (File, (u64, u64))> = HashMap::new();
+    let file_indices: BTreeSet<u32> = duplicate_chunks
+        .iter()
+        .flat_map(|(original, duplicates)| std::iter::once(original).chain(duplicates))
+        .map(|chunk_index| manifest.chunk_table[*chunk_index].file_index)
+        .collect();
+    for file_index in file_indices {
+        let path = checkpoint_root.join(&manifest.file_table[file_index as usize].relative_path);
+        match File::open(&path).and_then(|file| file.metadata().map(|m| (file, m))) {
+            Ok((file, metadata)) => {
+                files.insert(file_index, (file, (metadata.dev(), metadata.ino())));
+            }
+            Err(err) => warn!(log, "Failed to open {}: {}", path.display(), err),
+        }
+    }
+
+    let mut originals: Vec<_> = duplicate_chunks.keys().copied().collect();
+    originals.sort_unstable();
+
+    let mut deduplicated_bytes = 0;
+    for original in originals {
+        let original_chunk = &manifest.chunk_table[original];
+        let len = original_chunk.size_bytes as u64 / PAGE_SIZE as u64 * PAGE_SIZE as u64;
+        if len == 0 {
+            continue;
+        }
+        let Some((original_file, original_inode)) = files.get(&original_chunk.file_index) else {
+            continue;
+        };
+
+        for duplicate in &duplicate_chunks[&original] {
+            let duplicate_chunk = &manifest.chunk_table[*duplicate];
+            let Some((duplicate_file, duplicate_inode)) = files.get(&duplicate_chunk.file_index)
+            else {
+                continue;
+            };
+            if duplicate_inode == original_inode && duplicate_chunk.offset == original_chunk.offset
+            {
+                continue;
+            }
+
+            match ic_sys::fs::deduplicate_file_range(
+                original_file,
+                original_chunk.offset,
+                duplicate_file,
+                duplicate_chunk.offset,
+                len,
+            ) {
+                Ok(bytes) => deduplicated_bytes += bytes,
+                Err(FileCloneError::OperationNotSupported) => {
+                    info!(
+                        log,
+                        "The filesystem of {} does not support deduplication of file ranges",
+                        checkpoint_root.display()
+                    );
+                    return deduplicated_bytes;
+                }
+                Err(err) => warn!(
+                    log,
+                    "Failed to deduplicate chunk {} with chunk {}: {}", duplicate, original, err
+                ),
+            }
+        }
+    }
+
+    deduplicated_bytes
+}
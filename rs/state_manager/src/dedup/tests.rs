@@ -0,0 +1,115 @@
+use super::*;
+use crate::state_sync::types::{ChunkInfo, FileInfo};
+use ic_test_utilities_logger::with_test_replica_logger;
+use ic_test_utilities_tmpdir::tmpdir;
+use ic_types::state_sync::CURRENT_STATE_SYNC_VERSION;
+
+#[test]
+fn deduplicate_checkpoint_files_hardlinks_identical_files() {
+    with_test_replica_logger(|log| {
+        let tmp = tmpdir("checkpoint");
+        let root = tmp.path().join("checkpoint");
+        let fs_tmp = tmp.path().join("fs_tmp");
+        std::fs::create_dir_all(&root).unwrap();
+
+        let size = MIN_DEDUPLICATED_FILE_SIZE;
+        let small_size = MIN_DEDUPLICATED_FILE_SIZE - 1;
+        let files = [
+            ("a", vec![1; size as usize], [1; 32]),
+            ("b", vec![1; size as usize], [1; 32]),
+            ("c", vec![2; size as usize], [2; 32]),
+            ("d", vec![3; small_size as usize], [3; 32]),
+            ("e", vec![3; small_size as usize], [3; 32]),
+        ];
+        let mut file_table = Vec::new();
+        for (name, content, hash) in files.iter() {
+            std::fs::write(root.join(name), content).unwrap();
+            file_table.push(FileInfo {
+                relative_path: PathBuf::from(name),
+                size_bytes: content.len() as u64,
+                hash: *hash,
+            });
+        }
+        let manifest = Manifest::new(CURRENT_STATE_SYNC_VERSION, file_table, vec![]);
+
+        let deduplicated_bytes = deduplicate_checkpoint_files(&log, &fs_tmp, &root, &manifest);
+        assert_eq!(deduplicated_bytes, size);
+
+        let ino = |name: &str| root.join(name).metadata().unwrap().ino();
+        assert_eq!(ino("a"), ino("b"));
+        assert_ne!(ino("a"), ino("c"));
+        assert_ne!(ino("d"), ino("e"));
+        for (name, content, _) in files.iter() {
+            assert_eq!(&std::fs::read(root.join(name)).unwrap(), content);
+        }
+
+        // Running it again does not touch the already deduplicated files.
+        let deduplicated_bytes = deduplicate_checkpoint_files(&log, &fs_tmp, &root, &manifest);
+        assert_eq!(deduplicated_bytes, size);
+        assert_eq!(std::fs::read_dir(&fs_tmp).unwrap().count(), 0);
+    });
+}
+
+/// Returns whether the filesystem of `dir` supports deduplication of file ranges.
+fn supports_range_deduplication(dir: &Path) -> bool {
+    let content = vec![1; PAGE_SIZE];
+    std::fs::write(dir.join("probe_a"), &content).unwrap();
+    std::fs::write(dir.join("probe_b"), &content).unwrap();
+    let a = File::open(dir.join("probe_a")).unwrap();
+    let b = File::open(dir.join("probe_b")).unwrap();
+    let result = ic_sys::fs::deduplicate_file_range(&a, 0, &b, 0, PAGE_SIZE as u64);
+    std::fs::remove_file(dir.join("probe_a")).unwrap();
+    std::fs::remove_file(dir.join("probe_b")).unwrap();
+    !matches!(result, Err(FileCloneError::OperationNotSupported))
+}
+
+#[test]
+fn deduplicate_checkpoint_chunks_shares_identical_chunks_across_files() {
+    with_test_replica_logger(|log| {
+        let tmp = tmpdir("checkpoint");
+        let root = tmp.path().join("checkpoint");
+        std::fs::create_dir_all(&root).unwrap();
+
+        let page = |byte: u8| vec![byte; PAGE_SIZE];
+        // Files "a" and "b" share a chunk of two pages at different offsets, while
+        // the partial chunks of "c" and "d" are identical but smaller than a page.
+        let files: [(&str, Vec<Vec<u8>>); 4] = [
+            ("a", vec![[page(1), page(2)].concat(), page(3)]),
+            ("b", vec![page(4), [page(1), page(2)].concat()]),
+            ("c", vec![vec![5; 100]]),
+            ("d", vec![vec![5; 100]]),
+        ];
+        let mut file_table = Vec::new();
+        let mut chunk_table = Vec::new();
+        for (file_index, (name, chunks)) in files.iter().enumerate() {
+            let content = chunks.concat();
+            std::fs::write(root.join(name), &content).unwrap();
+            file_table.push(FileInfo {
+                relative_path: PathBuf::from(name),
+                size_bytes: content.len() as u64,
+                hash: [file_index as u8; 32],
+            });
+            let mut offset = 0;
+            for chunk in chunks {
+                chunk_table.push(ChunkInfo {
+                    file_index: file_index as u32,
+                    size_bytes: chunk.len() as u32,
+                    offset,
+                    hash: [chunk[0]; 32],
+                });
+                offset += chunk.len() as u64;
+            }
+        }
+        let manifest = Manifest::new(CURRENT_STATE_SYNC_VERSION, file_table, chunk_table);
+
+        let deduplicated_bytes = deduplicate_checkpoint_chunks(&log, &root, &manifest);
+        if supports_range_deduplication(tmp.path()) {
+            assert_eq!(deduplicated_bytes, 2 * PAGE_SIZE as u64);
+        } else {
+            assert_eq!(deduplicated_bytes, 0);
+        }
+        for (name, chunks) in files.iter() {
+            assert_eq!(std::fs::read(root.join(name)).unwrap(), chunks.concat());
+        }
+    });
+}
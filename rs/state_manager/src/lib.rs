@@ -1,6 +1,7 @@
 // Needs to be `pub` so that the benchmarking code in `state_benches`
 // can access it.
 pub mod checkpoint;
+mod dedup;
 pub mod labeled_tree_visitor;
 pub mod manifest;
 pub mod split;
@@ -63,7 +64,9 @@ use ic_types::{
     SubnetId,
 };
 use ic_utils_thread::JoinOnDrop;
-use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge};
+use prometheus::{
+    Gauge, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+};
 use prost::Message;
 use std::convert::{From, TryFrom};
 use std::fs::File;
@@ -121,6 +124,7 @@ const LABEL_FETCH: &str = "fetch";
 const LABEL_COPY_FILES: &str = "copy_files";
 const LABEL_COPY_CHUNKS: &str = "copy_chunks";
 const LABEL_PREALLOCATE: &str = "preallocate";
+const LABEL_DEDUP_CHUNKS: &str = "dedup_chunks";
 const LABEL_STATE_SYNC_MAKE_CHECKPOINT: &str = "state_sync_make_checkpoint";
 const LABEL_FETCH_META_MANIFEST_CHUNK: &str = "fetch_meta_manifest_chunk";
 const LABEL_FETCH_MANIFEST_CHUNK: &str = "fetch_manifest_chunk";
@@ -146,6 +150,8 @@ pub struct StateManagerMetrics {
     checkpoints_on_disk_count: IntGauge,
     state_sync_metrics: StateSyncMetrics,
     state_size: IntGauge,
+    checkpoint_deduplicated_bytes: IntGaugeVec,
+    checkpoint_dedup_ratio: Gauge,
     states_metadata_pbuf_size: IntGauge,
     checkpoint_metrics: CheckpointMetrics,
     manifest_metrics: ManifestMetrics,
@@ -319,7 +325,7 @@ impl StateManagerMetrics {
             &["op"],
         );
 
-        for op in &["compute_manifest", "create"] {
+        for op in &["compute_manifest", "create", "dedup_files", "dedup_chunks"] {
             checkpoint_op_duration.with_label_values(&[*op]);
         }
 
@@ -377,6 +383,17 @@ impl StateManagerMetrics {
             "Total size of the state on disk in bytes.",
         );
 
+        let checkpoint_deduplicated_bytes = metrics_registry.int_gauge_vec(
+            "state_manager_checkpoint_deduplicated_bytes",
+            "Size of the data of the last checkpoint that shares its storage with identical data of the same checkpoint, by granularity ('file', 'chunk'), in bytes.",
+            &["granularity"],
+        );
+
+        let checkpoint_dedup_ratio = metrics_registry.gauge(
+            "state_manager_checkpoint_dedup_ratio",
+            "Ratio of the total size of the files of the last checkpoint to the disk space they take up after deduplication.",
+        );
+
         let states_metadata_pbuf_size = metrics_registry.int_gauge(
             "state_manager_states_metadata_pbuf_size_bytes",
             "Size of states_metadata.pbuf in bytes.",
@@ -430,6 +447,8 @@ impl StateManagerMetrics {
             checkpoints_on_disk_count,
             state_sync_metrics: StateSyncMetrics::new(metrics_registry),
             state_size,
+            checkpoint_deduplicated_bytes,
+            checkpoint_dedup_ratio,
             states_metadata_pbuf_size,
             checkpoint_metrics: CheckpointMetrics::new(metrics_registry, log),
             manifest_metrics: ManifestMetrics::new(metrics_registry),
@@ -531,7 +550,7 @@ impl StateSyncMetrics {
     pub fn new(metrics_registry: &MetricsRegistry) -> Self {
         let size = metrics_registry.int_counter_vec(
             "state_sync_size_bytes_total",
-            "Size of chunks synchronized by different operations ('fetch', 'copy_files', 'copy_chunks', 'preallocate') during all the state sync in bytes.",
+            "Size of chunks synchronized by different operations ('fetch', 'copy_files', 'copy_chunks', 'preallocate', 'dedup_chunks') during all the state sync in bytes.",
             &["op"],
         );
 
@@ -541,6 +560,7 @@ impl StateSyncMetrics {
             LABEL_COPY_FILES,
             LABEL_COPY_CHUNKS,
             LABEL_PREALLOCATE,
+            LABEL_DEDUP_CHUNKS,
         ] {
             size.with_label_values(&[*op]);
         }
@@ -32,7 +32,7 @@ use ic_sys::{mmap::ScopedMmap, PAGE_SIZE};
 use ic_types::{crypto::CryptoHash, state_sync::StateSyncVersion, CryptoHashOfState, Height};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{hash_map::Entry, BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
@@ -1415,6 +1415,70 @@ pub fn filter_out_zero_chunks(manifest: &Manifest) -> HashSet<usize> {
     fetch_chunks
 }
 
+/// Removes from `fetch_chunks` the chunks whose content is identical to that of
+/// another chunk in `fetch_chunks`, so that content shared by many files (e.g.
+/// the memories of canisters installed from the same factory) is fetched only
+/// once per state sync.
+///
+/// Returns the chunk table indices of the removed chunks, keyed by the index of
+/// the chunk with the same content that is still to be fetched; once fetched,
+/// its content must be applied to all of them. Chunks that are fetched as part
+/// of a file group chunk are never deduplicated.
+pub(crate) fn deduplicate_fetch_chunks(
+    manifest: &Manifest,
+    fetch_chunks: &mut HashSet<usize>,
+) -> HashMap<usize, Vec<usize>> {
+    let grouped_chunks: HashSet<usize> = build_file_group_chunks(manifest)
+        .iter()
+        .flat_map(|(_, chunk_table_indices)| chunk_table_indices.iter().map(|i| *i as usize))
+        .collect();
+
+    let duplicate_chunks = group_chunks_by_content(
+        manifest,
+        fetch_chunks
+            .iter()
+            .copied()
+            .filter(|i| !grouped_chunks.contains(i)),
+    );
+    for chunk_index in duplicate_chunks.values().flatten() {
+        fetch_chunks.remove(chunk_index);
+    }
+    duplicate_chunks
+}
+
+/// Groups the chunks at `chunk_indices` by content, i.e. by hash and size, so
+/// that the same index can be used both to fetch shared content once during
+/// state sync and to share its storage on disk.
+///
+/// Returns, for each group of at least two chunks, the lowest chunk table index
+/// in the group mapped to the other indices in the group in increasing order.
+pub(crate) fn group_chunks_by_content(
+    manifest: &Manifest,
+    chunk_indices: impl IntoIterator<Item = usize>,
+) -> HashMap<usize, Vec<usize>> {
+    // Iterate in order so that the same chunk of each group is picked.
+    let mut chunk_indices: Vec<usize> = chunk_indices.into_iter().collect();
+    chunk_indices.sort_unstable();
+
+    let mut chunk_by_content: HashMap<([u8; 32], u32), usize> = HashMap::new();
+    let mut duplicate_chunks: HashMap<usize, Vec<usize>> = HashMap::new();
+    for chunk_index in chunk_indices {
+        let chunk_info = &manifest.chunk_table[chunk_index];
+        match chunk_by_content.entry((chunk_info.hash, chunk_info.size_bytes)) {
+            Entry::Vacant(entry) => {
+                entry.insert(chunk_index);
+            }
+            Entry::Occupied(entry) => {
+                duplicate_chunks
+                    .entry(*entry.get())
+                    .or_default()
+                    .push(chunk_index);
+            }
+        }
+    }
+    duplicate_chunks
+}
+
 /// Helper function to compute the manifest from a raw path.
 /// This function is intended for tests and external tools only.
 pub fn manifest_from_path(path: &Path) -> Result<Manifest, CheckpointError> {
@@ -1,8 +1,8 @@
 use crate::manifest::{
-    build_file_group_chunks, build_meta_manifest, compute_manifest, diff_manifest,
-    dirty_pages_to_dirty_chunks, file_chunk_range, files_with_sizes, filter_out_zero_chunks,
-    hash::ManifestHash, manifest_hash, manifest_hash_v1, manifest_hash_v2, meta_manifest_hash,
-    validate_chunk, validate_manifest, validate_manifest_internal_consistency,
+    build_file_group_chunks, build_meta_manifest, compute_manifest, deduplicate_fetch_chunks,
+    diff_manifest, dirty_pages_to_dirty_chunks, file_chunk_range, files_with_sizes,
+    filter_out_zero_chunks, hash::ManifestHash, manifest_hash, manifest_hash_v1, manifest_hash_v2,
+    meta_manifest_hash, validate_chunk, validate_manifest, validate_manifest_internal_consistency,
     validate_meta_manifest, validate_sub_manifest, ChunkValidationError, DiffScript, ManifestDelta,
     ManifestMetrics, ManifestValidationError, StateSyncVersion, DEFAULT_CHUNK_SIZE,
     MAX_FILE_SIZE_TO_GROUP,
@@ -824,6 +824,59 @@ fn test_filter_all_zero_chunks() {
     assert_eq!(filter_out_zero_chunks(&manifest), fetch_chunks);
 }
 
+#[test]
+fn test_deduplicate_fetch_chunks() {
+    let file_info = |path: &str| FileInfo {
+        relative_path: PathBuf::from(path),
+        size_bytes: 200,
+        hash: [0; 32],
+    };
+    let chunk_info = |file_index: u32, offset: u64, size_bytes: u32, hash: u8| ChunkInfo {
+        file_index,
+        size_bytes,
+        offset,
+        hash: [hash; 32],
+    };
+    let file_table = vec![
+        file_info("a/vmemory_0.bin"),
+        file_info("b/vmemory_0.bin"),
+        file_info(&format!("c/{}", CANISTER_FILE)),
+        file_info("d/vmemory_0.bin"),
+    ];
+    let chunk_table = vec![
+        chunk_info(0, 0, 100, 1),
+        chunk_info(0, 100, 100, 2),
+        // Same content as chunk 0.
+        chunk_info(1, 0, 100, 1),
+        // Same hash as chunk 1, but a different size.
+        chunk_info(1, 100, 50, 2),
+        // Same content as chunk 0, but part of a file group chunk.
+        chunk_info(2, 0, 100, 1),
+        // Same content as chunks 0 and 1, respectively.
+        chunk_info(3, 0, 100, 1),
+        chunk_info(3, 100, 100, 2),
+    ];
+    let manifest = Manifest::new(CURRENT_STATE_SYNC_VERSION, file_table, chunk_table);
+    assert_eq!(
+        build_file_group_chunks(&manifest)
+            .iter()
+            .flat_map(|(_, indices)| indices.clone())
+            .collect::<Vec<_>>(),
+        vec![4]
+    );
+
+    // Chunk 1 is available locally, so chunk 6 is the only copy of its content to fetch.
+    let mut fetch_chunks = maplit::hashset! {0, 2, 3, 4, 5, 6};
+    let duplicate_chunks = deduplicate_fetch_chunks(&manifest, &mut fetch_chunks);
+
+    assert_eq!(fetch_chunks, maplit::hashset! {0, 3, 4, 6});
+    assert_eq!(duplicate_chunks, maplit::hashmap! {0 => vec![2, 5]});
+
+    // Nothing is left to deduplicate.
+    assert!(deduplicate_fetch_chunks(&manifest, &mut fetch_chunks).is_empty());
+    assert_eq!(fetch_chunks, maplit::hashset! {0, 3, 4, 6});
+}
+
 #[test]
 fn test_missing_simple_manifest() {
     let (_, manifest_old) = simple_manifest(CURRENT_STATE_SYNC_VERSION);
@@ -1,5 +1,7 @@
 use crate::{
-    manifest::{build_file_group_chunks, filter_out_zero_chunks, DiffScript},
+    manifest::{
+        build_file_group_chunks, deduplicate_fetch_chunks, filter_out_zero_chunks, DiffScript,
+    },
     state_sync::types::{
         decode_manifest, decode_meta_manifest, state_sync_chunk_type, FileGroupChunks, Manifest,
         MetaManifest, StateSyncChunk, StateSyncMessage, FILE_CHUNK_ID_OFFSET,
@@ -7,9 +9,9 @@ use crate::{
     },
     state_sync::StateSync,
     StateManagerMetrics, StateSyncMetrics, StateSyncRefs,
-    CRITICAL_ERROR_STATE_SYNC_CORRUPTED_CHUNKS, LABEL_COPY_CHUNKS, LABEL_COPY_FILES, LABEL_FETCH,
-    LABEL_FETCH_MANIFEST_CHUNK, LABEL_FETCH_META_MANIFEST_CHUNK, LABEL_FETCH_STATE_CHUNK,
-    LABEL_PREALLOCATE, LABEL_STATE_SYNC_MAKE_CHECKPOINT,
+    CRITICAL_ERROR_STATE_SYNC_CORRUPTED_CHUNKS, LABEL_COPY_CHUNKS, LABEL_COPY_FILES,
+    LABEL_DEDUP_CHUNKS, LABEL_FETCH, LABEL_FETCH_MANIFEST_CHUNK, LABEL_FETCH_META_MANIFEST_CHUNK,
+    LABEL_FETCH_STATE_CHUNK, LABEL_PREALLOCATE, LABEL_STATE_SYNC_MAKE_CHECKPOINT,
 };
 use ic_interfaces::p2p::state_sync::{AddChunkError, Chunk, ChunkId, Chunkable};
 use ic_logger::{debug, error, fatal, info, trace, warn, ReplicaLogger};
@@ -61,6 +63,10 @@ enum DownloadState {
         /// a dedicated chunk id range.
         /// The manifest chunks are not part of `fetch_chunks` because they are fetched in the `Prep` phase.
         fetch_chunks: HashSet<usize>,
+        /// Chunks that are not fetched because they have the same content as a chunk in
+        /// `fetch_chunks`. Keys and values are indices into the manifest's chunk table: the
+        /// content of the chunk at the key index is also applied to the chunks at the values.
+        duplicate_chunks: HashMap<usize, Vec<usize>>,
     },
     /// Successfully completed and delivered the state sync, nothing else to do.
     Complete,
@@ -116,6 +122,7 @@ impl Drop for IncompleteState {
                 manifest: _,
                 state_sync_file_group,
                 fetch_chunks,
+                duplicate_chunks,
             } => {
                 self.metrics
                     .state_sync_metrics
@@ -127,7 +134,9 @@ impl Drop for IncompleteState {
                     .iter()
                     .map(|ix| {
                         if (*ix as u32) < FILE_GROUP_CHUNK_ID_OFFSET {
-                            1
+                            1 + duplicate_chunks
+                                .get(&(*ix - FILE_CHUNK_ID_OFFSET))
+                                .map_or(0, Vec::len)
                         } else {
                             state_sync_file_group
                                 .get(&(*ix as u32))
@@ -777,6 +786,7 @@ impl IncompleteState {
         started_at: Instant,
         root: &Path,
         height: Height,
+        manifest: &Manifest,
         state_layout: &StateLayout,
         thread_pool: &mut scoped_threadpool::Pool,
     ) {
@@ -791,6 +801,17 @@ impl IncompleteState {
             "state sync: start to make a checkpoint from the scratchpad"
         );
 
+        // Chunks fetched once for several destinations were written to each of them, so
+        // share their storage like for checkpoints created locally.
+        crate::dedup::deduplicate_checkpoint(
+            log,
+            metrics,
+            &state_layout.fs_tmp(),
+            root,
+            manifest,
+            height,
+        );
+
         let scratchpad_layout =
             CheckpointLayout::<RwPolicy<()>>::new_untracked(root.to_path_buf(), height)
                 .expect("failed to create checkpoint layout");
@@ -864,8 +885,13 @@ impl IncompleteState {
 
     /// Preallocates the files listed in the manifest and copies the chunks
     /// that we have locally.
-    /// Returns a set of chunks that still need to be fetched
-    fn initialize_state_on_disk(&mut self, manifest_new: &Manifest) -> HashSet<usize> {
+    /// Returns a set of chunks that still need to be fetched, as well as the
+    /// chunks that are not fetched because they duplicate one of the former
+    /// (see `deduplicate_fetch_chunks`).
+    fn initialize_state_on_disk(
+        &mut self,
+        manifest_new: &Manifest,
+    ) -> (HashSet<usize>, HashMap<usize, Vec<usize>>) {
         Self::preallocate_layout(&self.log, &self.root, manifest_new);
 
         let state_sync_size_fetch = self
@@ -888,6 +914,18 @@ impl IncompleteState {
             .state_sync_metrics
             .size
             .with_label_values(&[LABEL_PREALLOCATE]);
+        let state_sync_size_dedup = self
+            .metrics
+            .state_sync_metrics
+            .size
+            .with_label_values(&[LABEL_DEDUP_CHUNKS]);
+        let duplicate_bytes = |duplicate_chunks: &HashMap<usize, Vec<usize>>| -> u64 {
+            duplicate_chunks
+                .values()
+                .flatten()
+                .map(|i| manifest_new.chunk_table[*i].size_bytes as u64)
+                .sum()
+        };
         let total_bytes: u64 = manifest_new.file_table.iter().map(|f| f.size_bytes).sum();
 
         self.metrics
@@ -987,8 +1025,10 @@ impl IncompleteState {
                 },
                 height_old
             );
-            let diff_script =
+            let mut diff_script =
                 crate::manifest::diff_manifest(manifest_old, &missing_chunks, manifest_new);
+            let duplicate_chunks =
+                deduplicate_fetch_chunks(manifest_new, &mut diff_script.fetch_chunks);
             debug!(
                 self.log,
                 "State sync diff script (@{} -> @{}): {:?}", height_old, self.height, diff_script
@@ -1018,13 +1058,16 @@ impl IncompleteState {
                 .map(|i| manifest_new.file_table[*i].size_bytes)
                 .sum();
 
+            let dedup_bytes = duplicate_bytes(&duplicate_chunks);
+
             let copy_chunks_bytes: u64 =
-                total_bytes - diff_bytes - preallocate_bytes - copy_files_bytes;
+                total_bytes - diff_bytes - preallocate_bytes - copy_files_bytes - dedup_bytes;
 
             state_sync_size_fetch.inc_by(diff_bytes);
             state_sync_size_preallocate.inc_by(preallocate_bytes);
             state_sync_size_copy_files.inc_by(copy_files_bytes);
             state_sync_size_copy_chunks.inc_by(copy_chunks_bytes);
+            state_sync_size_dedup.inc_by(dedup_bytes);
 
             self.metrics
                 .state_sync_metrics
@@ -1058,32 +1101,35 @@ impl IncompleteState {
                 &mut fetch_chunks,
             );
 
-            fetch_chunks
+            (fetch_chunks, duplicate_chunks)
         } else {
             info!(
                 self.log,
                 "Initializing state sync for height {} without any caches or previous checkpoints",
                 self.height
             );
-            let non_zero_chunks = filter_out_zero_chunks(manifest_new);
+            let mut non_zero_chunks = filter_out_zero_chunks(manifest_new);
+            let zeros_chunks = manifest_new.chunk_table.len() - non_zero_chunks.len();
+            let duplicate_chunks = deduplicate_fetch_chunks(manifest_new, &mut non_zero_chunks);
             let diff_bytes: u64 = non_zero_chunks
                 .iter()
                 .map(|i| manifest_new.chunk_table[*i].size_bytes as u64)
                 .sum();
+            let dedup_bytes = duplicate_bytes(&duplicate_chunks);
             state_sync_size_fetch.inc_by(diff_bytes);
-            state_sync_size_preallocate.inc_by(total_bytes - diff_bytes);
-
-            let zeros_chunks = manifest_new.chunk_table.len() - non_zero_chunks.len();
+            state_sync_size_dedup.inc_by(dedup_bytes);
+            state_sync_size_preallocate.inc_by(total_bytes - diff_bytes - dedup_bytes);
 
             self.metrics
                 .state_sync_metrics
                 .remaining
                 .sub(zeros_chunks as i64);
 
-            non_zero_chunks
+            let fetch_chunks = non_zero_chunks
                 .iter()
                 .map(|i| *i + FILE_CHUNK_ID_OFFSET)
-                .collect()
+                .collect();
+            (fetch_chunks, duplicate_chunks)
         }
     }
 }
@@ -1166,6 +1212,7 @@ impl Chunkable<StateSyncMessage> for IncompleteState {
                 manifest: _,
                 state_sync_file_group: _,
                 ref fetch_chunks,
+                duplicate_chunks: _,
             } => {
                 #[allow(clippy::needless_collect)]
                 let ids: Vec<_> = fetch_chunks
@@ -1330,7 +1377,8 @@ impl Chunkable<StateSyncMessage> for IncompleteState {
 
                     let meta_manifest = meta_manifest.clone();
 
-                    let mut fetch_chunks = self.initialize_state_on_disk(&manifest);
+                    let (mut fetch_chunks, duplicate_chunks) =
+                        self.initialize_state_on_disk(&manifest);
 
                     if fetch_chunks.is_empty() {
                         debug!(
@@ -1344,6 +1392,7 @@ impl Chunkable<StateSyncMessage> for IncompleteState {
                             self.started_at,
                             &self.root,
                             self.height,
+                            &manifest,
                             &self.state_layout,
                             &mut self.thread_pool.lock().unwrap(),
                         );
@@ -1388,6 +1437,7 @@ impl Chunkable<StateSyncMessage> for IncompleteState {
                             manifest,
                             state_sync_file_group,
                             fetch_chunks,
+                            duplicate_chunks,
                         };
                         self.fetch_started_at = Some(Instant::now());
                         info!(
@@ -1406,6 +1456,7 @@ impl Chunkable<StateSyncMessage> for IncompleteState {
                 ref manifest,
                 ref mut fetch_chunks,
                 ref state_sync_file_group,
+                ref duplicate_chunks,
             } => {
                 debug!(
                     self.log,
@@ -1481,14 +1532,19 @@ impl Chunkable<StateSyncMessage> for IncompleteState {
                 for (chunk_table_index, &(start, end)) in
                     chunk_table_indices.iter().zip(payload_pieces.iter())
                 {
-                    Self::apply_chunk(
-                        &self.log,
-                        &self.metrics.state_sync_metrics,
-                        &self.root,
-                        *chunk_table_index as usize,
-                        &chunk.as_bytes()[start..end],
-                        manifest,
-                    );
+                    let duplicates = duplicate_chunks.get(&(*chunk_table_index as usize));
+                    for ix in std::iter::once(*chunk_table_index as usize)
+                        .chain(duplicates.into_iter().flatten().copied())
+                    {
+                        Self::apply_chunk(
+                            &self.log,
+                            &self.metrics.state_sync_metrics,
+                            &self.root,
+                            ix,
+                            &chunk.as_bytes()[start..end],
+                            manifest,
+                        );
+                    }
                 }
 
                 fetch_chunks.remove(&(ix as usize));
@@ -1521,6 +1577,7 @@ impl Chunkable<StateSyncMessage> for IncompleteState {
                         self.started_at,
                         &self.root,
                         self.height,
+                        manifest,
                         &self.state_layout,
                         &mut self.thread_pool.lock().unwrap(),
                     );
@@ -96,6 +96,7 @@ impl StateSyncCache {
         manifest: Manifest,
         fetch_chunks: HashSet<usize>,
         state_sync_file_group: FileGroupChunks,
+        duplicate_chunks: HashMap<usize, Vec<usize>>,
     ) {
         // fetch_chunks, as stored by IncompleteState considers the meta-manifest as chunk 0
         // For the cache we store indices into the manifest's chunk table as
@@ -105,7 +106,12 @@ impl StateSyncCache {
         for i in fetch_chunks.into_iter() {
             assert_ne!(0, i);
             if i < FILE_GROUP_CHUNK_ID_OFFSET as usize {
-                missing_chunks.insert(i - FILE_CHUNK_ID_OFFSET);
+                let chunk_index = i - FILE_CHUNK_ID_OFFSET;
+                missing_chunks.insert(chunk_index);
+                // Duplicates are only written once the chunk they duplicate is fetched.
+                if let Some(duplicates) = duplicate_chunks.get(&chunk_index) {
+                    missing_chunks.extend(duplicates);
+                }
             } else {
                 // If it's a chunk group, the individual chunks are missing in the manifest,
                 // not the group
@@ -185,12 +191,19 @@ impl StateSyncCache {
                 manifest,
                 state_sync_file_group,
                 fetch_chunks,
+                duplicate_chunks,
             } => {
                 if self.entry.is_some() {
                     // The current cache is newer
                     delete_folder(&self.log, &sync.root);
                 } else {
-                    self.push_inner(sync, manifest, fetch_chunks, state_sync_file_group);
+                    self.push_inner(
+                        sync,
+                        manifest,
+                        fetch_chunks,
+                        state_sync_file_group,
+                        duplicate_chunks,
+                    );
                 }
             }
             DownloadState::Complete | DownloadState::Blank | DownloadState::Prep { .. } => {
@@ -83,6 +83,7 @@ fn fake_loading(
         manifest: manifest.clone(),
         state_sync_file_group: state_sync_file_group.clone(),
         fetch_chunks: fetch_chunks.clone(),
+        duplicate_chunks: HashMap::new(),
     };
     (state, manifest, fetch_chunks, state_sync_file_group)
 }
@@ -138,6 +139,7 @@ fn incomplete_state_for_tests(
         ref manifest,
         state_sync_file_group: _,
         fetch_chunks: _,
+        duplicate_chunks: _,
     } = &result.state
     {
         std::fs::create_dir(&result.root).unwrap();
@@ -289,6 +291,41 @@ fn loading_sync() {
     })
 }
 
+// Chunks that are written from a duplicate chunk still to be fetched are
+// missing in the cache, too.
+#[test]
+fn loading_sync_with_duplicate_chunks() {
+    with_test_replica_logger(|log| {
+        let env = TestEnvironment::new(log);
+        let (state, _, fetch_chunks, file_groups) = fake_loading(V2, 1);
+        let state = match state {
+            DownloadState::Loading {
+                meta_manifest,
+                manifest,
+                state_sync_file_group,
+                fetch_chunks,
+                duplicate_chunks: _,
+            } => DownloadState::Loading {
+                meta_manifest,
+                manifest,
+                state_sync_file_group,
+                fetch_chunks,
+                duplicate_chunks: maplit::hashmap! { 1 => vec![7, 8] },
+            },
+            _ => unreachable!(),
+        };
+
+        let sync = incomplete_state_for_tests(&env, Height::new(5), state);
+        drop(sync);
+
+        let lock = env.cache.read();
+        let entry = lock.get().unwrap();
+        let mut expected_missing_chunks = ungroup_fetch_chunks(&fetch_chunks, &file_groups);
+        expected_missing_chunks.extend([7, 8]);
+        assert_eq!(entry.missing_chunks, expected_missing_chunks);
+    })
+}
+
 // Completed syncs can clear the cache if they are not older, but don't replace
 // the cache with anything new
 #[test]
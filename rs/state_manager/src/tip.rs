@@ -1045,6 +1045,8 @@ fn serialize_canister_to_tip(
             log_visibility: canister_state.system_state.log_visibility.clone(),
             canister_log: canister_state.system_state.canister_log.clone(),
             wasm_memory_limit: canister_state.system_state.wasm_memory_limit,
+            environment_variables: canister_state.system_state.environment_variables.clone(),
+            canister_profile: canister_state.system_state.canister_profile.clone(),
             next_snapshot_id: canister_state.system_state.next_snapshot_id,
             snapshots_memory_usage: canister_state.system_state.snapshots_memory_usage,
         }
@@ -1277,6 +1279,15 @@ fn handle_compute_manifest_request(
         .last_computed_manifest_height
         .set(checkpoint_layout.height().get() as i64);
 
+    crate::dedup::deduplicate_checkpoint(
+        log,
+        metrics,
+        &state_layout.fs_tmp(),
+        checkpoint_layout.raw_path(),
+        &manifest,
+        checkpoint_layout.height(),
+    );
+
     // This is where we maliciously alter the root_hash!
     #[cfg(feature = "malicious_code")]
     let malicious_root_hash = crate::maliciously_return_wrong_hash(
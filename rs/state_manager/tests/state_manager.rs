@@ -32,11 +32,13 @@ use ic_replicated_state::{
 };
 use ic_state_layout::{CheckpointLayout, ReadOnly, StateLayout, SYSTEM_METADATA_FILE, WASM_FILE};
 use ic_state_machine_tests::{StateMachine, StateMachineBuilder};
-use ic_state_manager::manifest::{build_meta_manifest, manifest_from_path, validate_manifest};
+use ic_state_manager::manifest::{
+    build_meta_manifest, filter_out_zero_chunks, manifest_from_path, validate_manifest,
+};
 use ic_state_manager::{
     state_sync::{
         types::{
-            StateSyncMessage, DEFAULT_CHUNK_SIZE, FILE_GROUP_CHUNK_ID_OFFSET,
+            StateSyncMessage, DEFAULT_CHUNK_SIZE, FILE_CHUNK_ID_OFFSET, FILE_GROUP_CHUNK_ID_OFFSET,
             MANIFEST_CHUNK_ID_OFFSET, META_MANIFEST_CHUNK,
         },
         StateSync,
@@ -2691,7 +2693,8 @@ fn can_state_sync_from_cache_alone() {
         assert_error_counters(src_metrics);
         state_manager_test_with_state_sync(|dst_metrics, dst_state_manager, dst_state_sync| {
             // In the first state sync, we omit the `software.wasm` of the first canister, which is the same as the other one.
-            // The state sync won't complete because all the chunks have to be fetched from scratch.
+            // The state sync won't complete because the identical `software.wasm` of the other canister is only written
+            // once the omitted chunk is fetched.
             //   file idx  |  file size | chunk idx |                         path
             // ------------+------------+---------- +------------------------------------------------------
             //           0 |        331 |     0     | canister_states/00000000000000640101/canister.pbuf
@@ -2734,12 +2737,25 @@ fn can_state_sync_from_cache_alone() {
                 let mut chunkable =
                     set_fetch_state_and_start_start_sync(&dst_state_manager, &dst_state_sync, &id);
 
-                // The meta-manifest and manifest are enough to complete the sync
-                // This is because the omitted file `canister_states/00000000000000640101/software.wasm` in the first state sync
-                // is the same as the other one. As a result, it will be copied and does not need to be fetched.
+                // Apart from the file group chunks, which are fetched unconditionally, only the omitted chunk is
+                // missing from the cache: all other files are copied from the cache and the identical `software.wasm`
+                // of the other canister is written from the omitted chunk once it is fetched.
                 let _res = pipe_meta_manifest(&msg, &mut *chunkable, false);
                 let is_finished = pipe_manifest(&msg, &mut *chunkable, false);
-                assert_matches!(is_finished, Ok(true), "State sync should have completed.");
+                assert_matches!(
+                    is_finished,
+                    Ok(false),
+                    "State sync should not have completed."
+                );
+                let chunks_to_download: HashSet<ChunkId> = chunkable
+                    .chunks_to_download()
+                    .filter(|chunk_id| chunk_id.get() < FILE_GROUP_CHUNK_ID_OFFSET)
+                    .collect();
+                assert_eq!(chunks_to_download, omit);
+
+                let completion =
+                    pipe_partial_state_sync(&msg, &mut *chunkable, &Default::default(), false);
+                assert_matches!(completion, Ok(true), "State sync should have completed.");
 
                 let recovered_state = dst_state_manager
                     .get_state_at(height(1))
@@ -3087,6 +3103,131 @@ fn can_group_small_files_in_state_sync() {
     })
 }
 
+#[test]
+fn can_fetch_duplicate_chunks_once_in_state_sync() {
+    state_manager_test_with_state_sync(|src_metrics, src_state_manager, src_state_sync| {
+        let (_height, mut state) = src_state_manager.take_tip();
+        // Canisters 100 and 101 have the same memory, canister 102 a different one.
+        for (id, byte) in [(100, 7u8), (101, 7u8), (102, 8u8)] {
+            insert_dummy_canister(&mut state, canister_test_id(id));
+            let canister_state = state.canister_state_mut(&canister_test_id(id)).unwrap();
+            let execution_state = canister_state.execution_state.as_mut().unwrap();
+            execution_state.wasm_memory.page_map.update(&[
+                (PageIndex::new(0), &[byte; PAGE_SIZE]),
+                (PageIndex::new(1), &[byte; PAGE_SIZE]),
+            ]);
+        }
+
+        src_state_manager.commit_and_certify(state, height(1), CertificationScope::Full, None);
+        let hash = wait_for_checkpoint(&*src_state_manager, height(1));
+        let id = StateSyncArtifactId {
+            height: height(1),
+            hash: hash.get(),
+        };
+
+        let state = src_state_manager.get_latest_state().take();
+
+        let msg = src_state_sync
+            .get(&id)
+            .expect("failed to get state sync messages");
+
+        // Chunk table indices of chunks with the same content, excluding all-zero chunks
+        // (which are not fetched) and those fetched as part of a file group chunk.
+        let non_zero_chunks = filter_out_zero_chunks(&msg.manifest);
+        let grouped_chunks: HashSet<usize> = msg
+            .state_sync_file_group
+            .iter()
+            .flat_map(|(_, indices)| indices.iter().map(|i| *i as usize))
+            .collect();
+        let mut chunks_by_content: BTreeMap<([u8; 32], u32), Vec<usize>> = BTreeMap::new();
+        for (index, chunk_info) in msg.manifest.chunk_table.iter().enumerate() {
+            if non_zero_chunks.contains(&index) && !grouped_chunks.contains(&index) {
+                chunks_by_content
+                    .entry((chunk_info.hash, chunk_info.size_bytes))
+                    .or_default()
+                    .push(index);
+            }
+        }
+        let duplicate_chunks: Vec<Vec<usize>> = chunks_by_content
+            .into_iter()
+            .filter(|(_, indices)| indices.len() > 1)
+            .map(|(_, indices)| indices)
+            .collect();
+        // The memory of canisters 100 and 101 is the same.
+        assert!(duplicate_chunks
+            .iter()
+            .any(
+                |indices| msg.manifest.chunk_table[indices[0]].size_bytes as usize >= 2 * PAGE_SIZE
+            ));
+
+        assert_error_counters(src_metrics);
+
+        state_manager_test_with_state_sync(|dst_metrics, dst_state_manager, dst_state_sync| {
+            let mut chunkable =
+                set_fetch_state_and_start_start_sync(&dst_state_manager, &dst_state_sync, &id);
+
+            let result = pipe_meta_manifest(&msg, &mut *chunkable, false);
+            assert_matches!(result, Ok(false));
+            let result = pipe_manifest(&msg, &mut *chunkable, false);
+            assert_matches!(result, Ok(false));
+
+            // Of each set of identical chunks, only the first one is fetched.
+            let chunks_to_download: HashSet<ChunkId> = chunkable.chunks_to_download().collect();
+            let chunk_id = |index: usize| ChunkId::new((index + FILE_CHUNK_ID_OFFSET) as u32);
+            let mut deduplicated_bytes = 0;
+            for indices in &duplicate_chunks {
+                assert!(chunks_to_download.contains(&chunk_id(indices[0])));
+                for index in &indices[1..] {
+                    assert!(!chunks_to_download.contains(&chunk_id(*index)));
+                    deduplicated_bytes += msg.manifest.chunk_table[*index].size_bytes as u64;
+                }
+            }
+
+            let mut dedup_label = Labels::new();
+            dedup_label.insert("op".to_string(), "dedup_chunks".to_string());
+            let size = fetch_int_counter_vec(dst_metrics, "state_sync_size_bytes_total");
+            assert_eq!(size[&dedup_label], deduplicated_bytes);
+
+            // Omit the fetched copy of each set of identical chunks in a first attempt: the
+            // duplicates must still be missing when the sync is resumed from the cache.
+            let omit: HashSet<ChunkId> = duplicate_chunks
+                .iter()
+                .map(|indices| chunk_id(indices[0]))
+                .collect();
+            let completed = pipe_partial_state_sync(&msg, &mut *chunkable, &omit, false)
+                .expect("State sync chunk verification failed.");
+            assert!(!completed);
+            drop(chunkable);
+
+            let mut chunkable =
+                set_fetch_state_and_start_start_sync(&dst_state_manager, &dst_state_sync, &id);
+            let result = pipe_meta_manifest(&msg, &mut *chunkable, false);
+            assert_matches!(result, Ok(false));
+            let result = pipe_manifest(&msg, &mut *chunkable, false);
+            assert_matches!(result, Ok(false));
+            // File group chunks are fetched again unconditionally.
+            let chunks_to_download: HashSet<ChunkId> = chunkable
+                .chunks_to_download()
+                .filter(|chunk_id| chunk_id.get() < FILE_GROUP_CHUNK_ID_OFFSET)
+                .collect();
+            assert_eq!(chunks_to_download, omit);
+
+            pipe_state_sync(msg, chunkable);
+
+            // Every duplicate was written from the single fetched chunk.
+            let recovered_state = dst_state_manager
+                .get_state_at(height(1))
+                .expect("Destination state manager didn't receive the state")
+                .take();
+            assert_eq!(height(1), dst_state_manager.latest_state_height());
+            assert_eq!(state, recovered_state);
+
+            assert_error_counters(dst_metrics);
+            assert_no_remaining_chunks(dst_metrics);
+        })
+    })
+}
+
 #[test]
 fn can_commit_after_prev_state_is_gone() {
     state_manager_test_with_state_sync(|src_metrics, src_state_manager, src_state_sync| {
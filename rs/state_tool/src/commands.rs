@@ -3,6 +3,7 @@ pub mod cdiff;
 pub mod chash;
 pub mod convert_ids;
 pub mod decode;
+pub mod extract_canister;
 pub mod import_state;
 pub mod list;
 pub mod manifest;
@@ -0,0 +1,374 @@
+//! Extracts the artifacts of a single canister from a checkpoint.
+
+use crate::commands::utils::copy_recursively;
+use ic_replicated_state::{
+    canister_state::WASM_PAGE_SIZE_IN_BYTES, page_map::TestPageAllocatorFileDescriptorImpl,
+    CanisterState, NumWasmPages, PageIndex, PageMap,
+};
+use ic_state_layout::CompleteCheckpointLayout;
+use ic_state_manager::{
+    checkpoint::{load_canister_state, load_snapshot},
+    CheckpointMetrics,
+};
+use ic_sys::PAGE_SIZE;
+use ic_types::{CanisterId, Height};
+use std::fs::{self, File};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Name of the directory holding the canister's files in the checkpoint
+/// layout, as expected by `StateMachine::import_canister_state`.
+const CANISTER_STATE_DIR: &str = "canister_state";
+/// Name of the directory holding the canister's snapshots.
+const SNAPSHOTS_DIR: &str = "snapshots";
+const WASM_FILE: &str = "software.wasm";
+const WASM_MEMORY_FILE: &str = "wasm_memory.bin";
+const STABLE_MEMORY_FILE: &str = "stable_memory.bin";
+const SYSTEM_STATE_FILE: &str = "system_state.json";
+
+/// Extracts the canister with the given ID from the checkpoint at `path` into
+/// the `output` directory.
+///
+/// The output contains a copy of the canister's checkpoint files (loadable by
+/// `StateMachine::import_canister_state` and thus PocketIC), the Wasm module,
+/// the Wasm and stable memories as raw files, a JSON view of the system
+/// state and the same set of artifacts for each of the canister's snapshots.
+pub fn do_extract_canister(
+    path: PathBuf,
+    canister_id: CanisterId,
+    output: PathBuf,
+) -> Result<(), String> {
+    let unused_height = Height::from(0);
+    let dummy_metrics_registry = ic_metrics::MetricsRegistry::new();
+    let dummy_metrics = CheckpointMetrics::new(&dummy_metrics_registry, crate::commands::logger());
+    let fd_factory = Arc::new(TestPageAllocatorFileDescriptorImpl::new());
+
+    let cp_layout = CompleteCheckpointLayout::new_untracked(path.clone(), unused_height)
+        .map_err(|e| format!("failed to open checkpoint {}: {}", path.display(), e))?;
+    let canister_ids = cp_layout
+        .canister_ids()
+        .map_err(|e| format!("failed to list canisters: {}", e))?;
+    if !canister_ids.contains(&canister_id) {
+        return Err(format!(
+            "canister {} not found in checkpoint {}",
+            canister_id,
+            path.display()
+        ));
+    }
+
+    let canister_layout = cp_layout
+        .canister(&canister_id)
+        .map_err(|e| format!("failed to open canister layout: {}", e))?;
+    let (canister_state, _) = load_canister_state(
+        &canister_layout,
+        &canister_id,
+        unused_height,
+        fd_factory.clone(),
+        &dummy_metrics,
+    )
+    .map_err(|e| format!("failed to load canister {}: {}", canister_id, e))?;
+
+    fs::create_dir_all(&output)
+        .map_err(|e| format!("failed to create directory {}: {}", output.display(), e))?;
+    copy_recursively(
+        &canister_layout.raw_path(),
+        &output.join(CANISTER_STATE_DIR),
+    )?;
+    if let Some(execution_state) = &canister_state.execution_state {
+        write_file(
+            &output.join(WASM_FILE),
+            execution_state.wasm_binary.binary.as_slice(),
+        )?;
+        write_memory(
+            &execution_state.wasm_memory.page_map,
+            execution_state.wasm_memory.size,
+            &output.join(WASM_MEMORY_FILE),
+        )?;
+        write_memory(
+            &execution_state.stable_memory.page_map,
+            execution_state.stable_memory.size,
+            &output.join(STABLE_MEMORY_FILE),
+        )?;
+    }
+    write_file(
+        &output.join(SYSTEM_STATE_FILE),
+        system_state_to_json(&canister_state).as_bytes(),
+    )?;
+
+    // Checkpoints created before canister snapshots were introduced have no
+    // snapshots directory.
+    let snapshot_ids = if cp_layout
+        .raw_path()
+        .join(ic_state_layout::SNAPSHOTS_DIR)
+        .exists()
+    {
+        cp_layout
+            .snapshot_ids()
+            .map_err(|e| format!("failed to list snapshots: {}", e))?
+    } else {
+        vec![]
+    };
+    for snapshot_id in snapshot_ids
+        .into_iter()
+        .filter(|id| id.get_canister_id() == canister_id)
+    {
+        let snapshot_layout = cp_layout
+            .snapshot(&snapshot_id)
+            .map_err(|e| format!("failed to open snapshot layout: {}", e))?;
+        let (snapshot, _) = load_snapshot(
+            &snapshot_layout,
+            &snapshot_id,
+            unused_height,
+            fd_factory.clone(),
+        )
+        .map_err(|e| format!("failed to load snapshot {}: {}", snapshot_id, e))?;
+
+        let snapshot_dir = output
+            .join(SNAPSHOTS_DIR)
+            .join(hex::encode(snapshot_id.as_slice()));
+        copy_recursively(
+            &snapshot_layout.raw_path(),
+            &snapshot_dir.join(CANISTER_STATE_DIR),
+        )?;
+        let execution_snapshot = snapshot.execution_snapshot();
+        write_file(
+            &snapshot_dir.join(WASM_FILE),
+            execution_snapshot.wasm_binary.as_slice(),
+        )?;
+        write_memory(
+            &execution_snapshot.wasm_memory.page_map,
+            execution_snapshot.wasm_memory.size,
+            &snapshot_dir.join(WASM_MEMORY_FILE),
+        )?;
+        write_memory(
+            &execution_snapshot.stable_memory.page_map,
+            execution_snapshot.stable_memory.size,
+            &snapshot_dir.join(STABLE_MEMORY_FILE),
+        )?;
+    }
+
+    println!(
+        "Successfully extracted canister {} into {}",
+        canister_id,
+        output.display()
+    );
+    Ok(())
+}
+
+/// Writes `bytes` to a new file at `path`.
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    fs::write(path, bytes).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+/// Writes the contents of a memory of `size` Wasm pages to `path`.
+///
+/// Pages that only contain zeros (including all pages that are not stored in
+/// the page map) are not written, so the file is sparse.
+fn write_memory(page_map: &PageMap, size: NumWasmPages, path: &Path) -> Result<(), String> {
+    let into_error = |e: std::io::Error| format!("failed to write {}: {}", path.display(), e);
+    let num_bytes = size.get() * WASM_PAGE_SIZE_IN_BYTES;
+    let num_pages = page_map.num_host_pages().min(num_bytes / PAGE_SIZE);
+
+    let file = File::create(path).map_err(into_error)?;
+    for index in 0..num_pages {
+        let page = page_map.get_page(PageIndex::new(index as u64));
+        if page.iter().any(|byte| *byte != 0) {
+            file.write_all_at(page, (index * PAGE_SIZE) as u64)
+                .map_err(into_error)?;
+        }
+    }
+    file.set_len(num_bytes as u64).map_err(into_error)
+}
+
+/// Returns a pretty-printed JSON view of the canister's system state.
+fn system_state_to_json(canister_state: &CanisterState) -> String {
+    let system_state = &canister_state.system_state;
+    let queues = system_state.queues();
+    let history = system_state.get_canister_history();
+    let value = serde_json::json!({
+        "canister_id": system_state.canister_id.to_string(),
+        "controllers": system_state
+            .controllers
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>(),
+        "status": system_state.status_string(),
+        "canister_version": system_state.canister_version,
+        "cycles_balance": system_state.balance().get().to_string(),
+        "reserved_balance": system_state.reserved_balance().get().to_string(),
+        "freeze_threshold": system_state.freeze_threshold.get(),
+        "memory_allocation": format!("{:?}", system_state.memory_allocation),
+        "compute_allocation": format!("{:?}", canister_state.scheduler_state.compute_allocation),
+        "wasm_memory_limit": system_state.wasm_memory_limit.map(|limit| limit.get()),
+        "certified_data": hex::encode(&system_state.certified_data),
+        "environment_variables": system_state.environment_variables,
+        "module_hash": canister_state
+            .execution_state
+            .as_ref()
+            .map(|es| hex::encode(es.wasm_binary.binary.module_hash())),
+        "queues": {
+            "ingress_messages": queues.ingress_queue_message_count(),
+            "input_messages": queues.input_queues_message_count(),
+            "output_messages": queues.output_queues_message_count(),
+        },
+        "history": {
+            "total_num_changes": history.get_total_num_changes(),
+            "changes": history
+                .get_changes(usize::MAX)
+                .map(|change| format!("{:?}", change))
+                .collect::<Vec<_>>(),
+        },
+    });
+    serde_json::to_string_pretty(&value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_management_canister_types::TakeCanisterSnapshotArgs;
+    use ic_state_machine_tests::{StateMachine, StateMachineBuilder};
+    use ic_universal_canister::{wasm, UNIVERSAL_CANISTER_WASM};
+
+    const STABLE_DATA: &[u8] = b"extracted";
+
+    /// Sets up a canister with some stable memory and a snapshot, creates a
+    /// checkpoint and returns the path of the checkpoint.
+    fn setup() -> (StateMachine, CanisterId, PathBuf) {
+        let env = StateMachineBuilder::new()
+            .with_checkpoints_enabled(true)
+            .build();
+        let canister_id = env
+            .install_canister(UNIVERSAL_CANISTER_WASM.to_vec(), vec![], None)
+            .unwrap();
+        env.execute_ingress(
+            canister_id,
+            "update",
+            wasm()
+                .stable_grow(1)
+                .stable_write(0, STABLE_DATA)
+                .reply()
+                .build(),
+        )
+        .unwrap();
+        env.take_canister_snapshot(TakeCanisterSnapshotArgs::new(canister_id, None))
+            .unwrap();
+        env.checkpointed_tick();
+
+        let height = *env.state_manager.checkpoint_heights().last().unwrap();
+        let path = env
+            .state_manager
+            .state_layout()
+            .checkpoint_verified(height)
+            .unwrap()
+            .raw_path()
+            .to_path_buf();
+        (env, canister_id, path)
+    }
+
+    fn assert_artifacts(dir: &Path) {
+        assert!(dir.join(CANISTER_STATE_DIR).is_dir());
+        assert_eq!(
+            fs::read(dir.join(WASM_FILE)).unwrap(),
+            UNIVERSAL_CANISTER_WASM
+        );
+        let stable_memory = fs::read(dir.join(STABLE_MEMORY_FILE)).unwrap();
+        assert_eq!(stable_memory.len(), WASM_PAGE_SIZE_IN_BYTES);
+        assert_eq!(&stable_memory[..STABLE_DATA.len()], STABLE_DATA);
+        assert!(dir.join(WASM_MEMORY_FILE).is_file());
+    }
+
+    #[test]
+    fn extract_canister_writes_canister_and_snapshot_artifacts() {
+        let (_env, canister_id, checkpoint) = setup();
+        let output = tempfile::tempdir().unwrap();
+
+        do_extract_canister(checkpoint, canister_id, output.path().to_path_buf()).unwrap();
+
+        assert_artifacts(output.path());
+        let system_state: serde_json::Value =
+            serde_json::from_slice(&fs::read(output.path().join(SYSTEM_STATE_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(system_state["canister_id"], canister_id.to_string());
+
+        let snapshots = fs::read_dir(output.path().join(SNAPSHOTS_DIR))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(snapshots.len(), 1);
+        assert_artifacts(&snapshots[0]);
+    }
+
+    #[test]
+    fn write_memory_skips_zero_pages() {
+        let mut page_map = PageMap::new_for_testing();
+        let page = [42; PAGE_SIZE];
+        page_map.update(&[(PageIndex::new(3), &page)]);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(WASM_MEMORY_FILE);
+
+        write_memory(&page_map, NumWasmPages::new(1), &path).unwrap();
+
+        let memory = fs::read(&path).unwrap();
+        assert_eq!(memory.len(), WASM_PAGE_SIZE_IN_BYTES);
+        assert!(memory[..3 * PAGE_SIZE].iter().all(|byte| *byte == 0));
+        assert_eq!(&memory[3 * PAGE_SIZE..4 * PAGE_SIZE], &page[..]);
+        assert!(memory[4 * PAGE_SIZE..].iter().all(|byte| *byte == 0));
+        // Only the non-zero page is allocated on disk.
+        let allocated_bytes =
+            std::os::unix::fs::MetadataExt::blocks(&fs::metadata(&path).unwrap()) * 512;
+        assert!(allocated_bytes < WASM_PAGE_SIZE_IN_BYTES as u64);
+    }
+
+    #[test]
+    fn extract_canister_fails_for_unknown_canister() {
+        let (_env, _, checkpoint) = setup();
+        let output = tempfile::tempdir().unwrap();
+
+        let err = do_extract_canister(
+            checkpoint,
+            CanisterId::from_u64(42),
+            output.path().to_path_buf(),
+        )
+        .unwrap_err();
+        assert!(err.contains("not found in checkpoint"), "{}", err);
+    }
+
+    #[test]
+    fn extract_canister_without_snapshots_directory_succeeds() {
+        let (_env, canister_id, checkpoint) = setup();
+        let copy = tempfile::tempdir().unwrap();
+        copy_recursively(&checkpoint, copy.path()).unwrap();
+        fs::remove_dir_all(copy.path().join(ic_state_layout::SNAPSHOTS_DIR)).unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        do_extract_canister(
+            copy.path().to_path_buf(),
+            canister_id,
+            output.path().to_path_buf(),
+        )
+        .unwrap();
+
+        assert_artifacts(output.path());
+        assert!(!output.path().join(SNAPSHOTS_DIR).exists());
+    }
+
+    #[test]
+    fn extract_canister_fails_if_snapshots_cannot_be_listed() {
+        let (_env, canister_id, checkpoint) = setup();
+        let copy = tempfile::tempdir().unwrap();
+        copy_recursively(&checkpoint, copy.path()).unwrap();
+        let snapshots_dir = copy.path().join(ic_state_layout::SNAPSHOTS_DIR);
+        fs::remove_dir_all(&snapshots_dir).unwrap();
+        fs::write(&snapshots_dir, b"not a directory").unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        let err = do_extract_canister(
+            copy.path().to_path_buf(),
+            canister_id,
+            output.path().to_path_buf(),
+        )
+        .unwrap_err();
+        assert!(err.contains("failed to list snapshots"), "{}", err);
+    }
+}
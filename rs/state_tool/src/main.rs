@@ -2,13 +2,13 @@
 //!
 //! A command-line tool to manage Internet Computer replicated states (decode
 //! persisted state files, diff checkpoints, compute partial state hashes and
-//! checkpoint manifests, import state trees).
+//! checkpoint manifests, import state trees, extract canisters).
 
 use clap::Parser;
 use ic_registry_routing_table::CanisterIdRange;
 use ic_registry_subnet_type::SubnetType;
 use ic_state_tool::commands;
-use ic_types::{PrincipalId, Time};
+use ic_types::{CanisterId, PrincipalId, Time};
 use std::path::PathBuf;
 
 /// Supported `state_tool` commands and their arguments.
@@ -27,6 +27,23 @@ enum Opt {
         path: PathBuf,
     },
 
+    /// Extracts a single canister (Wasm module, memories, system state and
+    /// snapshots) from a checkpoint.
+    #[clap(name = "extract-canister")]
+    ExtractCanister {
+        /// Path to a checkpoint.
+        #[clap(long = "state")]
+        path: PathBuf,
+
+        /// ID of the canister to extract.
+        #[clap(long = "canister_id")]
+        canister_id: CanisterId,
+
+        /// Directory to write the canister's artifacts to.
+        #[clap(long = "output")]
+        output: PathBuf,
+    },
+
     /// Imports replicated state from an external location.
     #[clap(name = "import")]
     ImportState {
@@ -154,6 +171,11 @@ fn main() {
     let result = match opt {
         Opt::CDiff { path_a, path_b } => commands::cdiff::do_diff(path_a, path_b),
         Opt::CHash { path } => commands::chash::do_hash(path),
+        Opt::ExtractCanister {
+            path,
+            canister_id,
+            output,
+        } => commands::extract_canister::do_extract_canister(path, canister_id, output),
         Opt::ImportState {
             state,
             config,
@@ -704,7 +704,10 @@ pub fn clone_file(src: &Path, dst: &Path) -> Result<(), FileCloneError> {
 }
 
 fn handle_last_os_error() -> FileCloneError {
-    let err = std::io::Error::last_os_error();
+    to_file_clone_error(std::io::Error::last_os_error())
+}
+
+fn to_file_clone_error(err: std::io::Error) -> FileCloneError {
     match err.raw_os_error() {
         Some(libc::EOPNOTSUPP) | Some(libc::ENOSYS) => FileCloneError::OperationNotSupported,
         // EOPNOTSUPP and ENOTSUP have the same value on Linux, but different
@@ -793,6 +796,133 @@ fn clone_file_impl(_src: &Path, _dst: &Path) -> Result<(), FileCloneError> {
     Err(FileCloneError::OperationNotSupported)
 }
 
+/// Makes the range `[dst_offset, dst_offset + len)` of `dst` share its storage
+/// with the range `[src_offset, src_offset + len)` of `src` if both ranges have
+/// identical contents. The kernel compares the contents under a lock, so this
+/// never changes what either file reads as and is safe to call on files that
+/// are concurrently read or mapped. `dst` does not need to be open for writing
+/// if the caller owns it.
+///
+/// Returns the number of bytes that now share storage, which is 0 if the
+/// contents differ. Offsets and `len` must be multiples of the filesystem block
+/// size.
+///
+/// # Errors
+///
+/// * Returns Err(OperationNotSupported) if the underlying filesystem doesn't
+///   support deduplication. Linux reports this as EINVAL, so it is also
+///   returned for misaligned ranges.
+///
+/// * Returns Err(DifferentFileSystems) if the files are not on the same
+///   filesystem.
+///
+/// * Returns low-level Err(IoError(e)) if the syscall fails for another reason.
+pub fn deduplicate_file_range(
+    src: &fs::File,
+    src_offset: u64,
+    dst: &fs::File,
+    dst_offset: u64,
+    len: u64,
+) -> Result<u64, FileCloneError> {
+    if *crate::IS_WSL {
+        Err(FileCloneError::OperationNotSupported)
+    } else {
+        deduplicate_file_range_impl(src, src_offset, dst, dst_offset, len)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn deduplicate_file_range_impl(
+    src: &fs::File,
+    src_offset: u64,
+    dst: &fs::File,
+    dst_offset: u64,
+    len: u64,
+) -> Result<u64, FileCloneError> {
+    use std::os::unix::io::AsRawFd;
+
+    // `struct file_dedupe_range` from <linux/fs.h> with a single destination.
+    #[repr(C)]
+    struct FileDedupeRange {
+        src_offset: u64,
+        src_length: u64,
+        dest_count: u16,
+        reserved1: u16,
+        reserved2: u32,
+        info: [FileDedupeRangeInfo; 1],
+    }
+
+    // `struct file_dedupe_range_info` from <linux/fs.h>.
+    #[repr(C)]
+    struct FileDedupeRangeInfo {
+        dest_fd: i64,
+        dest_offset: u64,
+        bytes_deduped: u64,
+        status: i32,
+        reserved: u32,
+    }
+
+    // FIDEDUPERANGE = _IOWR(0x94, 54, struct file_dedupe_range), where the size
+    // of the struct without the flexible `info` array is 24 bytes. See the
+    // comment on FICLONE_IO_REQ for why this is a macro.
+    macro_rules! FIDEDUPERANGE_IO_REQ {
+        () => {
+            3222836278
+        };
+    }
+    const FILE_DEDUPE_RANGE_DIFFERS: i32 = 1;
+
+    let mut range = FileDedupeRange {
+        src_offset,
+        src_length: len,
+        dest_count: 1,
+        reserved1: 0,
+        reserved2: 0,
+        info: [FileDedupeRangeInfo {
+            dest_fd: dst.as_raw_fd() as i64,
+            dest_offset: dst_offset,
+            bytes_deduped: 0,
+            status: 0,
+            reserved: 0,
+        }],
+    };
+
+    // See https://www.man7.org/linux/man-pages/man2/ioctl_fideduperange.2.html
+    //
+    // int ioctl(int src_fd, FIDEDUPERANGE, struct file_dedupe_range *arg);
+    let ec = unsafe {
+        libc::ioctl(
+            src.as_raw_fd(),
+            FIDEDUPERANGE_IO_REQ!(),
+            &mut range as *mut FileDedupeRange,
+        )
+    };
+    let to_error = |err: std::io::Error| match err.raw_os_error() {
+        Some(libc::EINVAL) => FileCloneError::OperationNotSupported,
+        _ => to_file_clone_error(err),
+    };
+    if ec < 0 {
+        return Err(to_error(std::io::Error::last_os_error()));
+    }
+    let info = &range.info[0];
+    match info.status {
+        FILE_DEDUPE_RANGE_DIFFERS => Ok(0),
+        status if status < 0 => Err(to_error(std::io::Error::from_raw_os_error(-status))),
+        _ => Ok(info.bytes_deduped),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn deduplicate_file_range_impl(
+    _src: &fs::File,
+    _src_offset: u64,
+    _dst: &fs::File,
+    _dst_offset: u64,
+    _len: u64,
+) -> Result<u64, FileCloneError> {
+    Err(FileCloneError::OperationNotSupported)
+}
+
 #[cfg(test)]
 mod tests {
     use super::advance_slices;
@@ -851,6 +981,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_deduplicate_file_range() {
+        use super::{deduplicate_file_range, FileCloneError};
+
+        let tmp_dir = tempfile::TempDir::new().expect("failed to create a temporary directory");
+        let len = 2 * crate::PAGE_SIZE;
+        let write = |name: &str, content: Vec<u8>| {
+            let path = tmp_dir.path().join(name);
+            std::fs::write(&path, content).expect("failed to write file");
+            std::fs::File::open(path).expect("failed to open file")
+        };
+        let a = write("a", vec![1; len]);
+        let b = write("b", vec![1; len]);
+        let c = write("c", vec![2; len]);
+
+        // Deduplication is not supported by every filesystem the tests run on.
+        match deduplicate_file_range(&a, 0, &b, 0, len as u64) {
+            Ok(bytes) => assert_eq!(bytes, len as u64),
+            Err(FileCloneError::OperationNotSupported) => return,
+            Err(err) => panic!("failed to deduplicate identical ranges: {}", err),
+        }
+        assert_eq!(
+            deduplicate_file_range(&a, 0, &c, 0, len as u64).expect("failed to deduplicate"),
+            0
+        );
+        assert_eq!(
+            std::fs::read(tmp_dir.path().join("b")).unwrap(),
+            vec![1; len]
+        );
+        assert_eq!(
+            std::fs::read(tmp_dir.path().join("c")).unwrap(),
+            vec![2; len]
+        );
+    }
+
     #[test]
     fn test_advance_slices() {
         let slice_size = 4096;
@@ -17,6 +17,9 @@ use ic_interfaces::execution_environment::{
     TrapCode::{self, CyclesAmountTooBigFor64Bit},
 };
 use ic_logger::{error, ReplicaLogger};
+use ic_management_canister_types::{
+    EcdsaCurve, EcdsaKeyId, MasterPublicKeyId, SchnorrAlgorithm, SchnorrKeyId,
+};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
     canister_state::WASM_PAGE_SIZE_IN_BYTES, memory_required_to_push_request, Memory, NumWasmPages,
@@ -57,6 +60,11 @@ const MAX_32_BIT_STABLE_MEMORY_IN_PAGES: u64 = 64 * 1024; // 4GiB
 /// best-effort responses represented in seconds.
 pub const MAX_CALL_TIMEOUT_SECONDS: u32 = 300;
 
+/// Results of `ic0.cost_sign_with_ecdsa` and `ic0.cost_sign_with_schnorr`.
+const COST_SIGN_WITH_SUCCESS: u32 = 0;
+const COST_SIGN_WITH_UNKNOWN_ALGORITHM: u32 = 1;
+const COST_SIGN_WITH_UNKNOWN_KEY: u32 = 2;
+
 // This macro is used in system calls for tracing.
 macro_rules! trace_syscall {
     ($self:ident, $name:ident, $result:expr $( , $args:expr )*) => {{
@@ -940,6 +948,16 @@ impl MemoryUsage {
     }
 }
 
+/// A Wasm function call that is active while executing with canister
+/// profiling enabled.
+struct ProfiledCall {
+    function_index: u32,
+    /// The message instructions executed when the call started.
+    start_instructions: u64,
+    /// Instructions executed by the completed callees of this call.
+    callee_instructions: u64,
+}
+
 /// Struct that implements the SystemApi trait. This trait enables a canister to
 /// have mediated access to its system state.
 pub struct SystemApiImpl {
@@ -990,6 +1008,10 @@ pub struct SystemApiImpl {
 
     /// How many times each tracked System API call was invoked.
     call_counters: SystemApiCallCounters,
+
+    /// The Wasm function calls that are currently active, innermost last.
+    /// Only maintained if the canister has profiling enabled.
+    profiled_call_stack: Vec<ProfiledCall>,
 }
 
 impl SystemApiImpl {
@@ -1033,6 +1055,7 @@ impl SystemApiImpl {
             current_slice_instruction_limit: i64::try_from(slice_limit).unwrap_or(i64::MAX),
             instructions_executed_before_current_slice: 0,
             call_counters: SystemApiCallCounters::default(),
+            profiled_call_stack: vec![],
         }
     }
 
@@ -1155,6 +1178,47 @@ impl SystemApiImpl {
         }
     }
 
+    /// Returns the name of the environment variable at position `index`,
+    /// where variables are ordered by name.
+    fn get_env_var_name(&self, method_name: &str, index: usize) -> HypervisorResult<&str> {
+        let environment_variables = self.sandbox_safe_system_state.environment_variables();
+        environment_variables
+            .keys()
+            .nth(index)
+            .map(|name| name.as_str())
+            .ok_or_else(|| ToolchainContractViolation {
+                error: format!(
+                    "{}: index {} is out of bounds for {} environment variables.",
+                    method_name,
+                    index,
+                    environment_variables.len()
+                ),
+            })
+    }
+
+    /// Returns the value of the environment variable whose name is stored in
+    /// heap[name_src..name_src+name_size] or `None` if there is no such variable.
+    fn get_env_var_value(
+        &self,
+        method_name: &str,
+        name_src: usize,
+        name_size: usize,
+        heap: &[u8],
+    ) -> HypervisorResult<Option<&str>> {
+        let name = valid_subslice(&format!("{} name", method_name), name_src, name_size, heap)?;
+        let name = std::str::from_utf8(name).map_err(|_| ToolchainContractViolation {
+            error: format!(
+                "{}: the environment variable name is not valid UTF-8.",
+                method_name
+            ),
+        })?;
+        Ok(self
+            .sandbox_safe_system_state
+            .environment_variables()
+            .get(name)
+            .map(|value| value.as_str()))
+    }
+
     fn get_response_info(&mut self) -> Option<(&mut Vec<u8>, &NumBytes, &mut ResponseStatus)> {
         match &mut self.api_type {
             ApiType::Start { .. }
@@ -1499,6 +1563,65 @@ impl SystemApiImpl {
         self.sandbox_safe_system_state.canister_log()
     }
 
+    /// Called by the instrumented Wasm code when entering the function with the
+    /// given index (in the module before instrumentation). Does nothing unless
+    /// the canister has profiling enabled.
+    pub fn profile_function_entry(&mut self, function_index: u32, instruction_counter: i64) {
+        if !self.sandbox_safe_system_state.canister_profiling() {
+            return;
+        }
+        let start_instructions = self
+            .message_instructions_executed(instruction_counter)
+            .get();
+        self.profiled_call_stack.push(ProfiledCall {
+            function_index,
+            start_instructions,
+            callee_instructions: 0,
+        });
+    }
+
+    /// Called by the instrumented Wasm code when returning from the function
+    /// with the given index. Records the instructions executed by the call.
+    /// Does nothing unless the canister has profiling enabled.
+    pub fn profile_function_exit(&mut self, function_index: u32, instruction_counter: i64) {
+        if !self.sandbox_safe_system_state.canister_profiling() {
+            return;
+        }
+        let end_instructions = self
+            .message_instructions_executed(instruction_counter)
+            .get();
+        let call = match self.profiled_call_stack.pop() {
+            Some(call) if call.function_index == function_index => call,
+            // Entries and exits are always balanced by the instrumentation,
+            // so this cannot happen.
+            _ => {
+                self.profiled_call_stack.clear();
+                return;
+            }
+        };
+        let inclusive_instructions = end_instructions.saturating_sub(call.start_instructions);
+        let exclusive_instructions =
+            inclusive_instructions.saturating_sub(call.callee_instructions);
+        if let Some(caller) = self.profiled_call_stack.last_mut() {
+            caller.callee_instructions = caller
+                .callee_instructions
+                .saturating_add(inclusive_instructions);
+        }
+        let mut call_stack: Vec<u32> = self
+            .profiled_call_stack
+            .iter()
+            .map(|call| call.function_index)
+            .collect();
+        let recursive = call_stack.contains(&function_index);
+        call_stack.push(function_index);
+        self.sandbox_safe_system_state.record_profiled_call(
+            &call_stack,
+            inclusive_instructions,
+            exclusive_instructions,
+            recursive,
+        );
+    }
+
     /// Checks if the current API type is an install or upgrade message.
     /// This is relevant when enforcing the stable memory dirty page limit.
     pub fn is_install_or_upgrade_message(&self) -> bool {
@@ -2053,6 +2176,325 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
+    fn ic0_subnet_self_size(&self) -> HypervisorResult<usize> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_subnet_self_size")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => Ok(self
+                .sandbox_safe_system_state
+                .subnet_id()
+                .get_ref()
+                .as_slice()
+                .len()),
+        };
+        trace_syscall!(self, SubnetSelfSize, result);
+        result
+    }
+
+    fn ic0_subnet_self_copy(
+        &self,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_subnet_self_copy")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::InspectMessage { .. } => {
+                valid_subslice("ic0.subnet_self_copy heap", dst, size, heap)?;
+                let subnet_id = self.sandbox_safe_system_state.subnet_id();
+                let id_bytes = subnet_id.get_ref().as_slice();
+                let slice = valid_subslice("ic0.subnet_self_copy id", offset, size, id_bytes)?;
+                deterministic_copy_from_slice(&mut heap[dst..dst + size], slice);
+                Ok(())
+            }
+        };
+        trace_syscall!(
+            self,
+            SubnetSelfCopy,
+            result,
+            dst,
+            offset,
+            size,
+            summarize(heap, dst, size)
+        );
+        result
+    }
+
+    fn ic0_root_key_size(&self) -> HypervisorResult<usize> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_root_key_size")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => Ok(self.sandbox_safe_system_state.root_key().len()),
+        };
+        trace_syscall!(self, RootKeySize, result);
+        result
+    }
+
+    fn ic0_root_key_copy(
+        &self,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_root_key_copy")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::InspectMessage { .. } => {
+                valid_subslice("ic0.root_key_copy heap", dst, size, heap)?;
+                let root_key = self.sandbox_safe_system_state.root_key();
+                let slice = valid_subslice("ic0.root_key_copy key", offset, size, root_key)?;
+                deterministic_copy_from_slice(&mut heap[dst..dst + size], slice);
+                Ok(())
+            }
+        };
+        trace_syscall!(
+            self,
+            RootKeyCopy,
+            result,
+            dst,
+            offset,
+            size,
+            summarize(heap, dst, size)
+        );
+        result
+    }
+
+    fn ic0_env_var_count(&self) -> HypervisorResult<usize> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_count")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                Ok(self.sandbox_safe_system_state.environment_variables().len())
+            }
+        };
+        trace_syscall!(self, EnvVarCount, result);
+        result
+    }
+
+    fn ic0_env_var_name_size(&self, index: usize) -> HypervisorResult<usize> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_name_size")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => self
+                .get_env_var_name("ic0.env_var_name_size", index)
+                .map(|name| name.len()),
+        };
+        trace_syscall!(self, EnvVarNameSize, result, index);
+        result
+    }
+
+    fn ic0_env_var_name_copy(
+        &self,
+        index: usize,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_name_copy")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                valid_subslice("ic0.env_var_name_copy heap", dst, size, heap)?;
+                let name = self.get_env_var_name("ic0.env_var_name_copy", index)?;
+                let slice =
+                    valid_subslice("ic0.env_var_name_copy name", offset, size, name.as_bytes())?;
+                deterministic_copy_from_slice(&mut heap[dst..dst + size], slice);
+                Ok(())
+            }
+        };
+        trace_syscall!(
+            self,
+            EnvVarNameCopy,
+            result,
+            index,
+            dst,
+            offset,
+            size,
+            summarize(heap, dst, size)
+        );
+        result
+    }
+
+    fn ic0_env_var_name_exists(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        heap: &[u8],
+    ) -> HypervisorResult<i32> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_name_exists")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                let value =
+                    self.get_env_var_value("ic0.env_var_name_exists", name_src, name_size, heap)?;
+                Ok(value.is_some() as i32)
+            }
+        };
+        trace_syscall!(
+            self,
+            EnvVarNameExists,
+            result,
+            summarize(heap, name_src, name_size)
+        );
+        result
+    }
+
+    fn ic0_env_var_value_size(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        heap: &[u8],
+    ) -> HypervisorResult<usize> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_value_size")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                match self.get_env_var_value("ic0.env_var_value_size", name_src, name_size, heap)? {
+                    Some(value) => Ok(value.len()),
+                    None => Err(ToolchainContractViolation {
+                        error: "ic0.env_var_value_size: the environment variable does not exist."
+                            .to_string(),
+                    }),
+                }
+            }
+        };
+        trace_syscall!(
+            self,
+            EnvVarValueSize,
+            result,
+            summarize(heap, name_src, name_size)
+        );
+        result
+    }
+
+    fn ic0_env_var_value_copy(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_value_copy")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                valid_subslice("ic0.env_var_value_copy heap", dst, size, heap)?;
+                let value = self
+                    .get_env_var_value("ic0.env_var_value_copy", name_src, name_size, heap)?
+                    .ok_or_else(|| ToolchainContractViolation {
+                        error: "ic0.env_var_value_copy: the environment variable does not exist."
+                            .to_string(),
+                    })?;
+                let slice = valid_subslice(
+                    "ic0.env_var_value_copy value",
+                    offset,
+                    size,
+                    value.as_bytes(),
+                )?;
+                deterministic_copy_from_slice(&mut heap[dst..dst + size], slice);
+                Ok(())
+            }
+        };
+        trace_syscall!(
+            self,
+            EnvVarValueCopy,
+            result,
+            name_src,
+            name_size,
+            dst,
+            offset,
+            size,
+            summarize(heap, dst, size)
+        );
+        result
+    }
+
     fn ic0_call_new(
         &mut self,
         callee_src: usize,
@@ -3302,6 +3744,174 @@ impl SystemApi for SystemApiImpl {
         trace_syscall!(self, CyclesBurn128, result, amount);
         result
     }
+
+    fn ic0_cost_call(
+        &self,
+        method_name_size: u64,
+        payload_size: u64,
+        dst: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let method_name = "ic0_cost_call";
+        let result = match self.api_type {
+            ApiType::Start { .. } => Err(self.error_for(method_name)),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::InspectMessage { .. } => {
+                let cost = self
+                    .sandbox_safe_system_state
+                    .cost_call(method_name_size, payload_size);
+                copy_cycles_to_heap(cost, dst, heap, method_name)
+            }
+        };
+        trace_syscall!(self, CostCall, result, method_name_size, payload_size);
+        result
+    }
+
+    fn ic0_cost_create_canister(&self, dst: usize, heap: &mut [u8]) -> HypervisorResult<()> {
+        let method_name = "ic0_cost_create_canister";
+        let result = match self.api_type {
+            ApiType::Start { .. } => Err(self.error_for(method_name)),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::InspectMessage { .. } => {
+                let cost = self.sandbox_safe_system_state.cost_create_canister();
+                copy_cycles_to_heap(cost, dst, heap, method_name)
+            }
+        };
+        trace_syscall!(self, CostCreateCanister, result);
+        result
+    }
+
+    fn ic0_cost_http_request(
+        &self,
+        request_size: u64,
+        max_res_bytes: u64,
+        dst: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let method_name = "ic0_cost_http_request";
+        let result = match self.api_type {
+            ApiType::Start { .. } => Err(self.error_for(method_name)),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::InspectMessage { .. } => {
+                let cost = self
+                    .sandbox_safe_system_state
+                    .cost_http_request(request_size, max_res_bytes);
+                copy_cycles_to_heap(cost, dst, heap, method_name)
+            }
+        };
+        trace_syscall!(self, CostHttpRequest, result, request_size, max_res_bytes);
+        result
+    }
+
+    fn ic0_cost_sign_with_ecdsa(
+        &self,
+        src: usize,
+        size: usize,
+        curve: u32,
+        dst: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<u32> {
+        let method_name = "ic0_cost_sign_with_ecdsa";
+        let result = match self.api_type {
+            ApiType::Start { .. } => Err(self.error_for(method_name)),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::InspectMessage { .. } => {
+                let name = valid_subslice("ic0.cost_sign_with_ecdsa", src, size, heap)?;
+                let name = String::from_utf8_lossy(name).to_string();
+                let key_id = match curve {
+                    0 => Some(MasterPublicKeyId::Ecdsa(EcdsaKeyId {
+                        curve: EcdsaCurve::Secp256k1,
+                        name,
+                    })),
+                    _ => None,
+                };
+                copy_signature_cost_to_heap(
+                    &self.sandbox_safe_system_state,
+                    key_id,
+                    dst,
+                    heap,
+                    method_name,
+                )
+            }
+        };
+        trace_syscall!(self, CostSignWithEcdsa, result, curve);
+        result
+    }
+
+    fn ic0_cost_sign_with_schnorr(
+        &self,
+        src: usize,
+        size: usize,
+        algorithm: u32,
+        dst: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<u32> {
+        let method_name = "ic0_cost_sign_with_schnorr";
+        let result = match self.api_type {
+            ApiType::Start { .. } => Err(self.error_for(method_name)),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::InspectMessage { .. } => {
+                let name = valid_subslice("ic0.cost_sign_with_schnorr", src, size, heap)?;
+                let name = String::from_utf8_lossy(name).to_string();
+                let algorithm = match algorithm {
+                    0 => Some(SchnorrAlgorithm::Bip340Secp256k1),
+                    1 => Some(SchnorrAlgorithm::Ed25519),
+                    _ => None,
+                };
+                let key_id = algorithm
+                    .map(|algorithm| MasterPublicKeyId::Schnorr(SchnorrKeyId { algorithm, name }));
+                copy_signature_cost_to_heap(
+                    &self.sandbox_safe_system_state,
+                    key_id,
+                    dst,
+                    heap,
+                    method_name,
+                )
+            }
+        };
+        trace_syscall!(self, CostSignWithSchnorr, result, algorithm);
+        result
+    }
 }
 
 /// The default implementation of the `OutOfInstructionHandler` trait.
@@ -3333,6 +3943,27 @@ impl OutOfInstructionsHandler for DefaultOutOfInstructionsHandler {
     }
 }
 
+/// Copies the fee for signing with the given threshold key to `dst`.
+/// The key is `None` if its curve or algorithm is unknown.
+fn copy_signature_cost_to_heap(
+    sandbox_safe_system_state: &SandboxSafeSystemState,
+    key_id: Option<MasterPublicKeyId>,
+    dst: usize,
+    heap: &mut [u8],
+    method_name: &str,
+) -> HypervisorResult<u32> {
+    let Some(key_id) = key_id else {
+        return Ok(COST_SIGN_WITH_UNKNOWN_ALGORITHM);
+    };
+    match sandbox_safe_system_state.cost_sign_with(&key_id) {
+        Some(cost) => {
+            copy_cycles_to_heap(cost, dst, heap, method_name)?;
+            Ok(COST_SIGN_WITH_SUCCESS)
+        }
+        None => Ok(COST_SIGN_WITH_UNKNOWN_KEY),
+    }
+}
+
 pub(crate) fn copy_cycles_to_heap(
     cycles: Cycles,
     dst: usize,
@@ -9,10 +9,11 @@ use ic_management_canister_types::{
     BitcoinGetUtxosArgs, BitcoinSendTransactionArgs, CanisterIdRecord, CanisterInfoRequest,
     ClearChunkStoreArgs, ComputeInitialIDkgDealingsArgs, DeleteCanisterSnapshotArgs,
     ECDSAPublicKeyArgs, InstallChunkedCodeArgs, InstallCodeArgsV2, ListCanisterSnapshotArgs,
-    LoadCanisterSnapshotArgs, MasterPublicKeyId, Method as Ic00Method, NodeMetricsHistoryArgs,
-    Payload, ProvisionalTopUpCanisterArgs, SchnorrPublicKeyArgs, SignWithECDSAArgs,
-    SignWithSchnorrArgs, StoredChunksArgs, TakeCanisterSnapshotArgs, UninstallCodeArgs,
-    UpdateSettingsArgs, UploadChunkArgs,
+    LoadCanisterSnapshotArgs, MasterPublicKeyId, Method as Ic00Method, MigrateCanisterArgs,
+    NodeMetricsHistoryArgs, Payload, ProvisionalTopUpCanisterArgs, SchnorrPublicKeyArgs,
+    SignWithECDSAArgs, SignWithECDSABatchArgs, SignWithSchnorrArgs, SignWithSchnorrBatchArgs,
+    StoredChunksArgs, TakeCanisterSnapshotArgs, UninstallCodeArgs, UpdateSettingsArgs,
+    UploadChunkArgs,
 };
 use ic_replicated_state::NetworkTopology;
 use itertools::Itertools;
@@ -65,7 +66,10 @@ pub(super) fn resolve_destination(
         | Ok(Ic00Method::ProvisionalCreateCanisterWithCycles)
         | Ok(Ic00Method::HttpRequest)
         | Ok(Ic00Method::BitcoinSendTransactionInternal)
-        | Ok(Ic00Method::BitcoinGetSuccessors) => Ok(own_subnet.get()),
+        | Ok(Ic00Method::BitcoinGetSuccessors)
+        // Only ever sent by subnets: canisters calling it are rejected by
+        // their own subnet.
+        | Ok(Ic00Method::ImportCanisterState) => Ok(own_subnet.get()),
         // This message needs to be routed to the NNS subnet.  We assume that
         // this message can only be sent by canisters on the NNS subnet hence
         // returning `own_subnet` here is fine.
@@ -168,12 +172,13 @@ pub(super) fn resolve_destination(
         Ok(Ic00Method::NodeMetricsHistory) => {
             Ok(NodeMetricsHistoryArgs::decode(payload)?.subnet_id)
         }
-        Ok(Ic00Method::FetchCanisterLogs) => {
+        Ok(method @ Ic00Method::FetchCanisterLogs)
+        | Ok(method @ Ic00Method::FetchCanisterProfile) => {
             Err(ResolveDestinationError::UserError(UserError::new(
                 ic_error_types::ErrorCode::CanisterRejectedMessage,
                 format!(
                     "{} API is only accessible to end users in non-replicated mode",
-                    Ic00Method::FetchCanisterLogs
+                    method
                 ),
             )))
         }
@@ -195,6 +200,15 @@ pub(super) fn resolve_destination(
                 IDkgSubnetKind::HoldsAndSignWithKey,
             )
         }
+        Ok(Ic00Method::SignWithECDSABatch) => {
+            let key_id = SignWithECDSABatchArgs::decode(payload)?.key_id;
+            route_idkg_message(
+                &MasterPublicKeyId::Ecdsa(key_id),
+                network_topology,
+                &None,
+                IDkgSubnetKind::HoldsAndSignWithKey,
+            )
+        }
         Ok(Ic00Method::ComputeInitialIDkgDealings) => {
             let args = ComputeInitialIDkgDealingsArgs::decode(payload)?;
             route_idkg_message(
@@ -222,6 +236,15 @@ pub(super) fn resolve_destination(
                 IDkgSubnetKind::HoldsAndSignWithKey,
             )
         }
+        Ok(Ic00Method::SignWithSchnorrBatch) => {
+            let args = SignWithSchnorrBatchArgs::decode(payload)?;
+            route_idkg_message(
+                &MasterPublicKeyId::Schnorr(args.key_id),
+                network_topology,
+                &None,
+                IDkgSubnetKind::HoldsAndSignWithKey,
+            )
+        }
         Ok(Ic00Method::UploadChunk) => {
             let args = UploadChunkArgs::decode(payload)?;
             let canister_id = args.get_canister_id();
@@ -273,6 +296,11 @@ pub(super) fn resolve_destination(
                 network_topology,
             )
         }
+        Ok(Ic00Method::MigrateCanister) => {
+            let args = MigrateCanisterArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            route_canister_id(canister_id, Ic00Method::MigrateCanister, network_topology)
+        }
         Err(_) => Err(ResolveDestinationError::MethodNotFound(
             method_name.to_string(),
         )),
@@ -395,6 +423,8 @@ mod tests {
     use ic_base_types::RegistryVersion;
     use ic_management_canister_types::{
         DerivationPath, EcdsaCurve, EcdsaKeyId, SchnorrAlgorithm, SchnorrKeyId, SignWithECDSAArgs,
+        SignWithECDSABatchEntries, SignWithECDSABatchEntry, SignWithSchnorrBatchEntries,
+        SignWithSchnorrBatchEntry,
     };
     use ic_replicated_state::SubnetTopology;
     use ic_test_utilities_types::ids::{canister_test_id, node_test_id, subnet_test_id};
@@ -537,6 +567,34 @@ mod tests {
         Encode!(&args).unwrap()
     }
 
+    fn ecdsa_sign_batch_request(key_id: EcdsaKeyId) -> Vec<u8> {
+        let args = SignWithECDSABatchArgs {
+            messages: SignWithECDSABatchEntries::new(vec![
+                SignWithECDSABatchEntry {
+                    message_hash: [1; 32],
+                    derivation_path: DerivationPath::new(vec![ByteBuf::from(vec![0; 10])]),
+                };
+                2
+            ]),
+            key_id,
+        };
+        Encode!(&args).unwrap()
+    }
+
+    fn schnorr_sign_batch_request(key_id: SchnorrKeyId) -> Vec<u8> {
+        let args = SignWithSchnorrBatchArgs {
+            messages: SignWithSchnorrBatchEntries::new(vec![
+                SignWithSchnorrBatchEntry {
+                    message: vec![1; 32],
+                    derivation_path: DerivationPath::new(vec![ByteBuf::from(vec![0; 10])]),
+                };
+                2
+            ]),
+            key_id,
+        };
+        Encode!(&args).unwrap()
+    }
+
     fn ecdsa_public_key_request(key_id: EcdsaKeyId) -> Vec<u8> {
         let args = ECDSAPublicKeyArgs {
             canister_id: Some(canister_test_id(1)),
@@ -693,6 +751,16 @@ mod tests {
                 Ic00Method::SignWithSchnorr,
                 schnorr_sign_request(schnorr_key_id1()),
             ),
+            (
+                network_with_ecdsa_subnets(),
+                Ic00Method::SignWithECDSABatch,
+                ecdsa_sign_batch_request(ecdsa_key_id1()),
+            ),
+            (
+                network_with_schnorr_subnets(),
+                Ic00Method::SignWithSchnorrBatch,
+                schnorr_sign_batch_request(schnorr_key_id1()),
+            ),
         ] {
             assert_eq!(
                 resolve_destination(
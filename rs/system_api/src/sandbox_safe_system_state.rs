@@ -11,8 +11,8 @@ use ic_interfaces::execution_environment::{HypervisorError, HypervisorResult};
 use ic_logger::{info, ReplicaLogger};
 use ic_management_canister_types::{
     CreateCanisterArgs, InstallChunkedCodeArgs, InstallCodeArgsV2, LoadCanisterSnapshotArgs,
-    Method as Ic00Method, Payload, ProvisionalCreateCanisterWithCyclesArgs, UninstallCodeArgs,
-    UpdateSettingsArgs, IC_00,
+    MasterPublicKeyId, Method as Ic00Method, Payload, ProvisionalCreateCanisterWithCyclesArgs,
+    UninstallCodeArgs, UpdateSettingsArgs, IC_00,
 };
 use ic_nns_constants::CYCLES_MINTING_CANISTER_ID;
 use ic_registry_subnet_type::SubnetType;
@@ -21,6 +21,7 @@ use ic_replicated_state::{
     CallOrigin, CanisterStatus, NetworkTopology, SystemState,
 };
 use ic_types::{
+    canister_profile::CanisterProfile,
     messages::{CallContextId, CallbackId, RejectContext, Request, RequestMetadata, NO_DEADLINE},
     methods::Callback,
     time::CoarseTime,
@@ -73,6 +74,8 @@ pub struct SystemStateChanges {
     requests: Vec<Request>,
     pub(super) new_global_timer: Option<CanisterTimer>,
     canister_log: CanisterLog,
+    // Calls recorded while executing with canister profiling enabled.
+    canister_profile: CanisterProfile,
 }
 
 impl Default for SystemStateChanges {
@@ -88,6 +91,7 @@ impl Default for SystemStateChanges {
             requests: vec![],
             new_global_timer: None,
             canister_log: Default::default(),
+            canister_profile: Default::default(),
         }
     }
 }
@@ -232,6 +236,7 @@ impl SystemStateChanges {
             Ok(Ic00Method::LoadCanisterSnapshot) => LoadCanisterSnapshotArgs::decode(payload)
                 .map(|record| record.get_sender_canister_version()),
             Ok(Ic00Method::SignWithECDSA)
+            | Ok(Ic00Method::SignWithECDSABatch)
             | Ok(Ic00Method::CanisterStatus)
             | Ok(Ic00Method::CanisterInfo)
             | Ok(Ic00Method::StartCanister)
@@ -245,6 +250,7 @@ impl SystemStateChanges {
             | Ok(Ic00Method::ComputeInitialIDkgDealings)
             | Ok(Ic00Method::SchnorrPublicKey)
             | Ok(Ic00Method::SignWithSchnorr)
+            | Ok(Ic00Method::SignWithSchnorrBatch)
             | Ok(Ic00Method::ProvisionalTopUpCanister)
             | Ok(Ic00Method::BitcoinSendTransactionInternal)
             | Ok(Ic00Method::BitcoinGetSuccessors)
@@ -255,12 +261,15 @@ impl SystemStateChanges {
             | Ok(Ic00Method::BitcoinGetCurrentFeePercentiles)
             | Ok(Ic00Method::NodeMetricsHistory)
             | Ok(Ic00Method::FetchCanisterLogs)
+            | Ok(Ic00Method::FetchCanisterProfile)
             | Ok(Ic00Method::UploadChunk)
             | Ok(Ic00Method::StoredChunks)
             | Ok(Ic00Method::ClearChunkStore)
             | Ok(Ic00Method::TakeCanisterSnapshot)
             | Ok(Ic00Method::ListCanisterSnapshots)
-            | Ok(Ic00Method::DeleteCanisterSnapshot) => Ok(None),
+            | Ok(Ic00Method::DeleteCanisterSnapshot)
+            | Ok(Ic00Method::MigrateCanister)
+            | Ok(Ic00Method::ImportCanisterState) => Ok(None),
             Err(_) => Err(UserError::new(
                 ErrorCode::CanisterMethodNotFound,
                 format!("Management canister has no method '{}'", msg.method_name),
@@ -468,6 +477,11 @@ impl SystemStateChanges {
             system_state.global_timer = new_global_timer;
         }
 
+        // Accumulate the recorded calls if profiling is (still) enabled.
+        if let Some(canister_profile) = system_state.canister_profile.as_mut() {
+            canister_profile.append(self.canister_profile);
+        }
+
         Ok(request_stats)
     }
 
@@ -573,9 +587,16 @@ pub struct SandboxSafeSystemState {
     available_request_slots: BTreeMap<CanisterId, usize>,
     ic00_available_request_slots: usize,
     ic00_aliases: BTreeSet<CanisterId>,
+    // The fees for signing with the threshold keys enabled for signing,
+    // scaled for the size of the signing subnet.
+    threshold_signature_fees: BTreeMap<MasterPublicKeyId, Cycles>,
+    // The DER-encoded public key of the IC root of trust (the NNS subnet).
+    root_key: Vec<u8>,
     global_timer: CanisterTimer,
     canister_version: u64,
     controllers: BTreeSet<PrincipalId>,
+    environment_variables: BTreeMap<String, String>,
+    canister_profiling: bool,
     pub(super) request_metadata: RequestMetadata,
     caller: Option<PrincipalId>,
 }
@@ -602,11 +623,15 @@ impl SandboxSafeSystemState {
         available_request_slots: BTreeMap<CanisterId, usize>,
         ic00_available_request_slots: usize,
         ic00_aliases: BTreeSet<CanisterId>,
+        threshold_signature_fees: BTreeMap<MasterPublicKeyId, Cycles>,
+        root_key: Vec<u8>,
         subnet_size: usize,
         dirty_page_overhead: NumInstructions,
         global_timer: CanisterTimer,
         canister_version: u64,
         controllers: BTreeSet<PrincipalId>,
+        environment_variables: BTreeMap<String, String>,
+        canister_profiling: bool,
         request_metadata: RequestMetadata,
         caller: Option<PrincipalId>,
         next_canister_log_record_idx: u64,
@@ -638,9 +663,13 @@ impl SandboxSafeSystemState {
             available_request_slots,
             ic00_available_request_slots,
             ic00_aliases,
+            threshold_signature_fees,
+            root_key,
             global_timer,
             canister_version,
             controllers,
+            environment_variables,
+            canister_profiling,
             request_metadata,
             caller,
         }
@@ -698,6 +727,36 @@ impl SandboxSafeSystemState {
         let subnet_size = network_topology
             .get_subnet_size(&cycles_account_manager.get_subnet_id())
             .unwrap_or(SMALL_APP_SUBNET_MAX_SIZE);
+        // Signature requests are routed to the first subnet enabled to sign
+        // with the key and are free of charge for canisters on the NNS subnet.
+        let threshold_signature_fees = network_topology
+            .idkg_signing_subnets
+            .iter()
+            .filter_map(|(key_id, signing_subnets)| {
+                let signing_subnet_size = network_topology
+                    .get_subnet_size(signing_subnets.first()?)
+                    .unwrap_or(SMALL_APP_SUBNET_MAX_SIZE);
+                let fee =
+                    if cycles_account_manager.get_subnet_id() == network_topology.nns_subnet_id {
+                        Cycles::zero()
+                    } else {
+                        match key_id {
+                            MasterPublicKeyId::Ecdsa(_) => {
+                                cycles_account_manager.ecdsa_signature_fee(signing_subnet_size)
+                            }
+                            MasterPublicKeyId::Schnorr(_) => {
+                                cycles_account_manager.schnorr_signature_fee(signing_subnet_size)
+                            }
+                        }
+                    };
+                Some((key_id.clone(), fee))
+            })
+            .collect();
+        let root_key = network_topology
+            .subnets
+            .get(&network_topology.nns_subnet_id)
+            .map(|subnet_topology| subnet_topology.public_key.clone())
+            .unwrap_or_default();
 
         Self::new_internal(
             system_state.canister_id,
@@ -719,11 +778,15 @@ impl SandboxSafeSystemState {
             available_request_slots,
             ic00_available_request_slots,
             ic00_aliases,
+            threshold_signature_fees,
+            root_key,
             subnet_size,
             dirty_page_overhead,
             system_state.global_timer,
             system_state.canister_version,
             system_state.controllers.clone(),
+            system_state.environment_variables.clone(),
+            system_state.canister_profile.is_some(),
             request_metadata,
             caller,
             system_state.canister_log.next_idx(),
@@ -873,6 +936,78 @@ impl SandboxSafeSystemState {
         burned_cycles
     }
 
+    /// Returns the ID of the subnet on which the canister is running.
+    pub(super) fn subnet_id(&self) -> SubnetId {
+        self.cycles_account_manager.get_subnet_id()
+    }
+
+    /// Returns the DER-encoded public key of the IC root of trust.
+    pub(super) fn root_key(&self) -> &[u8] {
+        &self.root_key
+    }
+
+    /// Returns the environment variables of the canister, ordered by name.
+    pub(super) fn environment_variables(&self) -> &BTreeMap<String, String> {
+        &self.environment_variables
+    }
+
+    /// Returns true if the canister has profiling enabled.
+    pub fn canister_profiling(&self) -> bool {
+        self.canister_profiling
+    }
+
+    /// Records a completed call of the innermost function of `call_stack` in
+    /// the system state changes. See [`CanisterProfile::record_call`].
+    pub(super) fn record_profiled_call(
+        &mut self,
+        call_stack: &[u32],
+        inclusive_instructions: u64,
+        exclusive_instructions: u64,
+        recursive: bool,
+    ) {
+        self.system_state_changes.canister_profile.record_call(
+            call_stack,
+            inclusive_instructions,
+            exclusive_instructions,
+            recursive,
+        );
+    }
+
+    /// Returns the cycles charged for performing a call with the given sizes
+    /// of the method name and payload (excluding the cycles attached to the call).
+    pub(super) fn cost_call(&self, method_name_size: u64, payload_size: u64) -> Cycles {
+        self.cycles_account_manager
+            .xnet_call_performed_fee(self.subnet_size)
+            + self.cycles_account_manager.xnet_call_bytes_transmitted_fee(
+                NumBytes::from(method_name_size.saturating_add(payload_size)),
+                self.subnet_size,
+            )
+            + self.prepayment_for_response_transmission()
+            + self.prepayment_for_response_execution()
+    }
+
+    /// Returns the fee for creating a canister on this subnet.
+    pub(super) fn cost_create_canister(&self) -> Cycles {
+        self.cycles_account_manager
+            .canister_creation_fee(self.subnet_size)
+    }
+
+    /// Returns the fee for an HTTPS outcall with the given request size
+    /// and maximum response size.
+    pub(super) fn cost_http_request(&self, request_size: u64, max_res_bytes: u64) -> Cycles {
+        self.cycles_account_manager.http_request_fee(
+            NumBytes::from(request_size),
+            Some(NumBytes::from(max_res_bytes)),
+            self.subnet_size,
+        )
+    }
+
+    /// Returns the fee for signing with the given threshold key
+    /// or `None` if no subnet is enabled to sign with the key.
+    pub(super) fn cost_sign_with(&self, key_id: &MasterPublicKeyId) -> Option<Cycles> {
+        self.threshold_signature_fees.get(key_id).copied()
+    }
+
     pub(super) fn refund_cycles(&mut self, cycles: Cycles) {
         let mut new_balance = self.cycles_balance();
         new_balance += cycles;
@@ -1383,11 +1518,15 @@ mod tests {
             BTreeMap::new(),
             0,
             BTreeSet::new(),
+            BTreeMap::new(),
+            vec![],
             SMALL_APP_SUBNET_MAX_SIZE,
             SchedulerConfig::application_subnet().dirty_page_overhead,
             CanisterTimer::Inactive,
             0,
             BTreeSet::new(),
+            BTreeMap::new(),
+            false,
             RequestMetadata::new(0, Time::from_nanos_since_unix_epoch(0)),
             None,
             0,
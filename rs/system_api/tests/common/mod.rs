@@ -179,12 +179,28 @@ pub fn get_system_api(
     api_type: ApiType,
     system_state: &SystemState,
     cycles_account_manager: CyclesAccountManager,
+) -> SystemApiImpl {
+    get_system_api_with_network_topology(
+        api_type,
+        system_state,
+        cycles_account_manager,
+        &NetworkTopology::default(),
+    )
+}
+
+// Not used in all test crates
+#[allow(dead_code)]
+pub fn get_system_api_with_network_topology(
+    api_type: ApiType,
+    system_state: &SystemState,
+    cycles_account_manager: CyclesAccountManager,
+    network_topology: &NetworkTopology,
 ) -> SystemApiImpl {
     let execution_mode = api_type.execution_mode();
     let sandbox_safe_system_state = SandboxSafeSystemState::new(
         system_state,
         cycles_account_manager,
-        &NetworkTopology::default(),
+        network_topology,
         SchedulerConfig::application_subnet().dirty_page_overhead,
         execution_parameters(execution_mode.clone()).compute_allocation,
         RequestMetadata::new(0, UNIX_EPOCH),
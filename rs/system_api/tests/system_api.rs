@@ -12,7 +12,8 @@ use ic_interfaces::execution_environment::{
 use ic_logger::replica_logger::no_op_logger;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    testing::CanisterQueuesTesting, CallOrigin, Memory, NetworkTopology, SystemState,
+    testing::CanisterQueuesTesting, CallOrigin, Memory, NetworkTopology, SubnetTopology,
+    SystemState,
 };
 use ic_system_api::{
     sandbox_safe_system_state::SandboxSafeSystemState, ApiType, DefaultOutOfInstructionsHandler,
@@ -29,7 +30,7 @@ use ic_types::{
     methods::{Callback, WasmClosure},
     time,
     time::UNIX_EPOCH,
-    CanisterTimer, CountBytes, Cycles, NumInstructions, PrincipalId, Time,
+    CanisterTimer, CountBytes, Cycles, NumBytes, NumInstructions, PrincipalId, Time,
     MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE,
 };
 use maplit::btreemap;
@@ -290,7 +291,22 @@ fn is_supported(api_type: SystemApiCallId, context: &str) -> bool {
         SystemApiCallId::InReplicatedExecution => vec!["*", "s"],
         SystemApiCallId::DebugPrint => vec!["*", "s"],
         SystemApiCallId::Trap => vec!["*", "s"],
-        SystemApiCallId::MintCycles => vec!["U", "Ry", "Rt", "T"]
+        SystemApiCallId::MintCycles => vec!["U", "Ry", "Rt", "T"],
+        SystemApiCallId::CostCall => vec!["*"],
+        SystemApiCallId::CostCreateCanister => vec!["*"],
+        SystemApiCallId::CostHttpRequest => vec!["*"],
+        SystemApiCallId::CostSignWithEcdsa => vec!["*"],
+        SystemApiCallId::CostSignWithSchnorr => vec!["*"],
+        SystemApiCallId::SubnetSelfSize => vec!["*"],
+        SystemApiCallId::SubnetSelfCopy => vec!["*"],
+        SystemApiCallId::RootKeySize => vec!["*"],
+        SystemApiCallId::RootKeyCopy => vec!["*"],
+        SystemApiCallId::EnvVarCount => vec!["*"],
+        SystemApiCallId::EnvVarNameSize => vec!["*"],
+        SystemApiCallId::EnvVarNameCopy => vec!["*"],
+        SystemApiCallId::EnvVarNameExists => vec!["*"],
+        SystemApiCallId::EnvVarValueSize => vec!["*"],
+        SystemApiCallId::EnvVarValueCopy => vec!["*"]
     };
     // the semantics of "*" is to cover all modes except for "s"
     matrix.get(&api_type).unwrap().contains(&context)
@@ -303,7 +319,10 @@ fn api_availability_test(
     api_type_enum: SystemApiCallId,
     context: &str,
 ) {
-    let system_state = get_system_state();
+    let mut system_state = get_system_state();
+    // The environment variable is named `*` so that the `ic0.env_var_*` calls
+    // below find it when reading the name from a heap filled with `42`s.
+    system_state.environment_variables = btreemap! {"*".to_string() => "value".to_string()};
     match api_type_enum {
         SystemApiCallId::MsgCallerSize => {
             assert_api_availability(
@@ -772,6 +791,156 @@ fn api_availability_test(
                 context,
             );
         }
+        SystemApiCallId::CostCall => {
+            assert_api_availability(
+                |api| api.ic0_cost_call(0, 0, 0, &mut [42; 128]),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::CostCreateCanister => {
+            assert_api_availability(
+                |api| api.ic0_cost_create_canister(0, &mut [42; 128]),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::CostHttpRequest => {
+            assert_api_availability(
+                |api| api.ic0_cost_http_request(0, 0, 0, &mut [42; 128]),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::CostSignWithEcdsa => {
+            assert_api_availability(
+                |api| api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut [42; 128]),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::CostSignWithSchnorr => {
+            assert_api_availability(
+                |api| api.ic0_cost_sign_with_schnorr(0, 0, 0, 0, &mut [42; 128]),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::SubnetSelfSize => {
+            assert_api_availability(
+                |api| api.ic0_subnet_self_size(),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::SubnetSelfCopy => {
+            assert_api_availability(
+                |api| api.ic0_subnet_self_copy(0, 0, 0, &mut [42; 128]),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::RootKeySize => {
+            assert_api_availability(
+                |api| api.ic0_root_key_size(),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::RootKeyCopy => {
+            assert_api_availability(
+                |api| api.ic0_root_key_copy(0, 0, 0, &mut [42; 128]),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::EnvVarCount => {
+            assert_api_availability(
+                |api| api.ic0_env_var_count(),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::EnvVarNameSize => {
+            assert_api_availability(
+                |api| api.ic0_env_var_name_size(0),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::EnvVarNameCopy => {
+            assert_api_availability(
+                |api| api.ic0_env_var_name_copy(0, 0, 0, 0, &mut [42; 128]),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::EnvVarNameExists => {
+            assert_api_availability(
+                |api| api.ic0_env_var_name_exists(0, 1, &[42; 128]),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::EnvVarValueSize => {
+            assert_api_availability(
+                |api| api.ic0_env_var_value_size(0, 1, &[42; 128]),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::EnvVarValueCopy => {
+            assert_api_availability(
+                |api| api.ic0_env_var_value_copy(0, 1, 0, 0, 0, &mut [42; 128]),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
         // stable API is tested separately
         SystemApiCallId::StableGrow
         | SystemApiCallId::StableRead
@@ -1699,6 +1868,156 @@ fn test_ic0_cycles_burn() {
     assert_eq!(Cycles::new(0), Cycles::from(&heap));
 }
 
+#[test]
+fn test_ic0_cost_apis() {
+    let system_state = SystemStateBuilder::default().build();
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let api = get_system_api(
+        ApiTypeBuilder::build_update_api(),
+        &system_state,
+        cycles_account_manager,
+    );
+
+    let mut heap = vec![0; 16];
+    api.ic0_cost_create_canister(0, &mut heap).unwrap();
+    assert_eq!(
+        cycles_account_manager.canister_creation_fee(SMALL_APP_SUBNET_MAX_SIZE),
+        Cycles::from(&heap)
+    );
+
+    let mut heap = vec![0; 16];
+    api.ic0_cost_http_request(100, 2_000, 0, &mut heap).unwrap();
+    assert_eq!(
+        cycles_account_manager.http_request_fee(
+            NumBytes::from(100),
+            Some(NumBytes::from(2_000)),
+            SMALL_APP_SUBNET_MAX_SIZE
+        ),
+        Cycles::from(&heap)
+    );
+
+    let mut heap = vec![0; 16];
+    api.ic0_cost_call(3, 10, 0, &mut heap).unwrap();
+    assert_eq!(
+        cycles_account_manager.xnet_call_performed_fee(SMALL_APP_SUBNET_MAX_SIZE)
+            + cycles_account_manager
+                .xnet_call_bytes_transmitted_fee(NumBytes::from(13), SMALL_APP_SUBNET_MAX_SIZE)
+            + cycles_account_manager
+                .prepayment_for_response_transmission(SMALL_APP_SUBNET_MAX_SIZE)
+            + cycles_account_manager.prepayment_for_response_execution(SMALL_APP_SUBNET_MAX_SIZE),
+        Cycles::from(&heap)
+    );
+
+    // No subnet is enabled to sign with any key.
+    let mut heap = b"test_key".to_vec();
+    heap.resize(32, 0);
+    assert_eq!(api.ic0_cost_sign_with_ecdsa(0, 8, 0, 16, &mut heap), Ok(2));
+    assert_eq!(
+        api.ic0_cost_sign_with_schnorr(0, 8, 1, 16, &mut heap),
+        Ok(2)
+    );
+    // Unknown curve and algorithm.
+    assert_eq!(api.ic0_cost_sign_with_ecdsa(0, 8, 1, 16, &mut heap), Ok(1));
+    assert_eq!(
+        api.ic0_cost_sign_with_schnorr(0, 8, 2, 16, &mut heap),
+        Ok(1)
+    );
+}
+
+#[test]
+fn test_ic0_subnet_self() {
+    let subnet_id = subnet_test_id(42);
+    let api = get_system_api(
+        ApiTypeBuilder::build_update_api(),
+        &SystemStateBuilder::default().build(),
+        CyclesAccountManagerBuilder::new()
+            .with_subnet_id(subnet_id)
+            .build(),
+    );
+
+    let size = api.ic0_subnet_self_size().unwrap();
+    assert_eq!(size, subnet_id.get_ref().as_slice().len());
+    let mut heap = vec![0; size];
+    api.ic0_subnet_self_copy(0, 0, size, &mut heap).unwrap();
+    assert_eq!(heap, subnet_id.get_ref().as_slice());
+
+    // Copying beyond the end of the subnet id fails.
+    assert!(api
+        .ic0_subnet_self_copy(0, 1, size, &mut vec![0; size + 1])
+        .is_err());
+}
+
+#[test]
+fn test_ic0_root_key() {
+    let nns_subnet_id = subnet_test_id(1);
+    let root_key = vec![7; 133];
+    let network_topology = NetworkTopology {
+        nns_subnet_id,
+        subnets: btreemap! {
+            nns_subnet_id => SubnetTopology {
+                public_key: root_key.clone(),
+                ..SubnetTopology::default()
+            },
+        },
+        ..NetworkTopology::default()
+    };
+    let api = get_system_api_with_network_topology(
+        ApiTypeBuilder::build_update_api(),
+        &SystemStateBuilder::default().build(),
+        CyclesAccountManagerBuilder::new().build(),
+        &network_topology,
+    );
+
+    let size = api.ic0_root_key_size().unwrap();
+    assert_eq!(size, root_key.len());
+    let mut heap = vec![0; size];
+    api.ic0_root_key_copy(0, 0, size, &mut heap).unwrap();
+    assert_eq!(heap, root_key);
+}
+
+#[test]
+fn test_ic0_env_vars() {
+    let system_state = SystemStateBuilder::default()
+        .environment_variables(btreemap! {
+            "NETWORK".to_string() => "staging".to_string(),
+            "LOG_LEVEL".to_string() => "debug".to_string(),
+        })
+        .build();
+    let api = get_system_api(
+        ApiTypeBuilder::build_update_api(),
+        &system_state,
+        CyclesAccountManagerBuilder::new().build(),
+    );
+
+    assert_eq!(api.ic0_env_var_count().unwrap(), 2);
+
+    // Variables are ordered by name.
+    let mut heap = vec![0; 32];
+    let size = api.ic0_env_var_name_size(0).unwrap();
+    api.ic0_env_var_name_copy(0, 0, 0, size, &mut heap).unwrap();
+    assert_eq!(&heap[..size], b"LOG_LEVEL");
+    let size = api.ic0_env_var_name_size(1).unwrap();
+    api.ic0_env_var_name_copy(1, 0, 0, size, &mut heap).unwrap();
+    assert_eq!(&heap[..size], b"NETWORK");
+    assert!(api.ic0_env_var_name_size(2).is_err());
+
+    // Look up the value of `NETWORK`, whose name is now at the start of the heap.
+    let name_size = b"NETWORK".len();
+    assert_eq!(api.ic0_env_var_name_exists(0, name_size, &heap).unwrap(), 1);
+    assert_eq!(api.ic0_env_var_name_exists(0, 3, &heap).unwrap(), 0);
+    let size = api.ic0_env_var_value_size(0, name_size, &heap).unwrap();
+    assert_eq!(size, b"staging".len());
+    api.ic0_env_var_value_copy(0, name_size, 16, 0, size, &mut heap)
+        .unwrap();
+    assert_eq!(&heap[16..16 + size], b"staging");
+
+    // Looking up the value of a missing variable traps.
+    assert!(api.ic0_env_var_value_size(0, 3, &heap).is_err());
+    assert!(api
+        .ic0_env_var_value_copy(0, 3, 16, 0, 1, &mut heap)
+        .is_err());
+}
+
 #[test]
 fn test_save_log_message_adds_canister_log_records() {
     let messages: Vec<Vec<_>> = vec![
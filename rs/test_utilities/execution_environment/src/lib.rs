@@ -2089,6 +2089,7 @@ impl ExecutionTestBuilder {
                 ChainKeySettings {
                     max_queue_size: 20,
                     pre_signatures_to_create_in_advance: 5,
+                    max_pre_signatures: 5,
                 },
             );
 
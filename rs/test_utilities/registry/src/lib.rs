@@ -2,7 +2,8 @@ use ic_crypto_test_utils_ni_dkg::dummy_transcript_for_tests_with_params;
 use ic_protobuf::registry::crypto::v1::AlgorithmId;
 use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
 use ic_protobuf::registry::subnet::v1::{
-    CatchUpPackageContents, InitialNiDkgTranscriptRecord, SubnetListRecord, SubnetRecord,
+    CatchUpPackageContents, IdleBlockConfig, InitialNiDkgTranscriptRecord, SubnetListRecord,
+    SubnetRecord,
 };
 use ic_registry_client_fake::FakeRegistryClient;
 use ic_registry_keys::{
@@ -214,6 +215,7 @@ pub fn test_subnet_record() -> SubnetRecord {
         ssh_backup_access: vec![],
         ecdsa_config: None,
         chain_key_config: None,
+        idle_block_config: None,
     }
 }
 
@@ -304,6 +306,11 @@ impl SubnetRecordBuilder {
         self
     }
 
+    pub fn with_idle_block_config(mut self, idle_block_config: IdleBlockConfig) -> Self {
+        self.record.idle_block_config = Some(idle_block_config);
+        self
+    }
+
     pub fn with_membership(mut self, node_ids: &[NodeId]) -> Self {
         self.record.membership = node_ids
             .iter()
@@ -487,6 +487,14 @@ impl SystemStateBuilder {
         self
     }
 
+    pub fn environment_variables(
+        mut self,
+        environment_variables: BTreeMap<String, String>,
+    ) -> Self {
+        self.system_state.environment_variables = environment_variables;
+        self
+    }
+
     pub fn build(self) -> SystemState {
         self.system_state
     }
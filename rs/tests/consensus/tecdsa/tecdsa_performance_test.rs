@@ -141,6 +141,8 @@ pub fn setup(env: TestEnv) {
                         .into_iter()
                         .map(|key_id| KeyConfig {
                             max_queue_size: MAX_QUEUE_SIZE,
+                            min_pre_signatures_to_create: None,
+                            max_pre_signatures_to_create: None,
                             pre_signatures_to_create_in_advance: PRE_SIGNATURES_TO_CREATE,
                             key_id,
                         })
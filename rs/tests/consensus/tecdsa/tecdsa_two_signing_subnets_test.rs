@@ -58,6 +58,8 @@ fn setup(env: TestEnv) {
                 .with_chain_key_config(ChainKeyConfig {
                     key_configs: vec![KeyConfig {
                         max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+                        min_pre_signatures_to_create: None,
+                        max_pre_signatures_to_create: None,
                         pre_signatures_to_create_in_advance: 5,
                         key_id: MasterPublicKeyId::Ecdsa(make_key(KEY_ID1)),
                     }],
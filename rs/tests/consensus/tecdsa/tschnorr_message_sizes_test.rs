@@ -73,6 +73,8 @@ fn setup(env: TestEnv) {
                             key_id,
                             pre_signatures_to_create_in_advance: 5,
                             max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+                            min_pre_signatures_to_create: None,
+                            max_pre_signatures_to_create: None,
                         })
                         .collect(),
                     signature_request_timeout_ns: None,
@@ -194,6 +194,7 @@ pub fn empty_subnet_update() -> UpdateSubnetPayload {
         max_number_of_canisters: None,
         ssh_readonly_access: None,
         ssh_backup_access: None,
+        idle_block_config: None,
         // Deprecated/unused values follow
         max_artifact_streams_per_peer: None,
         max_chunk_wait_ms: None,
@@ -662,6 +663,8 @@ pub async fn add_chain_keys_with_timeout_and_rotation_period(
                     key_id: Some(key_id.clone()),
                     pre_signatures_to_create_in_advance: Some(5),
                     max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 })
                 .collect(),
             signature_request_timeout_ns: timeout.map(|t| t.as_nanos() as u64),
@@ -722,6 +725,8 @@ pub async fn create_new_subnet_with_keys(
                     key_id: Some(key_id),
                     pre_signatures_to_create_in_advance: Some(4),
                     max_queue_size: Some(DEFAULT_ECDSA_MAX_QUEUE_SIZE),
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                 }),
                 subnet_id: Some(subnet_id),
             })
@@ -753,6 +758,7 @@ pub async fn create_new_subnet_with_keys(
         max_number_of_canisters: 4,
         ssh_readonly_access: vec![],
         ssh_backup_access: vec![],
+        idle_block_config: None,
         chain_key_config: Some(chain_key_config),
         // Unused section follows
         ecdsa_config: None,
@@ -586,6 +586,7 @@ pub async fn submit_create_application_subnet_proposal(
         max_number_of_canisters: 4,
         ssh_readonly_access: vec![],
         ssh_backup_access: vec![],
+        idle_block_config: None,
         ecdsa_config: None,
         chain_key_config: None,
         // Unused section follows
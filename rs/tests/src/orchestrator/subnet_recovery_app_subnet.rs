@@ -105,6 +105,8 @@ pub fn setup(
         .map(|key_id| KeyConfig {
             key_id,
             max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+            min_pre_signatures_to_create: None,
+            max_pre_signatures_to_create: None,
             pre_signatures_to_create_in_advance: 3,
         })
         .collect();
@@ -78,6 +78,8 @@ pub fn config(env: TestEnv, subnet_type: SubnetType, mainnet_version: bool) {
                 .into_iter()
                 .map(|key_id| KeyConfig {
                     max_queue_size: DEFAULT_ECDSA_MAX_QUEUE_SIZE,
+                    min_pre_signatures_to_create: None,
+                    max_pre_signatures_to_create: None,
                     pre_signatures_to_create_in_advance: 5,
                     key_id,
                 })
@@ -33,7 +33,14 @@ pub use provisional::{ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpC
 use serde::Serialize;
 use serde_bytes::ByteBuf;
 use std::mem::size_of;
-use std::{collections::BTreeSet, convert::TryFrom, error::Error, fmt, slice::Iter, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    error::Error,
+    fmt,
+    slice::Iter,
+    str::FromStr,
+};
 use strum_macros::{Display, EnumIter, EnumString};
 
 /// The id of the management canister.
@@ -50,6 +57,10 @@ const WASM_HASH_LENGTH: usize = 32;
 /// for details
 const MAXIMUM_DERIVATION_PATH_LENGTH: usize = 255;
 
+/// Maximum number of messages that can be signed in a single batched
+/// threshold signing request.
+pub const MAXIMUM_SIGNATURE_BATCH_SIZE: usize = 32;
+
 /// Limit the amount of work for skipping unneeded data on the wire when parsing Candid.
 /// The value of 10_000 follows the Candid recommendation.
 const DEFAULT_SKIPPING_QUOTA: usize = 10_000;
@@ -77,6 +88,7 @@ pub enum Method {
     RawRand,
     SetupInitialDKG,
     SignWithECDSA,
+    SignWithECDSABatch,
     StartCanister,
     StopCanister,
     UninstallCode,
@@ -86,6 +98,7 @@ pub enum Method {
     // Schnorr interface.
     SchnorrPublicKey,
     SignWithSchnorr,
+    SignWithSchnorrBatch,
 
     // Bitcoin Interface.
     BitcoinGetBalance,
@@ -100,6 +113,7 @@ pub enum Method {
     NodeMetricsHistory,
 
     FetchCanisterLogs,
+    FetchCanisterProfile,
 
     // These methods are only available on test IC instances where there is a
     // need to fabricate cycles without burning ICP first.
@@ -116,6 +130,12 @@ pub enum Method {
     LoadCanisterSnapshot,
     ListCanisterSnapshots,
     DeleteCanisterSnapshot,
+
+    // Support for migrating canisters between subnets.
+    MigrateCanister,
+    // Private API used exclusively by subnets to stream the state of a
+    // migrating canister.
+    ImportCanisterState,
 }
 
 fn candid_error_to_user_error(err: candid::Error) -> UserError {
@@ -1015,6 +1035,8 @@ fn test_log_visibility_v2_forward_compatibility() {
 ///     reserved_cycles_limit: nat;
 ///     log_visibility: log_visibility;
 ///     wasm_memory_limit: nat;
+///     environment_variables: vec environment_variable;
+///     profiling: bool;
 /// })`
 #[derive(CandidType, Clone, Deserialize, Debug, Eq, PartialEq)]
 pub struct DefiniteCanisterSettingsArgs {
@@ -1026,9 +1048,12 @@ pub struct DefiniteCanisterSettingsArgs {
     reserved_cycles_limit: candid::Nat,
     log_visibility: LogVisibilityV2,
     wasm_memory_limit: candid::Nat,
+    environment_variables: Vec<EnvironmentVariable>,
+    profiling: bool,
 }
 
 impl DefiniteCanisterSettingsArgs {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         controller: PrincipalId,
         controllers: Vec<PrincipalId>,
@@ -1038,6 +1063,8 @@ impl DefiniteCanisterSettingsArgs {
         reserved_cycles_limit: Option<u128>,
         log_visibility: LogVisibilityV2,
         wasm_memory_limit: Option<u64>,
+        environment_variables: Vec<EnvironmentVariable>,
+        profiling: bool,
     ) -> Self {
         let memory_allocation = candid::Nat::from(memory_allocation.unwrap_or(0));
         let reserved_cycles_limit = candid::Nat::from(reserved_cycles_limit.unwrap_or(0));
@@ -1051,6 +1078,8 @@ impl DefiniteCanisterSettingsArgs {
             reserved_cycles_limit,
             log_visibility,
             wasm_memory_limit,
+            environment_variables,
+            profiling,
         }
     }
 
@@ -1065,6 +1094,14 @@ impl DefiniteCanisterSettingsArgs {
     pub fn log_visibility(&self) -> &LogVisibilityV2 {
         &self.log_visibility
     }
+
+    pub fn environment_variables(&self) -> &[EnvironmentVariable] {
+        &self.environment_variables
+    }
+
+    pub fn profiling(&self) -> bool {
+        self.profiling
+    }
 }
 
 impl Payload<'_> for DefiniteCanisterSettingsArgs {}
@@ -1188,6 +1225,8 @@ impl CanisterStatusResultV2 {
         query_ingress_payload_size: u128,
         query_egress_payload_size: u128,
         wasm_memory_limit: Option<u64>,
+        environment_variables: Vec<EnvironmentVariable>,
+        profiling: bool,
     ) -> Self {
         Self {
             status,
@@ -1207,6 +1246,8 @@ impl CanisterStatusResultV2 {
                 reserved_cycles_limit,
                 log_visibility,
                 wasm_memory_limit,
+                environment_variables,
+                profiling,
             ),
             freezing_threshold: candid::Nat::from(freezing_threshold),
             idle_cycles_burned_per_day: candid::Nat::from(idle_cycles_burned_per_day),
@@ -1846,6 +1887,26 @@ impl DataSize for PrincipalId {
     }
 }
 
+/// Struct used for encoding/decoding
+/// `(record {
+///     name: text;
+///     value: text;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct EnvironmentVariable {
+    pub name: String,
+    pub value: String,
+}
+
+impl EnvironmentVariable {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
 /// Struct used for encoding/decoding
 /// `(record {
 ///     controllers: opt vec principal;
@@ -1856,6 +1917,8 @@ impl DataSize for PrincipalId {
 ///     log_visibility : opt log_visibility;
 ///     wasm_memory_limit: opt nat;
 ///     wasm_memory_threshold: opt nat;
+///     environment_variables: opt vec environment_variable;
+///     profiling: opt bool;
 /// })`
 #[derive(Default, Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
 pub struct CanisterSettingsArgs {
@@ -1867,6 +1930,8 @@ pub struct CanisterSettingsArgs {
     pub log_visibility: Option<LogVisibilityV2>,
     pub wasm_memory_limit: Option<candid::Nat>,
     pub wasm_memory_threshold: Option<candid::Nat>,
+    pub environment_variables: Option<Vec<EnvironmentVariable>>,
+    pub profiling: Option<bool>,
 }
 
 impl Payload<'_> for CanisterSettingsArgs {}
@@ -1884,6 +1949,8 @@ impl CanisterSettingsArgs {
             log_visibility: None,
             wasm_memory_limit: None,
             wasm_memory_threshold: None,
+            environment_variables: None,
+            profiling: None,
         }
     }
 }
@@ -1898,6 +1965,8 @@ pub struct CanisterSettingsArgsBuilder {
     log_visibility: Option<LogVisibilityV2>,
     wasm_memory_limit: Option<candid::Nat>,
     wasm_memory_threshold: Option<candid::Nat>,
+    environment_variables: Option<Vec<EnvironmentVariable>>,
+    profiling: Option<bool>,
 }
 
 #[allow(dead_code)]
@@ -1916,6 +1985,8 @@ impl CanisterSettingsArgsBuilder {
             log_visibility: self.log_visibility,
             wasm_memory_limit: self.wasm_memory_limit,
             wasm_memory_threshold: self.wasm_memory_threshold,
+            environment_variables: self.environment_variables,
+            profiling: self.profiling,
         }
     }
 
@@ -2000,6 +2071,25 @@ impl CanisterSettingsArgsBuilder {
             ..self
         }
     }
+
+    /// Sets the environment variables, replacing any existing ones.
+    pub fn with_environment_variables(
+        self,
+        environment_variables: Vec<EnvironmentVariable>,
+    ) -> Self {
+        Self {
+            environment_variables: Some(environment_variables),
+            ..self
+        }
+    }
+
+    /// Enables or disables function-level execution profiling.
+    pub fn with_profiling(self, profiling: bool) -> Self {
+        Self {
+            profiling: Some(profiling),
+            ..self
+        }
+    }
 }
 
 /// Struct used for encoding/decoding
@@ -2512,6 +2602,51 @@ pub struct SignWithECDSAReply {
 
 impl Payload<'_> for SignWithECDSAReply {}
 
+/// A single message to be signed as part of a `sign_with_ecdsa_batch` call.
+/// ```text
+/// (record {
+///   message_hash : blob;
+///   derivation_path : vec blob;
+/// })
+/// ```
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct SignWithECDSABatchEntry {
+    pub message_hash: [u8; 32],
+    pub derivation_path: DerivationPath,
+}
+
+impl DataSize for SignWithECDSABatchEntry {}
+
+pub type SignWithECDSABatchEntries =
+    BoundedVec<MAXIMUM_SIGNATURE_BATCH_SIZE, UNBOUNDED, UNBOUNDED, SignWithECDSABatchEntry>;
+
+/// Represents the argument of the sign_with_ecdsa_batch API.
+/// ```text
+/// (record {
+///   messages : vec record {
+///     message_hash : blob;
+///     derivation_path : vec blob;
+///   };
+///   key_id : ecdsa_key_id;
+/// })
+/// ```
+#[derive(CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct SignWithECDSABatchArgs {
+    pub messages: SignWithECDSABatchEntries,
+    pub key_id: EcdsaKeyId,
+}
+
+impl Payload<'_> for SignWithECDSABatchArgs {}
+
+/// Struct used to return the ECDSA signatures of a batch, in the order of the
+/// messages in the request.
+#[derive(CandidType, Deserialize, Debug)]
+pub struct SignWithECDSABatchReply {
+    pub signatures: Vec<SignWithECDSAReply>,
+}
+
+impl Payload<'_> for SignWithECDSABatchReply {}
+
 /// Represents the argument of the ecdsa_public_key API.
 /// ```text
 /// (record {
@@ -2631,6 +2766,52 @@ pub struct SignWithSchnorrReply {
 
 impl Payload<'_> for SignWithSchnorrReply {}
 
+/// A single message to be signed as part of a `sign_with_schnorr_batch` call.
+/// ```text
+/// (record {
+///   message : blob;
+///   derivation_path : vec blob;
+/// })
+/// ```
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct SignWithSchnorrBatchEntry {
+    #[serde(with = "serde_bytes")]
+    pub message: Vec<u8>,
+    pub derivation_path: DerivationPath,
+}
+
+impl DataSize for SignWithSchnorrBatchEntry {}
+
+pub type SignWithSchnorrBatchEntries =
+    BoundedVec<MAXIMUM_SIGNATURE_BATCH_SIZE, UNBOUNDED, UNBOUNDED, SignWithSchnorrBatchEntry>;
+
+/// Represents the argument of the sign_with_schnorr_batch API.
+/// ```text
+/// (record {
+///   messages : vec record {
+///     message : blob;
+///     derivation_path : vec blob;
+///   };
+///   key_id : schnorr_key_id;
+/// })
+/// ```
+#[derive(CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct SignWithSchnorrBatchArgs {
+    pub messages: SignWithSchnorrBatchEntries,
+    pub key_id: SchnorrKeyId,
+}
+
+impl Payload<'_> for SignWithSchnorrBatchArgs {}
+
+/// Struct used to return the Schnorr signatures of a batch, in the order of
+/// the messages in the request.
+#[derive(CandidType, Deserialize, Debug)]
+pub struct SignWithSchnorrBatchReply {
+    pub signatures: Vec<SignWithSchnorrReply>,
+}
+
+impl Payload<'_> for SignWithSchnorrBatchReply {}
+
 /// Represents the argument of the schnorr_public_key API.
 /// ```text
 /// (record {
@@ -2727,6 +2908,7 @@ impl Payload<'_> for BitcoinSendTransactionInternalArgs {}
 #[strum(serialize_all = "snake_case")]
 pub enum QueryMethod {
     FetchCanisterLogs,
+    FetchCanisterProfile,
 }
 
 /// `CandidType` for `NodeMetricsHistoryArgs`
@@ -2868,6 +3050,147 @@ pub struct FetchCanisterLogsResponse {
 
 impl Payload<'_> for FetchCanisterLogsResponse {}
 
+/// `CandidType` for `FetchCanisterProfileRequest`
+/// ```text
+/// record {
+///     canister_id: principal;
+/// }
+/// ```
+#[derive(Default, Clone, CandidType, Deserialize, Debug)]
+pub struct FetchCanisterProfileRequest {
+    pub canister_id: PrincipalId,
+}
+
+impl Payload<'_> for FetchCanisterProfileRequest {}
+
+impl FetchCanisterProfileRequest {
+    pub fn new(canister_id: CanisterId) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        CanisterId::unchecked_from_principal(self.canister_id)
+    }
+}
+
+/// `CandidType` for `FunctionProfileRecord`
+/// ```text
+/// record {
+///     function_index: nat32;
+///     function_name: opt text;
+///     calls: nat64;
+///     inclusive_instructions: nat64;
+///     exclusive_instructions: nat64;
+/// }
+/// ```
+#[derive(Default, Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct FunctionProfileRecord {
+    pub function_index: u32,
+    pub function_name: Option<String>,
+    pub calls: u64,
+    pub inclusive_instructions: u64,
+    pub exclusive_instructions: u64,
+}
+
+/// `CandidType` for `CallStackProfileRecord`
+/// ```text
+/// record {
+///     function_indices: vec nat32;
+///     instructions: nat64;
+/// }
+/// ```
+#[derive(Default, Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct CallStackProfileRecord {
+    /// The functions of the call stack, outermost first.
+    pub function_indices: Vec<u32>,
+    /// Instructions executed by the innermost function of the call stack.
+    pub instructions: u64,
+}
+
+/// `CandidType` for `FetchCanisterProfileResponse`
+/// ```text
+/// record {
+///     functions: vec function_profile_record;
+///     call_stacks: vec call_stack_profile_record;
+/// }
+/// ```
+#[derive(Default, Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct FetchCanisterProfileResponse {
+    pub functions: Vec<FunctionProfileRecord>,
+    pub call_stacks: Vec<CallStackProfileRecord>,
+}
+
+impl Payload<'_> for FetchCanisterProfileResponse {}
+
+impl FetchCanisterProfileResponse {
+    /// Returns the call stacks in the "folded stacks" format understood by
+    /// flamegraph tools, i.e. one `outer;...;inner instructions` line per call
+    /// stack. Functions without a name are written as `func[<index>]`.
+    pub fn to_folded_stacks(&self) -> String {
+        let names: BTreeMap<u32, &str> = self
+            .functions
+            .iter()
+            .filter_map(|function| {
+                function
+                    .function_name
+                    .as_deref()
+                    .map(|name| (function.function_index, name))
+            })
+            .collect();
+        let mut folded = String::new();
+        for call_stack in &self.call_stacks {
+            let frames: Vec<String> = call_stack
+                .function_indices
+                .iter()
+                .map(|index| match names.get(index) {
+                    Some(name) => name.replace([';', ' '], "_"),
+                    None => format!("func[{}]", index),
+                })
+                .collect();
+            folded.push_str(&format!(
+                "{} {}\n",
+                frames.join(";"),
+                call_stack.instructions
+            ));
+        }
+        folded
+    }
+}
+
+#[test]
+fn test_fetch_canister_profile_response_to_folded_stacks() {
+    let response = FetchCanisterProfileResponse {
+        functions: vec![
+            FunctionProfileRecord {
+                function_index: 1,
+                function_name: Some("canister_update go".to_string()),
+                ..Default::default()
+            },
+            FunctionProfileRecord {
+                function_index: 2,
+                function_name: None,
+                ..Default::default()
+            },
+        ],
+        call_stacks: vec![
+            CallStackProfileRecord {
+                function_indices: vec![1],
+                instructions: 10,
+            },
+            CallStackProfileRecord {
+                function_indices: vec![1, 2],
+                instructions: 20,
+            },
+        ],
+    };
+    assert_eq!(
+        response.to_folded_stacks(),
+        "canister_update_go 10\ncanister_update_go;func[2] 20\n"
+    );
+}
+
 /// Struct used for encoding/decoding
 /// `(record {
 ///     canister_id: principal;
@@ -3316,6 +3639,247 @@ impl ListCanisterSnapshotArgs {
 
 impl Payload<'_> for ListCanisterSnapshotArgs {}
 
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id: principal;
+///     target_subnet_id: principal;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct MigrateCanisterArgs {
+    canister_id: PrincipalId,
+    target_subnet_id: PrincipalId,
+}
+
+impl MigrateCanisterArgs {
+    pub fn new(canister_id: CanisterId, target_subnet_id: SubnetId) -> Self {
+        Self {
+            canister_id: canister_id.get(),
+            target_subnet_id: target_subnet_id.get(),
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        CanisterId::unchecked_from_principal(self.canister_id)
+    }
+
+    pub fn get_target_subnet_id(&self) -> SubnetId {
+        SubnetId::from(self.target_subnet_id)
+    }
+}
+
+impl Payload<'_> for MigrateCanisterArgs {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id: principal;
+///     step: canister_migration_step;
+/// })`
+///
+/// Sent by the management canister of the subnet a canister is migrated off
+/// of to the management canister of the subnet it is migrated to.
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq)]
+pub struct ImportCanisterStateArgs {
+    pub canister_id: PrincipalId,
+    pub step: CanisterMigrationStep,
+}
+
+impl ImportCanisterStateArgs {
+    pub fn get_canister_id(&self) -> CanisterId {
+        CanisterId::unchecked_from_principal(self.canister_id)
+    }
+}
+
+impl Payload<'_> for ImportCanisterStateArgs {}
+
+/// A single step of streaming the state of a migrating canister to its new
+/// subnet. Steps are applied in order: the canister's snapshots are imported
+/// one by one, followed by its current state and finally its cycles.
+///
+/// `CandidType` for `CanisterMigrationStep`
+/// ```text
+/// variant {
+///   metadata : canister_migration_metadata;
+///   module_chunk : blob;
+///   module : migrated_module;
+///   wasm_memory_pages : vec migrated_page;
+///   stable_memory_pages : vec migrated_page;
+///   chunk_store_chunk : blob;
+///   snapshot : migrated_snapshot;
+///   execution_state : migrated_execution_state;
+///   cycles : migrated_cycles;
+/// }
+/// ```
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq)]
+pub enum CanisterMigrationStep {
+    /// Creates the canister. Discards anything left over from an earlier,
+    /// unfinished import of the same canister.
+    #[serde(rename = "metadata")]
+    Metadata(CanisterMigrationMetadata),
+    /// Stages a piece of a Wasm module in the canister's chunk store.
+    #[serde(rename = "module_chunk")]
+    ModuleChunk(ByteBuf),
+    /// Instantiates the Wasm module assembled from the staged pieces and
+    /// clears the chunk store.
+    #[serde(rename = "module")]
+    Module(MigratedModule),
+    #[serde(rename = "wasm_memory_pages")]
+    WasmMemoryPages(Vec<MigratedPage>),
+    #[serde(rename = "stable_memory_pages")]
+    StableMemoryPages(Vec<MigratedPage>),
+    #[serde(rename = "chunk_store_chunk")]
+    ChunkStoreChunk(ByteBuf),
+    /// Turns what was imported since the last `Module` step into a snapshot
+    /// and leaves the canister empty.
+    #[serde(rename = "snapshot")]
+    Snapshot(MigratedSnapshot),
+    /// Completes the import of the current state of the canister.
+    #[serde(rename = "execution_state")]
+    ExecutionState(MigratedExecutionState),
+    /// Sets the cycles balances of the canister. May be repeated if the
+    /// balances on the source subnet changed while the step was in flight.
+    #[serde(rename = "cycles")]
+    Cycles(MigratedCycles),
+}
+
+/// `CandidType` for `CanisterMigrationMetadata`
+/// ```text
+/// record {
+///   controllers : vec principal;
+///   compute_allocation : nat64;
+///   memory_allocation : nat64;
+///   freezing_threshold : nat64;
+///   reserved_cycles_limit : opt nat;
+///   wasm_memory_limit : opt nat64;
+///   wasm_memory_threshold : nat64;
+///   log_visibility : log_visibility;
+///   environment_variables : vec environment_variable;
+///   global_timer : opt nat64;
+///   canister_version : nat64;
+///   next_snapshot_id : nat64;
+///   total_num_changes : nat64;
+///   changes : vec change;
+/// }
+/// ```
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterMigrationMetadata {
+    pub controllers: Vec<PrincipalId>,
+    pub compute_allocation: u64,
+    pub memory_allocation: u64,
+    pub freezing_threshold: u64,
+    pub reserved_cycles_limit: Option<u128>,
+    pub wasm_memory_limit: Option<u64>,
+    pub wasm_memory_threshold: u64,
+    pub log_visibility: LogVisibilityV2,
+    pub environment_variables: Vec<EnvironmentVariable>,
+    pub global_timer: Option<u64>,
+    pub canister_version: u64,
+    pub next_snapshot_id: u64,
+    pub total_num_changes: u64,
+    pub changes: Vec<CanisterChange>,
+}
+
+/// `CandidType` for `MigratedModule`
+/// ```text
+/// record {
+///   chunk_hashes : vec blob;
+///   wasm_memory_size : nat64;
+///   stable_memory_size : nat64;
+/// }
+/// ```
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct MigratedModule {
+    /// Hashes of the staged pieces of the module, in order.
+    pub chunk_hashes: Vec<ByteBuf>,
+    /// Size of the Wasm memory in Wasm pages.
+    pub wasm_memory_size: u64,
+    /// Size of the stable memory in Wasm pages.
+    pub stable_memory_size: u64,
+}
+
+/// `CandidType` for `MigratedPage`
+/// ```text
+/// record {
+///   index : nat64;
+///   bytes : blob;
+/// }
+/// ```
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct MigratedPage {
+    pub index: u64,
+    pub bytes: ByteBuf,
+}
+
+/// `CandidType` for `MigratedSnapshot`
+/// ```text
+/// record {
+///   snapshot_id : blob;
+///   taken_at_timestamp : nat64;
+///   canister_version : nat64;
+///   certified_data : blob;
+///   size : nat64;
+/// }
+/// ```
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct MigratedSnapshot {
+    pub snapshot_id: ByteBuf,
+    pub taken_at_timestamp: u64,
+    pub canister_version: u64,
+    pub certified_data: ByteBuf,
+    pub size: u64,
+}
+
+/// `CandidType` for `MigratedExecutionState`
+/// ```text
+/// record {
+///   certified_data : blob;
+///   exported_globals : opt vec migrated_global;
+/// }
+/// ```
+///
+/// `exported_globals` is only set if the canister has a Wasm module.
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq)]
+pub struct MigratedExecutionState {
+    pub certified_data: ByteBuf,
+    pub exported_globals: Option<Vec<MigratedGlobal>>,
+}
+
+/// `CandidType` for `MigratedGlobal`
+/// ```text
+/// variant {
+///   i32 : int32;
+///   i64 : int64;
+///   f32 : float32;
+///   f64 : float64;
+///   v128 : nat;
+/// }
+/// ```
+#[derive(Clone, Copy, CandidType, Deserialize, Debug, PartialEq)]
+pub enum MigratedGlobal {
+    #[serde(rename = "i32")]
+    I32(i32),
+    #[serde(rename = "i64")]
+    I64(i64),
+    #[serde(rename = "f32")]
+    F32(f32),
+    #[serde(rename = "f64")]
+    F64(f64),
+    #[serde(rename = "v128")]
+    V128(u128),
+}
+
+/// `CandidType` for `MigratedCycles`
+/// ```text
+/// record {
+///   balance : nat;
+///   reserved_balance : nat;
+/// }
+/// ```
+#[derive(Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub struct MigratedCycles {
+    pub balance: u128,
+    pub reserved_balance: u128,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -3613,4 +4177,45 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn verify_max_signature_batch_size() {
+        let key_id = SchnorrKeyId {
+            algorithm: SchnorrAlgorithm::Ed25519,
+            name: "test".to_string(),
+        };
+        let entry = SignWithSchnorrBatchEntry {
+            message: vec![1; 32],
+            derivation_path: DerivationPath::new(vec![]),
+        };
+
+        for i in 0..=MAXIMUM_SIGNATURE_BATCH_SIZE {
+            let args = SignWithSchnorrBatchArgs {
+                messages: SignWithSchnorrBatchEntries::new(vec![entry.clone(); i]),
+                key_id: key_id.clone(),
+            };
+            assert_eq!(
+                SignWithSchnorrBatchArgs::decode(&args.encode()).unwrap(),
+                args
+            );
+        }
+
+        let args = SignWithSchnorrBatchArgs {
+            messages: SignWithSchnorrBatchEntries::new(vec![
+                entry;
+                MAXIMUM_SIGNATURE_BATCH_SIZE + 1
+            ]),
+            key_id,
+        };
+        let result = SignWithSchnorrBatchArgs::decode(&args.encode()).unwrap_err();
+        assert_eq!(result.code(), ErrorCode::InvalidManagementPayload);
+        assert!(
+            result.description().contains(&format!(
+                "Deserialize error: The number of elements exceeds maximum allowed {}",
+                MAXIMUM_SIGNATURE_BATCH_SIZE
+            )),
+            "Actual: {}",
+            result.description()
+        );
+    }
 }
@@ -0,0 +1,307 @@
+use ic_protobuf::state::canister_state_bits::v1 as pb;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, mem::size_of};
+
+/// The maximum number of distinct call stacks kept in a canister profile.
+/// Instructions of call stacks seen after reaching this limit are still
+/// accounted in the per-function totals.
+pub const MAX_PROFILED_CALL_STACKS: usize = 1_000;
+
+/// The maximum number of frames kept per call stack. Deeper call stacks are
+/// truncated to their outermost frames.
+pub const MAX_PROFILED_CALL_STACK_DEPTH: usize = 64;
+
+/// Instruction totals of a single Wasm function.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FunctionProfile {
+    /// The number of completed calls of the function.
+    pub calls: u64,
+    /// Instructions executed by the function and all its callees. Nested
+    /// recursive calls are only accounted once, by the outermost call.
+    pub inclusive_instructions: u64,
+    /// Instructions executed by the function itself, excluding its callees.
+    pub exclusive_instructions: u64,
+}
+
+impl FunctionProfile {
+    fn add(&mut self, other: &FunctionProfile) {
+        self.calls = self.calls.saturating_add(other.calls);
+        self.inclusive_instructions = self
+            .inclusive_instructions
+            .saturating_add(other.inclusive_instructions);
+        self.exclusive_instructions = self
+            .exclusive_instructions
+            .saturating_add(other.exclusive_instructions);
+    }
+}
+
+/// Per-function instruction totals and call stacks collected while executing
+/// a canister that has profiling enabled.
+///
+/// Functions are identified by their index in the function index space of the
+/// canister's Wasm module as installed (i.e. before instrumentation), so they
+/// can be resolved using the `name` custom section of the module.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CanisterProfile {
+    functions: BTreeMap<u32, FunctionProfile>,
+    // Maps a call stack (outermost frame first) to the number of instructions
+    // executed exclusively by its innermost frame.
+    call_stacks: BTreeMap<Vec<u32>, u64>,
+}
+
+impl CanisterProfile {
+    pub fn new(
+        functions: BTreeMap<u32, FunctionProfile>,
+        call_stacks: BTreeMap<Vec<u32>, u64>,
+    ) -> Self {
+        let mut profile = Self {
+            functions,
+            call_stacks: BTreeMap::new(),
+        };
+        for (call_stack, instructions) in call_stacks {
+            profile.add_call_stack(&call_stack, instructions);
+        }
+        profile
+    }
+
+    pub fn functions(&self) -> &BTreeMap<u32, FunctionProfile> {
+        &self.functions
+    }
+
+    pub fn call_stacks(&self) -> &BTreeMap<Vec<u32>, u64> {
+        &self.call_stacks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty() && self.call_stacks.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.functions.clear();
+        self.call_stacks.clear();
+    }
+
+    /// Returns the (estimated) number of bytes taken by the profile, which
+    /// counts towards the memory usage of the canister.
+    pub fn memory_usage(&self) -> usize {
+        let functions_size =
+            self.functions.len() * (size_of::<u32>() + size_of::<FunctionProfile>());
+        let call_stacks_size: usize = self
+            .call_stacks
+            .keys()
+            .map(|call_stack| call_stack.len() * size_of::<u32>() + size_of::<u64>())
+            .sum();
+        functions_size + call_stacks_size
+    }
+
+    /// Records a completed call of the innermost function of `call_stack`.
+    ///
+    /// `recursive` indicates that the function is also active further up the
+    /// call stack, in which case its inclusive instructions are already
+    /// accounted by the outer call.
+    pub fn record_call(
+        &mut self,
+        call_stack: &[u32],
+        inclusive_instructions: u64,
+        exclusive_instructions: u64,
+        recursive: bool,
+    ) {
+        let Some(function_index) = call_stack.last() else {
+            return;
+        };
+        self.functions
+            .entry(*function_index)
+            .or_default()
+            .add(&FunctionProfile {
+                calls: 1,
+                inclusive_instructions: if recursive { 0 } else { inclusive_instructions },
+                exclusive_instructions,
+            });
+        self.add_call_stack(call_stack, exclusive_instructions);
+    }
+
+    /// Merges `other` into this profile.
+    pub fn append(&mut self, other: CanisterProfile) {
+        for (function_index, function) in other.functions {
+            self.functions
+                .entry(function_index)
+                .or_default()
+                .add(&function);
+        }
+        for (call_stack, instructions) in other.call_stacks {
+            self.add_call_stack(&call_stack, instructions);
+        }
+    }
+
+    fn add_call_stack(&mut self, call_stack: &[u32], instructions: u64) {
+        if instructions == 0 {
+            return;
+        }
+        let call_stack = &call_stack[..call_stack.len().min(MAX_PROFILED_CALL_STACK_DEPTH)];
+        let num_call_stacks = self.call_stacks.len();
+        match self.call_stacks.get_mut(call_stack) {
+            Some(total) => *total = total.saturating_add(instructions),
+            None if num_call_stacks < MAX_PROFILED_CALL_STACKS => {
+                self.call_stacks.insert(call_stack.to_vec(), instructions);
+            }
+            None => {}
+        }
+    }
+}
+
+impl From<&CanisterProfile> for pb::CanisterProfile {
+    fn from(item: &CanisterProfile) -> Self {
+        Self {
+            functions: item
+                .functions
+                .iter()
+                .map(|(function_index, function)| pb::FunctionProfile {
+                    function_index: *function_index,
+                    calls: function.calls,
+                    inclusive_instructions: function.inclusive_instructions,
+                    exclusive_instructions: function.exclusive_instructions,
+                })
+                .collect(),
+            call_stacks: item
+                .call_stacks
+                .iter()
+                .map(|(function_indices, instructions)| pb::CallStackProfile {
+                    function_indices: function_indices.clone(),
+                    instructions: *instructions,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<pb::CanisterProfile> for CanisterProfile {
+    fn from(item: pb::CanisterProfile) -> Self {
+        Self::new(
+            item.functions
+                .into_iter()
+                .map(|function| {
+                    (
+                        function.function_index,
+                        FunctionProfile {
+                            calls: function.calls,
+                            inclusive_instructions: function.inclusive_instructions,
+                            exclusive_instructions: function.exclusive_instructions,
+                        },
+                    )
+                })
+                .collect(),
+            item.call_stacks
+                .into_iter()
+                .map(|call_stack| (call_stack.function_indices, call_stack.instructions))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canister_profile_record_call() {
+        let mut profile = CanisterProfile::default();
+        // `0` calls `1`, which calls itself recursively once.
+        profile.record_call(&[0, 1, 1], 10, 10, true);
+        profile.record_call(&[0, 1], 25, 15, false);
+        profile.record_call(&[0], 30, 5, false);
+
+        assert_eq!(
+            profile.functions(),
+            &BTreeMap::from([
+                (
+                    0,
+                    FunctionProfile {
+                        calls: 1,
+                        inclusive_instructions: 30,
+                        exclusive_instructions: 5,
+                    }
+                ),
+                (
+                    1,
+                    FunctionProfile {
+                        calls: 2,
+                        inclusive_instructions: 25,
+                        exclusive_instructions: 25,
+                    }
+                ),
+            ])
+        );
+        assert_eq!(
+            profile.call_stacks(),
+            &BTreeMap::from([(vec![0], 5), (vec![0, 1], 15), (vec![0, 1, 1], 10)])
+        );
+    }
+
+    #[test]
+    fn test_canister_profile_append() {
+        let mut main = CanisterProfile::default();
+        main.record_call(&[0, 1], 10, 10, false);
+        main.record_call(&[0], 12, 2, false);
+        let mut delta = CanisterProfile::default();
+        delta.record_call(&[0, 2], 7, 7, false);
+        delta.record_call(&[0], 8, 1, false);
+
+        main.append(delta);
+
+        assert_eq!(
+            main.functions()[&0],
+            FunctionProfile {
+                calls: 2,
+                inclusive_instructions: 20,
+                exclusive_instructions: 3,
+            }
+        );
+        assert_eq!(
+            main.call_stacks(),
+            &BTreeMap::from([(vec![0], 3), (vec![0, 1], 10), (vec![0, 2], 7)])
+        );
+    }
+
+    #[test]
+    fn test_canister_profile_limits_call_stacks() {
+        let mut profile = CanisterProfile::default();
+        for i in 0..MAX_PROFILED_CALL_STACKS as u32 + 10 {
+            profile.record_call(&[0, i + 1], 1, 1, false);
+        }
+        let deep_call_stack: Vec<u32> = (0..MAX_PROFILED_CALL_STACK_DEPTH as u32 * 2).collect();
+        profile.record_call(&deep_call_stack, 1, 1, false);
+
+        assert_eq!(profile.call_stacks().len(), MAX_PROFILED_CALL_STACKS);
+        assert!(profile
+            .call_stacks()
+            .keys()
+            .all(|call_stack| call_stack.len() <= MAX_PROFILED_CALL_STACK_DEPTH));
+        // Function totals are kept even if the call stack is dropped.
+        let last_function = MAX_PROFILED_CALL_STACKS as u32 + 10;
+        assert_eq!(profile.functions()[&last_function].calls, 1);
+    }
+
+    #[test]
+    fn test_canister_profile_memory_usage() {
+        let mut profile = CanisterProfile::default();
+        assert_eq!(profile.memory_usage(), 0);
+
+        profile.record_call(&[0, 1], 10, 10, false);
+        profile.record_call(&[0], 12, 2, false);
+        // Two functions of 4 + 24 bytes and two call stacks of 2 and 1 frames.
+        assert_eq!(profile.memory_usage(), 2 * 28 + (8 + 8) + (4 + 8));
+
+        profile.clear();
+        assert_eq!(profile.memory_usage(), 0);
+    }
+
+    #[test]
+    fn test_canister_profile_proto_round_trip() {
+        let mut profile = CanisterProfile::default();
+        profile.record_call(&[3, 4], 10, 10, false);
+        profile.record_call(&[3], 12, 2, false);
+
+        let pb_profile = pb::CanisterProfile::from(&profile);
+        assert_eq!(CanisterProfile::from(pb_profile), profile);
+    }
+}
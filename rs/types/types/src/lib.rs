@@ -67,6 +67,7 @@ pub mod artifact;
 pub mod batch;
 pub mod canister_http;
 pub mod canister_log;
+pub mod canister_profile;
 pub mod consensus;
 pub mod crypto;
 pub mod funds;
@@ -14,8 +14,8 @@ use ic_error_types::{ErrorCode, UserError};
 use ic_management_canister_types::{
     CanisterIdRecord, CanisterInfoRequest, ClearChunkStoreArgs, DeleteCanisterSnapshotArgs,
     InstallChunkedCodeArgs, InstallCodeArgsV2, ListCanisterSnapshotArgs, LoadCanisterSnapshotArgs,
-    Method, Payload, StoredChunksArgs, TakeCanisterSnapshotArgs, UpdateSettingsArgs,
-    UploadChunkArgs, IC_00,
+    Method, MigrateCanisterArgs, Payload, StoredChunksArgs, TakeCanisterSnapshotArgs,
+    UpdateSettingsArgs, UploadChunkArgs, IC_00,
 };
 use ic_protobuf::{
     log::ingress_message_log_entry::v1::IngressMessageLogEntry,
@@ -552,6 +552,10 @@ pub fn extract_effective_canister_id(
                 Err(err) => Err(ParseIngressError::InvalidSubnetPayload(err.to_string())),
             }
         }
+        Ok(Method::MigrateCanister) => match MigrateCanisterArgs::decode(ingress.arg()) {
+            Ok(record) => Ok(Some(record.get_canister_id())),
+            Err(err) => Err(ParseIngressError::InvalidSubnetPayload(err.to_string())),
+        },
 
         Ok(Method::CreateCanister)
         | Ok(Method::SetupInitialDKG)
@@ -560,9 +564,11 @@ pub fn extract_effective_canister_id(
         | Ok(Method::RawRand)
         | Ok(Method::ECDSAPublicKey)
         | Ok(Method::SignWithECDSA)
+        | Ok(Method::SignWithECDSABatch)
         | Ok(Method::ComputeInitialIDkgDealings)
         | Ok(Method::SchnorrPublicKey)
         | Ok(Method::SignWithSchnorr)
+        | Ok(Method::SignWithSchnorrBatch)
         | Ok(Method::BitcoinGetBalance)
         | Ok(Method::BitcoinGetUtxos)
         | Ok(Method::BitcoinGetBlockHeaders)
@@ -571,7 +577,9 @@ pub fn extract_effective_canister_id(
         | Ok(Method::BitcoinGetSuccessors)
         | Ok(Method::BitcoinGetCurrentFeePercentiles)
         | Ok(Method::NodeMetricsHistory)
-        | Ok(Method::FetchCanisterLogs) => {
+        | Ok(Method::FetchCanisterLogs)
+        | Ok(Method::FetchCanisterProfile)
+        | Ok(Method::ImportCanisterState) => {
             // Subnet method not allowed for ingress.
             Err(ParseIngressError::SubnetMethodNotAllowed)
         }
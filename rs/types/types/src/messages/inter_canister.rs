@@ -6,9 +6,10 @@ use ic_error_types::{RejectCode, UserError};
 use ic_exhaustive_derive::ExhaustiveSet;
 use ic_management_canister_types::{
     CanisterIdRecord, CanisterInfoRequest, ClearChunkStoreArgs, DeleteCanisterSnapshotArgs,
-    InstallChunkedCodeArgs, InstallCodeArgsV2, ListCanisterSnapshotArgs, LoadCanisterSnapshotArgs,
-    Method, Payload as _, ProvisionalTopUpCanisterArgs, StoredChunksArgs, TakeCanisterSnapshotArgs,
-    UpdateSettingsArgs, UploadChunkArgs,
+    ImportCanisterStateArgs, InstallChunkedCodeArgs, InstallCodeArgsV2, ListCanisterSnapshotArgs,
+    LoadCanisterSnapshotArgs, Method, MigrateCanisterArgs, Payload as _,
+    ProvisionalTopUpCanisterArgs, StoredChunksArgs, TakeCanisterSnapshotArgs, UpdateSettingsArgs,
+    UploadChunkArgs,
 };
 use ic_protobuf::{
     proxy::{try_from_option_field, ProxyDecodeError},
@@ -201,15 +202,29 @@ impl Request {
                     Err(_) => None,
                 }
             }
+            Ok(Method::MigrateCanister) => {
+                match MigrateCanisterArgs::decode(&self.method_payload) {
+                    Ok(record) => Some(record.get_canister_id()),
+                    Err(_) => None,
+                }
+            }
+            Ok(Method::ImportCanisterState) => {
+                match ImportCanisterStateArgs::decode(&self.method_payload) {
+                    Ok(record) => Some(record.get_canister_id()),
+                    Err(_) => None,
+                }
+            }
             Ok(Method::CreateCanister)
             | Ok(Method::SetupInitialDKG)
             | Ok(Method::HttpRequest)
             | Ok(Method::RawRand)
             | Ok(Method::ECDSAPublicKey)
             | Ok(Method::SignWithECDSA)
+            | Ok(Method::SignWithECDSABatch)
             | Ok(Method::ComputeInitialIDkgDealings)
             | Ok(Method::SchnorrPublicKey)
             | Ok(Method::SignWithSchnorr)
+            | Ok(Method::SignWithSchnorrBatch)
             | Ok(Method::BitcoinGetBalance)
             | Ok(Method::BitcoinGetUtxos)
             | Ok(Method::BitcoinGetBlockHeaders)
@@ -221,10 +236,11 @@ impl Request {
                 // No effective canister id.
                 None
             }
-            // `FetchCanisterLogs` method is only allowed for messages sent by
-            // end users in non-replicated mode, so we should never reach this point.
-            // If we do, we return `None` (which should be no-op) to avoid panicking.
-            Ok(Method::FetchCanisterLogs) => None,
+            // `FetchCanisterLogs` and `FetchCanisterProfile` methods are only
+            // allowed for messages sent by end users in non-replicated mode, so
+            // we should never reach this point. If we do, we return `None`
+            // (which should be no-op) to avoid panicking.
+            Ok(Method::FetchCanisterLogs) | Ok(Method::FetchCanisterProfile) => None,
             Err(_) => None,
         }
     }
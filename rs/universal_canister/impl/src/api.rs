@@ -13,6 +13,10 @@ mod ic0 {
         pub fn canister_self_copy(dst: u32, offset: u32, size: u32) -> ();
         pub fn canister_self_size() -> u32;
         pub fn canister_status() -> u32;
+        pub fn subnet_self_copy(dst: u32, offset: u32, size: u32) -> ();
+        pub fn subnet_self_size() -> u32;
+        pub fn root_key_copy(dst: u32, offset: u32, size: u32) -> ();
+        pub fn root_key_size() -> u32;
         pub fn debug_print(offset: u32, size: u32) -> ();
         pub fn msg_arg_data_copy(dst: u32, offset: u32, size: u32) -> ();
         pub fn msg_arg_data_size() -> u32;
@@ -198,6 +202,26 @@ pub fn id() -> Vec<u8> {
     bytes
 }
 
+/// Returns the id of the subnet the canister is running on as a blob.
+pub fn subnet_self() -> Vec<u8> {
+    let len: u32 = unsafe { ic0::subnet_self_size() };
+    let mut bytes = vec![0; len as usize];
+    unsafe {
+        ic0::subnet_self_copy(bytes.as_mut_ptr() as u32, 0, len);
+    }
+    bytes
+}
+
+/// Returns the DER-encoded public key of the IC root of trust.
+pub fn root_key() -> Vec<u8> {
+    let len: u32 = unsafe { ic0::root_key_size() };
+    let mut bytes = vec![0; len as usize];
+    unsafe {
+        ic0::root_key_copy(bytes.as_mut_ptr() as u32, 0, len);
+    }
+    bytes
+}
+
 pub fn status() -> u32 {
     unsafe { ic0::canister_status() }
 }
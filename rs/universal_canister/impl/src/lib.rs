@@ -112,5 +112,7 @@ try_from_u8!(
         CallWithBestEffortResponse = 82,
         MsgDeadline = 83,
         MemorySizeIsAtLeast = 84,
+        SubnetSelf = 85,
+        RootKey = 86,
     }
 );
@@ -415,6 +415,8 @@ fn eval(ops_bytes: OpsBytes) {
             Ops::InReplicatedExecution => stack.push_int(api::in_replicated_execution()),
             Ops::CallWithBestEffortResponse => api::call_with_best_effort_response(stack.pop_int()),
             Ops::MsgDeadline => stack.push_int64(api::msg_deadline()),
+            Ops::SubnetSelf => stack.push_blob(api::subnet_self()),
+            Ops::RootKey => stack.push_blob(api::root_key()),
             Ops::MemorySizeIsAtLeast => {
                 #[cfg(target_arch = "wasm32")]
                 let current_memory_size = || {
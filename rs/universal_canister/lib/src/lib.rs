@@ -17,7 +17,7 @@ use universal_canister::Ops;
 /// `rs/universal_canister`.
 pub const UNIVERSAL_CANISTER_WASM: &[u8] = include_bytes!("universal-canister.wasm.gz");
 pub const UNIVERSAL_CANISTER_WASM_SHA256: [u8; 32] =
-    hex!("9c0b4ed1d729ffdd0e8d194df3be621f58a2fb86e1f1bbf556d89f43637de303");
+    hex!("8f97215f7238346d270206da108ad9aa3744f0d31414a735a340ccf8a191dad1");
 
 /// A succinct shortcut for creating a `PayloadBuilder`, which is used to encode
 /// instructions to be executed by the UC.
@@ -406,6 +406,18 @@ impl PayloadBuilder {
         self
     }
 
+    /// Pushes the id of the subnet the canister is running on onto the stack.
+    pub fn subnet_self(mut self) -> Self {
+        self.0.push(Ops::SubnetSelf as u8);
+        self
+    }
+
+    /// Pushes the DER-encoded public key of the IC root of trust onto the stack.
+    pub fn root_key(mut self) -> Self {
+        self.0.push(Ops::RootKey as u8);
+        self
+    }
+
     /// Store data (in a global variable) on the heap.
     /// NOTE: This does _not_ correspond to a Wasm global.
     pub fn set_global_data(mut self, data: &[u8]) -> Self {
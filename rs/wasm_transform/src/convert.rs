@@ -14,39 +14,52 @@ type Result<T> = std::result::Result<T, Error>;
 pub(super) mod parser_to_internal {
     use super::*;
 
-    pub(crate) fn const_expr(const_expr: wasmparser::ConstExpr) -> Result<wasmparser::Operator> {
-        let mut ops = const_expr.get_operators_reader().into_iter();
-        let first = ops.next();
-        let end = ops.next();
-        let rest = ops.next();
-        let (Some(first), Some(Ok(wasmparser::Operator::End)), None) = (first, end, rest) else {
+    pub(crate) fn const_expr(const_expr: wasmparser::ConstExpr) -> Result<crate::ConstExpr> {
+        let mut ops = const_expr
+            .get_operators_reader()
+            .into_iter()
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let (Some(wasmparser::Operator::End), false) = (ops.pop(), ops.is_empty()) else {
             return Err(Error::ConversionError(format!(
                 "Invalid const expression: {:?}",
                 const_expr
             )));
         };
         use wasmparser::Operator::*;
-        let first = first?;
         // In the MVP Wasm spec, these are the only instructions allowed in const expressions:
         // https://webassembly.github.io/spec/core/valid/instructions.html#constant-expressions
         //
-        // More instructions will be supported with the gc and extended const expressions proposals:
-        // https://github.com/WebAssembly/gc/blob/main/proposals/gc/Post-MVP.md
+        // The extended const expressions proposal additionally allows integer `add`, `sub`
+        // and `mul` instructions:
         // https://github.com/WebAssembly/extended-const/blob/master/proposals/extended-const/Overview.md
-        match first {
-            I32Const { .. }
-            | I64Const { .. }
-            | F32Const { .. }
-            | F64Const { .. }
-            | V128Const { .. }
-            | RefNull { .. }
-            | RefFunc { .. }
-            | GlobalGet { .. } => Ok(first),
-            other => Err(Error::ConversionError(format!(
-                "Invalid const expression operator: {:?}",
-                other
-            ))),
+        //
+        // More instructions will be supported with the gc proposal:
+        // https://github.com/WebAssembly/gc/blob/main/proposals/gc/Post-MVP.md
+        for op in &ops {
+            match op {
+                I32Const { .. }
+                | I64Const { .. }
+                | F32Const { .. }
+                | F64Const { .. }
+                | V128Const { .. }
+                | RefNull { .. }
+                | RefFunc { .. }
+                | GlobalGet { .. }
+                | I32Add
+                | I32Sub
+                | I32Mul
+                | I64Add
+                | I64Sub
+                | I64Mul => {}
+                other => {
+                    return Err(Error::ConversionError(format!(
+                        "Invalid const expression operator: {:?}",
+                        other
+                    )))
+                }
+            }
         }
+        Ok(ops)
     }
 
     fn data_kind(kind: wasmparser::DataKind) -> Result<crate::DataSegmentKind> {
@@ -136,11 +149,13 @@ pub(super) mod internal_to_encoder {
         }
     }
 
-    pub(crate) fn const_expr(expr: &wasmparser::Operator) -> Result<wasm_encoder::ConstExpr> {
+    pub(crate) fn const_expr(expr: &[wasmparser::Operator]) -> Result<wasm_encoder::ConstExpr> {
         use wasm_encoder::Encode;
 
         let mut bytes = vec![];
-        op(expr.clone())?.encode(&mut bytes);
+        for expr_op in expr {
+            op(expr_op.clone())?.encode(&mut bytes);
+        }
         Ok(wasm_encoder::ConstExpr::raw(bytes))
     }
 
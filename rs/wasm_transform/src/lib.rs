@@ -9,6 +9,12 @@ mod convert;
 use convert::internal_to_encoder;
 use convert::parser_to_internal;
 
+/// A constant expression given as its operators without the final `End`.
+/// In the MVP Wasm spec a constant expression consists of a single operator,
+/// the extended constant expressions proposal also allows integer `add`, `sub`
+/// and `mul` operators.
+pub type ConstExpr<'a> = Vec<Operator<'a>>;
+
 pub struct Body<'a> {
     /// Local variables of the function, given as tuples of (# of locals, type).
     /// Note that these do not include the function parameters which are given
@@ -23,7 +29,7 @@ pub enum ElementItems<'a> {
     Functions(Vec<u32>),
     ConstExprs {
         ty: RefType,
-        exprs: Vec<Operator<'a>>,
+        exprs: Vec<ConstExpr<'a>>,
     },
 }
 
@@ -31,7 +37,7 @@ pub enum ElementKind<'a> {
     Passive,
     Active {
         table_index: Option<u32>,
-        offset_expr: Operator<'a>,
+        offset_expr: ConstExpr<'a>,
     },
     Declared,
 }
@@ -52,14 +58,14 @@ pub enum DataSegmentKind<'a> {
     Active {
         /// The memory index for the data segment.
         memory_index: u32,
-        /// The initialization operator for the data segment.
-        offset_expr: Operator<'a>,
+        /// The initialization expression for the data segment.
+        offset_expr: ConstExpr<'a>,
     },
 }
 
 pub struct Global<'a> {
     pub ty: GlobalType,
-    pub init_expr: Operator<'a>,
+    pub init_expr: ConstExpr<'a>,
 }
 
 #[derive(Debug, Clone)]
@@ -162,7 +168,7 @@ pub struct Module<'a> {
     /// Mapping from function index to type index.
     pub functions: Vec<u32>,
     /// Each table has a type and optional initialization expression.
-    pub tables: Vec<(TableType, Option<Operator<'a>>)>,
+    pub tables: Vec<(TableType, Option<ConstExpr<'a>>)>,
     pub memories: Vec<MemoryType>,
     pub globals: Vec<Global<'a>>,
     pub data: Vec<DataSegment<'a>>,
@@ -0,0 +1,8 @@
+(module
+  (import "env" "base" (global $base i32))
+  (global i32 (i32.add (global.get $base) (i32.const 16)))
+  (global i64 (i64.mul (i64.sub (i64.const 10) (i64.const 2)) (i64.const 3)))
+  (table $table 4 funcref)
+  (elem (offset (i32.add (global.get $base) (i32.const 1))) func $f)
+  (func $f)
+)
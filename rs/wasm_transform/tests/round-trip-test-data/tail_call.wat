@@ -0,0 +1,11 @@
+(module
+  (type $t (func (param i32) (result i32)))
+  (table $table 1 funcref)
+  (elem (i32.const 0) func $f)
+  (func $f (type $t)
+    (return_call $g (local.get 0))
+  )
+  (func $g (type $t)
+    (return_call_indirect (type $t) (local.get 0) (i32.const 0))
+  )
+)
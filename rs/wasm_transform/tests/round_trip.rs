@@ -8,7 +8,10 @@ fn round_trip(testname: &str, folder: &str) {
         testname
     );
     let buff = wat::parse_file(filename).expect("couldn't convert the input wat to Wasm");
+    round_trip_wasm(buff);
+}
 
+fn round_trip_wasm(buff: Vec<u8>) {
     let module = Module::parse(&buff, false).unwrap();
     let result = module.encode().unwrap();
     let out = wasmprinter::print_bytes(result).expect("couldn't translated Wasm to wat");
@@ -39,6 +42,93 @@ mod round_trip {
         globals,
         exports,
         start,
-        const_expr
+        const_expr,
+        extended_const,
+        tail_call
+    );
+}
+
+/// Builds a module using typed function references with `wasm-encoder`
+/// because the version of `wat` in use emits a pre-standard encoding for them.
+fn function_references_module() -> Vec<u8> {
+    use wasm_encoder::{
+        BlockType, CodeSection, ConstExpr, ElementSection, Elements, Function, FunctionSection,
+        HeapType, Instruction as I, RefType, TableSection, TableType, TypeSection, ValType,
+    };
+
+    let ref_t = |nullable| RefType {
+        nullable,
+        heap_type: HeapType::Concrete(0),
+    };
+    let mut types = TypeSection::new();
+    types.function([ValType::I32], [ValType::I32]);
+    types.function([ValType::Ref(ref_t(true))], [ValType::I32]);
+    types.function([ValType::Ref(ref_t(true))], [ValType::Ref(ref_t(false))]);
+
+    let mut functions = FunctionSection::new();
+    functions.function(0).function(1).function(2);
+
+    let mut tables = TableSection::new();
+    tables.table_with_init(
+        TableType {
+            element_type: ref_t(false),
+            minimum: 1,
+            maximum: None,
+        },
+        &ConstExpr::ref_func(0),
     );
+
+    let mut elements = ElementSection::new();
+    elements.declared(Elements::Functions(&[0]));
+
+    let mut code = CodeSection::new();
+    let mut identity = Function::new([]);
+    identity.instruction(&I::LocalGet(0)).instruction(&I::End);
+    code.function(&identity);
+    let mut call = Function::new([(1, ValType::Ref(ref_t(false)))]);
+    for instruction in [
+        I::Block(BlockType::Empty),
+        I::LocalGet(0),
+        I::BrOnNull(0),
+        I::LocalSet(1),
+        I::I32Const(1),
+        I::LocalGet(1),
+        I::CallRef(0),
+        I::Return,
+        I::End,
+        I::I32Const(2),
+        I::LocalGet(0),
+        I::RefAsNonNull,
+        I::ReturnCallRef(0),
+        I::End,
+    ] {
+        call.instruction(&instruction);
+    }
+    code.function(&call);
+    let mut non_null = Function::new([]);
+    for instruction in [
+        I::Block(BlockType::Result(ValType::Ref(ref_t(false)))),
+        I::LocalGet(0),
+        I::BrOnNonNull(0),
+        I::RefFunc(0),
+        I::End,
+        I::End,
+    ] {
+        non_null.instruction(&instruction);
+    }
+    code.function(&non_null);
+
+    let mut module = wasm_encoder::Module::new();
+    module
+        .section(&types)
+        .section(&functions)
+        .section(&tables)
+        .section(&elements)
+        .section(&code);
+    module.finish()
+}
+
+#[test]
+fn function_references() {
+    round_trip_wasm(function_references_module());
 }